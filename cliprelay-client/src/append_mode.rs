@@ -0,0 +1,99 @@
+//! Append mode: applied incoming text goes after the clipboard's current
+//! text instead of replacing it, to collect several snippets sent from
+//! another machine.  Only received text is appended; copying from Activity
+//! History or the clip ring still replaces the clipboard.
+
+use serde::{Deserialize, Serialize};
+
+/// Put between the clipboard's text and the appended text.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppendSeparator {
+    #[default]
+    NewLine,
+    BlankLine,
+    Space,
+    Tab,
+    /// Nothing: the texts are joined directly.
+    None,
+}
+
+impl AppendSeparator {
+    pub const ALL: [AppendSeparator; 5] = [
+        AppendSeparator::NewLine,
+        AppendSeparator::BlankLine,
+        AppendSeparator::Space,
+        AppendSeparator::Tab,
+        AppendSeparator::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AppendSeparator::NewLine => "New line",
+            AppendSeparator::BlankLine => "Blank line",
+            AppendSeparator::Space => "Space",
+            AppendSeparator::Tab => "Tab",
+            AppendSeparator::None => "Nothing",
+        }
+    }
+
+    /// Windows line endings, as the clipboard text is pasted on Windows.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppendSeparator::NewLine => "\r\n",
+            AppendSeparator::BlankLine => "\r\n\r\n",
+            AppendSeparator::Space => " ",
+            AppendSeparator::Tab => "\t",
+            AppendSeparator::None => "",
+        }
+    }
+
+    fn is_line_break(self) -> bool {
+        matches!(self, AppendSeparator::NewLine | AppendSeparator::BlankLine)
+    }
+}
+
+/// `incoming` after `current`, joined by `separator`.  An empty clipboard
+/// gets `incoming` alone, and line separators replace any line breaks
+/// `current` already ends with, so they are not doubled.
+pub fn append(current: &str, incoming: &str, separator: AppendSeparator) -> String {
+    let current = if separator.is_line_break() {
+        current.trim_end_matches(['\r', '\n'])
+    } else {
+        current
+    };
+    if current.is_empty() {
+        return incoming.to_owned();
+    }
+    let mut out = String::with_capacity(current.len() + separator.as_str().len() + incoming.len());
+    out.push_str(current);
+    out.push_str(separator.as_str());
+    out.push_str(incoming);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incoming_text_goes_after_the_separator() {
+        assert_eq!(append("a", "b", AppendSeparator::NewLine), "a\r\nb");
+        assert_eq!(append("a", "b", AppendSeparator::BlankLine), "a\r\n\r\nb");
+        assert_eq!(append("a", "b", AppendSeparator::Space), "a b");
+        assert_eq!(append("a ", "b", AppendSeparator::Tab), "a \tb");
+        assert_eq!(append("a", "b", AppendSeparator::None), "ab");
+    }
+
+    #[test]
+    fn empty_clipboards_and_trailing_line_breaks() {
+        assert_eq!(append("", "b", AppendSeparator::Space), "b");
+        assert_eq!(append("\r\n", "b", AppendSeparator::NewLine), "b");
+        assert_eq!(append("a\r\n", "b", AppendSeparator::NewLine), "a\r\nb");
+        assert_eq!(
+            append("a\n\n", "b", AppendSeparator::BlankLine),
+            "a\r\n\r\nb"
+        );
+        assert_eq!(append("a\n", "b", AppendSeparator::Space), "a\n b");
+    }
+}
@@ -0,0 +1,472 @@
+//! Optional lock on what someone at an unlocked desk could read: the
+//! activity history, the snippets and the invite (which carries the room
+//! code).  Opening them asks for Windows Hello, or for the account password
+//! where Hello is not set up, and they lock again after a while unused.
+//!
+//! The unlock lives in memory only; every start begins locked.
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_RELOCK_MINS: u32 = 5;
+pub const MAX_RELOCK_MINS: u32 = 24 * 60;
+
+/// Saved in `ui_state.json`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct AuthGatePrefs {
+    pub enabled: bool,
+    /// Minutes without a locked view shown after which it locks again.
+    pub relock_mins: u32,
+}
+
+impl Default for AuthGatePrefs {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            relock_mins: DEFAULT_RELOCK_MINS,
+        }
+    }
+}
+
+impl AuthGatePrefs {
+    /// The timeout clamped to what the Options page allows.
+    pub fn normalized(self) -> Self {
+        Self {
+            relock_mins: self.relock_mins.clamp(1, MAX_RELOCK_MINS),
+            ..self
+        }
+    }
+
+    fn relock_ms(self) -> u64 {
+        u64::from(self.normalized().relock_mins) * 60_000
+    }
+}
+
+/// Whether the gated views are open, kept for the running process only.
+#[derive(Debug, Default)]
+pub struct AuthGate {
+    /// When a gated view was last shown after unlocking.
+    last_used_ms: Option<u64>,
+}
+
+impl AuthGate {
+    /// Whether the gated views may be shown at `now_ms`, without counting
+    /// as use.
+    pub fn is_unlocked(&self, prefs: AuthGatePrefs, now_ms: u64) -> bool {
+        !prefs.enabled
+            || self
+                .last_used_ms
+                .is_some_and(|used| now_ms.saturating_sub(used) < prefs.relock_ms())
+    }
+
+    /// Whether a gated view may be shown at `now_ms`.  Showing one counts
+    /// as use, so the re-lock timeout runs from the last time one was on
+    /// screen.
+    pub fn admit(&mut self, prefs: AuthGatePrefs, now_ms: u64) -> bool {
+        if !prefs.enabled {
+            return true;
+        }
+        if self.is_unlocked(prefs, now_ms) {
+            self.last_used_ms = Some(now_ms);
+            true
+        } else {
+            self.last_used_ms = None;
+            false
+        }
+    }
+
+    pub fn unlock(&mut self, now_ms: u64) {
+        self.last_used_ms = Some(now_ms);
+    }
+
+    pub fn lock(&mut self) {
+        self.last_used_ms = None;
+    }
+}
+
+/// Result of asking the user to prove who they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthOutcome {
+    Verified,
+    Canceled,
+    /// Why it did not work, for a toast.
+    Failed(String),
+}
+
+/// What the Windows Hello step came back with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HelloAnswer {
+    Answered(AuthOutcome),
+    /// Hello is not set up or is turned off, or could not be asked at all:
+    /// ask for the account password instead.
+    Unavailable,
+}
+
+impl HelloAnswer {
+    /// Parse the `UserConsentVerificationResult` name printed by the Hello
+    /// script.  Anything else, such as the output of a script that failed,
+    /// means Hello could not be used.
+    pub fn parse(output: &str) -> HelloAnswer {
+        match output.trim() {
+            "Verified" => HelloAnswer::Answered(AuthOutcome::Verified),
+            "Canceled" => HelloAnswer::Answered(AuthOutcome::Canceled),
+            "RetriesExhausted" => {
+                HelloAnswer::Answered(AuthOutcome::Failed("too many attempts".to_owned()))
+            }
+            "DeviceBusy" => {
+                HelloAnswer::Answered(AuthOutcome::Failed("Windows Hello is busy".to_owned()))
+            }
+            _ => HelloAnswer::Unavailable,
+        }
+    }
+}
+
+/// Split a name from the password prompt into the user and domain that
+/// `LogonUserW` expects: `DOMAIN\user`, a UPN (`user@example.com`, no
+/// domain) or a bare local account name (domain `.`).
+#[cfg(any(target_os = "windows", test))]
+fn split_logon_name(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('\\') {
+        Some((domain, user)) => (user, Some(domain)),
+        None if name.contains('@') => (name, None),
+        None => (name, Some(".")),
+    }
+}
+
+/// Ask for Windows Hello, falling back to the account password where Hello
+/// is unavailable.  Blocks until the user answers.
+#[cfg(target_os = "windows")]
+pub fn verify() -> AuthOutcome {
+    match windows_impl::ask_hello() {
+        HelloAnswer::Answered(outcome) => outcome,
+        HelloAnswer::Unavailable => windows_impl::ask_password(),
+    }
+}
+
+/// There is nothing to verify against outside Windows.
+#[cfg(not(target_os = "windows"))]
+pub fn verify() -> AuthOutcome {
+    AuthOutcome::Failed("only available on Windows".to_owned())
+}
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{ffi::c_void, io, mem, ptr};
+
+    use windows_sys::Win32::{
+        Foundation::{BOOL, CloseHandle, ERROR_CANCELLED, ERROR_LOGON_FAILURE, HANDLE, NO_ERROR},
+        Security::{
+            Credentials::{
+                CREDUI_INFOW, CREDUIWIN_ENUMERATE_CURRENT_USER, CredUIPromptForWindowsCredentialsW,
+                CredUnPackAuthenticationBufferW,
+            },
+            GetLengthSid, GetTokenInformation, LOGON32_LOGON_INTERACTIVE, LOGON32_PROVIDER_DEFAULT,
+            LogonUserW, TOKEN_QUERY, TOKEN_USER, TokenUser,
+        },
+        System::{
+            Com::CoTaskMemFree,
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    };
+
+    use super::{AuthOutcome, HelloAnswer, split_logon_name};
+
+    const MESSAGE: &str = "Confirm it is you to open ClipRelay history, snippets and invites.";
+
+    /// Ask through PowerShell, which can load the WinRT
+    /// `UserConsentVerifier`.
+    pub(super) fn ask_hello() -> HelloAnswer {
+        use std::os::windows::process::CommandExt;
+
+        /// Keeps powershell.exe from flashing a console window.
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let script = format!(
+            r#"
+            try {{
+                Add-Type -AssemblyName System.Runtime.WindowsRuntime
+                [void][Windows.Security.Credentials.UI.UserConsentVerifier,Windows.Security.Credentials.UI,ContentType=WindowsRuntime]
+                $asTask = [System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object {{
+                    $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and
+                    $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncOperation`1'
+                }} | Select-Object -First 1
+                $op = [Windows.Security.Credentials.UI.UserConsentVerifier]::RequestVerificationAsync('{MESSAGE}')
+                $task = $asTask.MakeGenericMethod([Windows.Security.Credentials.UI.UserConsentVerificationResult]).Invoke($null, @($op))
+                $task.GetAwaiter().GetResult().ToString()
+            }} catch {{
+                'Error'
+            }}
+            "#
+        );
+
+        match std::process::Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                HelloAnswer::parse(&String::from_utf8_lossy(&output.stdout))
+            }
+            _ => HelloAnswer::Unavailable,
+        }
+    }
+
+    fn wide_null(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// Overwrite memory that held a password.
+    fn wipe(buffer: &mut [u16]) {
+        for unit in buffer.iter_mut() {
+            // SAFETY: `unit` is a valid, exclusive reference.
+            unsafe { ptr::write_volatile(unit, 0) };
+        }
+    }
+
+    /// The Windows password prompt, limited to the signed-in account, with
+    /// the password checked by logging on.  Works for local, domain and
+    /// Microsoft accounts.
+    pub(super) fn ask_password() -> AuthOutcome {
+        let message = wide_null(MESSAGE);
+        let caption = wide_null("ClipRelay");
+        let info = CREDUI_INFOW {
+            cbSize: mem::size_of::<CREDUI_INFOW>() as u32,
+            hwndParent: 0,
+            pszMessageText: message.as_ptr(),
+            pszCaptionText: caption.as_ptr(),
+            hbmBanner: 0,
+        };
+        let mut package = 0u32;
+        let mut packed: *mut c_void = ptr::null_mut();
+        let mut packed_len = 0u32;
+        let mut save: BOOL = 0;
+        // SAFETY: every pointer refers to a live local; on success Windows
+        // allocates `packed`, which is freed below.
+        let status = unsafe {
+            CredUIPromptForWindowsCredentialsW(
+                &info,
+                0,
+                &mut package,
+                ptr::null(),
+                0,
+                &mut packed,
+                &mut packed_len,
+                &mut save,
+                CREDUIWIN_ENUMERATE_CURRENT_USER,
+            )
+        };
+        match status {
+            NO_ERROR => {}
+            ERROR_CANCELLED => return AuthOutcome::Canceled,
+            _ => return AuthOutcome::Failed("could not ask for the password".to_owned()),
+        }
+
+        let outcome = check_packed(packed, packed_len);
+        // SAFETY: `packed` holds `packed_len` bytes from CredUI, which are
+        // wiped (they include the password) and then freed.
+        unsafe {
+            wipe(std::slice::from_raw_parts_mut(
+                packed.cast::<u16>(),
+                packed_len as usize / 2,
+            ));
+            CoTaskMemFree(packed);
+        }
+        outcome
+    }
+
+    fn check_packed(packed: *const c_void, packed_len: u32) -> AuthOutcome {
+        let mut user = [0u16; 514];
+        let mut domain = [0u16; 256];
+        let mut password = [0u16; 257];
+        let mut user_len = user.len() as u32;
+        let mut domain_len = domain.len() as u32;
+        let mut password_len = password.len() as u32;
+        // SAFETY: each buffer is as long as the length passed with it.
+        let unpacked = unsafe {
+            CredUnPackAuthenticationBufferW(
+                0,
+                packed,
+                packed_len,
+                user.as_mut_ptr(),
+                &mut user_len,
+                domain.as_mut_ptr(),
+                &mut domain_len,
+                password.as_mut_ptr(),
+                &mut password_len,
+            )
+        };
+        let outcome = if unpacked == 0 {
+            AuthOutcome::Failed("could not read the password".to_owned())
+        } else {
+            let nul = |buffer: &[u16]| buffer.iter().position(|&c| c == 0).unwrap_or(0);
+            let user = String::from_utf16_lossy(&user[..nul(&user)]);
+            let domain = String::from_utf16_lossy(&domain[..nul(&domain)]);
+            let (user, domain) = if domain.is_empty() {
+                split_logon_name(&user)
+            } else {
+                (user.as_str(), Some(domain.as_str()))
+            };
+            log_on(user, domain, &password)
+        };
+        wipe(&mut password);
+        outcome
+    }
+
+    fn log_on(user: &str, domain: Option<&str>, password: &[u16]) -> AuthOutcome {
+        let user = wide_null(user);
+        let domain = domain.map(wide_null);
+        let mut token: HANDLE = 0;
+        // SAFETY: the strings are NUL-terminated (`password` by CredUI, in a
+        // zeroed buffer) and `token` is closed below.
+        let logged_on = unsafe {
+            LogonUserW(
+                user.as_ptr(),
+                domain
+                    .as_ref()
+                    .map_or(ptr::null(), |domain| domain.as_ptr()),
+                password.as_ptr(),
+                LOGON32_LOGON_INTERACTIVE,
+                LOGON32_PROVIDER_DEFAULT,
+                &mut token,
+            )
+        };
+        if logged_on == 0 {
+            let err = io::Error::last_os_error();
+            return if err.raw_os_error() == Some(ERROR_LOGON_FAILURE as i32) {
+                AuthOutcome::Failed("wrong password".to_owned())
+            } else {
+                AuthOutcome::Failed(format!("could not check the password: {err}"))
+            };
+        }
+
+        let mut own_token: HANDLE = 0;
+        // SAFETY: the pseudo handle of the current process needs no closing;
+        // `own_token` is closed below.
+        let same_user =
+            unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut own_token) } != 0
+                && token_user_sid(token).is_some_and(|sid| token_user_sid(own_token) == Some(sid));
+        // SAFETY: both handles were opened above (a zero handle is ignored).
+        unsafe {
+            CloseHandle(token);
+            if own_token != 0 {
+                CloseHandle(own_token);
+            }
+        }
+        if same_user {
+            AuthOutcome::Verified
+        } else {
+            AuthOutcome::Failed("not the signed-in user".to_owned())
+        }
+    }
+
+    /// The SID of the account a token belongs to.
+    fn token_user_sid(token: HANDLE) -> Option<Vec<u8>> {
+        let mut len = 0u32;
+        // SAFETY: asks only for the size.
+        unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut len) };
+        // `u64`s keep the `TOKEN_USER` at the start aligned.
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        // SAFETY: `buffer` holds at least `len` bytes.
+        let ok = unsafe {
+            GetTokenInformation(token, TokenUser, buffer.as_mut_ptr().cast(), len, &mut len)
+        };
+        if ok == 0 {
+            return None;
+        }
+        // SAFETY: on success the buffer starts with a `TOKEN_USER` whose SID
+        // points into the same buffer.
+        unsafe {
+            let sid = (*buffer.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+            let sid_len = GetLengthSid(sid) as usize;
+            Some(std::slice::from_raw_parts(sid.cast::<u8>(), sid_len).to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_MS: u64 = 60_000;
+
+    #[test]
+    fn disabled_gate_is_always_open() {
+        let mut gate = AuthGate::default();
+        assert!(gate.admit(AuthGatePrefs::default(), 0));
+    }
+
+    #[test]
+    fn relocks_after_the_timeout_without_use() {
+        let prefs = AuthGatePrefs {
+            enabled: true,
+            relock_mins: 5,
+        };
+        let mut gate = AuthGate::default();
+        assert!(!gate.admit(prefs, 0), "starts locked");
+
+        gate.unlock(0);
+        assert!(gate.admit(prefs, 4 * MINUTE_MS));
+        // Use restarts the timeout.
+        assert!(gate.admit(prefs, 8 * MINUTE_MS));
+        assert!(gate.is_unlocked(prefs, 12 * MINUTE_MS));
+        assert!(!gate.is_unlocked(prefs, 13 * MINUTE_MS));
+        assert!(!gate.admit(prefs, 13 * MINUTE_MS));
+        assert!(!gate.is_unlocked(prefs, 13 * MINUTE_MS), "stays locked");
+
+        gate.unlock(20 * MINUTE_MS);
+        gate.lock();
+        assert!(!gate.admit(prefs, 20 * MINUTE_MS));
+    }
+
+    #[test]
+    fn parses_hello_results() {
+        assert_eq!(
+            HelloAnswer::parse("Verified\r\n"),
+            HelloAnswer::Answered(AuthOutcome::Verified)
+        );
+        assert_eq!(
+            HelloAnswer::parse("Canceled"),
+            HelloAnswer::Answered(AuthOutcome::Canceled)
+        );
+        assert!(matches!(
+            HelloAnswer::parse("RetriesExhausted"),
+            HelloAnswer::Answered(AuthOutcome::Failed(reason)) if reason == "too many attempts"
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_the_password_without_hello() {
+        for output in [
+            "DeviceNotPresent",
+            "NotConfiguredForUser",
+            "DisabledByPolicy",
+        ] {
+            assert_eq!(
+                HelloAnswer::parse(output),
+                HelloAnswer::Unavailable,
+                "{output}"
+            );
+        }
+        // The script failed, or printed nothing at all.
+        assert_eq!(HelloAnswer::parse("Error\r\n"), HelloAnswer::Unavailable);
+        assert_eq!(HelloAnswer::parse(""), HelloAnswer::Unavailable);
+    }
+
+    #[test]
+    fn splits_logon_names() {
+        assert_eq!(split_logon_name(r"CORP\alice"), ("alice", Some("CORP")));
+        assert_eq!(
+            split_logon_name("alice@example.com"),
+            ("alice@example.com", None)
+        );
+        assert_eq!(split_logon_name("alice"), ("alice", Some(".")));
+    }
+
+    #[test]
+    fn timeout_is_clamped() {
+        let prefs = AuthGatePrefs {
+            enabled: true,
+            relock_mins: 0,
+        };
+        assert_eq!(prefs.normalized().relock_mins, 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! Local automation API for the ClipRelay client.
+//!
+//! When enabled in Options, the running client listens on a per-user named
+//! pipe (see [`pipe_name`]) so tools such as AutoHotkey scripts or editor
+//! plugins can drive it programmatically.  The protocol is line-delimited
+//! JSON: each request is a single JSON object terminated by `\n`, and every
+//! request receives exactly one JSON response line.
+//!
+//! ```text
+//! {"token":"…","cmd":"status"}
+//! {"token":"…","cmd":"peers"}
+//! {"token":"…","cmd":"history","limit":10}
+//! {"token":"…","cmd":"send_text","text":"hello"}
+//! {"token":"…","cmd":"send_file","path":"C:\\path\\to\\file.txt"}
+//! ```
+//!
+//! Responses are `{"ok":true,"data":…}` or `{"ok":false,"error":"…"}`.
+//! While the history lock (see [`crate::auth_gate`]) is on and closed,
+//! `history` answers `{"ok":false,"error":"locked"}`.
+//! The token is a random secret stored in `automation_token` next to the
+//! other client state files; requests carrying any other token are rejected.
+//! The same token authenticates requests on the instance pipe (see
+//! [`crate::instance`]).  The pipe admits only the signed-in user (see
+//! [`crate::pipe_security`]); clients should check with
+//! `GetNamedPipeServerProcessId` that the server runs as that user before
+//! sending the token.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_profiles;
+
+/// Upper bound on a single request line.  A `send_text` request carries at
+/// most 256 KiB of text; JSON escaping can expand that, so allow headroom.
+pub const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Default number of entries returned by `history` when no limit is given.
+pub const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// Length of the generated token in raw bytes (hex-encoded on disk).
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum AutomationCommand {
+    Status,
+    Peers,
+    History {
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    SendText {
+        text: String,
+    },
+    SendFile {
+        path: PathBuf,
+    },
+}
+
+impl AutomationCommand {
+    /// Whether the command reads what the history lock keeps hidden.
+    pub fn is_gated(&self) -> bool {
+        matches!(self, AutomationCommand::History { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AutomationRequest {
+    pub token: String,
+    #[serde(flatten)]
+    pub command: AutomationCommand,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AutomationResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+impl AutomationResponse {
+    pub fn success(data: serde_json::Value) -> Self {
+        Self {
+            ok: true,
+            error: None,
+            data: Some(data),
+        }
+    }
+
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            data: None,
+        }
+    }
+
+    /// Serialize as a single response line (including the trailing `\n`).
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self)
+            .unwrap_or_else(|_| r#"{"ok":false,"error":"response serialization failed"}"#.into());
+        line.push('\n');
+        line
+    }
+}
+
+/// Named pipe path for the given Windows user name.
+///
+/// The user name is included so that two users on the same machine (e.g.
+/// fast user switching or a terminal server) never collide on one pipe.
+pub fn pipe_name(user: &str) -> String {
+    format!(r"\\.\pipe\ClipRelay.Automation.{}", pipe_safe_user(user))
+}
+
+/// `user` with anything but ASCII letters, digits, `-`, `_` and `.`
+/// replaced, for use in a pipe name.
+pub fn pipe_safe_user(user: &str) -> String {
+    user.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.' {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Parse and authenticate one request line.
+///
+/// On failure the returned [`AutomationResponse`] is ready to be written
+/// back to the caller as-is.
+pub fn parse_request_line(
+    line: &str,
+    expected_token: &str,
+) -> Result<AutomationCommand, AutomationResponse> {
+    if line.len() > MAX_REQUEST_BYTES {
+        return Err(AutomationResponse::failure(format!(
+            "request too large (max {MAX_REQUEST_BYTES} bytes)"
+        )));
+    }
+    let request: AutomationRequest = serde_json::from_str(line.trim())
+        .map_err(|err| AutomationResponse::failure(format!("invalid request: {err}")))?;
+    if !tokens_match(&request.token, expected_token) {
+        return Err(AutomationResponse::failure("invalid token"));
+    }
+    Ok(request.command)
+}
+
+/// Refuse a gated command unless the history lock is open (or off).
+pub fn check_lock(command: &AutomationCommand, unlocked: bool) -> Result<(), AutomationResponse> {
+    if command.is_gated() && !unlocked {
+        return Err(AutomationResponse::failure("locked"));
+    }
+    Ok(())
+}
+
+/// Compare tokens without short-circuiting on the first differing byte.
+pub fn tokens_match(given: &str, expected: &str) -> bool {
+    let (a, b) = (given.as_bytes(), expected.as_bytes());
+    if a.len() != b.len() || expected.is_empty() {
+        return false;
+    }
+    a.iter().zip(b).fold(0_u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn token_path() -> PathBuf {
+    config_profiles::data_dir().join("automation_token")
+}
+
+pub fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; TOKEN_BYTES]>())
+}
+
+/// Read the token at `path`, creating a fresh one if the file is missing or
+/// does not contain a well-formed token.
+pub fn load_or_create_token_at(path: &Path) -> io::Result<String> {
+    if let Ok(existing) = fs::read_to_string(path) {
+        let existing = existing.trim();
+        if existing.len() == TOKEN_BYTES * 2 && existing.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(existing.to_owned());
+        }
+    }
+    regenerate_token_at(path)
+}
+
+pub fn regenerate_token_at(path: &Path) -> io::Result<String> {
+    let token = generate_token();
+    fs::write(path, token.as_bytes())?;
+    Ok(token)
+}
+
+pub fn load_or_create_token() -> io::Result<String> {
+    load_or_create_token_at(&token_path())
+}
+
+pub fn regenerate_token() -> io::Result<String> {
+    regenerate_token_at(&token_path())
+}
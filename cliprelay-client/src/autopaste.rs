@@ -0,0 +1,171 @@
+//! Synthesized Ctrl+V into the foreground application after a clipboard
+//! apply.
+//!
+//! The exclusion list holds executable file names (e.g. `keepass.exe`);
+//! matching is case-insensitive on the file name only, so entries work
+//! regardless of where the application is installed.
+
+/// Delay between hiding the ClipRelay window and sending the keystroke, so
+/// that Windows has restored focus to the previously active application.
+pub const FOCUS_SETTLE_MS: u64 = 150;
+
+/// Parse a user-entered exclusion list (comma, semicolon or newline
+/// separated) into normalized lower-case executable names.
+pub fn parse_exclusion_list(raw: &str) -> Vec<String> {
+    let mut out: Vec<String> = raw
+        .split([',', ';', '\n'])
+        .map(|s| s.trim().to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Whether the process at `exe_path` matches an entry in `excluded`.
+pub fn is_excluded(exe_path: &str, excluded: &[String]) -> bool {
+    let file_name = exe_path
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(exe_path)
+        .to_ascii_lowercase();
+    excluded.iter().any(|e| e.eq_ignore_ascii_case(&file_name))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteOutcome {
+    Sent,
+    /// The foreground window belongs to ClipRelay itself.
+    OwnWindow,
+    /// The foreground process is on the exclusion list.
+    Excluded(String),
+    NoForegroundWindow,
+    Failed(String),
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcessId, OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+        QueryFullProcessImageNameW,
+    };
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, SendInput, VK_CONTROL,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowThreadProcessId,
+    };
+
+    use super::{PasteOutcome, is_excluded};
+
+    const VK_V: u16 = b'V' as u16;
+
+    /// Returns `(pid, image path)` of the process owning the foreground
+    /// window, or `None` when there is no foreground window.
+    fn foreground_process() -> Option<(u32, String)> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd == 0 {
+            return None;
+        }
+        let mut pid: u32 = 0;
+        unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+        if pid == 0 {
+            return None;
+        }
+
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process == 0 {
+            // Elevated processes cannot be queried from a normal-integrity
+            // process; report the pid without a path.
+            return Some((pid, String::new()));
+        }
+        let mut buf = [0u16; 1024];
+        let mut len = buf.len() as u32;
+        let ok = unsafe {
+            QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len)
+        };
+        unsafe { CloseHandle(process) };
+        let path = if ok != 0 {
+            String::from_utf16_lossy(&buf[..len as usize])
+        } else {
+            String::new()
+        };
+        Some((pid, path))
+    }
+
+    fn key_input(vk: u16, key_up: bool) -> INPUT {
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: 0,
+                    dwFlags: if key_up { KEYEVENTF_KEYUP } else { 0 },
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+
+    pub fn paste_into_foreground(excluded: &[String]) -> PasteOutcome {
+        let Some((pid, exe_path)) = foreground_process() else {
+            return PasteOutcome::NoForegroundWindow;
+        };
+        if pid == unsafe { GetCurrentProcessId() } {
+            return PasteOutcome::OwnWindow;
+        }
+        if !exe_path.is_empty() && is_excluded(&exe_path, excluded) {
+            return PasteOutcome::Excluded(exe_path);
+        }
+
+        let inputs = [
+            key_input(VK_CONTROL, false),
+            key_input(VK_V, false),
+            key_input(VK_V, true),
+            key_input(VK_CONTROL, true),
+        ];
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_ptr(),
+                std::mem::size_of::<INPUT>() as i32,
+            )
+        };
+        if sent as usize == inputs.len() {
+            PasteOutcome::Sent
+        } else {
+            PasteOutcome::Failed(format!(
+                "SendInput injected {sent} of {} events (blocked by UIPI?)",
+                inputs.len()
+            ))
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use platform::paste_into_foreground;
+
+#[cfg(not(target_os = "windows"))]
+pub fn paste_into_foreground(_excluded: &[String]) -> PasteOutcome {
+    PasteOutcome::Failed("auto-paste is only supported on Windows".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_exclusion_list_normalizes_and_dedups() {
+        let list = parse_exclusion_list(" KeePass.exe, mstsc.exe;\nkeepass.exe,, ");
+        assert_eq!(list, vec!["keepass.exe".to_owned(), "mstsc.exe".to_owned()]);
+    }
+
+    #[test]
+    fn is_excluded_matches_file_name_only() {
+        let list = parse_exclusion_list("keepass.exe");
+        assert!(is_excluded(r"C:\Program Files\KeePass\KeePass.exe", &list));
+        assert!(!is_excluded(r"C:\keepass.exe.d\notepad.exe", &list));
+    }
+}
@@ -0,0 +1,667 @@
+//! Starting the client at logon.
+//!
+//! On Windows this is a `HKCU\...\Run` value or a per-user Scheduled Task
+//! (see [`AutostartBackend`]); elsewhere an XDG autostart `.desktop` entry or
+//! a macOS LaunchAgent.  Every mechanism launches the client with
+//! `--background`.
+
+/// Mechanism used to launch ClipRelay at logon.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutostartBackend {
+    /// `HKCU\...\CurrentVersion\Run` value (the original mechanism).
+    #[default]
+    RegistryRun,
+    /// Per-user Scheduled Task with a logon trigger.  Survives group
+    /// policies that strip HKCU Run entries.
+    ScheduledTask,
+}
+
+impl AutostartBackend {
+    pub const ALL: [AutostartBackend; 2] = [
+        AutostartBackend::RegistryRun,
+        AutostartBackend::ScheduledTask,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AutostartBackend::RegistryRun => "Registry (Run key)",
+            AutostartBackend::ScheduledTask => "Scheduled Task",
+        }
+    }
+}
+
+/// Escape text for inclusion in XML element content or attribute values
+/// (Scheduled Task definitions, LaunchAgent plists).
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_impl::*;
+
+#[cfg(not(target_os = "windows"))]
+pub use fallback::*;
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{
+        fmt, io, os::windows::process::CommandExt, path::Path, process::Command,
+        string::FromUtf16Error,
+    };
+
+    use super::{AutostartBackend, xml_escape};
+
+    use windows_sys::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+    use windows_sys::Win32::System::Registry::{
+        HKEY, HKEY_CURRENT_USER, KEY_QUERY_VALUE, KEY_READ, KEY_SET_VALUE, KEY_WRITE, REG_SZ,
+        RegCloseKey, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
+    };
+
+    const RUN_SUBKEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+    /// Upper bound on the autostart command string stored in the registry.
+    /// A reasonable autostart entry is under 1 KiB; 32 KiB is a generous
+    /// safety margin that prevents a malicious or corrupted value from
+    /// causing an out-of-memory allocation (size_bytes is a u32, so the
+    /// unchecked allocation could be up to ~4 GiB).
+    const MAX_RUN_VALUE_BYTES: u32 = 32 * 1024;
+
+    #[derive(Debug)]
+    pub enum AutostartError {
+        RegOpenRunKey { status: u32 },
+        RegQuerySize { status: u32 },
+        RegQueryData { status: u32 },
+        RegSet { status: u32 },
+        RegDelete { status: u32 },
+        ValueTooLarge,
+        InvalidUtf16(FromUtf16Error),
+        TaskXmlWrite(io::Error),
+        SchtasksSpawn(io::Error),
+        Schtasks(&'static str, String),
+    }
+
+    impl fmt::Display for AutostartError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AutostartError::RegOpenRunKey { status } => {
+                    write!(f, "RegOpenKeyExW(Run) failed: {status}")
+                }
+                AutostartError::RegQuerySize { status } => {
+                    write!(f, "RegQueryValueExW(size) failed: {status}")
+                }
+                AutostartError::RegQueryData { status } => {
+                    write!(f, "RegQueryValueExW(data) failed: {status}")
+                }
+                AutostartError::RegSet { status } => write!(f, "RegSetValueExW failed: {status}"),
+                AutostartError::RegDelete { status } => {
+                    write!(f, "RegDeleteValueW failed: {status}")
+                }
+                AutostartError::ValueTooLarge => write!(f, "registry value too large"),
+                AutostartError::InvalidUtf16(e) => write!(f, "invalid UTF-16 in Run value: {e}"),
+                AutostartError::TaskXmlWrite(e) => write!(f, "task XML write failed: {e}"),
+                AutostartError::SchtasksSpawn(e) => write!(f, "failed to run schtasks.exe: {e}"),
+                AutostartError::Schtasks(action, detail) => {
+                    write!(f, "schtasks {action} failed: {detail}")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for AutostartError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                AutostartError::InvalidUtf16(e) => Some(e),
+                AutostartError::TaskXmlWrite(e) | AutostartError::SchtasksSpawn(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn autostart_command(exe: &Path) -> String {
+        format!("\"{}\" --background", exe.display())
+    }
+
+    pub fn is_enabled(exe: &Path, value_name: &str) -> Result<bool, AutostartError> {
+        let expected = autostart_command(exe);
+        Ok(run_key_get_value_string(value_name)?.is_some_and(|v| v.trim() == expected.trim()))
+    }
+
+    pub fn set_enabled(exe: &Path, value_name: &str, enabled: bool) -> Result<(), AutostartError> {
+        if enabled {
+            let cmd = autostart_command(exe);
+            run_key_set_value_string(value_name, &cmd)?;
+        } else {
+            run_key_delete_value(value_name)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled_with(
+        backend: AutostartBackend,
+        exe: &Path,
+        name: &str,
+    ) -> Result<bool, AutostartError> {
+        match backend {
+            AutostartBackend::RegistryRun => is_enabled(exe, name),
+            AutostartBackend::ScheduledTask => task_is_enabled(exe, name),
+        }
+    }
+
+    pub fn set_enabled_with(
+        backend: AutostartBackend,
+        exe: &Path,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), AutostartError> {
+        match backend {
+            AutostartBackend::RegistryRun => set_enabled(exe, name, enabled),
+            AutostartBackend::ScheduledTask if enabled => task_create(exe, name),
+            AutostartBackend::ScheduledTask => task_delete(name),
+        }
+    }
+
+    // ── Scheduled Task backend ──────────────────────────────────────────────
+    //
+    // Driven through schtasks.exe with an XML definition: a logon trigger
+    // scoped to the current user can be registered without elevation this
+    // way, whereas `/SC ONLOGON` on the command line requires admin rights.
+
+    /// Keeps schtasks.exe from flashing a console window.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    fn current_task_user() -> String {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        match std::env::var("USERDOMAIN") {
+            Ok(domain) if !domain.is_empty() => format!("{domain}\\{user}"),
+            _ => user,
+        }
+    }
+
+    pub fn task_xml(exe: &Path, user: &str) -> String {
+        let user = xml_escape(user);
+        let exe = xml_escape(&exe.display().to_string());
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Start ClipRelay in the background at logon.</Description>
+  </RegistrationInfo>
+  <Triggers>
+    <LogonTrigger>
+      <Enabled>true</Enabled>
+      <UserId>{user}</UserId>
+    </LogonTrigger>
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      <UserId>{user}</UserId>
+      <LogonType>InteractiveToken</LogonType>
+      <RunLevel>LeastPrivilege</RunLevel>
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Enabled>true</Enabled>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{exe}</Command>
+      <Arguments>--background</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#
+        )
+    }
+
+    fn schtasks(action: &'static str, args: &[&str]) -> Result<String, AutostartError> {
+        let output = Command::new("schtasks.exe")
+            .args(args)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map_err(AutostartError::SchtasksSpawn)?;
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(AutostartError::Schtasks(
+                action,
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ))
+        }
+    }
+
+    fn task_is_enabled(exe: &Path, name: &str) -> Result<bool, AutostartError> {
+        // A failing query means the task does not exist.
+        let Ok(xml) = schtasks("query", &["/Query", "/TN", name, "/XML"]) else {
+            return Ok(false);
+        };
+        let xml = xml.to_ascii_lowercase();
+        let exe = xml_escape(&exe.display().to_string()).to_ascii_lowercase();
+        Ok(xml.contains(&format!("<command>{exe}</command>")) && xml.contains("--background"))
+    }
+
+    fn task_create(exe: &Path, name: &str) -> Result<(), AutostartError> {
+        // schtasks expects the XML file as UTF-16LE with a BOM.
+        let xml = task_xml(exe, &current_task_user());
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(xml.encode_utf16().flat_map(u16::to_le_bytes));
+        let path = std::env::temp_dir().join(format!("{name}-autostart-task.xml"));
+        std::fs::write(&path, bytes).map_err(AutostartError::TaskXmlWrite)?;
+
+        let path_str = path.display().to_string();
+        let result = schtasks("create", &["/Create", "/TN", name, "/XML", &path_str, "/F"]);
+        let _ = std::fs::remove_file(&path);
+        result.map(|_| ())
+    }
+
+    fn task_delete(name: &str) -> Result<(), AutostartError> {
+        if schtasks("query", &["/Query", "/TN", name]).is_err() {
+            return Ok(());
+        }
+        schtasks("delete", &["/Delete", "/TN", name, "/F"]).map(|_| ())
+    }
+
+    fn run_key_open(desired_access: u32) -> Result<HKEY, AutostartError> {
+        let subkey = wide_null(RUN_SUBKEY);
+        let mut out: HKEY = 0;
+        let status = unsafe {
+            RegOpenKeyExW(
+                HKEY_CURRENT_USER,
+                subkey.as_ptr(),
+                0,
+                desired_access,
+                &mut out,
+            )
+        };
+        if status != 0 {
+            return Err(AutostartError::RegOpenRunKey { status });
+        }
+        Ok(out)
+    }
+
+    fn run_key_get_value_string(name: &str) -> Result<Option<String>, AutostartError> {
+        let key = run_key_open(KEY_READ | KEY_QUERY_VALUE)?;
+        let name_w = wide_null(name);
+
+        let mut value_type: u32 = 0;
+        let mut size_bytes: u32 = 0;
+        let status = unsafe {
+            RegQueryValueExW(
+                key,
+                name_w.as_ptr(),
+                std::ptr::null_mut(),
+                &mut value_type,
+                std::ptr::null_mut(),
+                &mut size_bytes,
+            )
+        };
+
+        if status == ERROR_FILE_NOT_FOUND {
+            unsafe { RegCloseKey(key) };
+            return Ok(None);
+        }
+
+        if status != 0 {
+            unsafe { RegCloseKey(key) };
+            return Err(AutostartError::RegQuerySize { status });
+        }
+
+        if value_type != REG_SZ {
+            unsafe { RegCloseKey(key) };
+            return Ok(None);
+        }
+
+        if size_bytes == 0 {
+            unsafe { RegCloseKey(key) };
+            return Ok(Some(String::new()));
+        }
+
+        if size_bytes > MAX_RUN_VALUE_BYTES {
+            unsafe { RegCloseKey(key) };
+            return Err(AutostartError::ValueTooLarge);
+        }
+
+        let mut buf: Vec<u8> = vec![0u8; size_bytes as usize];
+        let mut size_bytes_2 = size_bytes;
+        let status = unsafe {
+            RegQueryValueExW(
+                key,
+                name_w.as_ptr(),
+                std::ptr::null_mut(),
+                &mut value_type,
+                buf.as_mut_ptr(),
+                &mut size_bytes_2,
+            )
+        };
+        unsafe { RegCloseKey(key) };
+        if status != 0 {
+            return Err(AutostartError::RegQueryData { status });
+        }
+
+        if !buf.len().is_multiple_of(2) {
+            return Ok(None);
+        }
+        let mut utf16: Vec<u16> = Vec::with_capacity(buf.len() / 2);
+        for chunk in buf.chunks_exact(2) {
+            utf16.push(u16::from_le_bytes([chunk[0], chunk[1]]));
+        }
+        if let Some(0) = utf16.last().copied() {
+            utf16.pop();
+        }
+
+        String::from_utf16(&utf16)
+            .map(Some)
+            .map_err(AutostartError::InvalidUtf16)
+    }
+
+    fn run_key_set_value_string(name: &str, value: &str) -> Result<(), AutostartError> {
+        let key = run_key_open(KEY_WRITE | KEY_SET_VALUE)?;
+        let name_w = wide_null(name);
+        let value_w = wide_null(value);
+        let bytes: &[u8] =
+            unsafe { std::slice::from_raw_parts(value_w.as_ptr() as *const u8, value_w.len() * 2) };
+
+        // Validate the byte length BEFORE entering the unsafe RegSetValueExW call.
+        // If the value is unreasonably large, close the key and return the error
+        // immediately — the previous code embedded the try_from inside the unsafe
+        // block's argument list, which meant the early-return via `?` bypassed
+        // the `RegCloseKey` call below and leaked the handle.
+        let byte_len = u32::try_from(bytes.len()).map_err(|_| {
+            unsafe { RegCloseKey(key) };
+            AutostartError::ValueTooLarge
+        })?;
+
+        let status =
+            unsafe { RegSetValueExW(key, name_w.as_ptr(), 0, REG_SZ, bytes.as_ptr(), byte_len) };
+        unsafe { RegCloseKey(key) };
+        if status != 0 {
+            return Err(AutostartError::RegSet { status });
+        }
+        Ok(())
+    }
+
+    fn run_key_delete_value(name: &str) -> Result<(), AutostartError> {
+        let key = run_key_open(KEY_WRITE | KEY_SET_VALUE)?;
+        let name_w = wide_null(name);
+        let status = unsafe { RegDeleteValueW(key, name_w.as_ptr()) };
+        unsafe { RegCloseKey(key) };
+        if status == ERROR_FILE_NOT_FOUND {
+            return Ok(());
+        }
+        if status != 0 {
+            return Err(AutostartError::RegDelete { status });
+        }
+        Ok(())
+    }
+
+    fn wide_null(s: &str) -> Vec<u16> {
+        let mut v: Vec<u16> = s.encode_utf16().collect();
+        v.push(0);
+        v
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn autostart_command_quotes_and_background() {
+            let p = std::path::PathBuf::from(r"C:\Program Files\ClipRelay\ClipRelay.exe");
+            let cmd = autostart_command(&p);
+            assert!(cmd.starts_with('"'));
+            assert!(cmd.contains("\" --background"));
+        }
+
+        #[test]
+        fn wide_null_has_trailing_nul() {
+            let v = wide_null("abc");
+            assert_eq!(v.last().copied(), Some(0));
+        }
+
+        #[test]
+        fn task_xml_escapes_and_runs_in_background() {
+            let p = std::path::PathBuf::from(r"C:\Tools & Apps\ClipRelay.exe");
+            let xml = task_xml(&p, r"CORP\alice");
+            assert!(xml.contains(r"<Command>C:\Tools &amp; Apps\ClipRelay.exe</Command>"));
+            assert!(xml.contains("<Arguments>--background</Arguments>"));
+            assert!(xml.contains(r"<UserId>CORP\alice</UserId>"));
+        }
+    }
+}
+
+/// Login-item support outside Windows: an XDG autostart `.desktop` entry on
+/// Linux and other freedesktop systems, a per-user LaunchAgent on macOS.
+/// Both launch the client with `--background`, like the Windows Run entry.
+#[cfg(not(target_os = "windows"))]
+mod fallback {
+    use std::{
+        fmt, fs, io,
+        path::{Path, PathBuf},
+    };
+
+    use super::{AutostartBackend, xml_escape};
+
+    #[derive(Debug)]
+    pub enum AutostartError {
+        /// Neither `$HOME` nor (on Linux) `$XDG_CONFIG_HOME` is set.
+        NoHomeDir,
+        Read(io::Error),
+        Write(io::Error),
+        Remove(io::Error),
+    }
+
+    impl fmt::Display for AutostartError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                AutostartError::NoHomeDir => write!(f, "home directory not found"),
+                AutostartError::Read(e) => write!(f, "autostart entry read failed: {e}"),
+                AutostartError::Write(e) => write!(f, "autostart entry write failed: {e}"),
+                AutostartError::Remove(e) => write!(f, "autostart entry remove failed: {e}"),
+            }
+        }
+    }
+
+    impl std::error::Error for AutostartError {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                AutostartError::Read(e) | AutostartError::Write(e) | AutostartError::Remove(e) => {
+                    Some(e)
+                }
+                AutostartError::NoHomeDir => None,
+            }
+        }
+    }
+
+    pub fn autostart_command(exe: &Path) -> String {
+        format!("\"{}\" --background", exe.display())
+    }
+
+    /// LaunchAgent label (and plist file stem) for `value_name`.
+    pub fn launch_agent_label(value_name: &str) -> String {
+        format!("uk.co.swatto.{}", value_name.to_ascii_lowercase())
+    }
+
+    /// Quote one argument for a desktop entry `Exec` key.
+    ///
+    /// Per the Desktop Entry spec, `"`, `` ` ``, `$` and `\` are
+    /// backslash-escaped inside the quotes, and the general string escaping
+    /// (which doubles backslashes) is applied on top of that.
+    fn desktop_exec_quote(arg: &str) -> String {
+        let mut quoted = String::with_capacity(arg.len() + 2);
+        quoted.push('"');
+        for ch in arg.chars() {
+            if matches!(ch, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(ch);
+        }
+        quoted.push('"');
+        quoted.replace('\\', "\\\\")
+    }
+
+    pub fn desktop_entry(exe: &Path, value_name: &str) -> String {
+        format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={value_name}\n\
+             Comment=Start {value_name} in the background at login\n\
+             Exec={} --background\n\
+             Terminal=false\n\
+             X-GNOME-Autostart-enabled=true\n",
+            desktop_exec_quote(&exe.display().to_string())
+        )
+    }
+
+    pub fn launch_agent_plist(exe: &Path, value_name: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>--background</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+</dict>
+</plist>
+"#,
+            xml_escape(&launch_agent_label(value_name)),
+            xml_escape(&exe.display().to_string())
+        )
+    }
+
+    /// Contents of the login entry for this platform.
+    pub fn entry_contents(exe: &Path, value_name: &str) -> String {
+        if cfg!(target_os = "macos") {
+            launch_agent_plist(exe, value_name)
+        } else {
+            desktop_entry(exe, value_name)
+        }
+    }
+
+    /// Location of the login entry for this platform.
+    pub fn entry_path(value_name: &str) -> Result<PathBuf, AutostartError> {
+        let home = std::env::var_os("HOME")
+            .filter(|h| !h.is_empty())
+            .map(PathBuf::from);
+        if cfg!(target_os = "macos") {
+            let home = home.ok_or(AutostartError::NoHomeDir)?;
+            return Ok(home
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", launch_agent_label(value_name))));
+        }
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home.map(|h| h.join(".config")))
+            .ok_or(AutostartError::NoHomeDir)?;
+        Ok(config_home
+            .join("autostart")
+            .join(format!("{}.desktop", value_name.to_ascii_lowercase())))
+    }
+
+    /// Whether `path` holds an active entry that launches `exe`.
+    pub fn is_enabled_at(
+        path: &Path,
+        exe: &Path,
+        value_name: &str,
+    ) -> Result<bool, AutostartError> {
+        let existing = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(AutostartError::Read(e)),
+        };
+        // Desktop environments disable an entry in place rather than
+        // deleting it; treat those as off.
+        let disabled = existing.lines().any(|line| {
+            let line = line.trim();
+            line.eq_ignore_ascii_case("Hidden=true")
+                || line.eq_ignore_ascii_case("X-GNOME-Autostart-enabled=false")
+        });
+        Ok(!disabled && existing.trim() == entry_contents(exe, value_name).trim())
+    }
+
+    pub fn set_enabled_at(
+        path: &Path,
+        exe: &Path,
+        value_name: &str,
+        enabled: bool,
+    ) -> Result<(), AutostartError> {
+        if !enabled {
+            return match fs::remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(AutostartError::Remove(e)),
+            };
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(AutostartError::Write)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, entry_contents(exe, value_name)).map_err(AutostartError::Write)?;
+        fs::rename(&tmp, path).map_err(AutostartError::Write)
+    }
+
+    pub fn is_enabled(exe: &Path, value_name: &str) -> Result<bool, AutostartError> {
+        is_enabled_at(&entry_path(value_name)?, exe, value_name)
+    }
+
+    pub fn set_enabled(exe: &Path, value_name: &str, enabled: bool) -> Result<(), AutostartError> {
+        set_enabled_at(&entry_path(value_name)?, exe, value_name, enabled)
+    }
+
+    /// There is a single login mechanism per platform here, so the backend
+    /// selection (a Windows concept) is ignored.
+    pub fn is_enabled_with(
+        _backend: AutostartBackend,
+        exe: &Path,
+        name: &str,
+    ) -> Result<bool, AutostartError> {
+        is_enabled(exe, name)
+    }
+
+    pub fn set_enabled_with(
+        _backend: AutostartBackend,
+        exe: &Path,
+        name: &str,
+        enabled: bool,
+    ) -> Result<(), AutostartError> {
+        set_enabled(exe, name, enabled)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn desktop_exec_quotes_reserved_characters() {
+            assert_eq!(
+                desktop_exec_quote("/opt/clip relay/bin"),
+                "\"/opt/clip relay/bin\""
+            );
+            assert_eq!(desktop_exec_quote("/tmp/$x"), "\"/tmp/\\\\$x\"");
+        }
+
+        #[test]
+        fn launch_agent_plist_escapes_path() {
+            let plist =
+                launch_agent_plist(Path::new("/Applications/A&B.app/ClipRelay"), "ClipRelay");
+            assert!(plist.contains("<string>uk.co.swatto.cliprelay</string>"));
+            assert!(plist.contains("<string>/Applications/A&amp;B.app/ClipRelay</string>"));
+            assert!(plist.contains("<string>--background</string>"));
+        }
+    }
+}
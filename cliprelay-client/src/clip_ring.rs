@@ -0,0 +1,133 @@
+//! In-memory ring of recent clipboard texts for the quick-pick overlay.
+//!
+//! Unlike the activity history (which keeps only short summaries on disk),
+//! the ring holds full texts so an item can be re-applied or re-sent.  It is
+//! deliberately never persisted: clipboard contents may be sensitive.
+
+use std::collections::VecDeque;
+
+/// Number of items kept in the ring.
+pub const MAX_CLIP_RING_ITEMS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RingSource {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RingItem {
+    pub ts_unix_ms: u64,
+    pub source: RingSource,
+    /// Device ID of the sender for received items, `"room"` for sent ones.
+    pub peer_device_id: String,
+    pub text: String,
+    /// The sender's label for the item, if any.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClipRing {
+    items: VecDeque<RingItem>,
+    capacity: usize,
+}
+
+impl Default for ClipRing {
+    fn default() -> Self {
+        Self::with_capacity(MAX_CLIP_RING_ITEMS)
+    }
+}
+
+impl ClipRing {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Insert `item` as the most recent entry.  An existing entry with the
+    /// same text is moved to the front instead of being duplicated.
+    pub fn push(&mut self, item: RingItem) {
+        if item.text.trim().is_empty() {
+            return;
+        }
+        self.items.retain(|existing| existing.text != item.text);
+        self.items.push_front(item);
+        self.items.truncate(self.capacity);
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &RingItem> {
+        self.items.iter()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RingItem> {
+        self.items.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Keep only the items `keep` returns `true` for, in order.
+    pub fn retain(&mut self, keep: impl FnMut(&RingItem) -> bool) {
+        self.items.retain(keep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(text: &str, ts: u64) -> RingItem {
+        RingItem {
+            ts_unix_ms: ts,
+            source: RingSource::Received,
+            peer_device_id: "peer".to_owned(),
+            text: text.to_owned(),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn push_moves_duplicates_to_front_and_bounds_size() {
+        let mut ring = ClipRing::with_capacity(3);
+        ring.push(item("a", 1));
+        ring.push(item("b", 2));
+        ring.push(item("a", 3));
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.get(0).map(|i| i.ts_unix_ms), Some(3));
+
+        ring.push(item("c", 4));
+        ring.push(item("d", 5));
+        assert_eq!(ring.len(), 3);
+        let texts: Vec<_> = ring.items().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, ["d", "c", "a"]);
+    }
+
+    #[test]
+    fn retain_removes_items_in_place() {
+        let mut ring = ClipRing::default();
+        ring.push(item("a", 1));
+        ring.push(item("b", 2));
+        ring.push(item("c", 3));
+        ring.retain(|i| i.text != "b");
+        let texts: Vec<_> = ring.items().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, ["c", "a"]);
+    }
+
+    #[test]
+    fn push_ignores_blank_text() {
+        let mut ring = ClipRing::default();
+        ring.push(item("  \n", 1));
+        assert!(ring.is_empty());
+    }
+}
@@ -0,0 +1,419 @@
+//! Syntax highlighting for received text that looks like code.
+//!
+//! The language comes from the sender's `lang` event extension (see
+//! `cliprelay_core::event_schema`) when it names one known here, otherwise
+//! from [`detect`].  [`tokenize`] only splits the text into ranges of the
+//! original string; colours are applied to those ranges when drawing, so
+//! the highlighted preview can never differ from the text that is applied.
+
+use std::ops::Range;
+
+/// Event extension holding the sender's language hint, e.g. `"rust"`.
+pub const LANGUAGE_EXTENSION: &str = "lang";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Json,
+    Shell,
+    Sql,
+    /// Code in no language above: C-style comments and strings only.
+    Generic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range in the tokenized text.
+    pub range: Range<usize>,
+}
+
+impl Language {
+    /// The language a hint names; common file extensions are accepted too.
+    pub fn from_hint(hint: &str) -> Option<Self> {
+        let language = match hint.trim().to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Language::Rust,
+            "python" | "py" => Language::Python,
+            "javascript" | "js" | "typescript" | "ts" => Language::JavaScript,
+            "json" => Language::Json,
+            "shell" | "sh" | "bash" | "powershell" | "ps1" => Language::Shell,
+            "sql" => Language::Sql,
+            "c" | "cpp" | "c++" | "csharp" | "cs" | "java" | "go" | "code" => Language::Generic,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+            Language::JavaScript => "JavaScript",
+            Language::Json => "JSON",
+            Language::Shell => "Shell",
+            Language::Sql => "SQL",
+            Language::Generic => "Code",
+        }
+    }
+
+    fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "else", "enum",
+                "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+                "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "trait", "true",
+                "type", "unsafe", "use", "where", "while",
+            ],
+            Language::Python => &[
+                "and", "as", "async", "await", "break", "class", "continue", "def", "elif", "else",
+                "except", "False", "finally", "for", "from", "if", "import", "in", "is", "lambda",
+                "None", "not", "or", "pass", "raise", "return", "True", "try", "while", "with",
+                "yield",
+            ],
+            Language::JavaScript => &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "default",
+                "else",
+                "export",
+                "false",
+                "for",
+                "from",
+                "function",
+                "if",
+                "import",
+                "let",
+                "new",
+                "null",
+                "return",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typeof",
+                "undefined",
+                "var",
+                "while",
+            ],
+            Language::Json => &["true", "false", "null"],
+            Language::Shell => &[
+                "case", "do", "done", "echo", "elif", "else", "esac", "export", "fi", "for",
+                "function", "if", "in", "local", "then", "while",
+            ],
+            Language::Sql => &[
+                "and", "as", "by", "create", "delete", "from", "group", "insert", "into", "join",
+                "left", "limit", "not", "null", "on", "or", "order", "select", "set", "table",
+                "update", "values", "where",
+            ],
+            Language::Generic => &[
+                "break", "class", "const", "continue", "else", "false", "for", "if", "new", "null",
+                "public", "private", "return", "static", "struct", "true", "void", "while",
+            ],
+        }
+    }
+
+    fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Language::Python | Language::Shell => Some("#"),
+            Language::Sql => Some("--"),
+            Language::Json => None,
+            Language::Rust | Language::JavaScript | Language::Generic => Some("//"),
+        }
+    }
+
+    fn block_comments(self) -> bool {
+        matches!(
+            self,
+            Language::Rust | Language::JavaScript | Language::Sql | Language::Generic
+        )
+    }
+
+    fn quotes(self) -> &'static [char] {
+        match self {
+            // `'` also starts lifetimes and is left alone.
+            Language::Rust | Language::Json => &['"'],
+            Language::JavaScript | Language::Shell => &['"', '\'', '`'],
+            Language::Python | Language::Sql | Language::Generic => &['"', '\''],
+        }
+    }
+}
+
+/// Line starts that point to one language.
+const SIGNATURES: &[(Language, &[&str])] = &[
+    (
+        Language::Rust,
+        &[
+            "fn ", "pub ", "use ", "impl ", "let mut ", "struct ", "enum ", "mod ", "#[",
+        ],
+    ),
+    (
+        Language::Python,
+        &["def ", "import ", "from ", "class ", "elif ", "print("],
+    ),
+    (
+        Language::JavaScript,
+        &[
+            "function ",
+            "const ",
+            "export ",
+            "var ",
+            "console.",
+            "import ",
+        ],
+    ),
+    (
+        Language::Sql,
+        &[
+            "select ", "insert ", "update ", "delete ", "create ", "from ", "where ",
+        ],
+    ),
+    (
+        Language::Shell,
+        &["#!/", "$ ", "sudo ", "echo ", "cd ", "export ", "git "],
+    ),
+];
+
+/// The language of `text` if it looks like code, `None` for prose.
+pub fn detect(text: &str) -> Option<Language> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some(Language::Json);
+    }
+
+    let lines: Vec<&str> = trimmed.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut scores = [0usize; SIGNATURES.len()];
+    let mut code_lines = 0;
+    for line in &lines {
+        let start = line.trim_start().to_ascii_lowercase();
+        let mut signed = false;
+        for (score, (_, prefixes)) in scores.iter_mut().zip(SIGNATURES) {
+            if prefixes.iter().any(|prefix| start.starts_with(prefix)) {
+                *score += 1;
+                signed = true;
+            }
+        }
+        let end = line.trim_end();
+        if signed || end.ends_with([';', '{', '}', ')', ':', ',']) || end == "]" {
+            code_lines += 1;
+        }
+    }
+
+    // Most lines read like code, and a single line needs a signature.
+    let best = (0..SIGNATURES.len()).max_by_key(|&i| (scores[i], std::cmp::Reverse(i)))?;
+    let signed = scores[best] > 0;
+    if code_lines * 2 < lines.len() || (lines.len() == 1 && !signed) {
+        return None;
+    }
+    Some(if signed {
+        SIGNATURES[best].0
+    } else {
+        Language::Generic
+    })
+}
+
+/// Split `text` into tokens whose ranges cover it exactly, in order.
+pub fn tokenize(text: &str, language: Language) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut push = |kind: TokenKind, range: Range<usize>| {
+        if range.is_empty() {
+            return;
+        }
+        match tokens.last_mut() {
+            Some(last) if last.kind == kind && last.range.end == range.start => {
+                last.range.end = range.end;
+            }
+            _ => tokens.push(Token { kind, range }),
+        }
+    };
+
+    let bytes = text.as_bytes();
+    let mut at = 0;
+    while at < text.len() {
+        let rest = &text[at..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        if let Some(marker) = language.line_comment()
+            && rest.starts_with(marker)
+        {
+            let end = rest.find('\n').map_or(text.len(), |n| at + n);
+            push(TokenKind::Comment, at..end);
+            at = end;
+        } else if language.block_comments() && rest.starts_with("/*") {
+            let end = rest[2..].find("*/").map_or(text.len(), |n| at + 2 + n + 2);
+            push(TokenKind::Comment, at..end);
+            at = end;
+        } else if language.quotes().contains(&c) {
+            let end = string_end(text, at, c);
+            push(TokenKind::String, at..end);
+            at = end;
+        } else if c.is_ascii_digit() {
+            let end = word_end(text, at);
+            push(TokenKind::Number, at..end);
+            at = end;
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = word_end(text, at);
+            let word = &text[at..end];
+            let keyword = if language == Language::Sql {
+                language
+                    .keywords()
+                    .contains(&word.to_ascii_lowercase().as_str())
+            } else {
+                language.keywords().contains(&word)
+            };
+            // `foo.if` is a field, not a keyword.
+            let after_dot = at > 0 && bytes[at - 1] == b'.';
+            let kind = if keyword && !after_dot {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Plain
+            };
+            push(kind, at..end);
+            at = end;
+        } else {
+            push(TokenKind::Plain, at..at + c.len_utf8());
+            at += c.len_utf8();
+        }
+    }
+    tokens
+}
+
+/// End of the string opened by `quote` at `start`: after the closing
+/// quote, or at the end of the line (end of the text for backticks) when
+/// it is not closed.
+fn string_end(text: &str, start: usize, quote: char) -> usize {
+    let mut escaped = false;
+    for (offset, c) in text[start..].char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return start + offset + c.len_utf8();
+        } else if c == '\n' && quote != '`' {
+            return start + offset;
+        }
+    }
+    text.len()
+}
+
+fn word_end(text: &str, start: usize) -> usize {
+    text[start..]
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_'))
+        .map_or(text.len(), |(offset, _)| start + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str, language: Language) -> Vec<(TokenKind, &str)> {
+        tokenize(text, language)
+            .into_iter()
+            .map(|token| (token.kind, &text[token.range]))
+            .collect()
+    }
+
+    #[test]
+    fn tokens_cover_the_text_exactly() {
+        let samples = [
+            "fn main() {\n    let s = \"a \\\" b\"; // done\n}",
+            "/* unterminated",
+            "'open string\nnext line",
+            "x = `multi\nline`",
+            "ünïcödé → 42 'é'",
+            "",
+        ];
+        for text in samples {
+            for language in [Language::Rust, Language::Shell, Language::Sql] {
+                let tokens = tokenize(text, language);
+                let rebuilt: String = tokens.iter().map(|t| &text[t.range.clone()]).collect();
+                assert_eq!(rebuilt, text);
+                assert!(
+                    tokens
+                        .windows(2)
+                        .all(|w| w[0].range.end == w[1].range.start)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keywords_strings_and_comments_are_found() {
+        assert_eq!(
+            kinds("let x = \"if\"; // if", Language::Rust),
+            vec![
+                (TokenKind::Keyword, "let"),
+                (TokenKind::Plain, " x = "),
+                (TokenKind::String, "\"if\""),
+                (TokenKind::Plain, "; "),
+                (TokenKind::Comment, "// if"),
+            ]
+        );
+        assert_eq!(
+            kinds("Select id FROM t", Language::Sql)[0],
+            (TokenKind::Keyword, "Select")
+        );
+        assert_eq!(
+            kinds("a.if 10", Language::Rust),
+            vec![(TokenKind::Plain, "a.if "), (TokenKind::Number, "10")]
+        );
+    }
+
+    #[test]
+    fn code_is_told_apart_from_prose() {
+        assert_eq!(
+            detect("fn main() {\n    println!(\"hi\");\n}"),
+            Some(Language::Rust)
+        );
+        assert_eq!(
+            detect("def f(x):\n    return x + 1\n"),
+            Some(Language::Python)
+        );
+        assert_eq!(detect(r#"{"a": [1, 2]}"#), Some(Language::Json));
+        assert_eq!(
+            detect("SELECT * FROM users WHERE id = 1"),
+            Some(Language::Sql)
+        );
+        assert_eq!(
+            detect("int a = 1;\nint b = 2;\nreturn a + b;"),
+            Some(Language::Generic)
+        );
+        assert_eq!(
+            detect("Hi Sam,\nthe meeting moved to 3pm.\nSee you there."),
+            None
+        );
+        assert_eq!(detect("Remember to buy milk;"), None);
+        assert_eq!(detect("   "), None);
+    }
+
+    #[test]
+    fn hints_name_languages() {
+        assert_eq!(Language::from_hint(" Py "), Some(Language::Python));
+        assert_eq!(Language::from_hint("ts"), Some(Language::JavaScript));
+        assert_eq!(Language::from_hint("klingon"), None);
+    }
+}
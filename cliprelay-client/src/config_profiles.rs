@@ -0,0 +1,171 @@
+//! Named configuration profiles ("Home", "Work", …) with fully separate
+//! settings.
+//!
+//! The default profile keeps its files directly in `%LOCALAPPDATA%\ClipRelay`
+//! as before.  A named profile keeps its own `config.json` (server, room,
+//! client name and send counter), `history.json`, `ui_state.json` and
+//! `usage_stats.json` in `%LOCALAPPDATA%\ClipRelay\config-profiles\<name>`;
+//! those are the files resolved through [`active_dir`].
+//!
+//! Everything else belongs to the Windows account and stays in [`data_dir`],
+//! shared by all profiles:
+//!
+//! - `identity.json`: the device is the same PC whichever profile runs, so
+//!   peers keep seeing one key for it.
+//! - `devices.json`: trust in a peer's key does not depend on the room.
+//! - `snippets.json` and `profiles.json`: the user's own library.
+//! - `automation_token`: the automation pipe is per Windows user, and
+//!   serves whichever profile is running.
+//! - `logs`, `crashes` and `diagnostics`: one client, one set of reports.
+//!
+//! The active profile is chosen once per process (`--profile NAME`, or the
+//! last one picked in the setup screen) and read by the path helpers.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Directory under the ClipRelay folder holding named profiles.
+pub const PROFILES_DIR: &str = "config-profiles";
+
+/// Name shown for (and accepted as) the default profile.
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// Longest profile name accepted (in characters).
+pub const MAX_NAME_CHARS: usize = 32;
+
+/// Remembers the profile last picked in the setup screen.
+const LAST_USED_FILE: &str = "last_used";
+
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileNameError {
+    TooLong { max: usize },
+    InvalidChar(char),
+}
+
+impl fmt::Display for ProfileNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileNameError::TooLong { max } => {
+                write!(f, "profile name is longer than {max} characters")
+            }
+            ProfileNameError::InvalidChar(c) => write!(
+                f,
+                "profile name contains {c:?}; use letters, digits, spaces, '-' or '_'"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProfileNameError {}
+
+/// Check a user-entered profile name.  Empty and "default" (in any case)
+/// mean the default profile.  The name becomes a directory name, so only
+/// letters, digits, spaces, `-` and `_` are allowed.
+pub fn parse_name(raw: &str) -> Result<Option<String>, ProfileNameError> {
+    let name = raw.trim();
+    if name.is_empty() || name.eq_ignore_ascii_case(DEFAULT_PROFILE_NAME) {
+        return Ok(None);
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(ProfileNameError::TooLong {
+            max: MAX_NAME_CHARS,
+        });
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || matches!(c, ' ' | '-' | '_')))
+    {
+        return Err(ProfileNameError::InvalidChar(c));
+    }
+    Ok(Some(name.to_owned()))
+}
+
+/// The profile whose files are in use; `None` is the default profile.
+pub fn active() -> Option<String> {
+    ACTIVE.read().map(|name| name.clone()).unwrap_or_default()
+}
+
+pub fn set_active(profile: Option<String>) {
+    if let Ok(mut active) = ACTIVE.write() {
+        *active = profile;
+    }
+}
+
+/// Label for a profile in the UI.
+pub fn display_name(profile: Option<&str>) -> &str {
+    profile.unwrap_or(DEFAULT_PROFILE_NAME)
+}
+
+/// `%LOCALAPPDATA%\ClipRelay`, shared by every profile, created if needed.
+/// Every file of the client lives in or below it.
+pub fn data_dir() -> PathBuf {
+    let base = std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let dir = base.join("ClipRelay");
+    let _ = fs::create_dir_all(&dir);
+    dir
+}
+
+/// Where `profile` keeps its files below `base`.
+pub fn profile_dir(base: &Path, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => base.join(PROFILES_DIR).join(name),
+        None => base.to_path_buf(),
+    }
+}
+
+/// Directory of the active profile, created if needed.
+pub fn active_dir() -> PathBuf {
+    let dir = profile_dir(&data_dir(), active().as_deref());
+    let _ = fs::create_dir_all(&dir);
+    dir
+}
+
+/// Named profiles under `base`, sorted by name.  Directories whose name is
+/// not a valid profile name are skipped.
+pub fn list_profiles(base: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(base.join(PROFILES_DIR)) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| matches!(parse_name(name), Ok(Some(parsed)) if parsed == *name))
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+/// Create the directory of a named profile.
+pub fn create_profile(base: &Path, name: &str) -> io::Result<()> {
+    fs::create_dir_all(profile_dir(base, Some(name)))
+}
+
+/// The profile last picked in the setup screen, if it still exists.
+pub fn load_last_used(base: &Path) -> Option<String> {
+    let raw = fs::read_to_string(base.join(PROFILES_DIR).join(LAST_USED_FILE)).ok()?;
+    let name = parse_name(&raw).ok()??;
+    profile_dir(base, Some(&name)).is_dir().then_some(name)
+}
+
+pub fn save_last_used(base: &Path, profile: Option<&str>) -> io::Result<()> {
+    let dir = base.join(PROFILES_DIR);
+    let path = dir.join(LAST_USED_FILE);
+    match profile {
+        Some(name) => {
+            fs::create_dir_all(&dir)?;
+            fs::write(path, name)
+        }
+        None => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        },
+    }
+}
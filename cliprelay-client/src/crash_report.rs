@@ -0,0 +1,139 @@
+//! Crash reports: when the client dies, a text report (reason, backtrace and
+//! the tail of the client log) is written to `%LOCALAPPDATA%\ClipRelay\crashes`
+//! next to a minidump, and a marker file makes the next start offer to open
+//! it.
+//!
+//! Only the file handling lives here; the panic hook and exception filter
+//! that call it are installed by the Windows client.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{config_profiles, log_view::strip_ansi};
+
+/// Log lines copied into each report.
+pub const CRASH_LOG_LINES: usize = 200;
+
+/// Reports (and their dumps) kept; older ones are deleted.
+pub const MAX_CRASH_REPORTS: usize = 5;
+
+/// Holds the path of the newest report until the user has been told.
+pub const PENDING_MARKER: &str = "pending.txt";
+
+/// How far back from the end of the log the tail is searched.
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+
+pub fn crash_dir() -> PathBuf {
+    config_profiles::data_dir().join("crashes")
+}
+
+/// File stem shared by a report and its dump.
+pub fn report_stem(ts_unix_ms: u64) -> String {
+    format!("crash-{ts_unix_ms}")
+}
+
+/// The last `count` lines of the log at `path`, without colour codes.
+pub fn last_log_lines(path: &Path, count: usize) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    let text = String::from_utf8_lossy(&buf);
+    let mut lines: Vec<&str> = text.lines().collect();
+    if start > 0 && !lines.is_empty() {
+        // Started mid-file: the first line is cut off.
+        lines.remove(0);
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..]
+        .iter()
+        .map(|line| strip_ansi(line.trim_end_matches('\r')))
+        .collect())
+}
+
+/// Text of a crash report.
+pub fn format_report(
+    reason: &str,
+    details: &str,
+    ts_unix_ms: u64,
+    dump_file: Option<&str>,
+    log_lines: &[String],
+) -> String {
+    let mut out = format!(
+        "ClipRelay {} crash report\n\
+         Time (unix ms): {ts_unix_ms}\n\
+         OS: {} ({})\n\
+         Reason: {reason}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    );
+    match dump_file {
+        Some(name) => out.push_str(&format!("Minidump: {name}\n")),
+        None => out.push_str("Minidump: not written\n"),
+    }
+    if !details.trim().is_empty() {
+        out.push_str("\nDetails:\n");
+        out.push_str(details.trim_end());
+        out.push('\n');
+    }
+    out.push_str(&format!("\nLast {} log lines:\n", log_lines.len()));
+    for line in log_lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Write `<stem>.txt` into `dir`, mark it as pending for the next start and
+/// delete reports beyond [`MAX_CRASH_REPORTS`].
+pub fn write_report(dir: &Path, stem: &str, text: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{stem}.txt"));
+    fs::write(&path, text)?;
+    fs::write(dir.join(PENDING_MARKER), path.display().to_string())?;
+    prune_reports(dir, MAX_CRASH_REPORTS);
+    Ok(path)
+}
+
+/// The report written by a previous run that the user has not been told
+/// about yet.  The marker is removed, so this returns it only once.
+pub fn take_pending_report(dir: &Path) -> Option<PathBuf> {
+    let marker = dir.join(PENDING_MARKER);
+    let contents = fs::read_to_string(&marker).ok()?;
+    let _ = fs::remove_file(&marker);
+    let path = PathBuf::from(contents.trim());
+    path.is_file().then_some(path)
+}
+
+/// Delete all but the newest `keep` crash stems (report and dump together).
+pub fn prune_reports(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_owned();
+            stem.starts_with("crash-").then_some((stem, path))
+        })
+        .collect();
+    let mut stems: Vec<&str> = files.iter().map(|(stem, _)| stem.as_str()).collect();
+    // Stems embed a millisecond timestamp of fixed width, so they sort by age.
+    stems.sort_unstable();
+    stems.dedup();
+    let stale: Vec<String> = stems[..stems.len().saturating_sub(keep)]
+        .iter()
+        .map(|s| (*s).to_owned())
+        .collect();
+    files.retain(|(stem, _)| stale.contains(stem));
+    for (_, path) in files {
+        let _ = fs::remove_file(path);
+    }
+}
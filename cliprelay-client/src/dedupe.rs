@@ -0,0 +1,97 @@
+//! Time-windowed suppression of incoming clipboard text that repeats the
+//! most recently applied content.
+//!
+//! Without a window, a peer that intentionally re-sends the same text would
+//! be ignored forever; with one, only quick repeats (e.g. echoes of our own
+//! apply bouncing through another device) are skipped.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateSuppression {
+    Off,
+    #[default]
+    ThirtySeconds,
+    FiveMinutes,
+}
+
+impl DuplicateSuppression {
+    pub const ALL: [DuplicateSuppression; 3] = [
+        DuplicateSuppression::Off,
+        DuplicateSuppression::ThirtySeconds,
+        DuplicateSuppression::FiveMinutes,
+    ];
+
+    pub fn window_ms(self) -> u64 {
+        match self {
+            DuplicateSuppression::Off => 0,
+            DuplicateSuppression::ThirtySeconds => 30_000,
+            DuplicateSuppression::FiveMinutes => 5 * 60_000,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DuplicateSuppression::Off => "Off",
+            DuplicateSuppression::ThirtySeconds => "Within 30 seconds",
+            DuplicateSuppression::FiveMinutes => "Within 5 minutes",
+        }
+    }
+}
+
+/// The most recently applied clipboard content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastApplied {
+    pub hash: [u8; 32],
+    pub applied_unix_ms: u64,
+}
+
+/// Whether incoming content with `hash` arriving at `now_unix_ms` should be
+/// skipped as a duplicate of `last`.
+pub fn is_duplicate(
+    mode: DuplicateSuppression,
+    last: Option<LastApplied>,
+    hash: &[u8; 32],
+    now_unix_ms: u64,
+) -> bool {
+    let Some(last) = last else {
+        return false;
+    };
+    mode != DuplicateSuppression::Off
+        && last.hash == *hash
+        && now_unix_ms.saturating_sub(last.applied_unix_ms) < mode.window_ms()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: [u8; 32] = [7; 32];
+
+    fn last_at(ms: u64) -> Option<LastApplied> {
+        Some(LastApplied {
+            hash: HASH,
+            applied_unix_ms: ms,
+        })
+    }
+
+    #[test]
+    fn duplicates_expire_after_window() {
+        let mode = DuplicateSuppression::ThirtySeconds;
+        assert!(is_duplicate(mode, last_at(1_000), &HASH, 10_000));
+        assert!(!is_duplicate(mode, last_at(1_000), &HASH, 31_000));
+        assert!(!is_duplicate(mode, last_at(1_000), &[8; 32], 2_000));
+        assert!(!is_duplicate(mode, None, &HASH, 2_000));
+    }
+
+    #[test]
+    fn off_never_suppresses() {
+        assert!(!is_duplicate(
+            DuplicateSuppression::Off,
+            last_at(1_000),
+            &HASH,
+            1_000
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+//! Locally persisted registry of devices seen in the room.
+//!
+//! Peers advertise a self-chosen `device_name`; the registry lets the user
+//! attach a local nickname, mark a device as trusted or blocked, and turn
+//! per-device behaviour (auto-apply, notifications) on or off.  Nothing here
+//! is ever sent to the relay or to other peers.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_profiles;
+
+/// Defensive bound on `devices.json`, mirroring `MAX_UI_STATE_BYTES`.
+pub const MAX_DEVICES_BYTES: u64 = 256 * 1024;
+
+/// Longest nickname accepted (in characters).
+pub const MAX_NICKNAME_CHARS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustState {
+    /// Seen but not yet reviewed; behaves like a trusted device.
+    #[default]
+    Unknown,
+    Trusted,
+    /// Clipboard text and files from this device are discarded.
+    Blocked,
+}
+
+impl TrustState {
+    pub const ALL: [TrustState; 3] = [
+        TrustState::Unknown,
+        TrustState::Trusted,
+        TrustState::Blocked,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrustState::Unknown => "Unknown",
+            TrustState::Trusted => "Trusted",
+            TrustState::Blocked => "Blocked",
+        }
+    }
+}
+
+/// What happens when a file transfer from a device completes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilePolicy {
+    /// Queue a notification with Save / Dismiss.
+    #[default]
+    Prompt,
+    /// Save straight to `Downloads\ClipRelay`.
+    AutoSave,
+    /// Delete the received file immediately.
+    Reject,
+}
+
+impl FilePolicy {
+    pub const ALL: [FilePolicy; 3] = [FilePolicy::Prompt, FilePolicy::AutoSave, FilePolicy::Reject];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilePolicy::Prompt => "Ask",
+            FilePolicy::AutoSave => "Save automatically",
+            FilePolicy::Reject => "Reject",
+        }
+    }
+}
+
+/// Result of comparing the identity key presented with an event against the
+/// key pinned for its sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCheck {
+    /// Signed with the pinned key.
+    Verified,
+    /// First signed event from this device; its key is now pinned.
+    Pinned,
+    /// Signed with a different key than the one pinned.
+    Changed,
+    /// Unsigned (older client), or from a device not in the registry.
+    Unverified,
+}
+
+impl KeyCheck {
+    /// Whether automatic actions (auto-apply, file auto-save) may run.
+    pub fn is_verified(self) -> bool {
+        matches!(self, KeyCheck::Verified | KeyCheck::Pinned)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KnownDevice {
+    pub device_id: String,
+    /// Name most recently advertised by the device itself.
+    pub advertised_name: String,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    pub first_seen_unix_ms: u64,
+    pub last_seen_unix_ms: u64,
+    #[serde(default)]
+    pub trust: TrustState,
+    /// Whether the global auto-apply setting applies to this device.
+    #[serde(default = "default_true")]
+    pub allow_auto_apply: bool,
+    /// Whether system notifications are shown for this device.
+    #[serde(default = "default_true")]
+    pub notify: bool,
+    #[serde(default)]
+    pub file_policy: FilePolicy,
+    /// Identity key (hex Ed25519 public key) pinned on first use.
+    #[serde(default)]
+    pub pinned_key: Option<String>,
+    /// A different key presented after pinning, awaiting the user's review.
+    #[serde(default)]
+    pub changed_key: Option<String>,
+}
+
+impl KnownDevice {
+    /// Nickname if set, otherwise the advertised name.
+    pub fn display_name(&self) -> &str {
+        self.nickname.as_deref().unwrap_or(&self.advertised_name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct DeviceRegistry {
+    #[serde(default)]
+    devices: BTreeMap<String, KnownDevice>,
+}
+
+impl DeviceRegistry {
+    /// Record that `device_id` is present right now, creating an entry on
+    /// first sight and refreshing the advertised name.
+    pub fn observe(&mut self, device_id: &str, advertised_name: &str, now_unix_ms: u64) {
+        let entry = self
+            .devices
+            .entry(device_id.to_owned())
+            .or_insert_with(|| KnownDevice {
+                device_id: device_id.to_owned(),
+                advertised_name: advertised_name.to_owned(),
+                nickname: None,
+                first_seen_unix_ms: now_unix_ms,
+                last_seen_unix_ms: now_unix_ms,
+                trust: TrustState::Unknown,
+                allow_auto_apply: true,
+                notify: true,
+                file_policy: FilePolicy::Prompt,
+                pinned_key: None,
+                changed_key: None,
+            });
+        entry.advertised_name = advertised_name.to_owned();
+        entry.last_seen_unix_ms = entry.last_seen_unix_ms.max(now_unix_ms);
+    }
+
+    /// Ids among `device_ids` that have never been seen before.  Always
+    /// empty while the registry itself is empty, so the members of the first
+    /// room this PC joins are learned silently instead of raising alerts.
+    pub fn unfamiliar<'a>(&self, device_ids: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+        if self.devices.is_empty() {
+            return Vec::new();
+        }
+        device_ids
+            .into_iter()
+            .filter(|id| !self.devices.contains_key(*id))
+            .collect()
+    }
+
+    pub fn get(&self, device_id: &str) -> Option<&KnownDevice> {
+        self.devices.get(device_id)
+    }
+
+    pub fn get_mut(&mut self, device_id: &str) -> Option<&mut KnownDevice> {
+        self.devices.get_mut(device_id)
+    }
+
+    /// Devices ordered by most recently seen first.
+    pub fn by_last_seen(&self) -> Vec<&KnownDevice> {
+        let mut list: Vec<_> = self.devices.values().collect();
+        list.sort_by_key(|d| std::cmp::Reverse(d.last_seen_unix_ms));
+        list
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Drop everything known about `device_id`.  The device reappears as
+    /// `Unknown` the next time it is seen.
+    pub fn forget(&mut self, device_id: &str) -> bool {
+        self.devices.remove(device_id).is_some()
+    }
+
+    pub fn display_name(&self, device_id: &str) -> Option<&str> {
+        self.devices.get(device_id).map(KnownDevice::display_name)
+    }
+
+    pub fn is_blocked(&self, device_id: &str) -> bool {
+        self.devices
+            .get(device_id)
+            .is_some_and(|d| d.trust == TrustState::Blocked)
+    }
+
+    pub fn allows_auto_apply(&self, device_id: &str) -> bool {
+        self.devices
+            .get(device_id)
+            .is_none_or(|d| d.allow_auto_apply)
+    }
+
+    pub fn wants_notifications(&self, device_id: &str) -> bool {
+        self.devices.get(device_id).is_none_or(|d| d.notify)
+    }
+
+    /// Check `presented_key` (the key that validly signed an event, if any)
+    /// against the key pinned for `device_id`, pinning it on first use.
+    pub fn check_key(&mut self, device_id: &str, presented_key: Option<&str>) -> KeyCheck {
+        let (Some(device), Some(presented)) = (self.devices.get_mut(device_id), presented_key)
+        else {
+            return KeyCheck::Unverified;
+        };
+        match device.pinned_key.as_deref() {
+            None => {
+                device.pinned_key = Some(presented.to_owned());
+                KeyCheck::Pinned
+            }
+            Some(pinned) if pinned == presented => KeyCheck::Verified,
+            Some(_) => {
+                device.changed_key = Some(presented.to_owned());
+                KeyCheck::Changed
+            }
+        }
+    }
+
+    /// Replace the pinned key of `device_id` with the changed key it last
+    /// presented.  Returns `false` when there was no change to accept.
+    pub fn accept_changed_key(&mut self, device_id: &str) -> bool {
+        match self.devices.get_mut(device_id) {
+            Some(device) if device.changed_key.is_some() => {
+                device.pinned_key = device.changed_key.take();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// File policy for `device_id`; devices never seen before are prompted.
+    pub fn file_policy(&self, device_id: &str) -> FilePolicy {
+        self.devices
+            .get(device_id)
+            .map(|d| d.file_policy)
+            .unwrap_or_default()
+    }
+}
+
+/// Short relative description of when a device was last present, e.g.
+/// `"3 min ago"`.
+pub fn last_seen_ago(last_seen_unix_ms: u64, now_unix_ms: u64) -> String {
+    let secs = now_unix_ms.saturating_sub(last_seen_unix_ms) / 1000;
+    match secs {
+        0..60 => "just now".to_owned(),
+        60..3_600 => format!("{} min ago", secs / 60),
+        3_600..86_400 => format!("{} h ago", secs / 3_600),
+        _ => format!("{} d ago", secs / 86_400),
+    }
+}
+
+/// Normalize a user-entered nickname: trimmed, bounded, empty means none.
+pub fn normalize_nickname(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.chars().take(MAX_NICKNAME_CHARS).collect())
+}
+
+pub fn devices_path() -> PathBuf {
+    config_profiles::data_dir().join("devices.json")
+}
+
+pub fn load_devices_from_path(path: &Path) -> io::Result<DeviceRegistry> {
+    let meta = fs::metadata(path)?;
+    if meta.len() > MAX_DEVICES_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "file too large: {} bytes (max {MAX_DEVICES_BYTES})",
+                meta.len()
+            ),
+        ));
+    }
+    let data = fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save_devices_to_path(path: &Path, registry: &DeviceRegistry) -> io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let payload = serde_json::to_string_pretty(registry)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(&tmp, payload.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+pub fn load_devices() -> DeviceRegistry {
+    load_devices_from_path(&devices_path()).unwrap_or_default()
+}
+
+pub fn save_devices(registry: &DeviceRegistry) -> io::Result<()> {
+    save_devices_to_path(&devices_path(), registry)
+}
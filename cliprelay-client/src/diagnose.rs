@@ -0,0 +1,287 @@
+//! `--diagnose`: a self-check of the saved config, the route to the relay,
+//! hotkeys, the clipboard and the folders ClipRelay writes to, saved as a
+//! text report the user can attach to an issue.
+//!
+//! The checks themselves run in the Windows client; the report model, its
+//! text and the redaction live here.  The room code, the client name and
+//! the Windows user name never appear in a report.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::config_profiles;
+
+/// Replaces redacted values in the report.
+pub const REDACTED: &str = "<redacted>";
+
+/// Log lines copied into each report.
+pub const REPORT_LOG_LINES: usize = 50;
+
+/// Reports kept; older ones are deleted.
+pub const MAX_REPORTS: usize = 5;
+
+pub fn reports_dir() -> PathBuf {
+    config_profiles::data_dir().join("diagnostics")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Config,
+    Dns,
+    Tcp,
+    Tls,
+    WebSocket,
+    Hotkeys,
+    Clipboard,
+    Folders,
+}
+
+impl Check {
+    pub const ALL: [Check; 8] = [
+        Check::Config,
+        Check::Dns,
+        Check::Tcp,
+        Check::Tls,
+        Check::WebSocket,
+        Check::Hotkeys,
+        Check::Clipboard,
+        Check::Folders,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Check::Config => "Saved config",
+            Check::Dns => "Resolve relay host",
+            Check::Tcp => "Connect to relay",
+            Check::Tls => "TLS handshake",
+            Check::WebSocket => "WebSocket upgrade",
+            Check::Hotkeys => "Register hotkeys",
+            Check::Clipboard => "Open clipboard",
+            Check::Folders => "Write to ClipRelay folders",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed(String),
+    /// Works, but something looks off.
+    Warning(String),
+    Failed(String),
+    /// Not run, e.g. because an earlier network step failed.
+    Skipped(String),
+}
+
+impl CheckOutcome {
+    fn tag(&self) -> &'static str {
+        match self {
+            CheckOutcome::Passed(_) => "OK",
+            CheckOutcome::Warning(_) => "WARN",
+            CheckOutcome::Failed(_) => "FAIL",
+            CheckOutcome::Skipped(_) => "SKIP",
+        }
+    }
+
+    fn detail(&self) -> &str {
+        match self {
+            CheckOutcome::Passed(d)
+            | CheckOutcome::Warning(d)
+            | CheckOutcome::Failed(d)
+            | CheckOutcome::Skipped(d) => d,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticReport {
+    pub results: Vec<(Check, CheckOutcome)>,
+}
+
+impl Default for DiagnosticReport {
+    fn default() -> Self {
+        Self {
+            results: Check::ALL
+                .iter()
+                .map(|check| (*check, CheckOutcome::Skipped("not run".to_owned())))
+                .collect(),
+        }
+    }
+}
+
+impl DiagnosticReport {
+    pub fn set(&mut self, check: Check, outcome: CheckOutcome) {
+        if let Some(slot) = self.results.iter_mut().find(|(c, _)| *c == check) {
+            slot.1 = outcome;
+        }
+    }
+
+    pub fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, CheckOutcome::Failed(_)))
+            .count()
+    }
+
+    /// One line per check, e.g. `"[FAIL] Resolve relay host: no such host"`.
+    pub fn lines(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .map(|(check, outcome)| {
+                format!(
+                    "[{:>4}] {}: {}",
+                    outcome.tag(),
+                    check.label(),
+                    outcome.detail()
+                )
+            })
+            .collect()
+    }
+
+    /// One-line result, e.g. `"2 of 8 checks failed"`.
+    pub fn summary(&self) -> String {
+        match self.failures() {
+            0 => "All checks passed".to_owned(),
+            n => format!("{n} of {} checks failed", self.results.len()),
+        }
+    }
+}
+
+/// `text` with every occurrence of each non-empty `secret` replaced by
+/// [`REDACTED`].  Longer secrets are replaced first, so one that contains
+/// another is not left half-visible.
+pub fn redact(text: &str, secrets: &[&str]) -> String {
+    let mut secrets: Vec<&str> = secrets
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    secrets
+        .into_iter()
+        .fold(text.to_owned(), |out, secret| out.replace(secret, REDACTED))
+}
+
+/// Text of a report, redacted of `secrets`.
+pub fn format_report(
+    report: &DiagnosticReport,
+    ts_unix_ms: u64,
+    environment: &[(&str, String)],
+    log_lines: &[String],
+    secrets: &[&str],
+) -> String {
+    let mut out = format!(
+        "ClipRelay {} diagnostic report\n\
+         Time (unix ms): {ts_unix_ms}\n\
+         OS: {} ({})\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    );
+    for (name, value) in environment {
+        out.push_str(&format!("{name}: {value}\n"));
+    }
+    out.push_str(&format!("\nResult: {}\n", report.summary()));
+    for line in report.lines() {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str(&format!("\nLast {} log lines:\n", log_lines.len()));
+    for line in log_lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    redact(&out, secrets)
+}
+
+/// Write `diagnose-<ts>.txt` into `dir` and delete reports beyond
+/// [`MAX_REPORTS`].
+pub fn write_report(dir: &Path, ts_unix_ms: u64, text: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("diagnose-{ts_unix_ms}.txt"));
+    fs::write(&path, text)?;
+    prune_reports(dir, MAX_REPORTS);
+    Ok(path)
+}
+
+fn prune_reports(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut reports: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("diagnose-") && name.ends_with(".txt"))
+        })
+        .collect();
+    // Names embed a millisecond timestamp of fixed width, so they sort by age.
+    reports.sort_unstable();
+    for path in &reports[..reports.len().saturating_sub(keep)] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Create and delete a probe file in `dir` (creating `dir` if needed).
+pub fn probe_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".cliprelay-diagnose-probe");
+    fs::write(&probe, b"probe")?;
+    fs::remove_file(&probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted_longest_first() {
+        let text = "room team-room-42 joined by alice on C:\\Users\\alice";
+        assert_eq!(
+            redact(text, &["team-room", "team-room-42", "alice", " "]),
+            "room <redacted> joined by <redacted> on C:\\Users\\<redacted>"
+        );
+    }
+
+    #[test]
+    fn report_lists_every_check_and_counts_failures() {
+        let mut report = DiagnosticReport::default();
+        report.set(
+            Check::Config,
+            CheckOutcome::Passed("room code set".to_owned()),
+        );
+        report.set(Check::Dns, CheckOutcome::Failed("no such host".to_owned()));
+        assert_eq!(report.summary(), "1 of 8 checks failed");
+
+        let text = format_report(
+            &report,
+            1,
+            &[("Profile", "secret-room".to_owned())],
+            &["joined secret-room".to_owned()],
+            &["secret-room"],
+        );
+        assert!(text.contains("[  OK] Saved config: room code set"));
+        assert!(text.contains("[FAIL] Resolve relay host: no such host"));
+        assert!(text.contains("[SKIP] Open clipboard: not run"));
+        assert!(!text.contains("secret-room"));
+    }
+
+    #[test]
+    fn old_reports_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        for ts in 1_000..1_000 + MAX_REPORTS as u64 + 2 {
+            write_report(dir.path(), ts, "r").unwrap();
+        }
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left.len(), MAX_REPORTS);
+        assert_eq!(left[0], "diagnose-1002.txt");
+        probe_writable(&dir.path().join("sub")).unwrap();
+    }
+}
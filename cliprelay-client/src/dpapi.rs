@@ -0,0 +1,212 @@
+//! Windows DPAPI (current-user scope) wrappers for secrets stored on disk:
+//! the room code in `config.json` and, through [`AtRest`], the identity key,
+//! the room codes of saved profiles and, optionally, `snippets.json`.
+//!
+//! Sealed data can only be opened by the same Windows account on the same
+//! machine.  Other platforms get stubs that report `Unsupported`, and callers
+//! fall back to plaintext storage.
+
+use std::io;
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Whether sealing is available on this platform.
+pub fn is_supported() -> bool {
+    cfg!(target_os = "windows")
+}
+
+/// A value in a JSON file, stored either as is or sealed, and tagged by
+/// `format` so that either can be read back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum AtRest<T> {
+    Plain {
+        value: T,
+    },
+    /// Base64 of the sealed JSON of the value.
+    Protected {
+        data: String,
+    },
+}
+
+impl<T: Serialize + DeserializeOwned> AtRest<T> {
+    /// Store `value`, sealed with `protect` when `seal` is set.  The
+    /// closure is [`protect`] outside of tests.
+    pub fn new(
+        value: T,
+        seal: bool,
+        protect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+    ) -> io::Result<Self> {
+        if !seal {
+            return Ok(Self::Plain { value });
+        }
+        let plain = serde_json::to_vec(&value).map_err(invalid)?;
+        Ok(Self::Protected {
+            data: base64::engine::general_purpose::STANDARD.encode(protect(&plain)?),
+        })
+    }
+
+    pub fn is_protected(&self) -> bool {
+        matches!(self, Self::Protected { .. })
+    }
+
+    /// The stored value, unsealed with `unprotect` if it was sealed.
+    pub fn open(self, unprotect: impl Fn(&[u8]) -> io::Result<Vec<u8>>) -> io::Result<T> {
+        match self {
+            Self::Plain { value } => Ok(value),
+            Self::Protected { data } => {
+                let sealed = base64::engine::general_purpose::STANDARD
+                    .decode(data)
+                    .map_err(invalid)?;
+                serde_json::from_slice(&unprotect(&sealed)?).map_err(invalid)
+            }
+        }
+    }
+}
+
+fn invalid(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Seal `plain` and return it base64-encoded, for embedding in JSON.
+pub fn protect_to_base64(plain: &[u8]) -> io::Result<String> {
+    Ok(base64::engine::general_purpose::STANDARD.encode(protect(plain)?))
+}
+
+/// Inverse of [`protect_to_base64`].
+pub fn unprotect_from_base64(sealed: &str) -> io::Result<Vec<u8>> {
+    let sealed = base64::engine::general_purpose::STANDARD
+        .decode(sealed.trim())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    unprotect(&sealed)
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_impl::{protect, unprotect};
+
+#[cfg(not(target_os = "windows"))]
+pub use fallback::{protect, unprotect};
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{io, ptr};
+
+    use windows_sys::Win32::{
+        Security::Cryptography::{
+            CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN, CryptProtectData, CryptUnprotectData,
+        },
+        System::Memory::LocalFree,
+    };
+
+    fn run(
+        input: &[u8],
+        call: impl FnOnce(*const CRYPT_INTEGER_BLOB, *mut CRYPT_INTEGER_BLOB) -> i32,
+    ) -> io::Result<Vec<u8>> {
+        let blob_in = CRYPT_INTEGER_BLOB {
+            cbData: u32::try_from(input.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "data too large"))?,
+            pbData: input.as_ptr() as *mut u8,
+        };
+        let mut blob_out = CRYPT_INTEGER_BLOB {
+            cbData: 0,
+            pbData: ptr::null_mut(),
+        };
+        if call(&blob_in, &mut blob_out) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: on success DPAPI returns a LocalAlloc'd buffer of `cbData`
+        // bytes that we copy and then free.
+        let out = unsafe {
+            std::slice::from_raw_parts(blob_out.pbData, blob_out.cbData as usize).to_vec()
+        };
+        unsafe { LocalFree(blob_out.pbData as isize) };
+        Ok(out)
+    }
+
+    pub fn protect(plain: &[u8]) -> io::Result<Vec<u8>> {
+        run(plain, |input, output| unsafe {
+            CryptProtectData(
+                input,
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                output,
+            )
+        })
+    }
+
+    pub fn unprotect(sealed: &[u8]) -> io::Result<Vec<u8>> {
+        run(sealed, |input, output| unsafe {
+            CryptUnprotectData(
+                input,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                output,
+            )
+        })
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod fallback {
+    use std::io;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "DPAPI is only available on Windows",
+        )
+    }
+
+    pub fn protect(_plain: &[u8]) -> io::Result<Vec<u8>> {
+        Err(unsupported())
+    }
+
+    pub fn unprotect(_sealed: &[u8]) -> io::Result<Vec<u8>> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_base64_is_rejected_before_unsealing() {
+        let err = unprotect_from_base64("not base64!").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn values_at_rest_round_trip_plain_and_sealed() {
+        let xor = |data: &[u8]| Ok(data.iter().map(|b| b ^ 0x5a).collect());
+        let plain = AtRest::new("secret".to_owned(), false, xor).unwrap();
+        assert!(!plain.is_protected());
+        assert_eq!(
+            serde_json::to_string(&plain).unwrap(),
+            r#"{"format":"plain","value":"secret"}"#
+        );
+
+        let sealed = AtRest::new("secret".to_owned(), true, xor).unwrap();
+        assert!(sealed.is_protected());
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains("secret"));
+        let read: AtRest<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.clone().open(xor).unwrap(), "secret");
+        let err = read.open(|_| Err(io::Error::other("no key"))).unwrap_err();
+        assert_eq!(err.to_string(), "no key");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn base64_round_trip() {
+        let sealed = protect_to_base64(b"room code").unwrap();
+        assert_eq!(unprotect_from_base64(&sealed).unwrap(), b"room code");
+    }
+}
@@ -0,0 +1,135 @@
+//! Which incoming files are accepted, by extension.
+//!
+//! The runtime checks every file chunk against this policy as it arrives,
+//! so a blocked file is dropped at its first chunk instead of being buffered
+//! in full first.  The default blocks common executable and script types.
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on listed extensions.
+pub const MAX_EXTENSIONS: usize = 100;
+
+/// Longest extension accepted (in characters).
+pub const MAX_EXTENSION_CHARS: usize = 16;
+
+/// Types Windows will run or install straight from Explorer.
+pub const DEFAULT_BLOCKED_EXTENSIONS: &[&str] = &[
+    "appx", "bat", "cmd", "com", "cpl", "exe", "hta", "jar", "js", "jse", "lnk", "msc", "msi",
+    "msix", "msp", "pif", "ps1", "psm1", "reg", "scr", "vbe", "vbs", "wsf", "wsh",
+];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionMode {
+    /// Accept everything except the listed extensions.
+    #[default]
+    Block,
+    /// Accept only the listed extensions.
+    Allow,
+}
+
+impl ExtensionMode {
+    pub const ALL: [ExtensionMode; 2] = [ExtensionMode::Block, ExtensionMode::Allow];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExtensionMode::Block => "Block listed types",
+            ExtensionMode::Allow => "Allow only listed types",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtensionPolicy {
+    #[serde(default)]
+    pub mode: ExtensionMode,
+    /// Lower-case, without the leading dot.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+impl Default for ExtensionPolicy {
+    fn default() -> Self {
+        Self {
+            mode: ExtensionMode::Block,
+            extensions: DEFAULT_BLOCKED_EXTENSIONS
+                .iter()
+                .map(|ext| (*ext).to_owned())
+                .collect(),
+        }
+    }
+}
+
+impl ExtensionPolicy {
+    /// Whether a file called `file_name` may be received.  A name without an
+    /// extension passes a block list and fails an allow list.
+    pub fn allows(&self, file_name: &str) -> bool {
+        let listed = file_extension(file_name).is_some_and(|ext| self.extensions.contains(&ext));
+        match self.mode {
+            ExtensionMode::Block => !listed,
+            ExtensionMode::Allow => listed,
+        }
+    }
+}
+
+/// Lower-case extension of `file_name`, ignoring the trailing dots and
+/// spaces Windows strips when it creates the file (`"a.exe. "` is an
+/// `exe`).
+pub fn file_extension(file_name: &str) -> Option<String> {
+    let name = file_name.trim_end_matches(['.', ' ']);
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let (stem, ext) = name.rsplit_once('.')?;
+    if stem.is_empty() || ext.is_empty() {
+        return None;
+    }
+    Some(ext.to_lowercase())
+}
+
+/// Parse a user-entered list such as `".exe, *.scr js"`: separated by
+/// commas, semicolons or whitespace, leading `*` and `.` ignored,
+/// lower-cased, deduplicated and bounded.
+pub fn parse_extension_list(text: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for token in text.split([',', ';', ' ', '\t', '\n', '\r']) {
+        let ext = token.trim().trim_start_matches('*').trim_start_matches('.');
+        if ext.is_empty() || ext.chars().count() > MAX_EXTENSION_CHARS {
+            continue;
+        }
+        let ext = ext.to_lowercase();
+        if !out.contains(&ext) {
+            out.push(ext);
+        }
+        if out.len() >= MAX_EXTENSIONS {
+            break;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_blocks_executables_only() {
+        let policy = ExtensionPolicy::default();
+        assert!(!policy.allows("setup.exe"));
+        assert!(!policy.allows("Invoice.PDF.js"));
+        assert!(!policy.allows("trick.scr. . "));
+        assert!(policy.allows("report.pdf"));
+        assert!(policy.allows("README"));
+    }
+
+    #[test]
+    fn allow_list_rejects_unlisted_and_bare_names() {
+        let policy = ExtensionPolicy {
+            mode: ExtensionMode::Allow,
+            extensions: parse_extension_list("*.PNG; .jpg txt"),
+        };
+        assert_eq!(policy.extensions, ["png", "jpg", "txt"]);
+        assert!(policy.allows("photo.png"));
+        assert!(!policy.allows("photo.png.exe"));
+        assert!(!policy.allows("Makefile"));
+        assert!(!policy.allows(".png"));
+    }
+}
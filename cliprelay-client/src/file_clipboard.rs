@@ -0,0 +1,287 @@
+//! Received files on the clipboard as files (`CF_HDROP`), so Ctrl+V pastes
+//! them into Explorer or an e-mail just like files copied in Explorer.
+//!
+//! The clipboard data is rendered on demand: a hidden window on its own
+//! thread owns the clipboard and builds the file list only when a program
+//! asks for it, then exits once something else is copied.  The files must
+//! still exist when pasted.  Other platforms get a stub that reports
+//! `Unsupported`.
+
+use std::path::{Path, PathBuf};
+
+/// Size of the `DROPFILES` header in front of the file list.
+const DROPFILES_HEADER_BYTES: u32 = 20;
+
+/// The `CF_HDROP` data for `paths`: a `DROPFILES` header (list offset,
+/// drop point, non-client flag, wide-character flag) followed by the paths
+/// in UTF-16, each NUL-terminated, and a final NUL.
+pub fn drop_files(paths: &[PathBuf]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&DROPFILES_HEADER_BYTES.to_le_bytes());
+    out.extend_from_slice(&[0; 8]);
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&1u32.to_le_bytes());
+    for path in paths {
+        for unit in wide(path) {
+            out.extend_from_slice(&unit.to_le_bytes());
+        }
+        out.extend_from_slice(&[0, 0]);
+    }
+    out.extend_from_slice(&[0, 0]);
+    out
+}
+
+#[cfg(target_os = "windows")]
+fn wide(path: &Path) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn wide(path: &Path) -> Vec<u16> {
+    path.to_string_lossy().encode_utf16().collect()
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_impl::put_files;
+
+#[cfg(not(target_os = "windows"))]
+pub use fallback::put_files;
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{cell::RefCell, io, path::PathBuf, ptr, sync::mpsc, thread, time::Duration};
+
+    use windows_sys::Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+        System::DataExchange::{
+            CloseClipboard, EmptyClipboard, GetClipboardOwner, OpenClipboard,
+            RegisterClipboardFormatW, SetClipboardData,
+        },
+        System::Memory::{GMEM_MOVEABLE, GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock},
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+            HWND_MESSAGE, MSG, PostQuitMessage, RegisterClassW, WM_DESTROYCLIPBOARD,
+            WM_RENDERALLFORMATS, WM_RENDERFORMAT, WNDCLASSW,
+        },
+    };
+
+    /// Standard clipboard format number of `CF_HDROP`.
+    const CF_HDROP: u32 = 15;
+    /// "Preferred DropEffect" value asking Explorer to copy, not move.
+    const DROPEFFECT_COPY: u32 = 1;
+    /// Another program may hold the clipboard open for a moment.
+    const MAX_OPEN_ATTEMPTS: u32 = 10;
+
+    thread_local! {
+        /// The formats this thread's window promised, with their data.
+        static FORMATS: RefCell<Vec<(u32, Vec<u8>)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Put `paths` on the clipboard as files, replacing what was there.
+    pub fn put_files(paths: &[PathBuf]) -> io::Result<()> {
+        let data = super::drop_files(paths);
+        let (ready_tx, ready_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("clipboard-files".to_owned())
+            .spawn(move || owner_thread(data, ready_tx))?;
+        ready_rx
+            .recv()
+            .map_err(|_| io::Error::other("clipboard thread exited"))?
+    }
+
+    fn wide_null(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0u16)).collect()
+    }
+
+    /// Owns the clipboard until another program (or the next call) takes
+    /// it over, answering render requests in the meantime.
+    fn owner_thread(data: Vec<u8>, ready: mpsc::Sender<io::Result<()>>) {
+        let hwnd = match create_window() {
+            Ok(hwnd) => hwnd,
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return;
+            }
+        };
+        let effect =
+            unsafe { RegisterClipboardFormatW(wide_null("Preferred DropEffect").as_ptr()) };
+        let mut formats = vec![(CF_HDROP, data)];
+        if effect != 0 {
+            formats.push((effect, DROPEFFECT_COPY.to_le_bytes().to_vec()));
+        }
+        let promised: Vec<u32> = formats.iter().map(|(format, _)| *format).collect();
+        FORMATS.with(|f| *f.borrow_mut() = formats);
+
+        let result = promise(hwnd, &promised);
+        let owned = result.is_ok();
+        let _ = ready.send(result);
+        if owned {
+            let mut msg: MSG = unsafe { std::mem::zeroed() };
+            while unsafe { GetMessageW(&mut msg, 0, 0, 0) } > 0 {
+                unsafe { DispatchMessageW(&msg) };
+            }
+        }
+        unsafe { DestroyWindow(hwnd) };
+    }
+
+    fn create_window() -> io::Result<HWND> {
+        let class = wide_null("ClipRelayClipboardFiles");
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(wnd_proc),
+            lpszClassName: class.as_ptr(),
+            // SAFETY: all-zero is a valid WNDCLASSW (null handles, no menu).
+            ..unsafe { std::mem::zeroed() }
+        };
+        // Fails harmlessly when an earlier call registered the class.
+        unsafe { RegisterClassW(&wc) };
+        let hwnd = unsafe {
+            CreateWindowExW(
+                0,
+                class.as_ptr(),
+                ptr::null(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                0,
+                0,
+                ptr::null(),
+            )
+        };
+        if hwnd == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(hwnd)
+        }
+    }
+
+    /// Empty the clipboard as `hwnd` and promise `formats` without data.
+    fn promise(hwnd: HWND, formats: &[u32]) -> io::Result<()> {
+        let mut attempts = 0;
+        while unsafe { OpenClipboard(hwnd) } == 0 {
+            attempts += 1;
+            if attempts >= MAX_OPEN_ATTEMPTS {
+                return Err(io::Error::last_os_error());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        unsafe {
+            EmptyClipboard();
+            for &format in formats {
+                SetClipboardData(format, 0);
+            }
+            CloseClipboard();
+        }
+        Ok(())
+    }
+
+    /// Hand the data promised for `format` to the clipboard, which takes
+    /// over the memory.
+    fn render(format: u32) {
+        FORMATS.with(|formats| {
+            let formats = formats.borrow();
+            let Some((_, data)) = formats.iter().find(|(f, _)| *f == format) else {
+                return;
+            };
+            // SAFETY: the block is `data.len()` bytes and locked while
+            // written; on success the clipboard owns it, otherwise it is
+            // freed here.
+            unsafe {
+                let hmem = GlobalAlloc(GMEM_MOVEABLE, data.len());
+                if hmem == 0 {
+                    return;
+                }
+                let dst = GlobalLock(hmem) as *mut u8;
+                if dst.is_null() {
+                    GlobalFree(hmem);
+                    return;
+                }
+                ptr::copy_nonoverlapping(data.as_ptr(), dst, data.len());
+                GlobalUnlock(hmem);
+                if SetClipboardData(format, hmem) == 0 {
+                    GlobalFree(hmem);
+                }
+            }
+        });
+    }
+
+    unsafe extern "system" fn wnd_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        match msg {
+            // A program is pasting one of the promised formats.
+            WM_RENDERFORMAT => {
+                render(wparam as u32);
+                0
+            }
+            // The window is going away while still owning the clipboard.
+            WM_RENDERALLFORMATS => {
+                if unsafe { OpenClipboard(hwnd) } != 0 {
+                    if unsafe { GetClipboardOwner() } == hwnd {
+                        let promised: Vec<u32> = FORMATS
+                            .with(|f| f.borrow().iter().map(|(format, _)| *format).collect());
+                        for format in promised {
+                            render(format);
+                        }
+                    }
+                    unsafe { CloseClipboard() };
+                }
+                0
+            }
+            // Something else was copied: nothing left to render.
+            WM_DESTROYCLIPBOARD => {
+                unsafe { PostQuitMessage(0) };
+                0
+            }
+            _ => unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod fallback {
+    use std::{io, path::PathBuf};
+
+    pub fn put_files(_paths: &[PathBuf]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file clipboard is only available on Windows",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16_bytes(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn drop_files_is_a_header_and_a_double_nul_terminated_list() {
+        let paths = [PathBuf::from("a.txt"), PathBuf::from("b é.pdf")];
+        let data = drop_files(&paths);
+
+        let header = DROPFILES_HEADER_BYTES as usize;
+        assert_eq!(&data[..4], &DROPFILES_HEADER_BYTES.to_le_bytes());
+        assert_eq!(&data[16..header], &1u32.to_le_bytes(), "wide paths");
+
+        let mut list = utf16_bytes("a.txt\0b é.pdf\0");
+        list.extend_from_slice(&[0, 0]);
+        assert_eq!(&data[header..], list.as_slice());
+    }
+
+    #[test]
+    fn empty_list_is_just_the_terminator() {
+        let data = drop_files(&[]);
+        assert_eq!(data.len(), DROPFILES_HEADER_BYTES as usize + 2);
+        assert!(data.ends_with(&[0, 0]));
+    }
+}
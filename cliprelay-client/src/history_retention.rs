@@ -0,0 +1,150 @@
+//! How much Activity History is kept: an entry limit, an optional maximum
+//! age, and whether content previews are stored at all.
+//!
+//! In metadata-only mode an entry keeps its time, direction, device and
+//! kind; the preview text (or file name) and any link are replaced.
+
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+
+/// Largest entry limit that can be configured.
+pub const MAX_ENTRIES_LIMIT: usize = 5_000;
+
+/// Longest age limit that can be configured (ten years).
+pub const MAX_AGE_DAYS_LIMIT: u32 = 3_650;
+
+/// Summary recorded in place of the content in metadata-only mode.
+pub const REDACTED_SUMMARY: &str = "(content not stored)";
+
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryRetention {
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Entries older than this many days are removed; `None` keeps them.
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// `false` records metadata only.
+    #[serde(default = "default_true")]
+    pub store_content: bool,
+}
+
+fn default_max_entries() -> usize {
+    DEFAULT_MAX_ENTRIES
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_age_days: None,
+            store_content: true,
+        }
+    }
+}
+
+impl HistoryRetention {
+    /// Clamp values from a hand-edited file or the Options tab into range.
+    pub fn normalized(self) -> Self {
+        Self {
+            max_entries: self.max_entries.clamp(1, MAX_ENTRIES_LIMIT),
+            max_age_days: self.max_age_days.map(|d| d.clamp(1, MAX_AGE_DAYS_LIMIT)),
+            store_content: self.store_content,
+        }
+    }
+
+    pub fn is_expired(self, ts_unix_ms: u64, now_unix_ms: u64) -> bool {
+        self.normalized().max_age_days.is_some_and(|days| {
+            now_unix_ms.saturating_sub(ts_unix_ms) > u64::from(days) * MS_PER_DAY
+        })
+    }
+
+    /// How many entries of a newest-first list with these timestamps to
+    /// keep: the leading ones that have not expired, up to the limit.
+    pub fn keep_count(
+        self,
+        newest_first: impl IntoIterator<Item = u64>,
+        now_unix_ms: u64,
+    ) -> usize {
+        newest_first
+            .into_iter()
+            .take(self.normalized().max_entries)
+            .take_while(|&ts| !self.is_expired(ts, now_unix_ms))
+            .count()
+    }
+}
+
+/// Delete `path`; a missing file is not an error.
+///
+/// The contents are not overwritten first.  History is saved by writing a
+/// new file and renaming it over the old one, so earlier versions sit in
+/// disk space this file no longer owns, and an overwrite would promise a
+/// secure delete it cannot give.
+pub fn delete_file(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_recent_entries_up_to_the_limit() {
+        let now = 100 * MS_PER_DAY;
+        let newest_first = [
+            now,
+            now - MS_PER_DAY,
+            now - 10 * MS_PER_DAY,
+            now - 40 * MS_PER_DAY,
+        ];
+        let unlimited = HistoryRetention::default();
+        assert_eq!(unlimited.keep_count(newest_first, now), 4);
+
+        let month = HistoryRetention {
+            max_age_days: Some(30),
+            ..unlimited
+        };
+        assert_eq!(month.keep_count(newest_first, now), 3);
+
+        let two = HistoryRetention {
+            max_entries: 2,
+            ..month
+        };
+        assert_eq!(two.keep_count(newest_first, now), 2);
+    }
+
+    #[test]
+    fn normalizes_out_of_range_values() {
+        let retention = HistoryRetention {
+            max_entries: 0,
+            max_age_days: Some(0),
+            store_content: false,
+        }
+        .normalized();
+        assert_eq!(retention.max_entries, 1);
+        assert_eq!(retention.max_age_days, Some(1));
+        assert!(!retention.store_content);
+    }
+
+    #[test]
+    fn delete_removes_the_file() {
+        let path =
+            std::env::temp_dir().join(format!("cliprelay-delete-test-{}.json", std::process::id()));
+        std::fs::write(&path, b"[{\"summary\":\"secret\"}]").unwrap();
+        delete_file(&path).unwrap();
+        assert!(!path.exists());
+        // Already gone: nothing to do.
+        delete_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+//! This device's long-lived signing identity (see
+//! [`cliprelay_core::DeviceIdentity`]).
+//!
+//! The private key is created on first run and kept in
+//! `%LOCALAPPDATA%\ClipRelay\identity.json`, sealed with DPAPI where it is
+//! available.  Losing or replacing the file gives the device a new key, which
+//! peers report as a key change.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine as _;
+use cliprelay_core::DeviceIdentity;
+
+use crate::{
+    config_profiles,
+    dpapi::{self, AtRest},
+};
+
+/// Defensive bound on `identity.json`; a sealed PKCS#8 key is well under 1 KiB.
+pub const MAX_IDENTITY_FILE_BYTES: u64 = 16 * 1024;
+
+fn invalid(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Serialize `identity` as base64 PKCS#8, sealing it with `protect` when
+/// `seal` is set.
+pub fn encode_identity(
+    identity: &DeviceIdentity,
+    seal: bool,
+    protect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    let pkcs8 = base64::engine::general_purpose::STANDARD.encode(identity.pkcs8());
+    serde_json::to_string_pretty(&AtRest::new(pkcs8, seal, protect)?).map_err(invalid)
+}
+
+/// Inverse of [`encode_identity`].
+pub fn decode_identity(
+    json: &str,
+    unprotect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<DeviceIdentity> {
+    let stored: AtRest<String> = serde_json::from_str(json).map_err(invalid)?;
+    let pkcs8 = base64::engine::general_purpose::STANDARD
+        .decode(stored.open(unprotect)?)
+        .map_err(invalid)?;
+    DeviceIdentity::from_pkcs8(&pkcs8).map_err(invalid)
+}
+
+pub fn identity_path() -> PathBuf {
+    config_profiles::data_dir().join("identity.json")
+}
+
+/// Load the identity at `path`, creating and saving a new one if the file
+/// does not exist.  An unreadable file is an error rather than silently
+/// replaced, since a new key looks like impersonation to every peer.
+pub fn load_or_create_identity_at(path: &Path) -> io::Result<DeviceIdentity> {
+    match fs::metadata(path) {
+        Ok(meta) => {
+            if meta.len() > MAX_IDENTITY_FILE_BYTES {
+                return Err(invalid(format!(
+                    "file too large: {} bytes (max {MAX_IDENTITY_FILE_BYTES})",
+                    meta.len()
+                )));
+            }
+            decode_identity(&fs::read_to_string(path)?, dpapi::unprotect)
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let identity = DeviceIdentity::generate().map_err(invalid)?;
+            let payload = encode_identity(&identity, dpapi::is_supported(), dpapi::protect)?;
+            let tmp = path.with_extension("json.tmp");
+            fs::write(&tmp, payload.as_bytes())?;
+            fs::rename(&tmp, path)?;
+            Ok(identity)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+pub fn load_or_create_identity() -> io::Result<DeviceIdentity> {
+    load_or_create_identity_at(&identity_path())
+}
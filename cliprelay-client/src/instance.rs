@@ -0,0 +1,271 @@
+//! Channel from short-lived `cliprelay-client` invocations to the running
+//! client: `--send-file` / `--send-text` (scripts and the Explorer entries,
+//! see [`crate::shell_menu`]), `--join-link` and `--apply-and-exit`.
+//!
+//! The running client listens on a per-user named pipe (see [`pipe_name`]).
+//! A request is a header line `<kind> <body bytes> <token>\n` followed by
+//! the UTF-8 body: one absolute path per line for `send-files`, the text for
+//! `send-text`, the link for `open-link`, nothing for `reload-config`.  The
+//! token is the automation token (see [`automation::token_path`]), so only a
+//! process that can read the user's ClipRelay files can send through the
+//! running client; a request with any other token is refused unread.
+//! [`forward`] sends the token only to a pipe served by a process running
+//! as the same user (see [`crate::pipe_security`]).
+//! The client answers with a single line, `ok` or `error <message>`, once
+//! the request is queued or refused.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::{automation, pipe_security};
+
+/// Upper bound on a request body.  A `send-text` body carries at most
+/// 256 KiB of text; the rest is headroom.
+pub const MAX_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Upper bound on the header and reply lines.
+pub const MAX_LINE_BYTES: usize = 4096;
+
+/// How long [`forward`] keeps retrying while every pipe instance is busy
+/// (Explorer starts one process per selected file at once).
+const BUSY_RETRY_FOR: Duration = Duration::from_secs(5);
+const BUSY_RETRY_STEP: Duration = Duration::from_millis(50);
+
+/// `ERROR_PIPE_BUSY`
+const PIPE_BUSY: i32 = 231;
+
+/// Named pipe path the running client listens on for the given user.
+pub fn pipe_name(user: &str) -> String {
+    format!(
+        r"\\.\pipe\ClipRelay.Instance.{}",
+        automation::pipe_safe_user(user)
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceRequest {
+    SendFiles(Vec<PathBuf>),
+    SendText(String),
+    OpenLink(String),
+    /// The saved room settings changed; reconnect with them.
+    ReloadConfig,
+}
+
+impl InstanceRequest {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            InstanceRequest::SendFiles(_) => "send-files",
+            InstanceRequest::SendText(_) => "send-text",
+            InstanceRequest::OpenLink(_) => "open-link",
+            InstanceRequest::ReloadConfig => "reload-config",
+        }
+    }
+
+    /// Header line and body, carrying `token`.
+    pub fn encode(&self, token: &str) -> Vec<u8> {
+        let body = match self {
+            InstanceRequest::SendFiles(paths) => paths
+                .iter()
+                .map(|path| format!("{}\n", path.to_string_lossy()))
+                .collect(),
+            InstanceRequest::SendText(text) => text.clone(),
+            InstanceRequest::OpenLink(link) => link.trim().to_owned(),
+            InstanceRequest::ReloadConfig => String::new(),
+        };
+        let mut out = format!("{} {} {token}\n", self.kind(), body.len()).into_bytes();
+        out.extend_from_slice(body.as_bytes());
+        out
+    }
+
+    /// Rebuild a request from its kind and body.  Blank lines and relative
+    /// paths are dropped; `None` for an unknown kind or an empty request.
+    pub fn decode(kind: &str, body: &str) -> Option<Self> {
+        match kind {
+            "send-files" => {
+                let paths: Vec<PathBuf> = body
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .filter(|path| path.is_absolute())
+                    .collect();
+                (!paths.is_empty()).then_some(InstanceRequest::SendFiles(paths))
+            }
+            "send-text" => (!body.is_empty()).then(|| InstanceRequest::SendText(body.to_owned())),
+            "open-link" => {
+                let link = body.trim();
+                (!link.is_empty()).then(|| InstanceRequest::OpenLink(link.to_owned()))
+            }
+            "reload-config" => Some(InstanceRequest::ReloadConfig),
+            _ => None,
+        }
+    }
+}
+
+/// Split a header line into the request kind, body length and token.
+pub fn parse_header(line: &str) -> Option<(&str, usize, &str)> {
+    let mut fields = line.trim_end().split(' ');
+    let (kind, len, token) = (fields.next()?, fields.next()?, fields.next()?);
+    let len: usize = len.parse().ok()?;
+    (fields.next().is_none() && len <= MAX_REQUEST_BYTES).then_some((kind, len, token))
+}
+
+/// The answer line for a handled request (including the trailing `\n`).
+pub fn reply_line(result: &Result<(), String>) -> String {
+    match result {
+        Ok(()) => "ok\n".to_owned(),
+        // Keep the message on one line.
+        Err(message) => format!("error {}\n", message.replace(['\r', '\n'], " ")),
+    }
+}
+
+pub fn parse_reply(line: &str) -> Result<(), String> {
+    match line.trim_end() {
+        "ok" => Ok(()),
+        other => Err(other
+            .strip_prefix("error ")
+            .unwrap_or("unexpected reply from ClipRelay")
+            .to_owned()),
+    }
+}
+
+#[derive(Debug)]
+pub enum ForwardError {
+    /// No client is listening.
+    NotRunning,
+    Io(io::Error),
+    /// The running client refused the request.
+    Refused(String),
+}
+
+impl fmt::Display for ForwardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForwardError::NotRunning => write!(f, "ClipRelay is not running"),
+            ForwardError::Io(e) => write!(f, "could not reach the running ClipRelay: {e}"),
+            ForwardError::Refused(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ForwardError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ForwardError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Hand `request` to the client listening on `pipe`, with the automation
+/// `token`, and wait for its answer.  Refused without writing anything if
+/// the pipe is served by another user's process.
+pub fn forward(pipe: &str, token: &str, request: &InstanceRequest) -> Result<(), ForwardError> {
+    let encoded = request.encode(token);
+    if encoded.len() > MAX_REQUEST_BYTES + MAX_LINE_BYTES {
+        return Err(ForwardError::Refused("request too large".to_owned()));
+    }
+    let mut waited = Duration::ZERO;
+    let mut file = loop {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe)
+        {
+            Ok(file) => break file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(ForwardError::NotRunning);
+            }
+            Err(err) if err.raw_os_error() == Some(PIPE_BUSY) && waited < BUSY_RETRY_FOR => {
+                std::thread::sleep(BUSY_RETRY_STEP);
+                waited += BUSY_RETRY_STEP;
+            }
+            Err(err) => return Err(ForwardError::Io(err)),
+        }
+    };
+    if !pipe_security::server_is_current_user(&file).map_err(ForwardError::Io)? {
+        return Err(ForwardError::Refused(
+            "the pipe is served by another user's process".to_owned(),
+        ));
+    }
+    file.write_all(&encoded).map_err(ForwardError::Io)?;
+    file.flush().map_err(ForwardError::Io)?;
+
+    let mut reply = String::new();
+    let read = BufReader::new(io::Read::take(&file, MAX_LINE_BYTES as u64))
+        .read_line(&mut reply)
+        .map_err(ForwardError::Io)?;
+    if read == 0 {
+        return Err(ForwardError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+    parse_reply(&reply).map_err(ForwardError::Refused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(request: &InstanceRequest) -> Option<InstanceRequest> {
+        let encoded = String::from_utf8(request.encode("c0ffee")).unwrap();
+        let (header, body) = encoded.split_once('\n').unwrap();
+        let (kind, len, token) = parse_header(header)?;
+        assert_eq!(len, body.len());
+        assert_eq!(token, "c0ffee");
+        InstanceRequest::decode(kind, body)
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        let dir = std::env::temp_dir();
+        let files = InstanceRequest::SendFiles(vec![dir.join("a file.txt"), dir.join("b.png")]);
+        assert_eq!(round_trip(&files), Some(files));
+        let text = InstanceRequest::SendText("two\nlines \n".to_owned());
+        assert_eq!(round_trip(&text), Some(text));
+        let link = InstanceRequest::OpenLink("cliprelay://join?server=x&room=y".to_owned());
+        assert_eq!(round_trip(&link), Some(link));
+        assert_eq!(
+            round_trip(&InstanceRequest::ReloadConfig),
+            Some(InstanceRequest::ReloadConfig)
+        );
+
+        assert_eq!(
+            InstanceRequest::decode("send-files", "relative.txt\n\n"),
+            None
+        );
+        assert_eq!(InstanceRequest::decode("format-disk", "C:\\"), None);
+        assert_eq!(
+            parse_header(&format!("send-text {} c0ffee", MAX_REQUEST_BYTES + 1)),
+            None
+        );
+        assert_eq!(parse_header("send-text 2"), None);
+    }
+
+    #[test]
+    fn replies_round_trip() {
+        assert_eq!(parse_reply(&reply_line(&Ok(()))), Ok(()));
+        assert_eq!(
+            parse_reply(&reply_line(&Err("not a file:\nx".to_owned()))),
+            Err("not a file: x".to_owned())
+        );
+        assert!(parse_reply("").is_err());
+        assert_eq!(
+            pipe_name("Jo Smith"),
+            r"\\.\pipe\ClipRelay.Instance.Jo_Smith"
+        );
+    }
+
+    #[test]
+    fn forwarding_without_a_listener_reports_not_running() {
+        let pipe = std::env::temp_dir().join("cliprelay-no-such-pipe");
+        let result = forward(
+            &pipe.to_string_lossy(),
+            "c0ffee",
+            &InstanceRequest::SendText("hi".to_owned()),
+        );
+        assert!(matches!(result, Err(ForwardError::NotRunning)));
+    }
+}
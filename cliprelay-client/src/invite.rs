@@ -0,0 +1,90 @@
+//! Room invites: a message to paste into a chat with everything needed to
+//! join the current room, built from the join link (see [`join_link`]),
+//! with the server and room code spelled out for typing in by hand and,
+//! optionally, a QR code of the link drawn with block characters.
+//!
+//! Like the join link, an invite carries the room code and is as secret as
+//! the code itself.
+
+use qrcodegen::{QrCode, QrCodeEcc};
+
+use crate::join_link;
+
+/// Light modules around the QR code.  The standard asks for 4; 2 is enough
+/// for phone cameras and keeps the text narrower.
+const QR_QUIET_MODULES: i32 = 2;
+
+/// The invite for a room.
+pub fn text(server_url: &str, room_code: &str, with_qr: bool) -> String {
+    let link = join_link::build(server_url, room_code);
+    let mut invite = format!(
+        "You are invited to a ClipRelay room.\n\
+         \n\
+         With ClipRelay installed, open this link to join:\n\
+         {link}\n\
+         \n\
+         Or enter these on ClipRelay's room setup screen:\n\
+         \x20 Server:    {server_url}\n\
+         \x20 Room code: {room_code}\n\
+         \n\
+         The room code is the room's encryption key: keep this message private.\n"
+    );
+    if with_qr && let Some(qr) = qr_text(&link) {
+        invite.push_str("\nScan to open the join link:\n\n");
+        invite.push_str(&qr);
+    }
+    invite
+}
+
+/// `data` as a QR code in text, two modules per line: each character is
+/// the upper and lower module of one column (`█`, `▀`, `▄` or a space),
+/// dark modules drawn dark for a light background.  `None` if `data` is
+/// too long for a QR code.
+pub fn qr_text(data: &str) -> Option<String> {
+    let qr = QrCode::encode_text(data, QrCodeEcc::Low).ok()?;
+    let range = -QR_QUIET_MODULES..qr.size() + QR_QUIET_MODULES;
+    let mut out = String::new();
+    for y in range.clone().step_by(2) {
+        for x in range.clone() {
+            out.push(match (qr.get_module(x, y), qr.get_module(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invites_carry_the_link_and_the_details() {
+        let invite = text("wss://relay.example/ws", "blue fox", false);
+        assert!(invite.contains(&join_link::build("wss://relay.example/ws", "blue fox")));
+        assert!(invite.contains("  Server:    wss://relay.example/ws\n"));
+        assert!(invite.contains("  Room code: blue fox\n"));
+        assert!(!invite.contains('█'));
+
+        let with_qr = text("wss://relay.example/ws", "blue fox", true);
+        assert!(with_qr.starts_with(&invite));
+        assert!(with_qr.contains('█'));
+    }
+
+    #[test]
+    fn qr_text_is_a_square_with_a_quiet_zone() {
+        let link = join_link::build("wss://relay.example/ws", "blue fox");
+        let size = QrCode::encode_text(&link, QrCodeEcc::Low).unwrap().size();
+        let width = (size + 2 * QR_QUIET_MODULES) as usize;
+        let qr = qr_text(&link).unwrap();
+        let lines: Vec<&str> = qr.lines().collect();
+        assert_eq!(lines.len(), width.div_ceil(2));
+        assert!(lines.iter().all(|line| line.chars().count() == width));
+        assert!(lines[0].chars().all(|c| c == ' '));
+        assert!(qr_text(&"x".repeat(8000)).is_none());
+    }
+}
@@ -0,0 +1,133 @@
+//! `cliprelay://join?server=…&room=…` links for sharing a room in one
+//! click.
+//!
+//! The room code is the room's encryption passphrase, so a join link is as
+//! secret as the code itself.  Opening one never joins silently: the client
+//! asks first, pre-filling the room setup screen.
+
+use std::fmt;
+
+use url::Url;
+
+pub const SCHEME: &str = "cliprelay";
+
+/// Longer links are rejected without parsing.
+pub const MAX_LINK_CHARS: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JoinLink {
+    pub server_url: String,
+    pub room_code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinLinkError {
+    TooLong,
+    NotAJoinLink,
+    MissingServer,
+    MissingRoom,
+    BadServer,
+}
+
+impl fmt::Display for JoinLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinLinkError::TooLong => write!(f, "link is too long"),
+            JoinLinkError::NotAJoinLink => write!(f, "not a {SCHEME}://join link"),
+            JoinLinkError::MissingServer => write!(f, "link has no server"),
+            JoinLinkError::MissingRoom => write!(f, "link has no room code"),
+            JoinLinkError::BadServer => write!(f, "link server is not a ws:// or wss:// URL"),
+        }
+    }
+}
+
+impl std::error::Error for JoinLinkError {}
+
+/// Parse a join link.  Explorer and browsers sometimes add a slash after
+/// the host (`cliprelay://join/?…`); that is accepted.
+pub fn parse(link: &str) -> Result<JoinLink, JoinLinkError> {
+    let link = link.trim();
+    if link.len() > MAX_LINK_CHARS {
+        return Err(JoinLinkError::TooLong);
+    }
+    let url = Url::parse(link).map_err(|_| JoinLinkError::NotAJoinLink)?;
+    if url.scheme() != SCHEME || url.host_str() != Some("join") || !matches!(url.path(), "" | "/") {
+        return Err(JoinLinkError::NotAJoinLink);
+    }
+    let mut server_url = None;
+    let mut room_code = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "server" => server_url = Some(value.trim().to_owned()),
+            "room" => room_code = Some(value.trim().to_owned()),
+            _ => {}
+        }
+    }
+    let server_url = server_url
+        .filter(|s| !s.is_empty())
+        .ok_or(JoinLinkError::MissingServer)?;
+    let room_code = room_code
+        .filter(|r| !r.is_empty())
+        .ok_or(JoinLinkError::MissingRoom)?;
+    let server = Url::parse(&server_url).map_err(|_| JoinLinkError::BadServer)?;
+    if !matches!(server.scheme(), "ws" | "wss") {
+        return Err(JoinLinkError::BadServer);
+    }
+    Ok(JoinLink {
+        server_url,
+        room_code,
+    })
+}
+
+/// The link for a room, with both values percent-encoded.
+pub fn build(server_url: &str, room_code: &str) -> String {
+    let mut url = Url::parse(&format!("{SCHEME}://join")).expect("static URL parses");
+    url.query_pairs_mut()
+        .append_pair("server", server_url)
+        .append_pair("room", room_code);
+    url.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_links_parse_back() {
+        let link = build("wss://relay.example/ws", "blue fox & 7=8");
+        assert!(link.starts_with("cliprelay://join?server="));
+        assert_eq!(
+            parse(&link),
+            Ok(JoinLink {
+                server_url: "wss://relay.example/ws".to_owned(),
+                room_code: "blue fox & 7=8".to_owned(),
+            })
+        );
+        let with_slash = link.replacen("join?", "join/?", 1);
+        assert!(parse(&with_slash).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        assert_eq!(
+            parse("https://join?server=wss://x&room=a"),
+            Err(JoinLinkError::NotAJoinLink)
+        );
+        assert_eq!(
+            parse("cliprelay://leave?server=wss://x&room=a"),
+            Err(JoinLinkError::NotAJoinLink)
+        );
+        assert_eq!(
+            parse("cliprelay://join?room=a"),
+            Err(JoinLinkError::MissingServer)
+        );
+        assert_eq!(
+            parse("cliprelay://join?server=wss://x&room=%20"),
+            Err(JoinLinkError::MissingRoom)
+        );
+        assert_eq!(
+            parse("cliprelay://join?server=http://x&room=a"),
+            Err(JoinLinkError::BadServer)
+        );
+    }
+}
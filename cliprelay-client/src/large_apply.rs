@@ -0,0 +1,106 @@
+//! Asking before auto-applying very large incoming text.
+//!
+//! Pasting a few hundred KB into some applications freezes them, and an
+//! unexpected blob replaces whatever was on the clipboard.  Above the
+//! threshold, auto-apply is downgraded to a confirmation window showing the
+//! size and the start of the text; the item is listed in Notifications
+//! either way.
+
+use cliprelay_core::MAX_CLIPBOARD_TEXT_BYTES;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_THRESHOLD_KIB: u32 = 64;
+pub const MIN_THRESHOLD_KIB: u32 = 1;
+/// Text messages cannot be larger than this, so a higher threshold would
+/// never apply.
+pub const MAX_THRESHOLD_KIB: u32 = (MAX_CLIPBOARD_TEXT_BYTES / 1024) as u32;
+
+/// Characters of the text shown in the confirmation window.
+pub const PREVIEW_CHARS: usize = 400;
+
+/// Saved in `ui_state.json`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LargeApplyConfirm {
+    pub enabled: bool,
+    pub threshold_kib: u32,
+}
+
+impl Default for LargeApplyConfirm {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold_kib: DEFAULT_THRESHOLD_KIB,
+        }
+    }
+}
+
+impl LargeApplyConfirm {
+    /// Whether auto-applying `text_bytes` of text should ask first.
+    pub fn needs_confirmation(&self, text_bytes: usize) -> bool {
+        self.enabled && text_bytes > self.normalized().threshold_kib as usize * 1024
+    }
+
+    /// The threshold clamped to what the Options page allows.
+    pub fn normalized(self) -> Self {
+        Self {
+            threshold_kib: self
+                .threshold_kib
+                .clamp(MIN_THRESHOLD_KIB, MAX_THRESHOLD_KIB),
+            ..self
+        }
+    }
+}
+
+/// The first [`PREVIEW_CHARS`] characters of `text`, with a note of how
+/// much was left out.
+pub fn preview(text: &str) -> String {
+    let Some((cut, _)) = text.char_indices().nth(PREVIEW_CHARS) else {
+        return text.to_owned();
+    };
+    let rest = text.len() - cut;
+    format!("{}\n… ({rest} more bytes)", &text[..cut])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asks_only_above_the_threshold() {
+        let confirm = LargeApplyConfirm::default();
+        let limit = DEFAULT_THRESHOLD_KIB as usize * 1024;
+        assert!(!confirm.needs_confirmation(limit));
+        assert!(confirm.needs_confirmation(limit + 1));
+
+        let off = LargeApplyConfirm {
+            enabled: false,
+            ..confirm
+        };
+        assert!(!off.needs_confirmation(MAX_CLIPBOARD_TEXT_BYTES));
+
+        // A hand-edited zero does not make every text ask.
+        let zero = LargeApplyConfirm {
+            enabled: true,
+            threshold_kib: 0,
+        };
+        assert!(!zero.needs_confirmation(1024));
+        assert!(zero.needs_confirmation(1025));
+    }
+
+    #[test]
+    fn preview_is_cut_on_a_char_boundary() {
+        assert_eq!(preview("short"), "short");
+        let text = "é".repeat(PREVIEW_CHARS + 10);
+        let shown = preview(&text);
+        assert!(shown.starts_with(&"é".repeat(PREVIEW_CHARS)));
+        assert!(shown.ends_with("(20 more bytes)"));
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let parsed: LargeApplyConfirm = serde_json::from_str(r#"{"enabled":false}"#).unwrap();
+        assert_eq!(parsed.threshold_kib, DEFAULT_THRESHOLD_KIB);
+        assert!(!parsed.enabled);
+    }
+}
@@ -0,0 +1,199 @@
+//! Round-trip times to the relay, for the latency graph in Options →
+//! Advanced.
+//!
+//! The keepalive pings double as probes: each carries the time it was sent,
+//! and the relay's pong echoes the payload back unchanged.  Samples are kept
+//! in memory only, across reconnects, so a degrading connection shows up as
+//! a trend rather than a single number.
+//!
+//! Alongside each ping goes a probe frame ([`cliprelay_core::LatencyProbe`])
+//! that the relay stamps with its own receive and forward times, so the
+//! panel can tell the leg to the relay from the leg back, for this PC and
+//! for every other device that sends probes.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use cliprelay_core::ProbeLatency;
+
+/// Probe frames sent without an answer before giving up on them for the
+/// session: relays that predate probes count each one as a bad frame.
+pub const MAX_UNANSWERED_PROBES: u32 = 3;
+
+/// Seconds between probes.
+pub const PROBE_INTERVAL_SECS: u64 = 10;
+
+/// Samples kept: half an hour at one per [`PROBE_INTERVAL_SECS`].
+pub const MAX_SAMPLES: usize = 180;
+
+/// Marks a ping payload as one of ours, so stray pongs are not misread.
+const PROBE_MAGIC: &[u8; 4] = b"CRL1";
+
+/// Ping payload for a probe sent at `sent_at_ms`.
+pub fn probe_payload(sent_at_ms: u64) -> Vec<u8> {
+    let mut payload = PROBE_MAGIC.to_vec();
+    payload.extend_from_slice(&sent_at_ms.to_le_bytes());
+    payload
+}
+
+/// Round trip of the probe a pong with `payload` answers, if it is one.
+pub fn rtt_from_pong(payload: &[u8], now_ms: u64) -> Option<u64> {
+    let sent_at = payload.strip_prefix(PROBE_MAGIC)?;
+    let sent_at = u64::from_le_bytes(sent_at.try_into().ok()?);
+    now_ms.checked_sub(sent_at)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    pub at_unix_ms: u64,
+    pub rtt_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub min_ms: u64,
+    pub avg_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Whether to keep sending probe frames on this connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeGate {
+    unanswered: u32,
+    answered: bool,
+}
+
+impl ProbeGate {
+    pub fn should_send(&self) -> bool {
+        self.answered || self.unanswered < MAX_UNANSWERED_PROBES
+    }
+
+    pub fn sent(&mut self) {
+        self.unanswered = self.unanswered.saturating_add(1);
+    }
+
+    /// One of our probes came back: the relay supports them.
+    pub fn answered(&mut self) {
+        self.answered = true;
+    }
+}
+
+/// The latest probe breakdown from one device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeReading {
+    pub device_name: String,
+    pub latency: ProbeLatency,
+    pub at_unix_ms: u64,
+}
+
+/// The most recent [`MAX_SAMPLES`] round trips, oldest first, and the
+/// latest probe breakdown per device.
+#[derive(Debug, Default)]
+pub struct LatencyHistory {
+    samples: VecDeque<LatencySample>,
+    probes: BTreeMap<String, ProbeReading>,
+}
+
+impl LatencyHistory {
+    pub fn push(&mut self, sample: LatencySample) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = &LatencySample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&LatencySample> {
+        self.samples.back()
+    }
+
+    pub fn record_probe(&mut self, device_id: &str, reading: ProbeReading) {
+        self.probes.insert(device_id.to_owned(), reading);
+    }
+
+    /// Latest breakdowns, by device id.
+    pub fn probes(&self) -> impl Iterator<Item = (&str, &ProbeReading)> {
+        self.probes
+            .iter()
+            .map(|(id, reading)| (id.as_str(), reading))
+    }
+
+    pub fn stats(&self) -> Option<LatencyStats> {
+        let count = self.samples.len() as u64;
+        let min_ms = self.samples.iter().map(|s| s.rtt_ms).min()?;
+        let max_ms = self.samples.iter().map(|s| s.rtt_ms).max()?;
+        let total: u64 = self.samples.iter().map(|s| s.rtt_ms).sum();
+        Some(LatencyStats {
+            min_ms,
+            avg_ms: total / count,
+            max_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pong_of_a_probe_gives_its_round_trip() {
+        let payload = probe_payload(1_000);
+        assert_eq!(rtt_from_pong(&payload, 1_042), Some(42));
+        assert_eq!(rtt_from_pong(&payload, 999), None);
+        assert_eq!(rtt_from_pong(&[], 1_042), None);
+        assert_eq!(rtt_from_pong(&payload[..8], 1_042), None);
+    }
+
+    #[test]
+    fn keeps_the_latest_samples_with_stats() {
+        let mut history = LatencyHistory::default();
+        assert_eq!(history.stats(), None);
+        for i in 0..MAX_SAMPLES as u64 + 5 {
+            history.push(LatencySample {
+                at_unix_ms: i,
+                rtt_ms: i % 10 + 20,
+            });
+        }
+        assert_eq!(history.samples().len(), MAX_SAMPLES);
+        assert_eq!(history.samples().next().unwrap().at_unix_ms, 5);
+        assert_eq!(history.latest().unwrap().at_unix_ms, MAX_SAMPLES as u64 + 4);
+        let stats = history.stats().unwrap();
+        assert_eq!((stats.min_ms, stats.max_ms), (20, 29));
+        assert_eq!(stats.avg_ms, 24);
+    }
+
+    #[test]
+    fn probes_stop_until_one_is_answered() {
+        let mut gate = ProbeGate::default();
+        for _ in 0..MAX_UNANSWERED_PROBES {
+            assert!(gate.should_send());
+            gate.sent();
+        }
+        assert!(!gate.should_send());
+        gate.answered();
+        gate.sent();
+        assert!(gate.should_send());
+    }
+
+    #[test]
+    fn keeps_the_latest_probe_per_device() {
+        let mut history = LatencyHistory::default();
+        let reading = |to_relay_ms, at_unix_ms| ProbeReading {
+            device_name: "Laptop".to_owned(),
+            latency: ProbeLatency {
+                to_relay_ms,
+                in_relay_ms: 0,
+                from_relay_ms: 5,
+            },
+            at_unix_ms,
+        };
+        history.record_probe("a", reading(10, 1));
+        history.record_probe("b", reading(20, 2));
+        history.record_probe("a", reading(30, 3));
+        let probes: Vec<_> = history.probes().collect();
+        assert_eq!(probes.len(), 2);
+        assert_eq!(probes[0], ("a", &reading(30, 3)));
+    }
+}
@@ -1,273 +1,107 @@
-#[cfg(target_os = "windows")]
-pub mod autostart {
-    use std::{fmt, path::Path, string::FromUtf16Error};
-
-    use windows_sys::Win32::Foundation::ERROR_FILE_NOT_FOUND;
-    use windows_sys::Win32::System::Registry::{
-        HKEY, HKEY_CURRENT_USER, KEY_QUERY_VALUE, KEY_READ, KEY_SET_VALUE, KEY_WRITE, REG_SZ,
-        RegCloseKey, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
-    };
-
-    const RUN_SUBKEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
-    /// Upper bound on the autostart command string stored in the registry.
-    /// A reasonable autostart entry is under 1 KiB; 32 KiB is a generous
-    /// safety margin that prevents a malicious or corrupted value from
-    /// causing an out-of-memory allocation (size_bytes is a u32, so the
-    /// unchecked allocation could be up to ~4 GiB).
-    const MAX_RUN_VALUE_BYTES: u32 = 32 * 1024;
-
-    #[derive(Debug)]
-    pub enum AutostartError {
-        RegOpenRunKey { status: u32 },
-        RegQuerySize { status: u32 },
-        RegQueryData { status: u32 },
-        RegSet { status: u32 },
-        RegDelete { status: u32 },
-        ValueTooLarge,
-        InvalidUtf16(FromUtf16Error),
-    }
-
-    impl fmt::Display for AutostartError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                AutostartError::RegOpenRunKey { status } => {
-                    write!(f, "RegOpenKeyExW(Run) failed: {status}")
-                }
-                AutostartError::RegQuerySize { status } => {
-                    write!(f, "RegQueryValueExW(size) failed: {status}")
-                }
-                AutostartError::RegQueryData { status } => {
-                    write!(f, "RegQueryValueExW(data) failed: {status}")
-                }
-                AutostartError::RegSet { status } => write!(f, "RegSetValueExW failed: {status}"),
-                AutostartError::RegDelete { status } => {
-                    write!(f, "RegDeleteValueW failed: {status}")
-                }
-                AutostartError::ValueTooLarge => write!(f, "registry value too large"),
-                AutostartError::InvalidUtf16(e) => write!(f, "invalid UTF-16 in Run value: {e}"),
-            }
-        }
-    }
-
-    impl std::error::Error for AutostartError {
-        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-            match self {
-                AutostartError::InvalidUtf16(e) => Some(e),
-                _ => None,
-            }
-        }
-    }
-
-    pub fn autostart_command(exe: &Path) -> String {
-        format!("\"{}\" --background", exe.display())
-    }
-
-    pub fn is_enabled(exe: &Path, value_name: &str) -> Result<bool, AutostartError> {
-        let expected = autostart_command(exe);
-        Ok(run_key_get_value_string(value_name)?.is_some_and(|v| v.trim() == expected.trim()))
-    }
-
-    pub fn set_enabled(exe: &Path, value_name: &str, enabled: bool) -> Result<(), AutostartError> {
-        if enabled {
-            let cmd = autostart_command(exe);
-            run_key_set_value_string(value_name, &cmd)?;
-        } else {
-            run_key_delete_value(value_name)?;
-        }
-        Ok(())
-    }
-
-    fn run_key_open(desired_access: u32) -> Result<HKEY, AutostartError> {
-        let subkey = wide_null(RUN_SUBKEY);
-        let mut out: HKEY = 0;
-        let status = unsafe {
-            RegOpenKeyExW(
-                HKEY_CURRENT_USER,
-                subkey.as_ptr(),
-                0,
-                desired_access,
-                &mut out,
-            )
-        };
-        if status != 0 {
-            return Err(AutostartError::RegOpenRunKey { status });
-        }
-        Ok(out)
-    }
-
-    fn run_key_get_value_string(name: &str) -> Result<Option<String>, AutostartError> {
-        let key = run_key_open(KEY_READ | KEY_QUERY_VALUE)?;
-        let name_w = wide_null(name);
-
-        let mut value_type: u32 = 0;
-        let mut size_bytes: u32 = 0;
-        let status = unsafe {
-            RegQueryValueExW(
-                key,
-                name_w.as_ptr(),
-                std::ptr::null_mut(),
-                &mut value_type,
-                std::ptr::null_mut(),
-                &mut size_bytes,
-            )
-        };
-
-        if status == ERROR_FILE_NOT_FOUND {
-            unsafe { RegCloseKey(key) };
-            return Ok(None);
-        }
-
-        if status != 0 {
-            unsafe { RegCloseKey(key) };
-            return Err(AutostartError::RegQuerySize { status });
-        }
-
-        if value_type != REG_SZ {
-            unsafe { RegCloseKey(key) };
-            return Ok(None);
-        }
-
-        if size_bytes == 0 {
-            unsafe { RegCloseKey(key) };
-            return Ok(Some(String::new()));
-        }
-
-        if size_bytes > MAX_RUN_VALUE_BYTES {
-            unsafe { RegCloseKey(key) };
-            return Err(AutostartError::ValueTooLarge);
-        }
-
-        let mut buf: Vec<u8> = vec![0u8; size_bytes as usize];
-        let mut size_bytes_2 = size_bytes;
-        let status = unsafe {
-            RegQueryValueExW(
-                key,
-                name_w.as_ptr(),
-                std::ptr::null_mut(),
-                &mut value_type,
-                buf.as_mut_ptr(),
-                &mut size_bytes_2,
-            )
-        };
-        unsafe { RegCloseKey(key) };
-        if status != 0 {
-            return Err(AutostartError::RegQueryData { status });
-        }
-
-        if !buf.len().is_multiple_of(2) {
-            return Ok(None);
-        }
-        let mut utf16: Vec<u16> = Vec::with_capacity(buf.len() / 2);
-        for chunk in buf.chunks_exact(2) {
-            utf16.push(u16::from_le_bytes([chunk[0], chunk[1]]));
-        }
-        if let Some(0) = utf16.last().copied() {
-            utf16.pop();
-        }
-
-        String::from_utf16(&utf16)
-            .map(Some)
-            .map_err(AutostartError::InvalidUtf16)
-    }
-
-    fn run_key_set_value_string(name: &str, value: &str) -> Result<(), AutostartError> {
-        let key = run_key_open(KEY_WRITE | KEY_SET_VALUE)?;
-        let name_w = wide_null(name);
-        let value_w = wide_null(value);
-        let bytes: &[u8] =
-            unsafe { std::slice::from_raw_parts(value_w.as_ptr() as *const u8, value_w.len() * 2) };
-
-        // Validate the byte length BEFORE entering the unsafe RegSetValueExW call.
-        // If the value is unreasonably large, close the key and return the error
-        // immediately — the previous code embedded the try_from inside the unsafe
-        // block's argument list, which meant the early-return via `?` bypassed
-        // the `RegCloseKey` call below and leaked the handle.
-        let byte_len = u32::try_from(bytes.len()).map_err(|_| {
-            unsafe { RegCloseKey(key) };
-            AutostartError::ValueTooLarge
-        })?;
-
-        let status =
-            unsafe { RegSetValueExW(key, name_w.as_ptr(), 0, REG_SZ, bytes.as_ptr(), byte_len) };
-        unsafe { RegCloseKey(key) };
-        if status != 0 {
-            return Err(AutostartError::RegSet { status });
-        }
-        Ok(())
-    }
-
-    fn run_key_delete_value(name: &str) -> Result<(), AutostartError> {
-        let key = run_key_open(KEY_WRITE | KEY_SET_VALUE)?;
-        let name_w = wide_null(name);
-        let status = unsafe { RegDeleteValueW(key, name_w.as_ptr()) };
-        unsafe { RegCloseKey(key) };
-        if status == ERROR_FILE_NOT_FOUND {
-            return Ok(());
-        }
-        if status != 0 {
-            return Err(AutostartError::RegDelete { status });
-        }
-        Ok(())
-    }
-
-    fn wide_null(s: &str) -> Vec<u16> {
-        let mut v: Vec<u16> = s.encode_utf16().collect();
-        v.push(0);
-        v
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-
-        #[test]
-        fn autostart_command_quotes_and_background() {
-            let p = std::path::PathBuf::from(r"C:\Program Files\ClipRelay\ClipRelay.exe");
-            let cmd = autostart_command(&p);
-            assert!(cmd.starts_with('"'));
-            assert!(cmd.contains("\" --background"));
-        }
-
-        #[test]
-        fn wide_null_has_trailing_nul() {
-            let v = wide_null("abc");
-            assert_eq!(v.last().copied(), Some(0));
-        }
-    }
-}
+pub mod autostart;
 
 pub mod ui_state;
 
 pub mod ui_layout;
 
-#[cfg(not(target_os = "windows"))]
-pub mod autostart {
-    use std::path::Path;
-
-    #[derive(Debug)]
-    pub struct AutostartError;
-
-    impl std::fmt::Display for AutostartError {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(f, "autostart is only supported on Windows")
-        }
-    }
-
-    impl std::error::Error for AutostartError {}
-
-    pub fn autostart_command(exe: &Path) -> String {
-        format!("\"{}\" --background", exe.display())
-    }
-
-    pub fn is_enabled(_exe: &Path, _value_name: &str) -> Result<bool, AutostartError> {
-        Ok(false)
-    }
-
-    pub fn set_enabled(
-        _exe: &Path,
-        _value_name: &str,
-        _enabled: bool,
-    ) -> Result<(), AutostartError> {
-        Ok(())
-    }
-}
+pub mod automation;
+
+pub mod autopaste;
+
+pub mod clip_ring;
+
+pub mod devices;
+
+pub mod startup;
+
+pub mod sound;
+
+pub mod dedupe;
+
+pub mod tray_badge;
+
+pub mod text_split;
+
+pub mod dpapi;
+
+pub mod snippets;
+
+pub mod sync_direction;
+
+pub mod extension_policy;
+
+pub mod mark_of_the_web;
+
+pub mod links;
+
+pub mod transforms;
+
+pub mod metered;
+
+pub mod reconnect;
+
+pub mod history_retention;
+
+pub mod self_test;
+
+pub mod identity;
+
+pub mod room_profiles;
+
+pub mod config_profiles;
+
+pub mod log_view;
+
+pub mod protocol_trace;
+
+pub mod crash_report;
+
+pub mod instance;
+
+pub mod shell_menu;
+
+pub mod join_link;
+
+pub mod invite;
+
+pub mod outbox;
+
+pub mod transfers;
+
+pub mod send_counters;
+
+pub mod latency;
+
+pub mod secrets;
+
+pub mod tray_clicks;
+
+pub mod notification_queue;
+
+pub mod simulated_peer;
+
+pub mod status_widget;
+
+pub mod text_file;
+
+pub mod diagnose;
+
+pub mod usage_stats;
+
+pub mod large_apply;
+
+pub mod oversize_text;
+
+pub mod code_preview;
+
+pub mod append_mode;
+
+pub mod onboarding;
+
+pub mod file_clipboard;
+
+pub mod network_change;
+
+pub mod auth_gate;
+
+pub mod pipe_security;
@@ -0,0 +1,128 @@
+//! Web links in received text, for the "Open Link" actions.
+//!
+//! Only `http` and `https` URLs are recognised; anything else (`file:`,
+//! `javascript:`, custom protocol handlers) is never offered for opening.
+//! URLs are returned in their parsed form, so internationalised host names
+//! show up as punycode rather than as look-alike characters.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Upper bound on links offered for one text.
+pub const MAX_LINKS: usize = 5;
+
+/// Longer candidates are ignored.
+pub const MAX_URL_CHARS: usize = 2048;
+
+/// What happens when a trusted device sends text that is just a link.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkAutoOpen {
+    /// Only offer the Open Link button.
+    #[default]
+    Off,
+    /// Ask before opening.
+    Ask,
+    /// Open in the browser straight away.
+    Open,
+}
+
+impl LinkAutoOpen {
+    pub const ALL: [LinkAutoOpen; 3] = [LinkAutoOpen::Off, LinkAutoOpen::Ask, LinkAutoOpen::Open];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkAutoOpen::Off => "Don't open automatically",
+            LinkAutoOpen::Ask => "Ask before opening",
+            LinkAutoOpen::Open => "Open without asking",
+        }
+    }
+}
+
+/// Parse `candidate` as an absolute web URL.
+pub fn web_url(candidate: &str) -> Option<String> {
+    if candidate.len() > MAX_URL_CHARS {
+        return None;
+    }
+    let url = Url::parse(candidate).ok()?;
+    (matches!(url.scheme(), "http" | "https") && url.host_str().is_some()).then(|| url.into())
+}
+
+/// Web URLs in `text`, in order of appearance, without duplicates.
+pub fn find_urls(text: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`')) {
+        let lower = token.to_ascii_lowercase();
+        let Some(start) = lower.find("https://").or_else(|| lower.find("http://")) else {
+            continue;
+        };
+        if let Some(url) = web_url(trim_trailing_punctuation(&token[start..]))
+            && !out.contains(&url)
+        {
+            out.push(url);
+            if out.len() >= MAX_LINKS {
+                break;
+            }
+        }
+    }
+    out
+}
+
+/// The URL when `text` is nothing but a single link (surrounding whitespace
+/// aside).
+pub fn sole_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.contains(char::is_whitespace) {
+        return None;
+    }
+    web_url(text)
+}
+
+/// Drop sentence punctuation after a URL, and closing brackets that have no
+/// opening partner inside it (`(see https://x.org/a_(b))` keeps one).
+pub fn trim_trailing_punctuation(mut candidate: &str) -> &str {
+    loop {
+        let Some(last) = candidate.chars().last() else {
+            return candidate;
+        };
+        let strip = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' => true,
+            ')' => candidate.matches('(').count() < candidate.matches(')').count(),
+            ']' => candidate.matches('[').count() < candidate.matches(']').count(),
+            _ => false,
+        };
+        if !strip {
+            return candidate;
+        }
+        candidate = &candidate[..candidate.len() - last.len_utf8()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_web_links_in_prose() {
+        let text = "See https://example.com/a?b=1, and (http://x.org/wiki/A_(b)).\n\
+                    Also HTTPS://Example.com/a?b=1 again, file:///C:/x and javascript:alert(1).";
+        assert_eq!(
+            find_urls(text),
+            ["https://example.com/a?b=1", "http://x.org/wiki/A_(b)"]
+        );
+    }
+
+    #[test]
+    fn sole_url_requires_the_whole_text() {
+        assert_eq!(
+            sole_url("  https://example.com\n").as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(sole_url("https://example.com and more"), None);
+        assert_eq!(sole_url("ftp://example.com/file"), None);
+        assert_eq!(
+            sole_url("https://bücher.example/").as_deref(),
+            Some("https://xn--bcher-kva.example/")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Reading the client's own log file for the in-app log viewer: tailing
+//! the file as it grows, parsing the level of each `tracing` line, and
+//! filtering by level and search text.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Lines kept in memory; older ones are dropped as new ones arrive.
+pub const MAX_LOG_LINES: usize = 5_000;
+
+/// How much of an existing file is read when the viewer opens.
+pub const INITIAL_TAIL_BYTES: u64 = 512 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        }
+    }
+
+    fn parse(token: &str) -> Option<LogLevel> {
+        LogLevel::ALL.into_iter().find(|l| l.label() == token)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    /// Level of the event; continuation lines of a multi-line message
+    /// inherit the level of the line they belong to.
+    pub level: Option<LogLevel>,
+    pub text: String,
+}
+
+/// Remove ANSI colour sequences (`ESC [ … letter`) written by the
+/// formatter.
+pub fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\u{1b}' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Level of a `tracing_subscriber::fmt` line
+/// (`2024-05-01T10:00:00.000000Z  INFO target: message`), if it is one.
+pub fn parse_level(line: &str) -> Option<LogLevel> {
+    let mut tokens = line.split_whitespace();
+    let timestamp = tokens.next()?;
+    if !timestamp.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    LogLevel::parse(tokens.next()?)
+}
+
+/// Incrementally reads a log file from where the previous read stopped.
+#[derive(Debug, Default)]
+pub struct LogTail {
+    lines: Vec<LogLine>,
+    offset: u64,
+    /// Bytes after the last newline, held until the line is complete.
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    pub fn lines(&self) -> &[LogLine] {
+        &self.lines
+    }
+
+    /// Read whatever was appended to `path` since the last call.  The first
+    /// call starts [`INITIAL_TAIL_BYTES`] from the end; a file that shrank
+    /// (truncated or replaced) is re-read from the start.  Returns whether
+    /// any lines were added.
+    pub fn refresh(&mut self, path: &Path) -> io::Result<bool> {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut skip_first_line = false;
+        if len < self.offset {
+            *self = LogTail::default();
+        }
+        if self.offset == 0 && len > INITIAL_TAIL_BYTES {
+            self.offset = len - INITIAL_TAIL_BYTES;
+            skip_first_line = true;
+        }
+        if len == self.offset {
+            return Ok(false);
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut buf)?;
+        self.offset += buf.len() as u64;
+
+        if skip_first_line {
+            // Started mid-file: drop the cut-off line.
+            match buf.iter().position(|b| *b == b'\n') {
+                Some(newline) => {
+                    buf.drain(..=newline);
+                }
+                None => buf.clear(),
+            }
+        }
+        let mut data = std::mem::take(&mut self.partial);
+        data.extend_from_slice(&buf);
+        let complete = match data.iter().rposition(|b| *b == b'\n') {
+            Some(newline) => {
+                self.partial = data.split_off(newline + 1);
+                data
+            }
+            None => {
+                self.partial = data;
+                return Ok(false);
+            }
+        };
+
+        let before = self.lines.len();
+        for raw in String::from_utf8_lossy(&complete).lines() {
+            let text = strip_ansi(raw.trim_end_matches('\r'));
+            let level = parse_level(&text).or_else(|| self.lines.last().and_then(|l| l.level));
+            self.lines.push(LogLine { level, text });
+        }
+        let added = self.lines.len() > before;
+        if self.lines.len() > MAX_LOG_LINES {
+            let excess = self.lines.len() - MAX_LOG_LINES;
+            self.lines.drain(..excess);
+        }
+        Ok(added)
+    }
+}
+
+/// Lines at or above `max_level` in severity (an `Info` filter shows
+/// errors, warnings and info) whose text contains `query`, ignoring case.
+/// Lines with no known level are always shown.
+pub fn filter_lines<'a>(
+    lines: &'a [LogLine],
+    max_level: LogLevel,
+    query: &str,
+) -> Vec<&'a LogLine> {
+    let query = query.trim().to_lowercase();
+    lines
+        .iter()
+        .filter(|line| line.level.is_none_or(|level| level <= max_level))
+        .filter(|line| query.is_empty() || line.text.to_lowercase().contains(&query))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_colour_codes_and_parses_level() {
+        let raw = "\u{1b}[2m2024-05-01T10:00:00.000000Z\u{1b}[0m \u{1b}[33m WARN\u{1b}[0m \
+                   \u{1b}[2mcliprelay_client\u{1b}[0m\u{1b}[2m:\u{1b}[0m relay slow";
+        let line = strip_ansi(raw);
+        assert_eq!(
+            line,
+            "2024-05-01T10:00:00.000000Z  WARN cliprelay_client: relay slow"
+        );
+        assert_eq!(parse_level(&line), Some(LogLevel::Warn));
+        assert_eq!(parse_level("  at src/main.rs:12"), None);
+    }
+
+    #[test]
+    fn filter_by_level_and_query() {
+        let line = |level, text: &str| LogLine {
+            level,
+            text: text.to_owned(),
+        };
+        let lines = vec![
+            line(Some(LogLevel::Error), "connect failed"),
+            line(Some(LogLevel::Info), "Connected to relay"),
+            line(Some(LogLevel::Debug), "frame received"),
+            line(None, "startup banner"),
+        ];
+        let texts = |max, query| -> Vec<&str> {
+            filter_lines(&lines, max, query)
+                .iter()
+                .map(|l| l.text.as_str())
+                .collect()
+        };
+        assert_eq!(
+            texts(LogLevel::Info, ""),
+            ["connect failed", "Connected to relay", "startup banner"]
+        );
+        assert_eq!(
+            texts(LogLevel::Trace, "CONNECT"),
+            ["connect failed", "Connected to relay"]
+        );
+        assert_eq!(texts(LogLevel::Error, "relay"), Vec::<&str>::new());
+    }
+}
@@ -23,19 +23,21 @@ mod windows_client {
         path::{Path, PathBuf},
         sync::{
             Arc, Mutex,
-            atomic::{AtomicBool, Ordering},
+            atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
         },
         time::{Duration, SystemTime, UNIX_EPOCH},
     };
 
     use arboard::Clipboard;
-    use base64::Engine;
     use clap::Parser;
     use cliprelay_core::{
-        ClipboardEventPlaintext, ControlMessage, DeviceId, EncryptedPayload, Hello,
-        MAX_CLIPBOARD_TEXT_BYTES, MIME_FILE_CHUNK_JSON_B64, MIME_TEXT_PLAIN, PeerInfo, WireMessage,
-        decode_frame, decrypt_clipboard_event, derive_room_key, encode_frame,
-        encrypt_clipboard_event, room_id_from_code, validate_counter,
+        ControlMessage, DeviceId, DeviceIdentity, EncryptedPayload, LatencyProbe,
+        MAX_CLIPBOARD_TEXT_BYTES, MAX_EVENT_LABEL_CHARS, MIME_FILE_CHUNK_JSON_B64,
+        MIME_FILE_CHUNK_SEALED_JSON_B64, MIME_TEXT_PLAIN, MIME_TRANSFER_CANCEL_JSON,
+        MaintenanceNotice, PeerInfo, ProbeLatency, ReconnectNotice, TransferCancel, WireMessage,
+        chunker::{self, FileChunkEnvelope, MAX_TOTAL_CHUNKS},
+        decode_frame, device_id_from, encode_frame, normalize_event_label, room_id_from_code,
+        session::{SessionEvent, SessionState},
     };
     use eframe::egui;
     use futures::{SinkExt, StreamExt};
@@ -51,9 +53,79 @@ mod windows_client {
     use tracing_subscriber::fmt::MakeWriter;
     use url::Url;
     use winrt_notification::{Duration as ToastDuration, Toast};
-    
-    use cliprelay_client::autostart;
-    use cliprelay_client::ui_state::{self, SavedUiState};
+
+    use cliprelay_client::append_mode::{self, AppendSeparator};
+    use cliprelay_client::auth_gate::{self, AuthGate, AuthGatePrefs, AuthOutcome};
+    use cliprelay_client::automation::{self, AutomationCommand, AutomationResponse};
+    use cliprelay_client::autopaste;
+    use cliprelay_client::autostart::{self, AutostartBackend};
+    use cliprelay_client::clip_ring::{ClipRing, RingItem, RingSource};
+    use cliprelay_client::code_preview::{self, Language, TokenKind};
+    use cliprelay_client::config_profiles;
+    use cliprelay_client::crash_report;
+    use cliprelay_client::dedupe::{self, DuplicateSuppression, LastApplied};
+    use cliprelay_client::devices::{self, DeviceRegistry, FilePolicy, KeyCheck, TrustState};
+    use cliprelay_client::diagnose::{self, Check, CheckOutcome, DiagnosticReport};
+    use cliprelay_client::dpapi;
+    use cliprelay_client::extension_policy::{self, ExtensionMode, ExtensionPolicy};
+    use cliprelay_client::file_clipboard;
+    use cliprelay_client::history_retention::{self, HistoryRetention};
+    use cliprelay_client::identity;
+    use cliprelay_client::instance::{self, ForwardError, InstanceRequest};
+    use cliprelay_client::invite;
+    use cliprelay_client::join_link::{self, JoinLink};
+    use cliprelay_client::large_apply;
+    use cliprelay_client::latency::{self, LatencyHistory, LatencySample, ProbeGate, ProbeReading};
+    use cliprelay_client::links::{self, LinkAutoOpen};
+    use cliprelay_client::log_view::{self, LogLevel, LogTail};
+    use cliprelay_client::mark_of_the_web;
+    use cliprelay_client::metered::{
+        self, DeferredSend, DeferredSends, MeteredPolicy, NetworkCost,
+    };
+    use cliprelay_client::network_change::{self, ConnectivityWatch};
+    use cliprelay_client::notification_queue::{
+        self, Admission, NotificationLimit, OverflowPolicy,
+    };
+    use cliprelay_client::onboarding::{self, OnboardingStep};
+    use cliprelay_client::outbox::{self, DeliveryStatus, Outbox, OutgoingText, SendId};
+    use cliprelay_client::oversize_text::{self, OversizedText};
+    use cliprelay_client::pipe_security::{self, OwnerOnly};
+    use cliprelay_client::protocol_trace::{self, FrameSummary, TraceDirection, TraceLog};
+    use cliprelay_client::reconnect::{self, ReconnectPolicy};
+    use cliprelay_client::room_profiles::{self, RoomProfile, RoomProfiles};
+    use cliprelay_client::secrets::{self, SecretCheck, SecretKind, SecretPolicy};
+    use cliprelay_client::self_test::{self, SelfTestReport, SelfTestStep, StepOutcome};
+    use cliprelay_client::send_counters::SendCounters;
+    use cliprelay_client::shell_menu;
+    use cliprelay_client::simulated_peer;
+    use cliprelay_client::snippets::{self, SnippetLibrary};
+    use cliprelay_client::sound::{self, SoundSource};
+    use cliprelay_client::startup::{self, StartupGate};
+    use cliprelay_client::status_widget::{self, WidgetCorner};
+    use cliprelay_client::sync_direction::SyncDirection;
+    use cliprelay_client::text_file;
+    use cliprelay_client::text_split;
+    use cliprelay_client::transfers::{
+        self, CancelSource, CancelledTransfers, TransferDirection, TransferList, TransferOutcome,
+        TransferProgress,
+    };
+    use cliprelay_client::transforms::{self, NewlineStyle, Stage, Transform, TransformKind};
+    use cliprelay_client::tray_badge;
+    use cliprelay_client::tray_clicks::{TrayAction, TrayClick, TrayClickActions};
+    use cliprelay_client::ui_layout::{DOCK_MARGIN_PX, PopupPlacement, ScreenRect, TextSize};
+    use cliprelay_client::ui_state::{self, SavedUiState, WindowPlacement};
+    use cliprelay_client::usage_stats::{self, PeerUsage, UsageKind, UsageStats};
+
+    mod automation_api;
+    mod file_transfer;
+    mod tray;
+
+    use automation_api::{automation_server_task, handle_automation_command};
+    use file_transfer::{
+        FileSendEnd, discard_incoming_transfer, handle_file_chunk_event, handle_transfer_cancel,
+        send_file_v1, send_transfer_cancel,
+    };
+    use tray::{TrayState, load_egui_icon, tray_recent_items};
 
     // ─── Win32 helpers ─────────────────────────────────────────────────────────
 
@@ -63,17 +135,26 @@ mod windows_client {
         s.encode_utf16().chain(std::iter::once(0u16)).collect()
     }
 
+    /// Where a hidden window reappears; mirrors the preference so the OS
+    /// callbacks, which have no access to it, can read it.
+    static POPUP_PLACEMENT: Mutex<PopupPlacement> = Mutex::new(PopupPlacement::Remembered);
+
     /// Show or hide the eframe window directly through Win32, bypassing the
     /// eframe event loop which is dormant when the window is invisible.
+    /// A hidden window is first moved according to [`POPUP_PLACEMENT`].
     ///
     /// # Safety
     /// `hwnd` must be a valid window handle obtained from `FindWindowW`.
     unsafe fn win32_set_window_visible(hwnd: isize, visible: bool) {
         use windows_sys::Win32::UI::WindowsAndMessaging::{
-            SW_HIDE, SW_RESTORE, SetForegroundWindow, ShowWindow,
+            IsWindowVisible, SW_HIDE, SW_RESTORE, SetForegroundWindow, ShowWindow,
         };
         if visible {
             unsafe {
+                if IsWindowVisible(hwnd) == 0 {
+                    let placement = POPUP_PLACEMENT.lock().map(|p| *p).unwrap_or_default();
+                    win32_place_window(hwnd, placement);
+                }
                 ShowWindow(hwnd, SW_RESTORE);
                 SetForegroundWindow(hwnd);
             }
@@ -84,6 +165,235 @@ mod windows_client {
         }
     }
 
+    /// Move the window to `placement` on the monitor under the mouse
+    /// pointer, keeping its size.
+    ///
+    /// # Safety
+    /// `hwnd` must be a valid window handle obtained from `FindWindowW`.
+    unsafe fn win32_place_window(hwnd: isize, placement: PopupPlacement) {
+        use windows_sys::Win32::Foundation::{POINT, RECT};
+        use windows_sys::Win32::Graphics::Gdi::{
+            GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromPoint,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            GetCursorPos, GetWindowRect, SWP_NOACTIVATE, SWP_NOSIZE, SWP_NOZORDER, SetWindowPos,
+        };
+        if placement == PopupPlacement::Remembered {
+            return;
+        }
+        let mut cursor = POINT { x: 0, y: 0 };
+        let mut window = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            if GetCursorPos(&mut cursor) == 0 || GetWindowRect(hwnd, &mut window) == 0 {
+                return;
+            }
+            let monitor = MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST);
+            let mut info: MONITORINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+            if GetMonitorInfoW(monitor, &mut info) == 0 {
+                return;
+            }
+            let work = ScreenRect {
+                left: info.rcWork.left,
+                top: info.rcWork.top,
+                right: info.rcWork.right,
+                bottom: info.rcWork.bottom,
+            };
+            let width = window.right - window.left;
+            let height = window.bottom - window.top;
+            if let Some((x, y)) = placement.origin(work, width, height) {
+                SetWindowPos(
+                    hwnd,
+                    0,
+                    x,
+                    y,
+                    0,
+                    0,
+                    SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+        }
+    }
+
+    /// Title of the status widget's window, for `FindWindowW`.
+    const STATUS_WIDGET_TITLE: &str = "ClipRelay Status";
+
+    /// Move the status widget into `corner` of the work area of the monitor
+    /// it is on, or into the nearest corner when `corner` is `None`.
+    /// Returns the corner it was put in.
+    ///
+    /// # Safety
+    /// `hwnd` must be a valid window handle obtained from `FindWindowW`.
+    unsafe fn win32_snap_widget(hwnd: isize, corner: Option<WidgetCorner>) -> Option<WidgetCorner> {
+        use windows_sys::Win32::Foundation::RECT;
+        use windows_sys::Win32::Graphics::Gdi::{
+            GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
+        };
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            GetWindowRect, SWP_NOACTIVATE, SWP_NOSIZE, SWP_NOZORDER, SetWindowPos,
+        };
+        let mut window = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            if GetWindowRect(hwnd, &mut window) == 0 {
+                return None;
+            }
+            let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+            let mut info: MONITORINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+            if GetMonitorInfoW(monitor, &mut info) == 0 {
+                return None;
+            }
+            let work = ScreenRect {
+                left: info.rcWork.left,
+                top: info.rcWork.top,
+                right: info.rcWork.right,
+                bottom: info.rcWork.bottom,
+            };
+            let corner = corner.unwrap_or_else(|| {
+                WidgetCorner::nearest(
+                    work,
+                    (window.left + window.right) / 2,
+                    (window.top + window.bottom) / 2,
+                )
+            });
+            let (x, y) =
+                corner.origin(work, window.right - window.left, window.bottom - window.top);
+            SetWindowPos(
+                hwnd,
+                0,
+                x,
+                y,
+                0,
+                0,
+                SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+            Some(corner)
+        }
+    }
+
+    /// Every monitor, as (whole monitor, work area).
+    fn win32_monitors() -> Vec<(ScreenRect, ScreenRect)> {
+        use windows_sys::Win32::Foundation::{BOOL, LPARAM, RECT};
+        use windows_sys::Win32::Graphics::Gdi::{
+            EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO,
+        };
+
+        unsafe extern "system" fn collect(
+            monitor: HMONITOR,
+            _hdc: HDC,
+            _clip: *mut RECT,
+            data: LPARAM,
+        ) -> BOOL {
+            let monitors = unsafe { &mut *(data as *mut Vec<(ScreenRect, ScreenRect)>) };
+            let mut info: MONITORINFO = unsafe { std::mem::zeroed() };
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+            if unsafe { GetMonitorInfoW(monitor, &mut info) } != 0 {
+                let rect = |r: RECT| ScreenRect {
+                    left: r.left,
+                    top: r.top,
+                    right: r.right,
+                    bottom: r.bottom,
+                };
+                monitors.push((rect(info.rcMonitor), rect(info.rcWork)));
+            }
+            1
+        }
+
+        let mut monitors: Vec<(ScreenRect, ScreenRect)> = Vec::new();
+        unsafe {
+            EnumDisplayMonitors(
+                0,
+                std::ptr::null(),
+                Some(collect),
+                &mut monitors as *mut _ as LPARAM,
+            );
+        }
+        monitors
+    }
+
+    /// Position and size of a visible, not minimised window.
+    ///
+    /// # Safety
+    /// `hwnd` must be a valid window handle obtained from `FindWindowW`.
+    unsafe fn win32_window_placement(hwnd: isize) -> Option<WindowPlacement> {
+        use windows_sys::Win32::Foundation::RECT;
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            GetWindowRect, IsIconic, IsWindowVisible,
+        };
+        let mut window = RECT {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            if IsWindowVisible(hwnd) == 0
+                || IsIconic(hwnd) != 0
+                || GetWindowRect(hwnd, &mut window) == 0
+            {
+                return None;
+            }
+        }
+        Some(WindowPlacement {
+            x: window.left,
+            y: window.top,
+            w: (window.right - window.left).max(0) as u32,
+            h: (window.bottom - window.top).max(0) as u32,
+        })
+    }
+
+    /// Move and size the window to `placement`, kept inside the work area
+    /// of the monitor holding its centre (the first monitor if none does).
+    ///
+    /// # Safety
+    /// `hwnd` must be a valid window handle obtained from `FindWindowW`.
+    unsafe fn win32_restore_placement(
+        hwnd: isize,
+        placement: WindowPlacement,
+        monitors: &[(ScreenRect, ScreenRect)],
+    ) {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            SWP_NOACTIVATE, SWP_NOZORDER, SetWindowPos,
+        };
+        let cx = placement.x + placement.w as i32 / 2;
+        let cy = placement.y + placement.h as i32 / 2;
+        let Some((_, work)) = monitors
+            .iter()
+            .find(|(m, _)| (m.left..m.right).contains(&cx) && (m.top..m.bottom).contains(&cy))
+            .or(monitors.first())
+        else {
+            return;
+        };
+        let placed = ui_state::clamp_placement_in_rect(
+            placement,
+            MIN_RESTORED_W_PX,
+            MIN_RESTORED_H_PX,
+            DOCK_MARGIN_PX,
+            [work.left, work.top, work.right, work.bottom],
+        );
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                0,
+                placed.x,
+                placed.y,
+                placed.w as i32,
+                placed.h as i32,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+    }
+
     // ─── Embedded icon data ────────────────────────────────────────────────────
 
     static TRAY_ICON_RED_BYTES: &[u8] = include_bytes!("../assets/tray-red.ico");
@@ -100,11 +410,41 @@ mod windows_client {
     const DEFAULT_MAX_FILE_BYTES: u64 = 200 * 1024 * 1024;
     const MAX_INFLIGHT_TRANSFERS: usize = 8;
     const TRANSFER_TIMEOUT_MS: u64 = 600_000;
-    const MAX_TOTAL_CHUNKS: u32 = 4096;
-    const FILE_CHUNK_RAW_BYTES: usize = 64 * 1024;
     const CHUNK_PACING: std::time::Duration = std::time::Duration::from_millis(5);
-    const MAX_NOTIFICATIONS: usize = 20;
-    const MAX_HISTORY_ENTRIES: usize = 200;
+    /// How often a file send checks for socket writes once every chunk is
+    /// queued.
+    const ACK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+    /// Suggested auto-dismiss timeout when the option is first enabled.
+    const DEFAULT_AUTO_DISMISS_SECS: u32 = 30;
+    /// Offline devices listed after the online ones in the Send tab.
+    const MAX_OFFLINE_RECIPIENTS_SHOWN: usize = 4;
+    /// Outgoing `.txt` files older than this are removed when a new one is
+    /// written; by then their transfer has long finished.
+    const OUTGOING_TEXT_FILE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+    /// Smallest main window a saved placement is restored to (physical
+    /// pixels).
+    const MIN_RESTORED_W_PX: u32 = 400;
+    const MIN_RESTORED_H_PX: u32 = 300;
+    /// How often the monitor arrangement and the main window's placement
+    /// are sampled.
+    const PLACEMENT_POLL_MS: u64 = 2_000;
+
+    /// Tray menu item IDs.  Snippet items are `snippet:<index>` and room
+    /// profile items `profile:<index>`.
+    const TRAY_MENU_QUIT_ID: &str = "quit";
+    const TRAY_MENU_CHANGE_ROOM_ID: &str = "change_room";
+    const TRAY_MENU_LEAVE_ROOM_ID: &str = "leave_room";
+    const TRAY_MENU_COPY_INVITE_ID: &str = "copy_invite";
+    const TRAY_MENU_RETRY_ID: &str = "retry_now";
+    const TRAY_MENU_RECONNECT_NOW_ID: &str = "reconnect_now";
+    const TRAY_MENU_APPLY_ALL_ID: &str = "apply_all";
+    const TRAY_MENU_DISMISS_ALL_ID: &str = "dismiss_all";
+    const TRAY_MENU_RESUME_ID: &str = "resume_sync";
+    const TRAY_MENU_SNIPPET_PREFIX: &str = "snippet:";
+    const TRAY_MENU_PROFILE_PREFIX: &str = "profile:";
+    const TRAY_MENU_HISTORY_PREFIX: &str = "history:";
+    /// Non-favorite history entries listed in the tray menu.
+    const TRAY_RECENT_ENTRIES: usize = 10;
 
     const DEFAULT_HOTKEY_LABEL: &str = "Ctrl+Alt+C";
     const HOTKEY_OPTIONS: &[&str] = &[
@@ -114,6 +454,9 @@ mod windows_client {
         "Ctrl+Shift+V",
         "Disabled",
     ];
+    const DEFAULT_QUICK_PICK_HOTKEY_LABEL: &str = "Ctrl+Alt+H";
+    const QUICK_PICK_HOTKEY_OPTIONS: &[&str] = &["Ctrl+Alt+H", "Ctrl+Shift+H", "Disabled"];
+    const PANIC_HOTKEY_OPTIONS: &[&str] = &["Disabled", "Ctrl+Alt+X", "Ctrl+Shift+X"];
 
     // ─── CLI args ──────────────────────────────────────────────────────────────
 
@@ -137,6 +480,52 @@ mod windows_client {
         /// and otherwise exit.
         #[arg(long, default_value_t = false)]
         background: bool,
+        /// Configuration profile (own server, room, history and settings).
+        /// Defaults to the profile picked last in the setup screen.
+        #[arg(long)]
+        profile: Option<String>,
+        /// Send these files to the room, then exit.  Handed to the running
+        /// client if there is one; otherwise this process joins the saved
+        /// room just long enough to send.  Also used by the Explorer "Send
+        /// with ClipRelay" entry and the SendTo shortcut (which appends the
+        /// selected files after the flag).
+        #[arg(long = "send-file", num_args = 1..)]
+        send_file: Vec<PathBuf>,
+        /// Send this text to the room, then exit, like `--send-file`.  `-`
+        /// reads the text from standard input.
+        #[arg(long = "send-text", conflicts_with = "send_file")]
+        send_text: Option<String>,
+        /// Offer to join the room of a `cliprelay://join?…` link.  Handed
+        /// to the running client if there is one.
+        #[arg(long = "join-link")]
+        join_link: Option<String>,
+        /// Save this room code (with `--apply-and-exit`).
+        #[arg(long = "set-room-code", requires = "apply_and_exit")]
+        set_room_code: Option<String>,
+        /// Save this relay URL (with `--apply-and-exit`).
+        #[arg(long = "set-server-url", requires = "apply_and_exit")]
+        set_server_url: Option<String>,
+        /// Check and save the `--set-room-code` / `--set-server-url` values,
+        /// tell the running client to reconnect with them, then exit.
+        #[arg(
+            long = "apply-and-exit",
+            default_value_t = false,
+            conflicts_with_all = ["send_file", "send_text", "join_link"]
+        )]
+        apply_and_exit: bool,
+        /// Check the saved config, the connection to the relay, hotkeys, the
+        /// clipboard and ClipRelay's folders, write a report for support,
+        /// then exit.
+        #[arg(
+            long = "diagnose",
+            default_value_t = false,
+            conflicts_with_all = ["send_file", "send_text", "join_link", "apply_and_exit"]
+        )]
+        diagnose: bool,
+        /// Development aid: also run a simulated second device in the room
+        /// that echoes received text and sends sample items.
+        #[arg(long = "simulate-peer", hide = true, default_value_t = false)]
+        simulate_peer: bool,
     }
 
     // ─── Config types ──────────────────────────────────────────────────────────
@@ -153,13 +542,28 @@ mod windows_client {
         initial_counter: u64,
     }
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[derive(Debug, Clone)]
     struct SavedClientConfig {
         server_url: String,
         room_code: String,
         device_name: String,
-        #[serde(default)]
-        last_counter: u64,
+    }
+
+    /// On-disk form of [`SavedClientConfig`], plus the send counters.  The
+    /// room code is effectively the room's encryption passphrase, so it is
+    /// stored sealed with DPAPI.  A plaintext `room_code` is still accepted
+    /// (configs written by older versions, or where sealing failed) and is
+    /// sealed on the next load.
+    #[derive(Serialize, Deserialize)]
+    struct StoredClientConfig {
+        server_url: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        room_code: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        room_code_protected: Option<String>,
+        device_name: String,
+        #[serde(flatten)]
+        counters: SendCounters,
     }
 
     // ─── Event / command enums ─────────────────────────────────────────────────
@@ -168,29 +572,137 @@ mod windows_client {
     enum UiEvent {
         ConnectionStatus(String),
         Peers(Vec<PeerInfo>),
-        LastSent(u64),
+        /// Text or a file went out; `kind` and `bytes` (its size) are for
+        /// the usage statistics.
+        LastSent {
+            ts_unix_ms: u64,
+            kind: UsageKind,
+            bytes: u64,
+        },
         LastReceived(u64),
         RoomKeyReady(bool),
+        /// `signer_key` is the identity key that validly signed the event
+        /// (every chunk, for files), if any.
         IncomingClipboard {
             sender_device_id: String,
             text: String,
             content_hash: [u8; 32],
+            signer_key: Option<String>,
+            /// The sender pinned it.
+            pinned: bool,
+            /// The sender's label, normalized.
+            label: Option<String>,
+            /// The sender's language hint (see [`code_preview`]).
+            language_hint: Option<String>,
         },
         IncomingFile {
             sender_device_id: String,
             file_name: String,
             temp_path: PathBuf,
             size_bytes: u64,
+            signer_key: Option<String>,
+            /// SHA-256 of the reassembled file.
+            sha256: [u8; 32],
+        },
+        /// An incoming file was refused by the extension policy at its
+        /// first chunk; nothing of it was kept.
+        FileBlocked {
+            sender_device_id: String,
+            file_name: String,
+            size_bytes: u64,
+        },
+        /// Incoming text matched the last applied text within the
+        /// duplicate-suppression window and was skipped.
+        DuplicateSkipped {
+            sender_device_id: String,
+            preview: String,
         },
         RuntimeError(String),
+        /// The relay announced maintenance and will disconnect us.
+        RelayMaintenance(MaintenanceNotice),
+        SelfTestFinished(SelfTestReport),
+        /// Cost of the current Internet connection changed.
+        NetworkCost(NetworkCost),
+        /// Number of sends held back by the metered-connection policy.
+        DeferredCount(usize),
+        /// Sends waiting for the room key after connecting.
+        AwaitingKeyCount(usize),
+        /// Delivery of the sends with this id changed; shown on their
+        /// history entry.
+        SendStatus {
+            id: SendId,
+            status: DeliveryStatus,
+        },
+        /// A file transfer in either direction advanced (or started).
+        TransferProgress(TransferProgress),
+        TransferEnded {
+            owner_device_id: String,
+            transfer_id: String,
+            outcome: TransferOutcome,
+        },
+        /// The reconnect policy's attempt limit was reached after this many
+        /// consecutive failures; the runtime waits for Retry Now.
+        ReconnectGaveUp(u32),
+        /// Metadata of a frame or decrypted event, sent only while the
+        /// protocol trace is enabled.
+        ProtocolFrame(FrameSummary),
+        /// Round trip of a keepalive probe to the relay.
+        Latency(LatencySample),
+        /// A relay-stamped probe frame from `device_id` (this PC included)
+        /// arrived.
+        ProbeLatency {
+            device_id: DeviceId,
+            latency: ProbeLatency,
+        },
+        /// A request from the local automation API.  Answered from UI state
+        /// so that sends go through the same history/validation path as the
+        /// Send tab.
+        AutomationRequest {
+            command: AutomationCommand,
+            reply: tokio::sync::oneshot::Sender<AutomationResponse>,
+        },
+        /// A request from another `cliprelay-client` process (Explorer
+        /// entries, `--send-text`/`--send-file`, `cliprelay://` links).
+        /// Answered once the send is queued or the prompt is shown.
+        InstanceRequest {
+            request: InstanceRequest,
+            reply: tokio::sync::oneshot::Sender<Result<(), String>>,
+        },
     }
 
     #[derive(Debug)]
     enum RuntimeCommand {
         SetAutoApply(bool),
         MarkApplied([u8; 32]),
-        SendText(String),
-        SendFile(PathBuf),
+        SetDuplicateSuppression(DuplicateSuppression),
+        SetSyncDirection(SyncDirection),
+        /// Temporarily stop sending and receiving without leaving the room.
+        SetSyncPaused(bool),
+        SetProtocolTrace(bool),
+        SetExtensionPolicy(ExtensionPolicy),
+        SetMeteredPolicy(MeteredPolicy),
+        SetReconnectPolicy(ReconnectPolicy),
+        SetOversizedText(OversizedText),
+        /// Drop an incoming transfer and ask its sender to stop.  Outgoing
+        /// transfers are stopped through [`SharedRuntimeState`] directly,
+        /// since the command handler is busy while one is sent.
+        CancelTransfer {
+            owner_device_id: String,
+            transfer_id: String,
+        },
+        RunSelfTest,
+        /// The id links the send to its history entry (see [`outbox`]).
+        SendText(SendId, OutgoingText),
+        SendFile(SendId, PathBuf),
+    }
+
+    impl From<(SendId, DeferredSend)> for RuntimeCommand {
+        fn from((id, send): (SendId, DeferredSend)) -> Self {
+            match send {
+                DeferredSend::Text(text) => RuntimeCommand::SendText(id, text),
+                DeferredSend::File(path) => RuntimeCommand::SendFile(id, path),
+            }
+        }
     }
 
     #[derive(Debug, Clone)]
@@ -198,17 +710,67 @@ mod windows_client {
         Text {
             sender_device_id: String,
             preview: String,
+            /// Web links found in the text (see [`links`]).
+            links: Vec<String>,
             full_text: String,
             content_hash: [u8; 32],
+            /// The sender's label, if any.
+            label: Option<String>,
+            /// Set for code: the preview is highlighted.  Apply always uses
+            /// `full_text` as received.
+            language: Option<Language>,
         },
         File {
             sender_device_id: String,
             preview: String,
             file_name: String,
             temp_path: PathBuf,
+            /// SHA-256 of the file, hex.
+            sha256: String,
         },
     }
 
+    /// A queued notification plus its multi-select state in the
+    /// Notifications tab.
+    #[derive(Debug, Clone)]
+    struct PendingNotification {
+        notification: Notification,
+        selected: bool,
+        /// Set once the Notifications tab has been shown with this entry;
+        /// unseen entries are counted on the tray badge.
+        seen: bool,
+        /// When the entry was first on screen; starts the auto-dismiss
+        /// countdown.
+        shown_unix_ms: Option<u64>,
+        /// "Keep Open" was pressed, so it is never auto-dismissed.
+        keep_open: bool,
+        /// Pinned by its sender: listed first, never auto-dismissed or
+        /// evicted, and kept after Apply until dismissed or unpinned.
+        pinned: bool,
+    }
+
+    impl PendingNotification {
+        /// Seconds left before a shown text entry is dismissed by the
+        /// `auto_dismiss_secs` setting, or `None` when it stays.  Files always
+        /// wait for Save or Dismiss, since dismissing deletes them.
+        fn auto_dismiss_remaining(
+            &self,
+            auto_dismiss_secs: Option<u32>,
+            now_ms: u64,
+        ) -> Option<u64> {
+            let secs = auto_dismiss_secs?;
+            let shown = self.shown_unix_ms?;
+            if self.keep_open
+                || self.pinned
+                || !matches!(self.notification, Notification::Text { .. })
+            {
+                return None;
+            }
+            let deadline = shown.saturating_add(u64::from(secs) * 1000);
+            Some(deadline.saturating_sub(now_ms).div_ceil(1000))
+        }
+    }
+
     // ─── Activity history ──────────────────────────────────────────────────────
 
     #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -224,18 +786,52 @@ mod windows_client {
         peer_device_id: String,
         kind: String,
         summary: String,
+        /// First web link of a received text, for the Open Link action.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        link: Option<String>,
+        /// Delivery state of a sent entry.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delivery: Option<DeliveryStatus>,
+        /// Matches [`UiEvent::SendStatus`] updates; only meaningful within
+        /// one run, so not saved.
+        #[serde(skip)]
+        send_id: Option<SendId>,
+        /// Shown first and kept by the entry and age limits until unpinned.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pinned: bool,
+        /// Listed under Favorites, here and in the tray, and kept by the
+        /// entry and age limits until unmarked.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        favorite: bool,
+        /// How many times in a row the same item was recorded.
+        #[serde(default = "single_repeat", skip_serializing_if = "is_single_repeat")]
+        repeat_count: u32,
+        /// SHA-256 of the text, to recognise repeats.  Kept in memory only:
+        /// a stored hash would give away short texts such as passwords.
+        #[serde(skip)]
+        content_hash: Option<[u8; 32]>,
+        /// SHA-256 (hex) of a received file, shown with a Copy Checksum
+        /// action.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        checksum: Option<String>,
+        /// Ticked in the history browser for Delete Selected.
+        #[serde(skip)]
+        selected: bool,
+    }
+
+    fn single_repeat() -> u32 {
+        1
+    }
+
+    fn is_single_repeat(count: &u32) -> bool {
+        *count <= 1
     }
 
     fn history_path() -> PathBuf {
-        let base = std::env::var_os("LOCALAPPDATA")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let dir = base.join("ClipRelay");
-        let _ = std::fs::create_dir_all(&dir);
-        dir.join("history.json")
+        config_profiles::active_dir().join("history.json")
     }
 
-    fn load_history() -> VecDeque<ActivityEntry> {
+    fn load_history(retention: HistoryRetention) -> VecDeque<ActivityEntry> {
         let path = history_path();
         let Ok(data) = std::fs::read_to_string(&path) else {
             return VecDeque::new();
@@ -244,8 +840,286 @@ mod windows_client {
             return VecDeque::new();
         };
         entries.sort_by(|a, b| b.ts_unix_ms.cmp(&a.ts_unix_ms));
-        entries.truncate(MAX_HISTORY_ENTRIES);
-        VecDeque::from(entries)
+        // Oldest first, so runs of repeats in older files are folded too.
+        let mut history = VecDeque::with_capacity(entries.len());
+        for mut entry in entries.into_iter().rev() {
+            entry.delivery = entry.delivery.map(DeliveryStatus::after_restart);
+            insert_history_entry(&mut history, entry);
+        }
+        enforce_history_retention(&mut history, retention);
+        history
+    }
+
+    impl ActivityEntry {
+        /// Drop the preview and link, keeping time, direction, device and
+        /// kind.
+        fn redact(&mut self) {
+            self.summary = history_retention::REDACTED_SUMMARY.to_owned();
+            self.link = None;
+            self.checksum = None;
+        }
+
+        /// Whether `newer` records the same item again: same direction,
+        /// device and kind, and the same content — by hash when both have
+        /// one, otherwise by preview (entries loaded from disk).  Redacted
+        /// previews say nothing about the content, so they never match.
+        fn is_repeated_by(&self, newer: &ActivityEntry) -> bool {
+            self.direction == newer.direction
+                && self.peer_device_id == newer.peer_device_id
+                && self.kind == newer.kind
+                && match (self.content_hash, newer.content_hash) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => {
+                        self.summary == newer.summary
+                            && self.summary != history_retention::REDACTED_SUMMARY
+                    }
+                }
+        }
+
+        /// Fold `newer`, a repeat of this entry, into it: the row moves to
+        /// the newer time and follows the newer send's delivery.
+        fn absorb(&mut self, newer: ActivityEntry) {
+            self.repeat_count = self.repeat_count.saturating_add(newer.repeat_count);
+            self.ts_unix_ms = newer.ts_unix_ms;
+            self.summary = newer.summary;
+            self.link = newer.link.or(self.link.take());
+            self.delivery = newer.delivery;
+            self.send_id = newer.send_id;
+            self.pinned |= newer.pinned;
+            self.favorite |= newer.favorite;
+            self.content_hash = newer.content_hash.or(self.content_hash);
+            self.checksum = newer.checksum.or(self.checksum.take());
+        }
+    }
+
+    /// Put `entry` at the front of `history`, or fold it into the front
+    /// entry when it repeats it.
+    fn insert_history_entry(history: &mut VecDeque<ActivityEntry>, entry: ActivityEntry) {
+        match history.front_mut() {
+            Some(front) if front.is_repeated_by(&entry) => front.absorb(entry),
+            _ => history.push_front(entry),
+        }
+    }
+
+    /// Apply the entry limit, age limit and metadata-only mode to
+    /// `history` (newest first).  Pinned entries and favorites are exempt
+    /// from the limits but not from metadata-only mode.  Returns `true`
+    /// when anything changed.
+    fn enforce_history_retention(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+    ) -> bool {
+        let exempt = |e: &ActivityEntry| e.pinned || e.favorite;
+        let keep = retention.keep_count(
+            history.iter().filter(|e| !exempt(e)).map(|e| e.ts_unix_ms),
+            now_unix_ms(),
+        );
+        let before = history.len();
+        let mut limited = 0;
+        history.retain(|e| {
+            limited += usize::from(!exempt(e));
+            exempt(e) || limited <= keep
+        });
+        let mut changed = history.len() < before;
+        if !retention.store_content {
+            for entry in history.iter_mut().filter(|e| {
+                e.summary != history_retention::REDACTED_SUMMARY
+                    || e.link.is_some()
+                    || e.checksum.is_some()
+            }) {
+                entry.redact();
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Add `entry` to the front of `history` (or count it on the front
+    /// entry it repeats), apply the retention settings and save.
+    fn push_history(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        entry: ActivityEntry,
+    ) {
+        insert_history_entry(history, entry);
+        enforce_history_retention(history, retention);
+        save_history(history);
+    }
+
+    /// Record something sent to the room.  Returns the id to send it
+    /// with, so that its delivery status shows on the entry.
+    fn push_sent_history(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        kind: &str,
+        summary: String,
+        content_hash: Option<[u8; 32]>,
+    ) -> SendId {
+        let id = outbox::next_send_id();
+        push_history(
+            history,
+            retention,
+            ActivityEntry {
+                ts_unix_ms: now_unix_ms(),
+                direction: ActivityDirection::Sent,
+                peer_device_id: "room".to_owned(),
+                kind: kind.to_owned(),
+                summary,
+                link: None,
+                delivery: Some(DeliveryStatus::Pending),
+                send_id: Some(id),
+                pinned: false,
+                favorite: false,
+                repeat_count: 1,
+                content_hash,
+                checksum: None,
+                selected: false,
+            },
+        );
+        id
+    }
+
+    /// [`push_sent_history`] for a text send: the summary shows its label,
+    /// and the entry is pinned when the send is.
+    fn push_sent_text_history(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        send: &OutgoingText,
+        summary: String,
+    ) -> SendId {
+        let summary = labelled(send.label.as_deref(), &summary);
+        let hash = sha256_bytes(send.text.as_bytes());
+        let id = push_sent_history(history, retention, "text", summary, Some(hash));
+        if send.pinned
+            && let Some(entry) = history.iter_mut().find(|e| e.send_id == Some(id))
+        {
+            entry.pinned = true;
+            save_history(history);
+        }
+        id
+    }
+
+    /// Toggle the favorite mark of `history[idx]` and save.  Unmarking may
+    /// put the entry past the limits.
+    fn toggle_history_favorite(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        idx: usize,
+    ) {
+        let Some(entry) = history.get_mut(idx) else {
+            return;
+        };
+        entry.favorite = !entry.favorite;
+        enforce_history_retention(history, retention);
+        save_history(history);
+    }
+
+    /// Remove the ticked history entries and the clip ring items holding
+    /// their text, then rewrite `history.json`.  Returns how many entries
+    /// were removed.
+    fn delete_selected_history(
+        history: &mut VecDeque<ActivityEntry>,
+        clip_ring: &mut ClipRing,
+    ) -> usize {
+        let hashes: Vec<[u8; 32]> = history
+            .iter()
+            .filter(|e| e.selected)
+            .filter_map(|e| e.content_hash)
+            .collect();
+        let before = history.len();
+        history.retain(|e| !e.selected);
+        clip_ring.retain(|item| !hashes.contains(&sha256_bytes(item.text.as_bytes())));
+        save_history(history);
+        before - history.len()
+    }
+
+    /// Empty the history and the clip ring.  The history file is deleted
+    /// and an empty one written in its place.
+    fn clear_all_history(
+        history: &mut VecDeque<ActivityEntry>,
+        clip_ring: &mut ClipRing,
+    ) -> std::io::Result<()> {
+        history.clear();
+        clip_ring.clear();
+        let path = history_path();
+        let deleted = history_retention::delete_file(&path.with_extension("json.tmp"))
+            .and_then(|()| history_retention::delete_file(&path));
+        save_history(history);
+        deleted
+    }
+
+    /// `text` headed by an item's label, when it has one.
+    fn labelled(label: Option<&str>, text: &str) -> String {
+        match label {
+            Some(label) => format!("{label} \u{2014} {text}"),
+            None => text.to_owned(),
+        }
+    }
+
+    /// Show a delivery update on the history entry of send `id`.
+    fn set_delivery_status(
+        history: &mut VecDeque<ActivityEntry>,
+        id: SendId,
+        status: DeliveryStatus,
+    ) {
+        let Some(entry) = history.iter_mut().find(|e| e.send_id == Some(id)) else {
+            return;
+        };
+        if entry.delivery != Some(status) {
+            entry.delivery = Some(status);
+            save_history(history);
+        }
+    }
+
+    fn outgoing_text_dir() -> PathBuf {
+        std::env::temp_dir().join("ClipRelay").join("outgoing")
+    }
+
+    /// Write oversized send-box text to `%TEMP%\ClipRelay\outgoing` so it can
+    /// be sent as a file transfer.
+    fn write_outgoing_text_file(text: &str) -> std::io::Result<PathBuf> {
+        let dir = outgoing_text_dir();
+        std::fs::create_dir_all(&dir)?;
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let stale = entry
+                    .metadata()
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age > OUTGOING_TEXT_FILE_MAX_AGE);
+                if stale {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        let name = oversize_text::file_name(text, &local_file_stamp());
+        let mut path = dir.join(&name);
+        let mut n = 2;
+        while path.exists() {
+            path = dir.join(format!("{}-{n}.txt", name.trim_end_matches(".txt")));
+            n += 1;
+        }
+        std::fs::write(&path, text.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Send a snippet to the room.  History records only the snippet's
+    /// name, since snippets often hold passwords.
+    fn send_snippet(
+        snippet: &snippets::Snippet,
+        runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+        history: &mut VecDeque<ActivityEntry>,
+        history_retention: HistoryRetention,
+    ) {
+        let id = push_sent_history(
+            history,
+            history_retention,
+            "text",
+            format!("snippet \"{}\"", snippet.name),
+            Some(sha256_bytes(snippet.text.as_bytes())),
+        );
+        let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(id, snippet.text.as_str().into()));
     }
 
     fn save_history(history: &VecDeque<ActivityEntry>) {
@@ -253,8 +1127,11 @@ mod windows_client {
         const BACKOFF_BASE_MS: u64 = 50;
         let path = history_path();
         let tmp = path.with_extension("json.tmp");
-        let entries: Vec<ActivityEntry> =
-            history.iter().take(MAX_HISTORY_ENTRIES).cloned().collect();
+        let entries: Vec<ActivityEntry> = history
+            .iter()
+            .take(history_retention::MAX_ENTRIES_LIMIT)
+            .cloned()
+            .collect();
         let Ok(payload) = serde_json::to_string_pretty(&entries) else {
             return;
         };
@@ -287,204 +1164,106 @@ mod windows_client {
 
     #[derive(Debug, Clone)]
     struct SharedRuntimeState {
-        room_key: Arc<Mutex<Option<[u8; 32]>>>,
-        last_applied_hash: Arc<Mutex<Option<[u8; 32]>>>,
+        /// Protocol state of the current connection: room key, peer list,
+        /// send counter and replay window (see [`SessionState`]).  `None`
+        /// between connections.
+        session: Arc<Mutex<Option<SessionState>>>,
+        last_applied: Arc<Mutex<Option<LastApplied>>>,
         auto_apply: Arc<Mutex<bool>>,
+        duplicate_suppression: Arc<Mutex<DuplicateSuppression>>,
+        sync_direction: Arc<Mutex<SyncDirection>>,
+        sync_paused: Arc<Mutex<bool>>,
+        protocol_trace: Arc<Mutex<bool>>,
+        extension_policy: Arc<Mutex<ExtensionPolicy>>,
+        network_cost: Arc<Mutex<NetworkCost>>,
+        metered_policy: Arc<Mutex<MeteredPolicy>>,
+        /// Sends held back while metered; they outlive reconnects.
+        deferred_sends: Arc<Mutex<DeferredSends>>,
+        /// Sends issued after connecting but before the room key was
+        /// derived; sent as soon as it is.
+        awaiting_key: Arc<Mutex<DeferredSends>>,
+        /// Sends in flight on the session, and those to resend after a
+        /// dropped connection.
+        outbox: Arc<Mutex<Outbox>>,
+        /// File transfers cancelled here or by a peer; shared with the UI,
+        /// whose Cancel takes effect between two chunks.
+        cancelled_transfers: Arc<Mutex<CancelledTransfers>>,
+        /// Wakes the command handler to release deferred sends, those
+        /// waiting for the room key and retries.
+        deferred_ready: Arc<tokio::sync::Notify>,
+        /// Read by the reconnect loop between sessions.
+        reconnect_policy: Arc<Mutex<ReconnectPolicy>>,
+        /// Whether text over the message limit is sent as a file.
+        oversized_text: Arc<Mutex<OversizedText>>,
+        /// Cuts the wait before the next reconnect attempt short; shared
+        /// with the UI's Reconnect Now.
+        reconnect_now: Arc<tokio::sync::Notify>,
+        /// Set by the receive task when the relay asks for a reconnect; the
+        /// command handler then lets the outbox drain and ends the session.
+        reconnect_notice: Arc<Mutex<Option<ReconnectNotice>>>,
+        reconnect_soon: Arc<tokio::sync::Notify>,
+        /// Signs outgoing events; see [`identity`].
+        identity: Arc<DeviceIdentity>,
+        /// Completion slot for a running self-test: the receive task hands
+        /// the decrypted probe (or the decrypt error) to the test task.
+        self_test_probe: Arc<Mutex<Option<tokio::sync::oneshot::Sender<Result<String, String>>>>>,
+        /// Whether the relay answers probe frames; reset for each session.
+        probe_gate: Arc<Mutex<ProbeGate>>,
     }
 
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    enum TrayStatus {
-        Red,
-        Amber,
-        Green,
-    }
-
-    // ─── Tray icon helpers ─────────────────────────────────────────────────────
-
-    fn load_tray_icon_from_ico(bytes: &[u8]) -> Option<tray_icon::Icon> {
-        let img = image::load_from_memory(bytes).ok()?.to_rgba8();
-        tray_icon::Icon::from_rgba(img.to_vec(), img.width(), img.height()).ok()
+    impl SharedRuntimeState {
+        /// Fresh state for a session, seeded from the saved preferences.
+        fn new(prefs: &SavedUiState, identity: Arc<DeviceIdentity>) -> Self {
+            Self {
+                session: Arc::new(Mutex::new(None)),
+                last_applied: Arc::new(Mutex::new(None)),
+                auto_apply: Arc::new(Mutex::new(false)),
+                duplicate_suppression: Arc::new(Mutex::new(prefs.duplicate_suppression)),
+                sync_direction: Arc::new(Mutex::new(prefs.sync_direction)),
+                sync_paused: Arc::new(Mutex::new(false)),
+                protocol_trace: Arc::new(Mutex::new(prefs.protocol_trace_enabled)),
+                extension_policy: Arc::new(Mutex::new(prefs.incoming_file_extensions.clone())),
+                network_cost: Arc::new(Mutex::new(NetworkCost::Unknown)),
+                metered_policy: Arc::new(Mutex::new(metered_policy(prefs, false))),
+                deferred_sends: Arc::new(Mutex::new(DeferredSends::default())),
+                awaiting_key: Arc::new(Mutex::new(DeferredSends::default())),
+                outbox: Arc::new(Mutex::new(Outbox::default())),
+                cancelled_transfers: Arc::new(Mutex::new(CancelledTransfers::default())),
+                deferred_ready: Arc::new(tokio::sync::Notify::new()),
+                reconnect_policy: Arc::new(Mutex::new(prefs.reconnect)),
+                oversized_text: Arc::new(Mutex::new(prefs.oversized_text)),
+                reconnect_now: Arc::new(tokio::sync::Notify::new()),
+                reconnect_notice: Arc::new(Mutex::new(None)),
+                reconnect_soon: Arc::new(tokio::sync::Notify::new()),
+                identity,
+                self_test_probe: Arc::new(Mutex::new(None)),
+                probe_gate: Arc::new(Mutex::new(ProbeGate::default())),
+            }
+        }
     }
 
-    fn load_egui_icon(bytes: &[u8]) -> Option<egui::IconData> {
-        let img = image::load_from_memory(bytes).ok()?.to_rgba8();
-        Some(egui::IconData {
-            rgba: img.to_vec(),
-            width: img.width(),
-            height: img.height(),
-        })
+    /// UI mirror of the runtime's metered-connection state.
+    #[derive(Debug, Clone, Copy, Default)]
+    struct MeteredStatus {
+        cost: NetworkCost,
+        deferred: usize,
+        /// "Send anyway" for the current metered connection.
+        override_active: bool,
     }
 
-    struct TrayState {
-        tray_icon: tray_icon::TrayIcon,
-        current_status: TrayStatus,
-        icon_red: tray_icon::Icon,
-        icon_amber: tray_icon::Icon,
-        icon_green: tray_icon::Icon,
-    }
-
-    impl TrayState {
-        /// Create the system tray icon and register OS-level event handlers.
-        ///
-        /// `quit_flag` is set `true` when the user clicks "Quit" in the tray
-        /// context menu (shown on right-click).  `toggle_flag` is set `true`
-        /// on a left-click (button-up) or double-click of the tray icon
-        /// itself.  Both handlers call `ctx.request_repaint()` to wake the
-        /// eframe event loop even when the window is hidden (which suppresses
-        /// normal repaint timers).
-        ///
-        /// `menu_on_left_click` is explicitly set to `false` so that the
-        /// context menu is only shown on right-click (standard Windows
-        /// behaviour).  The tray-icon crate defaults to `true`, which causes
-        /// `TrackPopupMenu` to fire on every left-click — blocking the event
-        /// loop and preventing the toggle handler from working.
-        ///
-        /// `eframe_hwnd` is the Win32 HWND of the main eframe window,
-        /// obtained via `FindWindowW`.  The toggle callback uses it to call
-        /// `ShowWindow`/`SetForegroundWindow` directly, because eframe does
-        /// **not** call `update()` (and therefore never processes toggle
-        /// flags) while the window is invisible.
-        fn new(
-            ctx: &egui::Context,
-            quit_flag: Arc<AtomicBool>,
-            toggle_flag: Arc<AtomicBool>,
-            eframe_hwnd: isize,
-            shared_visible: Arc<AtomicBool>,
-        ) -> Option<Self> {
-            use tray_icon::menu::{Menu, MenuEvent, MenuItem};
-            use tray_icon::{TrayIconBuilder, TrayIconEvent};
-
-            let icon_red = load_tray_icon_from_ico(TRAY_ICON_RED_BYTES)?;
-            let icon_amber = load_tray_icon_from_ico(TRAY_ICON_AMBER_BYTES)?;
-            let icon_green = load_tray_icon_from_ico(TRAY_ICON_GREEN_BYTES)?;
-
-            let quit_item = MenuItem::new("Quit", true, None);
-            let quit_id = quit_item.id().clone();
-
-            let menu = Menu::new();
-            let _ = menu.append(&quit_item);
-
-            info!("TrayState::new — building tray icon (menu_on_left_click=false)");
-            let tray_icon = match TrayIconBuilder::new()
-                .with_menu(Box::new(menu))
-                .with_menu_on_left_click(false)
-                .with_icon(icon_amber.clone())
-                .with_tooltip("ClipRelay | connecting")
-                .build()
-            {
-                Ok(t) => {
-                    info!("TrayState::new — tray icon built successfully");
-                    t
-                }
-                Err(err) => {
-                    error!("TrayState::new — tray icon build FAILED: {err}");
-                    return None;
-                }
-            };
-
-            // OS-level callbacks — fire from the Windows message handler so
-            // they work even when the eframe event loop is sleeping.
-            let ctx_menu = ctx.clone();
-            let quit_id_dbg = quit_id.clone();
-            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
-                // Log every menu event, even non-quit ones.
-                let is_quit = event.id == quit_id;
-                debug!(
-                    menu_event_id = ?event.id,
-                    quit_id = ?quit_id_dbg,
-                    is_quit,
-                    "MenuEvent received"
-                );
-                trace!(
-                    "[tray] MenuEvent received: id={:?}, quit_id={:?}, is_quit={}",
-                    event.id, quit_id_dbg, is_quit
-                );
-                if is_quit {
-                    quit_flag.store(true, Ordering::SeqCst);
-                    ctx_menu.request_repaint();
-                    debug!("quit_flag stored, repaint requested");
-                    trace!("[tray] quit_flag stored, repaint requested");
-
-                    // Fallback: if the eframe event loop is dormant (hidden
-                    // window), `request_repaint()` may never be honoured.
-                    // Give the event loop a short grace period to process the
-                    // quit flag cleanly, then force-exit.
-                    std::thread::spawn(|| {
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-                        trace!(
-                            "[tray] quit fallback: event loop did not exit in time, forcing exit"
-                        );
-                        std::process::exit(0);
-                    });
-                }
-            }));
-
-            let ctx_tray = ctx.clone();
-            TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
-                // Log EVERY tray icon event for debugging.
-                debug!(tray_event = ?event, "TrayIconEvent received");
-                trace!("[tray] TrayIconEvent: {event:?}");
-
-                // Only respond to left-button Up and DoubleClick events.
-                // Ignoring Down events prevents double-toggling when the
-                // Down and Up messages are dispatched in separate event-loop
-                // pump cycles.
-                let should_toggle = matches!(
-                    &event,
-                    TrayIconEvent::Click {
-                        button: tray_icon::MouseButton::Left,
-                        button_state: tray_icon::MouseButtonState::Up,
-                        ..
-                    } | TrayIconEvent::DoubleClick {
-                        button: tray_icon::MouseButton::Left,
-                        ..
-                    }
-                );
-                debug!(should_toggle, "tray toggle decision");
-                trace!("[tray] should_toggle={should_toggle}");
-                if should_toggle {
-                    toggle_flag.store(true, Ordering::SeqCst);
-                    ctx_tray.request_repaint();
-                    debug!("toggle_flag stored, repaint requested");
-                    trace!("[tray] toggle_flag stored, repaint requested");
-
-                    // Direct Win32 show/hide — bypasses the dormant eframe
-                    // event loop that never calls update() for hidden windows.
-                    if eframe_hwnd != 0 {
-                        let was_visible = shared_visible.load(Ordering::SeqCst);
-                        let new_visible = !was_visible;
-                        shared_visible.store(new_visible, Ordering::SeqCst);
-                        unsafe { win32_set_window_visible(eframe_hwnd, new_visible) };
-                        trace!("[tray] Win32 ShowWindow: visible={new_visible}");
-                    }
-                }
-            }));
-
-            Some(Self {
-                tray_icon,
-                current_status: TrayStatus::Amber,
-                icon_red,
-                icon_amber,
-                icon_green,
-            })
-        }
-
-        fn set_status(&mut self, status: TrayStatus) {
-            if self.current_status == status {
-                return;
-            }
-            self.current_status = status;
-            let icon = match status {
-                TrayStatus::Red => &self.icon_red,
-                TrayStatus::Amber => &self.icon_amber,
-                TrayStatus::Green => &self.icon_green,
-            };
-            let _ = self.tray_icon.set_icon(Some(icon.clone()));
+    fn metered_policy(prefs: &SavedUiState, override_active: bool) -> MeteredPolicy {
+        MeteredPolicy {
+            defer_files: !prefs.metered_send_files,
+            defer_large_text: prefs.metered_defer_large_text,
+            override_active,
         }
+    }
 
-        fn set_tooltip(&self, text: &str) {
-            let _ = self.tray_icon.set_tooltip(Some(text));
-        }
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TrayStatus {
+        Red,
+        Amber,
+        Green,
     }
 
     // ─── App phase ─────────────────────────────────────────────────────────────
@@ -494,6 +1273,38 @@ mod windows_client {
         Send,
         Options,
         Notifications,
+        Devices,
+        Snippets,
+    }
+
+    /// Pages of the Options tab.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum OptionsPage {
+        General,
+        Devices,
+        History,
+        Stats,
+        Advanced,
+    }
+
+    impl OptionsPage {
+        const ALL: [OptionsPage; 5] = [
+            OptionsPage::General,
+            OptionsPage::Devices,
+            OptionsPage::History,
+            OptionsPage::Stats,
+            OptionsPage::Advanced,
+        ];
+
+        fn label(self) -> &'static str {
+            match self {
+                OptionsPage::General => "General",
+                OptionsPage::Devices => "Devices",
+                OptionsPage::History => "History",
+                OptionsPage::Stats => "Stats",
+                OptionsPage::Advanced => "Advanced",
+            }
+        }
     }
 
     // `AppPhase::Running` is significantly larger than the other variants but
@@ -519,13 +1330,34 @@ mod windows_client {
 
             // UI state
             active_tab: Tab,
+            options_page: OptionsPage,
             send_text: String,
+            /// "Pin" is ticked for the next text send.
+            send_pinned: bool,
+            /// Optional label for the next text send.
+            send_label: String,
             connection_status: String,
             peers: Vec<PeerInfo>,
-            notifications: Vec<Notification>,
+            notifications: Vec<PendingNotification>,
+            /// Items left out of the full list by the coalesce policy.
+            coalesced_notifications: usize,
             auto_apply: bool,
             room_key_ready: bool,
+            /// Sends queued in the runtime until the room key is ready.
+            awaiting_key: usize,
+            /// File transfers in progress, shown in the Send tab.
+            transfers: TransferList,
+            cancelled_transfers: Arc<Mutex<CancelledTransfers>>,
+            /// Wakes the runtime's reconnect wait (Reconnect Now).
+            reconnect_now: Arc<tokio::sync::Notify>,
             autostart_enabled: bool,
+            /// Whether the Explorer "Send with ClipRelay" entry points at
+            /// this executable.
+            explorer_menu_enabled: bool,
+            /// Whether the ClipRelay shortcut is in the SendTo folder.
+            send_to_enabled: bool,
+            /// Whether `cliprelay://` links open in this executable.
+            url_handler_enabled: bool,
             last_sent_time: Option<u64>,
             last_received_time: Option<u64>,
             last_error: Option<String>,
@@ -535,6 +1367,20 @@ mod windows_client {
 
             /// Toast messages shown briefly in the UI.
             toast_message: Option<(String, u64)>,
+            /// Loopback self-test in progress / last result.
+            self_test_running: bool,
+            self_test_report: Option<SelfTestReport>,
+            /// Sending and receiving paused for this session.
+            sync_paused: bool,
+            /// Sync was paused by the panic hotkey; the tray stays red
+            /// until it is resumed.
+            panicked: bool,
+            metered: MeteredStatus,
+            /// The runtime stopped reconnecting after the attempt limit.
+            reconnect_gave_up: bool,
+            /// Devices never seen before that joined this session and have
+            /// not been acknowledged yet.
+            device_alerts: Vec<PeerInfo>,
         },
     }
 
@@ -547,6 +1393,17 @@ mod windows_client {
         wants_quit: bool,
         /// egui context for requesting repaints from background threads.
         egui_ctx: Option<egui::Context>,
+        /// Always-on-top state last sent to the window; `None` before the
+        /// first frame.
+        applied_always_on_top: Option<bool>,
+        /// Text size last applied as the zoom factor, likewise.
+        applied_text_size: Option<TextSize>,
+        /// Corner the status widget was last moved into; `None` while it is
+        /// closed or not yet placed.
+        status_widget_placed: Option<WidgetCorner>,
+        /// The status widget is being dragged; it snaps to a corner when
+        /// the mouse button is released.
+        status_widget_dragging: bool,
         // ── Tray event flags (set by OS callbacks, read in update loop) ──
         tray_quit_requested: Arc<AtomicBool>,
         tray_toggle_requested: Arc<AtomicBool>,
@@ -555,6 +1412,88 @@ mod windows_client {
         hotkey_current: Option<HotKey>,
         hotkey_toggle_requested: Arc<AtomicBool>,
         hotkey_label: String,
+        /// Registered quick-pick hotkey and its ID.  The ID is shared with
+        /// the OS hotkey callback so it can tell quick-pick presses apart
+        /// from show/hide presses (`0` means no quick-pick hotkey).
+        quick_pick_hotkey_current: Option<HotKey>,
+        quick_pick_hotkey_id: Arc<AtomicU32>,
+        quick_pick_requested: Arc<AtomicBool>,
+        /// Registered panic hotkey and its ID, shared with the OS hotkey
+        /// callback like the quick-pick one.
+        panic_hotkey_current: Option<HotKey>,
+        panic_hotkey_id: Arc<AtomicU32>,
+        panic_requested: Arc<AtomicBool>,
+        // ── Quick-pick clipboard ring ───────────────────────────────────
+        /// Recent full clipboard texts (memory only).  Lives on the app
+        /// rather than in `AppPhase::Running` so it survives reconnects.
+        clip_ring: ClipRing,
+        quick_pick_open: bool,
+        /// The send box has had keyboard focus since the window was last
+        /// shown; cleared while the window is hidden so that the next
+        /// showing puts the cursor there again.
+        send_box_focused: bool,
+        // ── Known devices (nicknames, trust, per-device toggles) ─────────
+        devices: DeviceRegistry,
+        // ── Usage statistics of the active profile (Options → Stats) ─────
+        usage_stats: UsageStats,
+        // ── Log viewer window (open when `Some`) ─────────────────────────
+        log_viewer: Option<LogViewer>,
+        // ── Protocol trace (memory only; survives reconnects) ────────────
+        protocol_trace: TraceLog,
+        protocol_trace_open: bool,
+        // ── Relay round trips (memory only; survives reconnects) ─────────
+        latency: LatencyHistory,
+        // ── Links in received text ───────────────────────────────────────
+        /// Link from a trusted device waiting for "Open Link?" confirmation.
+        link_prompt: Option<LinkPrompt>,
+        /// Text held back from auto-apply for its size, waiting for
+        /// "Apply Large Text?" confirmation.
+        large_apply_prompt: Option<LargeApplyPrompt>,
+        /// Link offered next to the toast with this timestamp.
+        toast_link: Option<(String, u64)>,
+        /// A `cliprelay://` link waiting for "Join Room?" confirmation.
+        join_prompt: Option<JoinLink>,
+        /// The first-run onboarding window and the page it is on.
+        onboarding: Option<OnboardingStep>,
+        /// Whether history, snippets and invites are unlocked.
+        auth_gate: AuthGate,
+        /// A Windows Hello / password prompt that is up.
+        pending_unlock: Option<PendingUnlock>,
+        /// Text that looks like a secret, waiting for "Send Anyway?".
+        secret_prompt: Option<SecretPrompt>,
+        /// Room details from a confirmed join link, pre-filled by the next
+        /// "Change Room" setup screen.
+        pending_join_link: Option<JoinLink>,
+        // ── Snippets ─────────────────────────────────────────────────────
+        snippets: SnippetLibrary,
+        /// Set when `snippets.json` exists but could not be read (e.g. it
+        /// was encrypted by another Windows account).  Editing is disabled
+        /// so the file is not overwritten.
+        snippets_error: Option<String>,
+        /// Snippet index picked from the tray submenu (set by the OS
+        /// callback, consumed in the update loop).
+        snippet_requested: Arc<Mutex<Option<usize>>>,
+        /// Apply All / Dismiss All picked from the tray menu.
+        queue_action_requested: Arc<Mutex<Option<NotificationAction>>>,
+        /// Time of the history entry whose favorite mark was toggled from
+        /// the tray menu.
+        favorite_toggle_requested: Arc<Mutex<Option<u64>>>,
+        /// Tray click bindings, shared with the OS callback and refreshed
+        /// when the setting changes.
+        tray_clicks: Arc<Mutex<TrayClickActions>>,
+        /// Tray click action left for the update loop (everything except
+        /// show/hide, which the callback does natively).
+        tray_action_requested: Arc<Mutex<Option<TrayAction>>>,
+        // ── Room profiles ────────────────────────────────────────────────
+        room_profiles: RoomProfiles,
+        /// Set when `profiles.json` exists but could not be read; saving is
+        /// disabled so the file is not overwritten.
+        room_profiles_error: Option<String>,
+        /// Room action picked from the tray menu (set by the OS callback,
+        /// consumed in the update loop).
+        room_action_requested: Arc<Mutex<Option<RoomAction>>>,
+        // ── Device identity ──────────────────────────────────────────────
+        identity: Arc<DeviceIdentity>,
         // ── Shared visibility state (written by OS callbacks via Win32) ──
         shared_visible: Arc<AtomicBool>,
         // ── Keepalive thread stop signal ────────────────────────────────
@@ -562,13 +1501,38 @@ mod windows_client {
         /// before spawning a new one during reconnects / room changes.
         keepalive_stop: Arc<AtomicBool>,
         // ── Pending phase-transition requests (set inside render_running) ──
-        /// Set to `true` when the user clicks "Change Room". Handled in
+        /// Set when the user changes or leaves the room. Handled in
         /// `update()` after `render_running` returns so that the pattern-match
         /// borrows on `self.phase` have been released.
-        pending_change_room: bool,
+        pending_room_action: Option<RoomAction>,
+        /// Connection settings edited in Options and already saved; the
+        /// runtime restarts with them.  Handled like `pending_room_action`.
+        pending_config_change: Option<SavedClientConfig>,
         /// Set to `true` when the user clicks "Reconnect". Handled in
-        /// `update()` similarly to `pending_change_room`.
+        /// `update()` similarly to `pending_room_action`.
         pending_reconnect: bool,
+        /// The room left by "Change Room"; cancelling the setup screen
+        /// rejoins it instead of quitting.
+        resume_config: Option<SavedClientConfig>,
+        // ── Window placement per monitor arrangement ─────────────────────
+        /// Main window HWND, looked up on the first placement poll.
+        main_hwnd: isize,
+        /// Fingerprint of the monitor arrangement at the last poll.
+        placement_topology: Option<String>,
+        /// Where the main window was at the last poll, saved for
+        /// `placement_topology` when the arrangement changes or on quit.
+        last_window_placement: Option<WindowPlacement>,
+        placement_polled_ms: u64,
+        /// The autostart delay / network wait applies only to the first
+        /// runtime started by a `--background` launch, not to reconnects.
+        startup_gate_pending: bool,
+        // ── Configuration profiles (setup screens) ──────────────────────
+        config_profile_names: Vec<String>,
+        /// Name typed into the setup screens' "New profile" field.
+        new_config_profile: String,
+        config_profile_error: Option<String>,
+        /// Last "Test Connection" run on the setup screen.
+        connection_test: Option<ConnectionTest>,
     }
 
     impl ClipRelayApp {
@@ -578,42 +1542,223 @@ mod windows_client {
             args: ClientArgs,
         ) -> Self {
             let ui_state = load_ui_state_logged();
+            let args_background = args.background;
             let hotkey_label = ui_state
                 .hotkey
                 .clone()
                 .unwrap_or_else(|| DEFAULT_HOTKEY_LABEL.to_owned());
+            let (snippets, snippets_error) = match snippets::load_snippets() {
+                Ok(library) => (library, None),
+                Err(err) => {
+                    warn!("failed to load snippets: {err}");
+                    (SnippetLibrary::default(), Some(err.to_string()))
+                }
+            };
+            let (room_profiles, room_profiles_error) = match room_profiles::load_profiles() {
+                Ok(profiles) => (profiles, None),
+                Err(err) => {
+                    warn!("failed to load room profiles: {err}");
+                    (RoomProfiles::default(), Some(err.to_string()))
+                }
+            };
+            let identity = match identity::load_or_create_identity() {
+                Ok(identity) => identity,
+                Err(err) => {
+                    // Keep running with a session-only key; peers that pinned
+                    // the stored one will report a key change.
+                    error!("failed to load device identity; using a temporary key: {err}");
+                    DeviceIdentity::generate().expect("generate device identity")
+                }
+            };
+            let tray_clicks = Arc::new(Mutex::new(ui_state.tray_clicks));
             Self {
                 phase: initial_phase,
                 args,
                 ui_state,
                 wants_quit: false,
                 egui_ctx: None,
+                applied_always_on_top: None,
+                applied_text_size: None,
+                status_widget_placed: None,
+                status_widget_dragging: false,
                 tray_quit_requested: Arc::new(AtomicBool::new(false)),
                 tray_toggle_requested: Arc::new(AtomicBool::new(false)),
                 hotkey_manager: None,
                 hotkey_current: None,
                 hotkey_toggle_requested: Arc::new(AtomicBool::new(false)),
                 hotkey_label,
+                quick_pick_hotkey_current: None,
+                quick_pick_hotkey_id: Arc::new(AtomicU32::new(0)),
+                quick_pick_requested: Arc::new(AtomicBool::new(false)),
+                panic_hotkey_current: None,
+                panic_hotkey_id: Arc::new(AtomicU32::new(0)),
+                panic_requested: Arc::new(AtomicBool::new(false)),
+                clip_ring: ClipRing::default(),
+                quick_pick_open: false,
+                send_box_focused: false,
+                devices: devices::load_devices(),
+                usage_stats: UsageStats::default(),
+                log_viewer: None,
+                protocol_trace: TraceLog::default(),
+                protocol_trace_open: false,
+                latency: LatencyHistory::default(),
+                link_prompt: None,
+                large_apply_prompt: None,
+                join_prompt: None,
+                onboarding: None,
+                auth_gate: AuthGate::default(),
+                pending_unlock: None,
+                secret_prompt: None,
+                pending_join_link: None,
+                toast_link: None,
+                snippets,
+                snippets_error,
+                snippet_requested: Arc::new(Mutex::new(None)),
+                queue_action_requested: Arc::new(Mutex::new(None)),
+                favorite_toggle_requested: Arc::new(Mutex::new(None)),
+                tray_clicks,
+                tray_action_requested: Arc::new(Mutex::new(None)),
+                room_profiles,
+                room_profiles_error,
+                room_action_requested: Arc::new(Mutex::new(None)),
+                identity: Arc::new(identity),
                 shared_visible: Arc::new(AtomicBool::new(true)),
                 keepalive_stop: Arc::new(AtomicBool::new(false)),
-                pending_change_room: false,
+                pending_room_action: None,
+                pending_config_change: None,
                 pending_reconnect: false,
+                resume_config: None,
+                main_hwnd: 0,
+                placement_topology: None,
+                last_window_placement: None,
+                placement_polled_ms: 0,
+                startup_gate_pending: args_background,
+                config_profile_names: config_profiles::list_profiles(&config_profiles::data_dir()),
+                new_config_profile: String::new(),
+                config_profile_error: None,
+                connection_test: None,
             }
         }
 
-        /// Transition from setup to running: create runtime, spawn networking,
-        /// create tray icon.
-        fn start_running(&mut self, saved: SavedClientConfig, ctx: &egui::Context) {
-            let device_id = stable_device_id(&saved.device_name);
-
+        /// Follow the main window and the monitor arrangement.  When the
+        /// arrangement changes (or on the first poll), the placement used
+        /// on the old one is remembered and the one saved for the new one,
+        /// if any, is restored; otherwise the window stays where Windows
+        /// put it.  Only with "Where it was last"; the other pop-up
+        /// placements move the window themselves.
+        fn track_window_placement(&mut self) {
+            let now = now_unix_ms();
+            if now < self.placement_polled_ms.saturating_add(PLACEMENT_POLL_MS) {
+                return;
+            }
+            self.placement_polled_ms = now;
+            if self.main_hwnd == 0 {
+                self.main_hwnd = unsafe {
+                    use windows_sys::Win32::UI::WindowsAndMessaging::FindWindowW;
+                    let title = to_wide_null(&window_title());
+                    FindWindowW(std::ptr::null(), title.as_ptr())
+                };
+                if self.main_hwnd == 0 {
+                    return;
+                }
+            }
+            let monitors = win32_monitors();
+            let rects: Vec<ScreenRect> = monitors.iter().map(|(monitor, _)| *monitor).collect();
+            let fingerprint = ui_state::topology_fingerprint(&rects);
+            if self.placement_topology.as_deref() == Some(fingerprint.as_str()) {
+                if let Some(placement) = unsafe { win32_window_placement(self.main_hwnd) } {
+                    self.last_window_placement = Some(placement);
+                }
+                return;
+            }
+            if self.placement_topology.is_some() {
+                info!(monitors = monitors.len(), "monitor arrangement changed");
+                if self.remember_window_placement()
+                    && let Err(err) = ui_state::save_ui_state_with_retry(&self.ui_state)
+                {
+                    warn!("failed to save window placement: {err}");
+                }
+            }
+            if self.ui_state.popup_placement == PopupPlacement::Remembered
+                && let Some(saved) = self.ui_state.main_placement(&fingerprint)
+            {
+                debug!(
+                    ?saved,
+                    "restoring window placement for this monitor arrangement"
+                );
+                unsafe { win32_restore_placement(self.main_hwnd, saved, &monitors) };
+            }
+            self.placement_topology = Some(fingerprint);
+            self.last_window_placement = None;
+        }
+
+        /// Put the last sampled main window placement into `ui_state` under
+        /// the arrangement it was sampled on.  Returns whether it changed.
+        fn remember_window_placement(&mut self) -> bool {
+            match (&self.placement_topology, self.last_window_placement) {
+                (Some(fingerprint), Some(placement)) => self
+                    .ui_state
+                    .remember_main_placement(fingerprint, placement),
+                _ => false,
+            }
+        }
+
+        /// Make room profile `index` the saved room and join it.  Falls back to
+        /// rejoining `current` when the profile cannot be used.
+        fn switch_to_profile(
+            &mut self,
+            index: usize,
+            current: Option<SavedClientConfig>,
+            ctx: &egui::Context,
+        ) {
+            let result = match self.room_profiles.get(index) {
+                Some(profile) => {
+                    let cfg = SavedClientConfig {
+                        server_url: profile.server_url.clone(),
+                        room_code: profile.room_code.clone(),
+                        device_name: profile.device_name.clone(),
+                    };
+                    save_saved_config(&cfg).map(|()| (profile.name.clone(), cfg))
+                }
+                None => Err(format!("no room profile at index {index}")),
+            };
+            match result {
+                Ok((name, cfg)) => {
+                    info!(profile = %name, "switching room profile");
+                    self.room_profiles.last_used = Some(name);
+                    if self.room_profiles_error.is_none()
+                        && let Err(err) = room_profiles::save_profiles(&self.room_profiles)
+                    {
+                        warn!("failed to save room profiles: {err}");
+                    }
+                    self.start_running(cfg, ctx);
+                }
+                Err(err) => {
+                    warn!("room profile switch failed: {err}");
+                    show_system_notification("Room not switched", &err);
+                    match current {
+                        Some(cfg) => self.start_running(cfg, ctx),
+                        None => self.phase = AppPhase::ChooseRoom { saved_config: None },
+                    }
+                }
+            }
+        }
+
+        /// Transition from setup to running: create runtime, spawn networking,
+        /// create tray icon.
+        fn start_running(&mut self, saved: SavedClientConfig, ctx: &egui::Context) {
+            self.resume_config = None;
+            let device_id = stable_device_id(&saved.device_name);
+
+            let room_id = room_id_from_code(&saved.room_code);
             let config = ClientConfig {
-                room_id: room_id_from_code(&saved.room_code),
+                initial_counter: load_send_counter(&room_id),
+                room_id,
                 server_url: saved.server_url.clone(),
                 room_code: saved.room_code.clone(),
                 device_name: saved.device_name.clone(),
                 device_id,
                 background: self.args.background,
-                initial_counter: saved.last_counter,
             };
 
             let runtime = match Runtime::new() {
@@ -627,11 +1772,9 @@ mod windows_client {
             let (ui_event_tx, ui_event_rx) = std::sync::mpsc::channel();
             let (runtime_cmd_tx, runtime_cmd_rx) = mpsc::unbounded_channel();
 
-            let shared_state = SharedRuntimeState {
-                room_key: Arc::new(Mutex::new(None)),
-                last_applied_hash: Arc::new(Mutex::new(None)),
-                auto_apply: Arc::new(Mutex::new(false)),
-            };
+            let shared_state = SharedRuntimeState::new(&self.ui_state, self.identity.clone());
+            let cancelled_transfers = shared_state.cancelled_transfers.clone();
+            let reconnect_now = shared_state.reconnect_now.clone();
 
             let repaint_ctx = ctx.clone();
             let repainting_tx = RepaintingSender {
@@ -639,14 +1782,43 @@ mod windows_client {
                 ctx: repaint_ctx,
             };
 
+            if self.ui_state.automation_api_enabled {
+                match automation::load_or_create_token() {
+                    Ok(token) => {
+                        runtime.spawn(automation_server_task(token, repainting_tx.clone()));
+                    }
+                    Err(err) => warn!("automation API disabled: token unavailable: {err}"),
+                }
+            }
+            match automation::load_or_create_token() {
+                Ok(token) => {
+                    runtime.spawn(instance_server_task(token, repainting_tx.clone()));
+                }
+                Err(err) => warn!("instance pipe disabled: token unavailable: {err}"),
+            }
+            if self.args.simulate_peer {
+                runtime.spawn(run_simulated_peer(config.clone()));
+            }
+
+            let startup_gate = if std::mem::take(&mut self.startup_gate_pending) {
+                StartupGate::new(
+                    self.ui_state.autostart_delay_secs,
+                    self.ui_state.autostart_wait_for_network,
+                )
+            } else {
+                StartupGate::default()
+            };
+
             runtime.spawn(run_client_runtime(
                 config.clone(),
+                startup_gate,
                 repainting_tx,
                 runtime_cmd_rx,
                 shared_state,
             ));
 
-            let history = load_history();
+            let history = load_history(self.ui_state.history_retention);
+            self.usage_stats = usage_stats::load_usage_stats(now_unix_ms());
 
             // ── Find the eframe window HWND for direct Win32 show/hide ──────
             //
@@ -674,13 +1846,35 @@ mod windows_client {
             self.shared_visible
                 .store(!self.args.background, Ordering::SeqCst);
 
-            let tray = TrayState::new(
+            let mut tray = TrayState::new(
                 ctx,
                 self.tray_quit_requested.clone(),
                 self.tray_toggle_requested.clone(),
+                self.snippet_requested.clone(),
+                self.room_action_requested.clone(),
+                self.queue_action_requested.clone(),
+                self.favorite_toggle_requested.clone(),
+                self.tray_clicks.clone(),
+                self.tray_action_requested.clone(),
                 eframe_hwnd,
                 self.shared_visible.clone(),
             );
+            if let Some(tray) = tray.as_mut() {
+                tray.set_locked(
+                    !self
+                        .auth_gate
+                        .is_unlocked(self.ui_state.auth_gate, now_unix_ms()),
+                );
+                tray.set_snippets(&self.snippets.names());
+                tray.set_profiles(
+                    &self.room_profiles.names(),
+                    self.room_profiles.active_index(
+                        &config.server_url,
+                        &config.room_code,
+                        &config.device_name,
+                    ),
+                );
+            }
             if tray.is_some() {
                 info!("TrayState created successfully");
                 trace!("[tray] TrayState created successfully");
@@ -688,7 +1882,10 @@ mod windows_client {
                 error!("TrayState creation FAILED -- tray icon will not appear");
                 trace!("[tray] TrayState creation FAILED");
             }
-            let autostart_enabled = windows_autostart_is_enabled();
+            let autostart_enabled = windows_autostart_is_enabled(self.ui_state.autostart_backend);
+            let explorer_menu_enabled = windows_explorer_menu_is_enabled();
+            let send_to_enabled = shell_menu::send_to_installed();
+            let url_handler_enabled = windows_url_handler_is_enabled();
 
             // ── Global hotkey registration ──────────────────────────────────
             let manager = GlobalHotKeyManager::new().ok();
@@ -715,11 +1912,31 @@ mod windows_client {
             }
             self.hotkey_manager = manager;
             self.hotkey_current = hotkey_current;
+            if let Err(err) = register_quick_pick_hotkey(
+                self.hotkey_manager.as_ref(),
+                &mut self.quick_pick_hotkey_current,
+                &self.quick_pick_hotkey_id,
+                self.ui_state.quick_pick_hotkey.as_deref(),
+            ) {
+                hotkey_error.get_or_insert(err);
+            }
+            if let Err(err) = register_panic_hotkey(
+                self.hotkey_manager.as_ref(),
+                &mut self.panic_hotkey_current,
+                &self.panic_hotkey_id,
+                self.ui_state.panic_hotkey.as_deref(),
+            ) {
+                hotkey_error.get_or_insert(err);
+            }
 
             let hk_flag = self.hotkey_toggle_requested.clone();
             let ctx_hk = ctx.clone();
             let hk_hwnd = eframe_hwnd;
             let hk_visible = self.shared_visible.clone();
+            let qp_id = self.quick_pick_hotkey_id.clone();
+            let qp_flag = self.quick_pick_requested.clone();
+            let panic_id = self.panic_hotkey_id.clone();
+            let panic_flag = self.panic_requested.clone();
             GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
                 debug!(hotkey_event = ?event, "GlobalHotKeyEvent received");
                 trace!("[hotkey] GlobalHotKeyEvent: {event:?}");
@@ -730,6 +1947,29 @@ mod windows_client {
                     trace!("[hotkey] ignoring Released event");
                     return;
                 }
+
+                // Quick-pick always shows the window (never toggles it off).
+                let quick_pick_id = qp_id.load(Ordering::SeqCst);
+                if quick_pick_id != 0 && event.id == quick_pick_id {
+                    qp_flag.store(true, Ordering::SeqCst);
+                    if hk_hwnd != 0 {
+                        hk_visible.store(true, Ordering::SeqCst);
+                        unsafe { win32_set_window_visible(hk_hwnd, true) };
+                    }
+                    ctx_hk.request_repaint();
+                    trace!("[hotkey] quick-pick requested");
+                    return;
+                }
+
+                // Panic leaves the window as it is.
+                let panic_hotkey_id = panic_id.load(Ordering::SeqCst);
+                if panic_hotkey_id != 0 && event.id == panic_hotkey_id {
+                    panic_flag.store(true, Ordering::SeqCst);
+                    ctx_hk.request_repaint();
+                    trace!("[hotkey] panic requested");
+                    return;
+                }
+
                 hk_flag.store(true, Ordering::SeqCst);
                 ctx_hk.request_repaint();
                 debug!("hotkey_toggle_flag stored, repaint requested");
@@ -779,13 +2019,24 @@ mod windows_client {
                 ui_event_rx,
                 runtime_cmd_tx,
                 active_tab: Tab::Send,
+                options_page: OptionsPage::General,
                 send_text: String::new(),
+                send_pinned: false,
+                send_label: String::new(),
                 connection_status: "Starting".to_string(),
                 peers: Vec::new(),
                 notifications: Vec::new(),
+                coalesced_notifications: 0,
                 auto_apply: false,
                 room_key_ready: false,
+                awaiting_key: 0,
+                transfers: TransferList::default(),
+                cancelled_transfers,
+                reconnect_now,
                 autostart_enabled,
+                explorer_menu_enabled,
+                send_to_enabled,
+                url_handler_enabled,
                 last_sent_time: None,
                 last_received_time: None,
                 last_error: hotkey_error,
@@ -793,6 +2044,13 @@ mod windows_client {
                 tray,
                 window_visible: !self.args.background,
                 toast_message: None,
+                self_test_running: false,
+                self_test_report: None,
+                sync_paused: false,
+                panicked: false,
+                metered: MeteredStatus::default(),
+                reconnect_gave_up: false,
+                device_alerts: Vec::new(),
             };
 
             if self.args.background {
@@ -805,6 +2063,25 @@ mod windows_client {
             }
         }
 
+        /// Unregister all global hotkeys before the manager is dropped.
+        fn unregister_hotkeys(&mut self) {
+            if let Some(mgr) = &self.hotkey_manager {
+                if let Some(old_hk) = self.hotkey_current.take() {
+                    let _ = mgr.unregister(old_hk);
+                }
+                if let Some(old_hk) = self.quick_pick_hotkey_current.take() {
+                    let _ = mgr.unregister(old_hk);
+                }
+                if let Some(old_hk) = self.panic_hotkey_current.take() {
+                    let _ = mgr.unregister(old_hk);
+                }
+            }
+            self.quick_pick_hotkey_id.store(0, Ordering::SeqCst);
+            self.panic_hotkey_id.store(0, Ordering::SeqCst);
+            self.hotkey_manager = None;
+            self.hotkey_current = None;
+        }
+
         // ─── Choose Room screen ────────────────────────────────────────────────
 
         fn render_choose_room(
@@ -813,10 +2090,19 @@ mod windows_client {
             saved_config: Option<SavedClientConfig>,
         ) {
             let mut action: Option<ChooseRoomAction> = None;
+            let mut picked_profile: Option<Option<String>> = None;
+            let mut editing_profile_name = false;
 
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.add_space(20.0);
                 ui.heading("Welcome to ClipRelay!");
+                ui.add_space(8.0);
+                (picked_profile, editing_profile_name) = config_profile_picker(
+                    ui,
+                    &self.config_profile_names,
+                    &mut self.new_config_profile,
+                    &mut self.config_profile_error,
+                );
                 ui.add_space(16.0);
 
                 if let Some(ref cfg) = saved_config {
@@ -842,7 +2128,7 @@ mod windows_client {
 
                     ui.add_space(20.0);
                     ui.horizontal(|ui| {
-                        if ui.button("Use Saved Room").clicked() {
+                        if ui.button("Use Saved Room").on_hover_text("Enter").clicked() {
                             action = Some(ChooseRoomAction::UseSaved);
                         }
                         ui.add_space(4.0);
@@ -850,7 +2136,7 @@ mod windows_client {
                             action = Some(ChooseRoomAction::SetupNew);
                         }
                         ui.add_space(4.0);
-                        if ui.button("Cancel").clicked() {
+                        if ui.button("Cancel").on_hover_text("Esc").clicked() {
                             action = Some(ChooseRoomAction::Cancel);
                         }
                     });
@@ -858,17 +2144,41 @@ mod windows_client {
                     ui.label("No saved room found. Set up a new room to start syncing.");
                     ui.add_space(20.0);
                     ui.horizontal(|ui| {
-                        if ui.button("Setup New Room").clicked() {
+                        if ui.button("Setup New Room").on_hover_text("Enter").clicked() {
                             action = Some(ChooseRoomAction::SetupNew);
                         }
                         ui.add_space(4.0);
-                        if ui.button("Cancel").clicked() {
+                        if ui.button("Cancel").on_hover_text("Esc").clicked() {
                             action = Some(ChooseRoomAction::Cancel);
                         }
                     });
                 }
             });
 
+            if let Some(profile) = picked_profile {
+                self.switch_config_profile(profile, ctx);
+                return;
+            }
+
+            // Enter picks the default (first) button, Esc cancels.
+            let (enter, escape) = ctx.input(|i| {
+                (
+                    i.key_pressed(egui::Key::Enter),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if action.is_none() && !editing_profile_name {
+                if escape {
+                    action = Some(ChooseRoomAction::Cancel);
+                } else if enter && ctx.memory(|m| m.focused().is_none()) {
+                    action = Some(if saved_config.is_some() {
+                        ChooseRoomAction::UseSaved
+                    } else {
+                        ChooseRoomAction::SetupNew
+                    });
+                }
+            }
+
             match action {
                 Some(ChooseRoomAction::UseSaved) => {
                     if let Some(cfg) = saved_config {
@@ -880,7 +2190,6 @@ mod windows_client {
                         server_url: self.args.server_url.clone(),
                         room_code: String::new(),
                         device_name: self.args.client_name.clone(),
-                        last_counter: 0,
                     });
                     self.phase = AppPhase::Setup {
                         room_code: defaults.room_code,
@@ -897,6 +2206,35 @@ mod windows_client {
             }
         }
 
+        /// Make `profile` the active configuration profile: load its
+        /// settings and show its saved room (or the setup screen).
+        fn switch_config_profile(&mut self, profile: Option<String>, ctx: &egui::Context) {
+            if profile == config_profiles::active() {
+                return;
+            }
+            info!(
+                profile = config_profiles::display_name(profile.as_deref()),
+                "switching configuration profile"
+            );
+            if let Err(err) =
+                config_profiles::save_last_used(&config_profiles::data_dir(), profile.as_deref())
+            {
+                warn!("failed to remember configuration profile: {err}");
+            }
+            config_profiles::set_active(profile);
+            self.ui_state = load_ui_state_logged();
+            self.hotkey_label = self
+                .ui_state
+                .hotkey
+                .clone()
+                .unwrap_or_else(|| DEFAULT_HOTKEY_LABEL.to_owned());
+            self.config_profile_names =
+                config_profiles::list_profiles(&config_profiles::data_dir());
+            self.config_profile_error = None;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(window_title()));
+            self.phase = interactive_initial_phase(&self.args);
+        }
+
         // ─── Setup screen ──────────────────────────────────────────────────────
 
         fn render_setup(
@@ -908,28 +2246,54 @@ mod windows_client {
             error_message: Option<String>,
         ) {
             let mut action: Option<SetupAction> = None;
+            let cancel_hint = if self.resume_config.is_some() {
+                "Esc \u{2014} rejoin the previous room"
+            } else {
+                "Esc"
+            };
+
+            let mut picked_profile: Option<Option<String>> = None;
+            let mut editing_profile_name = false;
 
             egui::CentralPanel::default().show(ctx, |ui| {
                 ui.add_space(20.0);
                 ui.heading("Room Setup");
                 ui.add_space(4.0);
                 ui.label("Enter your room details to get started.");
-                ui.add_space(16.0);
+                ui.add_space(8.0);
+                // Switching profile while changing room would leave the
+                // previous room's profile behind; only offer it at startup.
+                if self.resume_config.is_none() {
+                    (picked_profile, editing_profile_name) = config_profile_picker(
+                        ui,
+                        &self.config_profile_names,
+                        &mut self.new_config_profile,
+                        &mut self.config_profile_error,
+                    );
+                }
+                ui.add_space(8.0);
 
                 egui::Grid::new("setup_grid")
                     .num_columns(2)
                     .spacing([12.0, 10.0])
                     .show(ui, |ui| {
-                        ui.label("Room code:");
-                        ui.add(egui::TextEdit::singleline(&mut room_code).desired_width(300.0));
+                        let label = ui.label("Room code:");
+                        let room_field = ui
+                            .add(egui::TextEdit::singleline(&mut room_code).desired_width(300.0))
+                            .labelled_by(label.id);
+                        if ui.memory(|m| m.focused().is_none()) && room_code.is_empty() {
+                            room_field.request_focus();
+                        }
                         ui.end_row();
 
-                        ui.label("Server URL:");
-                        ui.add(egui::TextEdit::singleline(&mut server_url).desired_width(300.0));
+                        let label = ui.label("Server URL:");
+                        ui.add(egui::TextEdit::singleline(&mut server_url).desired_width(300.0))
+                            .labelled_by(label.id);
                         ui.end_row();
 
-                        ui.label("Client Name:");
-                        ui.add(egui::TextEdit::singleline(&mut device_name).desired_width(300.0));
+                        let label = ui.label("Client Name:");
+                        ui.add(egui::TextEdit::singleline(&mut device_name).desired_width(300.0))
+                            .labelled_by(label.id);
                         ui.end_row();
                     });
 
@@ -946,30 +2310,87 @@ mod windows_client {
                     ui.colored_label(egui::Color32::RED, msg);
                 }
 
+                let test = self
+                    .connection_test
+                    .as_ref()
+                    .filter(|t| t.matches(&server_url, &room_code, &device_name));
+                let test_result = test.map(|t| t.result.lock().ok().and_then(|r| r.clone()));
+                let testing = matches!(test_result, Some(None));
+                if let Some(Some(result)) = &test_result {
+                    ui.add_space(8.0);
+                    match result {
+                        Ok(message) => {
+                            ui.colored_label(egui::Color32::from_rgb(60, 160, 60), message)
+                        }
+                        Err(message) => ui.colored_label(egui::Color32::RED, message),
+                    };
+                }
+
                 ui.add_space(20.0);
                 ui.horizontal(|ui| {
-                    if ui.button("Connect").clicked() {
+                    if ui.button("Connect").on_hover_text("Enter").clicked() {
                         action = Some(SetupAction::Connect);
                     }
                     ui.add_space(4.0);
-                    if ui.button("Cancel").clicked() {
+                    let label = if testing {
+                        "Testing\u{2026}"
+                    } else {
+                        "Test Connection"
+                    };
+                    if ui
+                        .add_enabled(!testing, egui::Button::new(label))
+                        .on_hover_text(
+                            "Connect to the relay and join the room briefly as this device,\n\
+                             without saving anything.",
+                        )
+                        .clicked()
+                    {
+                        action = Some(SetupAction::TestConnection);
+                    }
+                    ui.add_space(4.0);
+                    if ui.button("Cancel").on_hover_text(cancel_hint).clicked() {
                         action = Some(SetupAction::Cancel);
                     }
                 });
+                if testing {
+                    ui.ctx().request_repaint_after(Duration::from_millis(200));
+                }
             });
 
+            if let Some(profile) = picked_profile {
+                self.switch_config_profile(profile, ctx);
+                return;
+            }
+
+            // Enter in any field connects; Esc cancels.  A single-line edit
+            // gives up focus on Enter, so this sees the key either way.
+            if action.is_none() && !editing_profile_name {
+                ctx.input(|i| {
+                    if i.key_pressed(egui::Key::Escape) {
+                        action = Some(SetupAction::Cancel);
+                    } else if i.key_pressed(egui::Key::Enter) {
+                        action = Some(SetupAction::Connect);
+                    }
+                });
+            }
+
             match action {
                 Some(SetupAction::Connect) => {
                     let cfg = SavedClientConfig {
                         room_code: room_code.clone(),
                         server_url: server_url.clone(),
                         device_name: device_name.clone(),
-                        last_counter: 0,
                     };
                     match validate_saved_config(&cfg) {
                         Ok(()) => {
+                            // Only the very first setup gets the onboarding,
+                            // not later room changes.
+                            let first_setup = matches!(load_saved_config(), Ok(None));
                             let _ = save_saved_config(&cfg);
                             self.start_running(cfg, ctx);
+                            if first_setup && !self.ui_state.onboarding_done {
+                                self.onboarding = Some(OnboardingStep::ALL[0]);
+                            }
                         }
                         Err(err) => {
                             self.phase = AppPhase::Setup {
@@ -981,9 +2402,39 @@ mod windows_client {
                         }
                     }
                 }
+                Some(SetupAction::TestConnection) => {
+                    let cfg = SavedClientConfig {
+                        room_code: room_code.clone(),
+                        server_url: server_url.clone(),
+                        device_name: device_name.clone(),
+                    };
+                    let invalid = validate_saved_config(&cfg).err();
+                    let probe = invalid.is_none();
+                    let result = Arc::new(Mutex::new(invalid.map(Err)));
+                    if probe {
+                        start_connection_test(cfg, result.clone(), ctx.clone());
+                    }
+                    self.connection_test = Some(ConnectionTest {
+                        server_url: server_url.clone(),
+                        room_code: room_code.clone(),
+                        device_name: device_name.clone(),
+                        result,
+                    });
+                    self.phase = AppPhase::Setup {
+                        room_code,
+                        server_url,
+                        device_name,
+                        error_message,
+                    };
+                }
                 Some(SetupAction::Cancel) => {
-                    self.wants_quit = true;
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    if let Some(cfg) = self.resume_config.take() {
+                        info!("room change cancelled — rejoining previous room");
+                        self.start_running(cfg, ctx);
+                    } else {
+                        self.wants_quit = true;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
                 }
                 None => {
                     // Persist text edits back into the phase.
@@ -1007,8 +2458,10 @@ mod windows_client {
             // Rust's field-level borrowing allows writing to these (and other
             // `self.*` fields) while `self.phase` is borrowed via the pattern
             // match below.
-            let mut change_room_requested = false;
+            let mut room_action: Option<RoomAction> = None;
             let mut reconnect_requested = false;
+            let mut config_change: Option<SavedClientConfig> = None;
+            let mut paste_requested = false;
 
             // Pre-bind hotkey_label so the central-panel closure can capture
             // it without borrowing all of `self`.
@@ -1022,13 +2475,24 @@ mod windows_client {
                 ref ui_event_rx,
                 ref runtime_cmd_tx,
                 ref mut active_tab,
+                ref mut options_page,
                 ref mut send_text,
+                ref mut send_pinned,
+                ref mut send_label,
                 ref mut connection_status,
                 ref mut peers,
                 ref mut notifications,
+                ref mut coalesced_notifications,
                 ref mut auto_apply,
                 ref mut room_key_ready,
+                ref mut awaiting_key,
+                ref mut transfers,
+                ref cancelled_transfers,
+                ref reconnect_now,
                 ref mut autostart_enabled,
+                ref mut explorer_menu_enabled,
+                ref mut send_to_enabled,
+                ref mut url_handler_enabled,
                 ref mut last_sent_time,
                 ref mut last_received_time,
                 ref mut last_error,
@@ -1036,6 +2500,13 @@ mod windows_client {
                 ref mut tray,
                 ref mut window_visible,
                 ref mut toast_message,
+                ref mut self_test_running,
+                ref mut self_test_report,
+                ref mut sync_paused,
+                ref mut panicked,
+                ref mut metered,
+                ref mut reconnect_gave_up,
+                ref mut device_alerts,
                 ..
             } = self.phase
             else {
@@ -1051,60 +2522,295 @@ mod windows_client {
                             *last_error = None;
                         }
                     }
-                    UiEvent::Peers(p) => *peers = p,
-                    UiEvent::LastSent(ts) => *last_sent_time = Some(ts),
+                    UiEvent::Peers(p) => {
+                        let now = now_unix_ms();
+                        let unfamiliar = self.devices.unfamiliar(
+                            p.iter()
+                                .map(|peer| peer.device_id.as_str())
+                                .filter(|id| *id != config.device_id),
+                        );
+                        if !self.ui_state.suppress_unknown_device_alerts {
+                            for peer in p.iter().filter(|peer| {
+                                unfamiliar.contains(&peer.device_id.as_str())
+                                    && !device_alerts.iter().any(|a| a.device_id == peer.device_id)
+                            }) {
+                                warn!(
+                                    device_id = %peer.device_id,
+                                    device_name = %peer.device_name,
+                                    "unknown device joined the room"
+                                );
+                                show_system_notification(
+                                    "Unknown device joined",
+                                    &format!(
+                                        "\"{}\" joined your room. If you don't recognise it, \
+                                         pause sync and change the room code.",
+                                        peer.device_name
+                                    ),
+                                );
+                                device_alerts.push(peer.clone());
+                            }
+                        }
+                        // Peers in the old list were present until now, so
+                        // refreshing them too records when departed ones left.
+                        for peer in peers
+                            .iter()
+                            .chain(p.iter())
+                            .filter(|p| p.device_id != config.device_id)
+                        {
+                            self.devices
+                                .observe(&peer.device_id, &peer.device_name, now);
+                        }
+                        save_devices_logged(&self.devices);
+                        *peers = p;
+                    }
+                    UiEvent::LastSent {
+                        ts_unix_ms,
+                        kind,
+                        bytes,
+                    } => {
+                        *last_sent_time = Some(ts_unix_ms);
+                        let names: Vec<(&str, String)> = peers
+                            .iter()
+                            .filter(|p| p.device_id != config.device_id)
+                            .map(|p| {
+                                (
+                                    p.device_id.as_str(),
+                                    resolve_peer_name(&self.devices, peers, &p.device_id),
+                                )
+                            })
+                            .collect();
+                        let recipients: Vec<(&str, &str)> = names
+                            .iter()
+                            .map(|(id, name)| (*id, name.as_str()))
+                            .collect();
+                        self.usage_stats
+                            .record_sent(&local_date_key(), kind, bytes, &recipients);
+                        save_usage_stats_logged(&self.usage_stats);
+                    }
                     UiEvent::LastReceived(ts) => *last_received_time = Some(ts),
                     UiEvent::RoomKeyReady(ready) => *room_key_ready = ready,
                     UiEvent::IncomingClipboard {
                         sender_device_id,
                         text,
                         content_hash,
+                        signer_key,
+                        pinned,
+                        label,
+                        language_hint,
                     } => {
-                        history.push_front(ActivityEntry {
+                        if self.devices.is_blocked(&sender_device_id) {
+                            info!(sender = %sender_device_id, "discarding clipboard from blocked device");
+                            continue;
+                        }
+                        self.usage_stats.record_received(
+                            &local_date_key(),
+                            UsageKind::Text,
+                            text.len() as u64,
+                            &sender_device_id,
+                            &resolve_peer_name(&self.devices, peers, &sender_device_id),
+                        );
+                        save_usage_stats_logged(&self.usage_stats);
+                        let verified = check_sender_key(
+                            &mut self.devices,
+                            peers,
+                            &sender_device_id,
+                            signer_key.as_deref(),
+                        );
+                        self.clip_ring.push(RingItem {
                             ts_unix_ms: now_unix_ms(),
-                            direction: ActivityDirection::Received,
+                            source: RingSource::Received,
                             peer_device_id: sender_device_id.clone(),
-                            kind: "text".to_owned(),
-                            summary: preview_text(&text, 140),
+                            text: text.clone(),
+                            label: label.clone(),
                         });
-                        while history.len() > MAX_HISTORY_ENTRIES {
-                            history.pop_back();
-                        }
-                        save_history(history);
+                        let found_links = links::find_urls(&text);
+                        let language = language_hint
+                            .as_deref()
+                            .and_then(Language::from_hint)
+                            .or_else(|| code_preview::detect(&text));
+                        push_history(
+                            history,
+                            self.ui_state.history_retention,
+                            ActivityEntry {
+                                ts_unix_ms: now_unix_ms(),
+                                direction: ActivityDirection::Received,
+                                peer_device_id: sender_device_id.clone(),
+                                kind: "text".to_owned(),
+                                summary: labelled(label.as_deref(), &preview_text(&text, 140)),
+                                link: found_links.first().cloned(),
+                                delivery: None,
+                                send_id: None,
+                                pinned,
+                                favorite: false,
+                                repeat_count: 1,
+                                content_hash: Some(content_hash),
+                                checksum: None,
+                                selected: false,
+                            },
+                        );
 
-                        if *auto_apply {
-                            if let Err(err) = apply_clipboard_text(&text) {
+                        let notify = self.devices.wants_notifications(&sender_device_id)
+                            && !sound::dnd_active(self.ui_state.dnd_schedule, local_hour());
+                        if notify && self.ui_state.sound_on_text {
+                            play_notification_sound(&self.ui_state.sound_source);
+                        }
+                        // A text that is just a link, from a device the user
+                        // marked as trusted and whose key checks out.
+                        let trusted_link = links::sole_url(&text).filter(|_| {
+                            verified
+                                && self
+                                    .devices
+                                    .get(&sender_device_id)
+                                    .is_some_and(|d| d.trust == TrustState::Trusted)
+                        });
+                        if let Some(url) = trusted_link {
+                            let name = resolve_peer_name(&self.devices, peers, &sender_device_id);
+                            match self.ui_state.trusted_link_auto_open {
+                                LinkAutoOpen::Off => {}
+                                LinkAutoOpen::Ask => {
+                                    info!(sender = %sender_device_id, "asking to open received link");
+                                    self.link_prompt = Some(LinkPrompt {
+                                        url,
+                                        sender_name: name,
+                                    });
+                                    *window_visible = true;
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                                }
+                                LinkAutoOpen::Open => {
+                                    info!(sender = %sender_device_id, "opening received link");
+                                    if let Err(err) = open_url(&url) {
+                                        warn!("open link failed: {err}");
+                                    }
+                                }
+                            }
+                        }
+                        let may_auto_apply = *auto_apply
+                            && verified
+                            && self.devices.allows_auto_apply(&sender_device_id);
+                        if may_auto_apply
+                            && !self
+                                .ui_state
+                                .large_apply_confirm
+                                .needs_confirmation(text.len())
+                        {
+                            let applied = transforms::run(
+                                &self.ui_state.text_transforms,
+                                Stage::Apply,
+                                &text,
+                            );
+                            if let Err(err) = apply_received_text(&applied, &self.ui_state) {
                                 warn!("auto-apply failed: {}", err);
                             } else {
                                 let _ =
                                     runtime_cmd_tx.send(RuntimeCommand::MarkApplied(content_hash));
-                                let name = resolve_peer_name(peers, &sender_device_id);
+                                let name =
+                                    resolve_peer_name(&self.devices, peers, &sender_device_id);
                                 *toast_message = Some((
-                                    format!("Clipboard auto-applied from {name}"),
+                                    labelled(
+                                        label.as_deref(),
+                                        &format!("Clipboard auto-applied from {name}"),
+                                    ),
                                     now_unix_ms(),
                                 ));
                                 // New system toast for auto-apply
-                                let preview = preview_text(&text, 100);
-                                show_system_notification("Clipboard auto-applied", &format!("From {}: {}", name, preview));
+                                if self.ui_state.auto_paste_allowed
+                                    && self.ui_state.auto_paste_after_apply
+                                {
+                                    schedule_auto_paste(self.ui_state.auto_paste_excluded.clone());
+                                }
+                                if notify {
+                                    let preview =
+                                        labelled(label.as_deref(), &preview_text(&text, 100));
+                                    show_system_notification(
+                                        "Clipboard auto-applied",
+                                        &format!("From {}: {}", name, preview),
+                                    );
+                                }
+                            }
+                            // Still listed, so it stays within reach.
+                            if pinned {
+                                push_notification(
+                                    notifications,
+                                    coalesced_notifications,
+                                    self.ui_state.notification_limit,
+                                    history,
+                                    self.ui_state.history_retention,
+                                    Notification::Text {
+                                        sender_device_id,
+                                        preview: preview_text(&text, 450),
+                                        links: found_links,
+                                        full_text: text,
+                                        content_hash,
+                                        label,
+                                        language,
+                                    },
+                                    true,
+                                );
                             }
                         } else {
+                            if may_auto_apply {
+                                info!(
+                                    sender = %sender_device_id,
+                                    bytes = text.len(),
+                                    "asking before auto-applying large text"
+                                );
+                                self.large_apply_prompt = Some(LargeApplyPrompt {
+                                    sender_name: resolve_peer_name(
+                                        &self.devices,
+                                        peers,
+                                        &sender_device_id,
+                                    ),
+                                    text: text.clone(),
+                                    content_hash,
+                                    label: label.clone(),
+                                });
+                                *window_visible = true;
+                                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                            }
                             // New system toast for manual notification
-                            let peer_name = resolve_peer_name(peers, &sender_device_id);
-                            let preview = preview_text(&text, 100);
-                            show_system_notification("New clipboard received", &format!("From {}: {}", peer_name, preview));
-                            
+                            if notify {
+                                let peer_name =
+                                    resolve_peer_name(&self.devices, peers, &sender_device_id);
+                                let preview = labelled(label.as_deref(), &preview_text(&text, 100));
+                                show_system_notification(
+                                    "New clipboard received",
+                                    &format!("From {}: {}", peer_name, preview),
+                                );
+                            }
+
+                            let first_link = found_links.first().cloned();
                             push_notification(
                                 notifications,
+                                coalesced_notifications,
+                                self.ui_state.notification_limit,
+                                history,
+                                self.ui_state.history_retention,
                                 Notification::Text {
                                     sender_device_id,
                                     preview: preview_text(&text, 450),
+                                    links: found_links,
                                     full_text: text,
                                     content_hash,
+                                    label: label.clone(),
+                                    language,
                                 },
+                                pinned,
                             );
                             if *active_tab != Tab::Notifications {
-                                *toast_message =
-                                    Some(("New clipboard received".to_string(), now_unix_ms()));
+                                let now = now_unix_ms();
+                                let message = if first_link.is_some() {
+                                    "Link received"
+                                } else if pinned {
+                                    "Pinned text received"
+                                } else {
+                                    "New clipboard received"
+                                };
+                                *toast_message = Some((labelled(label.as_deref(), message), now));
+                                if let Some(link) = first_link {
+                                    self.toast_link = Some((link, now));
+                                }
                             }
                         }
                     }
@@ -1113,58 +2819,490 @@ mod windows_client {
                         file_name,
                         temp_path,
                         size_bytes,
+                        signer_key,
+                        sha256,
                     } => {
-                        history.push_front(ActivityEntry {
-                            ts_unix_ms: now_unix_ms(),
-                            direction: ActivityDirection::Received,
-                            peer_device_id: sender_device_id.clone(),
-                            kind: "file".to_owned(),
-                            summary: format!("{file_name} ({size_bytes} bytes)"),
-                        });
-                        while history.len() > MAX_HISTORY_ENTRIES {
-                            history.pop_back();
+                        if self.devices.is_blocked(&sender_device_id) {
+                            info!(sender = %sender_device_id, "discarding file from blocked device");
+                            let _ = std::fs::remove_file(&temp_path);
+                            continue;
                         }
-                        save_history(history);
-// New system toast for file
-                        let peer_name = resolve_peer_name(peers, &sender_device_id);
-                        show_system_notification("New file received", &format!("{} ({size_bytes} bytes) from {}", file_name, peer_name));
-
-                        let preview = format!(
-                            "File: {file_name}\nSize: {size_bytes} bytes\n\n\
-                             Click Save to store it in Downloads\\ClipRelay."
+                        let verified = check_sender_key(
+                            &mut self.devices,
+                            peers,
+                            &sender_device_id,
+                            signer_key.as_deref(),
+                        );
+                        let policy = self.devices.file_policy(&sender_device_id);
+                        let peer_name = resolve_peer_name(&self.devices, peers, &sender_device_id);
+                        self.usage_stats.record_received(
+                            &local_date_key(),
+                            UsageKind::File,
+                            size_bytes,
+                            &sender_device_id,
+                            &peer_name,
+                        );
+                        save_usage_stats_logged(&self.usage_stats);
+                        let rejected = policy == FilePolicy::Reject;
+                        let sha256 = hex::encode(sha256);
+                        push_history(
+                            history,
+                            self.ui_state.history_retention,
+                            ActivityEntry {
+                                ts_unix_ms: now_unix_ms(),
+                                direction: ActivityDirection::Received,
+                                peer_device_id: sender_device_id.clone(),
+                                kind: "file".to_owned(),
+                                summary: if rejected {
+                                    format!("{file_name} ({size_bytes} bytes) - rejected")
+                                } else {
+                                    format!("{file_name} ({size_bytes} bytes)")
+                                },
+                                link: None,
+                                delivery: None,
+                                send_id: None,
+                                pinned: false,
+                                favorite: false,
+                                repeat_count: 1,
+                                content_hash: None,
+                                checksum: (!rejected).then(|| sha256.clone()),
+                                selected: false,
+                            },
                         );
+                        if rejected {
+                            info!(sender = %sender_device_id, file = %file_name, "file rejected by device policy");
+                            let _ = std::fs::remove_file(&temp_path);
+                            *toast_message =
+                                Some((format!("Rejected file from {peer_name}"), now_unix_ms()));
+                            continue;
+                        }
+
+                        let notify = self.devices.wants_notifications(&sender_device_id)
+                            && !sound::dnd_active(self.ui_state.dnd_schedule, local_hour());
+                        if notify && self.ui_state.sound_on_file {
+                            play_notification_sound(&self.ui_state.sound_source);
+                        }
+                        // Quarantine holds every file until it is accepted.
+                        if policy == FilePolicy::AutoSave
+                            && verified
+                            && !self.ui_state.files_quarantine
+                        {
+                            let dest_dir = received_files_dir(&self.ui_state, &peer_name);
+                            match save_received_file(
+                                &temp_path,
+                                &file_name,
+                                &dest_dir,
+                                mark_of_the_web_source(&self.ui_state, &config.server_url),
+                            ) {
+                                Ok(dest) => {
+                                    let _ = std::fs::remove_file(&temp_path);
+                                    if notify {
+                                        show_system_notification(
+                                            "File saved",
+                                            &format!(
+                                                "{file_name} from {peer_name} saved to {}",
+                                                dest.display()
+                                            ),
+                                        );
+                                    }
+                                    *toast_message = Some((
+                                        format!(
+                                            "Saved file from {peer_name} to {}",
+                                            dest.display()
+                                        ),
+                                        now_unix_ms(),
+                                    ));
+                                    continue;
+                                }
+                                // Fall back to prompting so the file is not lost.
+                                Err(err) => warn!("auto-save failed: {err}"),
+                            }
+                        }
+                        // New system toast for file
+                        if notify {
+                            show_system_notification(
+                                "New file received",
+                                &format!("{} ({size_bytes} bytes) from {}", file_name, peer_name),
+                            );
+                        }
+
+                        let preview = if self.ui_state.files_quarantine {
+                            format!(
+                                "File: {file_name}\nSize: {size_bytes} bytes\nSHA-256: {sha256}\n\n\
+                                 Held in quarantine. Check the checksum, then click Accept & Save \
+                                 to store it in {}.",
+                                received_files_dir(&self.ui_state, &peer_name).display()
+                            )
+                        } else {
+                            format!(
+                                "File: {file_name}\nSize: {size_bytes} bytes\nSHA-256: {sha256}\n\n\
+                                 Click Save to store it in {}.",
+                                received_files_dir(&self.ui_state, &peer_name).display()
+                            )
+                        };
                         push_notification(
                             notifications,
+                            coalesced_notifications,
+                            self.ui_state.notification_limit,
+                            history,
+                            self.ui_state.history_retention,
                             Notification::File {
                                 sender_device_id,
                                 preview,
                                 file_name,
                                 temp_path,
+                                sha256,
                             },
+                            false,
                         );
                         if *active_tab != Tab::Notifications {
                             *toast_message = Some(("New file received".to_string(), now_unix_ms()));
                         }
                     }
-                    UiEvent::RuntimeError(message) => {
-                        *last_error = Some(message.clone());
+                    UiEvent::NetworkCost(cost) => {
+                        if cost.is_metered() && !metered.cost.is_metered() {
+                            info!(cost = cost.label(), "connection is metered");
+                        }
+                        // The override applies to one metered stretch only.
+                        if !cost.is_metered() {
+                            metered.override_active = false;
+                        }
+                        metered.cost = cost;
+                    }
+                    UiEvent::RelayMaintenance(notice) => {
+                        let message = notice.describe(now_unix_ms());
+                        info!("{message}");
+                        show_system_notification("ClipRelay", &message);
+                        *toast_message = Some((message, now_unix_ms()));
+                    }
+                    UiEvent::ReconnectGaveUp(failures) => {
+                        let message = format!(
+                            "gave up reconnecting after {failures} failed attempt{}",
+                            if failures == 1 { "" } else { "s" }
+                        );
                         *connection_status = format!("Error: {message}");
-                        *room_key_ready = false;
+                        *last_error = Some(message);
+                        *reconnect_gave_up = true;
+                        if let Some(tray_state) = tray.as_mut() {
+                            tray_state.set_retry(true);
+                        }
+                        show_system_notification(
+                            "ClipRelay stopped reconnecting",
+                            "The relay could not be reached. Use Retry Now in the tray menu.",
+                        );
                     }
-                }
-            }
-
-            // ── Process tray / hotkey event flags ───────────────────────────
-            //
-            // The OS-level callbacks (tray icon, global hotkey) now call
-            // Win32 ShowWindow/SetForegroundWindow directly, so the actual
-            // show/hide has already happened by the time we get here.  This
-            // block syncs the local `window_visible` flag from the shared
-            // atomic and issues the corresponding ViewportCommands so that
-            // eframe's own visibility tracking stays consistent.
-            if self.tray_quit_requested.load(Ordering::SeqCst) {
+                    UiEvent::DeferredCount(count) => {
+                        if count > metered.deferred {
+                            *toast_message = Some((
+                                format!("Metered connection: send deferred ({count} waiting)"),
+                                now_unix_ms(),
+                            ));
+                        }
+                        metered.deferred = count;
+                    }
+                    UiEvent::AwaitingKeyCount(count) => {
+                        if count > *awaiting_key {
+                            *toast_message = Some((
+                                format!("Queued until the room key is ready ({count} waiting)"),
+                                now_unix_ms(),
+                            ));
+                        } else if count == 0 && *awaiting_key > 0 {
+                            *toast_message = Some((
+                                format!("Room key ready: sent {} queued item(s)", *awaiting_key),
+                                now_unix_ms(),
+                            ));
+                        }
+                        *awaiting_key = count;
+                    }
+                    UiEvent::SendStatus { id, status } => {
+                        match status {
+                            DeliveryStatus::Retrying { .. } => {
+                                *toast_message = Some((
+                                    "Connection lost: unsent item will be resent after reconnecting"
+                                        .to_string(),
+                                    now_unix_ms(),
+                                ));
+                            }
+                            DeliveryStatus::Failed => {
+                                *toast_message =
+                                    Some(("Send failed: see History".to_string(), now_unix_ms()));
+                            }
+                            DeliveryStatus::Pending
+                            | DeliveryStatus::Sent
+                            | DeliveryStatus::Cancelled => {}
+                        }
+                        set_delivery_status(history, id, status);
+                    }
+                    UiEvent::TransferProgress(progress) => {
+                        if progress.direction == TransferDirection::Incoming
+                            && self.devices.is_blocked(&progress.owner_device_id)
+                        {
+                            continue;
+                        }
+                        transfers.update(progress);
+                    }
+                    UiEvent::TransferEnded {
+                        owner_device_id,
+                        transfer_id,
+                        outcome,
+                    } => {
+                        let Some(ended) = transfers.finish(&owner_device_id, &transfer_id) else {
+                            continue;
+                        };
+                        let file_name = ended.progress.file_name;
+                        let message = match (outcome, ended.progress.direction) {
+                            (TransferOutcome::Cancelled(CancelSource::Local), _) => {
+                                format!("Cancelled transfer of {file_name}")
+                            }
+                            (
+                                TransferOutcome::Cancelled(CancelSource::Peer),
+                                TransferDirection::Outgoing,
+                            ) => format!("A receiver cancelled {file_name}"),
+                            (
+                                TransferOutcome::Cancelled(CancelSource::Peer),
+                                TransferDirection::Incoming,
+                            ) => {
+                                let peer_name =
+                                    resolve_peer_name(&self.devices, peers, &owner_device_id);
+                                format!("{peer_name} cancelled sending {file_name}")
+                            }
+                            (TransferOutcome::Failed, _) => {
+                                format!("Transfer of {file_name} failed")
+                            }
+                            (TransferOutcome::Completed, _) => continue,
+                        };
+                        *toast_message = Some((message, now_unix_ms()));
+                    }
+                    UiEvent::FileBlocked {
+                        sender_device_id,
+                        file_name,
+                        size_bytes,
+                    } => {
+                        if self.devices.is_blocked(&sender_device_id) {
+                            continue;
+                        }
+                        let peer_name = resolve_peer_name(&self.devices, peers, &sender_device_id);
+                        push_history(
+                            history,
+                            self.ui_state.history_retention,
+                            ActivityEntry {
+                                ts_unix_ms: now_unix_ms(),
+                                direction: ActivityDirection::Received,
+                                peer_device_id: sender_device_id,
+                                kind: "file".to_owned(),
+                                summary: format!(
+                                    "{file_name} ({size_bytes} bytes) - blocked file type"
+                                ),
+                                link: None,
+                                delivery: None,
+                                send_id: None,
+                                pinned: false,
+                                favorite: false,
+                                repeat_count: 1,
+                                content_hash: None,
+                                checksum: None,
+                                selected: false,
+                            },
+                        );
+                        *toast_message = Some((
+                            format!("Blocked {file_name} from {peer_name} (file type not allowed)"),
+                            now_unix_ms(),
+                        ));
+                    }
+                    UiEvent::DuplicateSkipped {
+                        sender_device_id,
+                        preview,
+                    } => {
+                        if self.devices.is_blocked(&sender_device_id) {
+                            continue;
+                        }
+                        push_history(
+                            history,
+                            self.ui_state.history_retention,
+                            ActivityEntry {
+                                ts_unix_ms: now_unix_ms(),
+                                direction: ActivityDirection::Received,
+                                peer_device_id: sender_device_id,
+                                kind: "text".to_owned(),
+                                summary: format!("duplicate skipped: {preview}"),
+                                link: None,
+                                delivery: None,
+                                send_id: None,
+                                pinned: false,
+                                favorite: false,
+                                repeat_count: 1,
+                                content_hash: None,
+                                checksum: None,
+                                selected: false,
+                            },
+                        );
+                    }
+                    UiEvent::RuntimeError(message) => {
+                        *last_error = Some(message.clone());
+                        *connection_status = format!("Error: {message}");
+                        *room_key_ready = false;
+                    }
+                    UiEvent::ProtocolFrame(summary) => {
+                        // Late events after the trace was switched off are
+                        // dropped rather than shown.
+                        if self.ui_state.protocol_trace_enabled {
+                            self.protocol_trace.push(summary);
+                        }
+                    }
+                    UiEvent::Latency(sample) => self.latency.push(sample),
+                    UiEvent::ProbeLatency { device_id, latency } => {
+                        let device_name = if device_id == config.device_id {
+                            "This PC".to_owned()
+                        } else {
+                            peers
+                                .iter()
+                                .find(|peer| peer.device_id == device_id)
+                                .map(|peer| peer.device_name.clone())
+                                .unwrap_or_else(|| device_id.clone())
+                        };
+                        self.latency.record_probe(
+                            &device_id,
+                            ProbeReading {
+                                device_name,
+                                latency,
+                                at_unix_ms: now_unix_ms(),
+                            },
+                        );
+                    }
+                    UiEvent::SelfTestFinished(report) => {
+                        *self_test_running = false;
+                        *toast_message = Some((report.summary(), now_unix_ms()));
+                        *self_test_report = Some(report);
+                    }
+                    UiEvent::AutomationRequest { command, reply } => {
+                        let response = handle_automation_command(
+                            command,
+                            config,
+                            connection_status,
+                            peers,
+                            *room_key_ready,
+                            self.ui_state.sync_direction,
+                            &self.ui_state.text_transforms,
+                            self.ui_state.secret_check,
+                            self.ui_state.oversized_text,
+                            history,
+                            self.ui_state.history_retention,
+                            self.auth_gate
+                                .is_unlocked(self.ui_state.auth_gate, now_unix_ms()),
+                            &mut self.clip_ring,
+                            runtime_cmd_tx,
+                        );
+                        let _ = reply.send(response);
+                    }
+                    UiEvent::InstanceRequest { request, reply } => {
+                        let result = match request {
+                            InstanceRequest::SendFiles(paths) => {
+                                let mut queued = 0;
+                                let mut failures = Vec::new();
+                                for path in paths {
+                                    let response = handle_automation_command(
+                                        AutomationCommand::SendFile { path },
+                                        config,
+                                        connection_status,
+                                        peers,
+                                        *room_key_ready,
+                                        self.ui_state.sync_direction,
+                                        &self.ui_state.text_transforms,
+                                        self.ui_state.secret_check,
+                                        self.ui_state.oversized_text,
+                                        history,
+                                        self.ui_state.history_retention,
+                                        self.auth_gate
+                                            .is_unlocked(self.ui_state.auth_gate, now_unix_ms()),
+                                        &mut self.clip_ring,
+                                        runtime_cmd_tx,
+                                    );
+                                    match response.error {
+                                        None => queued += 1,
+                                        Some(err) => failures.push(err),
+                                    }
+                                }
+                                if queued > 0 {
+                                    *toast_message =
+                                        Some((format!("Queued {queued} file(s)"), now_unix_ms()));
+                                }
+                                match failures.first() {
+                                    None => Ok(()),
+                                    Some(err) => {
+                                        warn!(failed = failures.len(), "file send failed: {err}");
+                                        Err(format!(
+                                            "{err} ({} of {} files)",
+                                            failures.len(),
+                                            queued + failures.len()
+                                        ))
+                                    }
+                                }
+                            }
+                            InstanceRequest::SendText(text) => {
+                                let response = handle_automation_command(
+                                    AutomationCommand::SendText { text },
+                                    config,
+                                    connection_status,
+                                    peers,
+                                    *room_key_ready,
+                                    self.ui_state.sync_direction,
+                                    &self.ui_state.text_transforms,
+                                    self.ui_state.secret_check,
+                                    self.ui_state.oversized_text,
+                                    history,
+                                    self.ui_state.history_retention,
+                                    self.auth_gate
+                                        .is_unlocked(self.ui_state.auth_gate, now_unix_ms()),
+                                    &mut self.clip_ring,
+                                    runtime_cmd_tx,
+                                );
+                                response.error.map_or(Ok(()), Err)
+                            }
+                            InstanceRequest::ReloadConfig => match load_saved_config() {
+                                Ok(Some(_)) => {
+                                    info!("saved config changed — reconnecting");
+                                    self.pending_reconnect = true;
+                                    *toast_message = Some((
+                                        "Room settings changed — reconnecting".to_owned(),
+                                        now_unix_ms(),
+                                    ));
+                                    Ok(())
+                                }
+                                Ok(None) => Err("no saved room".to_owned()),
+                                Err(err) => Err(err),
+                            },
+                            InstanceRequest::OpenLink(raw) => match join_link::parse(&raw) {
+                                Ok(link) => {
+                                    info!(server = %link.server_url, "join link opened");
+                                    self.join_prompt = Some(link);
+                                    *window_visible = true;
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                                    Ok(())
+                                }
+                                Err(err) => {
+                                    warn!("ignoring join link: {err}");
+                                    Err(format!("invalid link: {err}"))
+                                }
+                            },
+                        };
+                        let _ = reply.send(result);
+                    }
+                }
+            }
+
+            // ── Process tray / hotkey event flags ───────────────────────────
+            //
+            // The OS-level callbacks (tray icon, global hotkey) now call
+            // Win32 ShowWindow/SetForegroundWindow directly, so the actual
+            // show/hide has already happened by the time we get here.  This
+            // block syncs the local `window_visible` flag from the shared
+            // atomic and issues the corresponding ViewportCommands so that
+            // eframe's own visibility tracking stays consistent.
+            if self.tray_quit_requested.load(Ordering::SeqCst) {
                 info!("update loop: tray_quit_requested=true - exiting");
                 trace!("[tray] update loop: tray_quit_requested=true - exiting");
+                self.remember_window_placement();
                 if let Err(err) = ui_state::save_ui_state_with_retry(&self.ui_state) {
                     warn!("failed to save ui_state on quit: {err}");
                 }
@@ -1195,28 +3333,279 @@ mod windows_client {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
                 }
             }
+            if self.quick_pick_requested.swap(false, Ordering::SeqCst) {
+                // The hotkey callback already showed the window natively.
+                self.quick_pick_open = true;
+                *window_visible = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            if self.panic_requested.swap(false, Ordering::SeqCst) {
+                if let Err(err) = clear_clipboard() {
+                    warn!("panic: failed to clear the clipboard: {err}");
+                }
+                self.clip_ring.clear();
+                *sync_paused = true;
+                *panicked = true;
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SetSyncPaused(true));
+                // Queued entries stay queued but no longer badge or toast.
+                for pending in notifications.iter_mut() {
+                    pending.seen = true;
+                }
+                *toast_message = None;
+                info!("panic hotkey: clipboard cleared, sync paused");
+            }
+            if !*sync_paused {
+                *panicked = false;
+            }
+            let tray_room_action = self
+                .room_action_requested
+                .lock()
+                .ok()
+                .and_then(|mut slot| slot.take());
+            if tray_room_action == Some(RoomAction::Reconnect) {
+                reconnect_requested = true;
+            } else if tray_room_action == Some(RoomAction::ReconnectNow) {
+                reconnect_now.notify_one();
+            } else if tray_room_action == Some(RoomAction::CopyInvite) {
+                if self.auth_gate.admit(self.ui_state.auth_gate, now_unix_ms()) {
+                    copy_invite_from_tray(config, self.ui_state.invite_qr);
+                } else {
+                    start_unlock(&mut self.pending_unlock, AfterUnlock::TrayCopyInvite);
+                }
+            } else if let Some(action) = tray_room_action {
+                room_action = Some(action);
+                if action.needs_window() {
+                    // The menu callback already showed the window natively.
+                    *window_visible = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+            }
+            let tray_snippet = self
+                .snippet_requested
+                .lock()
+                .ok()
+                .and_then(|mut slot| slot.take());
+            if let Some(index) = tray_snippet
+                && !self.auth_gate.admit(self.ui_state.auth_gate, now_unix_ms())
+            {
+                // A menu built before the lock closed can still list names.
+                start_unlock(&mut self.pending_unlock, AfterUnlock::TraySnippet(index));
+            } else if let Some(snippet) = tray_snippet.and_then(|index| self.snippets.get(index)) {
+                if !self.ui_state.sync_direction.allows_send() {
+                    show_system_notification(
+                        "Snippet not sent",
+                        "ClipRelay is in receive-only mode.",
+                    );
+                } else if connection_status == "Connected" {
+                    send_snippet(
+                        snippet,
+                        runtime_cmd_tx,
+                        history,
+                        self.ui_state.history_retention,
+                    );
+                    *toast_message =
+                        Some((format!("Sent snippet \"{}\"", snippet.name), now_unix_ms()));
+                } else {
+                    show_system_notification(
+                        "Snippet not sent",
+                        "ClipRelay is not connected to the room.",
+                    );
+                }
+            }
+
+            let tray_favorite_toggle = self
+                .favorite_toggle_requested
+                .lock()
+                .ok()
+                .and_then(|mut slot| slot.take());
+            if let Some(idx) =
+                tray_favorite_toggle.and_then(|ts| history.iter().position(|e| e.ts_unix_ms == ts))
+            {
+                toggle_history_favorite(history, self.ui_state.history_retention, idx);
+            }
+
+            let tray_queue_action = self
+                .queue_action_requested
+                .lock()
+                .ok()
+                .and_then(|mut slot| slot.take());
+            if let Some(action) = tray_queue_action
+                && !notifications.is_empty()
+            {
+                let all: Vec<usize> = (0..notifications.len()).collect();
+                Self::process_notifications(
+                    notifications,
+                    &all,
+                    action,
+                    peers,
+                    runtime_cmd_tx,
+                    &self.devices,
+                    &self.ui_state,
+                    &config.server_url,
+                    toast_message,
+                    // Focus is on the tray, not an application worth pasting into.
+                    &mut false,
+                );
+                if action == NotificationAction::Dismiss {
+                    *toast_message = Some(("Notifications dismissed".to_string(), now_unix_ms()));
+                }
+                if !*window_visible && let Some((message, _)) = toast_message.as_ref() {
+                    show_system_notification("ClipRelay", message);
+                }
+            }
+
+            let tray_action = self
+                .tray_action_requested
+                .lock()
+                .ok()
+                .and_then(|mut slot| slot.take());
+            let mut tray_action_message: Option<String> = None;
+            match tray_action {
+                Some(TrayAction::ShowNotifications) => {
+                    // The click callback already showed the window natively.
+                    *active_tab = Tab::Notifications;
+                    *window_visible = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                Some(TrayAction::TogglePause) => {
+                    *sync_paused = !*sync_paused;
+                    let _ = runtime_cmd_tx.send(RuntimeCommand::SetSyncPaused(*sync_paused));
+                    tray_action_message = Some(
+                        if *sync_paused {
+                            "Sync paused"
+                        } else {
+                            "Sync resumed"
+                        }
+                        .to_string(),
+                    );
+                }
+                Some(TrayAction::ApplyLastReceived) => {
+                    let last = self
+                        .clip_ring
+                        .items()
+                        .find(|item| item.source == RingSource::Received);
+                    tray_action_message = Some(match last {
+                        None => "Nothing received yet".to_string(),
+                        Some(item) => {
+                            let applied = transforms::run(
+                                &self.ui_state.text_transforms,
+                                Stage::Apply,
+                                &item.text,
+                            );
+                            match apply_received_text(&applied, &self.ui_state) {
+                                Ok(()) => {
+                                    let _ = runtime_cmd_tx.send(RuntimeCommand::MarkApplied(
+                                        sha256_bytes(item.text.as_bytes()),
+                                    ));
+                                    labelled(item.label.as_deref(), "Last received text applied")
+                                }
+                                Err(err) => {
+                                    warn!("apply last received failed: {err}");
+                                    "Failed to apply last received text".to_string()
+                                }
+                            }
+                        }
+                    });
+                }
+                Some(TrayAction::ToggleWindow | TrayAction::Nothing) | None => {}
+            }
+            if let Some(message) = tray_action_message {
+                if !*window_visible {
+                    show_system_notification("ClipRelay", &message);
+                }
+                *toast_message = Some((message, now_unix_ms()));
+            }
 
             // ── Update tray icon status ────────────────────────────────────────
+            // Pending entries count as read once the Notifications tab is on
+            // screen; the rest are shown as a badge on the tray icon.
+            let now = now_unix_ms();
+            if *window_visible && *active_tab == Tab::Notifications {
+                for pending in notifications.iter_mut() {
+                    pending.seen = true;
+                    pending.shown_unix_ms.get_or_insert(now);
+                }
+            }
+            // Auto-dismissed texts stay in history and the quick-pick list.
+            let auto_dismiss_secs = self.ui_state.notification_auto_dismiss_secs;
+            notifications.retain(|n| n.auto_dismiss_remaining(auto_dismiss_secs, now) != Some(0));
+            if notifications
+                .iter()
+                .any(|n| n.auto_dismiss_remaining(auto_dismiss_secs, now).is_some())
+            {
+                ctx.request_repaint_after(Duration::from_secs(1));
+            }
+            let unread = notifications.iter().filter(|n| !n.seen).count();
             let tray_status = compute_tray_status(connection_status, *room_key_ready);
             if let Some(tray_state) = tray.as_mut() {
-                tray_state.set_status(tray_status);
-                let status_label = match tray_status {
-                    TrayStatus::Red => "not connected",
-                    TrayStatus::Amber => "connecting",
-                    TrayStatus::Green => "connected",
+                // An unacknowledged unknown device keeps the icon red.
+                tray_state.set_status(if device_alerts.is_empty() && !*panicked {
+                    tray_status
+                } else {
+                    TrayStatus::Red
+                });
+                tray_state.set_unread(unread);
+                tray_state.set_progress(transfers.overall_fraction());
+                tray_state.set_pending(notifications.len());
+                tray_state.set_resume(*sync_paused);
+                tray_state.set_reconnect_now(reconnect::is_waiting(connection_status));
+                tray_state.set_recent(tray_recent_items(history));
+                tray_state.set_locked(
+                    !self
+                        .auth_gate
+                        .is_unlocked(self.ui_state.auth_gate, now_unix_ms()),
+                );
+                let status_label = if *panicked {
+                    "PANIC: clipboard cleared"
+                } else if !device_alerts.is_empty() {
+                    "ALERT: unknown device joined"
+                } else if *reconnect_gave_up {
+                    "gave up reconnecting"
+                } else if reconnect::is_waiting(connection_status) {
+                    connection_status.as_str()
+                } else {
+                    match tray_status {
+                        TrayStatus::Red => "not connected",
+                        TrayStatus::Amber => "connecting",
+                        TrayStatus::Green => "connected",
+                    }
                 };
                 let peer_count = peers
                     .iter()
                     .filter(|p| p.device_id != config.device_id)
                     .count();
                 let room_id_short = &config.room_id[..config.room_id.len().min(8)];
+                let unread_label = if unread > 0 {
+                    format!(" | {unread} unread")
+                } else {
+                    String::new()
+                };
+                let transfer_label = transfers
+                    .summary(now_unix_ms())
+                    .map(|summary| format!("\n{summary}"))
+                    .unwrap_or_default();
+                let direction_label = if *sync_paused {
+                    " | sync paused".to_owned()
+                } else {
+                    self.ui_state
+                        .sync_direction
+                        .status_marker()
+                        .map(|marker| format!(" | {marker}"))
+                        .unwrap_or_default()
+                };
                 tray_state.set_tooltip(&format!(
-                    "ClipRelay | {} | {} peer{} | {} ({})",
+                    "ClipRelay | {}{} | {} peer{} | {} ({}){}{}",
                     status_label,
+                    direction_label,
                     peer_count,
                     if peer_count == 1 { "" } else { "s" },
                     config.room_code,
                     room_id_short,
+                    unread_label,
+                    transfer_label,
                 ));
             }
 
@@ -1232,8 +3621,102 @@ mod windows_client {
                 }
             }
 
+            // ── Esc hides the window, unless it closes something on top ────
+            let overlay_open = self.quick_pick_open
+                || self.secret_prompt.is_some()
+                || self.link_prompt.is_some()
+                || self.large_apply_prompt.is_some()
+                || self.join_prompt.is_some();
+            if *window_visible
+                && !overlay_open
+                && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+            {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                *window_visible = false;
+                self.shared_visible.store(false, Ordering::SeqCst);
+            }
+            if !*window_visible {
+                self.send_box_focused = false;
+            }
+
             // ── Render UI ──────────────────────────────────────────────────────
 
+            // Security alert banner: devices never seen before that joined
+            // the room.  Shown above everything until acknowledged.
+            if !device_alerts.is_empty() {
+                let mut resolved: Vec<String> = Vec::new();
+                egui::TopBottomPanel::top("device_alert")
+                    .frame(
+                        egui::Frame::side_top_panel(&ctx.style())
+                            .fill(egui::Color32::from_rgb(120, 20, 20)),
+                    )
+                    .show(ctx, |ui| {
+                        for peer in device_alerts.iter() {
+                            let id_short = &peer.device_id[..8.min(peer.device_id.len())];
+                            ui.colored_label(
+                                egui::Color32::WHITE,
+                                egui::RichText::new(format!(
+                                    "\u{26A0} Unknown device joined: {} ({id_short}\u{2026})",
+                                    peer.device_name
+                                ))
+                                .strong(),
+                            );
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("Trust")
+                                    .on_hover_text("I recognise this device.")
+                                    .clicked()
+                                {
+                                    if let Some(device) = self.devices.get_mut(&peer.device_id) {
+                                        device.trust = TrustState::Trusted;
+                                    }
+                                    save_devices_logged(&self.devices);
+                                    resolved.push(peer.device_id.clone());
+                                }
+                                if ui
+                                    .button("Block")
+                                    .on_hover_text("Discard text and files from this device.")
+                                    .clicked()
+                                {
+                                    if let Some(device) = self.devices.get_mut(&peer.device_id) {
+                                        device.trust = TrustState::Blocked;
+                                    }
+                                    save_devices_logged(&self.devices);
+                                    resolved.push(peer.device_id.clone());
+                                }
+                                if ui.button("Dismiss").clicked() {
+                                    resolved.push(peer.device_id.clone());
+                                }
+                            });
+                        }
+                        ui.horizontal(|ui| {
+                            if !*sync_paused
+                                && ui
+                                    .button("Pause Sync")
+                                    .on_hover_text(
+                                        "Stop sending and receiving until you resume \
+                                         (from the status bar).",
+                                    )
+                                    .clicked()
+                            {
+                                *sync_paused = true;
+                                let _ = runtime_cmd_tx.send(RuntimeCommand::SetSyncPaused(true));
+                            }
+                            if ui
+                                .button("Change Room Code\u{2026}")
+                                .on_hover_text(
+                                    "Leave this room and pick a new code. Other trusted \
+                                     devices must switch to the new code too.",
+                                )
+                                .clicked()
+                            {
+                                room_action = Some(RoomAction::Change);
+                            }
+                        });
+                    });
+                device_alerts.retain(|peer| !resolved.contains(&peer.device_id));
+            }
+
             // Top panel: tab bar
             egui::TopBottomPanel::top("tab_bar").show(ctx, |ui| {
                 ui.horizontal(|ui| {
@@ -1246,6 +3729,8 @@ mod windows_client {
                         format!("Notifications ({})", notifications.len())
                     };
                     ui.selectable_value(active_tab, Tab::Notifications, notif_label);
+                    ui.selectable_value(active_tab, Tab::Devices, "Devices");
+                    ui.selectable_value(active_tab, Tab::Snippets, "Snippets");
                 });
             });
 
@@ -1253,11 +3738,7 @@ mod windows_client {
             egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     // Status indicator circle
-                    let color = match tray_status {
-                        TrayStatus::Green => egui::Color32::from_rgb(0, 180, 0),
-                        TrayStatus::Amber => egui::Color32::from_rgb(255, 180, 0),
-                        TrayStatus::Red => egui::Color32::from_rgb(220, 30, 30),
-                    };
+                    let color = tray_status_color(tray_status);
                     let (rect, _) =
                         ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
                     ui.painter().circle_filled(rect.center(), 6.0, color);
@@ -1269,12 +3750,60 @@ mod windows_client {
                     } else {
                         "pending"
                     };
+                    let direction_text = self
+                        .ui_state
+                        .sync_direction
+                        .status_marker()
+                        .map(|marker| format!(" | {marker}"))
+                        .unwrap_or_default();
                     ui.label(format!(
-                        "{} | peers={} | room_key={}",
+                        "{} | peers={} | room_key={}{}",
                         connection_status,
                         peers.len(),
-                        room_key_text
+                        room_key_text,
+                        direction_text
                     ));
+                    if *sync_paused {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(200, 60, 60),
+                            if *panicked {
+                                "panic: clipboard cleared, sync paused"
+                            } else {
+                                "sync paused"
+                            },
+                        );
+                        if ui.small_button("Resume").clicked() {
+                            *sync_paused = false;
+                            let _ = runtime_cmd_tx.send(RuntimeCommand::SetSyncPaused(false));
+                        }
+                    }
+                    if metered.cost.is_metered() {
+                        ui.colored_label(egui::Color32::from_rgb(200, 120, 0), "metered");
+                    }
+                    if *reconnect_gave_up && ui.small_button("Retry Now").clicked() {
+                        reconnect_requested = true;
+                    }
+                    if reconnect::is_waiting(connection_status)
+                        && ui
+                            .small_button("Reconnect Now")
+                            .on_hover_text("Skip the rest of the wait and connect now.")
+                            .clicked()
+                    {
+                        reconnect_now.notify_one();
+                    }
+                    if *awaiting_key > 0 {
+                        ui.label(format!("{awaiting_key} waiting for key"))
+                            .on_hover_text("Sent as soon as the room key is ready.");
+                    }
+                    if metered.deferred > 0 {
+                        ui.label(format!("{} deferred", metered.deferred));
+                        if ui.small_button("Send Now").clicked() {
+                            metered.override_active = true;
+                            let _ = runtime_cmd_tx.send(RuntimeCommand::SetMeteredPolicy(
+                                metered_policy(&self.ui_state, true),
+                            ));
+                        }
+                    }
                 });
 
                 // Toast message (fades after 4 seconds)
@@ -1284,11 +3813,127 @@ mod windows_client {
                 if toast_expired {
                     *toast_message = None;
                 }
-                if let Some((msg, _ts)) = toast_message.as_ref() {
-                    ui.colored_label(egui::Color32::from_rgb(0, 120, 215), msg.as_str());
+                if let Some((msg, ts)) = toast_message.as_ref() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::from_rgb(0, 120, 215), msg.as_str());
+                        if let Some((link, _)) = self
+                            .toast_link
+                            .as_ref()
+                            .filter(|(_, link_ts)| link_ts == ts)
+                            && ui
+                                .small_button("Open in Browser")
+                                .on_hover_text(link.as_str())
+                                .clicked()
+                            && let Err(err) = open_url(link)
+                        {
+                            warn!("open link failed: {err}");
+                        }
+                    });
                 }
             });
 
+            // Persisted preferences edited from the Options tab.  Bound here
+            // (not at the top) because the quit path above reads
+            // `self.ui_state` while this borrow would otherwise be live.
+            let ui_prefs = &mut self.ui_state;
+            let prev_ui_prefs = ui_prefs.clone();
+            let clip_ring = &mut self.clip_ring;
+            let known_devices = &mut self.devices;
+            let mut devices_changed = false;
+            let snippet_library = &mut self.snippets;
+            let latency = &self.latency;
+            let usage_stats = &mut self.usage_stats;
+            let secret_prompt = &mut self.secret_prompt;
+            let send_box_focused = &mut self.send_box_focused;
+            let room_profiles = &mut self.room_profiles;
+            let room_profiles_error = self.room_profiles_error.as_deref();
+            let mut room_profiles_changed = false;
+            let mut view_logs_requested = false;
+            let mut protocol_trace_requested = false;
+            let mut snippets_changed = false;
+            let mut switch_to_send = false;
+            let connected =
+                connection_status == "Connected" && ui_prefs.sync_direction.allows_send();
+            let auth_gate = &mut self.auth_gate;
+            let unlocking = self.pending_unlock.is_some();
+            let mut unlock_request: Option<AfterUnlock> = None;
+
+            let answer =
+                self.pending_unlock
+                    .as_ref()
+                    .and_then(|pending| match pending.answer.try_recv() {
+                        Ok(outcome) => Some(outcome),
+                        Err(std::sync::mpsc::TryRecvError::Empty) => None,
+                        Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                            Some(AuthOutcome::Failed("the prompt closed".to_owned()))
+                        }
+                    });
+            if let Some(outcome) = answer
+                && let Some(PendingUnlock { then, .. }) = self.pending_unlock.take()
+            {
+                match outcome {
+                    AuthOutcome::Verified => {
+                        info!("history, snippets and invites unlocked");
+                        auth_gate.unlock(now_unix_ms());
+                        match then {
+                            AfterUnlock::Show => {}
+                            AfterUnlock::CopyJoinLink => {
+                                let link = join_link::build(&config.server_url, &config.room_code);
+                                *toast_message = Some((
+                                    match apply_clipboard_text(&link) {
+                                        Ok(()) => "Join link copied".to_string(),
+                                        Err(err) => format!("Failed to copy join link: {err}"),
+                                    },
+                                    now_unix_ms(),
+                                ));
+                            }
+                            AfterUnlock::CopyInvite => {
+                                *toast_message = Some((
+                                    match copy_invite(config, ui_prefs.invite_qr) {
+                                        Ok(()) => "Invite copied".to_string(),
+                                        Err(err) => format!("Failed to copy invite: {err}"),
+                                    },
+                                    now_unix_ms(),
+                                ));
+                            }
+                            AfterUnlock::TrayCopyInvite => {
+                                copy_invite_from_tray(config, ui_prefs.invite_qr);
+                            }
+                            AfterUnlock::TraySnippet(index) => {
+                                // Sent by the tray handler on the next frame.
+                                if let Ok(mut slot) = self.snippet_requested.lock() {
+                                    *slot = Some(index);
+                                }
+                                ctx.request_repaint();
+                            }
+                            AfterUnlock::EnableLock => {
+                                ui_prefs.auth_gate.enabled = true;
+                                *toast_message = Some((
+                                    "History, snippets and invites are now locked when unused"
+                                        .to_string(),
+                                    now_unix_ms(),
+                                ));
+                            }
+                            AfterUnlock::DisableLock => {
+                                ui_prefs.auth_gate.enabled = false;
+                                *toast_message = Some((
+                                    "History, snippets and invites are no longer locked"
+                                        .to_string(),
+                                    now_unix_ms(),
+                                ));
+                            }
+                        }
+                    }
+                    AuthOutcome::Canceled => {
+                        *toast_message = Some(("Still locked".to_string(), now_unix_ms()));
+                    }
+                    AuthOutcome::Failed(reason) => {
+                        warn!("unlock failed: {reason}");
+                        *toast_message = Some((format!("Not unlocked: {reason}"), now_unix_ms()));
+                    }
+                }
+            }
+
             // Central panel: active tab content
             egui::CentralPanel::default().show(ctx, |ui| {
                 match active_tab {
@@ -1296,12 +3941,35 @@ mod windows_client {
                         Self::render_send_tab(
                             ui,
                             send_text,
+                            send_pinned,
+                            send_label,
                             connection_status,
-                            *room_key_ready,
                             runtime_cmd_tx,
                             history,
+                            clip_ring,
+                            peers,
+                            known_devices,
+                            &config.device_id,
+                            ui_prefs.sync_direction,
+                            &ui_prefs.text_transforms,
+                            ui_prefs.history_retention,
+                            ui_prefs.secret_check,
+                            ui_prefs.oversized_text,
+                            secret_prompt,
                             toast_message,
+                            send_box_focused,
                         );
+                        transfers.prune_stale(now_unix_ms(), TRANSFER_TIMEOUT_MS);
+                        if !transfers.is_empty() {
+                            ui.add_space(8.0);
+                            ui.separator();
+                            Self::render_transfers(
+                                ui,
+                                transfers,
+                                cancelled_transfers,
+                                runtime_cmd_tx,
+                            );
+                        }
                     }
                     Tab::Options => {
                         Self::render_options_tab(
@@ -1314,13 +3982,34 @@ mod windows_client {
                             last_received_time,
                             auto_apply,
                             autostart_enabled,
+                            explorer_menu_enabled,
+                            send_to_enabled,
+                            url_handler_enabled,
                             last_error,
                             history, // &mut — needed for Clear History
+                            clip_ring,
                             runtime_cmd_tx,
                             hotkey_label,
+                            ui_prefs,
+                            known_devices,
                             toast_message,
-                            &mut change_room_requested,
+                            self_test_running,
+                            self_test_report.as_ref(),
+                            latency,
+                            usage_stats,
+                            room_profiles,
+                            room_profiles_error,
+                            &mut room_profiles_changed,
+                            &mut room_action,
+                            &mut config_change,
                             &mut reconnect_requested,
+                            &mut view_logs_requested,
+                            &mut protocol_trace_requested,
+                            metered,
+                            options_page,
+                            auth_gate,
+                            unlocking,
+                            &mut unlock_request,
                         );
                     }
                     Tab::Notifications => {
@@ -1330,16 +4019,741 @@ mod windows_client {
                             peers,
                             runtime_cmd_tx,
                             history,
+                            coalesced_notifications,
+                            known_devices,
+                            ui_prefs,
+                            &config.server_url,
                             toast_message,
+                            &mut paste_requested,
                         );
+                        // New arrivals are marked seen (and the tray badge
+                        // cleared) at the top of the next frame.
+                        if *window_visible && notifications.iter().any(|n| !n.seen) {
+                            ui.ctx().request_repaint();
+                        }
                     }
-                }
-            });
-
-            // ── Handle global hotkey change from Options tab ───────────────
-            if *hotkey_label != prev_hotkey_label {
-                // Unregister previous hotkey if any.
-                if let (Some(old_hk), Some(mgr)) =
+                    Tab::Devices => {
+                        devices_changed = Self::render_devices_tab(
+                            ui,
+                            known_devices,
+                            peers,
+                            usage_stats,
+                            toast_message,
+                        );
+                    }
+                    Tab::Snippets => {
+                        if auth_gate.admit(ui_prefs.auth_gate, now_unix_ms()) {
+                            snippets_changed = Self::render_snippets_tab(
+                                ui,
+                                snippet_library,
+                                self.snippets_error.as_deref(),
+                                send_text,
+                                connected,
+                                runtime_cmd_tx,
+                                history,
+                                ui_prefs.history_retention,
+                                toast_message,
+                                &mut switch_to_send,
+                            );
+                        } else if Self::render_locked(ui, "Snippets", unlocking) {
+                            unlock_request = Some(AfterUnlock::Show);
+                        }
+                    }
+                }
+            });
+            if devices_changed {
+                save_devices_logged(known_devices);
+            }
+            if snippets_changed {
+                if let Err(err) = snippets::save_snippets(snippet_library) {
+                    warn!("failed to save snippets: {err}");
+                    *toast_message =
+                        Some((format!("Failed to save snippets: {err}"), now_unix_ms()));
+                }
+                if let Some(tray_state) = tray.as_mut() {
+                    tray_state.set_snippets(&snippet_library.names());
+                }
+            }
+            if room_profiles_changed {
+                if let Err(err) = room_profiles::save_profiles(room_profiles) {
+                    warn!("failed to save room profiles: {err}");
+                    *toast_message = Some((
+                        format!("Failed to save room profiles: {err}"),
+                        now_unix_ms(),
+                    ));
+                }
+                if let Some(tray_state) = tray.as_mut() {
+                    tray_state.set_profiles(
+                        &room_profiles.names(),
+                        room_profiles.active_index(
+                            &config.server_url,
+                            &config.room_code,
+                            &config.device_name,
+                        ),
+                    );
+                }
+            }
+            if let Some(then) = unlock_request {
+                start_unlock(&mut self.pending_unlock, then);
+            }
+            if switch_to_send {
+                *active_tab = Tab::Send;
+            }
+            // ── First-run onboarding ───────────────────────────────────────
+            if let Some(step) = self.onboarding {
+                let mut open = true;
+                let mut next_step = Some(step);
+                egui::Window::new("Welcome to ClipRelay")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        ui.set_max_width(380.0);
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Step {} of {}: {}",
+                                step.number(),
+                                OnboardingStep::ALL.len(),
+                                step.title()
+                            ))
+                            .strong(),
+                        );
+                        ui.add_space(8.0);
+                        match step {
+                            OnboardingStep::TrayIcon => {
+                                ui.label(
+                                    "ClipRelay lives in the notification area.  Its icon \
+                                     shows the state of the room:",
+                                );
+                                ui.add_space(4.0);
+                                for (status, meaning) in [
+                                    (TrayStatus::Red, "cannot reach the relay"),
+                                    (TrayStatus::Amber, "connected, waiting for another device"),
+                                    (TrayStatus::Green, "ready to send and receive"),
+                                ] {
+                                    ui.horizontal(|ui| {
+                                        let (rect, _) = ui.allocate_exact_size(
+                                            egui::vec2(12.0, 12.0),
+                                            egui::Sense::hover(),
+                                        );
+                                        ui.painter().circle_filled(
+                                            rect.center(),
+                                            6.0,
+                                            tray_status_color(status),
+                                        );
+                                        ui.label(meaning);
+                                    });
+                                }
+                                ui.add_space(4.0);
+                                ui.label(
+                                    "Click the icon to show or hide this window; \
+                                     right-click it for the menu.",
+                                );
+                            }
+                            OnboardingStep::Startup => {
+                                Self::autostart_checkbox(
+                                    ui,
+                                    autostart_enabled,
+                                    ui_prefs.autostart_backend,
+                                    toast_message,
+                                );
+                                Self::auto_apply_checkbox(
+                                    ui,
+                                    auto_apply,
+                                    runtime_cmd_tx,
+                                    toast_message,
+                                );
+                                ui.add_space(4.0);
+                                ui.label(
+                                    egui::RichText::new(
+                                        "Without auto-apply, received text waits in the \
+                                         Notifications tab until you apply it.  Both can be \
+                                         changed later in Options.",
+                                    )
+                                    .weak(),
+                                );
+                            }
+                            OnboardingStep::Hotkey => {
+                                let quick_pick = ui_prefs
+                                    .quick_pick_hotkey
+                                    .as_deref()
+                                    .unwrap_or(DEFAULT_QUICK_PICK_HOTKEY_LABEL);
+                                egui::Grid::new("onboarding_hotkeys").num_columns(2).show(
+                                    ui,
+                                    |ui| {
+                                        ui.label("Show or hide ClipRelay:");
+                                        ui.label(
+                                            egui::RichText::new(hotkey_label.as_str()).monospace(),
+                                        );
+                                        ui.end_row();
+                                        ui.label("Pick a recent clip:");
+                                        ui.label(egui::RichText::new(quick_pick).monospace());
+                                        ui.end_row();
+                                    },
+                                );
+                                ui.add_space(4.0);
+                                ui.label(
+                                    egui::RichText::new(
+                                        "The keys can be changed in Options \u{2192} General.",
+                                    )
+                                    .weak(),
+                                );
+                            }
+                            OnboardingStep::FirstPeer => {
+                                let others: Vec<&PeerInfo> =
+                                    onboarding::other_peers(peers, &config.device_id).collect();
+                                if others.is_empty() {
+                                    ui.label(
+                                        "Install ClipRelay on another device and join the \
+                                         same room.  An invite has everything it needs.",
+                                    );
+                                    ui.add_space(4.0);
+                                    if ui.button("Copy Invite").clicked() {
+                                        *toast_message = Some((
+                                            match copy_invite(config, ui_prefs.invite_qr) {
+                                                Ok(()) => "Invite copied".to_string(),
+                                                Err(err) => {
+                                                    format!("Failed to copy invite: {err}")
+                                                }
+                                            },
+                                            now_unix_ms(),
+                                        ));
+                                    }
+                                    ui.add_space(8.0);
+                                    ui.horizontal(|ui| {
+                                        ui.spinner();
+                                        if connection_status == "Connected" {
+                                            ui.label("Waiting for another device to join\u{2026}");
+                                        } else {
+                                            ui.label(format!("Relay: {connection_status}"));
+                                        }
+                                    });
+                                } else {
+                                    for peer in &others {
+                                        ui.label(
+                                            egui::RichText::new(format!(
+                                                "\u{2714} {} joined",
+                                                peer.device_name
+                                            ))
+                                            .color(tray_status_color(TrayStatus::Green)),
+                                        );
+                                    }
+                                    ui.add_space(4.0);
+                                    if *room_key_ready {
+                                        ui.label("You are paired: copy something and send it.");
+                                    } else {
+                                        ui.horizontal(|ui| {
+                                            ui.spinner();
+                                            ui.label("Setting up the room key\u{2026}");
+                                        });
+                                    }
+                                }
+                            }
+                        }
+                        ui.add_space(12.0);
+                        ui.horizontal(|ui| {
+                            if let Some(prev) = step.prev()
+                                && ui.button("Back").clicked()
+                            {
+                                next_step = Some(prev);
+                            }
+                            match step.next() {
+                                Some(next) => {
+                                    if ui.button("Next").clicked() {
+                                        next_step = Some(next);
+                                    }
+                                    if ui.button("Skip").clicked() {
+                                        next_step = None;
+                                    }
+                                }
+                                None => {
+                                    if ui.button("Finish").clicked() {
+                                        next_step = None;
+                                    }
+                                }
+                            }
+                        });
+                    });
+                if !open {
+                    next_step = None;
+                }
+                self.onboarding = next_step;
+                if next_step.is_none() {
+                    ui_prefs.onboarding_done = true;
+                }
+            }
+            // ── Status widget ──────────────────────────────────────────────
+            if ui_prefs.status_widget.enabled {
+                let widget = ui_prefs.status_widget;
+                let peer_count = peers
+                    .iter()
+                    .filter(|p| p.device_id != config.device_id)
+                    .count();
+                let status = status_widget::status_line(connection_status, peer_count);
+                let last_item = history.front().map(|entry| {
+                    let arrow = match entry.direction {
+                        ActivityDirection::Sent => "\u{2191}",
+                        ActivityDirection::Received => "\u{2193}",
+                    };
+                    let who = if matches!(entry.direction, ActivityDirection::Received) {
+                        format!(
+                            "{}: ",
+                            resolve_peer_name(known_devices, peers, &entry.peer_device_id)
+                        )
+                    } else {
+                        String::new()
+                    };
+                    format!("{arrow} {who}{}", status_widget::preview(&entry.summary))
+                });
+                let tray_status = compute_tray_status(connection_status, *room_key_ready);
+                let mut open_main = false;
+                let mut close_widget = false;
+                let mut drag_started = false;
+                ctx.show_viewport_immediate(
+                    egui::ViewportId::from_hash_of("status_widget"),
+                    egui::ViewportBuilder::default()
+                        .with_title(STATUS_WIDGET_TITLE)
+                        .with_inner_size([status_widget::WIDGET_W_PX, status_widget::WIDGET_H_PX])
+                        .with_decorations(false)
+                        .with_resizable(false)
+                        .with_taskbar(false)
+                        .with_always_on_top()
+                        .with_mouse_passthrough(widget.click_through),
+                    |ctx, _class| {
+                        if ctx.input(|i| i.viewport().close_requested()) {
+                            close_widget = true;
+                        }
+                        egui::CentralPanel::default().show(ctx, |ui| {
+                            let response = ui.interact(
+                                ui.max_rect(),
+                                egui::Id::new("status_widget_body"),
+                                egui::Sense::click_and_drag(),
+                            );
+                            ui.horizontal(|ui| {
+                                ui.colored_label(tray_status_color(tray_status), "\u{25CF}");
+                                ui.label(egui::RichText::new(&status).strong());
+                            });
+                            ui.label(
+                                egui::RichText::new(
+                                    last_item.as_deref().unwrap_or("No activity yet"),
+                                )
+                                .weak(),
+                            );
+                            if response.drag_started() {
+                                ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+                                drag_started = true;
+                            }
+                            if response.double_clicked() {
+                                open_main = true;
+                            }
+                        });
+                        // Keep the status line current while nothing else
+                        // asks for a repaint.
+                        ctx.request_repaint_after(Duration::from_secs(1));
+                    },
+                );
+                if drag_started {
+                    self.status_widget_dragging = true;
+                }
+                // Place the widget when it first appears and when the corner
+                // is changed in Options; snap it to the nearest corner once a
+                // drag ends.
+                let dropped = self.status_widget_dragging && !ctx.input(|i| i.pointer.any_down());
+                if dropped || self.status_widget_placed != Some(widget.corner) {
+                    let hwnd = unsafe {
+                        use windows_sys::Win32::UI::WindowsAndMessaging::FindWindowW;
+                        let title = to_wide_null(STATUS_WIDGET_TITLE);
+                        FindWindowW(std::ptr::null(), title.as_ptr())
+                    };
+                    let wanted = if dropped { None } else { Some(widget.corner) };
+                    if hwnd != 0
+                        && let Some(corner) = unsafe { win32_snap_widget(hwnd, wanted) }
+                    {
+                        ui_prefs.status_widget.corner = corner;
+                        self.status_widget_placed = Some(corner);
+                        self.status_widget_dragging = false;
+                    }
+                }
+                if close_widget {
+                    ui_prefs.status_widget.enabled = false;
+                }
+                if open_main {
+                    let hwnd = unsafe {
+                        use windows_sys::Win32::UI::WindowsAndMessaging::FindWindowW;
+                        let title = to_wide_null("ClipRelay");
+                        FindWindowW(std::ptr::null(), title.as_ptr())
+                    };
+                    if hwnd != 0 {
+                        unsafe { win32_set_window_visible(hwnd, true) };
+                    }
+                    *window_visible = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+            } else {
+                self.status_widget_placed = None;
+                self.status_widget_dragging = false;
+            }
+
+            let ui_prefs_changed = *ui_prefs != prev_ui_prefs;
+
+            // ── Log viewer window ──────────────────────────────────────────
+            if view_logs_requested && self.log_viewer.is_none() {
+                self.log_viewer = Some(LogViewer::default());
+            }
+            if let Some(viewer) = self.log_viewer.as_mut() {
+                let mut open = true;
+                egui::Window::new("Logs")
+                    .open(&mut open)
+                    .default_size([760.0, 420.0])
+                    .show(ctx, |ui| Self::render_log_viewer(ui, viewer, toast_message));
+                if !open {
+                    self.log_viewer = None;
+                }
+            }
+
+            // ── Protocol trace window ──────────────────────────────────────
+            if protocol_trace_requested {
+                self.protocol_trace_open = true;
+            }
+            if !ui_prefs.protocol_trace_enabled {
+                self.protocol_trace_open = false;
+            }
+            if self.protocol_trace_open {
+                let mut open = true;
+                egui::Window::new("Protocol Trace")
+                    .open(&mut open)
+                    .default_size([760.0, 420.0])
+                    .show(ctx, |ui| {
+                        Self::render_protocol_trace(
+                            ui,
+                            &mut self.protocol_trace,
+                            known_devices,
+                            peers,
+                            toast_message,
+                        )
+                    });
+                if !open {
+                    self.protocol_trace_open = false;
+                }
+            }
+
+            // ── Link confirmation window ───────────────────────────────────
+            if let Some(prompt) = self.link_prompt.as_ref() {
+                let mut open = true;
+                let mut decided = false;
+                egui::Window::new("Open Link?")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label(format!("{} sent a link:", prompt.sender_name));
+                        ui.label(egui::RichText::new(&prompt.url).monospace());
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Open in Browser").clicked() {
+                                if let Err(err) = open_url(&prompt.url) {
+                                    warn!("open link failed: {err}");
+                                    *toast_message =
+                                        Some(("Failed to open link".to_string(), now_unix_ms()));
+                                }
+                                decided = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                decided = true;
+                            }
+                        });
+                    });
+                if !open || decided {
+                    self.link_prompt = None;
+                }
+            }
+
+            // ── Large text confirmation window ─────────────────────────────
+            if let Some(prompt) = self.large_apply_prompt.as_ref() {
+                let mut open = true;
+                let mut decided = false;
+                egui::Window::new("Apply Large Text?")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "{} sent {} of text. It was not applied automatically \
+                             because it is larger than {} KiB.",
+                            prompt.sender_name,
+                            transfers::format_bytes(prompt.text.len() as u64),
+                            ui_prefs.large_apply_confirm.normalized().threshold_kib
+                        ));
+                        egui::ScrollArea::vertical()
+                            .max_height(160.0)
+                            .show(ui, |ui| {
+                                ui.label(
+                                    egui::RichText::new(large_apply::preview(&prompt.text))
+                                        .monospace()
+                                        .weak(),
+                                );
+                            });
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Apply").clicked() {
+                                let text = transforms::run(
+                                    &ui_prefs.text_transforms,
+                                    Stage::Apply,
+                                    &prompt.text,
+                                );
+                                if let Err(err) = apply_received_text(&text, ui_prefs) {
+                                    warn!("apply failed: {err}");
+                                    *toast_message = Some((
+                                        "Failed to apply clipboard text".to_string(),
+                                        now_unix_ms(),
+                                    ));
+                                } else {
+                                    let _ = runtime_cmd_tx
+                                        .send(RuntimeCommand::MarkApplied(prompt.content_hash));
+                                    *toast_message = Some((
+                                        labelled(
+                                            prompt.label.as_deref(),
+                                            &format!(
+                                                "Clipboard applied from {}",
+                                                prompt.sender_name
+                                            ),
+                                        ),
+                                        now_unix_ms(),
+                                    ));
+                                    if ui_prefs.auto_paste_allowed
+                                        && ui_prefs.auto_paste_after_apply
+                                    {
+                                        schedule_auto_paste(ui_prefs.auto_paste_excluded.clone());
+                                    }
+                                }
+                                decided = true;
+                            }
+                            if ui
+                                .button("Not Now")
+                                .on_hover_text("The text stays in Notifications.")
+                                .clicked()
+                            {
+                                decided = true;
+                            }
+                        });
+                    });
+                if !open || decided {
+                    self.large_apply_prompt = None;
+                }
+            }
+
+            // ── Secret confirmation window ─────────────────────────────────
+            if let Some(prompt) = self.secret_prompt.as_ref() {
+                let mut open = true;
+                let mut send = false;
+                let mut decided = false;
+                egui::Window::new("Send Possible Secret?")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label(format!(
+                            "This text looks like it contains a secret: {}.",
+                            secrets::describe(&prompt.kinds)
+                        ));
+                        ui.label(
+                            "Every device in the room will receive it, and it may stay in \
+                             their history.",
+                        );
+                        ui.label(
+                            egui::RichText::new(preview_text(&prompt.send.text, 80))
+                                .monospace()
+                                .weak(),
+                        );
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            if ui.button("Send Anyway").clicked() {
+                                send = true;
+                                decided = true;
+                            }
+                            if ui.button("Don't Send").clicked() {
+                                decided = true;
+                            }
+                        });
+                    });
+                if send && let Some(prompt) = self.secret_prompt.take() {
+                    info!("held send confirmed");
+                    match prompt.origin {
+                        HeldSend::SendBox | HeldSend::SendBoxSplit => {
+                            send_text.clear();
+                            *send_pinned = false;
+                            send_label.clear();
+                            queue_send_box_text(
+                                prompt.send,
+                                prompt.origin == HeldSend::SendBoxSplit,
+                                history,
+                                ui_prefs.history_retention,
+                                clip_ring,
+                                runtime_cmd_tx,
+                                toast_message,
+                            );
+                        }
+                        HeldSend::TextFile => {
+                            queue_send_box_text(
+                                prompt.send,
+                                false,
+                                history,
+                                ui_prefs.history_retention,
+                                clip_ring,
+                                runtime_cmd_tx,
+                                toast_message,
+                            );
+                        }
+                        HeldSend::QuickPick => {
+                            let id = push_sent_text_history(
+                                history,
+                                ui_prefs.history_retention,
+                                &prompt.send,
+                                preview_text(&prompt.send.text, 120),
+                            );
+                            let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(id, prompt.send));
+                        }
+                    }
+                } else if !open || decided {
+                    self.secret_prompt = None;
+                }
+            }
+
+            // ── Join link confirmation window ──────────────────────────────
+            if let Some(link) = self.join_prompt.as_ref() {
+                let current =
+                    link.server_url == config.server_url && link.room_code == config.room_code;
+                let profile =
+                    self.room_profiles.profiles.iter().position(|p| {
+                        p.server_url == link.server_url && p.room_code == link.room_code
+                    });
+                let mut open = true;
+                let mut decided = false;
+                egui::Window::new("Join Room?")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(ctx, |ui| {
+                        ui.label("A ClipRelay link asks to join a room on:");
+                        ui.label(egui::RichText::new(&link.server_url).monospace());
+                        ui.add_space(4.0);
+                        if current {
+                            ui.label("You are already in this room.");
+                            if ui.button("Close").clicked() {
+                                decided = true;
+                            }
+                            return;
+                        }
+                        ui.horizontal(|ui| {
+                            if let Some(index) = profile {
+                                let name = &self.room_profiles.profiles[index].name;
+                                if ui
+                                    .button(format!("Switch to \u{201c}{name}\u{201d}"))
+                                    .clicked()
+                                {
+                                    room_action = Some(RoomAction::SwitchProfile(index));
+                                    decided = true;
+                                }
+                            }
+                            if ui
+                                .button("Join\u{2026}")
+                                .on_hover_text(
+                                    "Review the details in room setup, then Connect.\n\
+                                     Cancel there rejoins the current room.",
+                                )
+                                .clicked()
+                            {
+                                self.pending_join_link = Some(link.clone());
+                                room_action = Some(RoomAction::Change);
+                                decided = true;
+                            }
+                            if ui.button("Cancel").clicked() {
+                                decided = true;
+                            }
+                        });
+                    });
+                if !open || decided {
+                    self.join_prompt = None;
+                }
+            }
+
+            // ── Quick-pick overlay ─────────────────────────────────────────
+            let mut picked: Option<RingItem> = None;
+            if self.quick_pick_open {
+                let mut open = true;
+                egui::Window::new("Quick Pick")
+                    .open(&mut open)
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+                    .show(ctx, |ui| {
+                        picked = Self::render_quick_pick(ui, clip_ring, known_devices, peers);
+                    });
+                if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    self.quick_pick_open = false;
+                }
+            }
+            if let Some(item) = picked {
+                self.quick_pick_open = false;
+                match apply_clipboard_text(&item.text) {
+                    Ok(()) => {
+                        let _ = runtime_cmd_tx.send(RuntimeCommand::MarkApplied(sha256_bytes(
+                            item.text.as_bytes(),
+                        )));
+                        let can_send = connection_status == "Connected"
+                            && ui_prefs.sync_direction.allows_send();
+                        if ui_prefs.quick_pick_resend && can_send {
+                            let text =
+                                transforms::run(&ui_prefs.text_transforms, Stage::Send, &item.text);
+                            let send = OutgoingText {
+                                text,
+                                label: item.label.clone(),
+                                ..OutgoingText::default()
+                            };
+                            if let Some(send) = screen_outgoing_text(
+                                ui_prefs.secret_check,
+                                send,
+                                HeldSend::QuickPick,
+                                &mut self.secret_prompt,
+                                toast_message,
+                            ) {
+                                let id = push_sent_text_history(
+                                    history,
+                                    ui_prefs.history_retention,
+                                    &send,
+                                    preview_text(&send.text, 120),
+                                );
+                                let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(id, send));
+                            }
+                        }
+                        // Hide so focus returns to the app the user was in.
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                        *window_visible = false;
+                        self.shared_visible.store(false, Ordering::SeqCst);
+                        if ui_prefs.auto_paste_allowed && ui_prefs.auto_paste_after_apply {
+                            schedule_auto_paste(ui_prefs.auto_paste_excluded.clone());
+                        }
+                    }
+                    Err(err) => {
+                        *toast_message = Some((format!("Apply failed: {err}"), now_unix_ms()));
+                    }
+                }
+            }
+
+            // ── Apply & Paste: hide so focus returns to the previous app ──
+            if paste_requested {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                *window_visible = false;
+                self.shared_visible.store(false, Ordering::SeqCst);
+                schedule_auto_paste(self.ui_state.auto_paste_excluded.clone());
+            }
+
+            // ── Handle global hotkey change from Options tab ───────────────
+            if *hotkey_label != prev_hotkey_label {
+                // Unregister previous hotkey if any.
+                if let (Some(old_hk), Some(mgr)) =
                     (self.hotkey_current.take(), &self.hotkey_manager)
                 {
                     let _ = mgr.unregister(old_hk);
@@ -1370,6 +4784,33 @@ mod windows_client {
                 if let Err(err) = ui_state::save_ui_state_with_retry(&self.ui_state) {
                     warn!("failed to save hotkey setting: {err}");
                 }
+            } else if ui_prefs_changed
+                && let Err(err) = ui_state::save_ui_state_with_retry(&self.ui_state)
+            {
+                warn!("failed to save preferences: {err}");
+            }
+            if self.ui_state.quick_pick_hotkey != prev_ui_prefs.quick_pick_hotkey {
+                *last_error = register_quick_pick_hotkey(
+                    self.hotkey_manager.as_ref(),
+                    &mut self.quick_pick_hotkey_current,
+                    &self.quick_pick_hotkey_id,
+                    self.ui_state.quick_pick_hotkey.as_deref(),
+                )
+                .err();
+            }
+            if self.ui_state.panic_hotkey != prev_ui_prefs.panic_hotkey {
+                *last_error = register_panic_hotkey(
+                    self.hotkey_manager.as_ref(),
+                    &mut self.panic_hotkey_current,
+                    &self.panic_hotkey_id,
+                    self.ui_state.panic_hotkey.as_deref(),
+                )
+                .err();
+            }
+            if self.ui_state.tray_clicks != prev_ui_prefs.tray_clicks
+                && let Ok(mut shared) = self.tray_clicks.lock()
+            {
+                *shared = self.ui_state.tray_clicks;
             }
 
             // Request periodic repaint so we process runtime events even when idle.
@@ -1379,522 +4820,3898 @@ mod windows_client {
             // These write to fields of `self` OTHER than `self.phase`, so
             // Rust's field-level borrow splitting allows this even while the
             // AppPhase::Running pattern borrows above are still technically live.
-            self.pending_change_room |= change_room_requested;
+            if room_action.is_some() {
+                self.pending_room_action = room_action;
+            }
+            if config_change.is_some() {
+                self.pending_config_change = config_change;
+            }
             self.pending_reconnect |= reconnect_requested;
         }
 
         // ─── Send tab ──────────────────────────────────────────────────────────
 
+        #[allow(clippy::too_many_arguments)]
         fn render_send_tab(
             ui: &mut egui::Ui,
             send_text: &mut String,
+            send_pinned: &mut bool,
+            send_label: &mut String,
             connection_status: &str,
-            room_key_ready: bool,
             runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
             history: &mut VecDeque<ActivityEntry>,
+            clip_ring: &mut ClipRing,
+            peers: &[PeerInfo],
+            known_devices: &DeviceRegistry,
+            own_device_id: &str,
+            sync_direction: SyncDirection,
+            text_transforms: &[Transform],
+            history_retention: HistoryRetention,
+            secret_check: SecretPolicy,
+            oversized_text: OversizedText,
+            secret_prompt: &mut Option<SecretPrompt>,
             toast_message: &mut Option<(String, u64)>,
+            send_box_focused: &mut bool,
         ) {
-            let available = ui.available_size();
-            let text_height = (available.y - 50.0).max(100.0);
+            Self::render_recipients(ui, peers, known_devices, own_device_id);
+            if !sync_direction.allows_send() {
+                ui.label(
+                    egui::RichText::new("Receive-only mode: sending is disabled in Options.")
+                        .weak(),
+                );
+            }
+            ui.add_space(4.0);
 
-            ui.add_sized(
-                [available.x, text_height],
-                egui::TextEdit::multiline(send_text)
-                    .desired_width(f32::INFINITY)
-                    .hint_text("Enter text to send…"),
-            );
+            // Consume Ctrl+Enter before the text box sees it, so it sends
+            // instead of inserting a newline.
+            let send_shortcut =
+                ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter));
+            let file_shortcut =
+                ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::O));
+            let label = ui.label("Text to send:");
 
-            ui.add_space(8.0);
+            let available = ui.available_size();
+            let text_height = (available.y - 110.0).max(100.0);
+
+            let text_box = ui
+                .add_sized(
+                    [available.x, text_height],
+                    egui::TextEdit::multiline(send_text)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("Enter text to send… (Ctrl+Enter sends, Esc hides)"),
+                )
+                .labelled_by(label.id);
+            // Hotkey, type, Ctrl+Enter: the cursor is in the box as soon as
+            // the window shows.
+            if !*send_box_focused {
+                text_box.request_focus();
+                *send_box_focused = true;
+            }
 
+            // Sends made before the room key is ready wait in the runtime.
+            let connected = connection_status == "Connected" && sync_direction.allows_send();
+            let oversized = send_text.len() > MAX_CLIPBOARD_TEXT_BYTES;
             ui.horizontal(|ui| {
-                let input_ok =
-                    !send_text.trim().is_empty() && send_text.len() <= MAX_CLIPBOARD_TEXT_BYTES;
-                let can_send = connection_status == "Connected" && room_key_ready && input_ok;
-
-                if ui
-                    .add_enabled(can_send, egui::Button::new("Send Text"))
-                    .clicked()
-                {
-                    let text = send_text.clone();
-                    history.push_front(ActivityEntry {
-                        ts_unix_ms: now_unix_ms(),
-                        direction: ActivityDirection::Sent,
-                        peer_device_id: "room".to_owned(),
-                        kind: "text".to_owned(),
-                        summary: preview_text(&text, 120),
-                    });
-                    while history.len() > MAX_HISTORY_ENTRIES {
-                        history.pop_back();
+                let counter = text_split::counter_label(send_text, MAX_CLIPBOARD_TEXT_BYTES);
+                if oversized {
+                    ui.colored_label(egui::Color32::from_rgb(200, 60, 60), counter);
+                } else {
+                    ui.label(egui::RichText::new(counter).weak());
+                }
+            });
+            if oversized {
+                let parts = text_split::split_numbered(send_text, MAX_CLIPBOARD_TEXT_BYTES);
+                ui.horizontal(|ui| {
+                    ui.label("Too large for one message:");
+                    if ui
+                        .add_enabled(
+                            connected && !parts.is_empty(),
+                            egui::Button::new(format!("Split into {} Messages", parts.len())),
+                        )
+                        .on_hover_text(
+                            "Send the text as numbered parts (\"[part 1/N]\" header on each),\n\
+                             split at line breaks where possible.",
+                        )
+                        .clicked()
+                    {
+                        let send = OutgoingText {
+                            text: transforms::run(text_transforms, Stage::Send, send_text),
+                            pinned: *send_pinned,
+                            label: normalize_event_label(send_label),
+                        };
+                        if let Some(send) = screen_outgoing_text(
+                            secret_check,
+                            send,
+                            HeldSend::SendBoxSplit,
+                            secret_prompt,
+                            toast_message,
+                        ) {
+                            send_text.clear();
+                            *send_pinned = false;
+                            send_label.clear();
+                            queue_send_box_text(
+                                send,
+                                true,
+                                history,
+                                history_retention,
+                                clip_ring,
+                                runtime_cmd_tx,
+                                toast_message,
+                            );
+                        }
                     }
-                    save_history(history);
+                    if ui
+                        .add_enabled(connected, egui::Button::new("Send as File"))
+                        .on_hover_text("Send the text as a .txt file transfer instead.")
+                        .clicked()
+                    {
+                        queue_text_as_file(
+                            send_text,
+                            history,
+                            history_retention,
+                            runtime_cmd_tx,
+                            toast_message,
+                        );
+                    }
+                });
+            }
 
-                    let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(text));
-                    send_text.clear();
-                    *toast_message = Some(("Sent to connected devices".to_string(), now_unix_ms()));
-                }
+            ui.add_space(4.0);
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Label:");
+                ui.add(
+                    egui::TextEdit::singleline(send_label)
+                        .char_limit(MAX_EVENT_LABEL_CHARS)
+                        .desired_width(220.0)
+                        .hint_text("optional, e.g. \"staging token\""),
+                )
+                .labelled_by(label.id)
+                .on_hover_text(
+                    "A short title shown with the text on other devices, so that similar\n\
+                     items can be told apart.  Cleared after each send.",
+                );
+            });
 
-                let can_send_file = connection_status == "Connected" && room_key_ready;
+            ui.horizontal(|ui| {
+                let as_file = oversized && oversized_text == OversizedText::SendAsFile;
+                let input_ok = !send_text.trim().is_empty() && (!oversized || as_file);
+                let can_send = connected && input_ok;
 
                 if ui
-                    .add_enabled(can_send_file, egui::Button::new("Send File…"))
+                    .add_enabled(can_send, egui::Button::new("Send Text"))
+                    .on_hover_text(if as_file {
+                        "Ctrl+Enter: too large for one message, sent as a .txt file"
+                    } else {
+                        "Ctrl+Enter"
+                    })
                     .clicked()
-                    && let Some(path) = rfd::FileDialog::new()
-                        .set_title("Select file to send")
-                        .pick_file()
+                    || (send_shortcut && can_send)
                 {
-                    history.push_front(ActivityEntry {
-                        ts_unix_ms: now_unix_ms(),
-                        direction: ActivityDirection::Sent,
-                        peer_device_id: "room".to_owned(),
-                        kind: "file".to_owned(),
-                        summary: format!("{}", path.display()),
-                    });
-                    while history.len() > MAX_HISTORY_ENTRIES {
-                        history.pop_back();
-                    }
-                    save_history(history);
-
-                    let _ = runtime_cmd_tx.send(RuntimeCommand::SendFile(path.clone()));
-                    *toast_message =
-                        Some((format!("Queued file: {}", path.display()), now_unix_ms()));
-                }
-            });
-        }
-
-        // ─── Options tab ───────────────────────────────────────────────────────
-
-        #[allow(clippy::too_many_arguments)]
-        fn render_options_tab(
-            ui: &mut egui::Ui,
-            config: &ClientConfig,
-            connection_status: &str,
-            peers: &[PeerInfo],
-            room_key_ready: bool,
-            last_sent_time: &Option<u64>,
-            last_received_time: &Option<u64>,
-            auto_apply: &mut bool,
-            autostart_enabled: &mut bool,
-            last_error: &Option<String>,
-            history: &mut VecDeque<ActivityEntry>,
-            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
-            hotkey_label: &mut String,
-            toast_message: &mut Option<(String, u64)>,
-            // Set to `true` when the user requests a room change (handled by
-            // the caller after phase borrows are released).
-            change_room_requested: &mut bool,
-            // Set to `true` when the user requests a reconnect (handled by
-            // the caller after phase borrows are released).
-            reconnect_requested: &mut bool,
-        ) {
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                ui.heading("Connection Info");
-                ui.add_space(4.0);
-
-                egui::Grid::new("info_grid")
-                    .num_columns(2)
-                    .spacing([12.0, 4.0])
-                    .show(ui, |ui| {
-                        ui.strong("Server URL:");
-                        ui.label(&config.server_url);
-                        ui.end_row();
-
-                        ui.strong("Room code:");
-                        ui.label(&config.room_code);
-                        ui.end_row();
-
-                        ui.strong("Room ID:");
-                        ui.label(egui::RichText::new(&config.room_id).monospace().weak());
-                        ui.end_row();
-
-                        ui.strong("Client name:");
-                        ui.label(&config.device_name);
-                        ui.end_row();
-
-                        ui.strong("Device ID:");
-                        ui.label(egui::RichText::new(&config.device_id).monospace().weak());
-                        ui.end_row();
-
-                        ui.strong("Connection:");
-                        ui.label(connection_status);
-                        ui.end_row();
-
-                        ui.strong("Peers:");
-                        ui.label(format!("{}", peers.len()));
-                        ui.end_row();
-
-                        ui.strong("Room key:");
-                        ui.label(if room_key_ready { "ready" } else { "not ready" });
-                        ui.end_row();
-
-                        ui.strong("Last sent:");
-                        ui.label(
-                            last_sent_time
-                                .map(format_timestamp_local)
-                                .unwrap_or_else(|| "-".to_owned()),
-                        );
-                        ui.end_row();
-
-                        ui.strong("Last received:");
-                        ui.label(
-                            last_received_time
-                                .map(format_timestamp_local)
-                                .unwrap_or_else(|| "-".to_owned()),
+                    if as_file {
+                        queue_text_as_file(
+                            send_text,
+                            history,
+                            history_retention,
+                            runtime_cmd_tx,
+                            toast_message,
                         );
-                        ui.end_row();
-                    });
-
-                if let Some(err) = last_error {
-                    ui.add_space(8.0);
-                    ui.colored_label(
-                        egui::Color32::RED,
-                        format!("Last error: {}", preview_text(err, 200)),
-                    );
-                }
-
-                // ── Connected Peers ──────────────────────────────────────────────
-                ui.add_space(12.0);
-                ui.separator();
-                ui.add_space(8.0);
-
-                ui.horizontal(|ui| {
-                    ui.heading("Connected Peers");
-                    if peers.is_empty() {
-                        ui.label(egui::RichText::new("(none)").weak());
                     } else {
-                        let others = peers
-                            .iter()
-                            .filter(|p| p.device_id != config.device_id)
-                            .count();
-                        if others == 0 {
-                            ui.label(egui::RichText::new("(only you)").weak());
-                        } else {
-                            ui.label(
-                                egui::RichText::new(format!(
-                                    "({others} peer{})",
-                                    if others == 1 { "" } else { "s" }
-                                ))
-                                .weak(),
+                        let send = OutgoingText {
+                            text: transforms::run(text_transforms, Stage::Send, send_text),
+                            pinned: *send_pinned,
+                            label: normalize_event_label(send_label),
+                        };
+                        if let Some(send) = screen_outgoing_text(
+                            secret_check,
+                            send,
+                            HeldSend::SendBox,
+                            secret_prompt,
+                            toast_message,
+                        ) {
+                            send_text.clear();
+                            *send_pinned = false;
+                            send_label.clear();
+                            queue_send_box_text(
+                                send,
+                                false,
+                                history,
+                                history_retention,
+                                clip_ring,
+                                runtime_cmd_tx,
+                                toast_message,
                             );
                         }
                     }
-                });
+                }
+                ui.checkbox(send_pinned, "Pin").on_hover_text(
+                    "Receivers keep the text at the top of Notifications and Activity\n\
+                     History, and out of history clean-up, until they unpin it.",
+                );
 
-                ui.add_space(4.0);
-                let other_peers: Vec<_> = peers
-                    .iter()
-                    .filter(|p| p.device_id != config.device_id)
-                    .collect();
-                if other_peers.is_empty() {
-                    ui.label(
-                        egui::RichText::new(
-                            "No other peers in this room yet. Waiting for another device to join.",
-                        )
-                        .weak(),
+                if (ui
+                    .add_enabled(connected, egui::Button::new("Send File…"))
+                    .on_hover_text("Ctrl+O")
+                    .clicked()
+                    || (file_shortcut && connected))
+                    && let Some(path) = rfd::FileDialog::new()
+                        .set_title("Select file to send")
+                        .pick_file()
+                {
+                    let id = push_sent_history(
+                        history,
+                        history_retention,
+                        "file",
+                        format!("{}", path.display()),
+                        None,
                     );
-                } else {
-                    for peer in &other_peers {
-                        ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new("\u{2022}").strong());
-                            ui.label(&peer.device_name);
-                            let id_short = &peer.device_id[..8.min(peer.device_id.len())];
-                            ui.label(
-                                egui::RichText::new(format!("({id_short}\u{2026})"))
-                                    .weak()
-                                    .monospace(),
-                            );
-                        });
-                    }
+
+                    let _ = runtime_cmd_tx.send(RuntimeCommand::SendFile(id, path.clone()));
+                    *toast_message =
+                        Some((format!("Queued file: {}", path.display()), now_unix_ms()));
                 }
 
-                // ── Room / connection actions ────────────────────────────────────
-                ui.add_space(8.0);
-                ui.horizontal(|ui| {
+                let mut text_file_action: Option<bool> = None;
+                ui.menu_button("Text from File\u{2026}", |ui| {
                     if ui
-                        .button("Reconnect")
+                        .button("Insert into Text Box")
                         .on_hover_text(
-                            "Drop and re-establish the connection to the relay server.\n\
-                             This refreshes the peer list and room key without restarting the app.",
+                            "Load a UTF-8 text file into the box to edit before sending.",
                         )
                         .clicked()
                     {
-                        *reconnect_requested = true;
+                        text_file_action = Some(false);
+                        ui.close_menu();
                     }
                     if ui
-                        .button("Change Room\u{2026}")
+                        .add_enabled(connected, egui::Button::new("Send as Text"))
                         .on_hover_text(
-                            "Disconnect and return to the room-selection screen\n\
-                             so you can join or create a different room.",
+                            "Send a UTF-8 text file's contents as clipboard text rather\n\
+                             than as a file the receiver must save.",
                         )
                         .clicked()
                     {
-                        *change_room_requested = true;
+                        text_file_action = Some(true);
+                        ui.close_menu();
+                    }
+                });
+                if let Some(send_now) = text_file_action
+                    && let Some(path) = rfd::FileDialog::new()
+                        .set_title("Select text file")
+                        .add_filter("Text", &["txt", "log", "md", "csv", "json"])
+                        .add_filter("All files", &["*"])
+                        .pick_file()
+                {
+                    match text_file::read_text_file(&path) {
+                        Err(err) => {
+                            warn!(path = %path.display(), "read text file failed: {err}");
+                            *toast_message = Some((format!("Not loaded: {err}"), now_unix_ms()));
+                        }
+                        Ok(text) if !send_now => {
+                            if !send_text.is_empty() && !send_text.ends_with('\n') {
+                                send_text.push('\n');
+                            }
+                            send_text.push_str(&text);
+                            *send_box_focused = false;
+                        }
+                        Ok(text) => {
+                            let send = OutgoingText {
+                                text: transforms::run(text_transforms, Stage::Send, &text),
+                                pinned: false,
+                                label: None,
+                            };
+                            if let Some(send) = screen_outgoing_text(
+                                secret_check,
+                                send,
+                                HeldSend::TextFile,
+                                secret_prompt,
+                                toast_message,
+                            ) {
+                                queue_send_box_text(
+                                    send,
+                                    false,
+                                    history,
+                                    history_retention,
+                                    clip_ring,
+                                    runtime_cmd_tx,
+                                    toast_message,
+                                );
+                            }
+                        }
+                    }
+                }
+            });
+        }
+
+        /// Active file transfers with their progress and a Cancel button.
+        fn render_transfers(
+            ui: &mut egui::Ui,
+            transfers: &mut TransferList,
+            cancelled_transfers: &Arc<Mutex<CancelledTransfers>>,
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+        ) {
+            ui.label(egui::RichText::new("Transfers").strong());
+            let now = now_unix_ms();
+            let mut cancel_key = None;
+            for transfer in transfers.iter() {
+                let progress = &transfer.progress;
+                let arrow = match progress.direction {
+                    TransferDirection::Outgoing => "↑",
+                    TransferDirection::Incoming => "↓",
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("{arrow} {}", progress.file_name));
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} of {}",
+                            transfers::format_bytes(progress.done_bytes()),
+                            transfers::format_bytes(progress.total_bytes)
+                        ))
+                        .weak(),
+                    );
+                    if let Some(rate) = transfer.rate_label(now) {
+                        ui.label(egui::RichText::new(rate).weak());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::ProgressBar::new(progress.fraction())
+                            .desired_width(220.0)
+                            .show_percentage(),
+                    );
+                    let label = if transfer.cancel_requested {
+                        "Cancelling…"
+                    } else {
+                        "Cancel"
+                    };
+                    if ui
+                        .add_enabled(!transfer.cancel_requested, egui::Button::new(label))
+                        .on_hover_text("Stop this transfer for every device in the room.")
+                        .clicked()
+                    {
+                        cancel_key = Some(progress.key());
                     }
                 });
+            }
+            // Keep the speed current (and show a stall) between chunks.
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs(1));
+            let Some(key) = cancel_key else {
+                return;
+            };
+            let Some(progress) = transfers.request_cancel(&key) else {
+                return;
+            };
+            info!(file = %progress.file_name, "transfer cancel requested");
+            if let Ok(mut cancelled) = cancelled_transfers.lock() {
+                cancelled.insert(key, CancelSource::Local, now_unix_ms());
+            }
+            if progress.direction == TransferDirection::Incoming {
+                let _ = runtime_cmd_tx.send(RuntimeCommand::CancelTransfer {
+                    owner_device_id: progress.owner_device_id,
+                    transfer_id: progress.transfer_id,
+                });
+            }
+        }
 
-                ui.add_space(12.0);
-                ui.separator();
-                ui.add_space(8.0);
+        // ─── Quick-pick overlay ────────────────────────────────────────────────
 
-                let prev_auto = *auto_apply;
-                ui.checkbox(auto_apply, "Automatically apply incoming clipboard changes");
-                if *auto_apply != prev_auto {
-                    let _ = runtime_cmd_tx.send(RuntimeCommand::SetAutoApply(*auto_apply));
-                    *toast_message = Some((
-                        if *auto_apply {
-                            "Auto-apply enabled".to_string()
-                        } else {
-                            "Auto-apply disabled".to_string()
-                        },
-                        now_unix_ms(),
-                    ));
-                }
+        /// Render the quick-pick list.  Returns the item chosen by click or
+        /// by pressing its number key (1–9).
+        fn render_quick_pick(
+            ui: &mut egui::Ui,
+            clip_ring: &ClipRing,
+            known_devices: &DeviceRegistry,
+            peers: &[PeerInfo],
+        ) -> Option<RingItem> {
+            if clip_ring.is_empty() {
+                ui.label("No clipboard items yet.");
+                return None;
+            }
 
-                let prev_autostart = *autostart_enabled;
-                ui.checkbox(autostart_enabled, "Start ClipRelay when Windows starts");
-                if *autostart_enabled != prev_autostart {
-                    match windows_set_autostart_enabled(*autostart_enabled) {
-                        Ok(()) => {
-                            *toast_message = Some((
-                                if *autostart_enabled {
-                                    "Autostart enabled".to_string()
-                                } else {
-                                    "Autostart disabled".to_string()
-                                },
-                                now_unix_ms(),
-                            ));
-                        }
-                        Err(err) => {
-                            warn!("autostart toggle failed: {err}");
-                            *autostart_enabled = prev_autostart; // revert
-                            *toast_message = Some((
-                                "Failed to update autostart setting".to_string(),
-                                now_unix_ms(),
-                            ));
+            const DIGIT_KEYS: [egui::Key; 9] = [
+                egui::Key::Num1,
+                egui::Key::Num2,
+                egui::Key::Num3,
+                egui::Key::Num4,
+                egui::Key::Num5,
+                egui::Key::Num6,
+                egui::Key::Num7,
+                egui::Key::Num8,
+                egui::Key::Num9,
+            ];
+            let mut picked = ui.input(|i| {
+                DIGIT_KEYS
+                    .iter()
+                    .position(|key| i.key_pressed(*key))
+                    .and_then(|idx| clip_ring.get(idx).cloned())
+            });
+
+            ui.label(egui::RichText::new("Press 1–9 or click an item; Esc to close.").weak());
+            ui.add_space(4.0);
+            egui::ScrollArea::vertical()
+                .max_height(360.0)
+                .show(ui, |ui| {
+                    for (idx, item) in clip_ring.items().enumerate() {
+                        let origin = match item.source {
+                            RingSource::Sent => "sent".to_owned(),
+                            RingSource::Received => {
+                                resolve_peer_name(known_devices, peers, &item.peer_device_id)
+                            }
+                        };
+                        let key_hint = if idx < DIGIT_KEYS.len() {
+                            format!("{}.", idx + 1)
+                        } else {
+                            "  ".to_owned()
+                        };
+                        let label = format!(
+                            "{key_hint} [{}] {}",
+                            origin,
+                            labelled(
+                                item.label.as_deref(),
+                                &preview_text(&item.text, 80).replace('\n', " ")
+                            )
+                        );
+                        if ui
+                            .selectable_label(false, label)
+                            .on_hover_text(preview_text(&item.text, 450))
+                            .clicked()
+                        {
+                            picked = Some(item.clone());
                         }
                     }
-                }
+                });
+            picked
+        }
 
-                ui.add_space(12.0);
-                ui.separator();
-                ui.add_space(8.0);
+        // ─── Log viewer ────────────────────────────────────────────────────────
+
+        fn render_log_viewer(
+            ui: &mut egui::Ui,
+            viewer: &mut LogViewer,
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            const REFRESH_INTERVAL_MS: u64 = 1_000;
+
+            let path = active_log_path();
+            let now = now_unix_ms();
+            if now.saturating_sub(viewer.last_refresh_ms) >= REFRESH_INTERVAL_MS {
+                viewer.last_refresh_ms = now;
+                viewer.error = viewer.tail.refresh(&path).err().map(|err| err.to_string());
+            }
 
-                ui.label("Show/hide hotkey:");
-                ui.add_space(2.0);
-                egui::ComboBox::from_id_salt("hotkey_combo")
-                    .selected_text(hotkey_label.as_str())
+            ui.horizontal(|ui| {
+                let label = ui.label("Level:");
+                egui::ComboBox::from_id_salt("log_viewer_level")
+                    .selected_text(format!("{} and above", viewer.max_level.label()))
                     .show_ui(ui, |ui| {
-                        for &option in HOTKEY_OPTIONS {
-                            ui.selectable_value(hotkey_label, option.to_owned(), option);
+                        for level in LogLevel::ALL {
+                            ui.selectable_value(
+                                &mut viewer.max_level,
+                                level,
+                                format!("{} and above", level.label()),
+                            );
                         }
-                    });
-                ui.add_space(2.0);
+                    })
+                    .response
+                    .labelled_by(label.id);
+                let label = ui.label("Search:");
+                ui.add(egui::TextEdit::singleline(&mut viewer.query).desired_width(180.0))
+                    .labelled_by(label.id);
+                ui.checkbox(&mut viewer.follow, "Follow")
+                    .on_hover_text("Keep scrolled to the newest line.");
+            });
+
+            let lines =
+                log_view::filter_lines(viewer.tail.lines(), viewer.max_level, &viewer.query);
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!lines.is_empty(), egui::Button::new("Copy"))
+                    .on_hover_text("Copy the lines currently shown.")
+                    .clicked()
+                {
+                    let text = lines
+                        .iter()
+                        .map(|line| line.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let message = match apply_clipboard_text(&text) {
+                        Ok(()) => format!("Copied {} log lines", lines.len()),
+                        Err(err) => format!("Copy failed: {err}"),
+                    };
+                    *toast_message = Some((message, now_unix_ms()));
+                }
+                if ui.button("Open Log Folder").clicked()
+                    && let Some(dir) = path.parent()
+                    && let Err(err) = open_folder(dir)
+                {
+                    *toast_message = Some((format!("Open folder failed: {err}"), now_unix_ms()));
+                }
                 ui.label(
-                    egui::RichText::new(
-                        "Press this key combination to show or hide the ClipRelay window.",
-                    )
+                    egui::RichText::new(format!(
+                        "{} of {} lines | {}",
+                        lines.len(),
+                        viewer.tail.lines().len(),
+                        path.display()
+                    ))
                     .weak(),
                 );
+            });
+            if let Some(err) = &viewer.error {
+                ui.colored_label(
+                    egui::Color32::from_rgb(200, 60, 60),
+                    format!("Could not read the log file: {err}"),
+                );
+            }
+            ui.separator();
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom(viewer.follow)
+                .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                    for line in &lines[rows] {
+                        let text = egui::RichText::new(&line.text).monospace();
+                        let text = match line.level {
+                            Some(LogLevel::Error) => {
+                                text.color(egui::Color32::from_rgb(200, 60, 60))
+                            }
+                            Some(LogLevel::Warn) => {
+                                text.color(egui::Color32::from_rgb(200, 140, 0))
+                            }
+                            Some(LogLevel::Debug | LogLevel::Trace) => text.weak(),
+                            _ => text,
+                        };
+                        ui.add(egui::Label::new(text).extend());
+                    }
+                });
+        }
 
-                ui.add_space(12.0);
-                ui.separator();
-                ui.add_space(8.0);
+        // ─── Protocol trace ────────────────────────────────────────────────────
 
-                ui.horizontal(|ui| {
-                    ui.heading("Activity History");
-                    ui.add_space(4.0);
-                    if !history.is_empty()
-                        && ui
-                            .button("Clear")
-                            .on_hover_text("Remove all activity history entries permanently.")
-                            .clicked()
-                    {
-                        history.clear();
-                        save_history(history);
-                        *toast_message =
-                            Some(("Activity history cleared".to_string(), now_unix_ms()));
+        fn render_protocol_trace(
+            ui: &mut egui::Ui,
+            trace: &mut TraceLog,
+            known_devices: &DeviceRegistry,
+            peers: &[PeerInfo],
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            let line_for = |summary: &FrameSummary| {
+                let mut line = format!(
+                    "{}.{:03} {}",
+                    format_timestamp_local(summary.ts_unix_ms),
+                    summary.ts_unix_ms % 1_000,
+                    protocol_trace::format_summary(summary)
+                );
+                if let Some(sender) = &summary.sender {
+                    line.push_str(&format!(
+                        " ({})",
+                        resolve_peer_name(known_devices, peers, sender)
+                    ));
+                }
+                line
+            };
+
+            ui.horizontal(|ui| {
+                if ui.button("Clear").clicked() {
+                    trace.clear();
+                }
+                if ui
+                    .add_enabled(!trace.entries().is_empty(), egui::Button::new("Copy"))
+                    .clicked()
+                {
+                    let text = trace
+                        .entries()
+                        .iter()
+                        .map(line_for)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let message = match apply_clipboard_text(&text) {
+                        Ok(()) => format!("Copied {} trace lines", trace.entries().len()),
+                        Err(err) => format!("Copy failed: {err}"),
+                    };
+                    *toast_message = Some((message, now_unix_ms()));
+                }
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} frames (newest last, at most {}) | -> sent, <- received",
+                        trace.entries().len(),
+                        protocol_trace::MAX_TRACE_ENTRIES
+                    ))
+                    .weak(),
+                );
+            });
+            ui.separator();
+
+            let entries = trace.entries();
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                    for summary in entries.range(rows) {
+                        let text = egui::RichText::new(line_for(summary)).monospace();
+                        let text = match summary.kind {
+                            "Error" => text.color(egui::Color32::from_rgb(200, 60, 60)),
+                            "Decrypted" => text.color(egui::Color32::from_rgb(60, 140, 60)),
+                            _ => text,
+                        };
+                        ui.add(egui::Label::new(text).extend());
                     }
                 });
-                ui.add_space(4.0);
+        }
+
+        // ─── Options tab ───────────────────────────────────────────────────────
+
+        /// Saved room profiles with Switch / Delete, and saving the current
+        /// room as a profile.  Returns `true` when the list was modified and
+        /// should be saved.
+        fn render_room_profiles(
+            ui: &mut egui::Ui,
+            config: &ClientConfig,
+            room_profiles: &mut RoomProfiles,
+            room_profiles_error: Option<&str>,
+            toast_message: &mut Option<(String, u64)>,
+            room_action: &mut Option<RoomAction>,
+        ) -> bool {
+            let mut changed = false;
+            let mut delete: Option<usize> = None;
+            let editable = room_profiles_error.is_none();
+
+            ui.strong("Room profiles");
+            if let Some(err) = room_profiles_error {
+                ui.colored_label(
+                    egui::Color32::from_rgb(200, 60, 60),
+                    format!("Profiles could not be loaded, so editing is disabled: {err}"),
+                );
+            }
+            let active = room_profiles.active_index(
+                &config.server_url,
+                &config.room_code,
+                &config.device_name,
+            );
+            for (index, profile) in room_profiles.profiles.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if active == Some(index) {
+                        ui.label(egui::RichText::new(&profile.name).strong());
+                        ui.label(egui::RichText::new("(current)").weak());
+                    } else {
+                        ui.label(&profile.name);
+                        if ui
+                            .button("Switch")
+                            .on_hover_text("Disconnect and join this profile's room.")
+                            .clicked()
+                        {
+                            *room_action = Some(RoomAction::SwitchProfile(index));
+                        }
+                    }
+                    if ui
+                        .add_enabled(editable, egui::Button::new("Delete"))
+                        .clicked()
+                    {
+                        delete = Some(index);
+                    }
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} | {}",
+                            profile.server_url, profile.device_name
+                        ))
+                        .weak(),
+                    );
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let name_id = ui.make_persistent_id("new_room_profile_name");
+                let mut raw = ui
+                    .data_mut(|d| d.get_temp::<String>(name_id))
+                    .unwrap_or_default();
+                let label = ui.label("Save current room as:");
+                let response = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut raw)
+                            .hint_text("e.g. Home")
+                            .desired_width(140.0),
+                    )
+                    .labelled_by(label.id);
+                if response.changed() {
+                    ui.data_mut(|d| d.insert_temp(name_id, raw.clone()));
+                }
+                let name = room_profiles::normalize_profile_name(&raw);
+                if ui
+                    .add_enabled(
+                        editable && name.is_some(),
+                        egui::Button::new("Save Profile"),
+                    )
+                    .on_hover_text(
+                        "Save this room's server, room code and client name under a name\n\
+                         you can switch to from here or the tray menu.  Saving with an\n\
+                         existing name replaces that profile.",
+                    )
+                    .clicked()
+                    && let Some(name) = name
+                {
+                    let saved = room_profiles.upsert(RoomProfile {
+                        name: name.clone(),
+                        server_url: config.server_url.clone(),
+                        room_code: config.room_code.clone(),
+                        device_name: config.device_name.clone(),
+                    });
+                    if saved {
+                        room_profiles.last_used = Some(name.clone());
+                        changed = true;
+                        ui.data_mut(|d| d.remove::<String>(name_id));
+                        *toast_message =
+                            Some((format!("Saved room profile \"{name}\""), now_unix_ms()));
+                    } else {
+                        *toast_message = Some((
+                            format!("Profile limit reached ({})", room_profiles::MAX_PROFILES),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            });
+
+            if let Some(index) = delete
+                && let Some(removed) = room_profiles.remove(index)
+            {
+                changed = true;
+                *toast_message = Some((
+                    format!("Deleted room profile \"{}\"", removed.name),
+                    now_unix_ms(),
+                ));
+            }
+            changed
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        fn render_options_tab(
+            ui: &mut egui::Ui,
+            config: &ClientConfig,
+            connection_status: &str,
+            peers: &[PeerInfo],
+            room_key_ready: bool,
+            last_sent_time: &Option<u64>,
+            last_received_time: &Option<u64>,
+            auto_apply: &mut bool,
+            autostart_enabled: &mut bool,
+            explorer_menu_enabled: &mut bool,
+            send_to_enabled: &mut bool,
+            url_handler_enabled: &mut bool,
+            last_error: &Option<String>,
+            history: &mut VecDeque<ActivityEntry>,
+            clip_ring: &mut ClipRing,
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            hotkey_label: &mut String,
+            ui_prefs: &mut SavedUiState,
+            known_devices: &DeviceRegistry,
+            toast_message: &mut Option<(String, u64)>,
+            self_test_running: &mut bool,
+            self_test_report: Option<&SelfTestReport>,
+            latency: &LatencyHistory,
+            usage_stats: &mut UsageStats,
+            room_profiles: &mut RoomProfiles,
+            room_profiles_error: Option<&str>,
+            // Set when the profile list was edited and should be saved.
+            room_profiles_changed: &mut bool,
+            // Set when the user changes or leaves the room (handled by the
+            // caller after phase borrows are released).
+            room_action: &mut Option<RoomAction>,
+            // Set to the edited connection settings once they are saved
+            // (handled by the caller after phase borrows are released).
+            config_change: &mut Option<SavedClientConfig>,
+            // Set to `true` when the user requests a reconnect (handled by
+            // the caller after phase borrows are released).
+            reconnect_requested: &mut bool,
+            // Set to `true` when the user opens the log viewer.
+            view_logs_requested: &mut bool,
+            // Set to `true` when the user opens the protocol trace.
+            protocol_trace_requested: &mut bool,
+            metered: &mut MeteredStatus,
+            options_page: &mut OptionsPage,
+            auth_gate: &mut AuthGate,
+            // Whether an unlock prompt is up.
+            unlocking: bool,
+            // Set when a locked view or action asks to unlock (handled by
+            // the caller, which owns the prompt).
+            unlock_request: &mut Option<AfterUnlock>,
+        ) {
+            ui.horizontal(|ui| {
+                for page in OptionsPage::ALL {
+                    ui.selectable_value(options_page, page, page.label());
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .id_salt(("options_page", *options_page))
+                .show(ui, |ui| match *options_page {
+                    OptionsPage::General => Self::render_options_general(
+                        ui,
+                        auto_apply,
+                        autostart_enabled,
+                        explorer_menu_enabled,
+                        send_to_enabled,
+                        url_handler_enabled,
+                        runtime_cmd_tx,
+                        hotkey_label,
+                        ui_prefs,
+                        toast_message,
+                        metered,
+                    ),
+                    OptionsPage::Devices => Self::render_options_devices(
+                        ui,
+                        config,
+                        connection_status,
+                        peers,
+                        room_key_ready,
+                        last_sent_time,
+                        last_received_time,
+                        last_error,
+                        known_devices,
+                        &mut ui_prefs.invite_qr,
+                        !auth_gate.is_unlocked(ui_prefs.auth_gate, now_unix_ms()),
+                        unlock_request,
+                        toast_message,
+                        room_profiles,
+                        room_profiles_error,
+                        room_profiles_changed,
+                        room_action,
+                        config_change,
+                        reconnect_requested,
+                    ),
+                    OptionsPage::History => {
+                        if auth_gate.admit(ui_prefs.auth_gate, now_unix_ms()) {
+                            Self::render_options_history(
+                                ui,
+                                history,
+                                clip_ring,
+                                &mut ui_prefs.history_retention,
+                                &mut ui_prefs.auth_gate,
+                                auth_gate.is_unlocked(ui_prefs.auth_gate, now_unix_ms()),
+                                unlock_request,
+                                toast_message,
+                            );
+                        } else if Self::render_locked(ui, "History", unlocking) {
+                            *unlock_request = Some(AfterUnlock::Show);
+                        }
+                    }
+                    OptionsPage::Stats => {
+                        Self::render_options_stats(ui, usage_stats, toast_message)
+                    }
+                    OptionsPage::Advanced => Self::render_options_advanced(
+                        ui,
+                        connection_status,
+                        room_key_ready,
+                        runtime_cmd_tx,
+                        ui_prefs,
+                        toast_message,
+                        self_test_running,
+                        self_test_report,
+                        latency,
+                        reconnect_requested,
+                        view_logs_requested,
+                        protocol_trace_requested,
+                    ),
+                });
+        }
+
+        /// Relay round trips as a sparkline, with min/avg/max.
+        fn render_latency(ui: &mut egui::Ui, latency: &LatencyHistory) {
+            let (Some(latest), Some(stats)) = (latency.latest(), latency.stats()) else {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Relay latency: measured every {} s once connected",
+                        latency::PROBE_INTERVAL_SECS
+                    ))
+                    .weak(),
+                );
+                return;
+            };
+            ui.label(format!(
+                "Relay latency: {} ms (min {} / avg {} / max {} ms)",
+                latest.rtt_ms, stats.min_ms, stats.avg_ms, stats.max_ms
+            ));
+
+            let width = ui.available_width().min(360.0);
+            let (rect, response) =
+                ui.allocate_exact_size(egui::vec2(width, 48.0), egui::Sense::hover());
+            let painter = ui.painter_at(rect);
+            let visuals = ui.visuals();
+            painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+            // Scale to the worst round trip, leaving headroom above it.
+            let top_ms = (stats.max_ms as f32 * 1.2).max(1.0);
+            let step = rect.width() / (latency::MAX_SAMPLES - 1) as f32;
+            // Newest sample at the right edge.
+            let offset = latency::MAX_SAMPLES - latency.samples().len();
+            let points: Vec<egui::Pos2> = latency
+                .samples()
+                .enumerate()
+                .map(|(i, sample)| {
+                    egui::pos2(
+                        rect.left() + (offset + i) as f32 * step,
+                        rect.bottom() - rect.height() * (sample.rtt_ms as f32 / top_ms),
+                    )
+                })
+                .collect();
+            let stroke = egui::Stroke::new(1.5, visuals.selection.bg_fill);
+            if points.len() == 1 {
+                painter.circle_filled(points[0], 2.0, stroke.color);
+            } else {
+                painter.add(egui::Shape::line(points, stroke));
+            }
+
+            if let Some(pos) = response.hover_pos() {
+                let index = ((pos.x - rect.left()) / step).round() as usize;
+                if let Some(sample) = index
+                    .checked_sub(offset)
+                    .and_then(|i| latency.samples().nth(i))
+                {
+                    let age_secs = now_unix_ms().saturating_sub(sample.at_unix_ms) / 1000;
+                    response.on_hover_text(format!("{} ms, {age_secs} s ago", sample.rtt_ms));
+                }
+            }
+        }
+
+        /// The latest relay-stamped probe from each device, split into the
+        /// leg to the relay, the time in it and the leg to this PC.
+        fn render_probe_latency(ui: &mut egui::Ui, latency: &LatencyHistory) {
+            let mut probes = latency.probes().peekable();
+            if probes.peek().is_none() {
+                return;
+            }
+            ui.add_space(4.0);
+            ui.label("Latency breakdown (sender \u{2192} relay \u{2192} this PC):")
+                .on_hover_text(
+                    "From the latest probe of each device, stamped by the relay.\n\
+                     Each leg compares two clocks, so clock drift shifts time between\n\
+                     them; for this PC's own probes the total is exact.",
+                );
+            egui::Grid::new("probe_latency")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    for heading in ["Device", "To relay", "In relay", "To this PC", "Total"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for (_, reading) in probes {
+                        let l = reading.latency;
+                        ui.label(&reading.device_name);
+                        for ms in [l.to_relay_ms, l.in_relay_ms, l.from_relay_ms, l.total_ms()] {
+                            ui.label(format!("{ms} ms"));
+                        }
+                        ui.end_row();
+                    }
+                });
+        }
+
+        /// "Automatically apply" checkbox, shared by Options and onboarding.
+        fn auto_apply_checkbox(
+            ui: &mut egui::Ui,
+            auto_apply: &mut bool,
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            let prev_auto = *auto_apply;
+            ui.checkbox(auto_apply, "Automatically apply incoming clipboard changes");
+            if *auto_apply != prev_auto {
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SetAutoApply(*auto_apply));
+                *toast_message = Some((
+                    if *auto_apply {
+                        "Auto-apply enabled".to_string()
+                    } else {
+                        "Auto-apply disabled".to_string()
+                    },
+                    now_unix_ms(),
+                ));
+            }
+        }
+
+        /// "Start when Windows starts" checkbox, shared by Options and
+        /// onboarding; reverts when the autostart entry cannot be changed.
+        fn autostart_checkbox(
+            ui: &mut egui::Ui,
+            autostart_enabled: &mut bool,
+            backend: AutostartBackend,
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            let prev_autostart = *autostart_enabled;
+            ui.checkbox(autostart_enabled, "Start ClipRelay when Windows starts");
+            if *autostart_enabled != prev_autostart {
+                match windows_set_autostart_enabled(backend, *autostart_enabled) {
+                    Ok(()) => {
+                        *toast_message = Some((
+                            if *autostart_enabled {
+                                "Autostart enabled".to_string()
+                            } else {
+                                "Autostart disabled".to_string()
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                    Err(err) => {
+                        warn!("autostart toggle failed: {err}");
+                        *autostart_enabled = prev_autostart; // revert
+                        *toast_message = Some((
+                            "Failed to update autostart setting".to_string(),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        /// Options → General: clipboard behaviour, startup, hotkeys, pasting,
+        /// links, transforms, received files and notifications.
+        #[allow(clippy::too_many_arguments)]
+        fn render_options_general(
+            ui: &mut egui::Ui,
+            auto_apply: &mut bool,
+            autostart_enabled: &mut bool,
+            explorer_menu_enabled: &mut bool,
+            send_to_enabled: &mut bool,
+            url_handler_enabled: &mut bool,
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            hotkey_label: &mut String,
+            ui_prefs: &mut SavedUiState,
+            toast_message: &mut Option<(String, u64)>,
+            metered: &mut MeteredStatus,
+        ) {
+            Self::auto_apply_checkbox(ui, auto_apply, runtime_cmd_tx, toast_message);
+            ui.horizontal(|ui| {
+                let confirm = &mut ui_prefs.large_apply_confirm;
+                let label = ui.checkbox(&mut confirm.enabled, "Ask before auto-applying text over");
+                ui.add_enabled_ui(confirm.enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut confirm.threshold_kib)
+                            .range(large_apply::MIN_THRESHOLD_KIB..=large_apply::MAX_THRESHOLD_KIB)
+                            .suffix(" KiB"),
+                    )
+                    .labelled_by(label.id);
+                });
+                *confirm = confirm.normalized();
+            })
+            .response
+            .on_hover_text(
+                "Very large text can freeze the application you paste into.\n\
+                 Above this size, auto-apply shows the size and the start of the\n\
+                 text and waits for Apply; the item is listed in Notifications.",
+            );
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Text too large for one message:");
+                let prev = ui_prefs.oversized_text;
+                egui::ComboBox::from_id_salt("oversized_text_combo")
+                    .selected_text(ui_prefs.oversized_text.label())
+                    .show_ui(ui, |ui| {
+                        for action in OversizedText::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.oversized_text,
+                                action,
+                                action.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                if ui_prefs.oversized_text != prev {
+                    let _ = runtime_cmd_tx
+                        .send(RuntimeCommand::SetOversizedText(ui_prefs.oversized_text));
+                }
+            })
+            .response
+            .on_hover_text(format!(
+                "Text over {} KiB cannot be sent as one message.  It can be sent as a\n\
+                 .txt file named after its first line instead: offered in the send box,\n\
+                 or done for every send, including automation and Quick Pick.",
+                MAX_CLIPBOARD_TEXT_BYTES / 1024
+            ));
+
+            ui.horizontal(|ui| {
+                let label = ui.checkbox(
+                    &mut ui_prefs.append_mode,
+                    "Append received text to the clipboard, separated by",
+                );
+                ui.add_enabled_ui(ui_prefs.append_mode, |ui| {
+                    egui::ComboBox::from_id_salt("append_separator_combo")
+                        .selected_text(ui_prefs.append_separator.label())
+                        .show_ui(ui, |ui| {
+                            for separator in AppendSeparator::ALL {
+                                ui.selectable_value(
+                                    &mut ui_prefs.append_separator,
+                                    separator,
+                                    separator.label(),
+                                );
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                });
+            })
+            .response
+            .on_hover_text(
+                "Collect several snippets: applying received text adds it after the\n\
+                 text already on the clipboard instead of replacing it.  Copying from\n\
+                 Activity History still replaces the clipboard.  Also switched in\n\
+                 Notifications.",
+            );
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Sync direction:");
+                let prev_direction = ui_prefs.sync_direction;
+                egui::ComboBox::from_id_salt("sync_direction_combo")
+                    .selected_text(ui_prefs.sync_direction.label())
+                    .show_ui(ui, |ui| {
+                        for direction in SyncDirection::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.sync_direction,
+                                direction,
+                                direction.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                if ui_prefs.sync_direction != prev_direction {
+                    let _ = runtime_cmd_tx
+                        .send(RuntimeCommand::SetSyncDirection(ui_prefs.sync_direction));
+                    *toast_message = Some((
+                        format!("Sync direction: {}", ui_prefs.sync_direction.label()),
+                        now_unix_ms(),
+                    ));
+                }
+            })
+            .response
+            .on_hover_text(
+                "Receive only: nothing from this PC is ever sent to the room.\n\
+                 Send only: incoming clipboard text and files are ignored.",
+            );
+
+            let prev_metered = (
+                ui_prefs.metered_send_files,
+                ui_prefs.metered_defer_large_text,
+                metered.override_active,
+            );
+            ui.horizontal(|ui| {
+                ui.label(format!("Connection: {}", metered.cost.label()));
+                if metered.deferred > 0 {
+                    ui.label(format!("| {} send(s) deferred", metered.deferred));
+                }
+            });
+            let mut defer_files = !ui_prefs.metered_send_files;
+            if ui
+                .checkbox(
+                    &mut defer_files,
+                    "Hold back file sends on metered connections",
+                )
+                .on_hover_text(
+                    "Files you send wait until Windows reports an unmetered connection.\n\
+                     Files sent by other devices still arrive.",
+                )
+                .changed()
+            {
+                ui_prefs.metered_send_files = !defer_files;
+            }
+            ui.checkbox(
+                &mut ui_prefs.metered_defer_large_text,
+                format!(
+                    "Also hold back texts over {} KiB",
+                    metered::LARGE_TEXT_BYTES / 1024
+                ),
+            );
+            if metered.cost.is_metered() {
+                ui.checkbox(
+                    &mut metered.override_active,
+                    "Send anyway on this metered connection",
+                );
+            }
+            if prev_metered
+                != (
+                    ui_prefs.metered_send_files,
+                    ui_prefs.metered_defer_large_text,
+                    metered.override_active,
+                )
+            {
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SetMeteredPolicy(metered_policy(
+                    ui_prefs,
+                    metered.override_active,
+                )));
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Skip repeated incoming text:");
+                let prev_mode = ui_prefs.duplicate_suppression;
+                egui::ComboBox::from_id_salt("duplicate_suppression_combo")
+                    .selected_text(ui_prefs.duplicate_suppression.label())
+                    .show_ui(ui, |ui| {
+                        for mode in DuplicateSuppression::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.duplicate_suppression,
+                                mode,
+                                mode.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+                if ui_prefs.duplicate_suppression != prev_mode {
+                    let _ = runtime_cmd_tx.send(RuntimeCommand::SetDuplicateSuppression(
+                        ui_prefs.duplicate_suppression,
+                    ));
+                }
+            })
+            .response
+            .on_hover_text(
+                "Text identical to what was last applied is ignored if it\n\
+                 arrives again within this window. Skips are shown in history.",
+            );
+
+            let mut unknown_device_alerts = !ui_prefs.suppress_unknown_device_alerts;
+            ui.checkbox(
+                &mut unknown_device_alerts,
+                "Alert when an unknown device joins the room",
+            )
+            .on_hover_text(
+                "Anyone who learns the room code can join. Devices never seen\n\
+                 before raise a notification and a red tray icon until you\n\
+                 trust, block or dismiss them.",
+            );
+            ui_prefs.suppress_unknown_device_alerts = !unknown_device_alerts;
+
+            Self::autostart_checkbox(
+                ui,
+                autostart_enabled,
+                ui_prefs.autostart_backend,
+                toast_message,
+            );
+            let prev_backend = ui_prefs.autostart_backend;
+            ui.horizontal(|ui| {
+                let label = ui.label("Autostart method:");
+                egui::ComboBox::from_id_salt("autostart_backend_combo")
+                    .selected_text(ui_prefs.autostart_backend.label())
+                    .show_ui(ui, |ui| {
+                        for backend in AutostartBackend::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.autostart_backend,
+                                backend,
+                                backend.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            })
+            .response
+            .on_hover_text("Use Scheduled Task if group policy removes the registry Run entry.");
+            if ui_prefs.autostart_backend != prev_backend && *autostart_enabled {
+                // Move the existing entry to the newly selected backend.
+                let migrated = windows_set_autostart_enabled(ui_prefs.autostart_backend, true)
+                    .and_then(|()| windows_set_autostart_enabled(prev_backend, false));
+                match migrated {
+                    Ok(()) => {
+                        *toast_message = Some((
+                            format!("Autostart now uses {}", ui_prefs.autostart_backend.label()),
+                            now_unix_ms(),
+                        ));
+                    }
+                    Err(err) => {
+                        warn!("autostart backend switch failed: {err}");
+                        let _ = windows_set_autostart_enabled(ui_prefs.autostart_backend, false);
+                        ui_prefs.autostart_backend = prev_backend; // revert
+                        *toast_message = Some((
+                            format!("Failed to switch autostart method: {err}"),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            }
+            ui.add_enabled_ui(*autostart_enabled, |ui| {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Delay start:");
+                    egui::ComboBox::from_id_salt("autostart_delay_combo")
+                        .selected_text(startup::delay_label(ui_prefs.autostart_delay_secs))
+                        .show_ui(ui, |ui| {
+                            for &secs in startup::STARTUP_DELAY_OPTIONS {
+                                ui.selectable_value(
+                                    &mut ui_prefs.autostart_delay_secs,
+                                    secs,
+                                    startup::delay_label(secs),
+                                );
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                });
+                ui.checkbox(
+                    &mut ui_prefs.autostart_wait_for_network,
+                    "Wait for network before connecting",
+                )
+                .on_hover_text(
+                    "At login, wait (up to 2 minutes) until the relay server's\n\
+                     host name resolves before the first connection attempt.",
+                );
+            });
+
+            let prev_explorer_menu = *explorer_menu_enabled;
+            ui.checkbox(
+                explorer_menu_enabled,
+                format!("Add \"{}\" to Explorer's file menu", shell_menu::VERB_LABEL),
+            )
+            .on_hover_text(
+                "Right-click files in Explorer to send them to the room.\n\
+                 On Windows 11 the entry is under \"Show more options\".",
+            );
+            if *explorer_menu_enabled != prev_explorer_menu {
+                match windows_set_explorer_menu_enabled(*explorer_menu_enabled) {
+                    Ok(()) => {
+                        *toast_message = Some((
+                            if *explorer_menu_enabled {
+                                "Explorer menu entry added".to_string()
+                            } else {
+                                "Explorer menu entry removed".to_string()
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                    Err(err) => {
+                        warn!("Explorer menu toggle failed: {err}");
+                        *explorer_menu_enabled = prev_explorer_menu; // revert
+                        *toast_message = Some((
+                            "Failed to update the Explorer menu".to_string(),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            }
+
+            let prev_send_to = *send_to_enabled;
+            ui.checkbox(send_to_enabled, "Add ClipRelay to the \"Send to\" menu")
+                .on_hover_text("Places a ClipRelay shortcut in your SendTo folder.");
+            if *send_to_enabled != prev_send_to {
+                match windows_set_send_to_enabled(*send_to_enabled) {
+                    Ok(()) => {
+                        *toast_message = Some((
+                            if *send_to_enabled {
+                                "Send to shortcut added".to_string()
+                            } else {
+                                "Send to shortcut removed".to_string()
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                    Err(err) => {
+                        warn!("Send to toggle failed: {err}");
+                        *send_to_enabled = prev_send_to; // revert
+                        *toast_message = Some((
+                            "Failed to update the Send to menu".to_string(),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            }
+
+            let prev_url_handler = *url_handler_enabled;
+            ui.checkbox(url_handler_enabled, "Open cliprelay:// join links")
+                .on_hover_text(
+                    "Clicking a join link asks whether to join its room.\n\
+                     Create one with Copy Join Link on the Devices page.",
+                );
+            if *url_handler_enabled != prev_url_handler {
+                match windows_set_url_handler_enabled(*url_handler_enabled) {
+                    Ok(()) => {
+                        *toast_message = Some((
+                            if *url_handler_enabled {
+                                "Join links now open in ClipRelay".to_string()
+                            } else {
+                                "Join links no longer open in ClipRelay".to_string()
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                    Err(err) => {
+                        warn!("link handler toggle failed: {err}");
+                        *url_handler_enabled = prev_url_handler; // revert
+                        *toast_message = Some((
+                            "Failed to update the link handler".to_string(),
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+            }
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            let label = ui.label("Show/hide hotkey:");
+            ui.add_space(2.0);
+            egui::ComboBox::from_id_salt("hotkey_combo")
+                .selected_text(hotkey_label.as_str())
+                .show_ui(ui, |ui| {
+                    for &option in HOTKEY_OPTIONS {
+                        ui.selectable_value(hotkey_label, option.to_owned(), option);
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.add_space(2.0);
+            ui.label(
+                egui::RichText::new(
+                    "Press this key combination to show or hide the ClipRelay window.",
+                )
+                .weak(),
+            );
+
+            ui.add_space(8.0);
+            let label = ui.label("Quick-pick hotkey:");
+            ui.add_space(2.0);
+            let quick_pick_label = ui_prefs
+                .quick_pick_hotkey
+                .clone()
+                .unwrap_or_else(|| DEFAULT_QUICK_PICK_HOTKEY_LABEL.to_owned());
+            egui::ComboBox::from_id_salt("quick_pick_hotkey_combo")
+                .selected_text(quick_pick_label.as_str())
+                .show_ui(ui, |ui| {
+                    for &option in QUICK_PICK_HOTKEY_OPTIONS {
+                        if ui
+                            .selectable_label(quick_pick_label == option, option)
+                            .clicked()
+                        {
+                            ui_prefs.quick_pick_hotkey = Some(option.to_owned());
+                        }
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.checkbox(
+                &mut ui_prefs.quick_pick_resend,
+                "Also re-send the picked item to the room",
+            );
+            ui.label(
+                egui::RichText::new(
+                    "Opens a list of the last 20 clipboard items (kept in memory only).",
+                )
+                .weak(),
+            );
+
+            ui.add_space(8.0);
+            let label = ui.label("Panic hotkey:");
+            ui.add_space(2.0);
+            let panic_label = ui_prefs.panic_hotkey.as_deref().unwrap_or("Disabled");
+            egui::ComboBox::from_id_salt("panic_hotkey_combo")
+                .selected_text(panic_label)
+                .show_ui(ui, |ui| {
+                    for &option in PANIC_HOTKEY_OPTIONS {
+                        if ui.selectable_label(panic_label == option, option).clicked() {
+                            ui_prefs.panic_hotkey =
+                                (option != "Disabled").then(|| option.to_owned());
+                        }
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.label(
+                egui::RichText::new(
+                    "Clears the clipboard and the quick-pick list, pauses sync and \
+                     silences queued notifications.  Resume from the status bar or \
+                     the tray menu.",
+                )
+                .weak(),
+            );
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.checkbox(
+                &mut ui_prefs.auto_paste_allowed,
+                "Allow ClipRelay to paste into other applications (Ctrl+V)",
+            )
+            .on_hover_text(
+                "Safety switch for synthesized keystrokes. When off, ClipRelay\n\
+                 never presses Ctrl+V on your behalf.",
+            );
+            ui.add_enabled_ui(ui_prefs.auto_paste_allowed, |ui| {
+                ui.checkbox(
+                    &mut ui_prefs.auto_paste_after_apply,
+                    "Paste automatically after every apply",
+                );
+                ui.label("Never paste into (executable names):");
+                edit_list_setting(
+                    ui,
+                    "auto_paste_excluded",
+                    "e.g. keepass.exe, mstsc.exe",
+                    &mut ui_prefs.auto_paste_excluded,
+                    autopaste::parse_exclusion_list,
+                );
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Links from trusted devices:");
+                egui::ComboBox::from_id_salt("trusted_link_auto_open_combo")
+                    .selected_text(ui_prefs.trusted_link_auto_open.label())
+                    .show_ui(ui, |ui| {
+                        for mode in LinkAutoOpen::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.trusted_link_auto_open,
+                                mode,
+                                mode.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            })
+            .response
+            .on_hover_text(
+                "When a device marked Trusted (with a verified key) sends text that is\n\
+                 just a web link, open it in the browser, optionally after asking.\n\
+                 Links from other devices are only offered with Open in Browser.",
+            );
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Text that looks like a secret:");
+                egui::ComboBox::from_id_salt("secret_check_combo")
+                    .selected_text(ui_prefs.secret_check.label())
+                    .show_ui(ui, |ui| {
+                        for policy in SecretPolicy::ALL {
+                            ui.selectable_value(&mut ui_prefs.secret_check, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            })
+            .response
+            .on_hover_text(
+                "Checks text before it is sent for AWS access keys, private keys,\n\
+                 JSON Web Tokens and long random-looking tokens.  Sends from the\n\
+                 automation API or --send-text cannot ask, so they are refused\n\
+                 unless this is set to Don't check.  Snippets are never checked.",
+            );
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+            ui.label("Tray icon clicks:");
+            let tray_clicks = &mut ui_prefs.tray_clicks;
+            for (salt, name, binding) in [
+                (
+                    "tray_single_click_combo",
+                    "Single-click:",
+                    &mut tray_clicks.single,
+                ),
+                (
+                    "tray_double_click_combo",
+                    "Double-click:",
+                    &mut tray_clicks.double,
+                ),
+                (
+                    "tray_middle_click_combo",
+                    "Middle-click:",
+                    &mut tray_clicks.middle,
+                ),
+            ] {
+                ui.horizontal(|ui| {
+                    let label = ui.label(name);
+                    egui::ComboBox::from_id_salt(salt)
+                        .selected_text(binding.label())
+                        .show_ui(ui, |ui| {
+                            for action in TrayAction::ALL {
+                                ui.selectable_value(binding, action, action.label());
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                });
+            }
+            if tray_clicks.single_waits_for_double() {
+                ui.label(
+                    egui::RichText::new(
+                        "Single-click waits briefly in case a double-click follows.",
+                    )
+                    .weak(),
+                );
+            }
+            ui.horizontal(|ui| {
+                let label = ui.label("Text size:");
+                egui::ComboBox::from_id_salt("text_size_combo")
+                    .selected_text(ui_prefs.text_size.label())
+                    .show_ui(ui, |ui| {
+                        for size in TextSize::ALL {
+                            ui.selectable_value(&mut ui_prefs.text_size, size, size.label());
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            })
+            .response
+            .on_hover_text(
+                "Scales text and the layout around it in all ClipRelay windows,\n\
+                 on top of the Windows display scaling.",
+            );
+            ui.horizontal(|ui| {
+                let label = ui.label("Show the window at:");
+                egui::ComboBox::from_id_salt("popup_placement_combo")
+                    .selected_text(ui_prefs.popup_placement.label())
+                    .show_ui(ui, |ui| {
+                        for placement in PopupPlacement::ALL {
+                            ui.selectable_value(
+                                &mut ui_prefs.popup_placement,
+                                placement,
+                                placement.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .labelled_by(label.id);
+            })
+            .response
+            .on_hover_text(
+                "Where the hidden window appears when the tray icon, a hotkey or\n\
+                 the tray menu brings it up, for example to show incoming items.",
+            );
+            ui.checkbox(
+                &mut ui_prefs.always_on_top,
+                "Keep the ClipRelay window on top of other windows",
+            )
+            .on_hover_text(
+                "Also applies when the window is shown by the tray icon or hotkey,\n\
+                 for example to read incoming notifications.",
+            );
+            ui.checkbox(
+                &mut ui_prefs.status_widget.enabled,
+                "Show a status widget on the desktop",
+            )
+            .on_hover_text(
+                "A small window above all others with the connection state, the\n\
+                 number of devices and the newest item. Drag it to move it to\n\
+                 another corner; double-click it to open ClipRelay.",
+            );
+            ui.add_enabled_ui(ui_prefs.status_widget.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add_space(18.0);
+                    let label = ui.label("Corner:");
+                    egui::ComboBox::from_id_salt("status_widget_corner_combo")
+                        .selected_text(ui_prefs.status_widget.corner.label())
+                        .show_ui(ui, |ui| {
+                            for corner in WidgetCorner::ALL {
+                                ui.selectable_value(
+                                    &mut ui_prefs.status_widget.corner,
+                                    corner,
+                                    corner.label(),
+                                );
+                            }
+                        })
+                        .response
+                        .labelled_by(label.id);
+                });
+                ui.horizontal(|ui| {
+                    ui.add_space(18.0);
+                    ui.checkbox(
+                        &mut ui_prefs.status_widget.click_through,
+                        "Click-through (clicks go to the window underneath)",
+                    );
+                });
+            });
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+            Self::render_text_transforms(ui, &mut ui_prefs.text_transforms);
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.label("Save received files to:");
+            let current_dir = ui_prefs
+                .files_save_dir
+                .clone()
+                .unwrap_or_else(default_received_files_dir);
+            ui.label(
+                egui::RichText::new(current_dir.display().to_string())
+                    .monospace()
+                    .weak(),
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Choose Folder\u{2026}").clicked()
+                    && let Some(dir) = rfd::FileDialog::new()
+                        .set_title("Save received files to")
+                        .set_directory(&current_dir)
+                        .pick_folder()
+                {
+                    ui_prefs.files_save_dir = Some(dir);
+                }
+                if ui
+                    .add_enabled(
+                        ui_prefs.files_save_dir.is_some(),
+                        egui::Button::new("Use Default"),
+                    )
+                    .clicked()
+                {
+                    ui_prefs.files_save_dir = None;
+                }
+                if ui.button("Open Folder").clicked()
+                    && let Err(err) = open_folder(&current_dir)
+                {
+                    warn!("open folder failed: {err}");
+                    *toast_message = Some(("Failed to open folder".to_string(), now_unix_ms()));
+                }
+            });
+            ui.checkbox(
+                &mut ui_prefs.files_per_sender_subfolders,
+                "Put files from each device in its own subfolder",
+            );
+            let mut mark_of_the_web = !ui_prefs.files_skip_mark_of_the_web;
+            if ui
+                .checkbox(
+                    &mut mark_of_the_web,
+                    "Mark saved files as downloaded from the Internet",
+                )
+                .on_hover_text(
+                    "Writes the Zone.Identifier stream (Mark-of-the-Web), so SmartScreen \
+                     and Office Protected View treat saved files like browser downloads.",
+                )
+                .changed()
+            {
+                ui_prefs.files_skip_mark_of_the_web = !mark_of_the_web;
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(
+                    &mut ui_prefs.files_quarantine,
+                    "Keep received files in quarantine until accepted",
+                )
+                .on_hover_text(
+                    "Files wait in a staging folder, with their SHA-256 shown, until you \
+                     click Accept & Save.  Overrides \"Save automatically\".",
+                );
+                if ui.button("Open Staging Folder").clicked()
+                    && let Err(err) = open_folder(&incoming_staging_dir())
+                {
+                    warn!("open folder failed: {err}");
+                    *toast_message = Some(("Failed to open folder".to_string(), now_unix_ms()));
+                }
+            });
+
+            ui.add_space(4.0);
+            let prev_extensions = ui_prefs.incoming_file_extensions.clone();
+            ui.horizontal(|ui| {
+                ui.label("Incoming file types:");
+                let policy = &mut ui_prefs.incoming_file_extensions;
+                egui::ComboBox::from_id_salt("incoming_file_extension_mode")
+                    .selected_text(policy.mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in ExtensionMode::ALL {
+                            ui.selectable_value(&mut policy.mode, mode, mode.label());
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(
+                "Checked as each transfer starts: a refused file is dropped at its \
+                 first chunk and recorded in History.",
+            );
+            edit_list_setting(
+                ui,
+                "incoming_file_extensions",
+                "e.g. exe, scr, js",
+                &mut ui_prefs.incoming_file_extensions.extensions,
+                extension_policy::parse_extension_list,
+            );
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        ui_prefs.incoming_file_extensions != ExtensionPolicy::default(),
+                        egui::Button::new("Restore Default Block List"),
+                    )
+                    .clicked()
+                {
+                    ui_prefs.incoming_file_extensions = ExtensionPolicy::default();
+                }
+                if ui_prefs.incoming_file_extensions.mode == ExtensionMode::Allow
+                    && ui_prefs.incoming_file_extensions.extensions.is_empty()
+                {
+                    ui.label(
+                        egui::RichText::new("Empty allow list: every file is refused")
+                            .color(egui::Color32::from_rgb(200, 120, 0)),
+                    );
+                }
+            });
+            if ui_prefs.incoming_file_extensions != prev_extensions {
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SetExtensionPolicy(
+                    ui_prefs.incoming_file_extensions.clone(),
+                ));
+            }
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.label("Notification sound:");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut ui_prefs.sound_on_text, "Incoming text");
+                ui.checkbox(&mut ui_prefs.sound_on_file, "Incoming files");
+            });
+            ui.add_enabled_ui(ui_prefs.sound_on_text || ui_prefs.sound_on_file, |ui| {
+                ui.horizontal(|ui| {
+                    let is_custom = matches!(ui_prefs.sound_source, SoundSource::CustomWav { .. });
+                    if ui.radio(!is_custom, "System default").clicked() {
+                        ui_prefs.sound_source = SoundSource::SystemDefault;
+                    }
+                    if ui.radio(is_custom, "Custom WAV…").clicked()
+                        && let Some(path) = rfd::FileDialog::new()
+                            .set_title("Select notification sound")
+                            .add_filter("WAV audio", &["wav"])
+                            .pick_file()
+                    {
+                        ui_prefs.sound_source = SoundSource::CustomWav { path };
+                    }
+                    if ui.button("Test").clicked() {
+                        play_notification_sound(&ui_prefs.sound_source);
+                    }
+                });
+                if let SoundSource::CustomWav { path } = &ui_prefs.sound_source {
+                    ui.label(egui::RichText::new(path.display().to_string()).weak());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let mut enabled = ui_prefs.notification_auto_dismiss_secs.is_some();
+                let label = ui.checkbox(&mut enabled, "Auto-dismiss received text after");
+                let mut secs = ui_prefs
+                    .notification_auto_dismiss_secs
+                    .unwrap_or(DEFAULT_AUTO_DISMISS_SECS);
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.add(egui::DragValue::new(&mut secs).range(5..=600).suffix(" s"))
+                        .labelled_by(label.id);
+                });
+                ui_prefs.notification_auto_dismiss_secs = enabled.then_some(secs);
+            })
+            .response
+            .on_hover_text(
+                "Counts down once the item has been on screen in Notifications;\n\
+                 Keep Open stops it. The text stays in Activity History and the\n\
+                 quick-pick list. Files always wait for Save or Dismiss.",
+            );
+
+            ui.horizontal(|ui| {
+                let limit = &mut ui_prefs.notification_limit;
+                let label = ui.label("Notifications list holds up to");
+                ui.add(
+                    egui::DragValue::new(&mut limit.max_items)
+                        .range(notification_queue::MIN_ITEMS..=notification_queue::MAX_ITEMS_LIMIT)
+                        .suffix(" items"),
+                )
+                .labelled_by(label.id);
+                let when_full = ui.label("When full:");
+                egui::ComboBox::from_id_salt("notification_overflow_combo")
+                    .selected_text(limit.overflow.label())
+                    .show_ui(ui, |ui| {
+                        for policy in OverflowPolicy::ALL {
+                            ui.selectable_value(&mut limit.overflow, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .labelled_by(when_full.id);
+            })
+            .response
+            .on_hover_text(
+                "Every received item is in Activity History. A dropped item gets a\n\
+                 second entry there saying so; pinned items are never the ones\n\
+                 removed. Files that do not fit are deleted, not saved.",
+            );
+
+            let mut dnd_enabled = ui_prefs.dnd_schedule.is_some();
+            ui.horizontal(|ui| {
+                let from = ui.checkbox(&mut dnd_enabled, "Do not disturb from");
+                let mut schedule = ui_prefs.dnd_schedule.unwrap_or_default();
+                ui.add_enabled_ui(dnd_enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut schedule.start_hour)
+                            .range(0..=23)
+                            .suffix(":00"),
+                    )
+                    .labelled_by(from.id);
+                    let to = ui.label("to");
+                    ui.add(
+                        egui::DragValue::new(&mut schedule.end_hour)
+                            .range(0..=23)
+                            .suffix(":00"),
+                    )
+                    .labelled_by(to.id);
+                });
+                ui_prefs.dnd_schedule = dnd_enabled.then_some(schedule);
+            })
+            .response
+            .on_hover_text(
+                "During these hours incoming items are still queued, but no\n\
+                 sounds or system notifications are played.",
+            );
+        }
+
+        /// Options → Devices: this connection, the peers in the room and
+        /// room profiles.
+        #[allow(clippy::too_many_arguments)]
+        fn render_options_devices(
+            ui: &mut egui::Ui,
+            config: &ClientConfig,
+            connection_status: &str,
+            peers: &[PeerInfo],
+            room_key_ready: bool,
+            last_sent_time: &Option<u64>,
+            last_received_time: &Option<u64>,
+            last_error: &Option<String>,
+            known_devices: &DeviceRegistry,
+            invite_qr: &mut bool,
+            // Copying a join link or invite needs an unlock first.
+            invites_locked: bool,
+            unlock_request: &mut Option<AfterUnlock>,
+            toast_message: &mut Option<(String, u64)>,
+            room_profiles: &mut RoomProfiles,
+            room_profiles_error: Option<&str>,
+            room_profiles_changed: &mut bool,
+            room_action: &mut Option<RoomAction>,
+            config_change: &mut Option<SavedClientConfig>,
+            reconnect_requested: &mut bool,
+        ) {
+            ui.heading("Connection Info");
+            ui.add_space(4.0);
+
+            // Edited settings are kept between frames until applied or
+            // reverted.
+            let draft_id = ui.make_persistent_id("connection_settings_draft");
+            let current = SavedClientConfig {
+                server_url: config.server_url.clone(),
+                room_code: config.room_code.clone(),
+                device_name: config.device_name.clone(),
+            };
+            let mut draft = ui
+                .data_mut(|d| d.get_temp::<SavedClientConfig>(draft_id))
+                .unwrap_or_else(|| current.clone());
+
+            egui::Grid::new("info_grid")
+                .num_columns(2)
+                .spacing([12.0, 4.0])
+                .show(ui, |ui| {
+                    let label = ui.strong("Server URL:");
+                    ui.add(egui::TextEdit::singleline(&mut draft.server_url).desired_width(300.0))
+                        .labelled_by(label.id);
+                    ui.end_row();
+
+                    let label = ui.strong("Room code:");
+                    ui.add(egui::TextEdit::singleline(&mut draft.room_code).desired_width(300.0))
+                        .labelled_by(label.id);
+                    ui.end_row();
+
+                    ui.strong("Room ID:");
+                    ui.label(egui::RichText::new(&config.room_id).monospace().weak());
+                    ui.end_row();
+
+                    let label = ui.strong("Client name:");
+                    ui.add(egui::TextEdit::singleline(&mut draft.device_name).desired_width(300.0))
+                        .labelled_by(label.id);
+                    ui.end_row();
+
+                    ui.strong("Device ID:");
+                    ui.label(egui::RichText::new(&config.device_id).monospace().weak());
+                    ui.end_row();
+
+                    ui.strong("Connection:");
+                    ui.label(connection_status);
+                    ui.end_row();
+
+                    ui.strong("Peers:");
+                    let peer_names: Vec<&str> = peers
+                        .iter()
+                        .filter(|p| p.device_id != config.device_id)
+                        .map(|p| {
+                            known_devices
+                                .display_name(&p.device_id)
+                                .unwrap_or(&p.device_name)
+                        })
+                        .collect();
+                    if peer_names.is_empty() {
+                        ui.label("0");
+                    } else {
+                        ui.label(format!(
+                            "{} online: {}",
+                            peer_names.len(),
+                            peer_names.join(", ")
+                        ));
+                    }
+                    ui.end_row();
+
+                    ui.strong("Room key:");
+                    ui.label(if room_key_ready { "ready" } else { "not ready" });
+                    ui.end_row();
+
+                    ui.strong("Last sent:");
+                    ui.label(
+                        last_sent_time
+                            .map(format_timestamp_local)
+                            .unwrap_or_else(|| "-".to_owned()),
+                    );
+                    ui.end_row();
+
+                    ui.strong("Last received:");
+                    ui.label(
+                        last_received_time
+                            .map(format_timestamp_local)
+                            .unwrap_or_else(|| "-".to_owned()),
+                    );
+                    ui.end_row();
+                });
+
+            let draft = SavedClientConfig {
+                server_url: draft.server_url.trim().to_owned(),
+                room_code: draft.room_code.trim().to_owned(),
+                device_name: draft.device_name.trim().to_owned(),
+            };
+            let edited = draft.server_url != current.server_url
+                || draft.room_code != current.room_code
+                || draft.device_name != current.device_name;
+            let mut keep_draft = edited;
+            if edited {
+                ui.add_space(8.0);
+                let valid = validate_saved_config(&draft);
+                if let Err(msg) = &valid {
+                    ui.colored_label(egui::Color32::RED, msg);
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(valid.is_ok(), egui::Button::new("Apply & Reconnect"))
+                        .on_hover_text(
+                            "Save these settings and restart the connection with them.\n\
+                             A new room code joins another room; a new client name gives\n\
+                             this PC a new device ID, so other devices see a new device.",
+                        )
+                        .clicked()
+                    {
+                        match save_saved_config(&draft) {
+                            Ok(()) => {
+                                *config_change = Some(draft.clone());
+                                keep_draft = false;
+                            }
+                            Err(err) => {
+                                warn!("failed to save connection settings: {err}");
+                                *toast_message = Some((
+                                    format!("Failed to save settings: {err}"),
+                                    now_unix_ms(),
+                                ));
+                            }
+                        }
+                    }
+                    if ui.button("Revert").clicked() {
+                        keep_draft = false;
+                    }
+                });
+            }
+            ui.data_mut(|d| {
+                if keep_draft {
+                    d.insert_temp(draft_id, draft);
+                } else {
+                    d.remove::<SavedClientConfig>(draft_id);
+                }
+            });
+
+            if let Some(err) = last_error {
+                ui.add_space(8.0);
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Last error: {}", preview_text(err, 200)),
+                );
+            }
+
+            // ── Connected Peers ──────────────────────────────────────────────
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                ui.heading("Connected Peers");
+                if peers.is_empty() {
+                    ui.label(egui::RichText::new("(none)").weak());
+                } else {
+                    let others = peers
+                        .iter()
+                        .filter(|p| p.device_id != config.device_id)
+                        .count();
+                    if others == 0 {
+                        ui.label(egui::RichText::new("(only you)").weak());
+                    } else {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "({others} peer{})",
+                                if others == 1 { "" } else { "s" }
+                            ))
+                            .weak(),
+                        );
+                    }
+                }
+            });
+
+            ui.add_space(4.0);
+            let other_peers: Vec<_> = peers
+                .iter()
+                .filter(|p| p.device_id != config.device_id)
+                .collect();
+            if other_peers.is_empty() {
+                ui.label(
+                    egui::RichText::new(
+                        "No other peers in this room yet. Waiting for another device to join.",
+                    )
+                    .weak(),
+                );
+            } else {
+                for peer in &other_peers {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new("\u{2022}").strong());
+                        ui.label(resolve_peer_name(known_devices, peers, &peer.device_id));
+                        if known_devices.is_blocked(&peer.device_id) {
+                            ui.label(
+                                egui::RichText::new("blocked")
+                                    .color(egui::Color32::from_rgb(200, 60, 60)),
+                            );
+                        }
+                        let id_short = &peer.device_id[..8.min(peer.device_id.len())];
+                        ui.label(
+                            egui::RichText::new(format!("({id_short}\u{2026})"))
+                                .weak()
+                                .monospace(),
+                        );
+                    });
+                }
+            }
+
+            // ── Room / connection actions ────────────────────────────────────
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Reconnect")
+                    .on_hover_text(
+                        "Drop and re-establish the connection to the relay server.\n\
+                         This refreshes the peer list and room key without restarting the app.",
+                    )
+                    .clicked()
+                {
+                    *reconnect_requested = true;
+                }
+                if ui
+                    .button("Change Room\u{2026}")
+                    .on_hover_text(
+                        "Edit the room code, server or client name and join\n\
+                         without restarting.  Cancel rejoins this room.",
+                    )
+                    .clicked()
+                {
+                    *room_action = Some(RoomAction::Change);
+                }
+                if ui
+                    .button("Copy Join Link")
+                    .on_hover_text(
+                        "Copy a cliprelay:// link that joins this room in one click.\n\
+                         It contains the room code: share it only with people who\n\
+                         may read this room.",
+                    )
+                    .clicked()
+                {
+                    if invites_locked {
+                        *unlock_request = Some(AfterUnlock::CopyJoinLink);
+                    } else {
+                        let link = join_link::build(&config.server_url, &config.room_code);
+                        *toast_message = Some((
+                            match apply_clipboard_text(&link) {
+                                Ok(()) => "Join link copied".to_string(),
+                                Err(err) => format!("Failed to copy join link: {err}"),
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+                if ui
+                    .button("Copy Invite")
+                    .on_hover_text(
+                        "Copy a message to paste into a chat: the join link, the server\n\
+                         and room code for entering by hand, and how to join.  Also in\n\
+                         the tray menu.  It contains the room code.",
+                    )
+                    .clicked()
+                {
+                    if invites_locked {
+                        *unlock_request = Some(AfterUnlock::CopyInvite);
+                    } else {
+                        *toast_message = Some((
+                            match copy_invite(config, *invite_qr) {
+                                Ok(()) => "Invite copied".to_string(),
+                                Err(err) => format!("Failed to copy invite: {err}"),
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                }
+                ui.checkbox(invite_qr, "with QR code").on_hover_text(
+                    "Add a QR code of the join link, drawn with block characters.\n\
+                     It reads best pasted in a monospaced font.",
+                );
+                if ui
+                    .button("Leave Room")
+                    .on_hover_text("Disconnect and return to the room-selection screen.")
+                    .clicked()
+                {
+                    *room_action = Some(RoomAction::Leave);
+                }
+            });
+
+            ui.add_space(8.0);
+            *room_profiles_changed |= Self::render_room_profiles(
+                ui,
+                config,
+                room_profiles,
+                room_profiles_error,
+                toast_message,
+                room_action,
+            );
+        }
+
+        /// Stand-in for a view behind the lock.  Returns whether Unlock was
+        /// clicked.
+        fn render_locked(ui: &mut egui::Ui, what: &str, unlocking: bool) -> bool {
+            ui.add_space(24.0);
+            ui.vertical_centered(|ui| {
+                ui.heading(format!("{what} locked"));
+                ui.label("Confirm it is you with Windows Hello or your account password.");
+                ui.add_space(8.0);
+                if unlocking {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Waiting for Windows\u{2026}");
+                    });
+                    false
+                } else {
+                    ui.button("Unlock\u{2026}").clicked()
+                }
+            })
+            .inner
+        }
+
+        /// Options → History: the most recent activity entries.
+        fn render_options_history(
+            ui: &mut egui::Ui,
+            history: &mut VecDeque<ActivityEntry>,
+            clip_ring: &mut ClipRing,
+            retention: &mut HistoryRetention,
+            lock: &mut AuthGatePrefs,
+            unlocked: bool,
+            unlock_request: &mut Option<AfterUnlock>,
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            let prev_retention = *retention;
+            ui.horizontal(|ui| {
+                let label = ui.label("Keep at most");
+                ui.add(
+                    egui::DragValue::new(&mut retention.max_entries)
+                        .range(1..=history_retention::MAX_ENTRIES_LIMIT)
+                        .suffix(" entries"),
+                )
+                .labelled_by(label.id);
+            });
+            ui.horizontal(|ui| {
+                let mut limited = retention.max_age_days.is_some();
+                let label = ui.checkbox(&mut limited, "Remove entries older than");
+                let mut days = retention.max_age_days.unwrap_or(30);
+                ui.add_enabled_ui(limited, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut days)
+                            .range(1..=history_retention::MAX_AGE_DAYS_LIMIT)
+                            .suffix(" days"),
+                    )
+                    .labelled_by(label.id);
+                });
+                retention.max_age_days = limited.then_some(days);
+            });
+            ui.checkbox(&mut retention.store_content, "Store content previews")
+                .on_hover_text(
+                    "When off, history records only the time, direction, device and type\n\
+                     of each item.  Existing previews and links are removed.",
+                );
+            *retention = retention.normalized();
+            if *retention != prev_retention && enforce_history_retention(history, *retention) {
+                save_history(history);
+            }
+
+            ui.horizontal(|ui| {
+                let mut enabled = lock.enabled;
+                let label = ui
+                    .checkbox(&mut enabled, "Lock history, snippets and invites after")
+                    .on_hover_text(
+                        "Ask for Windows Hello, or the account password where Hello is\n\
+                         not set up, before showing activity history or snippets or\n\
+                         copying an invite, once they have gone unused this long.\n\
+                         Turning it on or off asks first.",
+                    );
+                // Either way the change waits for a successful check.
+                if enabled != lock.enabled {
+                    *unlock_request = Some(if enabled {
+                        AfterUnlock::EnableLock
+                    } else {
+                        AfterUnlock::DisableLock
+                    });
+                }
+                ui.add_enabled_ui(lock.enabled && unlocked, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut lock.relock_mins)
+                            .range(1..=auth_gate::MAX_RELOCK_MINS)
+                            .suffix(" min"),
+                    )
+                    .labelled_by(label.id);
+                });
+                *lock = lock.normalized();
+            });
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                ui.heading("Activity History");
+                ui.add_space(4.0);
+                if !history.is_empty()
+                    && ui
+                        .button("Clear All History")
+                        .on_hover_text(
+                            "Remove all activity history entries, with the recent texts\n\
+                             kept for Quick Pick, and delete the history file.  The file\n\
+                             is deleted, not wiped: its old contents may stay on disk.",
+                        )
+                        .clicked()
+                {
+                    *toast_message = Some(match clear_all_history(history, clip_ring) {
+                        Ok(()) => ("Activity history cleared".to_string(), now_unix_ms()),
+                        Err(err) => {
+                            warn!("history delete failed: {err}");
+                            (
+                                "Activity history cleared, but the file could not be removed"
+                                    .to_string(),
+                                now_unix_ms(),
+                            )
+                        }
+                    });
+                }
+            });
+            ui.add_space(4.0);
+
+            if history.is_empty() {
+                ui.label(egui::RichText::new("(no activity yet)").weak());
+            } else {
+                // Favorites first, then pinned entries, then the 30 most
+                // recent others.
+                let favorites = history.iter().filter(|e| e.favorite).count();
+                let shown: Vec<usize> = (0..history.len())
+                    .filter(|&i| history[i].favorite)
+                    .chain(
+                        (0..history.len()).filter(|&i| !history[i].favorite && history[i].pinned),
+                    )
+                    .chain(
+                        (0..history.len())
+                            .filter(|&i| !history[i].favorite && !history[i].pinned)
+                            .take(30),
+                    )
+                    .collect();
+                let selected = history.iter().filter(|e| e.selected).count();
+                ui.horizontal(|ui| {
+                    if ui.button("Select All").clicked() {
+                        shown.iter().for_each(|&i| history[i].selected = true);
+                    }
+                    if ui.button("Select None").clicked() {
+                        history.iter_mut().for_each(|e| e.selected = false);
+                    }
+                    if ui
+                        .add_enabled(
+                            selected > 0,
+                            egui::Button::new(format!("Delete Selected ({selected})")),
+                        )
+                        .on_hover_text(
+                            "Remove the ticked entries, and their texts kept for Quick Pick.",
+                        )
+                        .clicked()
+                    {
+                        let removed = delete_selected_history(history, clip_ring);
+                        *toast_message = Some((
+                            format!(
+                                "{removed} history entr{} deleted",
+                                if removed == 1 { "y" } else { "ies" }
+                            ),
+                            now_unix_ms(),
+                        ));
+                    }
+                });
+                ui.add_space(4.0);
+                let mut toggle_pin: Option<usize> = None;
+                let mut toggle_favorite: Option<usize> = None;
+                let mut toggle_selected: Option<usize> = None;
+                for (pos, (idx, entry)) in shown.iter().map(|&i| (i, &history[i])).enumerate() {
+                    if pos == 0 && favorites > 0 {
+                        ui.label(egui::RichText::new("Favorites").strong());
+                    }
+                    if pos == favorites && favorites > 0 {
+                        ui.add_space(6.0);
+                        ui.label(egui::RichText::new("Recent").strong());
+                    }
+                    let dir = match entry.direction {
+                        ActivityDirection::Sent => "↑ SENT",
+                        ActivityDirection::Received => "↓ RECV",
+                    };
+                    let ts = format_timestamp_local(entry.ts_unix_ms);
+                    ui.horizontal(|ui| {
+                        let mut ticked = entry.selected;
+                        if ui.checkbox(&mut ticked, "").changed() {
+                            toggle_selected = Some(idx);
+                        }
+                        ui.label(egui::RichText::new(format!("{}.", pos + 1)).weak());
+                        ui.label(
+                            egui::RichText::new(format!("[{}] {} {}", ts, dir, entry.kind))
+                                .strong(),
+                        );
+                        if entry.repeat_count > 1 {
+                            ui.label(format!("×{}", entry.repeat_count)).on_hover_text(
+                                "Recorded this many times in a row; the time is the latest.",
+                            );
+                        }
+                        if entry.pinned {
+                            ui.label(egui::RichText::new("pinned").italics());
+                        }
+                        let (pin_label, pin_hover) = if entry.pinned {
+                            (
+                                "Unpin",
+                                "Let the entry limit and age limit remove this entry.",
+                            )
+                        } else {
+                            (
+                                "Pin",
+                                "Keep this entry at the top, out of the entry and age limits.",
+                            )
+                        };
+                        if ui
+                            .small_button(pin_label)
+                            .on_hover_text(pin_hover)
+                            .clicked()
+                        {
+                            toggle_pin = Some(idx);
+                        }
+                        let (favorite_label, favorite_hover) = if entry.favorite {
+                            (
+                                "\u{2605} Unfavorite",
+                                "Let the entry limit and age limit remove this entry.",
+                            )
+                        } else {
+                            (
+                                "\u{2606} Favorite",
+                                "List this entry under Favorites, out of the entry and age \
+                                 limits.",
+                            )
+                        };
+                        if ui
+                            .small_button(favorite_label)
+                            .on_hover_text(favorite_hover)
+                            .clicked()
+                        {
+                            toggle_favorite = Some(idx);
+                        }
+                    });
+                    ui.indent(format!("hist_{idx}"), |ui| {
+                        ui.label(egui::RichText::new(&entry.summary).weak());
+                        match entry.delivery {
+                            Some(status @ DeliveryStatus::Failed) => {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(200, 60, 60),
+                                    status.label(),
+                                );
+                            }
+                            Some(status @ DeliveryStatus::Retrying { .. }) => {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(200, 140, 0),
+                                    status.label(),
+                                );
+                            }
+                            Some(
+                                status @ (DeliveryStatus::Pending | DeliveryStatus::Cancelled),
+                            ) => {
+                                ui.label(egui::RichText::new(status.label()).italics().weak());
+                            }
+                            Some(DeliveryStatus::Sent) | None => {}
+                        }
+                        if let Some(link) = entry.link.as_deref()
+                            && ui.small_button("Open Link").on_hover_text(link).clicked()
+                            && let Err(err) = open_url(link)
+                        {
+                            warn!("open link failed: {err}");
+                            *toast_message =
+                                Some(("Failed to open link".to_string(), now_unix_ms()));
+                        }
+                        if let Some(checksum) = entry.checksum.as_deref() {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "SHA-256 {}\u{2026}",
+                                        &checksum[..checksum.len().min(16)]
+                                    ))
+                                    .monospace()
+                                    .weak(),
+                                )
+                                .on_hover_text(checksum);
+                                if ui.small_button("Copy Checksum").clicked() {
+                                    copy_checksum(checksum, toast_message);
+                                }
+                            });
+                        }
+                    });
+                }
+                if let Some(idx) = toggle_pin {
+                    history[idx].pinned = !history[idx].pinned;
+                    // Unpinning may put the entry past the limits.
+                    enforce_history_retention(history, *retention);
+                    save_history(history);
+                }
+                if let Some(idx) = toggle_favorite {
+                    toggle_history_favorite(history, *retention, idx);
+                }
+                if let Some(entry) = toggle_selected.and_then(|idx| history.get_mut(idx)) {
+                    entry.selected = !entry.selected;
+                }
+            }
+        }
+
+        /// Options → Stats: local usage totals, by device and by day.
+        fn render_options_stats(
+            ui: &mut egui::Ui,
+            stats: &mut UsageStats,
+            toast_message: &mut Option<(String, u64)>,
+        ) {
+            /// Days listed, newest first.
+            const DAYS_SHOWN: usize = 14;
+
+            fn cell(messages: u64, bytes: u64) -> String {
+                format!("{messages} ({})", transfers::format_bytes(bytes))
+            }
+
+            ui.horizontal(|ui| {
+                ui.heading("Usage");
+                ui.add_space(4.0);
+                if ui
+                    .button("Reset")
+                    .on_hover_text("Set every counter back to zero.")
+                    .clicked()
+                {
+                    stats.reset(now_unix_ms());
+                    save_usage_stats_logged(stats);
+                    *toast_message = Some(("Usage statistics reset".to_string(), now_unix_ms()));
+                }
+            });
+            ui.label(
+                egui::RichText::new(format!(
+                    "Counted on this computer since {}; never sent anywhere.",
+                    format_timestamp_local(stats.since_unix_ms)
+                ))
+                .weak(),
+            );
+            ui.add_space(4.0);
+
+            egui::Grid::new("usage_totals_grid")
+                .num_columns(2)
+                .spacing([12.0, 4.0])
+                .show(ui, |ui| {
+                    ui.strong("Sent:");
+                    ui.label(cell(stats.totals.sent_messages, stats.totals.sent_bytes));
+                    ui.end_row();
+
+                    ui.strong("Received:");
+                    ui.label(cell(
+                        stats.totals.received_messages,
+                        stats.totals.received_bytes,
+                    ));
+                    ui.end_row();
+                });
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+            ui.heading("By Device");
+            ui.label(
+                egui::RichText::new(
+                    "A message sent while several devices were online counts for each.  \
+                     Messages counted before text and files were told apart are only in \
+                     Sent to and Received from.",
+                )
+                .weak(),
+            );
+            ui.add_space(4.0);
+            if stats.peers.is_empty() {
+                ui.label(egui::RichText::new("(nothing yet)").weak());
+            } else {
+                egui::Grid::new("usage_peers_grid")
+                    .num_columns(5)
+                    .spacing([12.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Device");
+                        ui.strong("Sent to");
+                        ui.strong("Received from");
+                        ui.strong("Text sent / received");
+                        ui.strong("Files sent / received");
+                        ui.end_row();
+                        for (device_id, usage) in stats.peers_by_volume() {
+                            let name = if usage.name.is_empty() {
+                                device_id
+                            } else {
+                                usage.name.as_str()
+                            };
+                            ui.label(name).on_hover_text(device_id);
+                            ui.label(cell(usage.counts.sent_messages, usage.counts.sent_bytes));
+                            ui.label(cell(
+                                usage.counts.received_messages,
+                                usage.counts.received_bytes,
+                            ));
+                            for counts in [usage.text, usage.files] {
+                                ui.label(format!(
+                                    "{} / {}",
+                                    cell(counts.sent_messages, counts.sent_bytes),
+                                    cell(counts.received_messages, counts.received_bytes)
+                                ));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            }
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+            ui.heading("By Day");
+            ui.add_space(4.0);
+            if stats.days.is_empty() {
+                ui.label(egui::RichText::new("(nothing yet)").weak());
+            } else {
+                egui::Grid::new("usage_days_grid")
+                    .num_columns(3)
+                    .spacing([12.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Day");
+                        ui.strong("Sent");
+                        ui.strong("Received");
+                        ui.end_row();
+                        for (day, counts) in stats.days.iter().rev().take(DAYS_SHOWN) {
+                            ui.label(day);
+                            ui.label(cell(counts.sent_messages, counts.sent_bytes));
+                            ui.label(cell(counts.received_messages, counts.received_bytes));
+                            ui.end_row();
+                        }
+                    });
+            }
+        }
+
+        /// Options → Advanced: diagnostics, reconnect policy, protocol trace
+        /// and the automation API.
+        #[allow(clippy::too_many_arguments)]
+        fn render_options_advanced(
+            ui: &mut egui::Ui,
+            connection_status: &str,
+            room_key_ready: bool,
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            ui_prefs: &mut SavedUiState,
+            toast_message: &mut Option<(String, u64)>,
+            self_test_running: &mut bool,
+            self_test_report: Option<&SelfTestReport>,
+            latency: &LatencyHistory,
+            reconnect_requested: &mut bool,
+            view_logs_requested: &mut bool,
+            protocol_trace_requested: &mut bool,
+        ) {
+            ui.heading("Diagnostics");
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                let can_test = connection_status == "Connected" && room_key_ready;
+                let label = if *self_test_running {
+                    "Testing\u{2026}"
+                } else {
+                    "Run Self-Test"
+                };
+                if ui
+                    .add_enabled(can_test && !*self_test_running, egui::Button::new(label))
+                    .on_hover_text(
+                        "Join this room as a temporary virtual device and send an encrypted\n\
+                         probe through the relay back to this PC, checking every step up to\n\
+                         applying it to the clipboard.  Other devices ignore the probe.",
+                    )
+                    .clicked()
+                {
+                    *self_test_running = true;
+                    let _ = runtime_cmd_tx.send(RuntimeCommand::RunSelfTest);
+                }
+                if ui
+                    .button("View Logs\u{2026}")
+                    .on_hover_text("Show this PC's ClipRelay log, with filtering and search.")
+                    .clicked()
+                {
+                    *view_logs_requested = true;
+                }
+            });
+            if let Some(report) = self_test_report {
+                ui.add_space(4.0);
+                let (color, heading) = if report.passed() {
+                    (egui::Color32::from_rgb(60, 160, 60), "Self-test passed")
+                } else {
+                    (egui::Color32::from_rgb(200, 60, 60), "Self-test failed")
+                };
+                ui.colored_label(color, heading);
+                for (step, outcome) in &report.results {
+                    let line = match outcome {
+                        StepOutcome::Passed => format!("[ ok ] {}", step.label()),
+                        StepOutcome::Failed(reason) => {
+                            format!("[FAIL] {}: {reason}", step.label())
+                        }
+                        StepOutcome::Skipped => format!("[skip] {}", step.label()),
+                    };
+                    ui.label(egui::RichText::new(line).monospace());
+                }
+            }
+
+            ui.add_space(8.0);
+            Self::render_latency(ui, latency);
+            Self::render_probe_latency(ui, latency);
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            let prev_reconnect = ui_prefs.reconnect;
+            ui.horizontal(|ui| {
+                let label = ui.label("Reconnect after");
+                ui.add(
+                    egui::DragValue::new(&mut ui_prefs.reconnect.initial_delay_secs)
+                        .range(1..=reconnect::MAX_DELAY_LIMIT_SECS)
+                        .suffix(" s"),
+                )
+                .labelled_by(label.id);
+                let max = ui.label(", doubling up to");
+                ui.add(
+                    egui::DragValue::new(&mut ui_prefs.reconnect.max_delay_secs)
+                        .range(1..=reconnect::MAX_DELAY_LIMIT_SECS)
+                        .suffix(" s"),
+                )
+                .labelled_by(max.id);
+            });
+            ui.horizontal(|ui| {
+                let mut limited = ui_prefs.reconnect.max_attempts.is_some();
+                if ui
+                    .checkbox(&mut limited, "Give up after")
+                    .on_hover_text(
+                        "Stop retrying after this many failed attempts in a row, for example\n\
+                         when the server URL is wrong.  The tray turns red and offers Retry Now.",
+                    )
+                    .changed()
+                {
+                    ui_prefs.reconnect.max_attempts = limited.then_some(10);
+                }
+                if let Some(attempts) = ui_prefs.reconnect.max_attempts.as_mut() {
+                    ui.add(
+                        egui::DragValue::new(attempts)
+                            .range(1..=reconnect::MAX_ATTEMPTS_LIMIT)
+                            .suffix(" attempts"),
+                    );
+                } else {
+                    ui.weak("(retry forever)");
+                }
+            });
+            ui_prefs.reconnect = ui_prefs.reconnect.normalized();
+            if ui_prefs.reconnect != prev_reconnect {
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SetReconnectPolicy(ui_prefs.reconnect));
+            }
+
+            ui.add_space(12.0);
+            ui.separator();
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                let prev_trace = ui_prefs.protocol_trace_enabled;
+                ui.checkbox(
+                    &mut ui_prefs.protocol_trace_enabled,
+                    "Protocol trace (advanced)",
+                )
+                .on_hover_text(
+                    "Record a summary of every frame exchanged with the relay:\n\
+                     message type, sender, counter, sizes and times.\n\
+                     Clipboard contents are never recorded.",
+                );
+                if ui_prefs.protocol_trace_enabled != prev_trace {
+                    let _ = runtime_cmd_tx.send(RuntimeCommand::SetProtocolTrace(
+                        ui_prefs.protocol_trace_enabled,
+                    ));
+                    *protocol_trace_requested = ui_prefs.protocol_trace_enabled;
+                }
+                if ui_prefs.protocol_trace_enabled && ui.button("Open Trace\u{2026}").clicked() {
+                    *protocol_trace_requested = true;
+                }
+            });
+            ui.add_space(4.0);
+
+            let prev_automation = ui_prefs.automation_api_enabled;
+            ui.checkbox(
+                &mut ui_prefs.automation_api_enabled,
+                "Enable local automation API (named pipe)",
+            )
+            .on_hover_text(
+                "Lets local tools (AutoHotkey, editor plugins, scripts) send text/files,\n\
+                 query status and peers, and read recent history.\n\
+                 Every request must include the access token below.",
+            );
+            if ui_prefs.automation_api_enabled != prev_automation {
+                // The pipe server lives on the runtime; restart it so the
+                // change takes effect immediately.
+                *reconnect_requested = true;
+            }
+            if ui_prefs.automation_api_enabled {
+                ui.label(
+                    egui::RichText::new(automation::pipe_name(&current_user_name()))
+                        .monospace()
+                        .weak(),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Token").clicked() {
+                        *toast_message = Some((
+                            match automation::load_or_create_token()
+                                .map_err(|e| e.to_string())
+                                .and_then(|token| apply_clipboard_text(&token))
+                            {
+                                Ok(()) => "Automation token copied".to_string(),
+                                Err(err) => format!("Failed to copy token: {err}"),
+                            },
+                            now_unix_ms(),
+                        ));
+                    }
+                    if ui
+                        .button("Regenerate Token")
+                        .on_hover_text("Invalidate the current token and create a new one.")
+                        .clicked()
+                    {
+                        match automation::regenerate_token() {
+                            Ok(_) => {
+                                *reconnect_requested = true;
+                                *toast_message = Some((
+                                    "Automation token regenerated".to_string(),
+                                    now_unix_ms(),
+                                ));
+                            }
+                            Err(err) => {
+                                warn!("automation token regenerate failed: {err}");
+                                *toast_message = Some((
+                                    "Failed to regenerate automation token".to_string(),
+                                    now_unix_ms(),
+                                ));
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
+        /// Options section editing the ordered text transform list.
+        fn render_text_transforms(ui: &mut egui::Ui, list: &mut Vec<Transform>) {
+            ui.horizontal(|ui| {
+                ui.strong("Text transforms");
+                ui.add_enabled_ui(list.len() < transforms::MAX_TRANSFORMS, |ui| {
+                    ui.menu_button("Add\u{2026}", |ui| {
+                        for kind in TransformKind::templates() {
+                            if ui.button(kind.label()).clicked() {
+                                list.push(Transform::new(kind));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            })
+            .response
+            .on_hover_text(
+                "Clean-up steps run top to bottom on text you send (Send tab, quick\n\
+                 pick, automation API) and/or on received text when it is applied.\n\
+                 Snippets are sent exactly as saved.",
+            );
+            if list.is_empty() {
+                ui.label(egui::RichText::new("(none)").weak());
+                return;
+            }
+
+            let mut move_request: Option<(usize, usize)> = None;
+            let mut remove: Option<usize> = None;
+            let count = list.len();
+            for (idx, transform) in list.iter_mut().enumerate() {
+                ui.group(|ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut transform.enabled, transform.kind.label());
+                        ui.checkbox(&mut transform.on_send, "On send");
+                        ui.checkbox(&mut transform.on_apply, "On apply");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("Remove").clicked() {
+                                remove = Some(idx);
+                            }
+                            if ui
+                                .add_enabled(idx + 1 < count, egui::Button::new("\u{2193}"))
+                                .on_hover_text("Move down")
+                                .clicked()
+                            {
+                                move_request = Some((idx, idx + 1));
+                            }
+                            if ui
+                                .add_enabled(idx > 0, egui::Button::new("\u{2191}"))
+                                .on_hover_text("Move up")
+                                .clicked()
+                            {
+                                move_request = Some((idx, idx - 1));
+                            }
+                        });
+                    });
+                    match &mut transform.kind {
+                        TransformKind::Trim | TransformKind::PlainPunctuation => {}
+                        TransformKind::NormalizeNewlines { style } => {
+                            ui.horizontal(|ui| {
+                                ui.label("Line breaks:");
+                                for option in NewlineStyle::ALL {
+                                    ui.radio_value(style, option, option.label());
+                                }
+                            });
+                        }
+                        TransformKind::StripQueryParams { patterns } => {
+                            edit_list_setting(
+                                ui,
+                                &format!("transform_params_{idx}"),
+                                "parameter names, * as wildcard (e.g. utm_*, fbclid)",
+                                patterns,
+                                transforms::parse_param_patterns,
+                            );
+                        }
+                        TransformKind::Replace { find, replace } => {
+                            ui.horizontal(|ui| {
+                                let label = ui.label("Find:");
+                                ui.add(egui::TextEdit::singleline(find).desired_width(140.0))
+                                    .labelled_by(label.id);
+                                let label = ui.label("Replace with:");
+                                ui.add(egui::TextEdit::singleline(replace).desired_width(140.0))
+                                    .labelled_by(label.id);
+                            });
+                        }
+                    }
+                });
+            }
+            if let Some((from, to)) = move_request {
+                list.swap(from, to);
+            }
+            if let Some(idx) = remove {
+                list.remove(idx);
+            }
+        }
+
+        // ─── Notifications tab ─────────────────────────────────────────────────
+
+        #[allow(clippy::too_many_arguments)]
+        fn render_notifications_tab(
+            ui: &mut egui::Ui,
+            notifications: &mut Vec<PendingNotification>,
+            peers: &[PeerInfo],
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            _history: &mut VecDeque<ActivityEntry>,
+            coalesced: &mut usize,
+            known_devices: &DeviceRegistry,
+            ui_prefs: &mut SavedUiState,
+            server_url: &str,
+            toast_message: &mut Option<(String, u64)>,
+            // Set to `true` when the applied text should also be pasted into
+            // the previously active application (handled by the caller,
+            // which owns window visibility).
+            paste_requested: &mut bool,
+        ) {
+            if *coalesced > 0 {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} more item{} arrived while the list was full",
+                            coalesced,
+                            if *coalesced == 1 { "" } else { "s" }
+                        ))
+                        .strong(),
+                    )
+                    .on_hover_text(
+                        "They are listed in Activity History (Options \u{2192} History).",
+                    );
+                    if ui.small_button("Clear").clicked() {
+                        *coalesced = 0;
+                    }
+                });
+                ui.separator();
+            }
+            if notifications.is_empty() {
+                ui.centered_and_justified(|ui| {
+                    ui.label(egui::RichText::new("No pending notifications").weak());
+                });
+                return;
+            }
+
+            // Requested operations, applied after the list is drawn so that
+            // indices stay valid while rendering.
+            let mut action: Option<(Vec<usize>, NotificationAction)> = None;
+            let mut move_request: Option<(usize, usize)> = None;
+            let mut unpin_request: Option<usize> = None;
+
+            let total = notifications.len();
+            let selected: Vec<usize> = notifications
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| n.selected)
+                .map(|(idx, _)| idx)
+                .collect();
+
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(format!("{total} pending")).strong());
+                ui.add_space(8.0);
+                if ui.button("Select All").clicked() {
+                    notifications.iter_mut().for_each(|n| n.selected = true);
+                }
+                if ui.button("Select None").clicked() {
+                    notifications.iter_mut().for_each(|n| n.selected = false);
+                }
+                ui.add_space(8.0);
+                if ui
+                    .button("Apply All")
+                    .on_hover_text(
+                        "Apply texts and save files for the whole queue, top to bottom,\n\
+                         so the newest text ends up on the clipboard.",
+                    )
+                    .clicked()
+                {
+                    action = Some(((0..total).collect(), NotificationAction::Apply));
+                }
+                if ui.button("Dismiss All").clicked() {
+                    action = Some(((0..total).collect(), NotificationAction::Dismiss));
+                }
+                ui.add_space(8.0);
+                ui.checkbox(&mut ui_prefs.append_mode, "Append")
+                    .on_hover_text(format!(
+                        "Add applied text after the text already on the clipboard\n\
+                         ({} in between) instead of replacing it.  The separator is\n\
+                         set in Options.",
+                        ui_prefs.append_separator.label().to_lowercase()
+                    ));
+            });
+            // Keyboard: Ctrl+Enter applies/saves and Delete dismisses the
+            // selected items, or the top item when nothing is selected.
+            let targets = if selected.is_empty() {
+                vec![0]
+            } else {
+                selected.clone()
+            };
+            ui.input_mut(|i| {
+                if i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter) {
+                    action = Some((targets.clone(), NotificationAction::Apply));
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Delete) {
+                    action = Some((targets.clone(), NotificationAction::Dismiss));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let any = !selected.is_empty();
+                if ui
+                    .add_enabled(
+                        any,
+                        egui::Button::new(format!("Apply/Save Selected ({})", selected.len())),
+                    )
+                    .on_hover_text(
+                        "Texts are applied in list order, so the last selected text\n\
+                         ends up on the clipboard. Files are saved to the folder chosen in Options.\n\
+                         Shortcut: Ctrl+Enter (the top item if nothing is selected).",
+                    )
+                    .clicked()
+                {
+                    action = Some((selected.clone(), NotificationAction::Apply));
+                }
+                if ui
+                    .add_enabled(any, egui::Button::new("Dismiss Selected"))
+                    .on_hover_text("Shortcut: Delete (the top item if nothing is selected).")
+                    .clicked()
+                {
+                    action = Some((selected.clone(), NotificationAction::Dismiss));
+                }
+            });
+            ui.add_space(4.0);
+            ui.separator();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (idx, pending) in notifications.iter_mut().enumerate() {
+                    let (sender_device_id, preview, links, label, language, checksum) =
+                        match &pending.notification {
+                            Notification::Text {
+                                sender_device_id,
+                                preview,
+                                links,
+                                label,
+                                language,
+                                ..
+                            } => (
+                                sender_device_id,
+                                preview,
+                                links.as_slice(),
+                                label.as_deref(),
+                                *language,
+                                None,
+                            ),
+                            Notification::File {
+                                sender_device_id,
+                                preview,
+                                sha256,
+                                ..
+                            } => (
+                                sender_device_id,
+                                preview,
+                                &[][..],
+                                None,
+                                None,
+                                Some(sha256.as_str()),
+                            ),
+                        };
+                    let is_text = checksum.is_none();
+                    let name = resolve_peer_name(known_devices, peers, sender_device_id);
+
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.horizontal(|ui| {
+                            let select = ui.checkbox(&mut pending.selected, "");
+                            let kind = if pending.pinned {
+                                "Pinned text"
+                            } else if is_text {
+                                "Text"
+                            } else {
+                                "File"
+                            };
+                            let title = ui.strong(labelled(label, &format!("{kind} from {name}")));
+                            select.labelled_by(title.id);
+                            if let Some(language) = language {
+                                ui.weak(language.label());
+                            }
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if pending.pinned
+                                        && ui
+                                            .button("Unpin")
+                                            .on_hover_text(
+                                                "Stop keeping this item at the top; applying it\n\
+                                                 then removes it from the list.",
+                                            )
+                                            .clicked()
+                                    {
+                                        unpin_request = Some(idx);
+                                    }
+                                    if ui
+                                        .add_enabled(
+                                            idx + 1 < total,
+                                            egui::Button::new("\u{2193} Down"),
+                                        )
+                                        .on_hover_text("Move down")
+                                        .clicked()
+                                    {
+                                        move_request = Some((idx, idx + 1));
+                                    }
+                                    if ui
+                                        .add_enabled(idx > 0, egui::Button::new("\u{2191} Up"))
+                                        .on_hover_text("Move up")
+                                        .clicked()
+                                    {
+                                        move_request = Some((idx, idx - 1));
+                                    }
+                                },
+                            );
+                        });
+
+                        egui::CollapsingHeader::new(preview_text(preview, 80).replace('\n', " "))
+                            .id_salt(("notification_preview", idx))
+                            .show(ui, |ui| {
+                                egui::ScrollArea::vertical()
+                                    .id_salt(("notification_preview_scroll", idx))
+                                    .max_height(160.0)
+                                    .show(ui, |ui| match language {
+                                        Some(language) => {
+                                            ui.label(highlighted_code(ui, preview, language));
+                                        }
+                                        None => {
+                                            ui.label(preview.as_str());
+                                        }
+                                    });
+                            });
+
+                        ui.horizontal(|ui| {
+                            let apply_label = if is_text {
+                                "Apply to Clipboard"
+                            } else if ui_prefs.files_quarantine {
+                                "Accept & Save"
+                            } else {
+                                "Save"
+                            };
+                            let mut apply = ui.button(apply_label);
+                            if !is_text {
+                                apply = apply.on_hover_text(
+                                    "Save the file, then put it on the clipboard: Ctrl+V \
+                                     pastes it into Explorer or an e-mail.",
+                                );
+                            }
+                            if apply.clicked() {
+                                action = Some((vec![idx], NotificationAction::Apply));
+                            }
+                            if is_text
+                                && ui_prefs.auto_paste_allowed
+                                && ui
+                                    .button("Apply & Paste")
+                                    .on_hover_text(
+                                        "Apply, hide ClipRelay, and press Ctrl+V in the \
+                                         previously active application.",
+                                    )
+                                    .clicked()
+                            {
+                                action = Some((vec![idx], NotificationAction::ApplyAndPaste));
+                            }
+                            let mut open_link: Option<&str> = None;
+                            match links {
+                                [] => {}
+                                [link] => {
+                                    if ui
+                                        .button("Open in Browser")
+                                        .on_hover_text(link.as_str())
+                                        .clicked()
+                                    {
+                                        open_link = Some(link.as_str());
+                                    }
+                                }
+                                _ => {
+                                    ui.menu_button("Open in Browser", |ui| {
+                                        for link in links {
+                                            if ui.button(link.as_str()).clicked() {
+                                                open_link = Some(link.as_str());
+                                                ui.close_menu();
+                                            }
+                                        }
+                                    });
+                                }
+                            }
+                            if let Some(link) = open_link
+                                && let Err(err) = open_url(link)
+                            {
+                                warn!("open link failed: {err}");
+                                *toast_message =
+                                    Some(("Failed to open link".to_string(), now_unix_ms()));
+                            }
+                            if !is_text
+                                && ui
+                                    .button("Open Folder")
+                                    .on_hover_text("Open the folder this file will be saved to.")
+                                    .clicked()
+                                && let Err(err) = open_folder(&received_files_dir(ui_prefs, &name))
+                            {
+                                warn!("open folder failed: {err}");
+                                *toast_message =
+                                    Some(("Failed to open folder".to_string(), now_unix_ms()));
+                            }
+                            if let Some(checksum) = checksum
+                                && ui
+                                    .button("Copy Checksum")
+                                    .on_hover_text(format!("SHA-256: {checksum}"))
+                                    .clicked()
+                            {
+                                copy_checksum(checksum, toast_message);
+                            }
+                            ui.add_space(4.0);
+                            if ui.button("Dismiss").clicked() {
+                                action = Some((vec![idx], NotificationAction::Dismiss));
+                            }
+                            if let Some(secs) = pending.auto_dismiss_remaining(
+                                ui_prefs.notification_auto_dismiss_secs,
+                                now_unix_ms(),
+                            ) {
+                                ui.label(
+                                    egui::RichText::new(format!("Dismissing in {secs}s")).weak(),
+                                );
+                                if ui
+                                    .small_button("Keep Open")
+                                    .on_hover_text("Keep this item until you apply or dismiss it.")
+                                    .clicked()
+                                {
+                                    pending.keep_open = true;
+                                }
+                            }
+                        });
+                    });
+                    ui.add_space(4.0);
+                }
+            });
+
+            if let Some((from, to)) = move_request {
+                notifications.swap(from, to);
+            }
+            if let Some(idx) = unpin_request {
+                // Back below the entries still pinned.
+                let mut pending = notifications.remove(idx);
+                pending.pinned = false;
+                let at = notifications.iter().take_while(|n| n.pinned).count();
+                notifications.insert(at, pending);
+            }
+
+            let Some((indices, action)) = action else {
+                return;
+            };
+            Self::process_notifications(
+                notifications,
+                &indices,
+                action,
+                peers,
+                runtime_cmd_tx,
+                known_devices,
+                ui_prefs,
+                server_url,
+                toast_message,
+                paste_requested,
+            );
+        }
+
+        /// Apply/save or dismiss the queue entries at `indices`, in queue
+        /// order (so the last text applied wins the clipboard), and report
+        /// the outcome in the toast.
+        #[allow(clippy::too_many_arguments)]
+        fn process_notifications(
+            notifications: &mut Vec<PendingNotification>,
+            indices: &[usize],
+            action: NotificationAction,
+            peers: &[PeerInfo],
+            runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+            known_devices: &DeviceRegistry,
+            ui_prefs: &SavedUiState,
+            server_url: &str,
+            toast_message: &mut Option<(String, u64)>,
+            paste_requested: &mut bool,
+        ) {
+            // Remove in descending index order, then restore queue order.
+            // Applying a pinned entry leaves it in place.
+            let mut taken: Vec<Notification> = Vec::with_capacity(indices.len());
+            for &idx in indices.iter().rev() {
+                match notifications.get_mut(idx) {
+                    Some(pending) if pending.pinned && action != NotificationAction::Dismiss => {
+                        pending.selected = false;
+                        taken.push(pending.notification.clone());
+                    }
+                    Some(_) => taken.push(notifications.remove(idx).notification),
+                    None => {}
+                }
+            }
+            taken.reverse();
+
+            if action == NotificationAction::Dismiss {
+                for n in taken {
+                    if let Notification::File { temp_path, .. } = n {
+                        let _ = std::fs::remove_file(&temp_path);
+                    }
+                }
+                return;
+            }
 
-                if history.is_empty() {
-                    ui.label(egui::RichText::new("(no activity yet)").weak());
+            let paste = ui_prefs.auto_paste_allowed
+                && (action == NotificationAction::ApplyAndPaste || ui_prefs.auto_paste_after_apply);
+            let count = taken.len();
+            let mut applied = 0usize;
+            let mut failures = 0usize;
+            let mut last_message = String::new();
+            let mut saved_files: Vec<PathBuf> = Vec::new();
+            for n in taken {
+                match n {
+                    Notification::Text {
+                        sender_device_id,
+                        full_text,
+                        content_hash,
+                        ..
+                    } => {
+                        let text =
+                            transforms::run(&ui_prefs.text_transforms, Stage::Apply, &full_text);
+                        if let Err(err) = apply_received_text(&text, ui_prefs) {
+                            warn!("apply failed: {err}");
+                            failures += 1;
+                            last_message = "Failed to apply clipboard text".to_string();
+                        } else {
+                            let _ = runtime_cmd_tx.send(RuntimeCommand::MarkApplied(content_hash));
+                            let name = resolve_peer_name(known_devices, peers, &sender_device_id);
+                            applied += 1;
+                            last_message = format!("Clipboard applied from {name}");
+                        }
+                    }
+                    Notification::File {
+                        sender_device_id,
+                        file_name,
+                        temp_path,
+                        ..
+                    } => {
+                        let name = resolve_peer_name(known_devices, peers, &sender_device_id);
+                        let dest_dir = received_files_dir(ui_prefs, &name);
+                        match save_received_file(
+                            &temp_path,
+                            &file_name,
+                            &dest_dir,
+                            mark_of_the_web_source(ui_prefs, server_url),
+                        ) {
+                            Ok(dest) => {
+                                let _ = std::fs::remove_file(&temp_path);
+                                last_message =
+                                    format!("Saved file from {name} to {}", dest.display());
+                                saved_files.push(dest);
+                            }
+                            Err(err) => {
+                                warn!("save file failed: {err}");
+                                failures += 1;
+                                last_message = "Failed to save received file".to_string();
+                            }
+                        }
+                    }
+                }
+            }
+            // Saved files go on the clipboard as files, ready to paste into
+            // Explorer or an e-mail, unless text was applied along with them.
+            if !saved_files.is_empty() && applied == 0 {
+                match file_clipboard::put_files(&saved_files) {
+                    Ok(()) => {
+                        applied += 1;
+                        if count == 1 {
+                            last_message.push_str(" \u{2014} Ctrl+V pastes it");
+                        }
+                    }
+                    Err(err) => warn!("putting saved files on the clipboard failed: {err}"),
+                }
+            }
+            if count > 1 {
+                last_message = if failures == 0 {
+                    format!("Processed {count} notifications")
                 } else {
-                    for (idx, entry) in history.iter().take(30).enumerate() {
-                        let dir = match entry.direction {
-                            ActivityDirection::Sent => "↑ SENT",
-                            ActivityDirection::Received => "↓ RECV",
+                    format!("Processed {count} notifications ({failures} failed)")
+                };
+            }
+            *toast_message = Some((last_message, now_unix_ms()));
+            *paste_requested |= paste && applied > 0;
+        }
+
+        /// One-line summary of who a send will reach: online peers first,
+        /// then recently seen offline devices with their last-seen time.
+        fn render_recipients(
+            ui: &mut egui::Ui,
+            peers: &[PeerInfo],
+            known_devices: &DeviceRegistry,
+            own_device_id: &str,
+        ) {
+            let now = now_unix_ms();
+            let online: Vec<&PeerInfo> = peers
+                .iter()
+                .filter(|p| p.device_id != own_device_id)
+                .collect();
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Recipients:");
+                if online.is_empty() {
+                    ui.label(egui::RichText::new("nobody online").weak());
+                }
+                for peer in &online {
+                    let name = known_devices
+                        .display_name(&peer.device_id)
+                        .unwrap_or(&peer.device_name);
+                    ui.colored_label(
+                        egui::Color32::from_rgb(0, 160, 0),
+                        format!("\u{25CF} {name}"),
+                    )
+                    .on_hover_text("Online");
+                }
+                for device in known_devices
+                    .by_last_seen()
+                    .into_iter()
+                    .filter(|d| !online.iter().any(|p| p.device_id == d.device_id))
+                    .filter(|d| d.trust != TrustState::Blocked)
+                    .take(MAX_OFFLINE_RECIPIENTS_SHOWN)
+                {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "\u{25CB} {} ({})",
+                            device.display_name(),
+                            devices::last_seen_ago(device.last_seen_unix_ms, now)
+                        ))
+                        .weak(),
+                    )
+                    .on_hover_text(format!(
+                        "Offline, last seen {}",
+                        format_timestamp_local(device.last_seen_unix_ms)
+                    ));
+                }
+            });
+        }
+
+        // ─── Devices tab ───────────────────────────────────────────────────────
+
+        /// Returns `true` when the registry was modified and should be saved.
+        fn render_devices_tab(
+            ui: &mut egui::Ui,
+            known_devices: &mut DeviceRegistry,
+            peers: &[PeerInfo],
+            usage_stats: &UsageStats,
+            toast_message: &mut Option<(String, u64)>,
+        ) -> bool {
+            let mut changed = false;
+            let mut forget: Option<String> = None;
+            let mut accept_key: Option<String> = None;
+
+            ui.heading("Devices");
+            ui.label(
+                egui::RichText::new(
+                    "Nicknames, trust and per-device settings are stored on this PC only.",
+                )
+                .weak(),
+            );
+            ui.add_space(8.0);
+
+            if known_devices.is_empty() {
+                ui.label(
+                    egui::RichText::new(
+                        "No devices seen yet. Devices appear here once they join the room.",
+                    )
+                    .weak(),
+                );
+                return false;
+            }
+
+            let ids: Vec<String> = known_devices
+                .by_last_seen()
+                .into_iter()
+                .map(|d| d.device_id.clone())
+                .collect();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for id in &ids {
+                    let Some(device) = known_devices.get_mut(id) else {
+                        continue;
+                    };
+                    let online = peers.iter().any(|p| &p.device_id == id);
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(device.display_name()).strong());
+                            let id_short = &id[..8.min(id.len())];
+                            ui.label(
+                                egui::RichText::new(format!("({id_short}\u{2026})"))
+                                    .weak()
+                                    .monospace(),
+                            );
+                            if online {
+                                ui.colored_label(egui::Color32::from_rgb(0, 160, 0), "online");
+                            } else {
+                                ui.label(egui::RichText::new("offline").weak());
+                            }
+                        });
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Advertised as \"{}\" | first seen {} | last seen {}",
+                                device.advertised_name,
+                                format_timestamp_local(device.first_seen_unix_ms),
+                                if online {
+                                    "now".to_owned()
+                                } else {
+                                    format!(
+                                        "{} ({})",
+                                        devices::last_seen_ago(
+                                            device.last_seen_unix_ms,
+                                            now_unix_ms()
+                                        ),
+                                        format_timestamp_local(device.last_seen_unix_ms)
+                                    )
+                                },
+                            ))
+                            .weak(),
+                        );
+                        match &device.pinned_key {
+                            Some(key) => ui.label(
+                                egui::RichText::new(format!(
+                                    "Identity: pinned {}",
+                                    cliprelay_core::key_fingerprint(key)
+                                ))
+                                .weak()
+                                .monospace(),
+                            ),
+                            None => ui.label(
+                                egui::RichText::new(
+                                    "Identity: not yet verified (no signed message received)",
+                                )
+                                .weak(),
+                            ),
                         };
-                        let ts = format_timestamp_local(entry.ts_unix_ms);
-                        ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new(format!("{}.", idx + 1)).weak());
+                        if let Some(new_key) = device.changed_key.clone() {
                             ui.label(
-                                egui::RichText::new(format!("[{}] {} {}", ts, dir, entry.kind))
-                                    .strong(),
+                                egui::RichText::new(format!(
+                                    "\u{26A0} This device presented a different identity key: {}\n\
+                                     Automatic actions are disabled until you accept it. \
+                                     Only accept if the device was reinstalled or reset.",
+                                    cliprelay_core::key_fingerprint(&new_key)
+                                ))
+                                .color(egui::Color32::from_rgb(200, 60, 60)),
                             );
+                            if ui.button("Accept New Key").clicked() {
+                                accept_key = Some(id.clone());
+                            }
+                        }
+                        ui.label(
+                            egui::RichText::new(match usage_stats.peers.get(id) {
+                                Some(usage) => peer_traffic(usage),
+                                None => "Traffic: nothing yet".to_owned(),
+                            })
+                            .weak(),
+                        )
+                        .on_hover_text(
+                            "Counted on this PC since the statistics were last reset \
+                             (Options \u{2192} Stats).",
+                        );
+
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Nickname:");
+                            // Buffer edits in temp memory and commit on focus
+                            // loss, so trimming doesn't fight the user's typing.
+                            let nick_id = ui.make_persistent_id(("device_nickname", id));
+                            let mut raw = ui
+                                .data_mut(|d| d.get_temp::<String>(nick_id))
+                                .unwrap_or_else(|| device.nickname.clone().unwrap_or_default());
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut raw)
+                                    .hint_text(device.advertised_name.as_str())
+                                    .desired_width(200.0),
+                            )
+                            .labelled_by(label.id);
+                            if response.changed() {
+                                ui.data_mut(|d| d.insert_temp(nick_id, raw.clone()));
+                            }
+                            if response.lost_focus() {
+                                let nickname = devices::normalize_nickname(&raw);
+                                if nickname != device.nickname {
+                                    device.nickname = nickname;
+                                    changed = true;
+                                }
+                                ui.data_mut(|d| d.remove::<String>(nick_id));
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Trust:");
+                            let prev_trust = device.trust;
+                            egui::ComboBox::from_id_salt(("device_trust", id))
+                                .selected_text(device.trust.label())
+                                .show_ui(ui, |ui| {
+                                    for state in TrustState::ALL {
+                                        ui.selectable_value(&mut device.trust, state, state.label());
+                                    }
+                                })
+                                .response
+                                .labelled_by(label.id);
+                            changed |= device.trust != prev_trust;
+
+                            changed |= ui
+                                .checkbox(&mut device.allow_auto_apply, "Auto-apply")
+                                .on_hover_text(
+                                    "When off, text from this device always waits in\n\
+                                     Notifications even if Auto apply is on.",
+                                )
+                                .changed();
+                            changed |= ui
+                                .checkbox(&mut device.notify, "System notifications")
+                                .changed();
+
+                            if ui
+                                .button("Forget")
+                                .on_hover_text(
+                                    "Remove this device's nickname, trust and settings.\n\
+                                     It reappears as Unknown the next time it is seen.",
+                                )
+                                .clicked()
+                            {
+                                forget = Some(id.clone());
+                            }
                         });
-                        ui.indent(format!("hist_{idx}"), |ui| {
-                            ui.label(egui::RichText::new(&entry.summary).weak());
+                        ui.horizontal(|ui| {
+                            let label = ui.label("Incoming files:");
+                            let prev_policy = device.file_policy;
+                            egui::ComboBox::from_id_salt(("device_file_policy", id))
+                                .selected_text(device.file_policy.label())
+                                .show_ui(ui, |ui| {
+                                    for policy in FilePolicy::ALL {
+                                        ui.selectable_value(
+                                            &mut device.file_policy,
+                                            policy,
+                                            policy.label(),
+                                        );
+                                    }
+                                })
+                                .response
+                                .labelled_by(label.id);
+                            changed |= device.file_policy != prev_policy;
                         });
-                    }
+                        if device.trust == TrustState::Blocked {
+                            ui.label(
+                                egui::RichText::new(
+                                    "Blocked: clipboard text and files from this device are discarded.",
+                                )
+                                .color(egui::Color32::from_rgb(200, 60, 60)),
+                            );
+                        }
+                    });
+                    ui.add_space(4.0);
                 }
             });
+
+            if let Some(id) = accept_key
+                && known_devices.accept_changed_key(&id)
+            {
+                changed = true;
+                *toast_message = Some(("New identity key accepted".to_string(), now_unix_ms()));
+            }
+            if let Some(id) = forget
+                && known_devices.forget(&id)
+            {
+                changed = true;
+                *toast_message = Some(("Device forgotten".to_string(), now_unix_ms()));
+            }
+            changed
         }
 
-        // ─── Notifications tab ─────────────────────────────────────────────────
+        // ─── Snippets tab ──────────────────────────────────────────────────────
 
-        fn render_notifications_tab(
+        /// Returns `true` when the library was modified and should be saved.
+        /// Sets `switch_to_send` after a snippet was inserted into the send box.
+        #[allow(clippy::too_many_arguments)]
+        fn render_snippets_tab(
             ui: &mut egui::Ui,
-            notifications: &mut Vec<Notification>,
-            peers: &[PeerInfo],
+            library: &mut SnippetLibrary,
+            load_error: Option<&str>,
+            send_text: &mut String,
+            connected: bool,
             runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
-            _history: &mut VecDeque<ActivityEntry>,
+            history: &mut VecDeque<ActivityEntry>,
+            history_retention: HistoryRetention,
             toast_message: &mut Option<(String, u64)>,
-        ) {
-            if notifications.is_empty() {
-                ui.centered_and_justified(|ui| {
-                    ui.label(egui::RichText::new("No pending notifications").weak());
-                });
-                return;
-            }
-
-            let total = notifications.len();
-            if total > 1 {
-                ui.label(egui::RichText::new(format!("{total} notifications pending")).strong());
+            switch_to_send: &mut bool,
+        ) -> bool {
+            let mut changed = false;
+            let mut delete: Option<usize> = None;
+
+            ui.heading("Snippets");
+            ui.label(
+                egui::RichText::new(
+                    "Saved texts you can insert into the send box, or send from the tray menu.",
+                )
+                .weak(),
+            );
+            if let Some(err) = load_error {
                 ui.add_space(8.0);
+                ui.colored_label(
+                    egui::Color32::from_rgb(200, 60, 60),
+                    format!("Could not read snippets.json: {err}"),
+                );
+                ui.label(
+                    egui::RichText::new("Editing is disabled so the file is not overwritten.")
+                        .weak(),
+                );
+                return false;
             }
+            ui.add_space(4.0);
+
+            ui.add_enabled_ui(snippets::encryption_supported(), |ui| {
+                changed |= ui
+                    .checkbox(&mut library.encrypt_at_rest, "Encrypt snippets on disk")
+                    .on_hover_text(
+                        "Seal snippets.json with Windows DPAPI so only your Windows\n\
+                         account on this PC can read it.",
+                    )
+                    .changed();
+            });
 
-            // Show the first notification.
-            let mut action: Option<NotificationAction> = None;
-
-            if let Some(notification) = notifications.first() {
-                match notification {
-                    Notification::Text {
-                        sender_device_id,
-                        preview,
-                        ..
-                    } => {
-                        let name = resolve_peer_name(peers, sender_device_id);
-                        ui.horizontal(|ui| {
-                            ui.strong("From:");
-                            ui.label(&name);
-                        });
-                        ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        library.snippets.len() < snippets::MAX_SNIPPETS,
+                        egui::Button::new("Add Snippet"),
+                    )
+                    .clicked()
+                {
+                    changed |= library.add("", "");
+                }
+                if ui
+                    .add_enabled(
+                        !send_text.trim().is_empty()
+                            && library.snippets.len() < snippets::MAX_SNIPPETS,
+                        egui::Button::new("Save Send Box as Snippet"),
+                    )
+                    .clicked()
+                {
+                    changed |= library.add("", send_text);
+                    *toast_message = Some(("Snippet saved".to_string(), now_unix_ms()));
+                }
+            });
+            ui.add_space(4.0);
 
-                        let available = ui.available_size();
-                        let preview_height = (available.y - 60.0).max(80.0);
-                        egui::ScrollArea::vertical()
-                            .max_height(preview_height)
-                            .show(ui, |ui| {
-                                ui.label(preview);
-                            });
+            if library.snippets.is_empty() {
+                ui.label(egui::RichText::new("No snippets yet.").weak());
+                return changed;
+            }
 
-                        ui.add_space(8.0);
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (index, snippet) in library.snippets.iter_mut().enumerate() {
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
                         ui.horizontal(|ui| {
-                            if ui.button("Apply to Clipboard").clicked() {
-                                action = Some(NotificationAction::Apply);
-                            }
-                            ui.add_space(4.0);
-                            if ui.button("Dismiss").clicked() {
-                                action = Some(NotificationAction::Dismiss);
+                            let label = ui.label("Name:");
+                            let response = ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut snippet.name)
+                                        .desired_width(220.0),
+                                )
+                                .labelled_by(label.id);
+                            if response.lost_focus() {
+                                snippet.name = snippets::normalize_snippet_name(&snippet.name)
+                                    .unwrap_or_else(|| format!("Snippet {}", index + 1));
+                                changed = true;
                             }
                         });
-                    }
-                    Notification::File {
-                        sender_device_id,
-                        preview,
-                        ..
-                    } => {
-                        let name = resolve_peer_name(peers, sender_device_id);
-                        ui.horizontal(|ui| {
-                            ui.strong("From:");
-                            ui.label(&name);
-                        });
-                        ui.add_space(8.0);
-
-                        let available = ui.available_size();
-                        let preview_height = (available.y - 60.0).max(80.0);
-                        egui::ScrollArea::vertical()
-                            .max_height(preview_height)
-                            .show(ui, |ui| {
-                                ui.label(preview);
-                            });
+                        let label = ui.label("Text:");
+                        let response = ui
+                            .add(
+                                egui::TextEdit::multiline(&mut snippet.text)
+                                    .desired_rows(2)
+                                    .desired_width(f32::INFINITY),
+                            )
+                            .labelled_by(label.id);
+                        // Save once editing finishes rather than per keystroke.
+                        changed |= response.lost_focus();
 
-                        ui.add_space(8.0);
                         ui.horizontal(|ui| {
-                            if ui.button("Save to Downloads").clicked() {
-                                action = Some(NotificationAction::Apply);
+                            let has_text = !snippet.text.is_empty();
+                            if ui
+                                .add_enabled(has_text, egui::Button::new("Insert"))
+                                .on_hover_text("Append to the text in the Send tab.")
+                                .clicked()
+                            {
+                                send_text.push_str(&snippet.text);
+                                *switch_to_send = true;
+                            }
+                            if ui
+                                .add_enabled(
+                                    has_text
+                                        && connected
+                                        && snippet.text.len() <= MAX_CLIPBOARD_TEXT_BYTES,
+                                    egui::Button::new("Send"),
+                                )
+                                .clicked()
+                            {
+                                send_snippet(snippet, runtime_cmd_tx, history, history_retention);
+                                *toast_message = Some((
+                                    format!("Sent snippet \"{}\"", snippet.name),
+                                    now_unix_ms(),
+                                ));
                             }
                             ui.add_space(4.0);
-                            if ui.button("Dismiss").clicked() {
-                                action = Some(NotificationAction::Dismiss);
+                            if ui.button("Delete").clicked() {
+                                delete = Some(index);
                             }
                         });
-                    }
+                    });
+                    ui.add_space(4.0);
                 }
-            }
+            });
 
-            match action {
-                Some(NotificationAction::Apply) => {
-                    if !notifications.is_empty() {
-                        let n = notifications.remove(0);
-                        match n {
-                            Notification::Text {
-                                sender_device_id,
-                                full_text,
-                                content_hash,
-                                ..
-                            } => {
-                                if let Err(err) = apply_clipboard_text(&full_text) {
-                                    warn!("apply failed: {err}");
-                                    *toast_message = Some((
-                                        "Failed to apply clipboard text".to_string(),
-                                        now_unix_ms(),
-                                    ));
-                                } else {
-                                    let _ = runtime_cmd_tx
-                                        .send(RuntimeCommand::MarkApplied(content_hash));
-                                    let name = resolve_peer_name(peers, &sender_device_id);
-                                    *toast_message = Some((
-                                        format!("Clipboard applied from {name}"),
-                                        now_unix_ms(),
-                                    ));
-                                }
-                            }
-                            Notification::File {
-                                sender_device_id,
-                                file_name,
-                                temp_path,
-                                ..
-                            } => match save_temp_file_to_downloads(&temp_path, &file_name) {
-                                Ok(dest) => {
-                                    let _ = std::fs::remove_file(&temp_path);
-                                    let name = resolve_peer_name(peers, &sender_device_id);
-                                    *toast_message = Some((
-                                        format!("Saved file from {name} to {}", dest.display()),
-                                        now_unix_ms(),
-                                    ));
-                                }
-                                Err(err) => {
-                                    warn!("save file failed: {err}");
-                                    *toast_message = Some((
-                                        "Failed to save received file".to_string(),
-                                        now_unix_ms(),
-                                    ));
-                                }
-                            },
-                        }
-                    }
-                }
-                Some(NotificationAction::Dismiss) => {
-                    if !notifications.is_empty() {
-                        let n = notifications.remove(0);
-                        if let Notification::File { temp_path, .. } = n {
-                            let _ = std::fs::remove_file(&temp_path);
-                        }
-                    }
-                }
-                None => {}
+            if let Some(index) = delete {
+                library.snippets.remove(index);
+                changed = true;
             }
+            changed
         }
     }
 
@@ -1908,11 +8725,273 @@ mod windows_client {
 
     enum SetupAction {
         Connect,
+        TestConnection,
         Cancel,
     }
 
+    /// What to do once the user has unlocked (see [`auth_gate`]).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum AfterUnlock {
+        /// Only show the locked history or snippets.
+        Show,
+        CopyJoinLink,
+        CopyInvite,
+        /// Tray "Copy Invite": the window is usually hidden, so the result
+        /// is a system notification.
+        TrayCopyInvite,
+        /// Tray "Send Snippet": send the snippet at this index.
+        TraySnippet(usize),
+        /// Turn the lock on, now that it is known to work on this PC.
+        EnableLock,
+        /// Turn the lock off.  Always asks, even while unlocked, so that
+        /// nobody passing by can switch it off.
+        DisableLock,
+    }
+
+    struct PendingUnlock {
+        answer: std::sync::mpsc::Receiver<AuthOutcome>,
+        then: AfterUnlock,
+    }
+
+    /// Leaving the running session, requested from Options, the device
+    /// alert banner or the tray menu.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum RoomAction {
+        /// Edit the room details and join; cancelling rejoins the old room.
+        Change,
+        /// Disconnect and return to the room-selection screen.
+        Leave,
+        /// Join the room of the saved profile at this index.
+        SwitchProfile(usize),
+        /// Restart the connection to the current room (tray "Retry Now").
+        Reconnect,
+        /// Skip the rest of the wait before the next reconnect attempt
+        /// (tray "Reconnect Now").
+        ReconnectNow,
+        /// Copy an invite to the current room (tray "Copy Invite").
+        CopyInvite,
+    }
+
+    impl RoomAction {
+        /// Whether the action ends on a screen, so the window must be shown.
+        fn needs_window(self) -> bool {
+            !matches!(
+                self,
+                RoomAction::SwitchProfile(_)
+                    | RoomAction::Reconnect
+                    | RoomAction::ReconnectNow
+                    | RoomAction::CopyInvite
+            )
+        }
+    }
+
+    /// Setup-screen "Test Connection" run for the details it was started
+    /// with; the result is hidden once any of them is edited.
+    struct ConnectionTest {
+        server_url: String,
+        room_code: String,
+        device_name: String,
+        /// `None` while the probe runs; then the outcome message.
+        result: Arc<Mutex<Option<Result<String, String>>>>,
+    }
+
+    impl ConnectionTest {
+        fn matches(&self, server_url: &str, room_code: &str, device_name: &str) -> bool {
+            self.server_url == server_url
+                && self.room_code == room_code
+                && self.device_name == device_name
+        }
+    }
+
+    /// A link from a trusted device, shown in the "Open Link?" window.
+    struct LinkPrompt {
+        url: String,
+        sender_name: String,
+    }
+
+    /// Received text too large to auto-apply without asking, shown in the
+    /// "Apply Large Text?" window.
+    struct LargeApplyPrompt {
+        sender_name: String,
+        text: String,
+        content_hash: [u8; 32],
+        label: Option<String>,
+    }
+
+    /// Where text held by the secret check came from.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum HeldSend {
+        SendBox,
+        /// The send box, as numbered parts.
+        SendBoxSplit,
+        /// A text file sent as text, leaving the send box alone.
+        TextFile,
+        QuickPick,
+    }
+
+    /// Text held back by the secret check until the user confirms it.
+    struct SecretPrompt {
+        /// Already transformed for sending.
+        send: OutgoingText,
+        origin: HeldSend,
+        kinds: Vec<SecretKind>,
+    }
+
+    /// Why `text` may not be sent without asking, for sends that cannot
+    /// ask (the automation API and `--send-text`).
+    fn secret_refusal(policy: SecretPolicy, text: &str) -> Option<String> {
+        match secrets::check(policy, text) {
+            SecretCheck::Allow => None,
+            SecretCheck::Confirm(kinds) | SecretCheck::Block(kinds) => Some(format!(
+                "not sent: the text looks like a secret ({}). Send it from the ClipRelay \
+                 window to confirm, or change Options → General → Text that looks like a secret",
+                secrets::describe(&kinds)
+            )),
+        }
+    }
+
+    /// Apply the secret check to `send` from `origin`.  Returns the send
+    /// when it can be made straight away; otherwise it is held in `prompt`
+    /// or refused with a toast.
+    fn screen_outgoing_text(
+        policy: SecretPolicy,
+        send: OutgoingText,
+        origin: HeldSend,
+        prompt: &mut Option<SecretPrompt>,
+        toast_message: &mut Option<(String, u64)>,
+    ) -> Option<OutgoingText> {
+        match secrets::check(policy, &send.text) {
+            SecretCheck::Allow => Some(send),
+            SecretCheck::Confirm(kinds) => {
+                info!(kinds = %secrets::describe(&kinds), "send held for confirmation");
+                *prompt = Some(SecretPrompt {
+                    send,
+                    origin,
+                    kinds,
+                });
+                None
+            }
+            SecretCheck::Block(kinds) => {
+                info!(kinds = %secrets::describe(&kinds), "send blocked by the secret check");
+                *toast_message = Some((
+                    format!(
+                        "Not sent: looks like a secret ({})",
+                        secrets::describe(&kinds)
+                    ),
+                    now_unix_ms(),
+                ));
+                None
+            }
+        }
+    }
+
+    /// Send the send box's text as a `.txt` file transfer and clear it.
+    fn queue_text_as_file(
+        send_text: &mut String,
+        history: &mut VecDeque<ActivityEntry>,
+        history_retention: HistoryRetention,
+        runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+        toast_message: &mut Option<(String, u64)>,
+    ) {
+        match write_outgoing_text_file(send_text) {
+            Ok(path) => {
+                let id = push_sent_history(
+                    history,
+                    history_retention,
+                    "file",
+                    format!("{}", path.display()),
+                    None,
+                );
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SendFile(id, path));
+                send_text.clear();
+                *toast_message = Some(("Queued text as a file".to_string(), now_unix_ms()));
+            }
+            Err(err) => {
+                warn!("write outgoing text file failed: {err}");
+                *toast_message = Some(("Failed to prepare text file".to_string(), now_unix_ms()));
+            }
+        }
+    }
+
+    /// Send transformed send-box text to the room, whole or as numbered
+    /// parts (each carrying the send's options).
+    fn queue_send_box_text(
+        send: OutgoingText,
+        split: bool,
+        history: &mut VecDeque<ActivityEntry>,
+        history_retention: HistoryRetention,
+        clip_ring: &mut ClipRing,
+        runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+        toast_message: &mut Option<(String, u64)>,
+    ) {
+        if split {
+            let parts = text_split::split_numbered(&send.text, MAX_CLIPBOARD_TEXT_BYTES);
+            let id = push_sent_text_history(
+                history,
+                history_retention,
+                &send,
+                format!(
+                    "{} (split into {} parts)",
+                    preview_text(&send.text, 100),
+                    parts.len()
+                ),
+            );
+            let count = parts.len();
+            for part in parts {
+                let part = OutgoingText {
+                    text: part,
+                    ..send.clone()
+                };
+                let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(id, part));
+            }
+            *toast_message = Some((format!("Sent as {count} messages"), now_unix_ms()));
+            return;
+        }
+        let id = push_sent_text_history(
+            history,
+            history_retention,
+            &send,
+            preview_text(&send.text, 120),
+        );
+        clip_ring.push(RingItem {
+            ts_unix_ms: now_unix_ms(),
+            source: RingSource::Sent,
+            peer_device_id: "room".to_owned(),
+            text: send.text.clone(),
+            label: send.label.clone(),
+        });
+        let _ = runtime_cmd_tx.send(RuntimeCommand::SendText(id, send));
+        *toast_message = Some(("Sent to connected devices".to_string(), now_unix_ms()));
+    }
+
+    /// State of the "Logs" window.
+    struct LogViewer {
+        tail: LogTail,
+        max_level: LogLevel,
+        query: String,
+        /// Keep the view scrolled to the newest line.
+        follow: bool,
+        last_refresh_ms: u64,
+        error: Option<String>,
+    }
+
+    impl Default for LogViewer {
+        fn default() -> Self {
+            Self {
+                tail: LogTail::default(),
+                max_level: LogLevel::Info,
+                query: String::new(),
+                follow: true,
+                last_refresh_ms: 0,
+                error: None,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     enum NotificationAction {
         Apply,
+        ApplyAndPaste,
         Dismiss,
     }
 
@@ -1924,6 +9003,27 @@ mod windows_client {
             if self.egui_ctx.is_none() {
                 self.egui_ctx = Some(ctx.clone());
             }
+            self.track_window_placement();
+            // The window level survives hiding, so it is only sent on the
+            // first frame and when the preference changes.
+            if let Ok(mut placement) = POPUP_PLACEMENT.lock() {
+                *placement = self.ui_state.popup_placement;
+            }
+            // Zoom rescales fonts and spacing alike in every viewport.
+            if self.applied_text_size != Some(self.ui_state.text_size) {
+                self.applied_text_size = Some(self.ui_state.text_size);
+                ctx.set_zoom_factor(self.ui_state.text_size.scale());
+            }
+            if self.applied_always_on_top != Some(self.ui_state.always_on_top) {
+                self.applied_always_on_top = Some(self.ui_state.always_on_top);
+                ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(
+                    if self.ui_state.always_on_top {
+                        egui::WindowLevel::AlwaysOnTop
+                    } else {
+                        egui::WindowLevel::Normal
+                    },
+                ));
+            }
 
             // Take the current phase to avoid borrow issues.
             let phase =
@@ -1960,30 +9060,46 @@ mod windows_client {
                     // ── Handle pending phase transitions ───────────────────────
                     // After render_running returns all AppPhase::Running borrows
                     // are released, so self.phase can be freely reassigned.
-                    if self.pending_change_room {
-                        self.pending_change_room = false;
+                    if let Some(action) = self.pending_room_action.take() {
                         // Clean up hotkey registration before leaving Running.
-                        if let (Some(old_hk), Some(mgr)) =
-                            (self.hotkey_current.take(), &self.hotkey_manager)
-                        {
-                            let _ = mgr.unregister(old_hk);
-                        }
-                        self.hotkey_manager = None;
+                        self.unregister_hotkeys();
                         // Dropping AppPhase::Running here also drops the
-                        // tokio Runtime, which cancels all background tasks.
+                        // tokio Runtime, which cancels all background tasks
+                        // and discards the room key, peers and replay state;
+                        // the next session starts over with a fresh Hello.
                         let saved_config = load_saved_config().ok().flatten();
-                        info!("change-room requested — returning to ChooseRoom");
-                        self.phase = AppPhase::ChooseRoom { saved_config };
+                        match (action, saved_config) {
+                            (RoomAction::Change, Some(cfg)) => {
+                                info!("change-room requested — opening room setup");
+                                let (room_code, server_url) = match self.pending_join_link.take() {
+                                    Some(link) => (link.room_code, link.server_url),
+                                    None => (cfg.room_code.clone(), cfg.server_url.clone()),
+                                };
+                                self.phase = AppPhase::Setup {
+                                    room_code,
+                                    server_url,
+                                    device_name: cfg.device_name.clone(),
+                                    error_message: None,
+                                };
+                                self.resume_config = Some(cfg);
+                            }
+                            (RoomAction::SwitchProfile(index), saved_config) => {
+                                self.switch_to_profile(index, saved_config, ctx);
+                            }
+                            (_, saved_config) => {
+                                info!("leave-room requested — returning to ChooseRoom");
+                                self.phase = AppPhase::ChooseRoom { saved_config };
+                            }
+                        }
+                    } else if let Some(cfg) = self.pending_config_change.take() {
+                        self.unregister_hotkeys();
+                        // Dropping AppPhase::Running here cancels the old runtime.
+                        info!("connection settings changed — restarting runtime");
+                        self.start_running(cfg, ctx);
                     } else if self.pending_reconnect {
                         self.pending_reconnect = false;
-                        // Unregister current hotkey; start_running will re-register.
-                        if let (Some(old_hk), Some(mgr)) =
-                            (self.hotkey_current.take(), &self.hotkey_manager)
-                        {
-                            let _ = mgr.unregister(old_hk);
-                        }
-                        self.hotkey_manager = None;
-                        self.hotkey_current = None;
+                        // Unregister current hotkeys; start_running will re-register.
+                        self.unregister_hotkeys();
                         // Dropping AppPhase::Running here cancels the old runtime.
                         match load_saved_config() {
                             Ok(Some(cfg)) => {
@@ -2000,29 +9116,246 @@ mod windows_client {
         }
     }
 
-    // ─── Helpers ───────────────────────────────────────────────────────────────
+    // ─── Helpers ───────────────────────────────────────────────────────────────
+
+    /// Queue `n` within `limit`: pinned entries after the other pinned
+    /// ones at the top, the rest at the end.  Items that do not fit are
+    /// recorded in `history` or counted in `coalesced`.
+    fn push_notification(
+        notifications: &mut Vec<PendingNotification>,
+        coalesced: &mut usize,
+        limit: NotificationLimit,
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        n: Notification,
+        pinned: bool,
+    ) {
+        match limit.admit(notifications.len()) {
+            Admission::Queue => {}
+            Admission::QueueAfterEvicting => {
+                // Evict the oldest unpinned notifications (more than one if
+                // the limit was lowered).
+                while notifications.len() >= limit.normalized().max_items {
+                    let oldest = notifications.iter().position(|n| !n.pinned).unwrap_or(0);
+                    let evicted = notifications.remove(oldest).notification;
+                    record_dropped_notification(
+                        history,
+                        retention,
+                        &evicted,
+                        "Removed from the full Notifications list",
+                    );
+                    discard_notification(evicted);
+                }
+            }
+            Admission::Drop => {
+                record_dropped_notification(
+                    history,
+                    retention,
+                    &n,
+                    "Not added to the full Notifications list",
+                );
+                discard_notification(n);
+                return;
+            }
+            Admission::Coalesce => {
+                *coalesced += 1;
+                discard_notification(n);
+                return;
+            }
+        }
+        let at = if pinned {
+            notifications.iter().take_while(|n| n.pinned).count()
+        } else {
+            notifications.len()
+        };
+        notifications.insert(
+            at,
+            PendingNotification {
+                notification: n,
+                selected: false,
+                seen: false,
+                shown_unix_ms: None,
+                keep_open: false,
+                pinned,
+            },
+        );
+    }
+
+    /// Add a history entry for a notification that left the list unseen.
+    fn record_dropped_notification(
+        history: &mut VecDeque<ActivityEntry>,
+        retention: HistoryRetention,
+        n: &Notification,
+        reason: &str,
+    ) {
+        let (sender_device_id, what) = match n {
+            Notification::Text {
+                sender_device_id,
+                full_text,
+                label,
+                ..
+            } => (
+                sender_device_id,
+                labelled(label.as_deref(), &preview_text(full_text, 100)),
+            ),
+            Notification::File {
+                sender_device_id,
+                file_name,
+                ..
+            } => (sender_device_id, format!("{file_name} (not saved)")),
+        };
+        push_history(
+            history,
+            retention,
+            ActivityEntry {
+                ts_unix_ms: now_unix_ms(),
+                direction: ActivityDirection::Received,
+                peer_device_id: sender_device_id.clone(),
+                kind: "dropped".to_owned(),
+                summary: format!("{reason}: {what}"),
+                link: None,
+                delivery: None,
+                send_id: None,
+                pinned: false,
+                favorite: false,
+                repeat_count: 1,
+                content_hash: None,
+                checksum: None,
+                selected: false,
+            },
+        );
+    }
 
-    fn push_notification(notifications: &mut Vec<Notification>, n: Notification) {
-        if notifications.len() >= MAX_NOTIFICATIONS {
-            // Evict the oldest notification.  If it is a File notification,
-            // delete its temp file now — otherwise it leaks on disk until the
-            // next app restart.
-            let evicted = notifications.remove(0);
-            if let Notification::File { temp_path, .. } = evicted {
-                let _ = std::fs::remove_file(&temp_path);
-            }
+    /// Drop a notification that will not be shown.  A File notification's
+    /// temp file is deleted now — otherwise it leaks on disk until the next
+    /// app restart.
+    fn discard_notification(n: Notification) {
+        if let Notification::File { temp_path, .. } = n {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+    }
+
+    /// Synthesize Ctrl+V into the foreground application after a short
+    /// delay, off the UI thread so the hide/focus change can complete.
+    fn schedule_auto_paste(excluded: Vec<String>) {
+        std::thread::Builder::new()
+            .name("auto-paste".into())
+            .spawn(move || {
+                std::thread::sleep(Duration::from_millis(autopaste::FOCUS_SETTLE_MS));
+                match autopaste::paste_into_foreground(&excluded) {
+                    autopaste::PasteOutcome::Sent => debug!("auto-paste sent"),
+                    autopaste::PasteOutcome::Excluded(exe) => {
+                        info!(exe = %exe, "auto-paste skipped: excluded application")
+                    }
+                    other => warn!(outcome = ?other, "auto-paste not sent"),
+                }
+            })
+            .ok();
+    }
+
+    /// Edit a list-valued preference as a single comma-separated line.
+    ///
+    /// The in-progress text lives in egui temp memory so that separators
+    /// typed mid-edit are not normalized away; the list is only re-parsed
+    /// when the field loses focus.
+    fn edit_list_setting(
+        ui: &mut egui::Ui,
+        id_salt: &str,
+        hint: &str,
+        list: &mut Vec<String>,
+        parse: fn(&str) -> Vec<String>,
+    ) {
+        let id = ui.make_persistent_id(id_salt);
+        let mut raw = ui
+            .data_mut(|d| d.get_temp::<String>(id))
+            .unwrap_or_else(|| list.join(", "));
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut raw)
+                .hint_text(hint)
+                .desired_width(f32::INFINITY),
+        );
+        if response.changed() {
+            ui.data_mut(|d| d.insert_temp(id, raw.clone()));
+        }
+        if response.lost_focus() {
+            *list = parse(&raw);
+            ui.data_mut(|d| d.remove::<String>(id));
         }
-        notifications.push(n);
     }
 
-    fn resolve_peer_name(peers: &[PeerInfo], device_id: &str) -> String {
+    /// Local nickname if one is set, else the name the peer advertises,
+    /// else the raw device ID.
+    fn resolve_peer_name(devices: &DeviceRegistry, peers: &[PeerInfo], device_id: &str) -> String {
+        if let Some(name) = devices.get(device_id).and_then(|d| d.nickname.clone()) {
+            return name;
+        }
         peers
             .iter()
             .find(|p| p.device_id == device_id)
             .map(|p| p.device_name.clone())
+            .or_else(|| devices.display_name(device_id).map(str::to_owned))
             .unwrap_or_else(|| device_id.to_string())
     }
 
+    /// Check the identity key an event was signed with against the sender's
+    /// pinned key and warn loudly on a change.  Returns whether automatic
+    /// actions may run for the event.
+    fn check_sender_key(
+        devices: &mut DeviceRegistry,
+        peers: &[PeerInfo],
+        sender_device_id: &str,
+        signer_key: Option<&str>,
+    ) -> bool {
+        let check = devices.check_key(sender_device_id, signer_key);
+        match check {
+            KeyCheck::Verified | KeyCheck::Unverified => {}
+            KeyCheck::Pinned => {
+                info!(sender = %sender_device_id, "pinned identity key on first use");
+                save_devices_logged(devices);
+            }
+            KeyCheck::Changed => {
+                let name = resolve_peer_name(devices, peers, sender_device_id);
+                warn!(sender = %sender_device_id, "identity key changed; automatic actions disabled");
+                save_devices_logged(devices);
+                // Shown even during quiet hours: this may be impersonation.
+                show_system_notification(
+                    "Device identity changed",
+                    &format!(
+                        "{name} is using a different identity key than before. If it was \
+                         not reinstalled, someone may be impersonating it. Review it in \
+                         the Devices tab."
+                    ),
+                );
+            }
+        }
+        check.is_verified()
+    }
+
+    fn save_devices_logged(registry: &DeviceRegistry) {
+        if let Err(err) = devices::save_devices(registry) {
+            warn!("failed to save devices: {err}");
+        }
+    }
+
+    /// One line for a device in the Devices tab: texts and files each way.
+    fn peer_traffic(usage: &PeerUsage) -> String {
+        let part =
+            |messages: u64, bytes: u64| format!("{messages} ({})", transfers::format_bytes(bytes));
+        format!(
+            "Traffic: text {} sent, {} received | files {} sent, {} received",
+            part(usage.text.sent_messages, usage.text.sent_bytes),
+            part(usage.text.received_messages, usage.text.received_bytes),
+            part(usage.files.sent_messages, usage.files.sent_bytes),
+            part(usage.files.received_messages, usage.files.received_bytes),
+        )
+    }
+
+    fn save_usage_stats_logged(stats: &UsageStats) {
+        if let Err(err) = usage_stats::save_usage_stats(stats) {
+            warn!("failed to save usage statistics: {err}");
+        }
+    }
+
     /// Map the raw connection status string to a tray traffic-light colour.
     ///
     /// * **Green** -- WebSocket is connected AND the room key has been
@@ -2032,6 +9365,15 @@ mod windows_client {
     /// * **Amber** -- Transitional states: starting, connecting, reconnecting,
     ///   or connected-but-key-not-yet-ready (waiting for a second peer).
     /// * **Red** -- An error has occurred and the app cannot reach the server.
+    /// The tray icon's colour for `status`, for status dots in the window.
+    fn tray_status_color(status: TrayStatus) -> egui::Color32 {
+        match status {
+            TrayStatus::Green => egui::Color32::from_rgb(0, 180, 0),
+            TrayStatus::Amber => egui::Color32::from_rgb(255, 180, 0),
+            TrayStatus::Red => egui::Color32::from_rgb(220, 30, 30),
+        }
+    }
+
     fn compute_tray_status(connection_status: &str, room_key_ready: bool) -> TrayStatus {
         if connection_status.starts_with("Error") {
             return TrayStatus::Red;
@@ -2044,6 +9386,77 @@ mod windows_client {
         TrayStatus::Amber
     }
 
+    /// (Re-)register the quick-pick hotkey for `label` (default
+    /// [`DEFAULT_QUICK_PICK_HOTKEY_LABEL`]) and publish its ID to the OS
+    /// callback.  Returns a user-facing error when registration fails.
+    fn register_quick_pick_hotkey(
+        manager: Option<&GlobalHotKeyManager>,
+        current: &mut Option<HotKey>,
+        id: &AtomicU32,
+        label: Option<&str>,
+    ) -> Result<(), String> {
+        if let (Some(old), Some(mgr)) = (current.take(), manager) {
+            let _ = mgr.unregister(old);
+        }
+        id.store(0, Ordering::SeqCst);
+
+        let label = label.unwrap_or(DEFAULT_QUICK_PICK_HOTKEY_LABEL);
+        let (Some(mgr), Some(hk)) = (manager, parse_hotkey_label(label)) else {
+            return Ok(());
+        };
+        match mgr.register(hk) {
+            Ok(()) => {
+                info!(hotkey = %label, "quick-pick hotkey registered");
+                id.store(hk.id(), Ordering::SeqCst);
+                *current = Some(hk);
+                Ok(())
+            }
+            Err(err) => {
+                warn!(hotkey = %label, "quick-pick hotkey register failed: {err}");
+                Err(format!(
+                    "Quick-pick hotkey '{label}' registration failed \
+                     (may conflict with another app): {err}"
+                ))
+            }
+        }
+    }
+
+    /// (Re-)register the panic hotkey for `label` (`None` means disabled)
+    /// and publish its ID to the OS callback.
+    fn register_panic_hotkey(
+        manager: Option<&GlobalHotKeyManager>,
+        current: &mut Option<HotKey>,
+        id: &AtomicU32,
+        label: Option<&str>,
+    ) -> Result<(), String> {
+        if let (Some(old), Some(mgr)) = (current.take(), manager) {
+            let _ = mgr.unregister(old);
+        }
+        id.store(0, Ordering::SeqCst);
+
+        let (Some(mgr), Some(label)) = (manager, label) else {
+            return Ok(());
+        };
+        let Some(hk) = parse_hotkey_label(label) else {
+            return Ok(());
+        };
+        match mgr.register(hk) {
+            Ok(()) => {
+                info!(hotkey = %label, "panic hotkey registered");
+                id.store(hk.id(), Ordering::SeqCst);
+                *current = Some(hk);
+                Ok(())
+            }
+            Err(err) => {
+                warn!(hotkey = %label, "panic hotkey register failed: {err}");
+                Err(format!(
+                    "Panic hotkey '{label}' registration failed \
+                     (may conflict with another app): {err}"
+                ))
+            }
+        }
+    }
+
     /// Convert a human-readable hotkey label into a [`HotKey`] value.
     ///
     /// Returns `None` for `"Disabled"` or any unrecognised string, which
@@ -2066,6 +9479,22 @@ mod windows_client {
                 Some(Modifiers::CONTROL | Modifiers::SHIFT),
                 Code::KeyV,
             )),
+            "Ctrl+Alt+H" => Some(HotKey::new(
+                Some(Modifiers::CONTROL | Modifiers::ALT),
+                Code::KeyH,
+            )),
+            "Ctrl+Shift+H" => Some(HotKey::new(
+                Some(Modifiers::CONTROL | Modifiers::SHIFT),
+                Code::KeyH,
+            )),
+            "Ctrl+Alt+X" => Some(HotKey::new(
+                Some(Modifiers::CONTROL | Modifiers::ALT),
+                Code::KeyX,
+            )),
+            "Ctrl+Shift+X" => Some(HotKey::new(
+                Some(Modifiers::CONTROL | Modifiers::SHIFT),
+                Code::KeyX,
+            )),
             _ => None, // "Disabled" or unknown
         }
     }
@@ -2081,16 +9510,79 @@ mod windows_client {
         }
     }
 
-    fn windows_autostart_is_enabled() -> bool {
+    fn windows_autostart_is_enabled(backend: AutostartBackend) -> bool {
         let Ok(exe) = std::env::current_exe() else {
             return false;
         };
-        autostart::is_enabled(&exe, "ClipRelay").unwrap_or(false)
+        autostart::is_enabled_with(backend, &exe, "ClipRelay").unwrap_or(false)
+    }
+
+    fn windows_set_autostart_enabled(
+        backend: AutostartBackend,
+        enabled: bool,
+    ) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        autostart::set_enabled_with(backend, &exe, "ClipRelay", enabled).map_err(|e| e.to_string())
+    }
+
+    fn windows_explorer_menu_is_enabled() -> bool {
+        std::env::current_exe().is_ok_and(|exe| shell_menu::is_registered(&exe))
     }
 
-    fn windows_set_autostart_enabled(enabled: bool) -> Result<(), String> {
+    fn windows_set_explorer_menu_enabled(enabled: bool) -> Result<(), String> {
         let exe = std::env::current_exe().map_err(|e| e.to_string())?;
-        autostart::set_enabled(&exe, "ClipRelay", enabled).map_err(|e| e.to_string())
+        shell_menu::set_registered(&exe, enabled).map_err(|e| e.to_string())
+    }
+
+    fn windows_set_send_to_enabled(enabled: bool) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        shell_menu::set_send_to(&exe, enabled).map_err(|e| e.to_string())
+    }
+
+    fn windows_url_handler_is_enabled() -> bool {
+        std::env::current_exe().is_ok_and(|exe| shell_menu::is_url_handler_registered(&exe))
+    }
+
+    fn windows_set_url_handler_enabled(enabled: bool) -> Result<(), String> {
+        let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+        shell_menu::set_url_handler(&exe, enabled).map_err(|e| e.to_string())
+    }
+
+    fn play_notification_sound(source: &SoundSource) {
+        if let Err(err) = sound::play(source) {
+            warn!("notification sound failed: {err}");
+        }
+    }
+
+    /// Current hour of the day in local time (0–23).
+    fn local_hour() -> u8 {
+        use windows_sys::Win32::System::SystemInformation::GetLocalTime;
+
+        let mut st = unsafe { std::mem::zeroed() };
+        unsafe { GetLocalTime(&mut st) };
+        st.wHour as u8
+    }
+
+    /// The local time as `YYYYMMDD-HHMMSS`, for generated file names.
+    fn local_file_stamp() -> String {
+        use windows_sys::Win32::System::SystemInformation::GetLocalTime;
+
+        let mut st = unsafe { std::mem::zeroed() };
+        unsafe { GetLocalTime(&mut st) };
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            st.wYear, st.wMonth, st.wDay, st.wHour, st.wMinute, st.wSecond
+        )
+    }
+
+    /// Today's local date as `YYYY-MM-DD`, the key of the per-day usage
+    /// statistics.
+    fn local_date_key() -> String {
+        use windows_sys::Win32::System::SystemInformation::GetLocalTime;
+
+        let mut st = unsafe { std::mem::zeroed() };
+        unsafe { GetLocalTime(&mut st) };
+        format!("{:04}-{:02}-{:02}", st.wYear, st.wMonth, st.wDay)
     }
 
     fn show_system_notification(title: &str, body: &str) {
@@ -2126,19 +9618,18 @@ mod windows_client {
 
     fn client_config_path() -> PathBuf {
         if let Some(override_dir) = std::env::var_os("CLIPRELAY_CONFIG_DIR") {
-            let dir = PathBuf::from(override_dir);
+            let dir = config_profiles::profile_dir(
+                Path::new(&override_dir),
+                config_profiles::active().as_deref(),
+            );
             let _ = std::fs::create_dir_all(&dir);
             return dir.join("config.json");
         }
-        let base = std::env::var_os("LOCALAPPDATA")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let dir = base.join("ClipRelay");
-        let _ = std::fs::create_dir_all(&dir);
-        dir.join("config.json")
+        config_profiles::active_dir().join("config.json")
     }
 
-    fn load_saved_config() -> Result<Option<SavedClientConfig>, String> {
+    /// Read the config file as stored, without unsealing the room code.
+    fn read_stored_config() -> Result<Option<StoredClientConfig>, String> {
         /// Defensive upper bound: the config JSON is tiny; reject anything that
         /// cannot plausibly be a valid config file to guard against OOM if the
         /// file on disk is corrupted or replaced with a huge decoy.
@@ -2160,12 +9651,57 @@ mod windows_client {
         }
         let data = std::fs::read_to_string(&path)
             .map_err(|err| format!("failed to read config {}: {err}", path.display()))?;
-        let cfg: SavedClientConfig = serde_json::from_str(&data)
-            .map_err(|err| format!("failed to parse config {}: {err}", path.display()))?;
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|err| format!("failed to parse config {}: {err}", path.display()))
+    }
+
+    fn load_saved_config() -> Result<Option<SavedClientConfig>, String> {
+        let Some(mut stored) = read_stored_config()? else {
+            return Ok(None);
+        };
+        let path = client_config_path();
+        let migrate = stored.room_code_protected.is_none() && dpapi::is_supported();
+        let room_code = match &stored.room_code_protected {
+            Some(sealed) => dpapi::unprotect_from_base64(sealed)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| String::from_utf8(bytes).map_err(|err| err.to_string()))
+                .map_err(|err| {
+                    format!(
+                        "failed to unseal the room code in {} (was it copied from \
+                         another Windows account or PC?): {err}",
+                        path.display()
+                    )
+                })?,
+            None => stored.room_code.clone(),
+        };
+        let cfg = SavedClientConfig {
+            server_url: stored.server_url.clone(),
+            room_code,
+            device_name: stored.device_name.clone(),
+        };
         validate_saved_config(&cfg)?;
+        // Configs from before per-room counters hold one counter, for the
+        // room they name.
+        if stored
+            .counters
+            .migrate_legacy(&room_id_from_code(&cfg.room_code))
+        {
+            match write_stored_config(&stored) {
+                Ok(()) => info!("moved the send counter into the per-room map"),
+                Err(err) => warn!("failed to migrate the send counter: {err}"),
+            }
+        }
+        if migrate {
+            match save_saved_config(&cfg) {
+                Ok(()) => info!("sealed plaintext room code in {}", path.display()),
+                Err(err) => warn!("failed to seal plaintext room code: {err}"),
+            }
+        }
         Ok(Some(cfg))
     }
 
+    /// Save `cfg`, keeping the send counters already on disk.
     fn save_saved_config(cfg: &SavedClientConfig) -> Result<(), String> {
         // Trim whitespace from all string fields before persisting so that a
         // room code entered as "  my-room  " on one device and "my-room" on
@@ -2174,14 +9710,48 @@ mod windows_client {
             server_url: cfg.server_url.trim().to_owned(),
             room_code: cfg.room_code.trim().to_owned(),
             device_name: cfg.device_name.trim().to_owned(),
-            last_counter: cfg.last_counter,
         };
         validate_saved_config(&cfg)?;
+        // Fall back to plaintext (as older versions stored it) rather than
+        // losing the config when sealing is unavailable.
+        let room_code_protected = if dpapi::is_supported() {
+            match dpapi::protect_to_base64(cfg.room_code.as_bytes()) {
+                Ok(sealed) => Some(sealed),
+                Err(err) => {
+                    warn!("failed to seal room code; storing it in plaintext: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let counters = match read_stored_config() {
+            Ok(existing) => existing.map(|stored| stored.counters).unwrap_or_default(),
+            Err(err) => {
+                warn!("send counters not carried over: {err}");
+                SendCounters::default()
+            }
+        };
+        let stored = StoredClientConfig {
+            server_url: cfg.server_url,
+            room_code: if room_code_protected.is_some() {
+                String::new()
+            } else {
+                cfg.room_code
+            },
+            room_code_protected,
+            device_name: cfg.device_name,
+            counters,
+        };
+        write_stored_config(&stored)
+    }
+
+    fn write_stored_config(stored: &StoredClientConfig) -> Result<(), String> {
         const MAX_ATTEMPTS: u32 = 3;
         const BACKOFF_BASE_MS: u64 = 50;
         let path = client_config_path();
         let tmp_path = path.with_extension("json.tmp");
-        let payload = serde_json::to_string_pretty(&cfg).map_err(|err| err.to_string())?;
+        let payload = serde_json::to_string_pretty(stored).map_err(|err| err.to_string())?;
 
         for attempt in 1..=MAX_ATTEMPTS {
             let result: Result<(), String> = (|| {
@@ -2261,14 +9831,34 @@ mod windows_client {
         }
     }
 
+    /// Counter to continue from in `room_id` (see [`SendCounters`]).
+    fn load_send_counter(room_id: &str) -> u64 {
+        match read_stored_config() {
+            Ok(stored) => stored.map_or(0, |stored| stored.counters.counter_for(room_id)),
+            Err(err) => {
+                warn!("failed to read the send counter: {err}");
+                0
+            }
+        }
+    }
+
     fn persist_last_counter(config: &ClientConfig, last_counter: u64) {
-        let cfg = SavedClientConfig {
-            server_url: config.server_url.clone(),
-            room_code: config.room_code.clone(),
-            device_name: config.device_name.clone(),
-            last_counter,
-        };
-        if let Err(err) = save_saved_config(&cfg) {
+        let result = read_stored_config().and_then(|stored| {
+            let mut stored = match stored {
+                Some(stored) => stored,
+                None => {
+                    save_saved_config(&SavedClientConfig {
+                        server_url: config.server_url.clone(),
+                        room_code: config.room_code.clone(),
+                        device_name: config.device_name.clone(),
+                    })?;
+                    read_stored_config()?.ok_or_else(|| "config was not written".to_owned())?
+                }
+            };
+            stored.counters.record(&config.room_id, last_counter);
+            write_stored_config(&stored)
+        });
+        if let Err(err) = result {
             warn!("failed to persist last_counter: {err}");
         }
     }
@@ -2282,6 +9872,110 @@ mod windows_client {
             .map_err(|err| err.to_string())
     }
 
+    /// Put received `text` on the clipboard, after the text already there
+    /// when append mode is on.  A clipboard holding no text counts as empty.
+    fn apply_received_text(text: &str, ui_prefs: &SavedUiState) -> Result<(), String> {
+        if !ui_prefs.append_mode {
+            return apply_clipboard_text(text);
+        }
+        let current = Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .unwrap_or_default();
+        apply_clipboard_text(&append_mode::append(
+            &current,
+            text,
+            ui_prefs.append_separator,
+        ))
+    }
+
+    /// Put an invite to the current room on the clipboard.
+    fn copy_invite(config: &ClientConfig, with_qr: bool) -> Result<(), String> {
+        apply_clipboard_text(&invite::text(
+            &config.server_url,
+            &config.room_code,
+            with_qr,
+        ))
+    }
+
+    /// Copy an invite for the tray menu, which reports through a system
+    /// notification since the window is usually hidden.
+    fn copy_invite_from_tray(config: &ClientConfig, with_qr: bool) {
+        match copy_invite(config, with_qr) {
+            Ok(()) => show_system_notification(
+                "Invite copied",
+                "Paste it into a chat with the person joining. It contains the room code.",
+            ),
+            Err(err) => show_system_notification("Failed to copy invite", &err),
+        }
+    }
+
+    /// Ask for Windows Hello or the account password on a background
+    /// thread, then do `then`.  While a prompt is already up, only `then`
+    /// is updated.
+    fn start_unlock(pending: &mut Option<PendingUnlock>, then: AfterUnlock) {
+        if let Some(pending) = pending {
+            if then != AfterUnlock::Show {
+                pending.then = then;
+            }
+            return;
+        }
+        let (answer_tx, answer) = std::sync::mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("auth-gate".into())
+            .spawn(move || {
+                let _ = answer_tx.send(auth_gate::verify());
+            });
+        match spawned {
+            Ok(_) => *pending = Some(PendingUnlock { answer, then }),
+            Err(err) => warn!("could not start the unlock prompt: {err}"),
+        }
+    }
+
+    /// Put a file's SHA-256 on the clipboard to compare with the sender's.
+    fn copy_checksum(checksum: &str, toast_message: &mut Option<(String, u64)>) {
+        let message = match apply_clipboard_text(checksum) {
+            Ok(()) => "Checksum copied".to_string(),
+            Err(err) => {
+                warn!("copy checksum failed: {err}");
+                "Failed to copy checksum".to_string()
+            }
+        };
+        *toast_message = Some((message, now_unix_ms()));
+    }
+
+    fn clear_clipboard() -> Result<(), String> {
+        let mut clipboard = Clipboard::new().map_err(|err| err.to_string())?;
+        clipboard.clear().map_err(|err| err.to_string())
+    }
+
+    /// `text` in the monospace font, coloured by [`code_preview::tokenize`].
+    /// Only the colours come from the tokens; the characters drawn are
+    /// `text`'s own.
+    fn highlighted_code(ui: &egui::Ui, text: &str, language: Language) -> egui::text::LayoutJob {
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let dark = ui.visuals().dark_mode;
+        let mut job = egui::text::LayoutJob::default();
+        for token in code_preview::tokenize(text, language) {
+            let color = match (token.kind, dark) {
+                (TokenKind::Plain, _) => ui.visuals().text_color(),
+                (TokenKind::Keyword, true) => egui::Color32::from_rgb(86, 156, 214),
+                (TokenKind::Keyword, false) => egui::Color32::from_rgb(0, 0, 200),
+                (TokenKind::String, true) => egui::Color32::from_rgb(206, 145, 120),
+                (TokenKind::String, false) => egui::Color32::from_rgb(163, 21, 21),
+                (TokenKind::Number, true) => egui::Color32::from_rgb(181, 206, 168),
+                (TokenKind::Number, false) => egui::Color32::from_rgb(9, 134, 88),
+                (TokenKind::Comment, true) => egui::Color32::from_rgb(106, 153, 85),
+                (TokenKind::Comment, false) => egui::Color32::from_rgb(0, 128, 0),
+            };
+            job.append(
+                &text[token.range],
+                0.0,
+                egui::TextFormat::simple(font.clone(), color),
+            );
+        }
+        job
+    }
+
     fn preview_text(text: &str, max_chars: usize) -> String {
         let mut out = String::new();
         for (index, ch) in text.chars().enumerate() {
@@ -2294,10 +9988,11 @@ mod windows_client {
         out
     }
 
-    fn device_id_from(host: &str, user: &str, device_name: &str) -> String {
-        let raw = format!("{}:{}:{}", host, user, device_name.trim());
-        let digest = Sha256::digest(raw.as_bytes());
-        hex::encode(&digest[0..16])
+    fn current_user_name() -> String {
+        std::env::var("USERNAME")
+            .ok()
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| "unknown-user".to_owned())
     }
 
     fn stable_device_id(device_name: &str) -> String {
@@ -2305,11 +10000,7 @@ mod windows_client {
             .ok()
             .or_else(|| std::env::var("HOSTNAME").ok())
             .unwrap_or_else(|| "unknown-host".to_owned());
-        let user = std::env::var("USERNAME")
-            .ok()
-            .or_else(|| std::env::var("USER").ok())
-            .unwrap_or_else(|| "unknown-user".to_owned());
-        device_id_from(&host, &user, device_name)
+        device_id_from(&host, &current_user_name(), device_name)
     }
 
     fn now_unix_ms() -> u64 {
@@ -2418,12 +10109,7 @@ mod windows_client {
             let _ = std::fs::create_dir_all(&dir);
             return dir;
         }
-        let base = std::env::var_os("LOCALAPPDATA")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let dir = base.join("ClipRelay");
-        let _ = std::fs::create_dir_all(&dir);
-        dir
+        config_profiles::data_dir()
     }
 
     fn downloads_dir() -> PathBuf {
@@ -2433,12 +10119,60 @@ mod windows_client {
             .join("Downloads")
     }
 
-    fn save_temp_file_to_downloads(
+    fn default_received_files_dir() -> PathBuf {
+        downloads_dir().join("ClipRelay")
+    }
+
+    /// Destination folder for a file received from `sender_name`: the
+    /// configured folder (default `Downloads\ClipRelay`), optionally with a
+    /// per-sender subfolder.
+    fn received_files_dir(prefs: &SavedUiState, sender_name: &str) -> PathBuf {
+        let base = prefs
+            .files_save_dir
+            .clone()
+            .unwrap_or_else(default_received_files_dir);
+        if prefs.files_per_sender_subfolders {
+            base.join(sanitize_file_name(sender_name))
+        } else {
+            base
+        }
+    }
+
+    fn open_folder(dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        std::process::Command::new("explorer.exe")
+            .arg(dir)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Open a web link (from [`links`], so always `http`/`https`) in the
+    /// default browser.
+    fn open_url(url: &str) -> Result<(), String> {
+        std::process::Command::new("rundll32.exe")
+            .args(["url.dll,FileProtocolHandler", url])
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Relay to name in the Mark-of-the-Web of saved files, or `None` when
+    /// tagging is turned off.
+    fn mark_of_the_web_source<'a>(prefs: &SavedUiState, server_url: &'a str) -> Option<&'a str> {
+        (!prefs.files_skip_mark_of_the_web).then_some(server_url)
+    }
+
+    /// Copy a received temp file into `base` under a unique name.  With
+    /// `motw_server_url`, the copy is tagged as downloaded from the Internet
+    /// zone via that relay.
+    fn save_received_file(
         temp_path: &PathBuf,
         file_name: &str,
+        base: &Path,
+        motw_server_url: Option<&str>,
     ) -> Result<PathBuf, String> {
-        let base = downloads_dir().join("ClipRelay");
-        std::fs::create_dir_all(&base).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(base).map_err(|e| e.to_string())?;
         let safe = sanitize_file_name(file_name);
         let mut dest = base.join(&safe);
         if dest.exists() {
@@ -2468,11 +10202,24 @@ mod windows_client {
             }
         }
         std::fs::copy(temp_path, &dest).map_err(|e| e.to_string())?;
+        if let Some(server_url) = motw_server_url {
+            let host_url = mark_of_the_web::relay_host_url(server_url);
+            // The file itself is saved; a volume without alternate streams
+            // (FAT, some shares) only loses the tag.
+            if let Err(err) = mark_of_the_web::apply(&dest, host_url.as_deref()) {
+                warn!(path = %dest.display(), "could not write Mark-of-the-Web: {err}");
+            }
+        }
         Ok(dest)
     }
 
+    /// Where received files wait until they are saved or dismissed.
+    fn incoming_staging_dir() -> PathBuf {
+        cliprelay_data_dir().join("incoming")
+    }
+
     fn write_incoming_temp_file(file_name: &str, bytes: &[u8]) -> Result<PathBuf, String> {
-        let dir = cliprelay_data_dir().join("incoming");
+        let dir = incoming_staging_dir();
         std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
         let safe = sanitize_file_name(file_name);
         let path = dir.join(format!("incoming_{}_{}", now_unix_ms(), safe));
@@ -2521,11 +10268,19 @@ mod windows_client {
         }
     }
 
+    /// Log file actually opened by `init_logging` (the temp-dir fallback
+    /// when the usual location was not writable).
+    static ACTIVE_LOG_PATH: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+    fn active_log_path() -> PathBuf {
+        ACTIVE_LOG_PATH
+            .get()
+            .cloned()
+            .unwrap_or_else(client_log_path)
+    }
+
     fn client_log_path() -> PathBuf {
-        let base = std::env::var_os("LOCALAPPDATA")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from("."));
-        let dir = base.join("ClipRelay").join("logs");
+        let dir = config_profiles::data_dir().join("logs");
         let _ = std::fs::create_dir_all(&dir);
         dir.join("cliprelay-client.log")
     }
@@ -2591,33 +10346,204 @@ mod windows_client {
             }
         }
 
-        let Some((file, chosen_path)) = opened else {
-            tracing_subscriber::fmt().with_env_filter(env_filter).init();
+        let Some((file, chosen_path)) = opened else {
+            tracing_subscriber::fmt().with_env_filter(env_filter).init();
+            return;
+        };
+
+        let make_writer = FileMakeWriter {
+            file: Arc::new(Mutex::new(file)),
+        };
+
+        tracing_subscriber::fmt()
+            .with_env_filter(env_filter)
+            .with_writer(make_writer)
+            .init();
+
+        info!(log_path = %chosen_path.display(), "logging initialized");
+        let _ = ACTIVE_LOG_PATH.set(chosen_path);
+    }
+
+    // ─── Crash reporting ───────────────────────────────────────────────────────
+
+    /// Set by the first crash handler to run, so a panic that ends in an
+    /// abort (or a fault inside the panic hook) is reported only once.
+    static CRASH_RECORDED: AtomicBool = AtomicBool::new(false);
+
+    /// The thread running the UI.  A panic there ends the process; panics
+    /// in runtime tasks and helper threads are logged and recovered from.
+    static MAIN_THREAD: std::sync::OnceLock<std::thread::ThreadId> = std::sync::OnceLock::new();
+
+    /// Install the panic hook and the last-chance SEH filter.  Both write a
+    /// report with the tail of the client log plus a minidump into
+    /// `crash_report::crash_dir()`, for the next start to offer.  Only
+    /// panics that end the process are reported: those on the main thread,
+    /// or any panic in a build that aborts on panic.
+    ///
+    /// The SEH filter runs in the crashing process.  After a stack overflow
+    /// it has almost no stack left, so for those the report, and above all
+    /// the minidump, is best effort and often missing.
+    fn install_crash_handlers() {
+        use windows_sys::Win32::System::Diagnostics::Debug::SetUnhandledExceptionFilter;
+
+        let _ = MAIN_THREAD.set(std::thread::current().id());
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let thread = std::thread::current();
+            let reason = format!("panic in thread '{}': {info}", thread.name().unwrap_or("?"));
+            // Last-chance log line, so it also lands in the report's tail.
+            error!("{reason}");
+            if cfg!(panic = "abort") || MAIN_THREAD.get() == Some(&thread.id()) {
+                let backtrace = std::backtrace::Backtrace::force_capture();
+                record_crash(&reason, &backtrace.to_string(), std::ptr::null());
+            }
+            previous(info);
+        }));
+        unsafe {
+            SetUnhandledExceptionFilter(Some(unhandled_exception_filter));
+        }
+    }
+
+    /// Runs for access violations, stack overflows and other structured
+    /// exceptions nothing else handled, just before Windows ends the process.
+    unsafe extern "system" fn unhandled_exception_filter(
+        info: *const windows_sys::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS,
+    ) -> i32 {
+        const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+        let code = unsafe {
+            info.as_ref()
+                .and_then(|ptrs| ptrs.ExceptionRecord.as_ref())
+                .map_or(0, |record| record.ExceptionCode as u32)
+        };
+        record_crash(&format!("unhandled exception 0x{code:08X}"), "", info);
+        EXCEPTION_CONTINUE_SEARCH
+    }
+
+    fn record_crash(
+        reason: &str,
+        details: &str,
+        exception: *const windows_sys::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS,
+    ) {
+        if CRASH_RECORDED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let dir = crash_report::crash_dir();
+        if std::fs::create_dir_all(&dir).is_err() {
+            return;
+        }
+        let ts = now_unix_ms();
+        let stem = crash_report::report_stem(ts);
+        let dump_name = format!("{stem}.dmp");
+        let dump_file = match write_minidump(&dir.join(&dump_name), exception) {
+            Ok(()) => Some(dump_name.as_str()),
+            Err(err) => {
+                eprintln!("minidump failed: {err}");
+                None
+            }
+        };
+        let log_lines =
+            crash_report::last_log_lines(&active_log_path(), crash_report::CRASH_LOG_LINES)
+                .unwrap_or_default();
+        let text = crash_report::format_report(reason, details, ts, dump_file, &log_lines);
+        if let Err(err) = crash_report::write_report(&dir, &stem, &text) {
+            eprintln!("crash report failed: {err}");
+        }
+    }
+
+    /// Dump thread stacks and module list (not the whole heap, which would
+    /// hold clipboard contents) to `path`.
+    fn write_minidump(
+        path: &Path,
+        exception: *const windows_sys::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS,
+    ) -> io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::System::Diagnostics::Debug::{
+            MINIDUMP_EXCEPTION_INFORMATION, MiniDumpNormal, MiniDumpWithThreadInfo,
+            MiniDumpWriteDump,
+        };
+        use windows_sys::Win32::System::Threading::{
+            GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId,
+        };
+
+        let file = File::create(path)?;
+        let exception_info = MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: unsafe { GetCurrentThreadId() },
+            ExceptionPointers: exception as *mut _,
+            ClientPointers: 0,
+        };
+        let exception_param = if exception.is_null() {
+            std::ptr::null()
+        } else {
+            &exception_info as *const MINIDUMP_EXCEPTION_INFORMATION
+        };
+        let ok = unsafe {
+            MiniDumpWriteDump(
+                GetCurrentProcess(),
+                GetCurrentProcessId(),
+                file.as_raw_handle() as isize,
+                MiniDumpNormal | MiniDumpWithThreadInfo,
+                exception_param,
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        if ok == 0 {
+            let err = io::Error::last_os_error();
+            drop(file);
+            let _ = std::fs::remove_file(path);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// If the previous run crashed, ask whether to show its report.  Asked
+    /// once per crash, before any window exists, so it also appears for
+    /// `--background` starts.
+    fn offer_pending_crash_report() {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            IDYES, MB_ICONWARNING, MB_YESNO, MessageBoxW,
+        };
+
+        let Some(report) = crash_report::take_pending_report(&crash_report::crash_dir()) else {
             return;
         };
-
-        let make_writer = FileMakeWriter {
-            file: Arc::new(Mutex::new(file)),
+        warn!(report = %report.display(), "previous run crashed");
+        let text = to_wide_null(
+            "ClipRelay crashed last time \u{2014} open crash report?\n\n\
+             The report folder also holds a minidump you can attach to an issue. \
+             It contains thread stacks, which may include fragments of recent \
+             clipboard data, so share it only where you are comfortable doing so.",
+        );
+        let caption = to_wide_null("ClipRelay");
+        let answer = unsafe {
+            MessageBoxW(
+                0,
+                text.as_ptr(),
+                caption.as_ptr(),
+                MB_YESNO | MB_ICONWARNING,
+            )
         };
-
-        tracing_subscriber::fmt()
-            .with_env_filter(env_filter)
-            .with_writer(make_writer)
-            .init();
-
-        info!(log_path = %chosen_path.display(), "logging initialized");
+        if answer == IDYES {
+            // Opens Explorer with the report selected, next to its dump.
+            if let Err(err) = std::process::Command::new("explorer.exe")
+                .arg(format!("/select,{}", report.display()))
+                .spawn()
+            {
+                warn!("failed to open crash report: {err}");
+            }
+        }
     }
 
     // ─── Networking runtime ────────────────────────────────────────────────────
 
     async fn run_client_runtime(
         config: ClientConfig,
+        startup_gate: StartupGate,
         ui_event_tx: RepaintingSender,
         mut runtime_cmd_rx: mpsc::UnboundedReceiver<RuntimeCommand>,
         shared_state: SharedRuntimeState,
     ) {
-        const RECONNECT_DELAY: Duration = Duration::from_secs(5);
-
         info!(
             server_url = %config.server_url,
             room_id = %config.room_id,
@@ -2632,11 +10558,21 @@ mod windows_client {
             return;
         }
 
+        if !startup_gate.is_noop() {
+            wait_for_startup_gate(startup_gate, &config.server_url, &ui_event_tx).await;
+        }
+
         let mut counter: u64 = config.initial_counter;
 
+        // Ends with the runtime (room change or quit).
+        tokio::spawn(network_cost_task(ui_event_tx.clone(), shared_state.clone()));
+        tokio::spawn(network_change_task(shared_state.clone()));
+
+        // Consecutive sessions that failed to connect.
+        let mut failures: u32 = 0;
         loop {
             info!("starting connection session");
-            run_single_session(
+            let connected = run_single_session(
                 &config,
                 &ui_event_tx,
                 &mut runtime_cmd_rx,
@@ -2644,29 +10580,136 @@ mod windows_client {
                 &mut counter,
             )
             .await;
+            failures = if connected { 0 } else { failures + 1 };
+
+            // Sends not fully written go out again once the next session
+            // has its room key.
+            let statuses = shared_state
+                .outbox
+                .lock()
+                .map(|mut outbox| outbox.end_session())
+                .unwrap_or_default();
+            for (id, status) in statuses {
+                let _ = ui_event_tx.send(UiEvent::SendStatus { id, status });
+            }
 
-            if let Ok(mut key_slot) = shared_state.room_key.lock() {
-                *key_slot = None;
+            if let Ok(mut session) = shared_state.session.lock() {
+                *session = None;
             }
             let _ = ui_event_tx.send(UiEvent::RoomKeyReady(false));
             let _ = ui_event_tx.send(UiEvent::Peers(Vec::new()));
-            let _ = ui_event_tx.send(UiEvent::ConnectionStatus("Reconnecting…".to_owned()));
 
-            info!(
-                delay_secs = RECONNECT_DELAY.as_secs(),
-                "waiting before reconnect"
-            );
-            tokio::time::sleep(RECONNECT_DELAY).await;
+            let policy = shared_state
+                .reconnect_policy
+                .lock()
+                .map(|guard| *guard)
+                .unwrap_or_default();
+            if policy.gives_up(failures) {
+                warn!(failures, "giving up reconnecting");
+                let _ = ui_event_tx.send(UiEvent::ReconnectGaveUp(failures));
+                // Idle until the UI restarts the runtime (Retry Now, room
+                // change or quit).
+                std::future::pending::<()>().await;
+            }
+
+            let delay = if connected {
+                policy.delay(1)
+            } else {
+                policy.delay(failures)
+            };
+            info!(delay_secs = delay.as_secs(), "waiting before reconnect");
+            wait_before_reconnect(delay, &ui_event_tx, &shared_state).await;
+        }
+    }
+
+    /// Sleep for `delay`, counting down the seconds left in the connection
+    /// status, unless Reconnect Now ends the wait early.
+    async fn wait_before_reconnect(
+        delay: Duration,
+        ui_event_tx: &RepaintingSender,
+        shared_state: &SharedRuntimeState,
+    ) {
+        let deadline = tokio::time::Instant::now() + delay;
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                return;
+            }
+            let _ = ui_event_tx.send(UiEvent::ConnectionStatus(reconnect::countdown_status(
+                remaining,
+            )));
+            // Wake when the whole seconds left, and so the label, change.
+            let tick = Duration::from_nanos((remaining.as_nanos() % 1_000_000_000) as u64);
+            let tick = if tick.is_zero() {
+                Duration::from_secs(1)
+            } else {
+                tick
+            };
+            tokio::select! {
+                () = tokio::time::sleep(tick) => {}
+                () = shared_state.reconnect_now.notified() => {
+                    info!("reconnect now: skipping the rest of the delay");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Apply the autostart delay and optional network wait before the first
+    /// connection attempt.  Never fails: on timeout the reconnect loop simply
+    /// starts as usual.
+    async fn wait_for_startup_gate(
+        gate: StartupGate,
+        server_url: &str,
+        ui_event_tx: &RepaintingSender,
+    ) {
+        if gate.delay_secs > 0 {
+            info!(delay_secs = gate.delay_secs, "delaying startup");
+            let _ = ui_event_tx.send(UiEvent::ConnectionStatus(format!(
+                "Starting in {}…",
+                startup::delay_label(gate.delay_secs)
+            )));
+            tokio::time::sleep(Duration::from_secs(u64::from(gate.delay_secs))).await;
+        }
+
+        if !gate.wait_for_network {
+            return;
+        }
+        let Some((host, port)) = startup::network_probe_target(server_url) else {
+            return;
+        };
+        let _ = ui_event_tx.send(UiEvent::ConnectionStatus("Waiting for network…".to_owned()));
+        let deadline =
+            tokio::time::Instant::now() + Duration::from_secs(startup::NETWORK_WAIT_TIMEOUT_SECS);
+        loop {
+            match tokio::net::lookup_host((host.as_str(), port)).await {
+                Ok(mut addrs) if addrs.next().is_some() => {
+                    info!(host = %host, "network ready");
+                    return;
+                }
+                Ok(_) => debug!(host = %host, "relay host resolved to no addresses"),
+                Err(err) => debug!(host = %host, "network not ready: {err}"),
+            }
+            if tokio::time::Instant::now() >= deadline {
+                warn!(
+                    timeout_secs = startup::NETWORK_WAIT_TIMEOUT_SECS,
+                    "network wait timed out; connecting anyway"
+                );
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs(startup::NETWORK_POLL_INTERVAL_SECS)).await;
         }
     }
 
+    /// Connect once and run the session until it ends.  Returns whether the
+    /// connection was established, which resets the reconnect backoff.
     async fn run_single_session(
         config: &ClientConfig,
         ui_event_tx: &RepaintingSender,
         runtime_cmd_rx: &mut mpsc::UnboundedReceiver<RuntimeCommand>,
         shared_state: &SharedRuntimeState,
         counter: &mut u64,
-    ) {
+    ) -> bool {
         const MAX_CONNECT_ATTEMPTS: u32 = 3;
         const CONNECT_TIMEOUT: Duration = Duration::from_secs(12);
         const BACKOFF_BASE_MS: u64 = 200;
@@ -2684,7 +10727,7 @@ mod windows_client {
                         error!(attempt, "{msg}");
                         if attempt >= MAX_CONNECT_ATTEMPTS {
                             let _ = ui_event_tx.send(UiEvent::RuntimeError(msg));
-                            return;
+                            return false;
                         }
                     }
                     Err(_) => {
@@ -2692,7 +10735,7 @@ mod windows_client {
                         error!(attempt, "{msg}");
                         if attempt >= MAX_CONNECT_ATTEMPTS {
                             let _ = ui_event_tx.send(UiEvent::RuntimeError(msg));
-                            return;
+                            return false;
                         }
                     }
                 }
@@ -2708,22 +10751,34 @@ mod windows_client {
         let (write_half, read_half) = ws_stream.split();
         let (network_send_tx, network_send_rx) = mpsc::unbounded_channel::<WireMessage>();
         let (control_tx, control_rx) = mpsc::unbounded_channel::<ControlMessage>();
+        if let Ok(mut gate) = shared_state.probe_gate.lock() {
+            *gate = ProbeGate::default();
+        }
 
-        let hello = ControlMessage::Hello(Hello {
-            room_id: config.room_id.clone(),
-            peer: PeerInfo {
-                device_id: config.device_id.clone(),
-                device_name: config.device_name.clone(),
-            },
-        });
+        let session = match new_session(config, shared_state, *counter) {
+            Ok(session) => session.with_probes(),
+            Err(err) => {
+                let _ = ui_event_tx.send(UiEvent::RuntimeError(err));
+                return false;
+            }
+        };
+        let hello = session.hello();
+        if let Ok(mut slot) = shared_state.session.lock() {
+            *slot = Some(session);
+        }
 
-        if network_send_tx.send(WireMessage::Control(hello)).is_err() {
+        if network_send_tx.send(hello).is_err() {
             error!("failed to queue hello");
             let _ = ui_event_tx.send(UiEvent::RuntimeError("failed to queue hello".to_owned()));
-            return;
+            return true;
         }
 
-        let send_task = tokio::spawn(network_send_task(write_half, network_send_rx));
+        let send_task = tokio::spawn(network_send_task(
+            write_half,
+            network_send_rx,
+            ui_event_tx.clone(),
+            shared_state.clone(),
+        ));
         let receive_task = tokio::spawn(network_receive_task(
             read_half,
             config.clone(),
@@ -2747,9 +10802,190 @@ mod windows_client {
             ) => info!("command handler ended"),
         }
 
+        let asked_to_reconnect = shared_state
+            .reconnect_notice
+            .lock()
+            .map(|mut slot| slot.take().is_some())
+            .unwrap_or(false);
+        if asked_to_reconnect {
+            info!("reconnecting as the relay asked");
+            let _ = ui_event_tx.send(UiEvent::ConnectionStatus("Reconnecting".to_owned()));
+            shared_state.reconnect_now.notify_one();
+            return true;
+        }
         let _ = ui_event_tx.send(UiEvent::RuntimeError(
             "connection ended – will reconnect".to_owned(),
         ));
+        true
+    }
+
+    fn current_sync_direction(shared_state: &SharedRuntimeState) -> SyncDirection {
+        shared_state
+            .sync_direction
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or_default()
+    }
+
+    fn sync_is_paused(shared_state: &SharedRuntimeState) -> bool {
+        shared_state
+            .sync_paused
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or(false)
+    }
+
+    /// Forward a trace summary to the UI when the protocol trace is on.
+    /// `summary` is only built in that case.
+    fn trace_protocol(
+        shared_state: &SharedRuntimeState,
+        ui_event_tx: &RepaintingSender,
+        summary: impl FnOnce() -> FrameSummary,
+    ) {
+        let enabled = shared_state
+            .protocol_trace
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or(false);
+        if enabled {
+            let _ = ui_event_tx.send(UiEvent::ProtocolFrame(summary()));
+        }
+    }
+
+    /// Poll the Internet connection cost, report changes to the UI and let
+    /// deferred sends go once the connection is no longer metered.
+    async fn network_cost_task(ui_event_tx: RepaintingSender, shared_state: SharedRuntimeState) {
+        let mut last: Option<NetworkCost> = None;
+        loop {
+            let cost = tokio::task::spawn_blocking(metered::query_network_cost)
+                .await
+                .unwrap_or_default();
+            if last != Some(cost) {
+                info!(cost = cost.label(), "network cost");
+                if let Ok(mut current) = shared_state.network_cost.lock() {
+                    *current = cost;
+                }
+                if !cost.is_metered()
+                    && let Ok(mut policy) = shared_state.metered_policy.lock()
+                {
+                    policy.override_active = false;
+                }
+                let _ = ui_event_tx.send(UiEvent::NetworkCost(cost));
+                shared_state.deferred_ready.notify_one();
+                last = Some(cost);
+            }
+            tokio::time::sleep(Duration::from_secs(metered::NETWORK_COST_POLL_SECS)).await;
+        }
+    }
+
+    /// Cut the reconnect delay short whenever Windows reports Internet
+    /// access again, e.g. after sleep or a Wi-Fi switch.
+    async fn network_change_task(shared_state: SharedRuntimeState) {
+        let (level_tx, mut level_rx) = mpsc::unbounded_channel();
+        // Unregistered when the task ends with the runtime.
+        let _subscription = match network_change::subscribe(move |level| {
+            let _ = level_tx.send(level);
+        }) {
+            Ok(subscription) => subscription,
+            Err(err) => {
+                warn!("network change notifications unavailable: {err}");
+                return;
+            }
+        };
+        let mut watch = ConnectivityWatch::default();
+        while let Some(level) = level_rx.recv().await {
+            debug!(?level, "network connectivity changed");
+            if watch.update(level) {
+                info!("network is back: reconnecting now");
+                shared_state.reconnect_now.notify_one();
+            }
+        }
+    }
+
+    fn metered_state(shared_state: &SharedRuntimeState) -> (NetworkCost, MeteredPolicy) {
+        let cost = shared_state
+            .network_cost
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or_default();
+        let policy = shared_state
+            .metered_policy
+            .lock()
+            .map(|guard| *guard)
+            .unwrap_or_default();
+        (cost, policy)
+    }
+
+    fn deferred_count(shared_state: &SharedRuntimeState) -> usize {
+        shared_state
+            .deferred_sends
+            .lock()
+            .map(|queue| queue.len())
+            .unwrap_or(0)
+    }
+
+    fn awaiting_key_count(shared_state: &SharedRuntimeState) -> usize {
+        shared_state
+            .awaiting_key
+            .lock()
+            .map(|queue| queue.len())
+            .unwrap_or(0)
+    }
+
+    fn room_key_ready(shared_state: &SharedRuntimeState) -> bool {
+        shared_state
+            .session
+            .lock()
+            .map(|session| session.as_ref().is_some_and(SessionState::room_key_ready))
+            .unwrap_or(false)
+    }
+
+    /// Protocol state for one connection of this device to its room.
+    fn new_session(
+        config: &ClientConfig,
+        shared_state: &SharedRuntimeState,
+        counter: u64,
+    ) -> Result<SessionState, String> {
+        let local = PeerInfo {
+            device_id: config.device_id.clone(),
+            device_name: config.device_name.clone(),
+            sealed_chunks: true,
+        };
+        SessionState::new(&config.room_code, local, counter)
+            .map(|session| session.with_identity(shared_state.identity.clone()))
+            .map_err(|err| err.to_string())
+    }
+
+    /// Run `f` on the current connection's [`SessionState`].
+    fn with_session<T>(
+        shared_state: &SharedRuntimeState,
+        f: impl FnOnce(&mut SessionState) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut slot = shared_state
+            .session
+            .lock()
+            .map_err(|_| "session state poisoned".to_string())?;
+        let session = slot.as_mut().ok_or_else(|| "not connected".to_string())?;
+        f(session)
+    }
+
+    /// Encrypt an event as the next one from this device, and note the
+    /// counter it used in `counter`.
+    fn seal_event(
+        shared_state: &SharedRuntimeState,
+        counter: &mut u64,
+        mime: &str,
+        text_utf8: String,
+        pinned: bool,
+        label: Option<String>,
+    ) -> Result<EncryptedPayload, String> {
+        with_session(shared_state, |session| {
+            let payload = session
+                .seal_payload(mime, text_utf8, now_unix_ms(), pinned, label)
+                .map_err(|err| err.to_string())?;
+            *counter = session.counter();
+            Ok(payload)
+        })
     }
 
     async fn process_runtime_commands(
@@ -2760,72 +10996,407 @@ mod windows_client {
         network_send_tx: &mpsc::UnboundedSender<WireMessage>,
         ui_event_tx: &RepaintingSender,
     ) {
-        while let Some(command) = runtime_cmd_rx.recv().await {
-            match command {
-                RuntimeCommand::SetAutoApply(_) | RuntimeCommand::MarkApplied(_) => {
-                    handle_runtime_command(command, shared_state);
+        // Release anything that became sendable while disconnected.
+        shared_state.deferred_ready.notify_one();
+        loop {
+            let command = tokio::select! {
+                command = runtime_cmd_rx.recv() => match command {
+                    Some(command) => command,
+                    None => return,
+                },
+                // Between two commands, so no send is half queued; new ones
+                // wait for the next session.
+                () = shared_state.reconnect_soon.notified() => {
+                    let Some(notice) = shared_state
+                        .reconnect_notice
+                        .lock()
+                        .ok()
+                        .and_then(|slot| *slot)
+                    else {
+                        // Left over from a session that ended first.
+                        continue;
+                    };
+                    wait_for_outbox_idle(shared_state, notice.at_unix_ms).await;
+                    return;
                 }
-                RuntimeCommand::SendText(text) => {
-                    if text.trim().is_empty() {
+                () = shared_state.deferred_ready.notified() => {
+                    let (retries, keyed) = if room_key_ready(shared_state) {
+                        (
+                            shared_state
+                                .outbox
+                                .lock()
+                                .map(|mut outbox| outbox.take_retries())
+                                .unwrap_or_default(),
+                            shared_state
+                                .awaiting_key
+                                .lock()
+                                .map(|mut queue| queue.take_all())
+                                .unwrap_or_default(),
+                        )
+                    } else {
+                        (Vec::new(), Vec::new())
+                    };
+                    if !retries.is_empty() {
+                        info!(count = retries.len(), "resending items lost to the last connection");
+                        for send in retries {
+                            dispatch_command(
+                                send.into(),
+                                counter,
+                                config,
+                                shared_state,
+                                network_send_tx,
+                                ui_event_tx,
+                            )
+                            .await;
+                        }
+                    }
+                    if !keyed.is_empty() {
+                        info!(count = keyed.len(), "sending items queued before the room key");
+                        let _ = ui_event_tx.send(UiEvent::AwaitingKeyCount(0));
+                        for send in keyed {
+                            dispatch_command(
+                                send.into(),
+                                counter,
+                                config,
+                                shared_state,
+                                network_send_tx,
+                                ui_event_tx,
+                            )
+                            .await;
+                        }
+                    }
+                    let (cost, policy) = metered_state(shared_state);
+                    let ready = shared_state
+                        .deferred_sends
+                        .lock()
+                        .map(|mut queue| queue.take_ready(cost, policy))
+                        .unwrap_or_default();
+                    if ready.is_empty() {
                         continue;
                     }
-                    if text.len() > MAX_CLIPBOARD_TEXT_BYTES {
+                    info!(count = ready.len(), "sending deferred items");
+                    let _ = ui_event_tx.send(UiEvent::DeferredCount(deferred_count(shared_state)));
+                    for send in ready {
+                        run_command(
+                            send.into(),
+                            counter,
+                            config,
+                            shared_state,
+                            network_send_tx,
+                            ui_event_tx,
+                        )
+                        .await;
+                    }
+                    continue;
+                }
+            };
+            dispatch_command(
+                command,
+                counter,
+                config,
+                shared_state,
+                network_send_tx,
+                ui_event_tx,
+            )
+            .await;
+        }
+    }
+
+    /// Wait until every queued frame is written, or until shortly before
+    /// the relay closes the connection at `deadline_unix_ms`.  Frames still
+    /// unwritten then are resent after the reconnect.
+    async fn wait_for_outbox_idle(shared_state: &SharedRuntimeState, deadline_unix_ms: u64) {
+        const POLL_INTERVAL: Duration = Duration::from_millis(50);
+        const CLOSE_MARGIN_MS: u64 = 1_000;
+
+        let deadline = deadline_unix_ms.saturating_sub(CLOSE_MARGIN_MS);
+        while now_unix_ms() < deadline
+            && !shared_state
+                .outbox
+                .lock()
+                .map(|outbox| outbox.is_idle())
+                .unwrap_or(true)
+        {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Run `command`, or hold a send back: until the room key is ready, or
+    /// while the metered policy defers it.
+    async fn dispatch_command(
+        command: RuntimeCommand,
+        counter: &mut u64,
+        config: &ClientConfig,
+        shared_state: &SharedRuntimeState,
+        network_send_tx: &mpsc::UnboundedSender<WireMessage>,
+        ui_event_tx: &RepaintingSender,
+    ) {
+        let command = match command {
+            RuntimeCommand::SendText(id, send)
+                if send.text.len() > MAX_CLIPBOARD_TEXT_BYTES
+                    && shared_state
+                        .oversized_text
+                        .lock()
+                        .is_ok_and(|action| *action == OversizedText::SendAsFile) =>
+            {
+                match write_outgoing_text_file(&send.text) {
+                    Ok(path) => {
+                        info!("sending oversized text as {}", path.display());
+                        RuntimeCommand::SendFile(id, path)
+                    }
+                    Err(err) => {
+                        warn!("write outgoing text file failed: {err}");
                         let _ = ui_event_tx.send(UiEvent::RuntimeError(
-                            "send failed: input exceeds limit".to_owned(),
+                            "send failed: could not prepare the text file".to_owned(),
                         ));
-                        continue;
+                        send_failed(ui_event_tx, id);
+                        return;
                     }
-                    let room_key = shared_state.room_key.lock().ok().and_then(|lock| *lock);
-                    let room_key = match room_key {
-                        Some(key) => key,
-                        None => {
-                            let _ = ui_event_tx.send(UiEvent::RuntimeError(
-                                "send failed: room key not ready".to_owned(),
-                            ));
-                            continue;
-                        }
-                    };
-                    *counter = counter.saturating_add(1);
-                    let plaintext = ClipboardEventPlaintext {
-                        sender_device_id: config.device_id.clone(),
-                        counter: *counter,
-                        timestamp_unix_ms: now_unix_ms(),
-                        mime: MIME_TEXT_PLAIN.to_owned(),
-                        text_utf8: text,
-                    };
-                    match encrypt_clipboard_event(&room_key, &plaintext) {
-                        Ok(payload) => {
-                            network_send_clipboard(network_send_tx, payload).await;
-                            let _ = ui_event_tx.send(UiEvent::LastSent(now_unix_ms()));
-                            persist_last_counter(config, *counter);
-                        }
-                        Err(err) => {
-                            let _ = ui_event_tx
-                                .send(UiEvent::RuntimeError(format!("encryption failed: {err}")));
-                        }
+                }
+            }
+            command => command,
+        };
+        let deferrable = match &command {
+            RuntimeCommand::SendText(id, text) => Some((*id, DeferredSend::Text(text.clone()))),
+            RuntimeCommand::SendFile(id, path) => Some((*id, DeferredSend::File(path.clone()))),
+            _ => None,
+        };
+        if let Some((id, send)) = deferrable
+            && outbound_allowed(shared_state)
+        {
+            if !room_key_ready(shared_state) {
+                let queued = shared_state
+                    .awaiting_key
+                    .lock()
+                    .map(|mut queue| queue.push(id, send))
+                    .unwrap_or(false);
+                if queued {
+                    info!("send queued until the room key is ready");
+                    let _ = ui_event_tx
+                        .send(UiEvent::AwaitingKeyCount(awaiting_key_count(shared_state)));
+                } else {
+                    let _ = ui_event_tx.send(UiEvent::RuntimeError(
+                        "send failed: too many sends waiting for the room key".to_owned(),
+                    ));
+                    send_failed(ui_event_tx, id);
+                }
+                return;
+            }
+            let (cost, policy) = metered_state(shared_state);
+            if policy.defers(cost, &send) {
+                let queued = shared_state
+                    .deferred_sends
+                    .lock()
+                    .map(|mut queue| queue.push(id, send))
+                    .unwrap_or(false);
+                if queued {
+                    info!(cost = cost.label(), "send deferred: metered connection");
+                    let _ = ui_event_tx.send(UiEvent::DeferredCount(deferred_count(shared_state)));
+                } else {
+                    let _ = ui_event_tx.send(UiEvent::RuntimeError(
+                        "send failed: too many sends waiting for an unmetered connection"
+                            .to_owned(),
+                    ));
+                    send_failed(ui_event_tx, id);
+                }
+                return;
+            }
+        }
+        run_command(
+            command,
+            counter,
+            config,
+            shared_state,
+            network_send_tx,
+            ui_event_tx,
+        )
+        .await;
+    }
+
+    /// Whether sends are currently allowed at all (sync direction and
+    /// pause), logging why not.
+    fn outbound_allowed(shared_state: &SharedRuntimeState) -> bool {
+        if !current_sync_direction(shared_state).allows_send() {
+            info!("dropping outbound send: receive-only mode");
+            return false;
+        }
+        if sync_is_paused(shared_state) {
+            info!("dropping outbound send: sync paused");
+            return false;
+        }
+        true
+    }
+
+    /// Carry out one command: a settings update, the self-test, or a send
+    /// (subject to [`outbound_allowed`]).
+    async fn run_command(
+        command: RuntimeCommand,
+        counter: &mut u64,
+        config: &ClientConfig,
+        shared_state: &SharedRuntimeState,
+        network_send_tx: &mpsc::UnboundedSender<WireMessage>,
+        ui_event_tx: &RepaintingSender,
+    ) {
+        let send_id = match &command {
+            RuntimeCommand::SendText(id, _) | RuntimeCommand::SendFile(id, _) => Some(*id),
+            _ => None,
+        };
+        if let Some(id) = send_id
+            && !outbound_allowed(shared_state)
+        {
+            send_failed(ui_event_tx, id);
+            return;
+        }
+        match command {
+            RuntimeCommand::SetAutoApply(_)
+            | RuntimeCommand::MarkApplied(_)
+            | RuntimeCommand::SetDuplicateSuppression(_)
+            | RuntimeCommand::SetSyncDirection(_)
+            | RuntimeCommand::SetSyncPaused(_)
+            | RuntimeCommand::SetProtocolTrace(_)
+            | RuntimeCommand::SetExtensionPolicy(_)
+            | RuntimeCommand::SetMeteredPolicy(_)
+            | RuntimeCommand::SetReconnectPolicy(_)
+            | RuntimeCommand::SetOversizedText(_) => {
+                handle_runtime_command(command, shared_state);
+            }
+            RuntimeCommand::SendText(id, send) => {
+                if send.text.trim().is_empty() {
+                    return;
+                }
+                if send.text.len() > MAX_CLIPBOARD_TEXT_BYTES {
+                    let _ = ui_event_tx.send(UiEvent::RuntimeError(
+                        "send failed: text exceeds the message limit (Options can send it \
+                         as a file instead)"
+                            .to_owned(),
+                    ));
+                    send_failed(ui_event_tx, id);
+                    return;
+                }
+                let retry_copy = DeferredSend::Text(send.clone());
+                let bytes = send.text.len() as u64;
+                match seal_event(
+                    shared_state,
+                    counter,
+                    MIME_TEXT_PLAIN,
+                    send.text,
+                    send.pinned,
+                    send.label,
+                ) {
+                    Ok(payload) => {
+                        network_send_clipboard(shared_state, network_send_tx, payload).await;
+                        let _ = ui_event_tx.send(UiEvent::LastSent {
+                            ts_unix_ms: now_unix_ms(),
+                            kind: UsageKind::Text,
+                            bytes,
+                        });
+                        persist_last_counter(config, *counter);
+                        track_send(shared_state, ui_event_tx, id, retry_copy);
+                    }
+                    Err(err) => {
+                        let _ =
+                            ui_event_tx.send(UiEvent::RuntimeError(format!("send failed: {err}")));
+                        send_failed(ui_event_tx, id);
                     }
                 }
-                RuntimeCommand::SendFile(path) => {
-                    if let Err(err) = send_file_v1(
-                        &path,
-                        config,
-                        shared_state,
-                        network_send_tx,
-                        counter,
-                        ui_event_tx,
-                    )
-                    .await
-                    {
-                        let _ = ui_event_tx
-                            .send(UiEvent::RuntimeError(format!("send file failed: {err}")));
-                    } else {
+            }
+            RuntimeCommand::RunSelfTest => {
+                tokio::spawn(run_self_test(
+                    config.clone(),
+                    ui_event_tx.clone(),
+                    shared_state.clone(),
+                ));
+            }
+            RuntimeCommand::SendFile(id, path) => {
+                match send_file_v1(
+                    &path,
+                    config,
+                    shared_state,
+                    network_send_tx,
+                    counter,
+                    ui_event_tx,
+                )
+                .await
+                {
+                    Ok(FileSendEnd::Sent) => {
+                        persist_last_counter(config, *counter);
+                        track_send(shared_state, ui_event_tx, id, DeferredSend::File(path));
+                    }
+                    Ok(FileSendEnd::Cancelled) => {
                         persist_last_counter(config, *counter);
+                        let _ = ui_event_tx.send(UiEvent::SendStatus {
+                            id,
+                            status: DeliveryStatus::Cancelled,
+                        });
+                        if path.starts_with(outgoing_text_dir()) {
+                            let _ = std::fs::remove_file(&path);
+                        }
+                    }
+                    Err(err) => {
+                        let _ = ui_event_tx
+                            .send(UiEvent::RuntimeError(format!("send file failed: {err}")));
+                        send_failed(ui_event_tx, id);
                     }
                 }
             }
+            RuntimeCommand::CancelTransfer {
+                owner_device_id,
+                transfer_id,
+            } => {
+                let key = transfers::transfer_key(&owner_device_id, &transfer_id);
+                if let Ok(mut cancelled) = shared_state.cancelled_transfers.lock() {
+                    cancelled.insert(key.clone(), CancelSource::Local, now_unix_ms());
+                }
+                discard_incoming_transfer(&key);
+                if let Err(err) = send_transfer_cancel(
+                    &transfer_id,
+                    &owner_device_id,
+                    config,
+                    shared_state,
+                    network_send_tx,
+                    counter,
+                )
+                .await
+                {
+                    warn!("could not tell the sender about the cancel: {err}");
+                }
+                let _ = ui_event_tx.send(UiEvent::TransferEnded {
+                    owner_device_id,
+                    transfer_id,
+                    outcome: TransferOutcome::Cancelled(CancelSource::Local),
+                });
+            }
+        }
+    }
+
+    /// Every frame of send `id` is queued: report it sent if they were
+    /// already written, otherwise watch for the writes (see [`outbox`]).
+    fn track_send(
+        shared_state: &SharedRuntimeState,
+        ui_event_tx: &RepaintingSender,
+        id: SendId,
+        send: DeferredSend,
+    ) {
+        let delivered = shared_state
+            .outbox
+            .lock()
+            .map(|mut outbox| outbox.track(id, send))
+            .unwrap_or(false);
+        if delivered {
+            let _ = ui_event_tx.send(UiEvent::SendStatus {
+                id,
+                status: DeliveryStatus::Sent,
+            });
         }
     }
 
+    fn send_failed(ui_event_tx: &RepaintingSender, id: SendId) {
+        let _ = ui_event_tx.send(UiEvent::SendStatus {
+            id,
+            status: DeliveryStatus::Failed,
+        });
+    }
+
     fn handle_runtime_command(command: RuntimeCommand, shared_state: &SharedRuntimeState) {
         match command {
             RuntimeCommand::SetAutoApply(value) => {
@@ -2834,11 +11405,58 @@ mod windows_client {
                 }
             }
             RuntimeCommand::MarkApplied(hash) => {
-                if let Ok(mut last_applied) = shared_state.last_applied_hash.lock() {
-                    *last_applied = Some(hash);
+                if let Ok(mut last_applied) = shared_state.last_applied.lock() {
+                    *last_applied = Some(LastApplied {
+                        hash,
+                        applied_unix_ms: now_unix_ms(),
+                    });
+                }
+            }
+            RuntimeCommand::SetDuplicateSuppression(mode) => {
+                if let Ok(mut current) = shared_state.duplicate_suppression.lock() {
+                    *current = mode;
+                }
+            }
+            RuntimeCommand::SetSyncDirection(direction) => {
+                if let Ok(mut current) = shared_state.sync_direction.lock() {
+                    *current = direction;
+                }
+            }
+            RuntimeCommand::SetSyncPaused(paused) => {
+                if let Ok(mut current) = shared_state.sync_paused.lock() {
+                    *current = paused;
+                }
+            }
+            RuntimeCommand::SetProtocolTrace(enabled) => {
+                if let Ok(mut current) = shared_state.protocol_trace.lock() {
+                    *current = enabled;
+                }
+            }
+            RuntimeCommand::SetExtensionPolicy(policy) => {
+                if let Ok(mut current) = shared_state.extension_policy.lock() {
+                    *current = policy;
+                }
+            }
+            RuntimeCommand::SetMeteredPolicy(policy) => {
+                if let Ok(mut current) = shared_state.metered_policy.lock() {
+                    *current = policy;
+                }
+                shared_state.deferred_ready.notify_one();
+            }
+            RuntimeCommand::SetReconnectPolicy(policy) => {
+                if let Ok(mut current) = shared_state.reconnect_policy.lock() {
+                    *current = policy;
                 }
             }
-            RuntimeCommand::SendText(_) | RuntimeCommand::SendFile(_) => {}
+            RuntimeCommand::SetOversizedText(action) => {
+                if let Ok(mut current) = shared_state.oversized_text.lock() {
+                    *current = action;
+                }
+            }
+            RuntimeCommand::RunSelfTest
+            | RuntimeCommand::SendText(..)
+            | RuntimeCommand::SendFile(..)
+            | RuntimeCommand::CancelTransfer { .. } => {}
         }
     }
 
@@ -2850,10 +11468,20 @@ mod windows_client {
             Message,
         >,
         mut outgoing_rx: mpsc::UnboundedReceiver<WireMessage>,
+        ui_event_tx: RepaintingSender,
+        shared_state: SharedRuntimeState,
     ) {
-        const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+        // Every keepalive ping carries its send time, so the pongs double
+        // as latency samples (see [`latency`]).
+        const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(latency::PROBE_INTERVAL_SECS);
         let mut ping_interval = tokio::time::interval(KEEPALIVE_INTERVAL);
         ping_interval.tick().await;
+        let mut next_probe_id: u64 = 0;
+        let session = shared_state
+            .outbox
+            .lock()
+            .map(|outbox| outbox.session())
+            .unwrap_or_default();
 
         loop {
             tokio::select! {
@@ -2872,6 +11500,27 @@ mod windows_client {
                                         break;
                                     }
                                     info!(kind = label, frame_bytes = len, "ws frame sent");
+                                    if matches!(message, WireMessage::Encrypted(_)) {
+                                        let delivered = shared_state
+                                            .outbox
+                                            .lock()
+                                            .map(|mut outbox| outbox.frame_written(session))
+                                            .unwrap_or_default();
+                                        for id in delivered {
+                                            let _ = ui_event_tx.send(UiEvent::SendStatus {
+                                                id,
+                                                status: DeliveryStatus::Sent,
+                                            });
+                                        }
+                                    }
+                                    trace_protocol(&shared_state, &ui_event_tx, || {
+                                        protocol_trace::summarize_frame(
+                                            &message,
+                                            TraceDirection::Sent,
+                                            len,
+                                            now_unix_ms(),
+                                        )
+                                    });
                                 }
                                 Err(err) => warn!("encode failed: {err}"),
                             }
@@ -2880,10 +11529,33 @@ mod windows_client {
                     }
                 }
                 _ = ping_interval.tick() => {
-                    if ws_write.send(Message::Ping(vec![].into())).await.is_err() {
+                    let probe = latency::probe_payload(now_unix_ms());
+                    if ws_write.send(Message::Ping(probe.into())).await.is_err() {
                         info!("keepalive ping failed");
                         break;
                     }
+                    let send_probe = shared_state
+                        .probe_gate
+                        .lock()
+                        .map(|mut gate| {
+                            let send = gate.should_send();
+                            if send {
+                                gate.sent();
+                            }
+                            send
+                        })
+                        .unwrap_or(false);
+                    if send_probe {
+                        next_probe_id += 1;
+                        let probe = LatencyProbe::new(next_probe_id, now_unix_ms());
+                        let frame = encode_frame(&WireMessage::Control(ControlMessage::Probe(probe)));
+                        if let Ok(frame) = frame
+                            && ws_write.send(Message::Binary(frame.into())).await.is_err()
+                        {
+                            info!("probe send failed");
+                            break;
+                        }
+                    }
                 }
             }
         }
@@ -2900,7 +11572,7 @@ mod windows_client {
         control_tx: mpsc::UnboundedSender<ControlMessage>,
         shared_state: SharedRuntimeState,
     ) {
-        let mut replay_map: HashMap<DeviceId, u64> = HashMap::new();
+        let self_test_id = self_test::virtual_device_id(&config.device_id);
 
         while let Some(next) = ws_read.next().await {
             let message = match next {
@@ -2911,6 +11583,16 @@ mod windows_client {
                 }
             };
 
+            if let Message::Pong(payload) = &message {
+                if let Some(rtt_ms) = latency::rtt_from_pong(payload, now_unix_ms()) {
+                    let _ = ui_event_tx.send(UiEvent::Latency(LatencySample {
+                        at_unix_ms: now_unix_ms(),
+                        rtt_ms,
+                    }));
+                }
+                continue;
+            }
+
             if let Message::Binary(data) = message {
                 let frame = match decode_frame(&data) {
                     Ok(frame) => frame,
@@ -2919,8 +11601,32 @@ mod windows_client {
                         continue;
                     }
                 };
+                trace_protocol(&shared_state, &ui_event_tx, || {
+                    protocol_trace::summarize_frame(
+                        &frame,
+                        TraceDirection::Received,
+                        data.len(),
+                        now_unix_ms(),
+                    )
+                });
 
                 match frame {
+                    // Timed here rather than in the presence task, so the
+                    // channel hop is not counted.
+                    WireMessage::Control(ControlMessage::Probe(probe)) => {
+                        let Some(latency) = probe.latency(now_unix_ms()) else {
+                            continue;
+                        };
+                        if probe.sender_device_id == config.device_id
+                            && let Ok(mut gate) = shared_state.probe_gate.lock()
+                        {
+                            gate.answered();
+                        }
+                        let _ = ui_event_tx.send(UiEvent::ProbeLatency {
+                            device_id: probe.sender_device_id,
+                            latency,
+                        });
+                    }
                     WireMessage::Control(control_message) => {
                         let _ = control_tx.send(control_message);
                     }
@@ -2928,39 +11634,100 @@ mod windows_client {
                         if encrypted.sender_device_id == config.device_id {
                             continue;
                         }
-                        if let Err(err) = validate_counter(
-                            &mut replay_map,
-                            &encrypted.sender_device_id,
-                            encrypted.counter,
-                        ) {
-                            warn!("replay rejected: {err}");
+                        let from_self_test = encrypted.sender_device_id == self_test_id;
+                        // Decrypted, then checked against the sender's last
+                        // counter.
+                        let opened = with_session(&shared_state, |session| {
+                            session
+                                .handle_message(WireMessage::Encrypted(encrypted))
+                                .map_err(|err| err.to_string())
+                        });
+                        if from_self_test {
+                            let outcome = opened.and_then(|event| match event {
+                                Some(SessionEvent::Clipboard { event, .. })
+                                    if event.mime == self_test::MIME_SELF_TEST =>
+                                {
+                                    Ok(event.text_utf8)
+                                }
+                                Some(SessionEvent::Clipboard { event, .. }) => {
+                                    Err(format!("unexpected MIME type {}", event.mime))
+                                }
+                                _ => Err("no event".to_owned()),
+                            });
+                            let waiting = shared_state
+                                .self_test_probe
+                                .lock()
+                                .ok()
+                                .and_then(|mut slot| slot.take());
+                            match waiting {
+                                Some(reply) => {
+                                    let _ = reply.send(outcome);
+                                }
+                                None => debug!("ignoring stray self-test probe"),
+                            }
                             continue;
                         }
-                        let maybe_key = shared_state.room_key.lock().ok().and_then(|lock| *lock);
-                        let room_key = match maybe_key {
-                            Some(key) => key,
-                            None => {
-                                warn!("dropping message: room key not ready");
-                                continue;
-                            }
-                        };
-                        let event = match decrypt_clipboard_event(&room_key, &encrypted) {
-                            Ok(event) => event,
+                        let (event, signer_key, extensions) = match opened {
+                            Ok(Some(SessionEvent::Clipboard {
+                                event,
+                                verified_signer,
+                                extensions,
+                                ..
+                            })) => (event, verified_signer, extensions),
+                            Ok(_) => continue,
                             Err(err) => {
-                                warn!("decrypt failed: {err}");
+                                warn!("dropping event: {err}");
                                 continue;
                             }
                         };
+                        let language_hint = extensions
+                            .get(code_preview::LANGUAGE_EXTENSION)
+                            .and_then(|value| value.as_str())
+                            .map(str::to_owned);
+                        trace_protocol(&shared_state, &ui_event_tx, || {
+                            protocol_trace::summarize_event(&event, now_unix_ms())
+                        });
+
+                        if event.mime == MIME_TRANSFER_CANCEL_JSON {
+                            match serde_json::from_str::<TransferCancel>(&event.text_utf8) {
+                                Ok(cancel) => handle_transfer_cancel(
+                                    &config,
+                                    &ui_event_tx,
+                                    &shared_state,
+                                    &event.sender_device_id,
+                                    cancel,
+                                ),
+                                Err(err) => warn!("invalid transfer cancel: {err}"),
+                            }
+                            continue;
+                        }
+
+                        if !current_sync_direction(&shared_state).allows_receive() {
+                            debug!(mime = %event.mime, "ignoring inbound event: send-only mode");
+                            continue;
+                        }
+                        if sync_is_paused(&shared_state) {
+                            debug!(mime = %event.mime, "ignoring inbound event: sync paused");
+                            continue;
+                        }
 
                         if event.mime == MIME_TEXT_PLAIN {
                             let content_hash = sha256_bytes(event.text_utf8.as_bytes());
-                            let duplicate_of_last_apply = shared_state
-                                .last_applied_hash
+                            let mode = shared_state
+                                .duplicate_suppression
+                                .lock()
+                                .map(|guard| *guard)
+                                .unwrap_or_default();
+                            let last = shared_state
+                                .last_applied
                                 .lock()
                                 .ok()
-                                .and_then(|guard| *guard)
-                                .is_some_and(|last| last == content_hash);
-                            if duplicate_of_last_apply {
+                                .and_then(|guard| *guard);
+                            if dedupe::is_duplicate(mode, last, &content_hash, now_unix_ms()) {
+                                let _ = ui_event_tx.send(UiEvent::DuplicateSkipped {
+                                    sender_device_id: event.sender_device_id,
+                                    preview: preview_text(&event.text_utf8, 100),
+                                });
                                 continue;
                             }
                             let _ = ui_event_tx.send(UiEvent::LastReceived(now_unix_ms()));
@@ -2968,16 +11735,27 @@ mod windows_client {
                                 sender_device_id: event.sender_device_id,
                                 text: event.text_utf8,
                                 content_hash,
+                                signer_key,
+                                pinned: event.pinned,
+                                label: event.label.as_deref().and_then(normalize_event_label),
+                                language_hint,
                             });
                             continue;
                         }
 
-                        if event.mime == MIME_FILE_CHUNK_JSON_B64
+                        // Plain chunks come from clients that predate
+                        // per-transfer keys, and from any client while one
+                        // of those is in the room.
+                        let sealed = event.mime == MIME_FILE_CHUNK_SEALED_JSON_B64;
+                        if (sealed || event.mime == MIME_FILE_CHUNK_JSON_B64)
                             && let Ok(Some(completed)) = handle_file_chunk_event(
                                 &config,
                                 &ui_event_tx,
+                                &shared_state,
                                 event.sender_device_id,
                                 &event.text_utf8,
+                                sealed,
+                                signer_key,
                             )
                         {
                             let _ = ui_event_tx.send(UiEvent::LastReceived(now_unix_ms()));
@@ -2986,6 +11764,8 @@ mod windows_client {
                                 file_name: completed.file_name,
                                 temp_path: completed.temp_path,
                                 size_bytes: completed.size_bytes,
+                                signer_key: completed.signer_key,
+                                sha256: completed.sha256,
                             });
                         }
                     }
@@ -2994,296 +11774,1287 @@ mod windows_client {
         }
     }
 
-    async fn presence_task(
-        config: ClientConfig,
-        mut control_rx: mpsc::UnboundedReceiver<ControlMessage>,
-        ui_event_tx: RepaintingSender,
-        shared_state: SharedRuntimeState,
-    ) {
-        let mut peers: HashMap<String, PeerInfo> = HashMap::new();
-        peers.insert(
-            config.device_id.clone(),
-            PeerInfo {
-                device_id: config.device_id.clone(),
-                device_name: config.device_name.clone(),
-            },
-        );
-
-        while let Some(message) = control_rx.recv().await {
-            match message {
-                ControlMessage::PeerList(peer_list) => {
-                    peers.clear();
-                    for peer in peer_list.peers {
-                        peers.insert(peer.device_id.clone(), peer);
-                    }
-                    let _ = ui_event_tx.send(UiEvent::Peers(peers.values().cloned().collect()));
-                }
-                ControlMessage::PeerJoined(joined) => {
-                    peers.insert(joined.peer.device_id.clone(), joined.peer);
-                    let _ = ui_event_tx.send(UiEvent::Peers(peers.values().cloned().collect()));
-                }
-                ControlMessage::PeerLeft(left) => {
-                    peers.remove(&left.device_id);
-                    let _ = ui_event_tx.send(UiEvent::Peers(peers.values().cloned().collect()));
-                }
-                ControlMessage::SaltExchange(exchange) => {
-                    let room_key = match derive_room_key(&config.room_code, &exchange.device_ids) {
-                        Ok(key) => key,
-                        Err(err) => {
-                            warn!("room key derivation failed: {err}");
-                            continue;
-                        }
-                    };
-                    if let Ok(mut key_slot) = shared_state.room_key.lock() {
-                        *key_slot = Some(room_key);
+    async fn presence_task(
+        config: ClientConfig,
+        mut control_rx: mpsc::UnboundedReceiver<ControlMessage>,
+        ui_event_tx: RepaintingSender,
+        shared_state: SharedRuntimeState,
+    ) {
+        // The self-test's virtual peer is an implementation detail; keep it
+        // out of the peer list (and so out of the device registry).
+        let self_test_id = self_test::virtual_device_id(&config.device_id);
+        let local = PeerInfo {
+            device_id: config.device_id.clone(),
+            device_name: config.device_name.clone(),
+            sealed_chunks: true,
+        };
+
+        while let Some(message) = control_rx.recv().await {
+            let event = with_session(&shared_state, |session| {
+                session
+                    .handle_message(WireMessage::Control(message))
+                    .map_err(|err| err.to_string())
+            });
+            match event {
+                Ok(Some(SessionEvent::PeersChanged(others))) => {
+                    let peers = std::iter::once(local.clone())
+                        .chain(others.into_iter().filter(|p| p.device_id != self_test_id))
+                        .collect();
+                    let _ = ui_event_tx.send(UiEvent::Peers(peers));
+                }
+                Ok(Some(SessionEvent::RoomKeyReady)) => {
+                    info!("room key ready");
+                    let _ = ui_event_tx.send(UiEvent::RoomKeyReady(true));
+                    shared_state.deferred_ready.notify_one();
+                }
+                Ok(Some(SessionEvent::RelayError(message))) => {
+                    let _ = ui_event_tx.send(UiEvent::RuntimeError(message));
+                }
+                Ok(Some(SessionEvent::Maintenance(notice))) => {
+                    let _ = ui_event_tx.send(UiEvent::RelayMaintenance(notice));
+                }
+                Ok(Some(SessionEvent::ReconnectSoon(notice))) => {
+                    info!(
+                        at_unix_ms = notice.at_unix_ms,
+                        "relay asked for a reconnect"
+                    );
+                    if let Ok(mut slot) = shared_state.reconnect_notice.lock() {
+                        *slot = Some(notice);
+                    }
+                    shared_state.reconnect_soon.notify_one();
+                }
+                Ok(Some(SessionEvent::Clipboard { .. }) | None) => {}
+                Err(err) => warn!("control message not applied: {err}"),
+            }
+        }
+    }
+
+    async fn network_send_clipboard(
+        shared_state: &SharedRuntimeState,
+        network_send_tx: &mpsc::UnboundedSender<WireMessage>,
+        payload: EncryptedPayload,
+    ) {
+        if let Err(err) = network_send_tx.send(WireMessage::Encrypted(payload)) {
+            error!("network_send_clipboard channel closed: {err}");
+            return;
+        }
+        if let Ok(mut outbox) = shared_state.outbox.lock() {
+            outbox.frame_queued();
+        }
+    }
+
+    // ─── Self-test ─────────────────────────────────────────────────────────────
+
+    /// Run one loopback self-test and report the result to the UI.
+    async fn run_self_test(
+        config: ClientConfig,
+        ui_event_tx: RepaintingSender,
+        shared_state: SharedRuntimeState,
+    ) {
+        info!("self-test starting");
+        let report = self_test_session(&config, &shared_state).await;
+        if let Ok(mut slot) = shared_state.self_test_probe.lock() {
+            *slot = None;
+        }
+        info!(passed = report.passed(), "{}", report.summary());
+        let _ = ui_event_tx.send(UiEvent::SelfTestFinished(report));
+    }
+
+    /// Join the room as a virtual peer over a second connection, send an
+    /// encrypted probe and follow it through this device's normal receive
+    /// path.  Each step is bounded by `STEP_TIMEOUT`.
+    async fn self_test_session(
+        config: &ClientConfig,
+        shared_state: &SharedRuntimeState,
+    ) -> SelfTestReport {
+        const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+        const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+        let mut report = SelfTestReport::default();
+        let virtual_id = self_test::virtual_device_id(&config.device_id);
+
+        let (ws_stream, _) = match timeout(STEP_TIMEOUT, connect_async(&config.server_url)).await {
+            Ok(Ok(ok)) => ok,
+            Ok(Err(err)) => {
+                report.fail(SelfTestStep::Connect, err.to_string());
+                return report;
+            }
+            Err(_) => {
+                report.fail(SelfTestStep::Connect, "timed out");
+                return report;
+            }
+        };
+        report.pass(SelfTestStep::Connect);
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+
+        let virtual_peer = PeerInfo {
+            device_id: virtual_id,
+            device_name: self_test::VIRTUAL_DEVICE_NAME.to_owned(),
+            sealed_chunks: false,
+        };
+        // Millisecond timestamps keep the virtual peer's counter increasing
+        // across runs, so the receive side's replay check accepts it.
+        let mut session = match SessionState::new(&config.room_code, virtual_peer, now_unix_ms()) {
+            Ok(session) => session,
+            Err(err) => {
+                report.fail(SelfTestStep::JoinRoom, err.to_string());
+                return report;
+            }
+        };
+        if let Err(err) = send_self_test_frame(&mut ws_write, &session.hello()).await {
+            report.fail(SelfTestStep::JoinRoom, err);
+            return report;
+        }
+
+        // The relay announces the new member list to everyone, the virtual
+        // peer included; the list tells us both that the join worked and
+        // which salt the room key is now derived from.
+        let joined = timeout(STEP_TIMEOUT, wait_for_room_key(&mut ws_read, &mut session)).await;
+        let others = match joined {
+            Ok(Ok(others)) => others,
+            Ok(Err(err)) => {
+                report.fail(SelfTestStep::JoinRoom, err);
+                return report;
+            }
+            Err(_) => {
+                report.fail(SelfTestStep::JoinRoom, "timed out");
+                return report;
+            }
+        };
+        report.pass(SelfTestStep::JoinRoom);
+
+        if !others.iter().any(|p| p.device_id == config.device_id) {
+            report.fail(
+                SelfTestStep::KeyExchange,
+                "this device is not in the room's member list",
+            );
+            return report;
+        }
+        // The runtime re-derives its key from the same announcement; wait
+        // for it so the probe is not decrypted with the previous key.
+        let key_deadline = tokio::time::Instant::now() + STEP_TIMEOUT;
+        loop {
+            let same_key = shared_state
+                .session
+                .lock()
+                .map(|current| {
+                    current
+                        .as_ref()
+                        .is_some_and(|current| current.shares_room_key(&session))
+                })
+                .unwrap_or(false);
+            if same_key {
+                break;
+            }
+            if tokio::time::Instant::now() >= key_deadline {
+                report.fail(
+                    SelfTestStep::KeyExchange,
+                    "this device did not derive the same room key",
+                );
+                return report;
+            }
+            tokio::time::sleep(KEY_POLL_INTERVAL).await;
+        }
+        report.pass(SelfTestStep::KeyExchange);
+
+        let (probe_tx, probe_rx) = tokio::sync::oneshot::channel();
+        if let Ok(mut slot) = shared_state.self_test_probe.lock() {
+            *slot = Some(probe_tx);
+        }
+        let probe = self_test::probe_text(now_unix_ms());
+        let frame = match session.seal(self_test::MIME_SELF_TEST, probe.clone(), now_unix_ms()) {
+            Ok(frame) => frame,
+            Err(err) => {
+                report.fail(SelfTestStep::Deliver, format!("encryption failed: {err}"));
+                return report;
+            }
+        };
+        if let Err(err) = ws_write.send(Message::Binary(frame.into())).await {
+            report.fail(SelfTestStep::Deliver, err.to_string());
+            return report;
+        }
+
+        match timeout(STEP_TIMEOUT, probe_rx).await {
+            Err(_) => {
+                report.fail(SelfTestStep::Deliver, "probe not received in time");
+                return report;
+            }
+            Ok(Err(_)) => {
+                report.fail(SelfTestStep::Deliver, "connection ended");
+                return report;
+            }
+            Ok(Ok(Err(err))) => {
+                report.pass(SelfTestStep::Deliver);
+                report.fail(SelfTestStep::Decrypt, err);
+                return report;
+            }
+            Ok(Ok(Ok(text))) => {
+                report.pass(SelfTestStep::Deliver);
+                if text != probe {
+                    report.fail(SelfTestStep::Decrypt, "probe content mismatch");
+                    return report;
+                }
+                report.pass(SelfTestStep::Decrypt);
+            }
+        }
+        let _ = ws_write.send(Message::Close(None)).await;
+
+        let applied = tokio::task::spawn_blocking(move || apply_self_test_probe(&probe))
+            .await
+            .unwrap_or_else(|err| Err(err.to_string()));
+        match applied {
+            Ok(()) => report.pass(SelfTestStep::Apply),
+            Err(err) => report.fail(SelfTestStep::Apply, err),
+        }
+        report
+    }
+
+    /// Read frames into `session` until it has derived the room key, and
+    /// return the other devices in the room.
+    async fn wait_for_room_key(
+        ws_read: &mut futures::stream::SplitStream<
+            tokio_tungstenite::WebSocketStream<
+                tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+            >,
+        >,
+        session: &mut SessionState,
+    ) -> Result<Vec<PeerInfo>, String> {
+        // The relay sends the member list before the salt derived from it.
+        let mut others = Vec::new();
+        while let Some(next) = ws_read.next().await {
+            let data = match next {
+                Ok(Message::Binary(data)) => data,
+                Ok(_) => continue,
+                Err(err) => return Err(err.to_string()),
+            };
+            match session.handle_frame(&data) {
+                Ok(Some(SessionEvent::PeersChanged(peers))) => others = peers,
+                Ok(Some(SessionEvent::RoomKeyReady)) => return Ok(others),
+                Ok(Some(SessionEvent::RelayError(message))) => return Err(message),
+                _ => {}
+            }
+        }
+        Err("relay closed the connection (is the room full?)".to_owned())
+    }
+
+    /// `--simulate-peer`: keep a simulated device in the room for as long
+    /// as this runtime lives, rejoining after the connection drops.
+    async fn run_simulated_peer(config: ClientConfig) {
+        let device_id = simulated_peer::simulated_device_id(&config.device_id);
+        // Millisecond timestamps keep the counter increasing across
+        // reconnects and restarts, so receivers' replay checks accept it.
+        let mut counter = now_unix_ms();
+        let mut samples_sent = 0;
+        loop {
+            match simulated_peer_session(&config, &device_id, &mut counter, &mut samples_sent).await
+            {
+                Ok(()) => info!("simulated peer: relay closed the connection"),
+                Err(err) => warn!("simulated peer: {err}"),
+            }
+            tokio::time::sleep(simulated_peer::RECONNECT_DELAY).await;
+        }
+    }
+
+    /// One connection of the simulated peer: join, track the room key from
+    /// member announcements, echo received text and send samples.
+    async fn simulated_peer_session(
+        config: &ClientConfig,
+        device_id: &str,
+        counter: &mut u64,
+        samples_sent: &mut usize,
+    ) -> Result<(), String> {
+        let local = PeerInfo {
+            device_id: device_id.to_owned(),
+            device_name: simulated_peer::SIMULATED_DEVICE_NAME.to_owned(),
+            sealed_chunks: false,
+        };
+        let mut session =
+            SessionState::new(&config.room_code, local, *counter).map_err(|err| err.to_string())?;
+        let (ws_stream, _) = connect_async(&config.server_url)
+            .await
+            .map_err(|err| err.to_string())?;
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+        let hello = session.hello_frame().map_err(|err| err.to_string())?;
+        ws_write
+            .send(Message::Binary(hello.into()))
+            .await
+            .map_err(|err| err.to_string())?;
+        info!(device_id, "simulated peer joined the room");
+
+        let mut samples = tokio::time::interval(simulated_peer::SAMPLE_INTERVAL);
+        // The first tick completes at once; the first sample waits a period.
+        samples.tick().await;
+        loop {
+            let text = tokio::select! {
+                next = ws_read.next() => {
+                    let data = match next {
+                        None => return Ok(()),
+                        Some(Err(err)) => return Err(err.to_string()),
+                        Some(Ok(Message::Binary(data))) => data,
+                        Some(Ok(_)) => continue,
+                    };
+                    match session.handle_frame(&data) {
+                        Ok(Some(SessionEvent::Clipboard { event, .. }))
+                            if event.mime == MIME_TEXT_PLAIN =>
+                        {
+                            match simulated_peer::echo_reply(&event.text_utf8) {
+                                Some(reply) => reply,
+                                None => continue,
+                            }
+                        }
+                        Ok(Some(SessionEvent::RelayError(message))) => return Err(message),
+                        Ok(_) => continue,
+                        Err(err) => {
+                            debug!("simulated peer: cannot read frame: {err}");
+                            continue;
+                        }
+                    }
+                }
+                _ = samples.tick() => {
+                    let text = simulated_peer::sample_text(*samples_sent);
+                    *samples_sent += 1;
+                    text
+                }
+            };
+            if !session.room_key_ready() {
+                continue;
+            }
+            match session.seal_text(&text, now_unix_ms()) {
+                Ok(frame) => {
+                    *counter = session.counter();
+                    ws_write
+                        .send(Message::Binary(frame.into()))
+                        .await
+                        .map_err(|err| err.to_string())?;
+                }
+                // An echo of text near the size limit no longer fits.
+                Err(err) => warn!("simulated peer: not sending: {err}"),
+            }
+        }
+    }
+
+    /// Run the setup screen's connection test on its own thread and store
+    /// the outcome in `result`.
+    fn start_connection_test(
+        cfg: SavedClientConfig,
+        result: Arc<Mutex<Option<Result<String, String>>>>,
+        ctx: egui::Context,
+    ) {
+        let spawned = std::thread::Builder::new()
+            .name("connection-test".into())
+            .spawn(move || {
+                let outcome = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime.block_on(probe_connection(&cfg)),
+                    Err(err) => Err(format!("Could not start the test: {err}")),
+                };
+                match &outcome {
+                    Ok(message) => info!("connection test passed: {message}"),
+                    Err(message) => warn!("connection test failed: {message}"),
+                }
+                if let Ok(mut slot) = result.lock() {
+                    *slot = Some(outcome);
+                }
+                ctx.request_repaint();
+            });
+        if let Err(err) = spawned {
+            warn!("connection test thread failed to start: {err}");
+        }
+    }
+
+    /// Connect to the relay, join the room as this device and wait for the
+    /// relay's peer list, then disconnect.  Returns a message for the setup
+    /// screen either way.
+    async fn probe_connection(cfg: &SavedClientConfig) -> Result<String, String> {
+        const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+        let (ws_stream, _) = match timeout(STEP_TIMEOUT, connect_async(&cfg.server_url)).await {
+            Ok(Ok(ok)) => ok,
+            Ok(Err(err)) => return Err(format!("Could not connect to the relay: {err}")),
+            Err(_) => return Err("Timed out connecting to the relay".to_owned()),
+        };
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+        let local = PeerInfo {
+            device_id: stable_device_id(&cfg.device_name),
+            device_name: cfg.device_name.clone(),
+            sealed_chunks: true,
+        };
+        let mut session = SessionState::new(&cfg.room_code, local, 0)
+            .map_err(|err| format!("Could not join the room: {err}"))?;
+        if let Err(err) = send_self_test_frame(&mut ws_write, &session.hello()).await {
+            return Err(format!("Connected, but could not join the room: {err}"));
+        }
+
+        // The first member list after joining is the answer.
+        let joined = timeout(STEP_TIMEOUT, async {
+            while let Some(next) = ws_read.next().await {
+                let data = match next {
+                    Ok(Message::Binary(data)) => data,
+                    Ok(_) => continue,
+                    Err(err) => return Err(err.to_string()),
+                };
+                match session.handle_frame(&data) {
+                    Ok(Some(SessionEvent::PeersChanged(others))) => return Ok(others.len()),
+                    Ok(Some(SessionEvent::RelayError(message))) => return Err(message),
+                    _ => {}
+                }
+            }
+            Err("the relay closed the connection (is the room full?)".to_owned())
+        })
+        .await;
+        let _ = ws_write.close().await;
+        match joined {
+            Ok(Ok(0)) => Ok(
+                "Connected and joined the room. No other devices are in it yet \
+                             (check the room code if you expected some)."
+                    .to_owned(),
+            ),
+            Ok(Ok(others)) => Ok(format!(
+                "Connected and joined the room. {others} other device{} online.",
+                if others == 1 { " is" } else { "s are" }
+            )),
+            Ok(Err(err)) => Err(format!("Connected, but could not join the room: {err}")),
+            Err(_) => Err("Connected, but the relay did not answer the join request".to_owned()),
+        }
+    }
+
+    async fn send_self_test_frame(
+        ws_write: &mut futures::stream::SplitSink<
+            tokio_tungstenite::WebSocketStream<
+                tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+            >,
+            Message,
+        >,
+        message: &WireMessage,
+    ) -> Result<(), String> {
+        let frame = encode_frame(message).map_err(|err| err.to_string())?;
+        ws_write
+            .send(Message::Binary(frame.into()))
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    /// Put the probe on the clipboard the same way received text is applied,
+    /// read it back, then restore the previous text.
+    fn apply_self_test_probe(probe: &str) -> Result<(), String> {
+        let previous = Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .ok();
+        apply_clipboard_text(probe)?;
+        let read_back = Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|err| format!("read back failed: {err}"))?;
+        if let Some(previous) = previous {
+            let _ = apply_clipboard_text(&previous);
+        }
+        if read_back == probe {
+            Ok(())
+        } else {
+            Err("clipboard did not hold the probe".to_owned())
+        }
+    }
+
+    // ─── Shell integration ─────────────────────────────────────────────────────
+
+    /// Accept requests from other `cliprelay-client` processes: the
+    /// Explorer menu entry and SendTo shortcut, `--send-file`/`--send-text`
+    /// from scripts, and `--join-link` for `cliprelay://` links (see
+    /// [`instance`]).  Requests carry the automation token, like the
+    /// automation pipe, whether or not the automation API is enabled, and
+    /// the pipe admits only this user (see [`pipe_security`]).
+    async fn instance_server_task(token: String, ui_event_tx: RepaintingSender) {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let pipe_name = instance_pipe_name();
+        let mut security = match OwnerOnly::new() {
+            Ok(security) => security,
+            Err(err) => {
+                warn!(pipe = %pipe_name, "instance pipe unavailable: {err}");
+                return;
+            }
+        };
+        // SAFETY: `security` outlives every pipe created from it.
+        let mut server = match unsafe {
+            ServerOptions::new()
+                .first_pipe_instance(true)
+                .reject_remote_clients(true)
+                .create_with_security_attributes_raw(&pipe_name, security.as_raw())
+        } {
+            Ok(server) => server,
+            Err(err) => {
+                // Typically another instance (e.g. a second profile)
+                // already owns the pipe.
+                warn!(pipe = %pipe_name, "instance pipe unavailable: {err}");
+                return;
+            }
+        };
+
+        loop {
+            if let Err(err) = server.connect().await {
+                warn!("instance pipe connect failed: {err}");
+                return;
+            }
+            let connected = server;
+            // SAFETY: as above.
+            server = match unsafe {
+                ServerOptions::new()
+                    .reject_remote_clients(true)
+                    .create_with_security_attributes_raw(&pipe_name, security.as_raw())
+            } {
+                Ok(server) => server,
+                Err(err) => {
+                    warn!("instance pipe re-create failed: {err}");
+                    return;
+                }
+            };
+
+            let token = token.clone();
+            let ui_event_tx = ui_event_tx.clone();
+            tokio::spawn(async move {
+                if let Err(err) = serve_instance_client(connected, &token, &ui_event_tx).await {
+                    debug!("instance client ended: {err}");
+                }
+            });
+        }
+    }
+
+    /// Read one request, pass it to the UI loop and write its answer.  A
+    /// request with the wrong token is refused before its body is read.
+    async fn serve_instance_client(
+        pipe: tokio::net::windows::named_pipe::NamedPipeServer,
+        token: &str,
+        ui_event_tx: &RepaintingSender,
+    ) -> io::Result<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        /// How long to wait for the UI loop to answer before giving up.
+        const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+        let (read_half, mut write_half) = tokio::io::split(pipe);
+        let mut reader = BufReader::new(read_half);
+        let mut header = String::new();
+        (&mut reader)
+            .take(instance::MAX_LINE_BYTES as u64)
+            .read_line(&mut header)
+            .await?;
+        let request = match instance::parse_header(&header) {
+            Some((_, _, given)) if !automation::tokens_match(given, token) => Err("invalid token"),
+            Some((kind, len, _)) => {
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).await?;
+                String::from_utf8(body)
+                    .ok()
+                    .and_then(|body| InstanceRequest::decode(kind, &body))
+                    .ok_or("malformed request")
+            }
+            None => Err("malformed request"),
+        };
+        let result = match request {
+            Err(message) => {
+                debug!("refusing instance request: {message}");
+                Err(message.to_owned())
+            }
+            Ok(request) => {
+                let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+                if ui_event_tx
+                    .send(UiEvent::InstanceRequest {
+                        request,
+                        reply: reply_tx,
+                    })
+                    .is_err()
+                {
+                    Err("ClipRelay is shutting down".to_owned())
+                } else {
+                    match timeout(REPLY_TIMEOUT, reply_rx).await {
+                        Ok(Ok(result)) => result,
+                        _ => Err("ClipRelay did not respond in time".to_owned()),
                     }
-                    info!("room key ready");
-                    let _ = ui_event_tx.send(UiEvent::RoomKeyReady(true));
-                }
-                ControlMessage::Error { message } => {
-                    let _ = ui_event_tx.send(UiEvent::RuntimeError(message));
                 }
-                ControlMessage::Hello(_) => {}
+            }
+        };
+        write_half
+            .write_all(instance::reply_line(&result).as_bytes())
+            .await?;
+        write_half.flush().await
+    }
+
+    fn instance_pipe_name() -> String {
+        instance::pipe_name(&current_user_name())
+    }
+
+    /// Hand `request` to the running client, with the automation token.
+    fn forward_to_running_client(request: &InstanceRequest) -> Result<(), ForwardError> {
+        let token = automation::load_or_create_token().map_err(ForwardError::Io)?;
+        instance::forward(&instance_pipe_name(), &token, request)
+    }
+
+    fn show_warning_box(caption: &str, message: &str) {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{MB_ICONWARNING, MB_OK, MessageBoxW};
+
+        let text = to_wide_null(message);
+        let caption = to_wide_null(caption);
+        unsafe { MessageBoxW(0, text.as_ptr(), caption.as_ptr(), MB_OK | MB_ICONWARNING) };
+    }
+
+    /// `--join-link`: hand the link to the running client.  Returns the
+    /// link when no client is running, so this process asks instead.
+    fn forward_join_link(raw: &str) -> Option<JoinLink> {
+        let link = match join_link::parse(raw) {
+            Ok(link) => link,
+            Err(err) => {
+                warn!("--join-link rejected: {err}");
+                show_warning_box(
+                    "ClipRelay",
+                    &format!("This ClipRelay link is invalid: {err}."),
+                );
+                return None;
+            }
+        };
+        match forward_to_running_client(&InstanceRequest::OpenLink(raw.to_owned())) {
+            Ok(()) => None,
+            Err(ForwardError::NotRunning) => Some(link),
+            Err(err) => {
+                warn!("--join-link forward failed: {err}");
+                Some(link)
             }
         }
     }
 
-    async fn network_send_clipboard(
-        network_send_tx: &mpsc::UnboundedSender<WireMessage>,
-        payload: EncryptedPayload,
-    ) {
-        if let Err(err) = network_send_tx.send(WireMessage::Encrypted(payload)) {
-            error!("network_send_clipboard channel closed: {err}");
+    // ─── One-shot sends ────────────────────────────────────────────────────────
+
+    /// Exit codes of `--send-file` / `--send-text` and `--apply-and-exit`.
+    /// 2 is also what a command line clap rejects exits with.
+    const EXIT_OK: i32 = 0;
+    const EXIT_FAILED: i32 = 1;
+    const EXIT_USAGE: i32 = 2;
+    const EXIT_NO_ROOM: i32 = 3;
+
+    /// The send asked for with `--send-file` / `--send-text`, if any.
+    fn one_shot_request(args: &ClientArgs) -> Result<Option<InstanceRequest>, String> {
+        if let Some(text) = &args.send_text {
+            let text = if text == "-" {
+                let mut input = String::new();
+                io::Read::read_to_string(&mut io::stdin(), &mut input)
+                    .map_err(|err| format!("could not read standard input: {err}"))?;
+                input
+            } else {
+                text.clone()
+            };
+            if text.trim().is_empty() {
+                return Err("nothing to send: the text is empty".to_owned());
+            }
+            return Ok(Some(InstanceRequest::SendText(text)));
         }
+        if args.send_file.is_empty() {
+            return Ok(None);
+        }
+        let paths = args
+            .send_file
+            .iter()
+            .map(|path| {
+                std::path::absolute(path).map_err(|err| format!("{}: {err}", path.display()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(InstanceRequest::SendFiles(paths)))
     }
 
-    // ─── File transfer ─────────────────────────────────────────────────────────
+    /// Whether errors can be written to stderr: it was redirected, or the
+    /// calling shell's console could be attached.  This is a GUI-subsystem
+    /// binary, so it gets no console of its own.
+    fn attach_stderr() -> bool {
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+        use windows_sys::Win32::System::Console::{
+            ATTACH_PARENT_PROCESS, AttachConsole, GetStdHandle, STD_ERROR_HANDLE,
+        };
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    struct FileChunkEnvelope {
-        transfer_id: String,
-        file_name: String,
-        total_size: u64,
-        chunk_index: u32,
-        total_chunks: u32,
-        chunk_b64: String,
+        let handle = unsafe { GetStdHandle(STD_ERROR_HANDLE) };
+        if handle != 0 && handle != INVALID_HANDLE_VALUE {
+            return true;
+        }
+        unsafe { AttachConsole(ATTACH_PARENT_PROCESS) != 0 }
     }
 
-    #[derive(Debug)]
-    struct CompletedFile {
-        sender_device_id: String,
-        file_name: String,
-        temp_path: PathBuf,
-        size_bytes: u64,
+    /// Report a one-shot error on stderr, or in a message box when started
+    /// from Explorer.
+    fn report_one_shot_error(message: &str) {
+        warn!("one-shot send failed: {message}");
+        if attach_stderr() {
+            eprintln!("cliprelay-client: {message}");
+        } else {
+            show_warning_box(shell_menu::VERB_LABEL, message);
+        }
     }
 
-    #[derive(Debug)]
-    struct InflightTransfer {
-        sender_device_id: String,
-        file_name: String,
-        total_size: u64,
-        total_chunks: u32,
-        received: Vec<Option<Vec<u8>>>,
-        last_update_ms: u64,
+    /// `--send-file` / `--send-text`: hand the send to the running client,
+    /// or send it from this process when there is none.  Returns the exit
+    /// code.
+    fn run_one_shot(request: InstanceRequest) -> i32 {
+        let outcome = match forward_to_running_client(&request) {
+            Ok(()) => {
+                info!(kind = request.kind(), "handed to the running client");
+                Ok(())
+            }
+            Err(ForwardError::NotRunning) => send_without_running_client(request),
+            Err(err) => Err((EXIT_FAILED, err.to_string())),
+        };
+        match outcome {
+            Ok(()) => EXIT_OK,
+            Err((code, message)) => {
+                report_one_shot_error(&message);
+                code
+            }
+        }
     }
 
-    async fn send_file_v1(
-        path: &Path,
-        config: &ClientConfig,
-        shared_state: &SharedRuntimeState,
-        network_send_tx: &mpsc::UnboundedSender<WireMessage>,
-        counter: &mut u64,
-        ui_event_tx: &RepaintingSender,
-    ) -> Result<(), String> {
-        let path = path.to_path_buf();
-        let max_bytes = max_file_bytes();
-
-        let (file_name, data) = tokio::task::spawn_blocking(move || {
-            let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
-            if meta.len() == 0 {
-                return Err("file is empty".to_string());
+    fn send_without_running_client(request: InstanceRequest) -> Result<(), (i32, String)> {
+        let saved = match load_saved_config() {
+            Ok(Some(saved)) => saved,
+            Ok(None) => {
+                return Err((
+                    EXIT_NO_ROOM,
+                    "ClipRelay is not running and no room is set up. \
+                     Start it and join a room first."
+                        .to_owned(),
+                ));
             }
-            if meta.len() > max_bytes {
-                return Err(format!(
-                    "file too large ({} bytes); limit is {max_bytes} bytes",
-                    meta.len()
+            Err(err) => {
+                return Err((
+                    EXIT_NO_ROOM,
+                    format!("could not load the saved room: {err}"),
                 ));
             }
-            let name = path
-                .file_name()
-                .and_then(|s| s.to_str())
-                .ok_or_else(|| "invalid file name".to_string())?
-                .to_string();
-            let data = std::fs::read(&path).map_err(|e| e.to_string())?;
-            Ok::<_, String>((name, data))
-        })
-        .await
-        .map_err(|e| e.to_string())??;
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| (EXIT_FAILED, format!("could not start: {err}")))?;
+        runtime
+            .block_on(one_shot_session(&saved, request))
+            .map_err(|message| (EXIT_FAILED, message))
+    }
 
-        let room_key = shared_state.room_key.lock().ok().and_then(|lock| *lock);
-        let room_key = room_key.ok_or_else(|| "room key not ready".to_string())?;
+    /// Join the saved room as this device just long enough to send
+    /// `request`, with the same limits and sync direction as the running
+    /// client.  Fails when no other device is in the room to receive it.
+    async fn one_shot_session(
+        saved: &SavedClientConfig,
+        request: InstanceRequest,
+    ) -> Result<(), String> {
+        const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+        const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
 
-        let transfer_id = {
-            let digest = Sha256::digest(
-                format!("{}:{}:{}", config.device_id, now_unix_ms(), file_name).as_bytes(),
-            );
-            hex::encode(&digest[..16])
+        let commands = match request {
+            InstanceRequest::SendFiles(paths) => {
+                if let Some(path) = paths.iter().find(|path| !path.is_file()) {
+                    return Err(format!("not a file: {}", path.display()));
+                }
+                paths
+                    .into_iter()
+                    .map(|path| RuntimeCommand::SendFile(outbox::next_send_id(), path))
+                    .collect()
+            }
+            InstanceRequest::SendText(text) => {
+                if let Some(refusal) = secret_refusal(load_ui_state_logged().secret_check, &text) {
+                    return Err(refusal);
+                }
+                vec![RuntimeCommand::SendText(
+                    outbox::next_send_id(),
+                    text.into(),
+                )]
+            }
+            InstanceRequest::OpenLink(_) => {
+                return Err("links need the ClipRelay window".to_owned());
+            }
+            InstanceRequest::ReloadConfig => return Err("nothing to send".to_owned()),
         };
 
-        let total_size = u64::try_from(data.len()).map_err(|_| "file too large".to_string())?;
-        let total_chunks = data.len().div_ceil(FILE_CHUNK_RAW_BYTES) as u32;
-        if total_chunks == 0 {
-            return Err("file produced no chunks".to_string());
-        }
-        if total_chunks > MAX_TOTAL_CHUNKS {
-            return Err(format!("file needs too many chunks ({total_chunks})"));
+        let room_id = room_id_from_code(&saved.room_code);
+        let config = ClientConfig {
+            initial_counter: load_send_counter(&room_id),
+            room_id,
+            server_url: saved.server_url.clone(),
+            room_code: saved.room_code.clone(),
+            device_name: saved.device_name.clone(),
+            device_id: stable_device_id(&saved.device_name),
+            background: true,
+        };
+        let identity = identity::load_or_create_identity()
+            .map_err(|err| format!("could not load the device identity: {err}"))?;
+        let shared_state = SharedRuntimeState::new(&load_ui_state_logged(), Arc::new(identity));
+        if !outbound_allowed(&shared_state) {
+            return Err("this device is set to receive only".to_owned());
         }
 
-        let engine = base64::engine::general_purpose::STANDARD;
-        for chunk_index in 0..total_chunks {
-            let start = (chunk_index as usize) * FILE_CHUNK_RAW_BYTES;
-            let end = ((chunk_index as usize) + 1) * FILE_CHUNK_RAW_BYTES;
-            let end = end.min(data.len());
-            let raw = &data[start..end];
-            let chunk_b64 = engine.encode(raw);
-
-            let env = FileChunkEnvelope {
-                transfer_id: transfer_id.clone(),
-                file_name: file_name.clone(),
-                total_size,
-                chunk_index,
-                total_chunks,
-                chunk_b64,
+        let (ws_stream, _) = match timeout(STEP_TIMEOUT, connect_async(&config.server_url)).await {
+            Ok(Ok(ok)) => ok,
+            Ok(Err(err)) => return Err(format!("could not connect to the relay: {err}")),
+            Err(_) => return Err("timed out connecting to the relay".to_owned()),
+        };
+        let (mut ws_write, mut ws_read) = ws_stream.split();
+        let mut session = new_session(&config, &shared_state, config.initial_counter)?;
+        send_self_test_frame(&mut ws_write, &session.hello())
+            .await
+            .map_err(|err| format!("could not join the room: {err}"))?;
+        let others =
+            match timeout(STEP_TIMEOUT, wait_for_room_key(&mut ws_read, &mut session)).await {
+                Ok(Ok(others)) => others,
+                Ok(Err(err)) => return Err(format!("could not join the room: {err}")),
+                Err(_) => return Err("the relay did not answer the join request".to_owned()),
             };
+        if others.is_empty() {
+            let _ = ws_write.close().await;
+            return Err("no other devices are in the room".to_owned());
+        }
+        if let Ok(mut slot) = shared_state.session.lock() {
+            *slot = Some(session);
+        }
+
+        // No window: events only collect errors.
+        let (ui_tx, ui_rx) = std::sync::mpsc::channel();
+        let ui_event_tx = RepaintingSender {
+            tx: ui_tx,
+            ctx: egui::Context::default(),
+        };
+        let (network_send_tx, mut network_send_rx) = mpsc::unbounded_channel();
+        let mut counter = config.initial_counter;
+        for command in commands {
+            run_command(
+                command,
+                &mut counter,
+                &config,
+                &shared_state,
+                &network_send_tx,
+                &ui_event_tx,
+            )
+            .await;
+        }
+        drop(network_send_tx);
+        let errors: Vec<String> = ui_rx
+            .try_iter()
+            .filter_map(|event| match event {
+                UiEvent::RuntimeError(message) => Some(message),
+                _ => None,
+            })
+            .collect();
 
-            let text_utf8 = serde_json::to_string(&env).map_err(|e| e.to_string())?;
-            if text_utf8.len() > MAX_CLIPBOARD_TEXT_BYTES {
-                return Err("chunk envelope exceeds max size".to_string());
+        let mut sent = Ok(());
+        while let Some(message) = network_send_rx.recv().await {
+            if let Err(err) = send_self_test_frame(&mut ws_write, &message).await {
+                sent = Err(format!("send failed: {err}"));
+                break;
             }
+        }
+        // Close cleanly so the relay reads every frame before the
+        // connection goes away.
+        let _ = ws_write.close().await;
+        let _ = timeout(CLOSE_TIMEOUT, async {
+            while let Some(Ok(_)) = ws_read.next().await {}
+        })
+        .await;
 
-            *counter = counter.saturating_add(1);
-            let plaintext = ClipboardEventPlaintext {
-                sender_device_id: config.device_id.clone(),
-                counter: *counter,
-                timestamp_unix_ms: now_unix_ms(),
-                mime: MIME_FILE_CHUNK_JSON_B64.to_owned(),
-                text_utf8,
-            };
-            let payload =
-                encrypt_clipboard_event(&room_key, &plaintext).map_err(|e| e.to_string())?;
-            network_send_clipboard(network_send_tx, payload).await;
+        sent?;
+        match errors.as_slice() {
+            [] => Ok(()),
+            errors => Err(errors.join("; ")),
+        }
+    }
 
-            if chunk_index + 1 < total_chunks {
-                tokio::time::sleep(CHUNK_PACING).await;
+    // ─── Room settings from the command line ───────────────────────────────────
+
+    /// `--apply-and-exit`: check the `--set-*` values against the saved
+    /// config, save the result and ask the running client (if any) to
+    /// reconnect.  Returns the exit code.
+    fn run_apply_settings(args: &ClientArgs) -> i32 {
+        match apply_settings(args) {
+            Ok(()) => EXIT_OK,
+            Err((code, message)) => {
+                report_one_shot_error(&message);
+                code
             }
         }
+    }
 
-        let _ = ui_event_tx.send(UiEvent::LastSent(now_unix_ms()));
-        Ok(())
+    fn apply_settings(args: &ClientArgs) -> Result<(), (i32, String)> {
+        if args.set_room_code.is_none() && args.set_server_url.is_none() {
+            return Err((
+                EXIT_USAGE,
+                "--apply-and-exit needs --set-room-code and/or --set-server-url".to_owned(),
+            ));
+        }
+        let saved = load_saved_config()
+            .map_err(|err| (EXIT_FAILED, format!("could not load the saved room: {err}")))?;
+        let room_code = match (&args.set_room_code, &saved) {
+            (Some(code), _) => code.clone(),
+            (None, Some(saved)) => saved.room_code.clone(),
+            (None, None) => {
+                return Err((
+                    EXIT_NO_ROOM,
+                    "no room is set up; pass --set-room-code as well".to_owned(),
+                ));
+            }
+        };
+        // Trimmed as `save_saved_config` would, so that what is checked here
+        // is what gets saved.
+        let cfg = SavedClientConfig {
+            server_url: args
+                .set_server_url
+                .as_deref()
+                .or(saved.as_ref().map(|saved| saved.server_url.as_str()))
+                .unwrap_or(&args.server_url)
+                .trim()
+                .to_owned(),
+            room_code: room_code.trim().to_owned(),
+            device_name: saved
+                .as_ref()
+                .map_or(args.client_name.as_str(), |saved| &saved.device_name)
+                .trim()
+                .to_owned(),
+        };
+        validate_saved_config(&cfg).map_err(|message| (EXIT_USAGE, message))?;
+        save_saved_config(&cfg)
+            .map_err(|err| (EXIT_FAILED, format!("could not save the room: {err}")))?;
+        info!(server = %cfg.server_url, "room settings saved from the command line");
+
+        match forward_to_running_client(&InstanceRequest::ReloadConfig) {
+            Ok(()) | Err(ForwardError::NotRunning) => Ok(()),
+            Err(err) => Err((
+                EXIT_FAILED,
+                format!("saved, but the running client did not reconnect: {err}"),
+            )),
+        }
     }
 
-    fn handle_file_chunk_event(
-        _config: &ClientConfig,
-        _ui_event_tx: &RepaintingSender,
-        sender_device_id: String,
-        text_utf8: &str,
-    ) -> Result<Option<CompletedFile>, String> {
-        use std::sync::OnceLock;
-
-        static TRANSFERS: OnceLock<Mutex<HashMap<String, InflightTransfer>>> = OnceLock::new();
-        let transfers = TRANSFERS.get_or_init(|| Mutex::new(HashMap::new()));
-
-        let env: FileChunkEnvelope = serde_json::from_str(text_utf8).map_err(|e| e.to_string())?;
-        if env.transfer_id.trim().is_empty()
-            || env.total_chunks == 0
-            || env.total_chunks > MAX_TOTAL_CHUNKS
-            || env.chunk_index >= env.total_chunks
-            || env.total_size == 0
-            || env.total_size > max_file_bytes()
+    // ─── Diagnostics ───────────────────────────────────────────────────────────
+
+    /// `--diagnose`: run every check, write the redacted report and say
+    /// where it is.  Exits 0 when no check failed.
+    fn run_diagnose(args: &ClientArgs) -> i32 {
+        info!("diagnostics starting");
+        let ui_state = load_ui_state_logged();
+        let mut report = DiagnosticReport::default();
+
+        let saved = match load_saved_config() {
+            Ok(Some(saved)) => {
+                report.set(
+                    Check::Config,
+                    CheckOutcome::Passed(format!(
+                        "room code of {} characters, relay {}",
+                        saved.room_code.chars().count(),
+                        relay_display(&saved.server_url)
+                    )),
+                );
+                Some(saved)
+            }
+            Ok(None) => {
+                report.set(
+                    Check::Config,
+                    CheckOutcome::Failed("no room is set up".to_owned()),
+                );
+                None
+            }
+            Err(err) => {
+                report.set(Check::Config, CheckOutcome::Failed(err));
+                None
+            }
+        };
+        let server_url = saved
+            .as_ref()
+            .map_or(args.server_url.as_str(), |saved| saved.server_url.as_str());
+        match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
         {
-            return Ok(None);
+            Ok(runtime) => runtime.block_on(diagnose_relay(server_url, &mut report)),
+            Err(err) => report.set(
+                Check::Dns,
+                CheckOutcome::Failed(format!("could not start: {err}")),
+            ),
         }
-
-        let engine = base64::engine::general_purpose::STANDARD;
-        let chunk = engine
-            .decode(env.chunk_b64.as_bytes())
-            .map_err(|e| e.to_string())?;
-        if chunk.is_empty() {
-            return Ok(None);
+        report.set(Check::Hotkeys, diagnose_hotkeys(&ui_state));
+        report.set(Check::Clipboard, diagnose_clipboard());
+        report.set(Check::Folders, diagnose_folders(&ui_state));
+
+        let ts = now_unix_ms();
+        let environment = [
+            (
+                "Profile",
+                config_profiles::active().unwrap_or_else(|| "Default".to_owned()),
+            ),
+            ("Relay", relay_display(server_url)),
+            ("Config", client_config_path().display().to_string()),
+        ];
+        let log_lines =
+            crash_report::last_log_lines(&active_log_path(), diagnose::REPORT_LOG_LINES)
+                .unwrap_or_default();
+        let user = current_user_name();
+        let host = std::env::var("COMPUTERNAME").unwrap_or_default();
+        let mut secrets = vec![user.as_str(), host.as_str(), args.client_name.as_str()];
+        if let Some(saved) = &saved {
+            secrets.extend([saved.room_code.as_str(), saved.device_name.as_str()]);
         }
+        let text = diagnose::format_report(&report, ts, &environment, &log_lines, &secrets);
+        info!("{}", report.summary());
 
-        let now = now_unix_ms();
-        let key = format!("{sender_device_id}:{}", env.transfer_id);
-        let mut guard = transfers
-            .lock()
-            .map_err(|_| "transfer map poisoned".to_string())?;
+        let path = match diagnose::write_report(&diagnose::reports_dir(), ts, &text) {
+            Ok(path) => path,
+            Err(err) => {
+                report_one_shot_error(&format!("could not write the report: {err}"));
+                return EXIT_FAILED;
+            }
+        };
+        if attach_stderr() {
+            for line in report.lines() {
+                eprintln!("{line}");
+            }
+            eprintln!("{}\nReport: {}", report.summary(), path.display());
+        } else if let Err(err) = std::process::Command::new("explorer.exe")
+            .arg(format!("/select,{}", path.display()))
+            .spawn()
+        {
+            warn!("failed to open the diagnostic report: {err}");
+        }
+        if report.failures() == 0 {
+            EXIT_OK
+        } else {
+            EXIT_FAILED
+        }
+    }
 
-        guard.retain(|_, t| now.saturating_sub(t.last_update_ms) <= TRANSFER_TIMEOUT_MS);
-        if !guard.contains_key(&key) && guard.len() >= MAX_INFLIGHT_TRANSFERS {
-            return Ok(None);
+    /// `server_url` without credentials, query or fragment.
+    fn relay_display(server_url: &str) -> String {
+        match Url::parse(server_url) {
+            Ok(mut url) => {
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                url.set_query(None);
+                url.set_fragment(None);
+                url.to_string()
+            }
+            Err(_) => "(invalid URL)".to_owned(),
         }
+    }
 
-        let entry = guard.entry(key).or_insert_with(|| InflightTransfer {
-            sender_device_id: sender_device_id.clone(),
-            file_name: sanitize_file_name(&env.file_name),
-            total_size: env.total_size,
-            total_chunks: env.total_chunks,
-            received: vec![None; env.total_chunks as usize],
-            last_update_ms: now,
-        });
+    /// Resolve, connect to and upgrade a connection to the relay, one step
+    /// per check.  Nothing is sent once the WebSocket is open.
+    async fn diagnose_relay(server_url: &str, report: &mut DiagnosticReport) {
+        use tokio_tungstenite::tungstenite::Error as WsError;
 
-        if entry.total_chunks != env.total_chunks || entry.total_size != env.total_size {
-            return Ok(None);
-        }
-        entry.last_update_ms = now;
+        const STEP_TIMEOUT: Duration = Duration::from_secs(10);
 
-        if entry.received[env.chunk_index as usize].is_none() {
-            entry.received[env.chunk_index as usize] = Some(chunk);
+        let url = match Url::parse(server_url) {
+            Ok(url) => url,
+            Err(err) => {
+                report.set(
+                    Check::Dns,
+                    CheckOutcome::Failed(format!("invalid relay URL: {err}")),
+                );
+                return;
+            }
+        };
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            report.set(
+                Check::Dns,
+                CheckOutcome::Failed("the relay URL has no host".to_owned()),
+            );
+            return;
+        };
+        let addrs: Vec<std::net::SocketAddr> =
+            match timeout(STEP_TIMEOUT, tokio::net::lookup_host((host, port))).await {
+                Ok(Ok(addrs)) => addrs.collect(),
+                Ok(Err(err)) => {
+                    report.set(Check::Dns, CheckOutcome::Failed(err.to_string()));
+                    return;
+                }
+                Err(_) => {
+                    report.set(Check::Dns, CheckOutcome::Failed("timed out".to_owned()));
+                    return;
+                }
+            };
+        if addrs.is_empty() {
+            report.set(
+                Check::Dns,
+                CheckOutcome::Failed(format!("{host} has no addresses")),
+            );
+            return;
         }
+        let listed: Vec<String> = addrs.iter().map(|addr| addr.ip().to_string()).collect();
+        report.set(
+            Check::Dns,
+            CheckOutcome::Passed(format!("{host} is {}", listed.join(", "))),
+        );
 
-        if entry.received.iter().any(|c| c.is_none()) {
-            return Ok(None);
+        let started = std::time::Instant::now();
+        let tcp = match timeout(STEP_TIMEOUT, tokio::net::TcpStream::connect(&addrs[..])).await {
+            Ok(Ok(tcp)) => tcp,
+            Ok(Err(err)) => {
+                report.set(Check::Tcp, CheckOutcome::Failed(err.to_string()));
+                return;
+            }
+            Err(_) => {
+                report.set(Check::Tcp, CheckOutcome::Failed("timed out".to_owned()));
+                return;
+            }
+        };
+        let peer = tcp
+            .peer_addr()
+            .map_or_else(|_| format!("port {port}"), |addr| addr.to_string());
+        report.set(
+            Check::Tcp,
+            CheckOutcome::Passed(format!("{peer} in {} ms", started.elapsed().as_millis())),
+        );
+
+        let uses_tls = url.scheme() == "wss";
+        let tls_passed = || {
+            if uses_tls {
+                CheckOutcome::Passed("certificate accepted".to_owned())
+            } else {
+                CheckOutcome::Warning(
+                    "ws:// relay: the connection is not TLS-protected \
+                     (clipboard data is still end-to-end encrypted)"
+                        .to_owned(),
+                )
+            }
+        };
+        match timeout(
+            STEP_TIMEOUT,
+            tokio_tungstenite::client_async_tls(server_url, tcp),
+        )
+        .await
+        {
+            Ok(Ok((mut ws, response))) => {
+                report.set(Check::Tls, tls_passed());
+                report.set(
+                    Check::WebSocket,
+                    CheckOutcome::Passed(format!("HTTP {}", response.status())),
+                );
+                let _ = ws.close(None).await;
+            }
+            Ok(Err(WsError::Tls(err))) => {
+                report.set(Check::Tls, CheckOutcome::Failed(err.to_string()));
+                report.set(
+                    Check::WebSocket,
+                    CheckOutcome::Skipped("TLS failed".to_owned()),
+                );
+            }
+            Ok(Err(err)) => {
+                report.set(Check::Tls, tls_passed());
+                report.set(Check::WebSocket, CheckOutcome::Failed(err.to_string()));
+            }
+            Err(_) => {
+                report.set(
+                    Check::Tls,
+                    CheckOutcome::Skipped("the handshake timed out".to_owned()),
+                );
+                report.set(
+                    Check::WebSocket,
+                    CheckOutcome::Failed("timed out".to_owned()),
+                );
+            }
         }
+    }
 
-        let mut out: Vec<u8> = Vec::with_capacity(entry.total_size as usize);
-        for bytes in entry.received.iter().flatten() {
-            out.extend_from_slice(bytes);
+    /// Register and release each configured hotkey.  A running client
+    /// holds its own hotkeys, so a clash is only a warning.
+    fn diagnose_hotkeys(ui_state: &SavedUiState) -> CheckOutcome {
+        let Ok(manager) = GlobalHotKeyManager::new() else {
+            return CheckOutcome::Failed("the global hotkey system is unavailable".to_owned());
+        };
+        let hotkeys = [
+            (
+                "show/hide",
+                Some(ui_state.hotkey.as_deref().unwrap_or(DEFAULT_HOTKEY_LABEL)),
+            ),
+            (
+                "quick pick",
+                Some(
+                    ui_state
+                        .quick_pick_hotkey
+                        .as_deref()
+                        .unwrap_or(DEFAULT_QUICK_PICK_HOTKEY_LABEL),
+                ),
+            ),
+            ("panic", ui_state.panic_hotkey.as_deref()),
+        ];
+        let mut free = Vec::new();
+        let mut taken = Vec::new();
+        for (name, label) in hotkeys {
+            let Some((label, hk)) = label.and_then(|l| parse_hotkey_label(l).map(|hk| (l, hk)))
+            else {
+                continue;
+            };
+            match manager.register(hk) {
+                Ok(()) => {
+                    let _ = manager.unregister(hk);
+                    free.push(format!("{name} {label}"));
+                }
+                Err(err) => taken.push(format!("{name} {label}: {err}")),
+            }
         }
-        if out.len() as u64 != entry.total_size {
-            return Ok(None);
+        match (free.is_empty(), taken.is_empty()) {
+            (true, true) => CheckOutcome::Passed("no hotkeys set".to_owned()),
+            (_, true) => CheckOutcome::Passed(format!("{} available", free.join(", "))),
+            _ => CheckOutcome::Warning(format!(
+                "{} (expected while ClipRelay is running)",
+                taken.join("; ")
+            )),
         }
+    }
 
-        // Extract the fields we need for the result, then remove the entry
-        // from the map and drop the lock BEFORE writing the temp file.
-        //
-        // Previous code wrote the file while holding the lock, which:
-        //   (a) blocked all other incoming chunks for the entire write duration, and
-        //   (b) left the entry in the map if `write_incoming_temp_file` failed,
-        //       holding up to `total_size` bytes until the TRANSFER_TIMEOUT_MS
-        //       expiry (120 s).
-        let transfer_key = format!("{}:{}", sender_device_id, env.transfer_id);
-        let (sender_id, file_name, total_size) = {
-            let e = guard.remove(&transfer_key);
-            match e {
-                Some(t) => (t.sender_device_id, t.file_name, t.total_size),
-                None => return Ok(None), // already removed (shouldn't happen)
-            }
-        };
-        drop(guard); // release the mutex before I/O
+    /// Open the clipboard and read it; the contents are not recorded.
+    fn diagnose_clipboard() -> CheckOutcome {
+        match Clipboard::new() {
+            Ok(mut clipboard) => match clipboard.get_text() {
+                Ok(_) | Err(arboard::Error::ContentNotAvailable) => {
+                    CheckOutcome::Passed("readable".to_owned())
+                }
+                Err(err) => CheckOutcome::Failed(format!("could not read: {err}")),
+            },
+            Err(err) => CheckOutcome::Failed(err.to_string()),
+        }
+    }
 
-        let temp_path = write_incoming_temp_file(&file_name, &out)?;
-        Ok(Some(CompletedFile {
-            sender_device_id: sender_id,
-            file_name,
-            temp_path,
-            size_bytes: total_size,
-        }))
+    /// Create and delete a file in each folder ClipRelay writes to.
+    fn diagnose_folders(ui_state: &SavedUiState) -> CheckOutcome {
+        let dirs = [
+            (
+                "config",
+                client_config_path().parent().map(Path::to_path_buf),
+            ),
+            ("logs", active_log_path().parent().map(Path::to_path_buf)),
+            ("incoming", Some(incoming_staging_dir())),
+            (
+                "received files",
+                Some(
+                    ui_state
+                        .files_save_dir
+                        .clone()
+                        .unwrap_or_else(default_received_files_dir),
+                ),
+            ),
+        ];
+        let failures: Vec<String> = dirs
+            .into_iter()
+            .filter_map(|(name, dir)| {
+                let dir = dir?;
+                diagnose::probe_writable(&dir)
+                    .err()
+                    .map(|err| format!("{name} ({}): {err}", dir.display()))
+            })
+            .collect();
+        if failures.is_empty() {
+            CheckOutcome::Passed("config, logs, incoming and received files".to_owned())
+        } else {
+            CheckOutcome::Failed(failures.join("; "))
+        }
     }
 
     // ─── Entry point ───────────────────────────────────────────────────────────
 
     pub fn run() {
         init_logging();
+        install_crash_handlers();
 
         let args = match ClientArgs::try_parse() {
             Ok(args) => args,
@@ -3292,15 +13063,70 @@ mod windows_client {
                 std::process::exit(2);
             }
         };
+        let one_shot = match one_shot_request(&args) {
+            Ok(request) => request,
+            Err(message) => {
+                report_one_shot_error(&message);
+                std::process::exit(EXIT_USAGE);
+            }
+        };
+
+        let profile = match args.profile.as_deref() {
+            Some(raw) => match config_profiles::parse_name(raw) {
+                Ok(profile) => profile,
+                Err(err) => {
+                    error!("invalid --profile: {err}");
+                    std::process::exit(2);
+                }
+            },
+            None => config_profiles::load_last_used(&config_profiles::data_dir()),
+        };
+        if let Some(name) = profile.as_deref() {
+            info!(profile = name, "using configuration profile");
+            if let Err(err) = config_profiles::create_profile(&config_profiles::data_dir(), name) {
+                warn!("failed to create configuration profile: {err}");
+            }
+        }
+        config_profiles::set_active(profile);
 
-        // Determine the initial phase of the app.
-        let initial_phase = resolve_initial_phase(&args);
+        if let Some(request) = one_shot {
+            std::process::exit(run_one_shot(request));
+        }
+        if args.apply_and_exit {
+            std::process::exit(run_apply_settings(&args));
+        }
+        if args.diagnose {
+            std::process::exit(run_diagnose(&args));
+        }
+        let join_link = match args.join_link.as_deref() {
+            Some(raw) => match forward_join_link(raw) {
+                Some(link) => Some(link),
+                None => return,
+            },
+            None => None,
+        };
+        offer_pending_crash_report();
+
+        // Determine the initial phase of the app.  A join link opened while
+        // no client was running asks through the pre-filled setup screen.
+        let initial_phase = match join_link {
+            Some(link) => AppPhase::Setup {
+                room_code: link.room_code,
+                server_url: link.server_url,
+                device_name: load_saved_config()
+                    .ok()
+                    .flatten()
+                    .map_or_else(|| args.client_name.clone(), |saved| saved.device_name),
+                error_message: None,
+            },
+            None => resolve_initial_phase(&args),
+        };
         let start_visible = !matches!(initial_phase, AppPhase::Running { .. });
 
         let icon_data = load_egui_icon(APP_ICON_BYTES);
 
         let mut viewport = egui::ViewportBuilder::default()
-            .with_title("ClipRelay")
+            .with_title(window_title())
             .with_inner_size([560.0, 420.0])
             .with_min_inner_size([400.0, 300.0]);
 
@@ -3343,7 +13169,6 @@ mod windows_client {
                         server_url: config.server_url.clone(),
                         room_code: config.room_code.clone(),
                         device_name: config.device_name.clone(),
-                        last_counter: config.initial_counter,
                     };
                     // Re-create the phase properly with egui context.
                     app.phase = AppPhase::ChooseRoom { saved_config: None }; // temp
@@ -3358,6 +13183,78 @@ mod windows_client {
         }
     }
 
+    /// "ClipRelay", plus the configuration profile unless it is the default.
+    fn window_title() -> String {
+        match config_profiles::active() {
+            Some(name) => format!("ClipRelay ({name})"),
+            None => "ClipRelay".to_owned(),
+        }
+    }
+
+    /// Profile combo and "New profile" field for the setup screens.
+    /// Returns the profile picked or created this frame, and whether the
+    /// name field has (or just gave up) keyboard focus, so that Enter there
+    /// does not also press the screen's default button.
+    fn config_profile_picker(
+        ui: &mut egui::Ui,
+        names: &[String],
+        new_name: &mut String,
+        error: &mut Option<String>,
+    ) -> (Option<Option<String>>, bool) {
+        let active = config_profiles::active();
+        let mut picked: Option<Option<String>> = None;
+        let mut editing = false;
+        ui.horizontal(|ui| {
+            let label = ui.label("Profile:");
+            egui::ComboBox::from_id_salt("config_profile_combo")
+                .selected_text(config_profiles::display_name(active.as_deref()))
+                .show_ui(ui, |ui| {
+                    for option in std::iter::once(None).chain(names.iter().cloned().map(Some)) {
+                        let text = config_profiles::display_name(option.as_deref()).to_owned();
+                        if ui.selectable_label(option == active, text).clicked() {
+                            picked = Some(option);
+                        }
+                    }
+                })
+                .response
+                .labelled_by(label.id);
+            ui.add_space(8.0);
+            let field = ui.add(
+                egui::TextEdit::singleline(new_name)
+                    .hint_text("New profile")
+                    .desired_width(120.0),
+            );
+            let submitted = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            editing = field.has_focus() || field.lost_focus();
+            if (ui.button("Create").clicked() || submitted) && !new_name.trim().is_empty() {
+                match config_profiles::parse_name(new_name) {
+                    Ok(profile) => {
+                        if let Some(name) = profile.as_deref()
+                            && let Err(err) =
+                                config_profiles::create_profile(&config_profiles::data_dir(), name)
+                        {
+                            *error = Some(format!("Could not create profile: {err}"));
+                        } else {
+                            new_name.clear();
+                            *error = None;
+                            picked = Some(profile);
+                        }
+                    }
+                    Err(err) => *error = Some(err.to_string()),
+                }
+            }
+        })
+        .response
+        .on_hover_text(
+            "Each profile has its own server, room, send counter, history and settings.\n\
+             Start a profile directly with --profile NAME.",
+        );
+        if let Some(err) = error.as_deref() {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        (picked, editing)
+    }
+
     fn resolve_initial_phase(args: &ClientArgs) -> AppPhase {
         // CLI provides room code → go directly to Running.
         if let Some(ref room_code) = args.room_code {
@@ -3365,7 +13262,6 @@ mod windows_client {
                 server_url: args.server_url.clone(),
                 room_code: room_code.clone(),
                 device_name: args.client_name.clone(),
-                last_counter: 0,
             };
             if let Err(err) = validate_saved_config(&cfg) {
                 error!("invalid CLI config: {err}");
@@ -3387,7 +13283,11 @@ mod windows_client {
             }
         }
 
-        // Interactive: check for saved config.
+        interactive_initial_phase(args)
+    }
+
+    /// The saved room of the active profile, or the setup screen.
+    fn interactive_initial_phase(args: &ClientArgs) -> AppPhase {
         match load_saved_config() {
             Ok(Some(cfg)) => AppPhase::ChooseRoom {
                 saved_config: Some(cfg),
@@ -3414,14 +13314,15 @@ mod windows_client {
     /// will be properly set up in `run()` once the egui context is available.
     fn placeholder_running_phase(cfg: &SavedClientConfig, background: bool) -> AppPhase {
         let device_id = stable_device_id(&cfg.device_name);
+        let room_id = room_id_from_code(&cfg.room_code);
         let config = ClientConfig {
-            room_id: room_id_from_code(&cfg.room_code),
+            initial_counter: load_send_counter(&room_id),
+            room_id,
             server_url: cfg.server_url.clone(),
             room_code: cfg.room_code.clone(),
             device_name: cfg.device_name.clone(),
             device_id,
             background,
-            initial_counter: cfg.last_counter,
         };
         // We use a dummy runtime and channels here — they'll be replaced in run().
         let runtime = Runtime::new().expect("tokio runtime");
@@ -3434,13 +13335,24 @@ mod windows_client {
             ui_event_rx: ui_rx,
             runtime_cmd_tx: cmd_tx,
             active_tab: Tab::Send,
+            options_page: OptionsPage::General,
             send_text: String::new(),
+            send_pinned: false,
+            send_label: String::new(),
             connection_status: "Starting".to_string(),
             peers: Vec::new(),
             notifications: Vec::new(),
+            coalesced_notifications: 0,
             auto_apply: false,
             room_key_ready: false,
+            awaiting_key: 0,
+            transfers: TransferList::default(),
+            cancelled_transfers: Arc::new(Mutex::new(CancelledTransfers::default())),
+            reconnect_now: Arc::new(tokio::sync::Notify::new()),
             autostart_enabled: false,
+            explorer_menu_enabled: false,
+            send_to_enabled: false,
+            url_handler_enabled: false,
             last_sent_time: None,
             last_received_time: None,
             last_error: None,
@@ -3448,6 +13360,12 @@ mod windows_client {
             tray: None,
             window_visible: !background,
             toast_message: None,
+            self_test_running: false,
+            self_test_report: None,
+            sync_paused: false,
+            metered: MeteredStatus::default(),
+            reconnect_gave_up: false,
+            device_alerts: Vec::new(),
         }
     }
 
@@ -3531,4 +13449,3 @@ mod windows_client {
         assert_ne!(a1, c);
     }
 }
-
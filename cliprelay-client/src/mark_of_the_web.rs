@@ -0,0 +1,82 @@
+//! Mark-of-the-Web for saved received files.
+//!
+//! Windows records where a downloaded file came from in a `Zone.Identifier`
+//! alternate data stream.  SmartScreen, Office Protected View and script
+//! hosts consult it, so files saved from the room are tagged as coming from
+//! the Internet zone, like a browser download.
+
+use std::{io, path::Path};
+
+use url::Url;
+
+/// `URLZONE_INTERNET`.
+pub const ZONE_INTERNET: u32 = 3;
+
+/// Contents of the `Zone.Identifier` stream.
+pub fn zone_identifier(host_url: Option<&str>) -> String {
+    let mut out = format!("[ZoneTransfer]\r\nZoneId={ZONE_INTERNET}\r\n");
+    if let Some(host_url) = host_url {
+        out.push_str(&format!("HostUrl={host_url}\r\n"));
+    }
+    out
+}
+
+/// Origin of the relay a file arrived through, as a web URL
+/// (`wss://relay.example.com/ws?x` → `https://relay.example.com/`).
+/// Credentials, path and query are left out.
+pub fn relay_host_url(server_url: &str) -> Option<String> {
+    let url = Url::parse(server_url).ok()?;
+    let scheme = match url.scheme() {
+        "wss" | "https" => "https",
+        "ws" | "http" => "http",
+        _ => return None,
+    };
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{scheme}://{host}:{port}/"),
+        None => format!("{scheme}://{host}/"),
+    })
+}
+
+/// Write the `Zone.Identifier` stream of `path`.  Only NTFS (and ReFS)
+/// support alternate streams, so this fails on FAT and network shares that
+/// lack them.
+#[cfg(target_os = "windows")]
+pub fn apply(path: &Path, host_url: Option<&str>) -> io::Result<()> {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":Zone.Identifier");
+    std::fs::write(stream, zone_identifier(host_url))
+}
+
+/// Alternate data streams are a Windows concept; nothing to do elsewhere.
+#[cfg(not(target_os = "windows"))]
+pub fn apply(_path: &Path, _host_url: Option<&str>) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_identifier_names_internet_zone_and_host() {
+        assert_eq!(
+            zone_identifier(Some("https://relay.example.com/")),
+            "[ZoneTransfer]\r\nZoneId=3\r\nHostUrl=https://relay.example.com/\r\n"
+        );
+        assert_eq!(zone_identifier(None), "[ZoneTransfer]\r\nZoneId=3\r\n");
+    }
+
+    #[test]
+    fn relay_host_url_keeps_only_the_origin() {
+        assert_eq!(
+            relay_host_url("wss://user:pw@relay.example.com/ws?room=1").as_deref(),
+            Some("https://relay.example.com/")
+        );
+        assert_eq!(
+            relay_host_url("ws://127.0.0.1:8080/ws").as_deref(),
+            Some("http://127.0.0.1:8080/")
+        );
+        assert_eq!(relay_host_url("not a url"), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! Metered-connection awareness: outgoing file sends (and optionally large
+//! texts) are held back while Windows reports the Internet connection as
+//! metered, and go out once it is not, or when the user overrides.
+//!
+//! Only outgoing sends can be deferred; what peers send still arrives.
+
+use std::{collections::VecDeque, path::PathBuf};
+
+use crate::outbox::{OutgoingText, SendId};
+
+/// How often the connection cost is re-read.
+pub const NETWORK_COST_POLL_SECS: u64 = 60;
+
+/// Texts at least this large count as "large" for deferral.
+pub const LARGE_TEXT_BYTES: usize = 64 * 1024;
+
+/// Upper bound on queued sends; further ones are refused.
+pub const MAX_DEFERRED_SENDS: usize = 50;
+
+/// `Windows.Networking.Connectivity.NetworkCostType` of the current
+/// Internet connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkCost {
+    /// Not known (no connection, or the query failed).
+    #[default]
+    Unknown,
+    Unrestricted,
+    /// Metered with a data limit.
+    Fixed,
+    /// Metered, charged per byte.
+    Variable,
+}
+
+impl NetworkCost {
+    /// Parse the `NetworkCostType` name printed by the query.
+    pub fn parse(output: &str) -> NetworkCost {
+        match output.trim().to_ascii_lowercase().as_str() {
+            "unrestricted" => NetworkCost::Unrestricted,
+            "fixed" => NetworkCost::Fixed,
+            "variable" => NetworkCost::Variable,
+            _ => NetworkCost::Unknown,
+        }
+    }
+
+    pub fn is_metered(self) -> bool {
+        matches!(self, NetworkCost::Fixed | NetworkCost::Variable)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NetworkCost::Unknown => "unknown",
+            NetworkCost::Unrestricted => "unmetered",
+            NetworkCost::Fixed => "metered (data limit)",
+            NetworkCost::Variable => "metered (pay per use)",
+        }
+    }
+}
+
+/// What is deferred while metered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeteredPolicy {
+    pub defer_files: bool,
+    pub defer_large_text: bool,
+    /// Send anyway on the current metered connection; cleared when the
+    /// connection stops being metered.
+    pub override_active: bool,
+}
+
+impl MeteredPolicy {
+    pub fn defers_file(self, cost: NetworkCost) -> bool {
+        cost.is_metered() && self.defer_files && !self.override_active
+    }
+
+    pub fn defers_text(self, cost: NetworkCost, len: usize) -> bool {
+        cost.is_metered()
+            && self.defer_large_text
+            && len >= LARGE_TEXT_BYTES
+            && !self.override_active
+    }
+
+    pub fn defers(self, cost: NetworkCost, send: &DeferredSend) -> bool {
+        match send {
+            DeferredSend::Text(send) => self.defers_text(cost, send.text.len()),
+            DeferredSend::File(_) => self.defers_file(cost),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeferredSend {
+    Text(OutgoingText),
+    File(PathBuf),
+}
+
+/// Sends held back (while metered, or until the room key is ready),
+/// oldest first, each with the id of its history entry.
+#[derive(Debug, Default)]
+pub struct DeferredSends {
+    items: VecDeque<(SendId, DeferredSend)>,
+}
+
+impl DeferredSends {
+    /// Queue `send`; returns `false` when the queue is full.
+    pub fn push(&mut self, id: SendId, send: DeferredSend) -> bool {
+        if self.items.len() >= MAX_DEFERRED_SENDS {
+            return false;
+        }
+        self.items.push_back((id, send));
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Remove and return every queued send, oldest first.
+    pub fn take_all(&mut self) -> Vec<(SendId, DeferredSend)> {
+        self.items.drain(..).collect()
+    }
+
+    /// Remove and return the sends `policy` no longer defers on `cost`,
+    /// in the order they were queued.
+    pub fn take_ready(
+        &mut self,
+        cost: NetworkCost,
+        policy: MeteredPolicy,
+    ) -> Vec<(SendId, DeferredSend)> {
+        let (ready, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|(_, send)| !policy.defers(cost, send));
+        self.items = waiting.into();
+        ready
+    }
+}
+
+/// Read the cost of the current Internet connection through the WinRT
+/// `NetworkInformation` API (via PowerShell, which can load WinRT types).
+#[cfg(target_os = "windows")]
+pub fn query_network_cost() -> NetworkCost {
+    use std::os::windows::process::CommandExt;
+
+    /// Keeps powershell.exe from flashing a console window.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    const SCRIPT: &str = "[void][Windows.Networking.Connectivity.NetworkInformation,\
+        Windows.Networking.Connectivity,ContentType=WindowsRuntime]; \
+        $p = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+        if ($p) { $p.GetConnectionCost().NetworkCostType } else { 'Unknown' }";
+
+    match std::process::Command::new("powershell.exe")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            NetworkCost::parse(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => NetworkCost::Unknown,
+    }
+}
+
+/// Connection cost is only reported on Windows.
+#[cfg(not(target_os = "windows"))]
+pub fn query_network_cost() -> NetworkCost {
+    NetworkCost::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cost_types() {
+        assert_eq!(NetworkCost::parse("Fixed\r\n"), NetworkCost::Fixed);
+        assert_eq!(
+            NetworkCost::parse("Unrestricted"),
+            NetworkCost::Unrestricted
+        );
+        assert_eq!(NetworkCost::parse(""), NetworkCost::Unknown);
+        assert!(NetworkCost::Variable.is_metered());
+        assert!(!NetworkCost::Unknown.is_metered());
+    }
+
+    #[test]
+    fn releases_sends_when_unmetered_or_overridden() {
+        let policy = MeteredPolicy {
+            defer_files: true,
+            defer_large_text: true,
+            override_active: false,
+        };
+        let mut queue = DeferredSends::default();
+        queue.push(1, DeferredSend::File(PathBuf::from("a.zip")));
+        queue.push(2, DeferredSend::Text("x".repeat(LARGE_TEXT_BYTES).into()));
+        // Small text is never deferred, so it is released straight away.
+        queue.push(3, DeferredSend::Text("hi".into()));
+
+        assert_eq!(
+            queue.take_ready(NetworkCost::Fixed, policy),
+            [(3, DeferredSend::Text("hi".into()))]
+        );
+        assert_eq!(queue.len(), 2);
+
+        let overridden = MeteredPolicy {
+            override_active: true,
+            ..policy
+        };
+        assert_eq!(queue.take_ready(NetworkCost::Fixed, overridden).len(), 2);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn queue_is_bounded() {
+        let mut queue = DeferredSends::default();
+        for _ in 0..MAX_DEFERRED_SENDS {
+            assert!(queue.push(1, DeferredSend::File(PathBuf::from("f"))));
+        }
+        assert!(!queue.push(1, DeferredSend::File(PathBuf::from("f"))));
+        assert_eq!(queue.take_all().len(), MAX_DEFERRED_SENDS);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Reconnecting as soon as the network comes back, e.g. after resuming
+//! from sleep or switching Wi-Fi, instead of waiting out the reconnect
+//! delay.
+//!
+//! Windows reports connectivity changes through
+//! `NotifyNetworkConnectivityHintChange`; [`ConnectivityWatch`] turns the
+//! levels it reports into "Internet access is back" moments.  Other
+//! platforms get a stub that reports `Unsupported`.
+
+/// `NL_NETWORK_CONNECTIVITY_LEVEL_HINT` of the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectivityLevel {
+    Unknown,
+    None,
+    LocalAccess,
+    InternetAccess,
+    /// Internet access behind a captive portal or similar.
+    ConstrainedInternetAccess,
+    /// The network is hidden from the user.
+    Hidden,
+}
+
+impl ConnectivityLevel {
+    pub fn from_hint(value: i32) -> ConnectivityLevel {
+        match value {
+            1 => ConnectivityLevel::None,
+            2 => ConnectivityLevel::LocalAccess,
+            3 => ConnectivityLevel::InternetAccess,
+            4 => ConnectivityLevel::ConstrainedInternetAccess,
+            5 => ConnectivityLevel::Hidden,
+            _ => ConnectivityLevel::Unknown,
+        }
+    }
+
+    /// Whether the relay may be reachable.  An unknown level counts, so
+    /// that a failed query never holds a reconnect back.
+    pub fn reaches_internet(self) -> bool {
+        matches!(
+            self,
+            ConnectivityLevel::InternetAccess | ConnectivityLevel::Unknown
+        )
+    }
+}
+
+/// The last reported level, to spot the moment Internet access returns.
+#[derive(Debug, Default)]
+pub struct ConnectivityWatch {
+    last: Option<ConnectivityLevel>,
+}
+
+impl ConnectivityWatch {
+    /// Record `level`.  Returns `true` when it brings Internet access back
+    /// after a level without; the first report only sets the baseline.
+    pub fn update(&mut self, level: ConnectivityLevel) -> bool {
+        let regained = self
+            .last
+            .is_some_and(|last| !last.reaches_internet() && level.reaches_internet());
+        self.last = Some(level);
+        regained
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_impl::{Subscription, subscribe};
+
+#[cfg(not(target_os = "windows"))]
+pub use fallback::{Subscription, subscribe};
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{ffi::c_void, io};
+
+    use windows_sys::Win32::{
+        Foundation::{HANDLE, NO_ERROR},
+        NetworkManagement::IpHelper::{
+            CancelMibChangeNotify2, NotifyNetworkConnectivityHintChange,
+        },
+        Networking::WinSock::NL_NETWORK_CONNECTIVITY_HINT,
+    };
+
+    use super::ConnectivityLevel;
+
+    type Callback = Box<dyn Fn(ConnectivityLevel) + Send + Sync>;
+
+    /// Keeps the notification registered; dropping it unregisters.
+    pub struct Subscription {
+        handle: HANDLE,
+        callback: *mut Callback,
+    }
+
+    // SAFETY: the handle is only used to unregister, which Windows allows
+    // from any thread, and the callback is `Send + Sync`.
+    unsafe impl Send for Subscription {}
+
+    impl Drop for Subscription {
+        fn drop(&mut self) {
+            // SAFETY: waits for a running callback to return, after which
+            // Windows no longer uses the context, so it can be freed.
+            unsafe {
+                CancelMibChangeNotify2(self.handle);
+                drop(Box::from_raw(self.callback));
+            }
+        }
+    }
+
+    /// Call `on_change` with the current level, then on every change, on
+    /// a Windows thread pool thread.
+    pub fn subscribe(
+        on_change: impl Fn(ConnectivityLevel) + Send + Sync + 'static,
+    ) -> io::Result<Subscription> {
+        let callback: *mut Callback = Box::into_raw(Box::new(Box::new(on_change)));
+        let mut handle: HANDLE = 0;
+        // SAFETY: `callback` stays valid until the subscription is
+        // cancelled in `Drop`.
+        let status = unsafe {
+            NotifyNetworkConnectivityHintChange(
+                Some(hint_changed),
+                callback as *const c_void,
+                1,
+                &mut handle,
+            )
+        };
+        if status != NO_ERROR as i32 {
+            // SAFETY: not registered, so nothing else holds the pointer.
+            drop(unsafe { Box::from_raw(callback) });
+            return Err(io::Error::from_raw_os_error(status));
+        }
+        Ok(Subscription { handle, callback })
+    }
+
+    unsafe extern "system" fn hint_changed(
+        context: *const c_void,
+        hint: NL_NETWORK_CONNECTIVITY_HINT,
+    ) {
+        // SAFETY: `context` is the `Callback` registered in `subscribe`,
+        // alive until the subscription is cancelled.
+        let callback = unsafe { &*(context as *const Callback) };
+        callback(ConnectivityLevel::from_hint(hint.ConnectivityLevel));
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod fallback {
+    use std::io;
+
+    use super::ConnectivityLevel;
+
+    pub struct Subscription;
+
+    pub fn subscribe(
+        _on_change: impl Fn(ConnectivityLevel) + Send + Sync + 'static,
+    ) -> io::Result<Subscription> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "network change notifications are only available on Windows",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_hint_levels() {
+        assert_eq!(
+            ConnectivityLevel::from_hint(3),
+            ConnectivityLevel::InternetAccess
+        );
+        assert_eq!(ConnectivityLevel::from_hint(1), ConnectivityLevel::None);
+        assert_eq!(ConnectivityLevel::from_hint(42), ConnectivityLevel::Unknown);
+        assert!(!ConnectivityLevel::ConstrainedInternetAccess.reaches_internet());
+    }
+
+    #[test]
+    fn fires_only_when_internet_access_returns() {
+        let mut watch = ConnectivityWatch::default();
+        assert!(!watch.update(ConnectivityLevel::InternetAccess), "baseline");
+        assert!(!watch.update(ConnectivityLevel::InternetAccess));
+        assert!(!watch.update(ConnectivityLevel::None));
+        assert!(!watch.update(ConnectivityLevel::LocalAccess));
+        assert!(watch.update(ConnectivityLevel::InternetAccess));
+
+        let mut offline_at_start = ConnectivityWatch::default();
+        assert!(!offline_at_start.update(ConnectivityLevel::None));
+        assert!(offline_at_start.update(ConnectivityLevel::InternetAccess));
+    }
+}
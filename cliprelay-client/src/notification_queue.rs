@@ -0,0 +1,136 @@
+//! Size of the Notifications list and what happens when it is full.
+//!
+//! Every received item also gets an Activity History entry, and items the
+//! list cannot hold are recorded there as well (or counted in a "more
+//! items" row), so nothing leaves the list without a trace.
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_ITEMS: usize = 20;
+
+/// Smallest list size that can be configured.
+pub const MIN_ITEMS: usize = 5;
+
+/// Largest list size that can be configured.
+pub const MAX_ITEMS_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Remove the oldest unpinned item to make room, noting it in history.
+    #[default]
+    DropOldest,
+    /// Keep the list as it is and note the new item in history.
+    DropNewest,
+    /// Keep the list and count new items in one "N more items" row.
+    Coalesce,
+}
+
+impl OverflowPolicy {
+    pub const ALL: [OverflowPolicy; 3] = [
+        OverflowPolicy::DropOldest,
+        OverflowPolicy::DropNewest,
+        OverflowPolicy::Coalesce,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "Drop the oldest item",
+            OverflowPolicy::DropNewest => "Drop the new item",
+            OverflowPolicy::Coalesce => "Add to a \"more items\" row",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotificationLimit {
+    #[serde(default = "default_max_items")]
+    pub max_items: usize,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+fn default_max_items() -> usize {
+    DEFAULT_MAX_ITEMS
+}
+
+impl Default for NotificationLimit {
+    fn default() -> Self {
+        Self {
+            max_items: DEFAULT_MAX_ITEMS,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// The fate of an item arriving at the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Queue,
+    /// Queue it after removing the oldest unpinned item.
+    QueueAfterEvicting,
+    /// Leave it out, recording it in history.
+    Drop,
+    /// Leave it out, counting it in the "more items" row.
+    Coalesce,
+}
+
+impl NotificationLimit {
+    /// Clamp values from a hand-edited file or the Options tab into range.
+    pub fn normalized(self) -> Self {
+        Self {
+            max_items: self.max_items.clamp(MIN_ITEMS, MAX_ITEMS_LIMIT),
+            overflow: self.overflow,
+        }
+    }
+
+    /// What to do with a new item when the list holds `len` items.
+    pub fn admit(self, len: usize) -> Admission {
+        if len < self.normalized().max_items {
+            return Admission::Queue;
+        }
+        match self.overflow {
+            OverflowPolicy::DropOldest => Admission::QueueAfterEvicting,
+            OverflowPolicy::DropNewest => Admission::Drop,
+            OverflowPolicy::Coalesce => Admission::Coalesce,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_list_follows_the_policy() {
+        let limit = NotificationLimit::default();
+        assert_eq!(limit.admit(19), Admission::Queue);
+        assert_eq!(limit.admit(20), Admission::QueueAfterEvicting);
+        let drop_new = NotificationLimit {
+            max_items: 10,
+            overflow: OverflowPolicy::DropNewest,
+        };
+        assert_eq!(drop_new.admit(10), Admission::Drop);
+        let coalesce = NotificationLimit {
+            overflow: OverflowPolicy::Coalesce,
+            ..drop_new
+        };
+        assert_eq!(coalesce.admit(9), Admission::Queue);
+        assert_eq!(coalesce.admit(12), Admission::Coalesce);
+    }
+
+    #[test]
+    fn limits_are_clamped() {
+        let tiny = NotificationLimit {
+            max_items: 0,
+            overflow: OverflowPolicy::DropNewest,
+        };
+        assert_eq!(tiny.normalized().max_items, MIN_ITEMS);
+        assert_eq!(tiny.admit(MIN_ITEMS - 1), Admission::Queue);
+        let huge = NotificationLimit {
+            max_items: usize::MAX,
+            ..tiny
+        };
+        assert_eq!(huge.normalized().max_items, MAX_ITEMS_LIMIT);
+    }
+}
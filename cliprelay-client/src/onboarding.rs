@@ -0,0 +1,90 @@
+//! First-run onboarding: a few short pages shown once after the first room
+//! setup, explaining the tray icon, offering the startup options, showing
+//! the hotkeys and waiting for the first other device to join.
+
+use cliprelay_core::PeerInfo;
+
+/// One page of the onboarding window, in the order they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingStep {
+    TrayIcon,
+    Startup,
+    Hotkey,
+    FirstPeer,
+}
+
+impl OnboardingStep {
+    pub const ALL: [OnboardingStep; 4] = [
+        OnboardingStep::TrayIcon,
+        OnboardingStep::Startup,
+        OnboardingStep::Hotkey,
+        OnboardingStep::FirstPeer,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            OnboardingStep::TrayIcon => "The tray icon",
+            OnboardingStep::Startup => "Startup and applying",
+            OnboardingStep::Hotkey => "Hotkeys",
+            OnboardingStep::FirstPeer => "Pair another device",
+        }
+    }
+
+    /// 1-based position, for "Step 2 of 4".
+    pub fn number(self) -> usize {
+        Self::ALL.iter().position(|&s| s == self).unwrap_or(0) + 1
+    }
+
+    /// The page after this one; `None` on the last.
+    pub fn next(self) -> Option<OnboardingStep> {
+        Self::ALL.get(self.number()).copied()
+    }
+
+    /// The page before this one; `None` on the first.
+    pub fn prev(self) -> Option<OnboardingStep> {
+        self.number().checked_sub(2).map(|i| Self::ALL[i])
+    }
+}
+
+/// Devices in the room other than this one.
+pub fn other_peers<'a>(
+    peers: &'a [PeerInfo],
+    own_device_id: &'a str,
+) -> impl Iterator<Item = &'a PeerInfo> {
+    peers.iter().filter(move |p| p.device_id != own_device_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_walk_forward_and_back() {
+        let mut step = OnboardingStep::ALL[0];
+        let mut seen = vec![step];
+        while let Some(next) = step.next() {
+            assert_eq!(next.prev(), Some(step));
+            step = next;
+            seen.push(step);
+        }
+        assert_eq!(seen, OnboardingStep::ALL);
+        assert_eq!(OnboardingStep::TrayIcon.prev(), None);
+        assert_eq!(
+            OnboardingStep::FirstPeer.number(),
+            OnboardingStep::ALL.len()
+        );
+    }
+
+    #[test]
+    fn own_device_is_not_a_peer() {
+        let peer = |id: &str| PeerInfo {
+            device_id: id.to_owned(),
+            device_name: id.to_uppercase(),
+            sealed_chunks: true,
+        };
+        let peers = [peer("me"), peer("laptop")];
+        let others: Vec<_> = other_peers(&peers, "me").collect();
+        assert_eq!(others, [&peers[1]]);
+        assert_eq!(other_peers(&peers[..1], "me").count(), 0);
+    }
+}
@@ -0,0 +1,306 @@
+//! Retry of sends lost to a dropped connection.
+//!
+//! Every send carries a [`SendId`] linking it to its history entry.  A send
+//! counts as delivered once all of its frames have been written to the
+//! relay socket.  Sends whose frames were still queued (or whose write
+//! failed) when the session ended are kept and resent once the next
+//! session has its room key, up to [`MAX_SEND_ATTEMPTS`] tries in total.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::metered::DeferredSend;
+
+/// Links a send to its history entry.  Several sends may share one id
+/// (the parts of a split text).
+pub type SendId = u64;
+
+/// Tries per send, including the first, before it is reported failed.
+pub const MAX_SEND_ATTEMPTS: u32 = 3;
+
+/// Upper bound on sends waiting for a retry; further ones are failed.
+pub const MAX_RETRY_SENDS: usize = 50;
+
+static NEXT_SEND_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Text to send, with the options chosen for it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutgoingText {
+    pub text: String,
+    /// Receivers keep it at the top of their lists until they unpin it.
+    pub pinned: bool,
+    /// Title shown with the item, already normalized.
+    pub label: Option<String>,
+}
+
+impl From<String> for OutgoingText {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&str> for OutgoingText {
+    fn from(text: &str) -> Self {
+        text.to_owned().into()
+    }
+}
+
+/// A fresh id, unique within this process.
+pub fn next_send_id() -> SendId {
+    NEXT_SEND_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Delivery state of a sent history entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Queued, not yet written to the relay.
+    Pending,
+    Sent,
+    /// Lost to a dropped connection; `attempt` is the next try.
+    Retrying {
+        attempt: u32,
+    },
+    /// Gave up, or the send could not be made at all.
+    Failed,
+    /// A file transfer stopped by this device or a receiver.
+    Cancelled,
+}
+
+impl DeliveryStatus {
+    pub fn label(self) -> String {
+        match self {
+            DeliveryStatus::Pending => "sending…".to_owned(),
+            DeliveryStatus::Sent => "sent".to_owned(),
+            DeliveryStatus::Retrying { attempt } => {
+                format!("retrying after reconnect (try {attempt} of {MAX_SEND_ATTEMPTS})")
+            }
+            DeliveryStatus::Failed => "not sent".to_owned(),
+            DeliveryStatus::Cancelled => "cancelled".to_owned(),
+        }
+    }
+
+    /// Status of a saved entry after a restart: retries do not survive
+    /// the process, so anything unfinished is reported failed.
+    pub fn after_restart(self) -> DeliveryStatus {
+        match self {
+            DeliveryStatus::Pending | DeliveryStatus::Retrying { .. } => DeliveryStatus::Failed,
+            settled => settled,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InFlight {
+    id: SendId,
+    send: DeferredSend,
+    /// Frame count (this session) at which the send is fully written.
+    last_frame: u64,
+}
+
+/// Sends in flight on the current session plus those waiting for a retry.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    /// Bumped at the end of every session, so that a send task outliving
+    /// its session cannot report writes into the next one.
+    session: u64,
+    queued_frames: u64,
+    written_frames: u64,
+    in_flight: VecDeque<InFlight>,
+    retry: VecDeque<(SendId, DeferredSend)>,
+    /// Failed tries per id, kept until the send is delivered or given up.
+    failures: HashMap<SendId, u32>,
+}
+
+impl Outbox {
+    pub fn session(&self) -> u64 {
+        self.session
+    }
+
+    /// The current session and the number of frames queued in it so far,
+    /// i.e. the position of the last frame queued.
+    pub fn queued_position(&self) -> (u64, u64) {
+        (self.session, self.queued_frames)
+    }
+
+    /// Frames of `session` written so far; `None` once it has ended.
+    pub fn written_in(&self, session: u64) -> Option<u64> {
+        (session == self.session).then_some(self.written_frames)
+    }
+
+    /// An encrypted frame was handed to the socket writer.
+    pub fn frame_queued(&mut self) {
+        self.queued_frames += 1;
+    }
+
+    /// Every frame queued this session has been written.
+    pub fn is_idle(&self) -> bool {
+        self.written_frames >= self.queued_frames
+    }
+
+    /// Every frame of send `id` has been queued.  Returns `true` when they
+    /// were already written, i.e. the send is delivered.
+    pub fn track(&mut self, id: SendId, send: DeferredSend) -> bool {
+        if self.is_idle() {
+            self.failures.remove(&id);
+            return true;
+        }
+        self.in_flight.push_back(InFlight {
+            id,
+            send,
+            last_frame: self.queued_frames,
+        });
+        false
+    }
+
+    /// An encrypted frame of `session` was written to the socket.  Returns
+    /// the sends this completed.
+    pub fn frame_written(&mut self, session: u64) -> Vec<SendId> {
+        if session != self.session {
+            return Vec::new();
+        }
+        self.written_frames += 1;
+        let mut delivered = Vec::new();
+        while let Some(front) = self.in_flight.front()
+            && front.last_frame <= self.written_frames
+        {
+            let id = front.id;
+            self.in_flight.pop_front();
+            self.failures.remove(&id);
+            delivered.push(id);
+        }
+        delivered
+    }
+
+    /// The session ended: queue its undelivered sends for a retry, or give
+    /// up on them.  Returns the new status of each.
+    pub fn end_session(&mut self) -> Vec<(SendId, DeliveryStatus)> {
+        self.session += 1;
+        self.queued_frames = 0;
+        self.written_frames = 0;
+        let mut statuses = Vec::new();
+        for InFlight { id, send, .. } in std::mem::take(&mut self.in_flight) {
+            let failures = self.failures.entry(id).or_insert(0);
+            *failures += 1;
+            if *failures >= MAX_SEND_ATTEMPTS || self.retry.len() >= MAX_RETRY_SENDS {
+                self.failures.remove(&id);
+                statuses.push((id, DeliveryStatus::Failed));
+            } else {
+                let attempt = *failures + 1;
+                self.retry.push_back((id, send));
+                statuses.push((id, DeliveryStatus::Retrying { attempt }));
+            }
+        }
+        statuses
+    }
+
+    /// Remove and return the sends waiting for a retry, oldest first.
+    pub fn take_retries(&mut self) -> Vec<(SendId, DeferredSend)> {
+        self.retry.drain(..).collect()
+    }
+
+    pub fn retry_len(&self) -> usize {
+        self.retry.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> DeferredSend {
+        DeferredSend::Text(s.into())
+    }
+
+    #[test]
+    fn delivered_once_every_frame_is_written() {
+        let mut outbox = Outbox::default();
+        let session = outbox.session();
+        outbox.frame_queued();
+        outbox.frame_queued();
+        assert!(!outbox.track(1, DeferredSend::File("a.zip".into())));
+        outbox.frame_queued();
+        assert!(!outbox.track(2, text("hi")));
+
+        assert!(outbox.frame_written(session).is_empty());
+        assert_eq!(outbox.frame_written(session), [1]);
+        assert_eq!(outbox.frame_written(session), [2]);
+        assert!(outbox.end_session().is_empty());
+    }
+
+    #[test]
+    fn written_before_tracking_counts_as_delivered() {
+        let mut outbox = Outbox::default();
+        let session = outbox.session();
+        outbox.frame_queued();
+        assert!(outbox.frame_written(session).is_empty());
+        assert!(outbox.track(1, text("hi")));
+    }
+
+    #[test]
+    fn undelivered_sends_are_retried_then_failed() {
+        let mut outbox = Outbox::default();
+        for attempt in 2..=MAX_SEND_ATTEMPTS {
+            outbox.frame_queued();
+            outbox.track(7, text("lost"));
+            assert_eq!(
+                outbox.end_session(),
+                [(7, DeliveryStatus::Retrying { attempt })]
+            );
+            assert_eq!(outbox.take_retries(), [(7, text("lost"))]);
+        }
+        outbox.frame_queued();
+        outbox.track(7, text("lost"));
+        assert_eq!(outbox.end_session(), [(7, DeliveryStatus::Failed)]);
+        assert_eq!(outbox.retry_len(), 0);
+    }
+
+    #[test]
+    fn idle_once_every_queued_frame_is_written() {
+        let mut outbox = Outbox::default();
+        assert!(outbox.is_idle());
+        outbox.frame_queued();
+        outbox.track(1, text("hi"));
+        assert!(!outbox.is_idle());
+        outbox.frame_written(outbox.session());
+        assert!(outbox.is_idle());
+    }
+
+    #[test]
+    fn stale_session_writes_are_ignored() {
+        let mut outbox = Outbox::default();
+        let old = outbox.session();
+        outbox.end_session();
+        outbox.frame_queued();
+        outbox.track(3, text("hi"));
+        assert!(outbox.frame_written(old).is_empty());
+        assert_eq!(outbox.frame_written(outbox.session()), [3]);
+    }
+
+    #[test]
+    fn reports_written_frames_for_the_current_session_only() {
+        let mut outbox = Outbox::default();
+        outbox.frame_queued();
+        outbox.frame_queued();
+        let (session, position) = outbox.queued_position();
+        assert_eq!(position, 2);
+        outbox.frame_written(session);
+        assert_eq!(outbox.written_in(session), Some(1));
+        outbox.end_session();
+        assert_eq!(outbox.written_in(session), None);
+    }
+
+    #[test]
+    fn unfinished_statuses_fail_after_restart() {
+        assert_eq!(
+            DeliveryStatus::Retrying { attempt: 2 }.after_restart(),
+            DeliveryStatus::Failed
+        );
+        assert_eq!(DeliveryStatus::Sent.after_restart(), DeliveryStatus::Sent);
+    }
+}
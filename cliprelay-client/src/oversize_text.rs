@@ -0,0 +1,81 @@
+//! Text over `MAX_CLIPBOARD_TEXT_BYTES` sent as a `.txt` file transfer.
+//!
+//! The send box always offers "Send as File" for oversized text.  Text that
+//! reaches the runtime some other way (automation, `--send-text`, Quick
+//! Pick) fails by default, or is converted when the Options setting says
+//! so.  The file is named after the text's first line and the local time,
+//! so receivers can tell several of them apart.
+
+use serde::{Deserialize, Serialize};
+
+/// Characters of the first line kept in the file name.
+pub const NAME_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OversizedText {
+    /// Offer "Send as File" in the send box; other sends fail.
+    #[default]
+    Ask,
+    /// Send every oversized text as a file.
+    SendAsFile,
+}
+
+impl OversizedText {
+    pub const ALL: [OversizedText; 2] = [OversizedText::Ask, OversizedText::SendAsFile];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OversizedText::Ask => "Offer to send as a file",
+            OversizedText::SendAsFile => "Always send as a file",
+        }
+    }
+}
+
+/// `<first line>-<stamp>.txt`, with the first non-blank line reduced to
+/// letters, digits, `-` and `_`; `clipboard-text-<stamp>.txt` when nothing
+/// of it is left.  `stamp` is the local time, e.g. `20261016-153012`.
+pub fn file_name(text: &str, stamp: &str) -> String {
+    let first_line = text.lines().find(|line| !line.trim().is_empty());
+    let mut slug = String::new();
+    for c in first_line.unwrap_or_default().chars() {
+        if slug.chars().count() >= NAME_CHARS {
+            break;
+        }
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        format!("clipboard-text-{stamp}.txt")
+    } else {
+        format!("{slug}-{stamp}.txt")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_come_from_the_first_line() {
+        assert_eq!(
+            file_name("\n  fn main() {\n}", "20261016-153012"),
+            "fn-main-20261016-153012.txt"
+        );
+        assert_eq!(
+            file_name("C:\\logs\\app.log: ERROR", "1"),
+            "C-logs-app-log-ERROR-1.txt"
+        );
+        assert_eq!(file_name("  \n{}\n", "1"), "clipboard-text-1.txt");
+    }
+
+    #[test]
+    fn long_first_lines_are_cut() {
+        let name = file_name(&"é".repeat(100), "1");
+        assert_eq!(name, format!("{}-1.txt", "é".repeat(NAME_CHARS)));
+    }
+}
@@ -0,0 +1,184 @@
+//! Access control for the client's named pipes (see [`crate::automation`]
+//! and [`crate::instance`]).
+//!
+//! The servers create their pipes with [`OwnerOnly`], a DACL that grants
+//! access to the signed-in user's SID and nobody else.  Before sending the
+//! automation token, a client checks with [`server_is_current_user`] that
+//! the process serving the pipe runs as the same user, so a pipe another
+//! account created first under the same name never sees the token.  Other
+//! platforms get stubs that report `Unsupported`.
+
+pub use imp::{OwnerOnly, server_is_current_user};
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use std::{ffi::c_void, fs::File, io, mem, os::windows::io::AsRawHandle, ptr};
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE},
+        Security::{
+            Authorization::{
+                ConvertSidToStringSidW, ConvertStringSecurityDescriptorToSecurityDescriptorW,
+                SDDL_REVISION_1,
+            },
+            GetTokenInformation, SECURITY_ATTRIBUTES, TOKEN_QUERY, TOKEN_USER, TokenUser,
+        },
+        System::{
+            Memory::LocalFree,
+            Pipes::GetNamedPipeServerProcessId,
+            Threading::{
+                GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+            },
+        },
+    };
+
+    /// Security attributes whose DACL allows only the current user, for
+    /// `ServerOptions::create_with_security_attributes_raw`.
+    pub struct OwnerOnly {
+        descriptor: *mut c_void,
+        attributes: SECURITY_ATTRIBUTES,
+    }
+
+    // SAFETY: the descriptor is a `LocalAlloc` block owned by this value
+    // alone and never written after construction.
+    unsafe impl Send for OwnerOnly {}
+
+    impl OwnerOnly {
+        pub fn new() -> io::Result<Self> {
+            // Protected DACL, generic-all for the user's SID only.
+            let sddl: Vec<u16> = format!("D:P(A;;GA;;;{})", current_user_sid()?)
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect();
+            let mut descriptor = ptr::null_mut();
+            // SAFETY: `sddl` is NUL-terminated; on success `descriptor` is
+            // a `LocalAlloc` block freed in `drop`.
+            let ok = unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                    sddl.as_ptr(),
+                    SDDL_REVISION_1,
+                    &mut descriptor,
+                    ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                descriptor,
+                attributes: SECURITY_ATTRIBUTES {
+                    nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                    lpSecurityDescriptor: descriptor,
+                    bInheritHandle: 0,
+                },
+            })
+        }
+
+        /// Pointer to the `SECURITY_ATTRIBUTES`, valid while `self` lives.
+        pub fn as_raw(&mut self) -> *mut c_void {
+            (&mut self.attributes as *mut SECURITY_ATTRIBUTES).cast()
+        }
+    }
+
+    impl Drop for OwnerOnly {
+        fn drop(&mut self) {
+            // SAFETY: allocated by
+            // `ConvertStringSecurityDescriptorToSecurityDescriptorW`.
+            unsafe { LocalFree(self.descriptor as isize) };
+        }
+    }
+
+    /// Whether the process serving the client end `pipe` runs as the
+    /// current user.
+    pub fn server_is_current_user(pipe: &File) -> io::Result<bool> {
+        let mut pid = 0u32;
+        // SAFETY: `pipe` is an open client end of a named pipe.
+        if unsafe { GetNamedPipeServerProcessId(pipe.as_raw_handle() as HANDLE, &mut pid) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: plain query; the handle is closed below.
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let server = process_user_sid(process);
+        // SAFETY: opened above.
+        unsafe { CloseHandle(process) };
+        Ok(server? == current_user_sid()?)
+    }
+
+    fn current_user_sid() -> io::Result<String> {
+        // SAFETY: the pseudo handle of the current process needs no closing.
+        process_user_sid(unsafe { GetCurrentProcess() })
+    }
+
+    /// The string SID of the account `process` runs as.
+    fn process_user_sid(process: HANDLE) -> io::Result<String> {
+        let mut token: HANDLE = 0;
+        // SAFETY: `token` is closed below.
+        if unsafe { OpenProcessToken(process, TOKEN_QUERY, &mut token) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let sid = token_user_sid(token);
+        // SAFETY: opened above.
+        unsafe { CloseHandle(token) };
+        sid
+    }
+
+    fn token_user_sid(token: HANDLE) -> io::Result<String> {
+        let mut len = 0u32;
+        // SAFETY: asks only for the size.
+        unsafe { GetTokenInformation(token, TokenUser, ptr::null_mut(), 0, &mut len) };
+        // `u64`s keep the `TOKEN_USER` at the start aligned.
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        // SAFETY: `buffer` holds at least `len` bytes.
+        let ok = unsafe {
+            GetTokenInformation(token, TokenUser, buffer.as_mut_ptr().cast(), len, &mut len)
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut wide = ptr::null_mut();
+        // SAFETY: on success the buffer starts with a `TOKEN_USER` whose SID
+        // points into the same buffer; `wide` is a `LocalAlloc` string freed
+        // below.
+        unsafe {
+            let sid = (*buffer.as_ptr().cast::<TOKEN_USER>()).User.Sid;
+            if ConvertSidToStringSidW(sid, &mut wide) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let len = (0..).take_while(|&i| *wide.add(i) != 0).count();
+            let sid = String::from_utf16_lossy(std::slice::from_raw_parts(wide, len));
+            LocalFree(wide as isize);
+            Ok(sid)
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod imp {
+    use std::{ffi::c_void, fs::File, io};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "pipe access control is only available on Windows",
+        )
+    }
+
+    pub struct OwnerOnly(());
+
+    impl OwnerOnly {
+        pub fn new() -> io::Result<Self> {
+            Err(unsupported())
+        }
+
+        pub fn as_raw(&mut self) -> *mut c_void {
+            std::ptr::null_mut()
+        }
+    }
+
+    pub fn server_is_current_user(_pipe: &File) -> io::Result<bool> {
+        Err(unsupported())
+    }
+}
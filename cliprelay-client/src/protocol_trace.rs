@@ -0,0 +1,248 @@
+//! Protocol trace for diagnosing sync problems: one metadata summary per
+//! frame sent to or received from the relay, plus one per decrypted event.
+//!
+//! Summaries carry message types, device ids, counters, sizes and times.
+//! Clipboard text, file contents and the room code never appear in them.
+
+use std::collections::VecDeque;
+
+use cliprelay_core::{ClipboardEventPlaintext, ControlMessage, WireMessage};
+
+/// Summaries kept for the trace window; older ones are dropped.
+pub const MAX_TRACE_ENTRIES: usize = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    Sent,
+    Received,
+}
+
+impl TraceDirection {
+    pub fn label(self) -> &'static str {
+        match self {
+            TraceDirection::Sent => "->",
+            TraceDirection::Received => "<-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSummary {
+    pub ts_unix_ms: u64,
+    pub direction: TraceDirection,
+    /// Message type, e.g. `"Hello"`, `"PeerList"`, `"Encrypted"`.
+    pub kind: &'static str,
+    pub sender: Option<String>,
+    pub counter: Option<u64>,
+    /// Size on the wire (the whole frame) or, for decrypted events, of the
+    /// plaintext payload.
+    pub bytes: usize,
+    pub detail: String,
+}
+
+/// Shorten a device id for display.
+fn short_id(id: &str) -> String {
+    id.chars().take(8).collect()
+}
+
+/// Summarize a frame crossing the websocket.
+pub fn summarize_frame(
+    message: &WireMessage,
+    direction: TraceDirection,
+    frame_bytes: usize,
+    ts_unix_ms: u64,
+) -> FrameSummary {
+    let (kind, sender, counter, detail) = match message {
+        WireMessage::Control(control) => {
+            let (kind, sender, detail) = match control {
+                ControlMessage::Hello(hello) => (
+                    "Hello",
+                    Some(hello.peer.device_id.clone()),
+                    format!(
+                        "room {} as \"{}\"",
+                        short_id(&hello.room_id),
+                        hello.peer.device_name
+                    ),
+                ),
+                ControlMessage::PeerList(list) => {
+                    ("PeerList", None, format!("{} peers", list.peers.len()))
+                }
+                ControlMessage::PeerJoined(joined) => (
+                    "PeerJoined",
+                    Some(joined.peer.device_id.clone()),
+                    format!("\"{}\"", joined.peer.device_name),
+                ),
+                ControlMessage::PeerLeft(left) => {
+                    ("PeerLeft", Some(left.device_id.clone()), String::new())
+                }
+                ControlMessage::SaltExchange(exchange) => (
+                    "SaltExchange",
+                    None,
+                    format!("{} device ids", exchange.device_ids.len()),
+                ),
+                ControlMessage::Error { message, .. } => ("Error", None, message.clone()),
+                ControlMessage::Maintenance(notice) => {
+                    ("Maintenance", None, format!("at {} ms", notice.at_unix_ms))
+                }
+                ControlMessage::Reconnect(notice) => {
+                    ("Reconnect", None, format!("at {} ms", notice.at_unix_ms))
+                }
+                ControlMessage::Probe(probe) => (
+                    "Probe",
+                    Some(probe.sender_device_id.clone()),
+                    format!("probe {}", probe.probe_id),
+                ),
+            };
+            (kind, sender, None, detail)
+        }
+        WireMessage::Encrypted(payload) => (
+            "Encrypted",
+            Some(payload.sender_device_id.clone()),
+            Some(payload.counter),
+            match payload.relay_seq {
+                Some(seq) => format!("{} B ciphertext, relay seq {seq}", payload.ciphertext.len()),
+                None => format!("{} B ciphertext", payload.ciphertext.len()),
+            },
+        ),
+    };
+    FrameSummary {
+        ts_unix_ms,
+        direction,
+        kind,
+        sender,
+        counter,
+        bytes: frame_bytes,
+        detail,
+    }
+}
+
+/// Summarize a received event after decryption: its MIME type, payload
+/// size, age (by the sender's clock) and whether it was signed, never its
+/// contents.
+pub fn summarize_event(event: &ClipboardEventPlaintext, ts_unix_ms: u64) -> FrameSummary {
+    FrameSummary {
+        ts_unix_ms,
+        direction: TraceDirection::Received,
+        kind: "Decrypted",
+        sender: Some(event.sender_device_id.clone()),
+        counter: Some(event.counter),
+        bytes: event.text_utf8.len(),
+        detail: format!(
+            "{} | age {} ms | {}",
+            event.mime,
+            // Negative when the sender's clock is ahead of ours.
+            ts_unix_ms as i128 - event.timestamp_unix_ms as i128,
+            if event.signature.is_some() {
+                "signed"
+            } else {
+                "unsigned"
+            }
+        ),
+    }
+}
+
+/// One line of plain text for copying, without the timestamp (the caller
+/// formats it in local time).
+pub fn format_summary(summary: &FrameSummary) -> String {
+    let mut line = format!("{} {:<12}", summary.direction.label(), summary.kind);
+    if let Some(sender) = &summary.sender {
+        line.push_str(&format!(" from {}", short_id(sender)));
+    }
+    if let Some(counter) = summary.counter {
+        line.push_str(&format!(" #{counter}"));
+    }
+    line.push_str(&format!(" {} B", summary.bytes));
+    if !summary.detail.is_empty() {
+        line.push_str(&format!(" | {}", summary.detail));
+    }
+    line
+}
+
+/// Bounded buffer of summaries shown by the trace window.
+#[derive(Debug, Default)]
+pub struct TraceLog {
+    entries: VecDeque<FrameSummary>,
+}
+
+impl TraceLog {
+    pub fn push(&mut self, summary: FrameSummary) {
+        if self.entries.len() >= MAX_TRACE_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(summary);
+    }
+
+    pub fn entries(&self) -> &VecDeque<FrameSummary> {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cliprelay_core::{EncryptedPayload, Hello, PeerInfo};
+
+    #[test]
+    fn encrypted_frames_report_metadata_only() {
+        let message = WireMessage::Encrypted(EncryptedPayload {
+            sender_device_id: "0123456789abcdef".to_owned(),
+            counter: 42,
+            ciphertext: vec![0; 300],
+            relay_seq: None,
+        });
+        let summary = summarize_frame(&message, TraceDirection::Received, 350, 1);
+        assert_eq!(summary.kind, "Encrypted");
+        assert_eq!(summary.counter, Some(42));
+        assert_eq!(
+            format_summary(&summary),
+            "<- Encrypted    from 01234567 #42 350 B | 300 B ciphertext"
+        );
+    }
+
+    #[test]
+    fn decrypted_events_never_include_text() {
+        let event = ClipboardEventPlaintext {
+            sender_device_id: "dev".to_owned(),
+            counter: 7,
+            timestamp_unix_ms: 1_700_000_000_000,
+            mime: "text/plain".to_owned(),
+            text_utf8: "super secret clipboard".to_owned(),
+            pinned: false,
+            label: None,
+            signature: None,
+        };
+        let line = format_summary(&summarize_event(&event, 1_700_000_000_250));
+        assert!(!line.contains("secret"));
+        assert!(line.contains("age 250 ms"));
+        assert!(line.contains("text/plain"));
+        assert!(line.contains("22 B"));
+    }
+
+    #[test]
+    fn hello_and_buffer_bound() {
+        let hello = WireMessage::Control(ControlMessage::Hello(Hello {
+            room_id: "roomidroomid".to_owned(),
+            peer: PeerInfo {
+                device_id: "dev-1".to_owned(),
+                device_name: "Laptop".to_owned(),
+                sealed_chunks: true,
+            },
+            sequenced: true,
+            probes: false,
+        }));
+        let summary = summarize_frame(&hello, TraceDirection::Sent, 90, 3);
+        assert_eq!(summary.detail, "room roomidro as \"Laptop\"");
+
+        let mut log = TraceLog::default();
+        for _ in 0..MAX_TRACE_ENTRIES + 5 {
+            log.push(summary.clone());
+        }
+        assert_eq!(log.entries().len(), MAX_TRACE_ENTRIES);
+        log.clear();
+        assert!(log.entries().is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! When and how often the client reconnects after losing the relay.
+//!
+//! The delay doubles after each failed attempt, from the initial delay up to
+//! the maximum.  With an attempt limit the client gives up after that many
+//! consecutive failures (a wrong server URL, say) until the user retries.
+//! While it waits, the connection status counts down the seconds left, and
+//! "Reconnect Now" cuts the wait short.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Longest delay that can be configured.
+pub const MAX_DELAY_LIMIT_SECS: u32 = 60 * 60;
+
+/// Largest attempt limit that can be configured.
+pub const MAX_ATTEMPTS_LIMIT: u32 = 1_000;
+
+const COUNTDOWN_PREFIX: &str = "Reconnecting in ";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    #[serde(default = "default_initial_delay_secs")]
+    pub initial_delay_secs: u32,
+    #[serde(default = "default_max_delay_secs")]
+    pub max_delay_secs: u32,
+    /// Consecutive failed attempts before giving up; `None` retries forever.
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+}
+
+fn default_initial_delay_secs() -> u32 {
+    5
+}
+
+fn default_max_delay_secs() -> u32 {
+    60
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_secs: default_initial_delay_secs(),
+            max_delay_secs: default_max_delay_secs(),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Clamp values from a hand-edited file or the Options tab into range.
+    pub fn normalized(self) -> Self {
+        let initial_delay_secs = self.initial_delay_secs.clamp(1, MAX_DELAY_LIMIT_SECS);
+        Self {
+            initial_delay_secs,
+            max_delay_secs: self
+                .max_delay_secs
+                .clamp(initial_delay_secs, MAX_DELAY_LIMIT_SECS),
+            max_attempts: self.max_attempts.map(|n| n.clamp(1, MAX_ATTEMPTS_LIMIT)),
+        }
+    }
+
+    /// Delay before the next attempt after `failures` consecutive failed
+    /// ones (at least 1).
+    pub fn delay(self, failures: u32) -> Duration {
+        let policy = self.normalized();
+        let doublings = failures.saturating_sub(1).min(31);
+        let secs = u64::from(policy.initial_delay_secs)
+            .saturating_mul(1_u64 << doublings)
+            .min(u64::from(policy.max_delay_secs));
+        Duration::from_secs(secs)
+    }
+
+    /// Whether to stop after `failures` consecutive failed attempts.
+    pub fn gives_up(self, failures: u32) -> bool {
+        self.normalized()
+            .max_attempts
+            .is_some_and(|max| failures >= max)
+    }
+}
+
+/// Connection status while waiting, e.g. `"Reconnecting in 12s…"`.  Part
+/// seconds round up, so the label shows 1s rather than 0s until the attempt.
+pub fn countdown_status(remaining: Duration) -> String {
+    let secs = remaining.as_millis().div_ceil(1_000);
+    format!("{COUNTDOWN_PREFIX}{secs}s…")
+}
+
+/// Whether `status` is a [`countdown_status`], i.e. the client is waiting
+/// for the next attempt.
+pub fn is_waiting(status: &str) -> bool {
+    status.starts_with(COUNTDOWN_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let policy = ReconnectPolicy::default();
+        let secs: Vec<u64> = (1..=6).map(|n| policy.delay(n).as_secs()).collect();
+        assert_eq!(secs, [5, 10, 20, 40, 60, 60]);
+        assert_eq!(policy.delay(u32::MAX).as_secs(), 60);
+    }
+
+    #[test]
+    fn gives_up_only_with_a_limit() {
+        assert!(!ReconnectPolicy::default().gives_up(10_000));
+        let limited = ReconnectPolicy {
+            max_attempts: Some(3),
+            ..ReconnectPolicy::default()
+        };
+        assert!(!limited.gives_up(2));
+        assert!(limited.gives_up(3));
+    }
+
+    #[test]
+    fn countdown_rounds_up_and_is_recognised() {
+        let status = countdown_status(Duration::from_millis(4_200));
+        assert_eq!(status, "Reconnecting in 5s…");
+        assert!(is_waiting(&status));
+        assert_eq!(
+            countdown_status(Duration::from_millis(1)),
+            "Reconnecting in 1s…"
+        );
+        assert!(!is_waiting("Connecting"));
+    }
+
+    #[test]
+    fn normalizes_out_of_range_values() {
+        let policy = ReconnectPolicy {
+            initial_delay_secs: 0,
+            max_delay_secs: 0,
+            max_attempts: Some(0),
+        }
+        .normalized();
+        assert_eq!(policy.initial_delay_secs, 1);
+        assert_eq!(policy.max_delay_secs, 1);
+        assert_eq!(policy.max_attempts, Some(1));
+    }
+}
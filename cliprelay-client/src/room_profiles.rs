@@ -0,0 +1,213 @@
+//! Named room profiles ("Home", "Work", …) for switching between rooms from
+//! the tray menu without retyping the room details.
+//!
+//! Profiles are stored in `%LOCALAPPDATA%\ClipRelay\profiles.json`.  As in
+//! `config.json`, each room code is sealed with DPAPI where it is available.
+//! The active room itself is still whatever `config.json` holds; switching
+//! profile rewrites that file and reconnects.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config_profiles,
+    dpapi::{self, AtRest},
+};
+
+/// Upper bound on saved profiles; also keeps the tray submenu usable.
+pub const MAX_PROFILES: usize = 20;
+
+/// Longest profile name accepted (in characters).
+pub const MAX_PROFILE_NAME_CHARS: usize = 40;
+
+/// Defensive bound on `profiles.json`.
+pub const MAX_PROFILES_FILE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomProfile {
+    pub name: String,
+    pub server_url: String,
+    pub room_code: String,
+    pub device_name: String,
+}
+
+impl RoomProfile {
+    /// Whether this profile describes the given room details.
+    pub fn matches(&self, server_url: &str, room_code: &str, device_name: &str) -> bool {
+        self.server_url == server_url
+            && self.room_code == room_code
+            && self.device_name == device_name
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoomProfiles {
+    pub profiles: Vec<RoomProfile>,
+    /// Name of the profile most recently switched to.
+    pub last_used: Option<String>,
+}
+
+impl RoomProfiles {
+    /// Save `profile`, replacing any existing profile of the same name.
+    /// Returns `false` when it is new and the list is full.
+    pub fn upsert(&mut self, profile: RoomProfile) -> bool {
+        if let Some(existing) = self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            *existing = profile;
+            return true;
+        }
+        if self.profiles.len() >= MAX_PROFILES {
+            return false;
+        }
+        self.profiles.push(profile);
+        true
+    }
+
+    pub fn get(&self, index: usize) -> Option<&RoomProfile> {
+        self.profiles.get(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<RoomProfile> {
+        if index >= self.profiles.len() {
+            return None;
+        }
+        let removed = self.profiles.remove(index);
+        if self.last_used.as_deref() == Some(removed.name.as_str()) {
+            self.last_used = None;
+        }
+        Some(removed)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Index of the profile for the current room: the last-used profile if
+    /// it still matches, otherwise the first profile that does.
+    pub fn active_index(
+        &self,
+        server_url: &str,
+        room_code: &str,
+        device_name: &str,
+    ) -> Option<usize> {
+        let matching = |p: &RoomProfile| p.matches(server_url, room_code, device_name);
+        self.profiles
+            .iter()
+            .position(|p| self.last_used.as_deref() == Some(p.name.as_str()) && matching(p))
+            .or_else(|| self.profiles.iter().position(matching))
+    }
+}
+
+/// Trim and bound a user-entered name; empty means none.
+pub fn normalize_profile_name(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.chars().take(MAX_PROFILE_NAME_CHARS).collect())
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProfile {
+    name: String,
+    server_url: String,
+    room_code: AtRest<String>,
+    device_name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProfiles {
+    #[serde(default)]
+    profiles: Vec<StoredProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_used: Option<String>,
+}
+
+fn invalid(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Serialize `profiles`, sealing each room code with `protect` when `seal`
+/// is set.
+pub fn encode_profiles(
+    profiles: &RoomProfiles,
+    seal: bool,
+    protect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    let stored = StoredProfiles {
+        profiles: profiles
+            .profiles
+            .iter()
+            .map(|p| {
+                Ok(StoredProfile {
+                    name: p.name.clone(),
+                    server_url: p.server_url.clone(),
+                    room_code: AtRest::new(p.room_code.clone(), seal, &protect)?,
+                    device_name: p.device_name.clone(),
+                })
+            })
+            .collect::<io::Result<_>>()?,
+        last_used: profiles.last_used.clone(),
+    };
+    serde_json::to_string_pretty(&stored).map_err(invalid)
+}
+
+/// Inverse of [`encode_profiles`].
+pub fn decode_profiles(
+    json: &str,
+    unprotect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<RoomProfiles> {
+    let stored: StoredProfiles = serde_json::from_str(json).map_err(invalid)?;
+    let profiles = stored
+        .profiles
+        .into_iter()
+        .map(|p| {
+            Ok(RoomProfile {
+                name: p.name,
+                server_url: p.server_url,
+                room_code: p.room_code.open(&unprotect)?,
+                device_name: p.device_name,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(RoomProfiles {
+        profiles,
+        last_used: stored.last_used,
+    })
+}
+
+pub fn profiles_path() -> PathBuf {
+    config_profiles::data_dir().join("profiles.json")
+}
+
+pub fn load_profiles_from_path(path: &Path) -> io::Result<RoomProfiles> {
+    let meta = fs::metadata(path)?;
+    if meta.len() > MAX_PROFILES_FILE_BYTES {
+        return Err(invalid(format!(
+            "file too large: {} bytes (max {MAX_PROFILES_FILE_BYTES})",
+            meta.len()
+        )));
+    }
+    decode_profiles(&fs::read_to_string(path)?, dpapi::unprotect)
+}
+
+pub fn save_profiles_to_path(path: &Path, profiles: &RoomProfiles) -> io::Result<()> {
+    let payload = encode_profiles(profiles, dpapi::is_supported(), dpapi::protect)?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, payload.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+pub fn load_profiles() -> io::Result<RoomProfiles> {
+    match load_profiles_from_path(&profiles_path()) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(RoomProfiles::default()),
+        other => other,
+    }
+}
+
+pub fn save_profiles(profiles: &RoomProfiles) -> io::Result<()> {
+    save_profiles_to_path(&profiles_path(), profiles)
+}
@@ -0,0 +1,163 @@
+//! Loopback self-test: a short-lived virtual peer joins this device's room,
+//! sends an encrypted probe through the relay, and the report records how
+//! far the probe got (connect → join → key exchange → delivery → decrypt →
+//! clipboard apply).
+//!
+//! The probe uses its own MIME type, so other devices in the room (including
+//! older clients) decrypt and silently ignore it instead of applying it.
+
+/// MIME type of the self-test probe.
+pub const MIME_SELF_TEST: &str = "application/x-cliprelay-self-test";
+
+/// Name the virtual peer advertises to the room.
+pub const VIRTUAL_DEVICE_NAME: &str = "ClipRelay self-test";
+
+/// Device id used by the virtual peer of `device_id`.  Derived rather than
+/// random so the runtime can recognise (and hide) it without extra state.
+pub fn virtual_device_id(device_id: &str) -> String {
+    format!("{device_id}-selftest")
+}
+
+/// Probe text carrying a per-run nonce, so a stale probe is never mistaken
+/// for the current one.
+pub fn probe_text(nonce: u64) -> String {
+    format!("ClipRelay self-test probe {nonce:016x}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestStep {
+    Connect,
+    JoinRoom,
+    KeyExchange,
+    Deliver,
+    Decrypt,
+    Apply,
+}
+
+impl SelfTestStep {
+    pub const ALL: [SelfTestStep; 6] = [
+        SelfTestStep::Connect,
+        SelfTestStep::JoinRoom,
+        SelfTestStep::KeyExchange,
+        SelfTestStep::Deliver,
+        SelfTestStep::Decrypt,
+        SelfTestStep::Apply,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SelfTestStep::Connect => "Connect to relay",
+            SelfTestStep::JoinRoom => "Join room as virtual peer",
+            SelfTestStep::KeyExchange => "Agree on room key",
+            SelfTestStep::Deliver => "Relay probe to this device",
+            SelfTestStep::Decrypt => "Decrypt probe",
+            SelfTestStep::Apply => "Apply probe to clipboard",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    Passed,
+    Failed(String),
+    /// Not reached because an earlier step failed.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub results: Vec<(SelfTestStep, StepOutcome)>,
+}
+
+impl Default for SelfTestReport {
+    fn default() -> Self {
+        Self {
+            results: SelfTestStep::ALL
+                .iter()
+                .map(|step| (*step, StepOutcome::Skipped))
+                .collect(),
+        }
+    }
+}
+
+impl SelfTestReport {
+    fn set(&mut self, step: SelfTestStep, outcome: StepOutcome) {
+        if let Some(slot) = self.results.iter_mut().find(|(s, _)| *s == step) {
+            slot.1 = outcome;
+        }
+    }
+
+    pub fn pass(&mut self, step: SelfTestStep) {
+        self.set(step, StepOutcome::Passed);
+    }
+
+    pub fn fail(&mut self, step: SelfTestStep, reason: impl Into<String>) {
+        self.set(step, StepOutcome::Failed(reason.into()));
+    }
+
+    pub fn passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|(_, outcome)| *outcome == StepOutcome::Passed)
+    }
+
+    /// One-line result, e.g. `"Self-test failed: Decrypt probe (decryption failed)"`.
+    pub fn summary(&self) -> String {
+        if self.passed() {
+            return "Self-test passed".to_owned();
+        }
+        match self
+            .results
+            .iter()
+            .find_map(|(step, outcome)| match outcome {
+                StepOutcome::Failed(reason) => Some((step, reason)),
+                _ => None,
+            }) {
+            Some((step, reason)) => format!("Self-test failed: {} ({reason})", step.label()),
+            None => "Self-test incomplete".to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresh_report_is_all_skipped() {
+        let report = SelfTestReport::default();
+        assert_eq!(report.results.len(), SelfTestStep::ALL.len());
+        assert!(!report.passed());
+        assert_eq!(report.summary(), "Self-test incomplete");
+    }
+
+    #[test]
+    fn summary_names_first_failure() {
+        let mut report = SelfTestReport::default();
+        report.pass(SelfTestStep::Connect);
+        report.pass(SelfTestStep::JoinRoom);
+        report.fail(SelfTestStep::KeyExchange, "timed out");
+        assert!(!report.passed());
+        assert_eq!(
+            report.summary(),
+            "Self-test failed: Agree on room key (timed out)"
+        );
+        assert_eq!(report.results[3].1, StepOutcome::Skipped);
+    }
+
+    #[test]
+    fn all_steps_passing_passes() {
+        let mut report = SelfTestReport::default();
+        for step in SelfTestStep::ALL {
+            report.pass(step);
+        }
+        assert!(report.passed());
+        assert_eq!(report.summary(), "Self-test passed");
+    }
+
+    #[test]
+    fn probe_and_virtual_id_are_distinct_per_input() {
+        assert_ne!(probe_text(1), probe_text(2));
+        assert_eq!(virtual_device_id("abc"), "abc-selftest");
+    }
+}
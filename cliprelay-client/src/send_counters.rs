@@ -0,0 +1,99 @@
+//! Send counters, kept per room in the saved config.
+//!
+//! Every event a device sends carries a counter that receivers use to reject
+//! replays, so it must only ever grow within a room.  Older versions kept a
+//! single `last_counter` for whichever room was configured; switching rooms
+//! or profiles then either reset it (risking replay rejections) or carried
+//! one room's count into another.  Counters are now stored by room id.
+//!
+//! `last_counter` is still written, as the highest counter sent in any room:
+//! it is the starting point for a room with no entry yet (including rooms
+//! used before the per-room map existed), and keeps counters monotonic if an
+//! older version is run against the same config.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Saved state for one room.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomState {
+    pub last_counter: u64,
+}
+
+/// Flattened into the stored client config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendCounters {
+    /// Highest counter sent in any room (the legacy single counter).
+    #[serde(default)]
+    pub last_counter: u64,
+    /// By room id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rooms: BTreeMap<String, RoomState>,
+}
+
+impl SendCounters {
+    /// Counter to continue from in `room_id`.
+    pub fn counter_for(&self, room_id: &str) -> u64 {
+        self.rooms
+            .get(room_id)
+            .map_or(self.last_counter, |room| room.last_counter)
+    }
+
+    /// Record `counter` as sent in `room_id`.  Counters never go back.
+    pub fn record(&mut self, room_id: &str, counter: u64) {
+        let room = self.rooms.entry(room_id.to_owned()).or_default();
+        room.last_counter = room.last_counter.max(counter);
+        self.last_counter = self.last_counter.max(counter);
+    }
+
+    /// Give the legacy counter to `room_id`, the room it was last used in.
+    /// Returns `true` when there was anything to migrate.
+    pub fn migrate_legacy(&mut self, room_id: &str) -> bool {
+        if !self.rooms.is_empty() || self.last_counter == 0 {
+            return false;
+        }
+        let counter = self.last_counter;
+        self.record(room_id, counter);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_kept_per_room() {
+        let mut counters = SendCounters::default();
+        counters.record("room-a", 40);
+        counters.record("room-b", 7);
+        counters.record("room-b", 3);
+        assert_eq!(counters.counter_for("room-a"), 40);
+        assert_eq!(counters.counter_for("room-b"), 7);
+        // A new room starts above anything sent before.
+        assert_eq!(counters.counter_for("room-c"), 40);
+    }
+
+    #[test]
+    fn legacy_counter_moves_to_the_configured_room() {
+        let mut counters: SendCounters = serde_json::from_str(r#"{"last_counter":12}"#).unwrap();
+        assert!(counters.migrate_legacy("room-a"));
+        assert!(!counters.migrate_legacy("room-b"));
+        assert_eq!(counters.counter_for("room-a"), 12);
+
+        let json = serde_json::to_value(&counters).unwrap();
+        assert_eq!(json["last_counter"], 12);
+        assert_eq!(json["rooms"]["room-a"]["last_counter"], 12);
+    }
+
+    #[test]
+    fn nothing_to_migrate_from_a_fresh_config() {
+        let mut counters = SendCounters::default();
+        assert!(!counters.migrate_legacy("room-a"));
+        assert_eq!(
+            serde_json::to_string(&counters).unwrap(),
+            r#"{"last_counter":0}"#
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Windows shell integration: Explorer's "Send with ClipRelay" context-menu
+//! entry, the "Send To" shortcut and the `cliprelay://` link handler.
+//!
+//! The entry is a per-user shell verb on all files
+//! (`HKCU\Software\Classes\*\shell\ClipRelay`).  Explorer starts
+//! `cliprelay-client.exe --send-file "<path>"` once per selected file; that
+//! short-lived process hands the path to the running client (see
+//! [`crate::instance`]) and exits.
+//!
+//! The "Send To" submenu works the same way through a `ClipRelay` shortcut
+//! in the user's SendTo folder, which runs `--send-file` with every selected
+//! file appended.
+//!
+//! Opening a `cliprelay://` link (see [`crate::join_link`]) runs
+//! `--join-link "<link>"`, which hands the link to the running client the
+//! same way.
+
+use std::path::{Path, PathBuf};
+
+/// Text of the menu entry.
+pub const VERB_LABEL: &str = "Send with ClipRelay";
+
+/// Registry key (under `HKEY_CURRENT_USER`) holding the verb.
+pub const VERB_SUBKEY: &str = r"Software\Classes\*\shell\ClipRelay";
+
+/// Registry key (under `HKEY_CURRENT_USER`) of the `cliprelay:` protocol.
+pub const URL_PROTOCOL_SUBKEY: &str = r"Software\Classes\cliprelay";
+
+/// Name of the shortcut in the SendTo folder; Explorer shows the stem.
+pub const SEND_TO_SHORTCUT: &str = "ClipRelay.lnk";
+
+/// Command line Explorer runs for the verb.
+pub fn verb_command(exe: &Path) -> String {
+    format!("\"{}\" --send-file \"%1\"", exe.display())
+}
+
+/// Command line Windows runs for a `cliprelay://` link.
+pub fn url_handler_command(exe: &Path) -> String {
+    format!("\"{}\" --join-link \"%1\"", exe.display())
+}
+
+/// `%APPDATA%\Microsoft\Windows\SendTo`
+pub fn send_to_dir() -> Option<PathBuf> {
+    let appdata = std::env::var_os("APPDATA")?;
+    Some(
+        PathBuf::from(appdata)
+            .join("Microsoft")
+            .join("Windows")
+            .join("SendTo"),
+    )
+}
+
+pub fn send_to_shortcut(send_to_dir: &Path) -> PathBuf {
+    send_to_dir.join(SEND_TO_SHORTCUT)
+}
+
+/// Whether the SendTo shortcut exists.
+pub fn send_to_installed() -> bool {
+    send_to_dir().is_some_and(|dir| send_to_shortcut(&dir).is_file())
+}
+
+#[cfg(target_os = "windows")]
+pub use windows_impl::{
+    is_registered, is_url_handler_registered, set_registered, set_send_to, set_url_handler,
+};
+
+#[cfg(not(target_os = "windows"))]
+pub use fallback::{
+    is_registered, is_url_handler_registered, set_registered, set_send_to, set_url_handler,
+};
+
+#[cfg(target_os = "windows")]
+mod windows_impl {
+    use std::{io, os::windows::process::CommandExt, path::Path, process::Command, ptr};
+
+    use windows_sys::Win32::{
+        Foundation::ERROR_FILE_NOT_FOUND,
+        System::Registry::{
+            HKEY_CURRENT_USER, REG_SZ, RRF_RT_REG_SZ, RegDeleteTreeW, RegGetValueW, RegSetKeyValueW,
+        },
+    };
+
+    use super::{
+        URL_PROTOCOL_SUBKEY, VERB_LABEL, VERB_SUBKEY, send_to_dir, send_to_shortcut,
+        url_handler_command, verb_command,
+    };
+
+    /// Keeps powershell.exe from flashing a console window.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    /// Writes the shortcut through the WScript.Shell COM object.  The
+    /// values come in through environment variables so that no quoting of
+    /// paths is needed.
+    const SHORTCUT_SCRIPT: &str = "$s = (New-Object -ComObject WScript.Shell)\
+        .CreateShortcut($env:CLIPRELAY_LNK); \
+        $s.TargetPath = $env:CLIPRELAY_EXE; \
+        $s.Arguments = '--send-file'; \
+        $s.IconLocation = $env:CLIPRELAY_EXE; \
+        $s.Description = 'Send with ClipRelay'; \
+        $s.Save()";
+
+    fn wide_null(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    fn verb_command_subkey() -> String {
+        format!(r"{VERB_SUBKEY}\command")
+    }
+
+    fn url_command_subkey() -> String {
+        format!(r"{URL_PROTOCOL_SUBKEY}\shell\open\command")
+    }
+
+    /// Write a string value, creating the key if needed.  `None` is the
+    /// key's default value.
+    fn set_string(subkey: &str, name: Option<&str>, value: &str) -> io::Result<()> {
+        let subkey = wide_null(subkey);
+        let name = name.map(wide_null);
+        let data = wide_null(value);
+        let status = unsafe {
+            RegSetKeyValueW(
+                HKEY_CURRENT_USER,
+                subkey.as_ptr(),
+                name.as_ref().map_or(ptr::null(), |n| n.as_ptr()),
+                REG_SZ,
+                data.as_ptr().cast(),
+                (data.len() * 2) as u32,
+            )
+        };
+        if status != 0 {
+            return Err(io::Error::from_raw_os_error(status as i32));
+        }
+        Ok(())
+    }
+
+    /// Whether the default value of `subkey` is `expected`.
+    fn command_matches(subkey: &str, expected: &str) -> bool {
+        let subkey = wide_null(subkey);
+        let mut buf = vec![0_u16; 2048];
+        let mut size = (buf.len() * 2) as u32;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                subkey.as_ptr(),
+                ptr::null(),
+                RRF_RT_REG_SZ,
+                ptr::null_mut(),
+                buf.as_mut_ptr().cast(),
+                &mut size,
+            )
+        };
+        if status != 0 {
+            return false;
+        }
+        buf.truncate(size as usize / 2);
+        while buf.last() == Some(&0) {
+            buf.pop();
+        }
+        String::from_utf16(&buf).is_ok_and(|cmd| cmd == expected)
+    }
+
+    fn delete_tree(subkey: &str) -> io::Result<()> {
+        let subkey = wide_null(subkey);
+        let status = unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, subkey.as_ptr()) };
+        if status != 0 && status != ERROR_FILE_NOT_FOUND {
+            return Err(io::Error::from_raw_os_error(status as i32));
+        }
+        Ok(())
+    }
+
+    /// Whether the verb is registered and runs `exe`.
+    pub fn is_registered(exe: &Path) -> bool {
+        command_matches(&verb_command_subkey(), &verb_command(exe))
+    }
+
+    pub fn set_registered(exe: &Path, enabled: bool) -> io::Result<()> {
+        if !enabled {
+            return delete_tree(VERB_SUBKEY);
+        }
+        set_string(VERB_SUBKEY, None, VERB_LABEL)?;
+        set_string(VERB_SUBKEY, Some("Icon"), &exe.display().to_string())?;
+        // Keep the entry when more than 15 files are selected.
+        set_string(VERB_SUBKEY, Some("MultiSelectModel"), "Player")?;
+        set_string(&verb_command_subkey(), None, &verb_command(exe))
+    }
+
+    /// Whether `cliprelay://` links open in `exe`.
+    pub fn is_url_handler_registered(exe: &Path) -> bool {
+        command_matches(&url_command_subkey(), &url_handler_command(exe))
+    }
+
+    pub fn set_url_handler(exe: &Path, enabled: bool) -> io::Result<()> {
+        if !enabled {
+            return delete_tree(URL_PROTOCOL_SUBKEY);
+        }
+        set_string(URL_PROTOCOL_SUBKEY, None, "URL:ClipRelay room link")?;
+        set_string(URL_PROTOCOL_SUBKEY, Some("URL Protocol"), "")?;
+        set_string(
+            &format!(r"{URL_PROTOCOL_SUBKEY}\DefaultIcon"),
+            None,
+            &exe.display().to_string(),
+        )?;
+        set_string(&url_command_subkey(), None, &url_handler_command(exe))
+    }
+
+    /// Create or remove the SendTo shortcut.
+    pub fn set_send_to(exe: &Path, enabled: bool) -> io::Result<()> {
+        let dir = send_to_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "APPDATA is not set"))?;
+        let shortcut = send_to_shortcut(&dir);
+        if !enabled {
+            return match std::fs::remove_file(&shortcut) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        std::fs::create_dir_all(&dir)?;
+        let output = Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command", SHORTCUT_SCRIPT])
+            .env("CLIPRELAY_LNK", &shortcut)
+            .env("CLIPRELAY_EXE", exe)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()?;
+        if !output.status.success() || !shortcut.is_file() {
+            return Err(io::Error::other(format!(
+                "creating the shortcut failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod fallback {
+    use std::{io, path::Path};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the Explorer menu is only available on Windows",
+        )
+    }
+
+    pub fn is_registered(_exe: &Path) -> bool {
+        false
+    }
+
+    pub fn set_registered(_exe: &Path, _enabled: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn set_send_to(_exe: &Path, _enabled: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn is_url_handler_registered(_exe: &Path) -> bool {
+        false
+    }
+
+    pub fn set_url_handler(_exe: &Path, _enabled: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verb_quotes_the_executable_and_argument() {
+        assert_eq!(
+            verb_command(Path::new("/opt/ClipRelay/cliprelay-client")),
+            "\"/opt/ClipRelay/cliprelay-client\" --send-file \"%1\""
+        );
+        assert_eq!(
+            url_handler_command(Path::new("/opt/ClipRelay/cliprelay-client")),
+            "\"/opt/ClipRelay/cliprelay-client\" --join-link \"%1\""
+        );
+        assert_eq!(
+            send_to_shortcut(Path::new("/SendTo")),
+            Path::new("/SendTo/ClipRelay.lnk")
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Simulated second device for development and demos (`--simulate-peer`).
+//!
+//! The peer joins this device's room over its own relay connection with a
+//! derived device id, so everything it sends or receives goes through the
+//! real encrypt → relay → decrypt path.  It echoes received text back and
+//! sends a sample item at a fixed interval.  Its events are unsigned, so
+//! they show up as coming from an unverified device.
+
+use std::time::Duration;
+
+/// Name the simulated peer advertises to the room.
+pub const SIMULATED_DEVICE_NAME: &str = "Simulated peer";
+
+/// Time between two sample items.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wait before rejoining after the connection drops.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+const ECHO_PREFIX: &str = "Echo: ";
+
+const SAMPLE_TEXTS: [&str; 4] = [
+    "Hello from the simulated peer",
+    "https://example.com/cliprelay-demo",
+    "Line one\nLine two\nLine three",
+    "{\"sample\": true, \"items\": [1, 2, 3]}",
+];
+
+/// Device id used by the simulated peer of `device_id`.
+pub fn simulated_device_id(device_id: &str) -> String {
+    format!("{device_id}-simulated")
+}
+
+/// Reply to a received text, or `None` for text that is itself an echo
+/// (two simulated peers in one room would otherwise bounce forever).
+pub fn echo_reply(text: &str) -> Option<String> {
+    (!text.starts_with(ECHO_PREFIX)).then(|| format!("{ECHO_PREFIX}{text}"))
+}
+
+/// The `n`th sample item; the samples repeat in turn.
+pub fn sample_text(n: usize) -> String {
+    format!("{} (#{})", SAMPLE_TEXTS[n % SAMPLE_TEXTS.len()], n + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echoes_are_not_echoed_again() {
+        let echo = echo_reply("hello").unwrap();
+        assert_eq!(echo, "Echo: hello");
+        assert_eq!(echo_reply(&echo), None);
+    }
+
+    #[test]
+    fn samples_cycle_and_are_numbered() {
+        assert_eq!(sample_text(0), "Hello from the simulated peer (#1)");
+        assert!(sample_text(SAMPLE_TEXTS.len()).starts_with("Hello from the simulated peer"));
+        assert_ne!(simulated_device_id("abc"), "abc");
+    }
+}
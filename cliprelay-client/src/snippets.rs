@@ -0,0 +1,139 @@
+//! Named text snippets (Wi-Fi passwords, host names, addresses, …) that can
+//! be inserted into the send box or sent straight from the tray menu.
+//!
+//! Snippets are stored in `%LOCALAPPDATA%\ClipRelay\snippets.json`.  When
+//! encryption is enabled the list is sealed with Windows DPAPI (current user
+//! scope) so the file is unreadable to other accounts and on other machines.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config_profiles,
+    dpapi::{self, AtRest},
+};
+
+/// Upper bound on stored snippets; also keeps the tray submenu usable.
+pub const MAX_SNIPPETS: usize = 50;
+
+/// Longest snippet name accepted (in characters).
+pub const MAX_SNIPPET_NAME_CHARS: usize = 64;
+
+/// Defensive bound on `snippets.json`.
+pub const MAX_SNIPPETS_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnippetLibrary {
+    pub snippets: Vec<Snippet>,
+    /// Seal the file with DPAPI when saving.
+    pub encrypt_at_rest: bool,
+}
+
+impl SnippetLibrary {
+    /// Append a snippet unless the library is full.  Returns `false` when it
+    /// was not added.
+    pub fn add(&mut self, name: &str, text: &str) -> bool {
+        if self.snippets.len() >= MAX_SNIPPETS {
+            return false;
+        }
+        let name = normalize_snippet_name(name)
+            .unwrap_or_else(|| format!("Snippet {}", self.snippets.len() + 1));
+        self.snippets.push(Snippet {
+            name,
+            text: text.to_owned(),
+        });
+        true
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snippet> {
+        self.snippets.get(index)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.snippets.iter().map(|s| s.name.clone()).collect()
+    }
+}
+
+/// Trim and bound a user-entered name; empty means none.
+pub fn normalize_snippet_name(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.chars().take(MAX_SNIPPET_NAME_CHARS).collect())
+}
+
+fn invalid(err: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Serialize `library`, sealing the snippet list with `protect` when
+/// encryption is enabled.
+pub fn encode_snippets(
+    library: &SnippetLibrary,
+    protect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<String> {
+    let stored = AtRest::new(library.snippets.clone(), library.encrypt_at_rest, protect)?;
+    serde_json::to_string_pretty(&stored).map_err(invalid)
+}
+
+/// Inverse of [`encode_snippets`].
+pub fn decode_snippets(
+    json: &str,
+    unprotect: impl Fn(&[u8]) -> io::Result<Vec<u8>>,
+) -> io::Result<SnippetLibrary> {
+    let stored: AtRest<Vec<Snippet>> = serde_json::from_str(json).map_err(invalid)?;
+    Ok(SnippetLibrary {
+        encrypt_at_rest: stored.is_protected(),
+        snippets: stored.open(unprotect)?,
+    })
+}
+
+pub fn snippets_path() -> PathBuf {
+    config_profiles::data_dir().join("snippets.json")
+}
+
+pub fn load_snippets_from_path(path: &Path) -> io::Result<SnippetLibrary> {
+    let meta = fs::metadata(path)?;
+    if meta.len() > MAX_SNIPPETS_FILE_BYTES {
+        return Err(invalid(format!(
+            "file too large: {} bytes (max {MAX_SNIPPETS_FILE_BYTES})",
+            meta.len()
+        )));
+    }
+    let data = fs::read_to_string(path)?;
+    decode_snippets(&data, dpapi::unprotect)
+}
+
+pub fn save_snippets_to_path(path: &Path, library: &SnippetLibrary) -> io::Result<()> {
+    let payload = encode_snippets(library, dpapi::protect)?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, payload.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+pub fn load_snippets() -> io::Result<SnippetLibrary> {
+    match load_snippets_from_path(&snippets_path()) {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(SnippetLibrary::default()),
+        other => other,
+    }
+}
+
+pub fn save_snippets(library: &SnippetLibrary) -> io::Result<()> {
+    save_snippets_to_path(&snippets_path(), library)
+}
+
+/// Whether at-rest encryption is available on this platform.
+pub fn encryption_supported() -> bool {
+    dpapi::is_supported()
+}
@@ -0,0 +1,117 @@
+//! Notification sounds for incoming clipboard text and files, and the
+//! do-not-disturb schedule that silences them.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SoundSource {
+    /// The Windows "Notification" system sound (follows the sound scheme).
+    #[default]
+    SystemDefault,
+    /// A user-chosen `.wav` file.
+    CustomWav { path: PathBuf },
+}
+
+/// Daily do-not-disturb window in local time, `[start_hour, end_hour)`.
+/// A window whose end is before its start wraps past midnight (e.g. 22–7).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DndSchedule {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+impl Default for DndSchedule {
+    fn default() -> Self {
+        Self {
+            start_hour: 22,
+            end_hour: 7,
+        }
+    }
+}
+
+impl DndSchedule {
+    /// Whether `hour` (0–23, local time) falls inside the window.  An empty
+    /// window (`start == end`) never matches.
+    pub fn contains(&self, hour: u8) -> bool {
+        let (start, end) = (self.start_hour % 24, self.end_hour % 24);
+        if start <= end {
+            (start..end).contains(&hour)
+        } else {
+            hour >= start || hour < end
+        }
+    }
+}
+
+/// Whether `schedule` is set and covers the current local `hour`.
+pub fn dnd_active(schedule: Option<DndSchedule>, hour: u8) -> bool {
+    schedule.is_some_and(|s| s.contains(hour))
+}
+
+#[cfg(target_os = "windows")]
+pub fn play(source: &SoundSource) -> Result<(), String> {
+    use windows_sys::Win32::Media::Audio::{
+        PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT,
+    };
+
+    let (name, flags) = match source {
+        SoundSource::SystemDefault => ("Notification.Default".to_owned(), SND_ALIAS),
+        SoundSource::CustomWav { path } => {
+            if !path.is_file() {
+                return Err(format!("sound file not found: {}", path.display()));
+            }
+            (path.display().to_string(), SND_FILENAME)
+        }
+    };
+    let wide: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    // SND_ASYNC returns immediately; SND_NODEFAULT keeps a bad file from
+    // falling back to the generic "ding".
+    let ok = unsafe { PlaySoundW(wide.as_ptr(), 0, flags | SND_ASYNC | SND_NODEFAULT) };
+    if ok != 0 {
+        Ok(())
+    } else {
+        Err(format!("PlaySoundW failed for {name}"))
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn play(_source: &SoundSource) -> Result<(), String> {
+    Err("notification sounds are only supported on Windows".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_wraps_past_midnight() {
+        let night = DndSchedule {
+            start_hour: 22,
+            end_hour: 7,
+        };
+        assert!(night.contains(23));
+        assert!(night.contains(0));
+        assert!(night.contains(6));
+        assert!(!night.contains(7));
+        assert!(!night.contains(12));
+    }
+
+    #[test]
+    fn schedule_same_day_and_empty() {
+        let lunch = DndSchedule {
+            start_hour: 12,
+            end_hour: 13,
+        };
+        assert!(lunch.contains(12));
+        assert!(!lunch.contains(13));
+
+        let empty = DndSchedule {
+            start_hour: 5,
+            end_hour: 5,
+        };
+        assert!(!empty.contains(5));
+        assert!(!dnd_active(None, 5));
+    }
+}
@@ -0,0 +1,90 @@
+//! Startup gating for the `--background` (autostart) launch path.
+//!
+//! At login the network stack is often not ready when ClipRelay starts, so
+//! the first connection attempts fail and are logged as errors.  The gate
+//! optionally sleeps for a fixed delay and then waits until the relay host
+//! resolves before the runtime makes its first connection attempt.
+
+use url::Url;
+
+/// Upper bound on the configurable startup delay.
+pub const MAX_STARTUP_DELAY_SECS: u32 = 300;
+
+/// Delays offered in Options (seconds; `0` means no delay).
+pub const STARTUP_DELAY_OPTIONS: &[u32] = &[0, 10, 30, 60, 120];
+
+/// Give up waiting for the network after this long and connect anyway; the
+/// normal reconnect loop takes over from there.
+pub const NETWORK_WAIT_TIMEOUT_SECS: u64 = 120;
+
+/// Interval between network readiness probes.
+pub const NETWORK_POLL_INTERVAL_SECS: u64 = 3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StartupGate {
+    pub delay_secs: u32,
+    pub wait_for_network: bool,
+}
+
+impl StartupGate {
+    pub fn new(delay_secs: u32, wait_for_network: bool) -> Self {
+        Self {
+            delay_secs: delay_secs.min(MAX_STARTUP_DELAY_SECS),
+            wait_for_network,
+        }
+    }
+
+    pub fn is_noop(&self) -> bool {
+        self.delay_secs == 0 && !self.wait_for_network
+    }
+}
+
+/// Human-readable label for a delay option.
+pub fn delay_label(secs: u32) -> String {
+    match secs {
+        0 => "No delay".to_owned(),
+        s if s % 60 == 0 => format!("{} min", s / 60),
+        s => format!("{s} s"),
+    }
+}
+
+/// `(host, port)` whose DNS resolution signals that the network is up.
+pub fn network_probe_target(server_url: &str) -> Option<(String, u16)> {
+    let url = Url::parse(server_url).ok()?;
+    let host = url.host_str()?.to_owned();
+    let port = url.port_or_known_default()?;
+    Some((host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_clamps_delay() {
+        let gate = StartupGate::new(10_000, false);
+        assert_eq!(gate.delay_secs, MAX_STARTUP_DELAY_SECS);
+        assert!(StartupGate::new(0, false).is_noop());
+        assert!(!StartupGate::new(0, true).is_noop());
+    }
+
+    #[test]
+    fn probe_target_uses_scheme_default_port() {
+        assert_eq!(
+            network_probe_target("wss://relay.example.com/ws"),
+            Some(("relay.example.com".to_owned(), 443))
+        );
+        assert_eq!(
+            network_probe_target("ws://127.0.0.1:8080/ws"),
+            Some(("127.0.0.1".to_owned(), 8080))
+        );
+        assert_eq!(network_probe_target("not a url"), None);
+    }
+
+    #[test]
+    fn delay_labels() {
+        assert_eq!(delay_label(0), "No delay");
+        assert_eq!(delay_label(30), "30 s");
+        assert_eq!(delay_label(120), "2 min");
+    }
+}
@@ -0,0 +1,158 @@
+//! The status widget: a small borderless window kept above other windows
+//! that shows the connection state, the number of peers and the newest
+//! history item.
+//!
+//! It sits in a corner of the monitor's work area.  Dragged elsewhere, it
+//! snaps to the nearest corner when released.  With click-through on, the
+//! mouse goes to the windows underneath and the corner is picked in
+//! Options instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ui_layout::{DOCK_MARGIN_PX, ScreenRect};
+
+/// Widget width (logical pixels).
+pub const WIDGET_W_PX: f32 = 260.0;
+/// Widget height (logical pixels).
+pub const WIDGET_H_PX: f32 = 64.0;
+/// Characters of the newest item shown.
+pub const PREVIEW_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WidgetCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl WidgetCorner {
+    pub const ALL: [WidgetCorner; 4] = [
+        WidgetCorner::TopLeft,
+        WidgetCorner::TopRight,
+        WidgetCorner::BottomLeft,
+        WidgetCorner::BottomRight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WidgetCorner::TopLeft => "Top-left",
+            WidgetCorner::TopRight => "Top-right",
+            WidgetCorner::BottomLeft => "Bottom-left",
+            WidgetCorner::BottomRight => "Bottom-right",
+        }
+    }
+
+    /// The corner of `work` closest to the point `(x, y)`.
+    pub fn nearest(work: ScreenRect, x: i32, y: i32) -> WidgetCorner {
+        let left = x - work.left < work.right - x;
+        let top = y - work.top < work.bottom - y;
+        match (left, top) {
+            (true, true) => WidgetCorner::TopLeft,
+            (false, true) => WidgetCorner::TopRight,
+            (true, false) => WidgetCorner::BottomLeft,
+            (false, false) => WidgetCorner::BottomRight,
+        }
+    }
+
+    /// Top-left corner for a `width` x `height` widget in this corner of
+    /// `work`, kept inside it.
+    pub fn origin(self, work: ScreenRect, width: i32, height: i32) -> (i32, i32) {
+        let left = work.left + DOCK_MARGIN_PX;
+        let right = work.right - width - DOCK_MARGIN_PX;
+        let top = work.top + DOCK_MARGIN_PX;
+        let bottom = work.bottom - height - DOCK_MARGIN_PX;
+        let (x, y) = match self {
+            WidgetCorner::TopLeft => (left, top),
+            WidgetCorner::TopRight => (right, top),
+            WidgetCorner::BottomLeft => (left, bottom),
+            WidgetCorner::BottomRight => (right, bottom),
+        };
+        (x.max(work.left), y.max(work.top))
+    }
+}
+
+/// Status widget settings, saved in `ui_state.json`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusWidgetPrefs {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub corner: WidgetCorner,
+    /// Let clicks pass through to the windows underneath.
+    #[serde(default)]
+    pub click_through: bool,
+}
+
+/// First line of the widget, e.g. "Connected · 2 peers".
+pub fn status_line(connection_status: &str, peer_count: usize) -> String {
+    match peer_count {
+        1 => format!("{connection_status} · 1 peer"),
+        n => format!("{connection_status} · {n} peers"),
+    }
+}
+
+/// `summary` on one line, cut to [`PREVIEW_CHARS`] characters.
+pub fn preview(summary: &str) -> String {
+    let line = summary.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= PREVIEW_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(PREVIEW_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORK: ScreenRect = ScreenRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    #[test]
+    fn snaps_to_the_nearest_corner() {
+        assert_eq!(WidgetCorner::nearest(WORK, 100, 100), WidgetCorner::TopLeft);
+        assert_eq!(
+            WidgetCorner::nearest(WORK, 1800, 80),
+            WidgetCorner::TopRight
+        );
+        assert_eq!(
+            WidgetCorner::nearest(WORK, 10, 900),
+            WidgetCorner::BottomLeft
+        );
+        assert_eq!(
+            WidgetCorner::nearest(WORK, 1500, 700),
+            WidgetCorner::BottomRight
+        );
+    }
+
+    #[test]
+    fn corners_keep_the_dock_margin() {
+        assert_eq!(
+            WidgetCorner::TopLeft.origin(WORK, 260, 64),
+            (DOCK_MARGIN_PX, DOCK_MARGIN_PX)
+        );
+        assert_eq!(
+            WidgetCorner::BottomRight.origin(WORK, 260, 64),
+            (1920 - 260 - DOCK_MARGIN_PX, 1040 - 64 - DOCK_MARGIN_PX)
+        );
+        // Larger than the work area: pinned to its top-left corner.
+        assert_eq!(WidgetCorner::BottomRight.origin(WORK, 4000, 2000), (0, 0));
+    }
+
+    #[test]
+    fn preview_is_one_short_line() {
+        assert_eq!(preview("a\n  b"), "a b");
+        let long = "x".repeat(100);
+        let shown = preview(&long);
+        assert_eq!(shown.chars().count(), PREVIEW_CHARS);
+        assert!(shown.ends_with('…'));
+        assert_eq!(status_line("Connected", 1), "Connected · 1 peer");
+    }
+}
@@ -0,0 +1,74 @@
+//! Which way clipboard content may flow for this device.
+//!
+//! A work laptop can be set to receive-only so nothing copied on it is ever
+//! transmitted, or a kiosk to send-only so it never has content pushed onto
+//! its clipboard.  The setting is enforced in the runtime: outbound sends or
+//! inbound clipboard events are dropped there, not just hidden in the UI.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    #[default]
+    Both,
+    SendOnly,
+    ReceiveOnly,
+}
+
+impl SyncDirection {
+    pub const ALL: [SyncDirection; 3] = [
+        SyncDirection::Both,
+        SyncDirection::SendOnly,
+        SyncDirection::ReceiveOnly,
+    ];
+
+    pub fn allows_send(self) -> bool {
+        self != SyncDirection::ReceiveOnly
+    }
+
+    pub fn allows_receive(self) -> bool {
+        self != SyncDirection::SendOnly
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncDirection::Both => "Send and receive",
+            SyncDirection::SendOnly => "Send only",
+            SyncDirection::ReceiveOnly => "Receive only",
+        }
+    }
+
+    /// Short marker for the status bar and tray tooltip; `None` for the
+    /// default two-way mode.
+    pub fn status_marker(self) -> Option<&'static str> {
+        match self {
+            SyncDirection::Both => None,
+            SyncDirection::SendOnly => Some("send-only"),
+            SyncDirection::ReceiveOnly => Some("receive-only"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direction_gates() {
+        assert!(SyncDirection::Both.allows_send() && SyncDirection::Both.allows_receive());
+        assert!(!SyncDirection::ReceiveOnly.allows_send());
+        assert!(SyncDirection::ReceiveOnly.allows_receive());
+        assert!(SyncDirection::SendOnly.allows_send());
+        assert!(!SyncDirection::SendOnly.allows_receive());
+        assert_eq!(SyncDirection::Both.status_marker(), None);
+    }
+
+    #[test]
+    fn serializes_snake_case() {
+        assert_eq!(
+            serde_json::to_string(&SyncDirection::ReceiveOnly).unwrap(),
+            "\"receive_only\""
+        );
+    }
+}
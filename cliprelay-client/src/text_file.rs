@@ -0,0 +1,98 @@
+//! Reading a small text file (a `.txt` or `.log`) to send as clipboard
+//! text instead of as a file transfer.
+
+use std::{fmt, fs, io, path::Path};
+
+use cliprelay_core::MAX_CLIPBOARD_TEXT_BYTES;
+
+#[derive(Debug)]
+pub enum TextFileError {
+    Read(io::Error),
+    /// Larger than one text message.
+    TooLarge {
+        bytes: u64,
+    },
+    NotUtf8,
+}
+
+impl fmt::Display for TextFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextFileError::Read(e) => write!(f, "could not read the file: {e}"),
+            TextFileError::TooLarge { bytes } => write!(
+                f,
+                "the file is {bytes} bytes; text messages are limited to \
+                 {MAX_CLIPBOARD_TEXT_BYTES} bytes (use Send File instead)"
+            ),
+            TextFileError::NotUtf8 => write!(f, "the file is not UTF-8 text"),
+        }
+    }
+}
+
+impl std::error::Error for TextFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextFileError::Read(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The contents of `path` as text.  The size is checked before the file is
+/// read; a UTF-8 byte order mark is dropped.
+pub fn read_text_file(path: &Path) -> Result<String, TextFileError> {
+    let bytes = fs::metadata(path).map_err(TextFileError::Read)?.len();
+    if bytes > MAX_CLIPBOARD_TEXT_BYTES as u64 {
+        return Err(TextFileError::TooLarge { bytes });
+    }
+    decode_text(fs::read(path).map_err(TextFileError::Read)?)
+}
+
+fn decode_text(data: Vec<u8>) -> Result<String, TextFileError> {
+    if data.len() > MAX_CLIPBOARD_TEXT_BYTES {
+        // The file grew between the size check and the read.
+        return Err(TextFileError::TooLarge {
+            bytes: data.len() as u64,
+        });
+    }
+    let mut text = String::from_utf8(data).map_err(|_| TextFileError::NotUtf8)?;
+    if text.starts_with('\u{feff}') {
+        text.remove(0);
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_utf8_and_drops_the_bom() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.log");
+        fs::write(&path, "\u{feff}line one\nline two\n").unwrap();
+        assert_eq!(read_text_file(&path).unwrap(), "line one\nline two\n");
+    }
+
+    #[test]
+    fn refuses_binary_and_oversized_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("a.bin");
+        fs::write(&binary, [0xff, 0xfe, 0x00, 0x80]).unwrap();
+        assert!(matches!(
+            read_text_file(&binary),
+            Err(TextFileError::NotUtf8)
+        ));
+
+        let big = dir.path().join("big.txt");
+        fs::write(&big, vec![b'x'; MAX_CLIPBOARD_TEXT_BYTES + 1]).unwrap();
+        assert!(matches!(
+            read_text_file(&big),
+            Err(TextFileError::TooLarge { .. })
+        ));
+        assert!(matches!(
+            read_text_file(&dir.path().join("missing.txt")),
+            Err(TextFileError::Read(_))
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+//! Splitting oversized clipboard text into numbered messages that each fit
+//! under `MAX_CLIPBOARD_TEXT_BYTES`, plus the send window's size counter.
+
+/// Header prepended to each part, e.g. `"[part 2/5]\n"`.
+pub fn part_header(index: usize, total: usize) -> String {
+    format!("[part {index}/{total}]\n")
+}
+
+/// Split `text` into parts of at most `max_bytes` bytes each, including the
+/// `[part i/n]` header.  Splits fall on a line break when one is available in
+/// the second half of a part, otherwise on a character boundary.  Text that
+/// already fits is returned unchanged, without a header.
+pub fn split_numbered(text: &str, max_bytes: usize) -> Vec<String> {
+    if text.len() <= max_bytes {
+        return vec![text.to_owned()];
+    }
+    // The header width depends on the number of parts; re-split until the
+    // estimate is stable (at most a couple of rounds as digits are added).
+    let mut total = 2;
+    loop {
+        let budget = max_bytes.saturating_sub(part_header(total, total).len());
+        if budget == 0 {
+            return Vec::new();
+        }
+        let chunks = chunk(text, budget);
+        if chunks.len() <= total {
+            let n = chunks.len();
+            return chunks
+                .into_iter()
+                .enumerate()
+                .map(|(i, body)| format!("{}{body}", part_header(i + 1, n)))
+                .collect();
+        }
+        total = chunks.len();
+    }
+}
+
+fn chunk(text: &str, budget: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if rest.len() <= budget {
+            parts.push(rest);
+            break;
+        }
+        let mut end = budget;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            // `budget` is smaller than one character; cannot make progress.
+            return Vec::new();
+        }
+        if let Some(newline) = rest[..end].rfind('\n')
+            && newline + 1 >= end / 2
+        {
+            end = newline + 1;
+        }
+        parts.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    parts
+}
+
+/// Human-readable byte size (`"512 B"`, `"12.5 KiB"`, `"3.0 MiB"`).
+pub fn format_bytes(bytes: usize) -> String {
+    const KIB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b < KIB {
+        format!("{bytes} B")
+    } else if b < KIB * KIB {
+        format!("{:.1} KiB", b / KIB)
+    } else {
+        format!("{:.1} MiB", b / (KIB * KIB))
+    }
+}
+
+/// Counter shown under the send box, e.g. `"1,024 chars | 1.0 KiB / 256.0 KiB"`.
+pub fn counter_label(text: &str, limit_bytes: usize) -> String {
+    format!(
+        "{} chars | {} / {}",
+        group_thousands(text.chars().count()),
+        format_bytes(text.len()),
+        format_bytes(limit_bytes)
+    )
+}
+
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_not_split() {
+        assert_eq!(split_numbered("hello", 100), vec!["hello".to_owned()]);
+    }
+
+    #[test]
+    fn parts_fit_and_reassemble() {
+        let text: String = (0..500).map(|i| format!("line {i} ✓\n")).collect();
+        let parts = split_numbered(&text, 400);
+        assert!(parts.len() > 1);
+        let n = parts.len();
+        let mut rebuilt = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            assert!(part.len() <= 400, "part {i} is {} bytes", part.len());
+            let header = part_header(i + 1, n);
+            assert!(part.starts_with(&header));
+            rebuilt.push_str(&part[header.len()..]);
+        }
+        assert_eq!(rebuilt, text);
+        // Line-oriented input splits on line breaks.
+        assert!(parts[..n - 1].iter().all(|p| p.ends_with('\n')));
+    }
+
+    #[test]
+    fn splits_on_char_boundaries_without_newlines() {
+        let text = "é".repeat(300);
+        let parts = split_numbered(&text, 101);
+        assert!(parts.iter().all(|p| p.len() <= 101));
+        let rebuilt: String = parts
+            .iter()
+            .map(|p| p.split_once('\n').unwrap().1)
+            .collect();
+        assert_eq!(rebuilt, text);
+    }
+
+    #[test]
+    fn counter_formats_sizes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(256 * 1024), "256.0 KiB");
+        assert_eq!(counter_label("abc", 1024), "3 chars | 3 B / 1.0 KiB");
+        assert_eq!(group_thousands(1_234_567), "1,234,567");
+    }
+}
@@ -0,0 +1,405 @@
+//! Progress and cancellation of file transfers, in both directions.
+//!
+//! Cancelling is cooperative: the cancelling side stops sending (or drops
+//! the chunks it buffered) straight away and tells the other side with a
+//! [`cliprelay_core::TransferCancel`] event.  A receiver's cancel stops the
+//! sender, and so the transfer for every device in the room.
+//!
+//! Throughput is measured over the last [`RATE_WINDOW_MS`] of progress
+//! reports.  Outgoing reports are made as chunks are written to the relay
+//! socket, incoming ones as chunks arrive, so both follow the wire rather
+//! than the send queue.
+
+use std::collections::{HashMap, VecDeque};
+
+/// Span of progress reports throughput is averaged over.
+pub const RATE_WINDOW_MS: u64 = 5_000;
+
+/// Reports must span at least this long before a rate is shown.
+const MIN_RATE_SPAN_MS: u64 = 1_000;
+
+/// Identifies a transfer across devices: transfer ids are only unique per
+/// sending device.
+pub fn transfer_key(owner_device_id: &str, transfer_id: &str) -> String {
+    format!("{owner_device_id}:{transfer_id}")
+}
+
+/// Human-readable size, e.g. `"512 B"`, `"1.5 MiB"`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Remaining time, e.g. `"45s"`, `"3m 05s"`, `"1h 02m"`.
+pub fn format_eta(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {:02}s", secs / 60, secs % 60),
+        _ => format!("{}h {:02}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+    Outgoing,
+    Incoming,
+}
+
+/// Where a cancellation came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelSource {
+    Local,
+    Peer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferOutcome {
+    Completed,
+    Cancelled(CancelSource),
+    Failed,
+}
+
+/// Progress report for one transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    /// Device sending the file (this device for outgoing transfers).
+    pub owner_device_id: String,
+    pub direction: TransferDirection,
+    pub file_name: String,
+    pub total_bytes: u64,
+    pub total_chunks: u32,
+    pub done_chunks: u32,
+    /// When `done_chunks` was reached.
+    pub at_unix_ms: u64,
+}
+
+impl TransferProgress {
+    pub fn key(&self) -> String {
+        transfer_key(&self.owner_device_id, &self.transfer_id)
+    }
+
+    /// Completed share, `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total_chunks == 0 {
+            return 0.0;
+        }
+        (self.done_chunks.min(self.total_chunks) as f32) / (self.total_chunks as f32)
+    }
+
+    /// Approximate bytes done, assuming equal-sized chunks.
+    pub fn done_bytes(&self) -> u64 {
+        if self.total_chunks == 0 {
+            return 0;
+        }
+        self.total_bytes * u64::from(self.done_chunks.min(self.total_chunks))
+            / u64::from(self.total_chunks)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveTransfer {
+    pub progress: TransferProgress,
+    /// Cancel was pressed; waiting for the runtime to confirm.
+    pub cancel_requested: bool,
+    /// `(at_unix_ms, done_bytes)` of recent reports, oldest first.
+    samples: VecDeque<(u64, u64)>,
+}
+
+impl ActiveTransfer {
+    fn new(progress: TransferProgress) -> Self {
+        let mut transfer = ActiveTransfer {
+            progress,
+            cancel_requested: false,
+            samples: VecDeque::new(),
+        };
+        transfer.record_sample();
+        transfer
+    }
+
+    fn record_sample(&mut self) {
+        let at = self.progress.at_unix_ms;
+        self.samples.push_back((at, self.progress.done_bytes()));
+        // Keep one report older than the window so the rate covers all of it.
+        while self.samples.len() > 2 && at.saturating_sub(self.samples[1].0) >= RATE_WINDOW_MS {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes per second over the last [`RATE_WINDOW_MS`], measured up to
+    /// `now_ms` so that a stalled transfer slows to zero.  `None` until
+    /// there is enough to go on.
+    pub fn bytes_per_sec(&self, now_ms: u64) -> Option<f64> {
+        let &(last_at, last_bytes) = self.samples.back()?;
+        if now_ms.saturating_sub(last_at) >= RATE_WINDOW_MS {
+            return Some(0.0);
+        }
+        let &(first_at, first_bytes) = self.samples.front()?;
+        let span_ms = now_ms.max(last_at).saturating_sub(first_at);
+        if span_ms < MIN_RATE_SPAN_MS {
+            return None;
+        }
+        Some(last_bytes.saturating_sub(first_bytes) as f64 * 1000.0 / span_ms as f64)
+    }
+
+    /// Seconds until done at the current rate.
+    pub fn eta_secs(&self, now_ms: u64) -> Option<u64> {
+        let rate = self.bytes_per_sec(now_ms)?;
+        if rate < 1.0 {
+            return None;
+        }
+        let remaining = self
+            .progress
+            .total_bytes
+            .saturating_sub(self.progress.done_bytes());
+        Some((remaining as f64 / rate).ceil() as u64)
+    }
+
+    /// Speed and time left, e.g. `"1.2 MiB/s, 32s left"`; `None` while
+    /// the rate is still being measured.
+    pub fn rate_label(&self, now_ms: u64) -> Option<String> {
+        let rate = self.bytes_per_sec(now_ms)?;
+        Some(match self.eta_secs(now_ms) {
+            Some(eta) => format!("{}/s, {} left", format_bytes(rate as u64), format_eta(eta)),
+            None => "stalled".to_owned(),
+        })
+    }
+}
+
+/// Transfers in progress, in the order they started.
+#[derive(Debug, Default)]
+pub struct TransferList {
+    items: Vec<ActiveTransfer>,
+}
+
+impl TransferList {
+    /// Record `progress`, adding the transfer if it is new.
+    pub fn update(&mut self, progress: TransferProgress) {
+        let key = progress.key();
+        match self.items.iter_mut().find(|t| t.progress.key() == key) {
+            Some(existing) => {
+                existing.progress = progress;
+                existing.record_sample();
+            }
+            None => self.items.push(ActiveTransfer::new(progress)),
+        }
+    }
+
+    /// Remove a finished transfer, returning it if it was listed.
+    pub fn finish(&mut self, owner_device_id: &str, transfer_id: &str) -> Option<ActiveTransfer> {
+        let key = transfer_key(owner_device_id, transfer_id);
+        let index = self.items.iter().position(|t| t.progress.key() == key)?;
+        Some(self.items.remove(index))
+    }
+
+    /// Mark the transfer with `key` as being cancelled.  Returns its
+    /// progress, or `None` when it is not listed or already cancelling.
+    pub fn request_cancel(&mut self, key: &str) -> Option<TransferProgress> {
+        let transfer = self
+            .items
+            .iter_mut()
+            .find(|t| t.progress.key() == key && !t.cancel_requested)?;
+        transfer.cancel_requested = true;
+        Some(transfer.progress.clone())
+    }
+
+    /// Drop transfers without progress for `timeout_ms` (the sender went
+    /// away, or the receiver discarded them).
+    pub fn prune_stale(&mut self, now_ms: u64, timeout_ms: u64) {
+        self.items
+            .retain(|t| now_ms.saturating_sub(t.progress.at_unix_ms) <= timeout_ms);
+    }
+
+    /// One-line summary for the tray tooltip, e.g.
+    /// `"↓ photo.jpg 45%, 1.2 MiB/s, 32s left (+1 more)"`.
+    pub fn summary(&self, now_ms: u64) -> Option<String> {
+        const MAX_NAME_CHARS: usize = 24;
+        let first = self.items.first()?;
+        let arrow = match first.progress.direction {
+            TransferDirection::Outgoing => "↑",
+            TransferDirection::Incoming => "↓",
+        };
+        let mut name: String = first
+            .progress
+            .file_name
+            .chars()
+            .take(MAX_NAME_CHARS)
+            .collect();
+        if first.progress.file_name.chars().count() > MAX_NAME_CHARS {
+            name.push('…');
+        }
+        let mut summary = format!("{arrow} {name} {:.0}%", first.progress.fraction() * 100.0);
+        if let Some(rate) = first.rate_label(now_ms) {
+            summary.push_str(&format!(", {rate}"));
+        }
+        if self.items.len() > 1 {
+            summary.push_str(&format!(" (+{} more)", self.items.len() - 1));
+        }
+        Some(summary)
+    }
+
+    /// Share of all listed bytes done, for the tray icon's progress pie;
+    /// `None` when nothing is in flight.
+    pub fn overall_fraction(&self) -> Option<f32> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let total: u64 = self.items.iter().map(|t| t.progress.total_bytes).sum();
+        if total == 0 {
+            return Some(0.0);
+        }
+        let done: u64 = self.items.iter().map(|t| t.progress.done_bytes()).sum();
+        Some(done as f32 / total as f32)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ActiveTransfer> {
+        self.items.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+/// Transfers cancelled on this device or by a peer, by [`transfer_key`].
+/// Kept for a while so that chunks still in flight are dropped.
+#[derive(Debug, Default)]
+pub struct CancelledTransfers {
+    keys: HashMap<String, (CancelSource, u64)>,
+}
+
+impl CancelledTransfers {
+    /// Record a cancellation; the first source recorded wins.
+    pub fn insert(&mut self, key: String, source: CancelSource, now_ms: u64) {
+        self.keys.entry(key).or_insert((source, now_ms));
+    }
+
+    pub fn get(&self, key: &str) -> Option<CancelSource> {
+        self.keys.get(key).map(|(source, _)| *source)
+    }
+
+    /// Forget cancellations older than `ttl_ms`.
+    pub fn prune(&mut self, now_ms: u64, ttl_ms: u64) {
+        self.keys
+            .retain(|_, (_, at)| now_ms.saturating_sub(*at) <= ttl_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(id: &str, done: u32) -> TransferProgress {
+        progress_at(id, done, 10)
+    }
+
+    fn progress_at(id: &str, done: u32, at_unix_ms: u64) -> TransferProgress {
+        TransferProgress {
+            transfer_id: id.to_owned(),
+            owner_device_id: "dev".to_owned(),
+            direction: TransferDirection::Incoming,
+            file_name: "a.bin".to_owned(),
+            total_bytes: 1000,
+            total_chunks: 4,
+            done_chunks: done,
+            at_unix_ms,
+        }
+    }
+
+    #[test]
+    fn tracks_progress_and_finishes() {
+        let mut list = TransferList::default();
+        list.update(progress_at("t1", 1, 10));
+        list.update(progress_at("t1", 3, 20));
+        list.update(progress_at("t2", 0, 20));
+        assert_eq!(list.len(), 2);
+        let first = list.iter().next().unwrap();
+        assert_eq!(first.progress.done_bytes(), 750);
+        assert!((first.progress.fraction() - 0.75).abs() < f32::EPSILON);
+
+        // 750 of 2000 bytes across both.
+        assert_eq!(list.overall_fraction(), Some(0.375));
+
+        assert!(list.finish("dev", "t1").is_some());
+        assert!(list.finish("dev", "t1").is_none());
+        list.prune_stale(1_000, 100);
+        assert!(list.is_empty());
+        assert_eq!(list.overall_fraction(), None);
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(50 * 1024 * 1024), "50.0 MiB");
+        assert_eq!(format_eta(45), "45s");
+        assert_eq!(format_eta(185), "3m 05s");
+        assert_eq!(format_eta(3_720), "1h 02m");
+    }
+
+    #[test]
+    fn measures_rate_and_eta_over_recent_reports() {
+        let mut list = TransferList::default();
+        list.update(progress_at("t1", 0, 0));
+        assert_eq!(list.iter().next().unwrap().bytes_per_sec(500), None);
+        // 250 bytes a second, 500 bytes left.
+        list.update(progress_at("t1", 1, 1_000));
+        list.update(progress_at("t1", 2, 2_000));
+        let transfer = list.iter().next().unwrap();
+        assert_eq!(transfer.bytes_per_sec(2_000), Some(250.0));
+        assert_eq!(transfer.eta_secs(2_000), Some(2));
+        assert_eq!(
+            list.summary(2_000).as_deref(),
+            Some("↓ a.bin 50%, 250 B/s, 2s left")
+        );
+        // Nothing for a whole window: stalled.
+        assert_eq!(transfer.bytes_per_sec(7_000), Some(0.0));
+        assert_eq!(transfer.rate_label(7_000).as_deref(), Some("stalled"));
+    }
+
+    #[test]
+    fn rate_forgets_reports_outside_the_window() {
+        let mut list = TransferList::default();
+        // A slow start followed by a fast stretch.
+        list.update(progress_at("t1", 0, 0));
+        list.update(progress_at("t1", 1, 10_000));
+        list.update(progress_at("t1", 2, 11_000));
+        list.update(progress_at("t1", 3, 16_000));
+        let transfer = list.iter().next().unwrap();
+        // Measured from the 11 s report: 250 bytes in 5 s.
+        assert_eq!(transfer.bytes_per_sec(16_000), Some(50.0));
+    }
+
+    #[test]
+    fn cancel_is_requested_once() {
+        let mut list = TransferList::default();
+        list.update(progress("t1", 1));
+        let key = transfer_key("dev", "t1");
+        assert!(list.request_cancel(&key).is_some());
+        assert!(list.request_cancel(&key).is_none());
+        assert!(list.request_cancel("dev:other").is_none());
+    }
+
+    #[test]
+    fn cancellations_keep_first_source_and_expire() {
+        let mut cancelled = CancelledTransfers::default();
+        cancelled.insert("dev:t1".to_owned(), CancelSource::Peer, 10);
+        cancelled.insert("dev:t1".to_owned(), CancelSource::Local, 20);
+        assert_eq!(cancelled.get("dev:t1"), Some(CancelSource::Peer));
+        cancelled.prune(200, 100);
+        assert_eq!(cancelled.get("dev:t1"), None);
+    }
+}
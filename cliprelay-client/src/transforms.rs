@@ -0,0 +1,334 @@
+//! User-defined clean-up of clipboard text, applied in order when text is
+//! sent and/or when received text is applied to the clipboard.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::links;
+
+/// Upper bound on configured transforms.
+pub const MAX_TRANSFORMS: usize = 20;
+
+/// Query parameters stripped by a new "Strip URL parameters" step.
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &["utm_*", "fbclid", "gclid", "mc_eid"];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NewlineStyle {
+    /// `\n`
+    Lf,
+    /// `\r\n`, what Windows applications expect.
+    #[default]
+    CrLf,
+}
+
+impl NewlineStyle {
+    pub const ALL: [NewlineStyle; 2] = [NewlineStyle::Lf, NewlineStyle::CrLf];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NewlineStyle::Lf => "LF",
+            NewlineStyle::CrLf => "CRLF",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransformKind {
+    /// Remove leading and trailing whitespace.
+    Trim,
+    /// Convert every line break to one style.
+    NormalizeNewlines { style: NewlineStyle },
+    /// Straighten curly quotes and dashes, turn non-breaking spaces into
+    /// spaces and drop zero-width characters.
+    PlainPunctuation,
+    /// Remove query parameters whose name matches a pattern (`*` is a
+    /// wildcard) from `http`/`https` URLs.
+    StripQueryParams { patterns: Vec<String> },
+    /// Replace every occurrence of `find` (case-sensitive) with `replace`.
+    Replace { find: String, replace: String },
+}
+
+impl TransformKind {
+    /// Starting points offered by the Options "Add…" menu.
+    pub fn templates() -> Vec<TransformKind> {
+        vec![
+            TransformKind::Trim,
+            TransformKind::NormalizeNewlines {
+                style: NewlineStyle::CrLf,
+            },
+            TransformKind::PlainPunctuation,
+            TransformKind::StripQueryParams {
+                patterns: DEFAULT_TRACKING_PARAMS
+                    .iter()
+                    .map(|p| (*p).to_owned())
+                    .collect(),
+            },
+            TransformKind::Replace {
+                find: String::new(),
+                replace: String::new(),
+            },
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransformKind::Trim => "Trim whitespace",
+            TransformKind::NormalizeNewlines { .. } => "Normalize line breaks",
+            TransformKind::PlainPunctuation => "Plain quotes, dashes and spaces",
+            TransformKind::StripQueryParams { .. } => "Strip URL parameters",
+            TransformKind::Replace { .. } => "Find and replace",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            TransformKind::Trim => text.trim().to_owned(),
+            TransformKind::NormalizeNewlines { style } => normalize_newlines(text, *style),
+            TransformKind::PlainPunctuation => plain_punctuation(text),
+            TransformKind::StripQueryParams { patterns } => strip_query_params(text, patterns),
+            TransformKind::Replace { find, replace } if !find.is_empty() => {
+                text.replace(find.as_str(), replace)
+            }
+            TransformKind::Replace { .. } => text.to_owned(),
+        }
+    }
+}
+
+/// When a transform runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Before text goes to the room.
+    Send,
+    /// Before received text is put on the clipboard.
+    Apply,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transform {
+    pub kind: TransformKind,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub on_send: bool,
+    #[serde(default)]
+    pub on_apply: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Transform {
+    /// A new step, enabled for received text only.
+    pub fn new(kind: TransformKind) -> Self {
+        Self {
+            kind,
+            enabled: true,
+            on_send: false,
+            on_apply: true,
+        }
+    }
+
+    pub fn runs_at(&self, stage: Stage) -> bool {
+        self.enabled
+            && match stage {
+                Stage::Send => self.on_send,
+                Stage::Apply => self.on_apply,
+            }
+    }
+}
+
+/// Run the enabled transforms for `stage` over `text`, in list order.
+pub fn run(transforms: &[Transform], stage: Stage, text: &str) -> String {
+    transforms
+        .iter()
+        .filter(|t| t.runs_at(stage))
+        .fold(text.to_owned(), |text, t| t.kind.apply(&text))
+}
+
+fn normalize_newlines(text: &str, style: NewlineStyle) -> String {
+    let lf = text.replace("\r\n", "\n").replace('\r', "\n");
+    match style {
+        NewlineStyle::Lf => lf,
+        NewlineStyle::CrLf => lf.replace('\n', "\r\n"),
+    }
+}
+
+fn plain_punctuation(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => out.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => out.push('"'),
+            '\u{2013}' | '\u{2014}' | '\u{2212}' => out.push('-'),
+            '\u{2026}' => out.push_str("..."),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => out.push(' '),
+            // Zero-width joiner is left alone: emoji sequences depend on it.
+            '\u{200B}' | '\u{200C}' | '\u{2060}' | '\u{FEFF}' => {}
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Case-insensitive match of `name` against `pattern`, where `*` matches
+/// any run of characters.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let tail: Vec<&str> = parts.collect();
+    let Some((last, middle)) = tail.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn strip_query_params(text: &str, patterns: &[String]) -> String {
+    if patterns.is_empty() {
+        return text.to_owned();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = find_url_start(rest) {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let end = tail
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+            .unwrap_or(tail.len());
+        let candidate = links::trim_trailing_punctuation(&tail[..end]);
+        match strip_url(candidate, patterns) {
+            Some(stripped) => out.push_str(&stripped),
+            None => out.push_str(candidate),
+        }
+        rest = &tail[candidate.len()..];
+        if candidate.is_empty() {
+            // Not reachable with a scheme prefix, but never loop forever.
+            out.push_str(rest);
+            return out;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn find_url_start(text: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    match (lower.find("https://"), lower.find("http://")) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `candidate` without matching parameters, or `None` when nothing changes.
+fn strip_url(candidate: &str, patterns: &[String]) -> Option<String> {
+    let mut url = Url::parse(candidate).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    let kept: Vec<&(String, String)> = pairs
+        .iter()
+        .filter(|(name, _)| !patterns.iter().any(|p| wildcard_match(p, name)))
+        .collect();
+    if kept.len() == pairs.len() {
+        return None;
+    }
+    url.set_query(None);
+    if !kept.is_empty() {
+        url.query_pairs_mut()
+            .extend_pairs(kept.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+    }
+    Some(url.into())
+}
+
+/// Parse a user-entered list of parameter patterns (comma, semicolon or
+/// whitespace separated).
+pub fn parse_param_patterns(text: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for token in text.split([',', ';', ' ', '\t', '\n', '\r']) {
+        let token = token.trim();
+        if !token.is_empty() && !out.iter().any(|p| p == token) {
+            out.push(token.to_owned());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_enabled_steps_for_the_stage_in_order() {
+        let transforms = vec![
+            Transform::new(TransformKind::Trim),
+            Transform {
+                on_send: true,
+                on_apply: false,
+                ..Transform::new(TransformKind::Replace {
+                    find: "colour".to_owned(),
+                    replace: "color".to_owned(),
+                })
+            },
+            Transform::new(TransformKind::NormalizeNewlines {
+                style: NewlineStyle::CrLf,
+            }),
+            Transform {
+                enabled: false,
+                ..Transform::new(TransformKind::PlainPunctuation)
+            },
+        ];
+        let text = "  \u{201C}colour\u{201D}\r\nline\n";
+        assert_eq!(
+            run(&transforms, Stage::Apply, text),
+            "\u{201C}colour\u{201D}\r\nline"
+        );
+        assert_eq!(
+            run(&transforms, Stage::Send, text),
+            text.replace("colour", "color")
+        );
+    }
+
+    #[test]
+    fn strips_tracking_parameters_only_from_urls() {
+        let patterns = parse_param_patterns("utm_*, fbclid");
+        let text = "Read https://example.com/a?id=7&utm_source=x&FBCLID=1. \
+                    Or (https://example.com/b?utm_medium=y) utm_source=kept";
+        assert_eq!(
+            strip_query_params(text, &patterns),
+            "Read https://example.com/a?id=7. \
+             Or (https://example.com/b) utm_source=kept"
+        );
+        // Untouched URLs keep their exact spelling.
+        assert_eq!(
+            strip_query_params("HTTPS://Example.com/x?a=1", &patterns),
+            "HTTPS://Example.com/x?a=1"
+        );
+    }
+
+    #[test]
+    fn plain_punctuation_and_wildcards() {
+        assert_eq!(
+            plain_punctuation("It\u{2019}s\u{00A0}fine \u{2014} ok\u{2026}\u{200B}"),
+            "It's fine - ok..."
+        );
+        assert!(wildcard_match("utm_*", "UTM_Source"));
+        assert!(wildcard_match("*clid", "gclid"));
+        assert!(wildcard_match("a*b*c", "axxbyyc"));
+        assert!(!wildcard_match("fbclid", "fbclid2"));
+        assert!(!wildcard_match("a*b*c", "acb"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Numeric "unread" badge and transfer progress pie composited onto the tray
+//! icon.
+//!
+//! The tray icons ship as fixed `.ico` assets, so both are drawn straight
+//! into their decoded RGBA pixels: the badge is a red disc in the
+//! bottom-right corner with the count in a tiny built-in bitmap font (no font
+//! rendering needed), the pie a disc in the top-left corner filled clockwise
+//! from twelve o'clock.
+
+/// Counts above this are shown as `9+`.
+pub const MAX_BADGE_DIGIT: usize = 9;
+
+const GLYPH_W: usize = 3;
+const GLYPH_H: usize = 5;
+
+/// 3×5 bitmaps, one row per byte, most significant of the low three bits on
+/// the left.
+const DIGITS: [[u8; GLYPH_H]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const PLUS: [u8; GLYPH_H] = [0b000, 0b010, 0b111, 0b010, 0b000];
+
+const BADGE_FILL: [u8; 4] = [220, 30, 30, 255];
+const BADGE_TEXT: [u8; 4] = [255, 255, 255, 255];
+
+/// Steps the progress pie is drawn in, so the icon changes at most this
+/// many times per transfer.
+pub const PROGRESS_STEPS: u8 = 8;
+
+const PIE_TRACK: [u8; 4] = [40, 40, 40, 255];
+const PIE_FILL: [u8; 4] = [40, 140, 255, 255];
+
+/// Text shown in the badge, or `None` when there is nothing unread.
+pub fn badge_text(count: usize) -> Option<String> {
+    match count {
+        0 => None,
+        n if n > MAX_BADGE_DIGIT => Some(format!("{MAX_BADGE_DIGIT}+")),
+        n => Some(n.to_string()),
+    }
+}
+
+fn glyph(ch: char) -> Option<&'static [u8; GLYPH_H]> {
+    match ch {
+        '+' => Some(&PLUS),
+        c => c.to_digit(10).map(|d| &DIGITS[d as usize]),
+    }
+}
+
+/// Return a copy of `rgba` (`width`×`height`, 4 bytes per pixel) with an
+/// unread badge for `count` drawn on it.  `count == 0` returns the input
+/// unchanged.
+pub fn with_badge(rgba: &[u8], width: u32, height: u32, count: usize) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let Some(text) = badge_text(count) else {
+        return out;
+    };
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 || out.len() < w * h * 4 {
+        return out;
+    }
+
+    let mut put = |x: usize, y: usize, color: [u8; 4]| {
+        if x < w && y < h {
+            let i = (y * w + x) * 4;
+            out[i..i + 4].copy_from_slice(&color);
+        }
+    };
+
+    // Disc covering the bottom-right ~60% of the icon.
+    let diameter = (w.min(h) * 3 / 5).max(GLYPH_H + 2);
+    let radius = diameter as f32 / 2.0;
+    let (cx, cy) = (w as f32 - radius, h as f32 - radius);
+    for y in h.saturating_sub(diameter)..h {
+        for x in w.saturating_sub(diameter)..w {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                put(x, y, BADGE_FILL);
+            }
+        }
+    }
+
+    // Glyphs are separated by one blank column and scaled by a whole factor
+    // so they stay crisp at every icon size.
+    let glyphs: Vec<_> = text.chars().filter_map(glyph).collect();
+    let cols = glyphs.len() * (GLYPH_W + 1) - 1;
+    let inner = diameter * 7 / 10;
+    let scale = (inner / cols).min(inner / GLYPH_H).max(1);
+    let text_w = cols * scale;
+    let text_h = GLYPH_H * scale;
+    let left = (cx - text_w as f32 / 2.0).round().max(0.0) as usize;
+    let top = (cy - text_h as f32 / 2.0).round().max(0.0) as usize;
+    for (gi, rows) in glyphs.iter().enumerate() {
+        let gx = left + gi * (GLYPH_W + 1) * scale;
+        for (ry, row) in rows.iter().enumerate() {
+            for rx in 0..GLYPH_W {
+                if row & (1 << (GLYPH_W - 1 - rx)) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        put(gx + rx * scale + sx, top + ry * scale + sy, BADGE_TEXT);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `fraction` (`0.0..=1.0`) rounded down to a pie step, `0..=PROGRESS_STEPS`.
+pub fn progress_step(fraction: f32) -> u8 {
+    (fraction.clamp(0.0, 1.0) * f32::from(PROGRESS_STEPS)).floor() as u8
+}
+
+/// Return a copy of `rgba` with a progress pie filled to `step` of
+/// [`PROGRESS_STEPS`] drawn on it.  An empty pie still shows its track, so
+/// a transfer is visible from its start.
+pub fn with_progress(rgba: &[u8], width: u32, height: u32, step: u8) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 || out.len() < w * h * 4 {
+        return out;
+    }
+    let filled = f32::from(step.min(PROGRESS_STEPS)) / f32::from(PROGRESS_STEPS);
+    let diameter = (w.min(h) / 2).max(4);
+    let radius = diameter as f32 / 2.0;
+    for y in 0..diameter.min(h) {
+        for x in 0..diameter.min(w) {
+            let (dx, dy) = (x as f32 + 0.5 - radius, y as f32 + 0.5 - radius);
+            if dx * dx + dy * dy > radius * radius {
+                continue;
+            }
+            // Angle clockwise from twelve o'clock, as a share of a turn.
+            let turn = (dx.atan2(-dy) / std::f32::consts::TAU).rem_euclid(1.0);
+            let color = if turn < filled { PIE_FILL } else { PIE_TRACK };
+            let i = (y * w + x) * 4;
+            out[i..i + 4].copy_from_slice(&color);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(rgba: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * width + x) * 4) as usize;
+        rgba[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn badge_text_caps_at_nine() {
+        assert_eq!(badge_text(0), None);
+        assert_eq!(badge_text(3).as_deref(), Some("3"));
+        assert_eq!(badge_text(42).as_deref(), Some("9+"));
+    }
+
+    #[test]
+    fn zero_count_leaves_icon_untouched() {
+        let icon = vec![10u8; 16 * 16 * 4];
+        assert_eq!(with_badge(&icon, 16, 16, 0), icon);
+    }
+
+    #[test]
+    fn badge_is_drawn_bottom_right_only() {
+        let icon = vec![0u8; 32 * 32 * 4];
+        let badged = with_badge(&icon, 32, 32, 5);
+        assert_eq!(pixel(&badged, 32, 0, 0), [0, 0, 0, 0]);
+        let bottom_right = pixel(&badged, 32, 28, 28);
+        assert!(bottom_right == BADGE_FILL || bottom_right == BADGE_TEXT);
+        // Some text pixels are present inside the disc.
+        assert!(badged.chunks_exact(4).any(|p| p == BADGE_TEXT));
+    }
+
+    #[test]
+    fn progress_pie_fills_clockwise_in_the_top_left() {
+        assert_eq!(progress_step(0.0), 0);
+        assert_eq!(progress_step(0.49), 3);
+        assert_eq!(progress_step(1.5), PROGRESS_STEPS);
+
+        let icon = vec![0u8; 32 * 32 * 4];
+        let half = with_progress(&icon, 32, 32, PROGRESS_STEPS / 2);
+        // Right half of the pie filled, left half track, rest untouched.
+        assert_eq!(pixel(&half, 32, 12, 8), PIE_FILL);
+        assert_eq!(pixel(&half, 32, 3, 8), PIE_TRACK);
+        assert_eq!(pixel(&half, 32, 28, 28), [0, 0, 0, 0]);
+        let empty = with_progress(&icon, 32, 32, 0);
+        assert!(empty.chunks_exact(4).all(|p| p != PIE_FILL));
+    }
+
+    #[test]
+    fn short_buffer_is_ignored() {
+        let icon = vec![0u8; 8];
+        assert_eq!(with_badge(&icon, 16, 16, 2), icon);
+    }
+}
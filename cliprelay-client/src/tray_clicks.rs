@@ -0,0 +1,156 @@
+//! What a click on the tray icon does.
+//!
+//! Single, double and middle clicks each map to one [`TrayAction`].  A
+//! double click begins with a single click, so when the two are set to
+//! different actions the single-click action waits out the system
+//! double-click time and is dropped if the second click arrives.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayAction {
+    /// Show the Send window, or hide it when it is already shown.
+    ToggleWindow,
+    /// Show the window on the Notifications tab.
+    ShowNotifications,
+    /// Pause sync, or resume it when paused.
+    TogglePause,
+    /// Put the most recently received text on the clipboard.
+    ApplyLastReceived,
+    Nothing,
+}
+
+impl TrayAction {
+    pub const ALL: [TrayAction; 5] = [
+        TrayAction::ToggleWindow,
+        TrayAction::ShowNotifications,
+        TrayAction::TogglePause,
+        TrayAction::ApplyLastReceived,
+        TrayAction::Nothing,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrayAction::ToggleWindow => "Show/hide Send window",
+            TrayAction::ShowNotifications => "Open notification list",
+            TrayAction::TogglePause => "Pause/resume sync",
+            TrayAction::ApplyLastReceived => "Apply last received text",
+            TrayAction::Nothing => "Nothing",
+        }
+    }
+
+    /// Whether the action shows the main window.
+    pub fn shows_window(self) -> bool {
+        matches!(self, TrayAction::ShowNotifications)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayClick {
+    /// Left button, once.
+    Single,
+    /// Left button, twice in quick succession.
+    Double,
+    Middle,
+}
+
+/// The action bound to each kind of tray click.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrayClickActions {
+    #[serde(default = "default_single")]
+    pub single: TrayAction,
+    #[serde(default = "default_double")]
+    pub double: TrayAction,
+    #[serde(default = "default_middle")]
+    pub middle: TrayAction,
+}
+
+fn default_single() -> TrayAction {
+    TrayAction::ToggleWindow
+}
+
+fn default_double() -> TrayAction {
+    TrayAction::ToggleWindow
+}
+
+fn default_middle() -> TrayAction {
+    TrayAction::Nothing
+}
+
+impl Default for TrayClickActions {
+    fn default() -> Self {
+        Self {
+            single: default_single(),
+            double: default_double(),
+            middle: default_middle(),
+        }
+    }
+}
+
+impl TrayClickActions {
+    pub fn action(&self, click: TrayClick) -> TrayAction {
+        match click {
+            TrayClick::Single => self.single,
+            TrayClick::Double => self.double,
+            TrayClick::Middle => self.middle,
+        }
+    }
+
+    /// Whether a single click must wait to see if it becomes a double click.
+    /// Not needed when the double click does nothing, or the same thing (the
+    /// first click has then already done it).
+    pub fn single_waits_for_double(&self) -> bool {
+        self.double != TrayAction::Nothing && self.double != self.single
+    }
+
+    /// The action for a double click.  When it matches the single-click
+    /// action, the first click has already run it.
+    pub fn double_click_action(&self) -> TrayAction {
+        if self.double != self.single {
+            self.double
+        } else {
+            TrayAction::Nothing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_toggle_once_per_click_or_double_click() {
+        let actions = TrayClickActions::default();
+        assert!(!actions.single_waits_for_double());
+        assert_eq!(actions.action(TrayClick::Single), TrayAction::ToggleWindow);
+        // The first click of the double click already toggled.
+        assert_eq!(actions.double_click_action(), TrayAction::Nothing);
+        assert_eq!(actions.action(TrayClick::Middle), TrayAction::Nothing);
+    }
+
+    #[test]
+    fn distinct_double_click_defers_single() {
+        let actions = TrayClickActions {
+            single: TrayAction::ApplyLastReceived,
+            double: TrayAction::ToggleWindow,
+            middle: TrayAction::TogglePause,
+        };
+        assert!(actions.single_waits_for_double());
+        assert_eq!(actions.double_click_action(), TrayAction::ToggleWindow);
+
+        let no_double = TrayClickActions {
+            double: TrayAction::Nothing,
+            ..actions
+        };
+        assert!(!no_double.single_waits_for_double());
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let actions: TrayClickActions =
+            serde_json::from_str(r#"{"middle":"toggle_pause"}"#).unwrap();
+        assert_eq!(actions.single, TrayAction::ToggleWindow);
+        assert_eq!(actions.middle, TrayAction::TogglePause);
+    }
+}
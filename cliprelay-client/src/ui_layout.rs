@@ -2,6 +2,17 @@
 //!
 //! With egui, DPI scaling is handled automatically. These constants define
 //! logical pixel sizes used for window defaults.
+//!
+//! `assets/app.manifest` declares per-monitor-v2 DPI awareness.  When a
+//! window moves between monitors with different scale factors, winit handles
+//! `WM_DPICHANGED` (resizing the window to the suggested rectangle) and eframe
+//! re-runs layout with the new `pixels_per_point`, so there is no hand-written
+//! Win32 layout pass to redo.
+//!
+//! [`TextSize`] is a user preference on top of that: it becomes the egui
+//! zoom factor, so text and every layout metric grow together.
+
+use serde::{Deserialize, Serialize};
 
 /// Default options window width (logical pixels).
 pub const OPTIONS_DEFAULT_W_PX: f32 = 680.0;
@@ -19,6 +30,110 @@ pub const CHOOSE_ROOM_HAS_SAVED_H_PX: f32 = 320.0;
 /// Choose-room dialog height when no saved config exists.
 pub const CHOOSE_ROOM_NO_SAVED_H_PX: f32 = 230.0;
 
+/// Gap between a docked window and the edges of the work area (physical
+/// pixels).
+pub const DOCK_MARGIN_PX: i32 = 12;
+
+/// A rectangle in screen coordinates, such as a monitor's work area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// Where the window goes when it is brought up to show incoming items or
+/// the Send tab.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PopupPlacement {
+    /// Wherever it was last left.
+    #[default]
+    Remembered,
+    BottomRight,
+    TopRight,
+    /// Centred on the monitor under the mouse pointer.
+    CenterOfActiveMonitor,
+}
+
+impl PopupPlacement {
+    pub const ALL: [PopupPlacement; 4] = [
+        PopupPlacement::Remembered,
+        PopupPlacement::BottomRight,
+        PopupPlacement::TopRight,
+        PopupPlacement::CenterOfActiveMonitor,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PopupPlacement::Remembered => "Where it was last",
+            PopupPlacement::BottomRight => "Bottom-right corner",
+            PopupPlacement::TopRight => "Top-right corner",
+            PopupPlacement::CenterOfActiveMonitor => "Centre of active monitor",
+        }
+    }
+
+    /// Top-left corner for a `width` x `height` window in `work`, kept
+    /// inside it; `None` when the window stays where it is.
+    pub fn origin(self, work: ScreenRect, width: i32, height: i32) -> Option<(i32, i32)> {
+        let right = (work.right - width - DOCK_MARGIN_PX).max(work.left);
+        let (x, y) = match self {
+            PopupPlacement::Remembered => return None,
+            PopupPlacement::BottomRight => (right, work.bottom - height - DOCK_MARGIN_PX),
+            PopupPlacement::TopRight => (right, work.top + DOCK_MARGIN_PX),
+            PopupPlacement::CenterOfActiveMonitor => (
+                (work.left + work.right - width) / 2,
+                (work.top + work.bottom - height) / 2,
+            ),
+        };
+        Some((x.max(work.left), y.max(work.top)))
+    }
+}
+
+/// Text size picked in Options, independent of the monitor's DPI scale.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSize {
+    Small,
+    #[default]
+    Normal,
+    Large,
+    Larger,
+    Largest,
+}
+
+impl TextSize {
+    pub const ALL: [TextSize; 5] = [
+        TextSize::Small,
+        TextSize::Normal,
+        TextSize::Large,
+        TextSize::Larger,
+        TextSize::Largest,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextSize::Small => "Small (90%)",
+            TextSize::Normal => "Normal (100%)",
+            TextSize::Large => "Large (115%)",
+            TextSize::Larger => "Larger (130%)",
+            TextSize::Largest => "Largest (150%)",
+        }
+    }
+
+    /// Multiplier applied to fonts, spacing and window contents.
+    pub fn scale(self) -> f32 {
+        match self {
+            TextSize::Small => 0.9,
+            TextSize::Normal => 1.0,
+            TextSize::Large => 1.15,
+            TextSize::Larger => 1.3,
+            TextSize::Largest => 1.5,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,4 +146,49 @@ mod tests {
         assert!(OPTIONS_MIN_H_PX >= 360.0);
         assert!(CHOOSE_ROOM_DEFAULT_W_PX >= 520.0);
     }
+
+    #[test]
+    fn placements_stay_inside_the_work_area() {
+        let work = ScreenRect {
+            left: 1920,
+            top: 0,
+            right: 3840,
+            bottom: 1040,
+        };
+        assert_eq!(PopupPlacement::Remembered.origin(work, 560, 420), None);
+        assert_eq!(
+            PopupPlacement::BottomRight.origin(work, 560, 420),
+            Some((3840 - 560 - DOCK_MARGIN_PX, 1040 - 420 - DOCK_MARGIN_PX))
+        );
+        assert_eq!(
+            PopupPlacement::TopRight.origin(work, 560, 420),
+            Some((3840 - 560 - DOCK_MARGIN_PX, DOCK_MARGIN_PX))
+        );
+        assert_eq!(
+            PopupPlacement::CenterOfActiveMonitor.origin(work, 560, 420),
+            Some((2600, 310))
+        );
+        // Larger than the monitor: pinned to its top-left corner.
+        assert_eq!(
+            PopupPlacement::BottomRight.origin(work, 4000, 2000),
+            Some((1920, 0))
+        );
+    }
+
+    #[test]
+    fn text_sizes_grow_in_order() {
+        assert_eq!(TextSize::default().scale(), 1.0);
+        assert!(
+            TextSize::ALL
+                .windows(2)
+                .all(|w| w[0].scale() < w[1].scale())
+        );
+    }
+
+    #[test]
+    fn manifest_declares_per_monitor_v2_awareness() {
+        let manifest = include_str!("../assets/app.manifest");
+        assert!(manifest.contains("<dpiAwareness"));
+        assert!(manifest.contains("PerMonitorV2"));
+    }
 }
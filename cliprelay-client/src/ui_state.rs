@@ -6,6 +6,26 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::append_mode::AppendSeparator;
+use crate::auth_gate::AuthGatePrefs;
+use crate::autostart::AutostartBackend;
+use crate::config_profiles;
+use crate::dedupe::DuplicateSuppression;
+use crate::extension_policy::ExtensionPolicy;
+use crate::history_retention::HistoryRetention;
+use crate::large_apply::LargeApplyConfirm;
+use crate::links::LinkAutoOpen;
+use crate::notification_queue::NotificationLimit;
+use crate::oversize_text::OversizedText;
+use crate::reconnect::ReconnectPolicy;
+use crate::secrets::SecretPolicy;
+use crate::sound::{DndSchedule, SoundSource};
+use crate::status_widget::StatusWidgetPrefs;
+use crate::sync_direction::SyncDirection;
+use crate::transforms::Transform;
+use crate::tray_clicks::TrayClickActions;
+use crate::ui_layout::{PopupPlacement, ScreenRect, TextSize};
+
 /// Defensive bound: `ui_state.json` is expected to be tiny.
 ///
 /// This prevents pathological reads if the file is corrupted or replaced.
@@ -19,6 +39,19 @@ pub struct WindowPlacement {
     pub h: u32,
 }
 
+/// Monitor arrangements whose placements are kept; the least recently used
+/// is dropped beyond this.
+pub const MAX_SAVED_TOPOLOGIES: usize = 8;
+
+/// Window placements for one monitor arrangement, keyed by
+/// [`topology_fingerprint`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct TopologyPlacements {
+    pub fingerprint: String,
+    #[serde(default)]
+    pub main: Option<WindowPlacement>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct SavedUiState {
     #[serde(default)]
@@ -27,10 +60,154 @@ pub struct SavedUiState {
     pub options: Option<WindowPlacement>,
     #[serde(default)]
     pub popup: Option<WindowPlacement>,
+    /// Main window placement per monitor arrangement, most recently used
+    /// first.
+    #[serde(default)]
+    pub placements: Vec<TopologyPlacements>,
     /// Persisted global hotkey label (e.g. "Ctrl+Shift+V").
     /// `None` or `"None"` means hotkey is disabled.
     #[serde(default)]
     pub hotkey: Option<String>,
+    /// Whether the local named-pipe automation API is started with the
+    /// runtime.  Off by default; see [`crate::automation`].
+    #[serde(default)]
+    pub automation_api_enabled: bool,
+    /// Safety switch: ClipRelay may synthesize Ctrl+V into other
+    /// applications only while this is set.
+    #[serde(default)]
+    pub auto_paste_allowed: bool,
+    /// Paste automatically after every apply (auto or manual).
+    #[serde(default)]
+    pub auto_paste_after_apply: bool,
+    /// Executable names that never receive a synthesized paste.
+    #[serde(default)]
+    pub auto_paste_excluded: Vec<String>,
+    /// Quick-pick hotkey label; `None` means the default (`Ctrl+Alt+H`).
+    #[serde(default)]
+    pub quick_pick_hotkey: Option<String>,
+    /// Re-send the picked quick-pick item to the room after applying it.
+    #[serde(default)]
+    pub quick_pick_resend: bool,
+    /// Panic hotkey label; `None` means no panic hotkey.
+    #[serde(default)]
+    pub panic_hotkey: Option<String>,
+    /// Seconds to wait before connecting when launched with `--background`.
+    #[serde(default)]
+    pub autostart_delay_secs: u32,
+    /// When launched with `--background`, wait until the relay host
+    /// resolves before the first connection attempt.
+    #[serde(default)]
+    pub autostart_wait_for_network: bool,
+    /// How "Start with Windows" is implemented.
+    #[serde(default)]
+    pub autostart_backend: AutostartBackend,
+    /// Play [`Self::sound_source`] when clipboard text arrives.
+    #[serde(default)]
+    pub sound_on_text: bool,
+    /// Play [`Self::sound_source`] when a file arrives.
+    #[serde(default)]
+    pub sound_on_file: bool,
+    #[serde(default)]
+    pub sound_source: SoundSource,
+    /// Daily quiet hours: no sounds or system notifications.
+    #[serde(default)]
+    pub dnd_schedule: Option<DndSchedule>,
+    /// Folder for saved files; `None` means `Downloads\ClipRelay`.
+    #[serde(default)]
+    pub files_save_dir: Option<PathBuf>,
+    /// Save into a subfolder named after the sending device.
+    #[serde(default)]
+    pub files_per_sender_subfolders: bool,
+    /// Do not tag saved files with Mark-of-the-Web (Internet zone).
+    #[serde(default)]
+    pub files_skip_mark_of_the_web: bool,
+    /// Hold every received file in the staging folder until it is
+    /// accepted, even from devices set to save automatically.
+    #[serde(default)]
+    pub files_quarantine: bool,
+    /// Which incoming file types are accepted; enforced by the runtime.
+    #[serde(default)]
+    pub incoming_file_extensions: ExtensionPolicy,
+    /// How long incoming text identical to the last applied text is skipped.
+    #[serde(default)]
+    pub duplicate_suppression: DuplicateSuppression,
+    /// Ask before auto-applying incoming text above a size.
+    #[serde(default)]
+    pub large_apply_confirm: LargeApplyConfirm,
+    /// Text too large for one message: offer a file transfer or always
+    /// send one.
+    #[serde(default)]
+    pub oversized_text: OversizedText,
+    /// Applying received text adds it after the clipboard's text instead
+    /// of replacing it.
+    #[serde(default)]
+    pub append_mode: bool,
+    /// Between the clipboard's text and appended text.
+    #[serde(default)]
+    pub append_separator: AppendSeparator,
+    /// Copied invites end with a QR code of the join link.
+    #[serde(default)]
+    pub invite_qr: bool,
+    /// The first-run onboarding window was finished or skipped.
+    #[serde(default)]
+    pub onboarding_done: bool,
+    /// Windows Hello / password lock on history, snippets and invites.
+    #[serde(default)]
+    pub auth_gate: AuthGatePrefs,
+    /// Send-only / receive-only restriction, enforced by the runtime.
+    #[serde(default)]
+    pub sync_direction: SyncDirection,
+    /// Send files even while the connection is metered (otherwise they
+    /// wait for an unmetered one).
+    #[serde(default)]
+    pub metered_send_files: bool,
+    /// Also hold back large texts while the connection is metered.
+    #[serde(default)]
+    pub metered_defer_large_text: bool,
+    /// Remove received text from the Notifications list this many seconds
+    /// after it was first shown; `None` keeps it until acted on.
+    #[serde(default)]
+    pub notification_auto_dismiss_secs: Option<u32>,
+    /// Notifications list size and what happens to items beyond it.
+    #[serde(default)]
+    pub notification_limit: NotificationLimit,
+    /// Ordered clean-up steps for sent and applied text.
+    #[serde(default)]
+    pub text_transforms: Vec<Transform>,
+    /// Opening text that is just a link from a trusted device.
+    #[serde(default)]
+    pub trusted_link_auto_open: LinkAutoOpen,
+    /// Do not alert when a device never seen before joins the room.
+    #[serde(default)]
+    pub suppress_unknown_device_alerts: bool,
+    /// Record frame summaries for the Protocol Trace window (advanced).
+    #[serde(default)]
+    pub protocol_trace_enabled: bool,
+    /// Reconnect delay, backoff and attempt limit.
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    /// Activity History entry limit, age limit and content storage.
+    #[serde(default)]
+    pub history_retention: HistoryRetention,
+    /// Sent text that looks like a credential: ask first, block, or send.
+    #[serde(default)]
+    pub secret_check: SecretPolicy,
+    /// What single, double and middle clicks on the tray icon do.
+    #[serde(default)]
+    pub tray_clicks: TrayClickActions,
+    /// Keep the main window above other windows.
+    #[serde(default)]
+    pub always_on_top: bool,
+    /// Size of text, and with it the whole layout, in every window.
+    #[serde(default)]
+    pub text_size: TextSize,
+    /// Where the hidden window reappears when the tray, a hotkey or a
+    /// notification brings it up.
+    #[serde(default)]
+    pub popup_placement: PopupPlacement,
+    /// The always-visible status widget and where it sits.
+    #[serde(default)]
+    pub status_widget: StatusWidgetPrefs,
 }
 
 #[derive(Debug)]
@@ -92,13 +269,9 @@ impl std::error::Error for UiStateSaveError {
     }
 }
 
+/// `ui_state.json` of the active configuration profile.
 pub fn ui_state_path() -> PathBuf {
-    let base = std::env::var_os("LOCALAPPDATA")
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("."));
-    let dir = base.join("ClipRelay");
-    let _ = fs::create_dir_all(&dir);
-    dir.join("ui_state.json")
+    config_profiles::active_dir().join("ui_state.json")
 }
 
 pub fn parse_ui_state_json(data: &str) -> Result<SavedUiState, serde_json::Error> {
@@ -159,6 +332,62 @@ pub fn save_ui_state_with_retry(state: &SavedUiState) -> Result<(), UiStateSaveE
     Err(last_err.expect("retry loop sets last_err"))
 }
 
+/// Identifies a monitor arrangement by the position and size of every
+/// monitor, in any order, e.g. `"-1920,0 1920x1080|0,0 2560x1440"`.
+pub fn topology_fingerprint(monitors: &[ScreenRect]) -> String {
+    let mut rects: Vec<String> = monitors
+        .iter()
+        .map(|m| {
+            format!(
+                "{},{} {}x{}",
+                m.left,
+                m.top,
+                m.right - m.left,
+                m.bottom - m.top
+            )
+        })
+        .collect();
+    rects.sort_unstable();
+    rects.join("|")
+}
+
+impl SavedUiState {
+    /// Main window placement saved for the arrangement `fingerprint`.
+    pub fn main_placement(&self, fingerprint: &str) -> Option<WindowPlacement> {
+        self.placements
+            .iter()
+            .find(|set| set.fingerprint == fingerprint)
+            .and_then(|set| set.main)
+    }
+
+    /// Record `placement` for `fingerprint` and make that arrangement the
+    /// most recently used.  Returns whether anything changed.
+    pub fn remember_main_placement(
+        &mut self,
+        fingerprint: &str,
+        placement: WindowPlacement,
+    ) -> bool {
+        let index = self
+            .placements
+            .iter()
+            .position(|set| set.fingerprint == fingerprint);
+        if index == Some(0) && self.placements[0].main == Some(placement) {
+            return false;
+        }
+        let mut set = match index {
+            Some(i) => self.placements.remove(i),
+            None => TopologyPlacements {
+                fingerprint: fingerprint.to_owned(),
+                main: None,
+            },
+        };
+        set.main = Some(placement);
+        self.placements.insert(0, set);
+        self.placements.truncate(MAX_SAVED_TOPOLOGIES);
+        true
+    }
+}
+
 /// Clamp a window placement into a given monitor rectangle.
 ///
 /// `rect` is `[left, top, right, bottom]` in virtual-screen coordinates.
@@ -0,0 +1,320 @@
+//! Local usage statistics: messages and bytes sent and received, per day and
+//! per device, shown on the Stats page in Options.
+//!
+//! The counters are kept in `usage_stats.json` next to the Activity History
+//! of the active profile and are never sent to the relay or to other peers.
+//! A text message counts its UTF-8 bytes, a file its size.  Each device's
+//! counters are also kept apart for text and files, shown on the Stats page
+//! and in the Devices tab.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config_profiles;
+
+/// Defensive bound on `usage_stats.json`, mirroring `MAX_DEVICES_BYTES`.
+pub const MAX_USAGE_STATS_BYTES: u64 = 512 * 1024;
+
+/// Days kept in the per-day breakdown; older days are dropped (the totals
+/// still include them).
+pub const MAX_DAYS: usize = 90;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Counts {
+    #[serde(default)]
+    pub sent_messages: u64,
+    #[serde(default)]
+    pub sent_bytes: u64,
+    #[serde(default)]
+    pub received_messages: u64,
+    #[serde(default)]
+    pub received_bytes: u64,
+}
+
+impl Counts {
+    fn add_sent(&mut self, bytes: u64) {
+        self.sent_messages = self.sent_messages.saturating_add(1);
+        self.sent_bytes = self.sent_bytes.saturating_add(bytes);
+    }
+
+    fn add_received(&mut self, bytes: u64) {
+        self.received_messages = self.received_messages.saturating_add(1);
+        self.received_bytes = self.received_bytes.saturating_add(bytes);
+    }
+}
+
+/// What a counted message carried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageKind {
+    Text,
+    File,
+}
+
+/// Counters for one device, with the name it last used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PeerUsage {
+    #[serde(default)]
+    pub name: String,
+    /// Everything, including what was counted before text and files were
+    /// told apart.
+    #[serde(flatten)]
+    pub counts: Counts,
+    #[serde(default)]
+    pub text: Counts,
+    #[serde(default)]
+    pub files: Counts,
+}
+
+impl PeerUsage {
+    fn kind_mut(&mut self, kind: UsageKind) -> &mut Counts {
+        match kind {
+            UsageKind::Text => &mut self.text,
+            UsageKind::File => &mut self.files,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageStats {
+    /// When counting started: first use, or the last reset.
+    #[serde(default)]
+    pub since_unix_ms: u64,
+    /// Everything since `since_unix_ms`.  Like `days`, each message counts
+    /// once however many devices received it.
+    #[serde(default)]
+    pub totals: Counts,
+    /// By local date (`YYYY-MM-DD`), so keys sort oldest first.
+    #[serde(default)]
+    pub days: BTreeMap<String, Counts>,
+    /// By device id.  A message sent to a room with two other devices
+    /// counts for each of them.
+    #[serde(default)]
+    pub peers: BTreeMap<String, PeerUsage>,
+}
+
+impl UsageStats {
+    pub fn new(now_unix_ms: u64) -> Self {
+        Self {
+            since_unix_ms: now_unix_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Count a message of `bytes` sent on `day` to `recipients`
+    /// (`(device_id, name)` pairs of the devices online at the time).
+    pub fn record_sent(
+        &mut self,
+        day: &str,
+        kind: UsageKind,
+        bytes: u64,
+        recipients: &[(&str, &str)],
+    ) {
+        self.totals.add_sent(bytes);
+        self.day_mut(day).add_sent(bytes);
+        for (device_id, name) in recipients {
+            let peer = self.peer_mut(device_id, name);
+            peer.counts.add_sent(bytes);
+            peer.kind_mut(kind).add_sent(bytes);
+        }
+    }
+
+    /// Count a message of `bytes` received on `day` from `device_id`.
+    pub fn record_received(
+        &mut self,
+        day: &str,
+        kind: UsageKind,
+        bytes: u64,
+        device_id: &str,
+        name: &str,
+    ) {
+        self.totals.add_received(bytes);
+        self.day_mut(day).add_received(bytes);
+        let peer = self.peer_mut(device_id, name);
+        peer.counts.add_received(bytes);
+        peer.kind_mut(kind).add_received(bytes);
+    }
+
+    /// Devices, busiest (most bytes either way) first.
+    pub fn peers_by_volume(&self) -> Vec<(&str, &PeerUsage)> {
+        let mut peers: Vec<(&str, &PeerUsage)> = self
+            .peers
+            .iter()
+            .map(|(id, usage)| (id.as_str(), usage))
+            .collect();
+        peers.sort_by_key(|(_, usage)| {
+            std::cmp::Reverse(
+                usage
+                    .counts
+                    .sent_bytes
+                    .saturating_add(usage.counts.received_bytes),
+            )
+        });
+        peers
+    }
+
+    pub fn reset(&mut self, now_unix_ms: u64) {
+        *self = Self::new(now_unix_ms);
+    }
+
+    fn day_mut(&mut self, day: &str) -> &mut Counts {
+        if !self.days.contains_key(day) {
+            self.days.insert(day.to_owned(), Counts::default());
+            while self.days.len() > MAX_DAYS {
+                self.days.pop_first();
+            }
+        }
+        self.days.entry(day.to_owned()).or_default()
+    }
+
+    fn peer_mut(&mut self, device_id: &str, name: &str) -> &mut PeerUsage {
+        let peer = self.peers.entry(device_id.to_owned()).or_default();
+        if !name.is_empty() {
+            peer.name = name.to_owned();
+        }
+        peer
+    }
+}
+
+pub fn usage_stats_path() -> PathBuf {
+    config_profiles::active_dir().join("usage_stats.json")
+}
+
+pub fn load_usage_stats_from_path(path: &Path) -> io::Result<UsageStats> {
+    let meta = fs::metadata(path)?;
+    if meta.len() > MAX_USAGE_STATS_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "file too large: {} bytes (max {MAX_USAGE_STATS_BYTES})",
+                meta.len()
+            ),
+        ));
+    }
+    let data = fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn save_usage_stats_to_path(path: &Path, stats: &UsageStats) -> io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let payload = serde_json::to_string_pretty(stats)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(&tmp, payload.as_bytes())?;
+    fs::rename(&tmp, path)
+}
+
+/// The saved statistics, or fresh ones starting at `now_unix_ms`.
+pub fn load_usage_stats(now_unix_ms: u64) -> UsageStats {
+    load_usage_stats_from_path(&usage_stats_path()).unwrap_or_else(|_| UsageStats::new(now_unix_ms))
+}
+
+pub fn save_usage_stats(stats: &UsageStats) -> io::Result<()> {
+    save_usage_stats_to_path(&usage_stats_path(), stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_count_once_per_day_and_once_per_recipient() {
+        let mut stats = UsageStats::new(1);
+        stats.record_sent(
+            "2026-10-01",
+            UsageKind::Text,
+            100,
+            &[("a", "Laptop"), ("b", "Phone")],
+        );
+        stats.record_received("2026-10-01", UsageKind::Text, 40, "a", "Laptop (work)");
+        stats.record_sent("2026-10-02", UsageKind::Text, 10, &[]);
+
+        assert_eq!(
+            stats.totals,
+            Counts {
+                sent_messages: 2,
+                sent_bytes: 110,
+                received_messages: 1,
+                received_bytes: 40,
+            }
+        );
+        assert_eq!(stats.peers["b"].counts.sent_bytes, 100);
+        let busiest = stats.peers_by_volume();
+        assert_eq!(busiest[0].0, "a");
+        assert_eq!(busiest[0].1.name, "Laptop (work)");
+        assert_eq!(busiest[0].1.counts.received_messages, 1);
+
+        stats.reset(5);
+        assert_eq!(stats, UsageStats::new(5));
+    }
+
+    #[test]
+    fn text_and_files_are_counted_apart_per_device() {
+        let mut stats = UsageStats::new(1);
+        stats.record_sent("d", UsageKind::Text, 10, &[("a", "")]);
+        stats.record_sent("d", UsageKind::File, 1000, &[("a", "")]);
+        stats.record_received("d", UsageKind::File, 500, "a", "");
+
+        let peer = &stats.peers["a"];
+        assert_eq!(
+            peer.text,
+            Counts {
+                sent_messages: 1,
+                sent_bytes: 10,
+                ..Counts::default()
+            }
+        );
+        assert_eq!(
+            peer.files,
+            Counts {
+                sent_messages: 1,
+                sent_bytes: 1000,
+                received_messages: 1,
+                received_bytes: 500,
+            }
+        );
+        assert_eq!(peer.counts.sent_bytes, 1010);
+    }
+
+    #[test]
+    fn counters_saved_before_the_split_still_load() {
+        let stats: UsageStats = serde_json::from_str(
+            r#"{"peers": {"a": {"name": "Laptop", "sent_messages": 2, "sent_bytes": 9}}}"#,
+        )
+        .unwrap();
+        let peer = &stats.peers["a"];
+        assert_eq!(peer.counts.sent_bytes, 9);
+        assert_eq!(peer.text, Counts::default());
+        assert_eq!(peer.files, Counts::default());
+    }
+
+    #[test]
+    fn only_recent_days_are_kept() {
+        let mut stats = UsageStats::default();
+        for day in 0..MAX_DAYS + 3 {
+            stats.record_received(&format!("day-{day:04}"), UsageKind::Text, 1, "a", "");
+        }
+        assert_eq!(stats.days.len(), MAX_DAYS);
+        assert!(!stats.days.contains_key("day-0002"));
+        assert!(stats.days.contains_key("day-0003"));
+        // The totals keep counting what fell off the per-day table.
+        assert_eq!(stats.totals.received_messages, MAX_DAYS as u64 + 3);
+        assert_eq!(
+            stats.peers["a"].counts.received_messages,
+            MAX_DAYS as u64 + 3
+        );
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("usage_stats.json");
+        let mut stats = UsageStats::new(7);
+        stats.record_sent("2026-10-16", UsageKind::File, 3, &[("a", "Laptop")]);
+        save_usage_stats_to_path(&path, &stats).unwrap();
+        assert_eq!(load_usage_stats_from_path(&path).unwrap(), stats);
+    }
+}
@@ -0,0 +1,250 @@
+//! Automation API pipe server and command dispatch (see
+//! [`cliprelay_client::automation`]).
+
+use super::*;
+
+/// Accept automation clients on the per-user named pipe for as long as
+/// the runtime lives.  Each connection is served on its own task; the
+/// next pipe instance is created before handing off so that a second
+/// client never sees `ERROR_PIPE_BUSY` while the first is being served.
+/// Every instance carries a DACL admitting only this user, and the
+/// first fails if another process already owns the name.
+pub(super) async fn automation_server_task(token: String, ui_event_tx: RepaintingSender) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let pipe_name = automation::pipe_name(&current_user_name());
+    let mut security = match OwnerOnly::new() {
+        Ok(security) => security,
+        Err(err) => {
+            warn!(pipe = %pipe_name, "automation API unavailable: {err}");
+            return;
+        }
+    };
+    // SAFETY: `security` outlives every pipe created from it.
+    let mut server = match unsafe {
+        ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create_with_security_attributes_raw(&pipe_name, security.as_raw())
+    } {
+        Ok(server) => server,
+        Err(err) => {
+            warn!(pipe = %pipe_name, "automation API unavailable: {err}");
+            return;
+        }
+    };
+    info!(pipe = %pipe_name, "automation API listening");
+
+    loop {
+        if let Err(err) = server.connect().await {
+            warn!("automation pipe connect failed: {err}");
+            return;
+        }
+        let connected = server;
+        // SAFETY: as above.
+        server = match unsafe {
+            ServerOptions::new()
+                .reject_remote_clients(true)
+                .create_with_security_attributes_raw(&pipe_name, security.as_raw())
+        } {
+            Ok(server) => server,
+            Err(err) => {
+                warn!("automation pipe re-create failed: {err}");
+                return;
+            }
+        };
+
+        let token = token.clone();
+        let ui_event_tx = ui_event_tx.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_automation_client(connected, &token, &ui_event_tx).await {
+                debug!("automation client ended: {err}");
+            }
+        });
+    }
+}
+
+async fn serve_automation_client(
+    pipe: tokio::net::windows::named_pipe::NamedPipeServer,
+    token: &str,
+    ui_event_tx: &RepaintingSender,
+) -> io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    /// How long to wait for the UI loop to answer before giving up.
+    const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    let (read_half, mut write_half) = tokio::io::split(pipe);
+    let mut reader = BufReader::new(read_half);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = (&mut reader)
+            .take(automation::MAX_REQUEST_BYTES as u64 + 1)
+            .read_line(&mut line)
+            .await?;
+        if read == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let oversized = line.len() > automation::MAX_REQUEST_BYTES;
+
+        let response = match automation::parse_request_line(&line, token) {
+            Ok(command) => {
+                let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+                if ui_event_tx
+                    .send(UiEvent::AutomationRequest {
+                        command,
+                        reply: reply_tx,
+                    })
+                    .is_err()
+                {
+                    AutomationResponse::failure("client is shutting down")
+                } else {
+                    match timeout(REPLY_TIMEOUT, reply_rx).await {
+                        Ok(Ok(response)) => response,
+                        _ => AutomationResponse::failure("client did not respond in time"),
+                    }
+                }
+            }
+            Err(response) => response,
+        };
+        write_half.write_all(response.to_line().as_bytes()).await?;
+        write_half.flush().await?;
+
+        // The rest of an oversized line would be misread as a new
+        // request; drop the connection instead.
+        if oversized {
+            return Ok(());
+        }
+    }
+}
+
+/// Answer one automation API request from the current UI state.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn handle_automation_command(
+    command: AutomationCommand,
+    config: &ClientConfig,
+    connection_status: &str,
+    peers: &[PeerInfo],
+    room_key_ready: bool,
+    sync_direction: SyncDirection,
+    text_transforms: &[Transform],
+    secret_check: SecretPolicy,
+    oversized_text: OversizedText,
+    history: &mut VecDeque<ActivityEntry>,
+    history_retention: HistoryRetention,
+    history_unlocked: bool,
+    clip_ring: &mut ClipRing,
+    runtime_cmd_tx: &mpsc::UnboundedSender<RuntimeCommand>,
+) -> AutomationResponse {
+    if let Err(response) = automation::check_lock(&command, history_unlocked) {
+        return response;
+    }
+    // Sends made before the room key is ready wait in the runtime.
+    let can_send = connection_status == "Connected";
+    if matches!(
+        command,
+        AutomationCommand::SendText { .. } | AutomationCommand::SendFile { .. }
+    ) && !sync_direction.allows_send()
+    {
+        return AutomationResponse::failure("sending is disabled (receive-only mode)");
+    }
+    match command {
+        AutomationCommand::Status => AutomationResponse::success(serde_json::json!({
+            "connection": connection_status,
+            "room_key_ready": room_key_ready,
+            "room_code": config.room_code,
+            "room_id": config.room_id,
+            "device_id": config.device_id,
+            "device_name": config.device_name,
+            "peer_count": peers.iter().filter(|p| p.device_id != config.device_id).count(),
+        })),
+        AutomationCommand::Peers => {
+            let list: Vec<_> = peers
+                .iter()
+                .filter(|p| p.device_id != config.device_id)
+                .map(
+                    |p| serde_json::json!({"device_id": p.device_id, "device_name": p.device_name}),
+                )
+                .collect();
+            AutomationResponse::success(serde_json::Value::Array(list))
+        }
+        AutomationCommand::History { limit } => {
+            let limit = limit
+                .unwrap_or(automation::DEFAULT_HISTORY_LIMIT)
+                .min(history_retention::MAX_ENTRIES_LIMIT);
+            let entries: Vec<&ActivityEntry> = history.iter().take(limit).collect();
+            match serde_json::to_value(entries) {
+                Ok(value) => AutomationResponse::success(value),
+                Err(err) => AutomationResponse::failure(format!("history unavailable: {err}")),
+            }
+        }
+        AutomationCommand::SendText { text } => {
+            if !can_send {
+                return AutomationResponse::failure("not connected");
+            }
+            let text = transforms::run(text_transforms, Stage::Send, &text);
+            if text.trim().is_empty() {
+                return AutomationResponse::failure("text is empty");
+            }
+            // Converted to a file transfer by the runtime when allowed.
+            if text.len() > MAX_CLIPBOARD_TEXT_BYTES && oversized_text != OversizedText::SendAsFile
+            {
+                return AutomationResponse::failure(format!(
+                    "text exceeds {MAX_CLIPBOARD_TEXT_BYTES} bytes"
+                ));
+            }
+            if let Some(refusal) = secret_refusal(secret_check, &text) {
+                return AutomationResponse::failure(refusal);
+            }
+            let id = push_sent_history(
+                history,
+                history_retention,
+                "text",
+                preview_text(&text, 120),
+                Some(sha256_bytes(text.as_bytes())),
+            );
+            clip_ring.push(RingItem {
+                ts_unix_ms: now_unix_ms(),
+                source: RingSource::Sent,
+                peer_device_id: "room".to_owned(),
+                text: text.clone(),
+                label: None,
+            });
+            let bytes = text.len();
+            if runtime_cmd_tx
+                .send(RuntimeCommand::SendText(id, text.into()))
+                .is_err()
+            {
+                return AutomationResponse::failure("runtime unavailable");
+            }
+            AutomationResponse::success(serde_json::json!({ "queued_bytes": bytes }))
+        }
+        AutomationCommand::SendFile { path } => {
+            if !can_send {
+                return AutomationResponse::failure("not connected");
+            }
+            if !path.is_file() {
+                return AutomationResponse::failure(format!("not a file: {}", path.display()));
+            }
+            let id = push_sent_history(
+                history,
+                history_retention,
+                "file",
+                format!("{}", path.display()),
+                None,
+            );
+            let shown = path.display().to_string();
+            if runtime_cmd_tx
+                .send(RuntimeCommand::SendFile(id, path))
+                .is_err()
+            {
+                return AutomationResponse::failure("runtime unavailable");
+            }
+            AutomationResponse::success(serde_json::json!({ "queued_file": shown }))
+        }
+    }
+}
@@ -0,0 +1,511 @@
+//! File transfer: sending files in chunks, cancelling and reassembling
+//! incoming transfers.
+
+use super::*;
+
+#[derive(Debug)]
+pub(super) struct CompletedFile {
+    pub(super) sender_device_id: String,
+    pub(super) file_name: String,
+    pub(super) temp_path: PathBuf,
+    pub(super) size_bytes: u64,
+    pub(super) signer_key: Option<String>,
+    pub(super) sha256: [u8; 32],
+}
+
+#[derive(Debug)]
+struct InflightTransfer {
+    sender_device_id: String,
+    file_name: String,
+    total_size: u64,
+    total_chunks: u32,
+    received: Vec<Option<Vec<u8>>>,
+    last_update_ms: u64,
+    /// Key that signed every chunk so far; `None` once any chunk was
+    /// unsigned or signed by a different key.
+    signer_key: Option<String>,
+    /// The first chunk was sealed; later chunks must be too, so nobody
+    /// can slip plain data into a sealed transfer or the reverse.
+    sealed: bool,
+}
+
+/// Chunks of an outgoing transfer written to the relay socket, judged
+/// from the [`Outbox`] frame counts.
+#[derive(Default)]
+struct ChunkAcks {
+    /// Session the first chunk was queued on.
+    session: Option<u64>,
+    /// Frame position of each chunk queued on that session.
+    chunk_frames: Vec<u64>,
+}
+
+impl ChunkAcks {
+    /// A chunk has just been queued.
+    fn queued(&mut self, shared_state: &SharedRuntimeState) {
+        let Ok(outbox) = shared_state.outbox.lock() else {
+            return;
+        };
+        let (session, position) = outbox.queued_position();
+        if *self.session.get_or_insert(session) == session {
+            self.chunk_frames.push(position);
+        }
+    }
+
+    /// Chunks written so far; `None` once their session has ended.
+    fn written(&self, shared_state: &SharedRuntimeState) -> Option<u32> {
+        let Some(session) = self.session else {
+            return Some(0);
+        };
+        let written = shared_state.outbox.lock().ok()?.written_in(session)?;
+        Some(self.chunk_frames.partition_point(|&frame| frame <= written) as u32)
+    }
+}
+
+/// How a file send that got under way ended.
+pub(super) enum FileSendEnd {
+    Sent,
+    Cancelled,
+}
+
+pub(super) async fn send_file_v1(
+    path: &Path,
+    config: &ClientConfig,
+    shared_state: &SharedRuntimeState,
+    network_send_tx: &mpsc::UnboundedSender<WireMessage>,
+    counter: &mut u64,
+    ui_event_tx: &RepaintingSender,
+) -> Result<FileSendEnd, String> {
+    let path = path.to_path_buf();
+    let max_bytes = max_file_bytes();
+
+    let (file_name, data) = tokio::task::spawn_blocking(move || {
+        let meta = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+        if meta.len() == 0 {
+            return Err("file is empty".to_string());
+        }
+        if meta.len() > max_bytes {
+            return Err(format!(
+                "file too large ({} bytes); limit is {max_bytes} bytes",
+                meta.len()
+            ));
+        }
+        let name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| "invalid file name".to_string())?
+            .to_string();
+        let data = std::fs::read(&path).map_err(|e| e.to_string())?;
+        Ok::<_, String>((name, data))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    // Random, never derived from the file or the time: the chunk nonces
+    // are only unique while each transfer key seals a single file.
+    let transfer_id = hex::encode(rand::random::<[u8; 16]>());
+    // Decided once, so that a device joining mid-transfer cannot make
+    // it mix sealed and plain chunks.
+    let sealed = with_session(shared_state, |session| {
+        if session.room_key_ready() {
+            Ok(session.peers_open_sealed_chunks())
+        } else {
+            Err("room key not ready".to_string())
+        }
+    })?;
+    let chunk_mime = if sealed {
+        MIME_FILE_CHUNK_SEALED_JSON_B64
+    } else {
+        MIME_FILE_CHUNK_JSON_B64
+    };
+
+    let total_size = u64::try_from(data.len()).map_err(|_| "file too large".to_string())?;
+    let total_chunks = chunker::total_chunks(data.len());
+    if total_chunks == 0 {
+        return Err("file produced no chunks".to_string());
+    }
+    if total_chunks > MAX_TOTAL_CHUNKS {
+        return Err(format!("file needs too many chunks ({total_chunks})"));
+    }
+
+    let key = transfers::transfer_key(&config.device_id, &transfer_id);
+    let progress = |done_chunks: u32| {
+        let _ = ui_event_tx.send(UiEvent::TransferProgress(TransferProgress {
+            transfer_id: transfer_id.clone(),
+            owner_device_id: config.device_id.clone(),
+            direction: TransferDirection::Outgoing,
+            file_name: file_name.clone(),
+            total_bytes: total_size,
+            total_chunks,
+            done_chunks,
+            at_unix_ms: now_unix_ms(),
+        }));
+    };
+    let ended = |outcome: TransferOutcome| {
+        let _ = ui_event_tx.send(UiEvent::TransferEnded {
+            owner_device_id: config.device_id.clone(),
+            transfer_id: transfer_id.clone(),
+            outcome,
+        });
+    };
+    progress(0);
+
+    let mut acks = ChunkAcks::default();
+    let mut next_chunk = 0;
+    let mut reported = 0;
+    loop {
+        // Checked before every chunk: set by the Cancel button or by a
+        // receiver's cancel event.
+        let cancelled = shared_state
+            .cancelled_transfers
+            .lock()
+            .ok()
+            .and_then(|cancelled| cancelled.get(&key));
+        if let Some(source) = cancelled {
+            // Announced even when a receiver cancelled, so that every
+            // other receiver drops what it buffered.
+            send_transfer_cancel(
+                &transfer_id,
+                &config.device_id,
+                config,
+                shared_state,
+                network_send_tx,
+                counter,
+            )
+            .await?;
+            info!(file = %file_name, sent_chunks = reported, "file send cancelled");
+            ended(TransferOutcome::Cancelled(source));
+            return Ok(FileSendEnd::Cancelled);
+        }
+
+        if next_chunk < total_chunks {
+            let chunk_index = next_chunk;
+            let text_utf8 = if sealed {
+                with_session(shared_state, |session| {
+                    session
+                        .seal_file_chunk(&transfer_id, &file_name, &data, chunk_index)
+                        .map_err(|e| e.to_string())
+                })
+            } else {
+                Ok(FileChunkEnvelope::for_chunk(
+                    &transfer_id,
+                    &file_name,
+                    &data,
+                    chunk_index,
+                ))
+            }
+            .and_then(|env| env.to_text().map_err(|e| e.to_string()));
+            let payload = text_utf8.and_then(|text_utf8| {
+                seal_event(shared_state, counter, chunk_mime, text_utf8, false, None)
+            });
+            let payload = match payload {
+                Ok(payload) => payload,
+                Err(err) => {
+                    ended(TransferOutcome::Failed);
+                    return Err(err);
+                }
+            };
+            network_send_clipboard(shared_state, network_send_tx, payload).await;
+            acks.queued(shared_state);
+            next_chunk += 1;
+        }
+
+        // Progress, and so the speed shown, follows the socket writes,
+        // which trail the paced queue: keep reporting until the last
+        // chunk is out (or the session it was queued on has ended).
+        let written = acks.written(shared_state);
+        if let Some(written) = written
+            && written != reported
+        {
+            progress(written);
+            reported = written;
+        }
+        let all_queued = next_chunk == total_chunks;
+        match written {
+            Some(written) if written >= total_chunks => break,
+            None if all_queued => break,
+            _ => {}
+        }
+        tokio::time::sleep(if all_queued {
+            ACK_POLL_INTERVAL
+        } else {
+            CHUNK_PACING
+        })
+        .await;
+    }
+
+    ended(TransferOutcome::Completed);
+    let _ = ui_event_tx.send(UiEvent::LastSent {
+        ts_unix_ms: now_unix_ms(),
+        kind: UsageKind::File,
+        bytes: total_size,
+    });
+    Ok(FileSendEnd::Sent)
+}
+
+/// Tell the room that transfer `transfer_id` of `owner_device_id` has
+/// been cancelled.
+pub(super) async fn send_transfer_cancel(
+    transfer_id: &str,
+    owner_device_id: &str,
+    config: &ClientConfig,
+    shared_state: &SharedRuntimeState,
+    network_send_tx: &mpsc::UnboundedSender<WireMessage>,
+    counter: &mut u64,
+) -> Result<(), String> {
+    let cancel = TransferCancel {
+        transfer_id: transfer_id.to_owned(),
+        owner_device_id: owner_device_id.to_owned(),
+    };
+    let text_utf8 = serde_json::to_string(&cancel).map_err(|e| e.to_string())?;
+    let payload = seal_event(
+        shared_state,
+        counter,
+        MIME_TRANSFER_CANCEL_JSON,
+        text_utf8,
+        false,
+        None,
+    )?;
+    network_send_clipboard(shared_state, network_send_tx, payload).await;
+    persist_last_counter(config, *counter);
+    Ok(())
+}
+
+/// A peer cancelled a transfer: either the sender stopped one we are
+/// receiving, or a receiver stopped one of ours.
+pub(super) fn handle_transfer_cancel(
+    config: &ClientConfig,
+    ui_event_tx: &RepaintingSender,
+    shared_state: &SharedRuntimeState,
+    sender_device_id: &str,
+    cancel: TransferCancel,
+) {
+    let ours = cancel.owner_device_id == config.device_id;
+    if !ours && cancel.owner_device_id != sender_device_id {
+        // Only the sender can cancel for third parties.
+        return;
+    }
+    let key = transfers::transfer_key(&cancel.owner_device_id, &cancel.transfer_id);
+    // A transfer cancelled here too keeps reporting as ours.
+    let source = shared_state
+        .cancelled_transfers
+        .lock()
+        .ok()
+        .and_then(|mut cancelled| {
+            cancelled.insert(key.clone(), CancelSource::Peer, now_unix_ms());
+            cancelled.get(&key)
+        })
+        .unwrap_or(CancelSource::Peer);
+    if ours {
+        // `send_file_v1` notices before its next chunk.
+        info!(from = %sender_device_id, "receiver cancelled our file transfer");
+        return;
+    }
+    discard_incoming_transfer(&key);
+    let _ = ui_event_tx.send(UiEvent::TransferEnded {
+        owner_device_id: cancel.owner_device_id,
+        transfer_id: cancel.transfer_id,
+        outcome: TransferOutcome::Cancelled(source),
+    });
+}
+
+/// Incoming transfers being reassembled, by [`transfers::transfer_key`].
+fn incoming_transfers() -> &'static Mutex<HashMap<String, InflightTransfer>> {
+    use std::sync::OnceLock;
+
+    static TRANSFERS: OnceLock<Mutex<HashMap<String, InflightTransfer>>> = OnceLock::new();
+    TRANSFERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Drop the chunks buffered for an incoming transfer.  Returns whether
+/// anything was buffered.
+pub(super) fn discard_incoming_transfer(key: &str) -> bool {
+    incoming_transfers()
+        .lock()
+        .map(|mut guard| guard.remove(key).is_some())
+        .unwrap_or(false)
+}
+
+pub(super) fn handle_file_chunk_event(
+    _config: &ClientConfig,
+    ui_event_tx: &RepaintingSender,
+    shared_state: &SharedRuntimeState,
+    sender_device_id: String,
+    text_utf8: &str,
+    sealed: bool,
+    signer_key: Option<String>,
+) -> Result<Option<CompletedFile>, String> {
+    use std::sync::OnceLock;
+
+    let transfers = incoming_transfers();
+    /// Transfers refused by the extension policy, so the UI hears about
+    /// each one once rather than per chunk.
+    static BLOCKED: OnceLock<Mutex<HashMap<String, u64>>> = OnceLock::new();
+    let blocked = BLOCKED.get_or_init(|| Mutex::new(HashMap::new()));
+
+    let env = FileChunkEnvelope::parse(text_utf8).map_err(|e| e.to_string())?;
+    if env.transfer_id.trim().is_empty()
+        || env.total_chunks == 0
+        || env.total_chunks > MAX_TOTAL_CHUNKS
+        || env.chunk_index >= env.total_chunks
+        || env.total_size == 0
+        || env.total_size > max_file_bytes()
+    {
+        return Ok(None);
+    }
+
+    let now = now_unix_ms();
+    let key = transfers::transfer_key(&sender_device_id, &env.transfer_id);
+
+    // Chunks still arriving for a cancelled transfer are dropped.
+    let cancelled = shared_state
+        .cancelled_transfers
+        .lock()
+        .map(|mut cancelled| {
+            cancelled.prune(now, TRANSFER_TIMEOUT_MS);
+            cancelled.get(&key).is_some()
+        })
+        .unwrap_or(false);
+    if cancelled {
+        discard_incoming_transfer(&key);
+        return Ok(None);
+    }
+
+    // Every chunk names the file, so a blocked type is refused at
+    // whichever chunk arrives first and is never buffered.
+    let file_name = sanitize_file_name(&env.file_name);
+    let allowed = shared_state
+        .extension_policy
+        .lock()
+        .map(|policy| policy.allows(&file_name))
+        .unwrap_or(false);
+    if !allowed {
+        let mut blocked = blocked
+            .lock()
+            .map_err(|_| "blocked map poisoned".to_string())?;
+        blocked.retain(|_, ts| now.saturating_sub(*ts) <= TRANSFER_TIMEOUT_MS);
+        if blocked.insert(key.clone(), now).is_none() {
+            info!(sender = %sender_device_id, file = %file_name, "file blocked by extension policy");
+            let _ = ui_event_tx.send(UiEvent::FileBlocked {
+                sender_device_id,
+                file_name,
+                size_bytes: env.total_size,
+            });
+        }
+        // Drop anything buffered before the policy changed.
+        if let Ok(mut guard) = transfers.lock() {
+            guard.remove(&key);
+        }
+        return Ok(None);
+    }
+
+    let chunk = if sealed {
+        with_session(shared_state, |session| {
+            session.open_file_chunk(&env).map_err(|e| e.to_string())
+        })?
+    } else {
+        env.data().map_err(|e| e.to_string())?
+    };
+    if chunk.is_empty() {
+        return Ok(None);
+    }
+
+    let mut guard = transfers
+        .lock()
+        .map_err(|_| "transfer map poisoned".to_string())?;
+
+    guard.retain(|_, t| now.saturating_sub(t.last_update_ms) <= TRANSFER_TIMEOUT_MS);
+    if !guard.contains_key(&key) && guard.len() >= MAX_INFLIGHT_TRANSFERS {
+        return Ok(None);
+    }
+
+    let entry = guard.entry(key).or_insert_with(|| InflightTransfer {
+        sender_device_id: sender_device_id.clone(),
+        file_name,
+        total_size: env.total_size,
+        total_chunks: env.total_chunks,
+        received: vec![None; env.total_chunks as usize],
+        last_update_ms: now,
+        signer_key: signer_key.clone(),
+        sealed,
+    });
+
+    if entry.total_chunks != env.total_chunks || entry.total_size != env.total_size {
+        return Ok(None);
+    }
+    if entry.sealed != sealed {
+        return Err(format!(
+            "transfer {} mixes sealed and plain chunks",
+            env.transfer_id
+        ));
+    }
+    entry.last_update_ms = now;
+    if entry.signer_key != signer_key {
+        entry.signer_key = None;
+    }
+
+    if entry.received[env.chunk_index as usize].is_none() {
+        entry.received[env.chunk_index as usize] = Some(chunk);
+    }
+
+    let done_chunks = entry.received.iter().filter(|c| c.is_some()).count() as u32;
+    let _ = ui_event_tx.send(UiEvent::TransferProgress(TransferProgress {
+        transfer_id: env.transfer_id.clone(),
+        owner_device_id: sender_device_id.clone(),
+        direction: TransferDirection::Incoming,
+        file_name: entry.file_name.clone(),
+        total_bytes: entry.total_size,
+        total_chunks: entry.total_chunks,
+        done_chunks,
+        at_unix_ms: now,
+    }));
+    if done_chunks < entry.total_chunks {
+        return Ok(None);
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(entry.total_size as usize);
+    for bytes in entry.received.iter().flatten() {
+        out.extend_from_slice(bytes);
+    }
+    if out.len() as u64 != entry.total_size {
+        return Ok(None);
+    }
+
+    // Extract the fields we need for the result, then remove the entry
+    // from the map and drop the lock BEFORE writing the temp file.
+    //
+    // Previous code wrote the file while holding the lock, which:
+    //   (a) blocked all other incoming chunks for the entire write duration, and
+    //   (b) left the entry in the map if `write_incoming_temp_file` failed,
+    //       holding up to `total_size` bytes until the TRANSFER_TIMEOUT_MS
+    //       expiry (120 s).
+    let transfer_key = transfers::transfer_key(&sender_device_id, &env.transfer_id);
+    let (sender_id, file_name, total_size, signer_key) = {
+        let e = guard.remove(&transfer_key);
+        match e {
+            Some(t) => (t.sender_device_id, t.file_name, t.total_size, t.signer_key),
+            None => return Ok(None), // already removed (shouldn't happen)
+        }
+    };
+    drop(guard); // release the mutex before I/O
+
+    let sha256 = sha256_bytes(&out);
+    let written = write_incoming_temp_file(&file_name, &out);
+    let _ = ui_event_tx.send(UiEvent::TransferEnded {
+        owner_device_id: sender_id.clone(),
+        transfer_id: env.transfer_id,
+        outcome: if written.is_ok() {
+            TransferOutcome::Completed
+        } else {
+            TransferOutcome::Failed
+        },
+    });
+    let temp_path = written?;
+    Ok(Some(CompletedFile {
+        sender_device_id: sender_id,
+        file_name,
+        temp_path,
+        size_bytes: total_size,
+        signer_key,
+        sha256,
+    }))
+}
@@ -0,0 +1,676 @@
+//! Tray icon, click hooks and menu.
+
+use super::*;
+
+/// Tray "Recent Activity" rows: every favorite, then the newest
+/// [`TRAY_RECENT_ENTRIES`] others.
+pub(super) fn tray_recent_items(history: &VecDeque<ActivityEntry>) -> Vec<TrayRecentItem> {
+    history
+        .iter()
+        .filter(|e| e.favorite)
+        .chain(
+            history
+                .iter()
+                .filter(|e| !e.favorite)
+                .take(TRAY_RECENT_ENTRIES),
+        )
+        .map(|e| TrayRecentItem {
+            ts_unix_ms: e.ts_unix_ms,
+            label: format!(
+                "{} {}",
+                match e.direction {
+                    ActivityDirection::Sent => "\u{2191}",
+                    ActivityDirection::Received => "\u{2193}",
+                },
+                preview_text(&e.summary.replace(['\r', '\n'], " "), 40)
+            ),
+            favorite: e.favorite,
+        })
+        .collect()
+}
+
+/// Decoded tray icon pixels, kept so the unread badge and the transfer
+/// progress pie can be composited onto them whenever either changes.
+struct TrayIconImage {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl TrayIconImage {
+    fn to_icon(&self, unread: usize, progress: Option<u8>) -> Option<tray_icon::Icon> {
+        let rgba = match progress {
+            Some(step) => tray_badge::with_progress(&self.rgba, self.width, self.height, step),
+            None => self.rgba.clone(),
+        };
+        let rgba = tray_badge::with_badge(&rgba, self.width, self.height, unread);
+        tray_icon::Icon::from_rgba(rgba, self.width, self.height).ok()
+    }
+}
+
+fn load_tray_image_from_ico(bytes: &[u8]) -> Option<TrayIconImage> {
+    let img = image::load_from_memory(bytes).ok()?.to_rgba8();
+    Some(TrayIconImage {
+        width: img.width(),
+        height: img.height(),
+        rgba: img.into_raw(),
+    })
+}
+
+pub(super) fn load_egui_icon(bytes: &[u8]) -> Option<egui::IconData> {
+    let img = image::load_from_memory(bytes).ok()?.to_rgba8();
+    Some(egui::IconData {
+        rgba: img.to_vec(),
+        width: img.width(),
+        height: img.height(),
+    })
+}
+
+/// What the tray click callback needs to carry out a [`TrayAction`],
+/// also from the thread that runs a deferred single click.
+struct TrayClickHooks {
+    ctx: egui::Context,
+    toggle_flag: Arc<AtomicBool>,
+    action_requested: Arc<Mutex<Option<TrayAction>>>,
+    eframe_hwnd: isize,
+    shared_visible: Arc<AtomicBool>,
+}
+
+impl TrayClickHooks {
+    fn run(&self, action: TrayAction) {
+        debug!(?action, "tray click action");
+        match action {
+            TrayAction::Nothing => return,
+            TrayAction::ToggleWindow => {
+                self.toggle_flag.store(true, Ordering::SeqCst);
+                // Direct Win32 show/hide — bypasses the dormant eframe
+                // event loop that never calls update() for hidden windows.
+                if self.eframe_hwnd != 0 {
+                    let new_visible = !self.shared_visible.load(Ordering::SeqCst);
+                    self.shared_visible.store(new_visible, Ordering::SeqCst);
+                    unsafe { win32_set_window_visible(self.eframe_hwnd, new_visible) };
+                    trace!("[tray] Win32 ShowWindow: visible={new_visible}");
+                }
+            }
+            action => {
+                if let Ok(mut slot) = self.action_requested.lock() {
+                    *slot = Some(action);
+                }
+                if action.shows_window() && self.eframe_hwnd != 0 {
+                    self.shared_visible.store(true, Ordering::SeqCst);
+                    unsafe { win32_set_window_visible(self.eframe_hwnd, true) };
+                }
+            }
+        }
+        self.ctx.request_repaint();
+    }
+}
+
+/// One history entry in the tray's Recent Activity submenu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TrayRecentItem {
+    ts_unix_ms: u64,
+    label: String,
+    favorite: bool,
+}
+
+pub(super) struct TrayState {
+    tray_icon: tray_icon::TrayIcon,
+    current_status: TrayStatus,
+    current_unread: usize,
+    /// Progress pie step while transfers are in flight.
+    current_progress: Option<u8>,
+    icon_red: TrayIconImage,
+    icon_amber: TrayIconImage,
+    icon_green: TrayIconImage,
+    /// Current menu contents, kept so either list can be replaced
+    /// without the other.
+    snippet_names: Vec<String>,
+    profile_names: Vec<String>,
+    active_profile: Option<usize>,
+    /// Offer "Retry Now" after the runtime gave up reconnecting.
+    retry_visible: bool,
+    /// Offer "Reconnect Now" while waiting to reconnect.
+    reconnect_now_visible: bool,
+    /// Queued notifications; Apply All / Dismiss All are shown when
+    /// non-zero.
+    pending_count: usize,
+    /// Offer "Resume Sync" while sync is paused.
+    resume_visible: bool,
+    recent: Vec<TrayRecentItem>,
+    /// The history lock is closed: snippet names and history previews
+    /// are replaced by "Locked".
+    locked: bool,
+}
+
+impl TrayState {
+    /// Create the system tray icon and register OS-level event handlers.
+    ///
+    /// `quit_flag` is set `true` when the user clicks "Quit" in the tray
+    /// context menu (shown on right-click).  Clicks on the tray icon
+    /// itself run the action `click_actions` binds to them: `toggle_flag`
+    /// is set `true` for a show/hide, other actions are left in
+    /// `click_action_requested`.  All handlers call `ctx.request_repaint()`
+    /// to wake the eframe event loop even when the window is hidden
+    /// (which suppresses normal repaint timers).
+    ///
+    /// `menu_on_left_click` is explicitly set to `false` so that the
+    /// context menu is only shown on right-click (standard Windows
+    /// behaviour).  The tray-icon crate defaults to `true`, which causes
+    /// `TrackPopupMenu` to fire on every left-click — blocking the event
+    /// loop and preventing the toggle handler from working.
+    ///
+    /// `snippet_requested` receives the index of a snippet picked from
+    /// the tray's Snippets submenu; the update loop sends it.
+    /// `queue_action_requested` likewise receives Apply All / Dismiss All,
+    /// and `favorite_toggle_requested` the time of a history entry
+    /// clicked in the Recent Activity submenu.
+    ///
+    /// `eframe_hwnd` is the Win32 HWND of the main eframe window,
+    /// obtained via `FindWindowW`.  The toggle callback uses it to call
+    /// `ShowWindow`/`SetForegroundWindow` directly, because eframe does
+    /// **not** call `update()` (and therefore never processes toggle
+    /// flags) while the window is invisible.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        ctx: &egui::Context,
+        quit_flag: Arc<AtomicBool>,
+        toggle_flag: Arc<AtomicBool>,
+        snippet_requested: Arc<Mutex<Option<usize>>>,
+        room_action_requested: Arc<Mutex<Option<RoomAction>>>,
+        queue_action_requested: Arc<Mutex<Option<NotificationAction>>>,
+        favorite_toggle_requested: Arc<Mutex<Option<u64>>>,
+        click_actions: Arc<Mutex<TrayClickActions>>,
+        click_action_requested: Arc<Mutex<Option<TrayAction>>>,
+        eframe_hwnd: isize,
+        shared_visible: Arc<AtomicBool>,
+    ) -> Option<Self> {
+        use tray_icon::menu::{MenuEvent, MenuId};
+        use tray_icon::{TrayIconBuilder, TrayIconEvent};
+
+        let icon_red = load_tray_image_from_ico(TRAY_ICON_RED_BYTES)?;
+        let icon_amber = load_tray_image_from_ico(TRAY_ICON_AMBER_BYTES)?;
+        let icon_green = load_tray_image_from_ico(TRAY_ICON_GREEN_BYTES)?;
+
+        let quit_id = MenuId::new(TRAY_MENU_QUIT_ID);
+        let menu = build_tray_menu(&[], &[], None, false, false, 0, false, &[]);
+
+        info!("TrayState::new — building tray icon (menu_on_left_click=false)");
+        let tray_icon = match TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_menu_on_left_click(false)
+            .with_icon(icon_amber.to_icon(0, None)?)
+            .with_tooltip("ClipRelay | connecting")
+            .build()
+        {
+            Ok(t) => {
+                info!("TrayState::new — tray icon built successfully");
+                t
+            }
+            Err(err) => {
+                error!("TrayState::new — tray icon build FAILED: {err}");
+                return None;
+            }
+        };
+
+        // OS-level callbacks — fire from the Windows message handler so
+        // they work even when the eframe event loop is sleeping.
+        let ctx_menu = ctx.clone();
+        let quit_id_dbg = quit_id.clone();
+        let menu_visible = shared_visible.clone();
+        let menu_click_action = click_action_requested.clone();
+        MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+            let room_action = match event.id.0.as_str() {
+                TRAY_MENU_CHANGE_ROOM_ID => Some(RoomAction::Change),
+                TRAY_MENU_LEAVE_ROOM_ID => Some(RoomAction::Leave),
+                TRAY_MENU_COPY_INVITE_ID => Some(RoomAction::CopyInvite),
+                TRAY_MENU_RETRY_ID => Some(RoomAction::Reconnect),
+                TRAY_MENU_RECONNECT_NOW_ID => Some(RoomAction::ReconnectNow),
+                id => id
+                    .strip_prefix(TRAY_MENU_PROFILE_PREFIX)
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .map(RoomAction::SwitchProfile),
+            };
+            if let Some(action) = room_action {
+                debug!(?action, "tray room action selected");
+                if let Ok(mut slot) = room_action_requested.lock() {
+                    *slot = Some(action);
+                }
+                // update() does not run while the window is hidden.
+                if action.needs_window() && eframe_hwnd != 0 {
+                    menu_visible.store(true, Ordering::SeqCst);
+                    unsafe { win32_set_window_visible(eframe_hwnd, true) };
+                }
+                ctx_menu.request_repaint();
+                return;
+            }
+            let queue_action = match event.id.0.as_str() {
+                TRAY_MENU_APPLY_ALL_ID => Some(NotificationAction::Apply),
+                TRAY_MENU_DISMISS_ALL_ID => Some(NotificationAction::Dismiss),
+                _ => None,
+            };
+            if let Some(action) = queue_action {
+                debug!(?action, "tray queue action selected");
+                if let Ok(mut slot) = queue_action_requested.lock() {
+                    *slot = Some(action);
+                }
+                ctx_menu.request_repaint();
+                return;
+            }
+            if let Some(ts) = event
+                .id
+                .0
+                .strip_prefix(TRAY_MENU_HISTORY_PREFIX)
+                .and_then(|ts| ts.parse::<u64>().ok())
+            {
+                debug!(ts, "tray favorite toggled");
+                if let Ok(mut slot) = favorite_toggle_requested.lock() {
+                    *slot = Some(ts);
+                }
+                ctx_menu.request_repaint();
+                return;
+            }
+            if event.id.0 == TRAY_MENU_RESUME_ID {
+                // Only offered while paused, so the toggle resumes.
+                if let Ok(mut slot) = menu_click_action.lock() {
+                    *slot = Some(TrayAction::TogglePause);
+                }
+                ctx_menu.request_repaint();
+                return;
+            }
+            if let Some(index) = event
+                .id
+                .0
+                .strip_prefix(TRAY_MENU_SNIPPET_PREFIX)
+                .and_then(|i| i.parse::<usize>().ok())
+            {
+                debug!(index, "tray snippet selected");
+                if let Ok(mut slot) = snippet_requested.lock() {
+                    *slot = Some(index);
+                }
+                ctx_menu.request_repaint();
+                return;
+            }
+            // Log every menu event, even non-quit ones.
+            let is_quit = event.id == quit_id;
+            debug!(
+                menu_event_id = ?event.id,
+                quit_id = ?quit_id_dbg,
+                is_quit,
+                "MenuEvent received"
+            );
+            trace!(
+                "[tray] MenuEvent received: id={:?}, quit_id={:?}, is_quit={}",
+                event.id, quit_id_dbg, is_quit
+            );
+            if is_quit {
+                quit_flag.store(true, Ordering::SeqCst);
+                ctx_menu.request_repaint();
+                debug!("quit_flag stored, repaint requested");
+                trace!("[tray] quit_flag stored, repaint requested");
+
+                // Fallback: if the eframe event loop is dormant (hidden
+                // window), `request_repaint()` may never be honoured.
+                // Give the event loop a short grace period to process the
+                // quit flag cleanly, then force-exit.
+                std::thread::spawn(|| {
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+                    trace!("[tray] quit fallback: event loop did not exit in time, forcing exit");
+                    std::process::exit(0);
+                });
+            }
+        }));
+
+        let hooks = Arc::new(TrayClickHooks {
+            ctx: ctx.clone(),
+            toggle_flag,
+            action_requested: click_action_requested,
+            eframe_hwnd,
+            shared_visible,
+        });
+        // Bumped by every click, so a deferred single click can tell
+        // whether a double click followed it.
+        let click_generation = Arc::new(AtomicU64::new(0));
+        // Set by a double click, whose button-up arrives after it.
+        let skip_next_up = AtomicBool::new(false);
+        TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+            // Log EVERY tray icon event for debugging.
+            debug!(tray_event = ?event, "TrayIconEvent received");
+            trace!("[tray] TrayIconEvent: {event:?}");
+
+            // Only respond to button-up and DoubleClick events.
+            // Ignoring Down events prevents double-toggling when the
+            // Down and Up messages are dispatched in separate event-loop
+            // pump cycles.
+            let click = match &event {
+                TrayIconEvent::Click {
+                    button: tray_icon::MouseButton::Left,
+                    button_state: tray_icon::MouseButtonState::Up,
+                    ..
+                } => {
+                    if skip_next_up.swap(false, Ordering::SeqCst) {
+                        return;
+                    }
+                    TrayClick::Single
+                }
+                TrayIconEvent::DoubleClick {
+                    button: tray_icon::MouseButton::Left,
+                    ..
+                } => TrayClick::Double,
+                TrayIconEvent::Click {
+                    button: tray_icon::MouseButton::Middle,
+                    button_state: tray_icon::MouseButtonState::Up,
+                    ..
+                } => TrayClick::Middle,
+                _ => return,
+            };
+            let actions = click_actions.lock().map(|a| *a).unwrap_or_default();
+            let generation = click_generation.fetch_add(1, Ordering::SeqCst) + 1;
+            trace!("[tray] click={click:?}");
+            let action = match click {
+                TrayClick::Single if actions.single_waits_for_double() => {
+                    // Run it only if no double click follows in time.
+                    let hooks = hooks.clone();
+                    let click_generation = click_generation.clone();
+                    std::thread::spawn(move || {
+                        use windows_sys::Win32::UI::Input::KeyboardAndMouse::GetDoubleClickTime;
+                        let wait_ms = unsafe { GetDoubleClickTime() };
+                        std::thread::sleep(Duration::from_millis(u64::from(wait_ms)));
+                        if click_generation.load(Ordering::SeqCst) == generation {
+                            hooks.run(actions.single);
+                        }
+                    });
+                    return;
+                }
+                TrayClick::Double => {
+                    skip_next_up.store(true, Ordering::SeqCst);
+                    actions.double_click_action()
+                }
+                click => actions.action(click),
+            };
+            hooks.run(action);
+        }));
+
+        Some(Self {
+            tray_icon,
+            current_status: TrayStatus::Amber,
+            current_unread: 0,
+            current_progress: None,
+            icon_red,
+            icon_amber,
+            icon_green,
+            snippet_names: Vec::new(),
+            profile_names: Vec::new(),
+            active_profile: None,
+            retry_visible: false,
+            reconnect_now_visible: false,
+            pending_count: 0,
+            resume_visible: false,
+            recent: Vec::new(),
+            locked: true,
+        })
+    }
+
+    pub(super) fn set_status(&mut self, status: TrayStatus) {
+        if self.current_status == status {
+            return;
+        }
+        self.current_status = status;
+        self.refresh_icon();
+    }
+
+    /// Show `unread` as a numeric badge on the icon (`0` hides it).
+    pub(super) fn set_unread(&mut self, unread: usize) {
+        if self.current_unread == unread {
+            return;
+        }
+        self.current_unread = unread;
+        self.refresh_icon();
+    }
+
+    /// Show a progress pie for `fraction` of the active transfers, or
+    /// the plain status icon when `None`.
+    pub(super) fn set_progress(&mut self, fraction: Option<f32>) {
+        let progress = fraction.map(tray_badge::progress_step);
+        if self.current_progress == progress {
+            return;
+        }
+        self.current_progress = progress;
+        self.refresh_icon();
+    }
+
+    fn refresh_icon(&self) {
+        let image = match self.current_status {
+            TrayStatus::Red => &self.icon_red,
+            TrayStatus::Amber => &self.icon_amber,
+            TrayStatus::Green => &self.icon_green,
+        };
+        if let Some(icon) = image.to_icon(self.current_unread, self.current_progress) {
+            let _ = self.tray_icon.set_icon(Some(icon));
+        }
+    }
+
+    pub(super) fn set_tooltip(&self, text: &str) {
+        let _ = self.tray_icon.set_tooltip(Some(text));
+    }
+
+    /// Rebuild the context menu after the snippet list changed.
+    pub(super) fn set_snippets(&mut self, names: &[String]) {
+        self.snippet_names = names.to_vec();
+        self.rebuild_menu();
+    }
+
+    /// Rebuild the context menu after the room profiles or the active
+    /// room changed.
+    pub(super) fn set_profiles(&mut self, names: &[String], active: Option<usize>) {
+        self.profile_names = names.to_vec();
+        self.active_profile = active;
+        self.rebuild_menu();
+    }
+
+    /// Show or hide the "Retry Now" menu item.
+    pub(super) fn set_retry(&mut self, visible: bool) {
+        if self.retry_visible == visible {
+            return;
+        }
+        self.retry_visible = visible;
+        self.rebuild_menu();
+    }
+
+    /// Show or hide the "Reconnect Now" menu item.
+    pub(super) fn set_reconnect_now(&mut self, visible: bool) {
+        if self.reconnect_now_visible == visible {
+            return;
+        }
+        self.reconnect_now_visible = visible;
+        self.rebuild_menu();
+    }
+
+    /// Rebuild the context menu when the listed history entries or
+    /// their favorite marks changed.
+    pub(super) fn set_recent(&mut self, recent: Vec<TrayRecentItem>) {
+        if self.recent == recent {
+            return;
+        }
+        self.recent = recent;
+        self.rebuild_menu();
+    }
+
+    /// Rebuild the context menu when the history lock opened or closed.
+    pub(super) fn set_locked(&mut self, locked: bool) {
+        if self.locked == locked {
+            return;
+        }
+        self.locked = locked;
+        self.rebuild_menu();
+    }
+
+    /// Show or hide the "Resume Sync" menu item.
+    pub(super) fn set_resume(&mut self, visible: bool) {
+        if self.resume_visible == visible {
+            return;
+        }
+        self.resume_visible = visible;
+        self.rebuild_menu();
+    }
+
+    /// Rebuild the context menu when the queue length changed.
+    pub(super) fn set_pending(&mut self, count: usize) {
+        if self.pending_count == count {
+            return;
+        }
+        self.pending_count = count;
+        self.rebuild_menu();
+    }
+
+    fn rebuild_menu(&self) {
+        self.tray_icon.set_menu(Some(Box::new(build_tray_menu(
+            &self.snippet_names,
+            &self.profile_names,
+            self.active_profile,
+            self.retry_visible,
+            self.reconnect_now_visible,
+            self.pending_count,
+            self.resume_visible,
+            &self.recent,
+            self.locked,
+        ))));
+    }
+}
+
+/// Context menu: Resume Sync (while paused), Retry Now (after giving up
+/// reconnecting) or Reconnect Now (while waiting to), Apply All /
+/// Dismiss All (while notifications are queued), Snippets, Recent
+/// Activity and room-profile submenus (when any exist), room actions and
+/// Quit.  A Recent Activity entry is ticked when it is a favorite;
+/// clicking it toggles the mark.  While `locked`, the Snippets and
+/// Recent Activity submenus hold a single disabled "Locked" item.
+#[allow(clippy::too_many_arguments)]
+fn build_tray_menu(
+    snippet_names: &[String],
+    profile_names: &[String],
+    active_profile: Option<usize>,
+    retry: bool,
+    reconnect_now: bool,
+    pending: usize,
+    resume: bool,
+    recent: &[TrayRecentItem],
+    locked: bool,
+) -> tray_icon::menu::Menu {
+    use tray_icon::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+
+    let menu = Menu::new();
+    if resume {
+        let _ = menu.append(&MenuItem::with_id(
+            TRAY_MENU_RESUME_ID,
+            "Resume Sync",
+            true,
+            None,
+        ));
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    if retry {
+        let _ = menu.append(&MenuItem::with_id(
+            TRAY_MENU_RETRY_ID,
+            "Retry Now",
+            true,
+            None,
+        ));
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    } else if reconnect_now {
+        let _ = menu.append(&MenuItem::with_id(
+            TRAY_MENU_RECONNECT_NOW_ID,
+            "Reconnect Now",
+            true,
+            None,
+        ));
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    if pending > 0 {
+        let _ = menu.append(&MenuItem::with_id(
+            TRAY_MENU_APPLY_ALL_ID,
+            format!("Apply All Notifications ({pending})"),
+            true,
+            None,
+        ));
+        let _ = menu.append(&MenuItem::with_id(
+            TRAY_MENU_DISMISS_ALL_ID,
+            "Dismiss All Notifications",
+            true,
+            None,
+        ));
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    if !snippet_names.is_empty() {
+        let submenu = Submenu::new("Send Snippet", true);
+        if locked {
+            let _ = submenu.append(&MenuItem::new("Locked", false, None));
+        } else {
+            for (index, name) in snippet_names.iter().enumerate() {
+                let item = MenuItem::with_id(
+                    format!("{TRAY_MENU_SNIPPET_PREFIX}{index}"),
+                    name,
+                    true,
+                    None,
+                );
+                let _ = submenu.append(&item);
+            }
+        }
+        let _ = menu.append(&submenu);
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    if !recent.is_empty() {
+        let submenu = Submenu::new("Recent Activity", true);
+        if locked {
+            let _ = submenu.append(&MenuItem::new("Locked", false, None));
+        } else {
+            for item in recent {
+                let entry = CheckMenuItem::with_id(
+                    format!("{TRAY_MENU_HISTORY_PREFIX}{}", item.ts_unix_ms),
+                    &item.label,
+                    true,
+                    item.favorite,
+                    None,
+                );
+                let _ = submenu.append(&entry);
+            }
+        }
+        let _ = menu.append(&submenu);
+        let _ = menu.append(&PredefinedMenuItem::separator());
+    }
+    if !profile_names.is_empty() {
+        let submenu = Submenu::new("Switch Room", true);
+        for (index, name) in profile_names.iter().enumerate() {
+            let item = CheckMenuItem::with_id(
+                format!("{TRAY_MENU_PROFILE_PREFIX}{index}"),
+                name,
+                true,
+                active_profile == Some(index),
+                None,
+            );
+            let _ = submenu.append(&item);
+        }
+        let _ = menu.append(&submenu);
+    }
+    let _ = menu.append(&MenuItem::with_id(
+        TRAY_MENU_COPY_INVITE_ID,
+        "Copy Invite",
+        true,
+        None,
+    ));
+    let _ = menu.append(&MenuItem::with_id(
+        TRAY_MENU_CHANGE_ROOM_ID,
+        "Change Room\u{2026}",
+        true,
+        None,
+    ));
+    let _ = menu.append(&MenuItem::with_id(
+        TRAY_MENU_LEAVE_ROOM_ID,
+        "Leave Room",
+        true,
+        None,
+    ));
+    let _ = menu.append(&PredefinedMenuItem::separator());
+    let _ = menu.append(&MenuItem::with_id(TRAY_MENU_QUIT_ID, "Quit", true, None));
+    menu
+}
@@ -0,0 +1,73 @@
+use cliprelay_client::automation::{
+    AutomationCommand, MAX_REQUEST_BYTES, check_lock, load_or_create_token_at, parse_request_line,
+    pipe_name,
+};
+
+const TOKEN: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+#[test]
+fn parse_request_line_accepts_valid_commands() {
+    let line = format!(r#"{{"token":"{TOKEN}","cmd":"send_text","text":"hello"}}"#);
+    let cmd = parse_request_line(&line, TOKEN).expect("valid request");
+    assert_eq!(
+        cmd,
+        AutomationCommand::SendText {
+            text: "hello".to_owned()
+        }
+    );
+
+    let line = format!(r#"{{"token":"{TOKEN}","cmd":"history"}}"#);
+    let cmd = parse_request_line(&line, TOKEN).expect("valid request");
+    assert_eq!(cmd, AutomationCommand::History { limit: None });
+}
+
+#[test]
+fn parse_request_line_rejects_wrong_token_and_oversized_input() {
+    let line = r#"{"token":"nope","cmd":"status"}"#;
+    let err = parse_request_line(line, TOKEN).expect_err("wrong token");
+    assert!(!err.ok);
+    assert_eq!(err.error.as_deref(), Some("invalid token"));
+
+    let huge = "x".repeat(MAX_REQUEST_BYTES + 1);
+    let err = parse_request_line(&huge, TOKEN).expect_err("oversized");
+    assert!(err.error.unwrap_or_default().contains("too large"));
+}
+
+#[test]
+fn check_lock_refuses_history_only_while_locked() {
+    let history = AutomationCommand::History { limit: Some(5) };
+    let err = check_lock(&history, false).expect_err("locked history");
+    assert!(!err.ok);
+    assert_eq!(err.error.as_deref(), Some("locked"));
+    assert!(check_lock(&history, true).is_ok());
+
+    assert!(check_lock(&AutomationCommand::Status, false).is_ok());
+    let send = AutomationCommand::SendText {
+        text: "hi".to_owned(),
+    };
+    assert!(check_lock(&send, false).is_ok());
+}
+
+#[test]
+fn pipe_name_sanitizes_user_name() {
+    let name = pipe_name(r"DOMAIN\jane doe");
+    assert!(name.starts_with(r"\\.\pipe\ClipRelay.Automation."));
+    assert!(!name["\\\\.\\pipe\\".len()..].contains('\\'));
+    assert!(!name.contains(' '));
+}
+
+#[test]
+fn load_or_create_token_is_stable_and_replaces_garbage() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("automation_token");
+
+    let first = load_or_create_token_at(&path).expect("create token");
+    assert_eq!(first.len(), 64);
+    let second = load_or_create_token_at(&path).expect("reload token");
+    assert_eq!(first, second);
+
+    std::fs::write(&path, "not-a-token").expect("corrupt token");
+    let third = load_or_create_token_at(&path).expect("replace token");
+    assert_ne!(third, "not-a-token");
+    assert_eq!(third.len(), 64);
+}
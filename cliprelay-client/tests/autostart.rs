@@ -0,0 +1,35 @@
+#![cfg(not(target_os = "windows"))]
+
+use std::path::Path;
+
+use cliprelay_client::autostart::{entry_contents, is_enabled_at, set_enabled_at};
+
+#[test]
+fn set_enabled_round_trips_entry_file() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("autostart").join("cliprelay.desktop");
+    let exe = Path::new("/usr/local/bin/ClipRelay");
+
+    assert!(!is_enabled_at(&path, exe, "ClipRelay").unwrap());
+    set_enabled_at(&path, exe, "ClipRelay", true).expect("enable");
+    assert!(is_enabled_at(&path, exe, "ClipRelay").unwrap());
+
+    // An entry for a different executable does not count as enabled.
+    assert!(!is_enabled_at(&path, Path::new("/other/ClipRelay"), "ClipRelay").unwrap());
+
+    set_enabled_at(&path, exe, "ClipRelay", false).expect("disable");
+    assert!(!path.exists());
+    // Disabling twice is not an error.
+    set_enabled_at(&path, exe, "ClipRelay", false).expect("disable again");
+}
+
+#[test]
+fn entry_disabled_in_place_is_reported_off() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("cliprelay.desktop");
+    let exe = Path::new("/usr/local/bin/ClipRelay");
+
+    let contents = format!("{}Hidden=true\n", entry_contents(exe, "ClipRelay"));
+    std::fs::write(&path, contents).unwrap();
+    assert!(!is_enabled_at(&path, exe, "ClipRelay").unwrap());
+}
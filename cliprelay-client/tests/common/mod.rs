@@ -0,0 +1,6 @@
+use std::io;
+
+/// Stand-in for DPAPI in tests: reversible, and unreadable as plain text.
+pub fn xor(data: &[u8]) -> io::Result<Vec<u8>> {
+    Ok(data.iter().map(|b| b ^ 0x5a).collect())
+}
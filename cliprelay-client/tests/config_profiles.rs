@@ -0,0 +1,52 @@
+use cliprelay_client::config_profiles::{
+    MAX_NAME_CHARS, PROFILES_DIR, ProfileNameError, create_profile, list_profiles, load_last_used,
+    parse_name, profile_dir, save_last_used,
+};
+
+#[test]
+fn default_and_invalid_names() {
+    assert_eq!(parse_name("  "), Ok(None));
+    assert_eq!(parse_name("default"), Ok(None));
+    assert_eq!(parse_name(" Work 2 "), Ok(Some("Work 2".to_owned())));
+    assert_eq!(parse_name("../x"), Err(ProfileNameError::InvalidChar('.')));
+    assert_eq!(
+        parse_name(&"a".repeat(MAX_NAME_CHARS + 1)),
+        Err(ProfileNameError::TooLong {
+            max: MAX_NAME_CHARS
+        })
+    );
+}
+
+#[test]
+fn named_profiles_live_in_their_own_directories() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let base = dir.path();
+    assert_eq!(profile_dir(base, None), base);
+    assert_eq!(
+        profile_dir(base, Some("Work")),
+        base.join(PROFILES_DIR).join("Work")
+    );
+
+    assert!(list_profiles(base).is_empty());
+    create_profile(base, "work").expect("create");
+    create_profile(base, "Home").expect("create");
+    std::fs::create_dir_all(base.join(PROFILES_DIR).join("not.valid")).expect("create");
+    assert_eq!(list_profiles(base), ["Home", "work"]);
+}
+
+#[test]
+fn last_used_profile_round_trips_and_is_forgotten_when_deleted() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let base = dir.path();
+    assert_eq!(load_last_used(base), None);
+
+    create_profile(base, "Work").expect("create");
+    save_last_used(base, Some("Work")).expect("save");
+    assert_eq!(load_last_used(base).as_deref(), Some("Work"));
+
+    std::fs::remove_dir_all(profile_dir(base, Some("Work"))).expect("remove");
+    assert_eq!(load_last_used(base), None);
+
+    save_last_used(base, None).expect("clear");
+    save_last_used(base, None).expect("clear again");
+}
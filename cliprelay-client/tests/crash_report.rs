@@ -0,0 +1,67 @@
+use std::fs;
+
+use cliprelay_client::crash_report::{
+    MAX_CRASH_REPORTS, PENDING_MARKER, format_report, last_log_lines, report_stem,
+    take_pending_report, write_report,
+};
+
+#[test]
+fn report_is_pending_exactly_once() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let text = format_report("panic: boom", "", 1_700_000_000_000, None, &[]);
+    let path = write_report(dir.path(), &report_stem(1_700_000_000_000), &text).expect("write");
+
+    assert!(dir.path().join(PENDING_MARKER).exists());
+    assert_eq!(take_pending_report(dir.path()), Some(path.clone()));
+    assert_eq!(take_pending_report(dir.path()), None);
+    assert!(path.exists());
+}
+
+#[test]
+fn old_reports_and_dumps_are_pruned() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let first = 1_700_000_000_000_u64;
+    for i in 0..MAX_CRASH_REPORTS as u64 + 2 {
+        let stem = report_stem(first + i);
+        fs::write(dir.path().join(format!("{stem}.dmp")), b"dump").expect("dump");
+        write_report(dir.path(), &stem, "report").expect("write");
+    }
+
+    let oldest = report_stem(first);
+    assert!(!dir.path().join(format!("{oldest}.txt")).exists());
+    assert!(!dir.path().join(format!("{oldest}.dmp")).exists());
+    let newest = report_stem(first + MAX_CRASH_REPORTS as u64 + 1);
+    assert!(dir.path().join(format!("{newest}.dmp")).exists());
+    let reports = fs::read_dir(dir.path())
+        .expect("read dir")
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "txt"))
+        .filter(|e| e.file_name() != PENDING_MARKER)
+        .count();
+    assert_eq!(reports, MAX_CRASH_REPORTS);
+}
+
+#[test]
+fn report_includes_log_tail_without_colour_codes() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let log = dir.path().join("client.log");
+    let mut contents = String::new();
+    for i in 0..10 {
+        contents.push_str(&format!("\u{1b}[32m INFO\u{1b}[0m line {i}\r\n"));
+    }
+    fs::write(&log, contents).expect("write log");
+
+    let lines = last_log_lines(&log, 3).expect("tail");
+    assert_eq!(lines, [" INFO line 7", " INFO line 8", " INFO line 9"]);
+
+    let text = format_report(
+        "unhandled exception 0xC0000005",
+        "",
+        5,
+        Some("crash-5.dmp"),
+        &lines,
+    );
+    assert!(text.contains("Reason: unhandled exception 0xC0000005"));
+    assert!(text.contains("Minidump: crash-5.dmp"));
+    assert!(text.ends_with("Last 3 log lines:\n INFO line 7\n INFO line 8\n INFO line 9\n"));
+}
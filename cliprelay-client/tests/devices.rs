@@ -0,0 +1,143 @@
+use cliprelay_client::devices::{
+    DeviceRegistry, FilePolicy, KeyCheck, MAX_NICKNAME_CHARS, TrustState, last_seen_ago,
+    load_devices_from_path, normalize_nickname, save_devices_to_path,
+};
+
+#[test]
+fn observe_tracks_first_and_last_seen() {
+    let mut registry = DeviceRegistry::default();
+    registry.observe("dev-a", "Laptop", 1_000);
+    registry.observe("dev-a", "Laptop (renamed)", 5_000);
+    registry.observe("dev-a", "Laptop (renamed)", 3_000);
+
+    let device = registry.get("dev-a").expect("device recorded");
+    assert_eq!(device.first_seen_unix_ms, 1_000);
+    assert_eq!(device.last_seen_unix_ms, 5_000);
+    assert_eq!(device.advertised_name, "Laptop (renamed)");
+    assert_eq!(device.trust, TrustState::Unknown);
+}
+
+#[test]
+fn nickname_overrides_advertised_name() {
+    let mut registry = DeviceRegistry::default();
+    registry.observe("dev-a", "DESKTOP-1234", 1);
+    registry.get_mut("dev-a").unwrap().nickname = normalize_nickname("  Work PC  ");
+    assert_eq!(registry.display_name("dev-a"), Some("Work PC"));
+    assert_eq!(registry.display_name("dev-b"), None);
+
+    assert_eq!(normalize_nickname("   "), None);
+    let long = "x".repeat(MAX_NICKNAME_CHARS + 10);
+    assert_eq!(
+        normalize_nickname(&long).map(|n| n.chars().count()),
+        Some(MAX_NICKNAME_CHARS)
+    );
+}
+
+#[test]
+fn blocked_and_forgotten_devices() {
+    let mut registry = DeviceRegistry::default();
+    registry.observe("dev-a", "Phone", 1);
+    assert!(!registry.is_blocked("dev-a"));
+    assert!(registry.allows_auto_apply("unseen"));
+
+    registry.get_mut("dev-a").unwrap().trust = TrustState::Blocked;
+    assert!(registry.is_blocked("dev-a"));
+
+    assert!(registry.forget("dev-a"));
+    assert!(!registry.is_blocked("dev-a"));
+    assert!(registry.is_empty());
+}
+
+#[test]
+fn registry_round_trips_through_disk() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("devices.json");
+
+    let mut registry = DeviceRegistry::default();
+    registry.observe("dev-a", "Laptop", 10);
+    {
+        let device = registry.get_mut("dev-a").unwrap();
+        device.trust = TrustState::Trusted;
+        device.notify = false;
+    }
+    save_devices_to_path(&path, &registry).expect("save");
+
+    let loaded = load_devices_from_path(&path).expect("load");
+    assert_eq!(loaded, registry);
+}
+
+#[test]
+fn missing_toggles_default_to_enabled() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("devices.json");
+    std::fs::write(
+        &path,
+        r#"{"devices":{"dev-a":{"device_id":"dev-a","advertised_name":"Old",
+            "first_seen_unix_ms":1,"last_seen_unix_ms":2}}}"#,
+    )
+    .unwrap();
+
+    let loaded = load_devices_from_path(&path).expect("load");
+    let device = loaded.get("dev-a").unwrap();
+    assert!(device.allow_auto_apply);
+    assert!(device.notify);
+    assert_eq!(device.nickname, None);
+    assert_eq!(device.file_policy, FilePolicy::Prompt);
+}
+
+#[test]
+fn file_policy_defaults_to_prompt_for_unknown_devices() {
+    let mut registry = DeviceRegistry::default();
+    assert_eq!(registry.file_policy("unseen"), FilePolicy::Prompt);
+
+    registry.observe("dev-a", "Desktop", 1);
+    registry.get_mut("dev-a").unwrap().file_policy = FilePolicy::AutoSave;
+    assert_eq!(registry.file_policy("dev-a"), FilePolicy::AutoSave);
+}
+
+#[test]
+fn last_seen_ago_buckets() {
+    let now = 10 * 86_400_000;
+    assert_eq!(last_seen_ago(now - 5_000, now), "just now");
+    assert_eq!(last_seen_ago(now - 3 * 60_000, now), "3 min ago");
+    assert_eq!(last_seen_ago(now - 2 * 3_600_000, now), "2 h ago");
+    assert_eq!(last_seen_ago(now - 3 * 86_400_000, now), "3 d ago");
+    assert_eq!(last_seen_ago(now + 1_000, now), "just now");
+}
+
+#[test]
+fn unfamiliar_devices_are_reported_once_registry_has_members() {
+    let mut registry = DeviceRegistry::default();
+    assert!(registry.unfamiliar(["dev-a"]).is_empty());
+
+    registry.observe("dev-a", "Laptop", 1_000);
+    assert_eq!(registry.unfamiliar(["dev-a", "dev-b"]), vec!["dev-b"]);
+
+    registry.forget("dev-a");
+    registry.observe("dev-b", "Desktop", 2_000);
+    assert_eq!(registry.unfamiliar(["dev-a", "dev-b"]), vec!["dev-a"]);
+}
+
+#[test]
+fn identity_keys_are_pinned_on_first_use() {
+    let mut registry = DeviceRegistry::default();
+    assert_eq!(
+        registry.check_key("dev-a", Some("k1")),
+        KeyCheck::Unverified
+    );
+
+    registry.observe("dev-a", "Laptop", 1_000);
+    assert_eq!(registry.check_key("dev-a", None), KeyCheck::Unverified);
+    assert_eq!(registry.check_key("dev-a", Some("k1")), KeyCheck::Pinned);
+    assert_eq!(registry.check_key("dev-a", Some("k1")), KeyCheck::Verified);
+
+    assert_eq!(registry.check_key("dev-a", Some("k2")), KeyCheck::Changed);
+    assert!(!KeyCheck::Changed.is_verified());
+    let device = registry.get("dev-a").expect("device recorded");
+    assert_eq!(device.pinned_key.as_deref(), Some("k1"));
+    assert_eq!(device.changed_key.as_deref(), Some("k2"));
+
+    assert!(registry.accept_changed_key("dev-a"));
+    assert!(!registry.accept_changed_key("dev-a"));
+    assert_eq!(registry.check_key("dev-a", Some("k2")), KeyCheck::Verified);
+}
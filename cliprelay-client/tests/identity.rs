@@ -0,0 +1,39 @@
+mod common;
+
+use std::io;
+
+use cliprelay_client::identity::{decode_identity, encode_identity, load_or_create_identity_at};
+use cliprelay_core::DeviceIdentity;
+
+use common::xor;
+
+#[test]
+fn identity_is_created_once_and_reloaded() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("identity.json");
+
+    let created = load_or_create_identity_at(&path).expect("create");
+    assert!(path.exists());
+    let reloaded = load_or_create_identity_at(&path).expect("reload");
+    assert_eq!(reloaded.public_key_hex(), created.public_key_hex());
+}
+
+#[test]
+fn sealed_identity_round_trips() {
+    let identity = DeviceIdentity::generate().expect("generate");
+    let json = encode_identity(&identity, true, xor).expect("encode");
+    assert!(json.contains("\"protected\""));
+
+    let decoded = decode_identity(&json, xor).expect("decode");
+    assert_eq!(decoded.public_key_hex(), identity.public_key_hex());
+}
+
+#[test]
+fn corrupt_identity_file_is_an_error() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("identity.json");
+    std::fs::write(&path, r#"{"format":"plain","value":"AAAA"}"#).expect("write");
+
+    let err = load_or_create_identity_at(&path).expect_err("rejected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
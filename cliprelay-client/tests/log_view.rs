@@ -0,0 +1,72 @@
+use std::{fs, io::Write};
+
+use cliprelay_client::log_view::{INITIAL_TAIL_BYTES, LogLevel, LogTail};
+
+fn append(path: &std::path::Path, text: &str) {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .expect("open log");
+    file.write_all(text.as_bytes()).expect("append");
+}
+
+#[test]
+fn tail_follows_appends_and_holds_partial_lines() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("client.log");
+    append(
+        &path,
+        "2024-05-01T10:00:00.000000Z ERROR app: boom\nbacktrace line\n",
+    );
+
+    let mut tail = LogTail::default();
+    assert!(tail.refresh(&path).expect("refresh"));
+    assert_eq!(tail.lines().len(), 2);
+    // The continuation line belongs to the error above it.
+    assert_eq!(tail.lines()[1].level, Some(LogLevel::Error));
+
+    append(&path, "2024-05-01T10:00:01.000000Z  INFO app: hal");
+    assert!(!tail.refresh(&path).expect("refresh"));
+    append(&path, "f done\n");
+    assert!(tail.refresh(&path).expect("refresh"));
+    assert_eq!(
+        tail.lines()[2].text,
+        "2024-05-01T10:00:01.000000Z  INFO app: half done"
+    );
+    assert!(!tail.refresh(&path).expect("refresh"));
+}
+
+#[test]
+fn tail_restarts_when_the_file_shrinks() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("client.log");
+    append(&path, "2024-05-01T10:00:00.000000Z  INFO app: first run\n");
+    let mut tail = LogTail::default();
+    tail.refresh(&path).expect("refresh");
+
+    fs::write(&path, "2024-05-01T11:00:00.000000Z  WARN app: new\n").expect("truncate");
+    tail.refresh(&path).expect("refresh");
+    assert_eq!(tail.lines().len(), 1);
+    assert_eq!(tail.lines()[0].level, Some(LogLevel::Warn));
+}
+
+#[test]
+fn large_files_are_read_from_the_end() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("client.log");
+    let line = "2024-05-01T10:00:00.000000Z DEBUG app: padding padding padding\n";
+    let count = (INITIAL_TAIL_BYTES as usize / line.len()) * 2;
+    append(&path, &line.repeat(count));
+    append(&path, "2024-05-01T10:00:01.000000Z  INFO app: latest\n");
+
+    let mut tail = LogTail::default();
+    tail.refresh(&path).expect("refresh");
+    let lines = tail.lines();
+    assert!(lines.len() < count);
+    assert!(lines.iter().all(|l| l.level.is_some()));
+    assert_eq!(
+        lines.last().map(|l| l.text.as_str()),
+        Some("2024-05-01T10:00:01.000000Z  INFO app: latest")
+    );
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::io;
+
+use cliprelay_client::room_profiles::{
+    MAX_PROFILE_NAME_CHARS, MAX_PROFILES, RoomProfile, RoomProfiles, decode_profiles,
+    encode_profiles, load_profiles_from_path, normalize_profile_name, save_profiles_to_path,
+};
+
+use common::xor;
+
+fn profile(name: &str, room_code: &str) -> RoomProfile {
+    RoomProfile {
+        name: name.to_owned(),
+        server_url: "wss://relay.example.com/ws".to_owned(),
+        room_code: room_code.to_owned(),
+        device_name: "laptop".to_owned(),
+    }
+}
+
+#[test]
+fn plain_profiles_round_trip_through_disk() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("profiles.json");
+
+    let mut profiles = RoomProfiles::default();
+    assert!(profiles.upsert(profile("Home", "home-room")));
+    assert!(profiles.upsert(profile("Work", "work-room")));
+    profiles.last_used = Some("Work".to_owned());
+    save_profiles_to_path(&path, &profiles).expect("save");
+
+    // Sealing is unavailable off Windows, so the code is stored as-is there.
+    let loaded = load_profiles_from_path(&path).expect("load");
+    assert_eq!(loaded, profiles);
+    assert_eq!(loaded.names(), vec!["Home", "Work"]);
+}
+
+#[test]
+fn sealed_profiles_hide_room_codes_and_round_trip() {
+    let mut profiles = RoomProfiles::default();
+    profiles.upsert(profile("Home", "correct-horse-battery"));
+
+    let json = encode_profiles(&profiles, true, xor).expect("encode");
+    assert!(!json.contains("correct-horse-battery"));
+    assert!(json.contains("\"protected\""));
+    assert_eq!(decode_profiles(&json, xor).expect("decode"), profiles);
+
+    let err = decode_profiles(&json, |_| Err(io::Error::other("no key"))).unwrap_err();
+    assert_eq!(err.to_string(), "no key");
+}
+
+#[test]
+fn upsert_replaces_by_name_and_is_bounded() {
+    let mut profiles = RoomProfiles::default();
+    profiles.upsert(profile("Home", "old"));
+    profiles.upsert(profile("Home", "new"));
+    assert_eq!(profiles.profiles.len(), 1);
+    assert_eq!(profiles.profiles[0].room_code, "new");
+
+    for i in 1..MAX_PROFILES {
+        assert!(profiles.upsert(profile(&format!("p{i}"), "x")));
+    }
+    assert!(!profiles.upsert(profile("one too many", "x")));
+    assert!(profiles.upsert(profile("Home", "replacing is fine")));
+
+    let long = "n".repeat(MAX_PROFILE_NAME_CHARS + 5);
+    assert_eq!(
+        normalize_profile_name(&long).map(|n| n.chars().count()),
+        Some(MAX_PROFILE_NAME_CHARS)
+    );
+    assert_eq!(normalize_profile_name("   "), None);
+}
+
+#[test]
+fn active_profile_prefers_last_used_match() {
+    let mut profiles = RoomProfiles::default();
+    profiles.upsert(profile("Home", "shared"));
+    profiles.upsert(profile("Home (copy)", "shared"));
+    profiles.upsert(profile("Work", "work"));
+    let server = "wss://relay.example.com/ws";
+
+    assert_eq!(profiles.active_index(server, "shared", "laptop"), Some(0));
+    profiles.last_used = Some("Home (copy)".to_owned());
+    assert_eq!(profiles.active_index(server, "shared", "laptop"), Some(1));
+    // A last-used profile that no longer matches the room is ignored.
+    assert_eq!(profiles.active_index(server, "work", "laptop"), Some(2));
+    assert_eq!(profiles.active_index(server, "other", "laptop"), None);
+
+    profiles.remove(1);
+    assert_eq!(profiles.last_used, None);
+}
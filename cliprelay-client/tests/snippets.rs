@@ -0,0 +1,68 @@
+mod common;
+
+use std::io;
+
+use cliprelay_client::snippets::{
+    MAX_SNIPPET_NAME_CHARS, MAX_SNIPPETS, SnippetLibrary, decode_snippets, encode_snippets,
+    load_snippets_from_path, normalize_snippet_name, save_snippets_to_path,
+};
+
+use common::xor;
+
+#[test]
+fn plain_library_round_trips_through_disk() {
+    let dir = tempfile::tempdir().expect("create tempdir");
+    let path = dir.path().join("snippets.json");
+
+    let mut library = SnippetLibrary::default();
+    assert!(library.add("SSH host", "ssh admin@10.0.0.5"));
+    assert!(library.add("  ", "unnamed"));
+    save_snippets_to_path(&path, &library).expect("save");
+
+    let loaded = load_snippets_from_path(&path).expect("load");
+    assert_eq!(loaded, library);
+    assert_eq!(loaded.names(), vec!["SSH host", "Snippet 2"]);
+}
+
+#[test]
+fn protected_library_hides_text_and_round_trips() {
+    let mut library = SnippetLibrary {
+        encrypt_at_rest: true,
+        ..SnippetLibrary::default()
+    };
+    library.add("Wi-Fi", "hunter2-secret");
+
+    let json = encode_snippets(&library, xor).expect("encode");
+    assert!(!json.contains("hunter2-secret"));
+    assert!(json.contains("\"protected\""));
+
+    let decoded = decode_snippets(&json, xor).expect("decode");
+    assert_eq!(decoded, library);
+}
+
+#[test]
+fn failed_unprotect_is_an_error() {
+    let mut library = SnippetLibrary {
+        encrypt_at_rest: true,
+        ..SnippetLibrary::default()
+    };
+    library.add("a", "b");
+    let json = encode_snippets(&library, xor).expect("encode");
+    let err = decode_snippets(&json, |_| Err(io::Error::other("no key"))).unwrap_err();
+    assert_eq!(err.to_string(), "no key");
+}
+
+#[test]
+fn library_and_names_are_bounded() {
+    let mut library = SnippetLibrary::default();
+    for i in 0..MAX_SNIPPETS {
+        assert!(library.add(&format!("s{i}"), "x"));
+    }
+    assert!(!library.add("one too many", "x"));
+
+    let long = "n".repeat(MAX_SNIPPET_NAME_CHARS + 5);
+    assert_eq!(
+        normalize_snippet_name(&long).map(|n| n.chars().count()),
+        Some(MAX_SNIPPET_NAME_CHARS)
+    );
+}
@@ -1,7 +1,9 @@
 use std::io::Write;
 
+use cliprelay_client::ui_layout::ScreenRect;
 use cliprelay_client::ui_state::{
-    MAX_UI_STATE_BYTES, WindowPlacement, clamp_placement_in_rect, load_ui_state_from_path,
+    MAX_SAVED_TOPOLOGIES, MAX_UI_STATE_BYTES, SavedUiState, WindowPlacement,
+    clamp_placement_in_rect, load_ui_state_from_path, parse_ui_state_json, topology_fingerprint,
 };
 
 #[test]
@@ -38,3 +40,56 @@ fn load_ui_state_ignores_oversized_file() {
     let msg = err.to_string();
     assert!(msg.contains("too large"), "unexpected error: {msg}");
 }
+
+#[test]
+fn placements_are_kept_per_monitor_topology() {
+    let laptop = [ScreenRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    }];
+    let docked = [
+        ScreenRect {
+            left: 1920,
+            top: 0,
+            right: 4480,
+            bottom: 1440,
+        },
+        laptop[0],
+    ];
+    let mut reversed = docked;
+    reversed.reverse();
+    assert_eq!(
+        topology_fingerprint(&docked),
+        topology_fingerprint(&reversed)
+    );
+    assert_ne!(topology_fingerprint(&docked), topology_fingerprint(&laptop));
+
+    let mut state = SavedUiState::default();
+    let on_external = WindowPlacement {
+        x: 3000,
+        y: 200,
+        w: 560,
+        h: 420,
+    };
+    assert!(state.remember_main_placement(&topology_fingerprint(&docked), on_external));
+    assert!(!state.remember_main_placement(&topology_fingerprint(&docked), on_external));
+    assert_eq!(
+        state.main_placement(&topology_fingerprint(&docked)),
+        Some(on_external)
+    );
+    assert_eq!(state.main_placement(&topology_fingerprint(&laptop)), None);
+
+    for i in 0..MAX_SAVED_TOPOLOGIES {
+        state.remember_main_placement(&format!("other {i}"), on_external);
+    }
+    assert_eq!(state.placements.len(), MAX_SAVED_TOPOLOGIES);
+    assert_eq!(state.main_placement(&topology_fingerprint(&docked)), None);
+}
+
+#[test]
+fn ui_state_without_placements_still_parses() {
+    let state = parse_ui_state_json(r#"{"hotkey":"Ctrl+Alt+C"}"#).expect("parse");
+    assert!(state.placements.is_empty());
+}
@@ -0,0 +1,247 @@
+//! Files split into events: each chunk of a file travels as the JSON text
+//! of one [`MIME_FILE_CHUNK_JSON_B64`](crate::MIME_FILE_CHUNK_JSON_B64)
+//! event, encrypted like any other.
+//!
+//! Sealed chunks ([`MIME_FILE_CHUNK_SEALED_JSON_B64`](crate::MIME_FILE_CHUNK_SEALED_JSON_B64))
+//! additionally encrypt their bytes under the transfer's own key
+//! ([`derive_transfer_key`](crate::derive_transfer_key)), with the
+//! transfer id, file name, size and chunk position as associated data: a
+//! chunk only opens as the chunk it was sealed as.
+//!
+//! The chunk nonce is the chunk index alone, so a transfer id must seal
+//! exactly one plaintext: senders pick a fresh random id for every file
+//! and never reuse one, not even to resend the same file.
+
+use base64::Engine;
+use chacha20poly1305::{
+    KeyInit, XChaCha20Poly1305,
+    aead::{Aead, Payload, generic_array::GenericArray},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{CoreError, MAX_CLIPBOARD_TEXT_BYTES, canonical::CanonicalEncoder};
+
+const CHUNK_AAD_CONTEXT: &[u8] = b"cliprelay v1 file chunk";
+
+/// File bytes carried by one chunk (before base64).
+pub const FILE_CHUNK_RAW_BYTES: usize = 64 * 1024;
+
+/// Most chunks a transfer may have; receivers drop larger transfers.
+pub const MAX_TOTAL_CHUNKS: u32 = 4096;
+
+/// One chunk of a file, as the text of a file-chunk event.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileChunkEnvelope {
+    pub transfer_id: String,
+    pub file_name: String,
+    pub total_size: u64,
+    pub chunk_index: u32,
+    pub total_chunks: u32,
+    pub chunk_b64: String,
+}
+
+impl FileChunkEnvelope {
+    /// Chunk `chunk_index` of `data`.
+    pub fn for_chunk(transfer_id: &str, file_name: &str, data: &[u8], chunk_index: u32) -> Self {
+        let start = (chunk_index as usize)
+            .saturating_mul(FILE_CHUNK_RAW_BYTES)
+            .min(data.len());
+        let end = start.saturating_add(FILE_CHUNK_RAW_BYTES).min(data.len());
+        Self {
+            transfer_id: transfer_id.to_owned(),
+            file_name: file_name.to_owned(),
+            total_size: data.len() as u64,
+            chunk_index,
+            total_chunks: total_chunks(data.len()),
+            chunk_b64: base64::engine::general_purpose::STANDARD.encode(&data[start..end]),
+        }
+    }
+
+    /// Read an envelope from the text of a file-chunk event.
+    pub fn parse(text_utf8: &str) -> Result<Self, CoreError> {
+        serde_json::from_str(text_utf8).map_err(|err| CoreError::Serialization(err.to_string()))
+    }
+
+    /// The event text for this envelope.
+    pub fn to_text(&self) -> Result<String, CoreError> {
+        let text =
+            serde_json::to_string(self).map_err(|err| CoreError::Serialization(err.to_string()))?;
+        if text.len() > MAX_CLIPBOARD_TEXT_BYTES {
+            return Err(CoreError::ClipboardTooLarge);
+        }
+        Ok(text)
+    }
+
+    /// The file bytes this chunk carries.  For a sealed chunk these are
+    /// still encrypted; use [`Self::open`].
+    pub fn data(&self) -> Result<Vec<u8>, CoreError> {
+        base64::engine::general_purpose::STANDARD
+            .decode(self.chunk_b64.as_bytes())
+            .map_err(|err| CoreError::Serialization(err.to_string()))
+    }
+
+    /// Chunk `chunk_index` of `data`, sealed under `transfer_key`.
+    pub fn sealed_chunk(
+        transfer_key: &[u8; 32],
+        transfer_id: &str,
+        file_name: &str,
+        data: &[u8],
+        chunk_index: u32,
+    ) -> Result<Self, CoreError> {
+        let mut envelope = Self::for_chunk(transfer_id, file_name, data, chunk_index);
+        let plain = envelope.data()?;
+        let sealed = XChaCha20Poly1305::new(GenericArray::from_slice(transfer_key))
+            .encrypt(
+                GenericArray::from_slice(&chunk_nonce(chunk_index)),
+                Payload {
+                    msg: &plain,
+                    aad: &envelope.associated_data(),
+                },
+            )
+            .map_err(|_| CoreError::EncryptionFailed)?;
+        envelope.chunk_b64 = base64::engine::general_purpose::STANDARD.encode(sealed);
+        Ok(envelope)
+    }
+
+    /// The file bytes of a sealed chunk.  Fails if the chunk was sealed for
+    /// another transfer or position, or altered.
+    pub fn open(&self, transfer_key: &[u8; 32]) -> Result<Vec<u8>, CoreError> {
+        XChaCha20Poly1305::new(GenericArray::from_slice(transfer_key))
+            .decrypt(
+                GenericArray::from_slice(&chunk_nonce(self.chunk_index)),
+                Payload {
+                    msg: &self.data()?,
+                    aad: &self.associated_data(),
+                },
+            )
+            .map_err(|_| CoreError::DecryptionFailed)
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        CanonicalEncoder::new(CHUNK_AAD_CONTEXT)
+            .str(&self.transfer_id)
+            .str(&self.file_name)
+            .u64(self.total_size)
+            .u64(u64::from(self.chunk_index))
+            .u64(u64::from(self.total_chunks))
+            .finish()
+    }
+}
+
+/// Each transfer key seals one chunk per index, so the index alone is a
+/// unique nonce, as long as the transfer id is never used for a second
+/// plaintext.
+fn chunk_nonce(chunk_index: u32) -> [u8; 24] {
+    let mut nonce = [0_u8; 24];
+    nonce[..4].copy_from_slice(&chunk_index.to_le_bytes());
+    nonce
+}
+
+/// Number of chunks a file of `len` bytes is sent in.
+pub fn total_chunks(len: usize) -> u32 {
+    u32::try_from(len.div_ceil(FILE_CHUNK_RAW_BYTES)).unwrap_or(u32::MAX)
+}
+
+/// Every chunk of `data`, in order.
+pub fn split_file(
+    transfer_id: &str,
+    file_name: &str,
+    data: &[u8],
+) -> Result<Vec<FileChunkEnvelope>, CoreError> {
+    let total = checked_total_chunks(data.len())?;
+    Ok((0..total)
+        .map(|index| FileChunkEnvelope::for_chunk(transfer_id, file_name, data, index))
+        .collect())
+}
+
+/// Every chunk of `data`, in order, sealed under the key of `transfer_id`
+/// in the room of `room_key`.
+pub fn split_file_sealed(
+    room_key: &[u8; 32],
+    transfer_id: &str,
+    file_name: &str,
+    data: &[u8],
+) -> Result<Vec<FileChunkEnvelope>, CoreError> {
+    let total = checked_total_chunks(data.len())?;
+    let transfer_key = crate::derive_transfer_key(room_key, transfer_id)?;
+    (0..total)
+        .map(|index| {
+            FileChunkEnvelope::sealed_chunk(&transfer_key, transfer_id, file_name, data, index)
+        })
+        .collect()
+}
+
+fn checked_total_chunks(len: usize) -> Result<u32, CoreError> {
+    let total = total_chunks(len);
+    if total == 0 {
+        return Err(CoreError::EmptyFile);
+    }
+    if total > MAX_TOTAL_CHUNKS {
+        return Err(CoreError::TooManyChunks(total));
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_reassemble_into_the_file() {
+        let data: Vec<u8> = (0..FILE_CHUNK_RAW_BYTES * 2 + 10)
+            .map(|i| i as u8)
+            .collect();
+        let chunks = split_file("t1", "a.bin", &data).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| c.total_chunks == 3));
+
+        let mut joined = Vec::new();
+        for chunk in &chunks {
+            let parsed = FileChunkEnvelope::parse(&chunk.to_text().unwrap()).unwrap();
+            joined.extend(parsed.data().unwrap());
+        }
+        assert_eq!(joined, data);
+    }
+
+    #[test]
+    fn sealed_chunks_open_only_as_themselves() {
+        let room_key = [7_u8; 32];
+        let data: Vec<u8> = (0..FILE_CHUNK_RAW_BYTES + 10).map(|i| i as u8).collect();
+        let chunks = split_file_sealed(&room_key, "t1", "a.bin", &data).unwrap();
+        let key = crate::derive_transfer_key(&room_key, "t1").unwrap();
+
+        let mut joined = Vec::new();
+        for chunk in &chunks {
+            let parsed = FileChunkEnvelope::parse(&chunk.to_text().unwrap()).unwrap();
+            let plain = parsed.open(&key).unwrap();
+            // The envelope carries the ciphertext and its tag, never the bytes.
+            assert_eq!(parsed.data().unwrap().len(), plain.len() + 16);
+            assert_ne!(parsed.data().unwrap()[..plain.len()], plain[..]);
+            joined.extend(plain);
+        }
+        assert_eq!(joined, data);
+
+        // Moved to another transfer, position or key, a chunk does not open.
+        let mut moved = chunks[0].clone();
+        moved.transfer_id = "t2".to_owned();
+        assert!(moved.open(&key).is_err());
+        let mut reordered = chunks[1].clone();
+        reordered.chunk_index = 0;
+        assert!(reordered.open(&key).is_err());
+        let other = crate::derive_transfer_key(&room_key, "t2").unwrap();
+        assert!(chunks[0].open(&other).is_err());
+    }
+
+    #[test]
+    fn empty_and_oversized_files_are_refused() {
+        assert!(matches!(
+            split_file("t", "empty", &[]),
+            Err(CoreError::EmptyFile)
+        ));
+        let huge = vec![0; FILE_CHUNK_RAW_BYTES * MAX_TOTAL_CHUNKS as usize + 1];
+        assert!(matches!(
+            split_file("t", "huge", &huge),
+            Err(CoreError::TooManyChunks(_))
+        ));
+    }
+}
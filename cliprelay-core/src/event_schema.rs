@@ -0,0 +1,192 @@
+//! Versioned encoding of the decrypted clipboard event.
+//!
+//! Version 1 is the bare JSON of [`ClipboardEventPlaintext`].  Version 2
+//! is the same object with two more keys, so that a v1 reader still finds
+//! every field it knows:
+//!
+//! - `"v"`: the schema version, `2`.  Absent means version 1.
+//! - `"ext"`: an object of named extensions, omitted when empty.
+//!
+//! Rules for readers and writers:
+//!
+//! 1. Unknown top-level keys are ignored.  No event type denies unknown
+//!    fields, and a new field is only ever optional with a default.
+//! 2. Extensions a reader does not know are ignored, as are known
+//!    extensions whose value does not have the expected shape
+//!    ([`EventEnvelope::extension`] returns `None`).  Decoding keeps them,
+//!    so nothing is lost by a reader that only passes an event on.
+//! 3. The meaning or type of an existing field never changes.  A change
+//!    that old readers must not misread bumps `"v"`, and readers refuse
+//!    versions newer than [`EVENT_SCHEMA_VERSION`].
+//! 4. Extensions are not covered by the event signature, which stays over
+//!    the v1 fields; an extension that needs to be attributable to the
+//!    sender carries its own proof.
+//!
+//! During the transition both versions are read.  Writers keep sending
+//! version 1 ([`crate::encrypt_clipboard_event`]) and send version 2 only
+//! for events that carry extensions, which v1 peers read without them.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{ClipboardEventPlaintext, CoreError};
+
+/// Newest schema version this build reads and writes.
+pub const EVENT_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventEnvelope {
+    #[serde(rename = "v", default = "schema_v1", skip_serializing_if = "is_v1")]
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: ClipboardEventPlaintext,
+    #[serde(rename = "ext", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, serde_json::Value>,
+}
+
+fn schema_v1() -> u32 {
+    1
+}
+
+fn is_v1(version: &u32) -> bool {
+    *version == 1
+}
+
+impl EventEnvelope {
+    /// `event` in the version 1 encoding, which cannot carry extensions.
+    pub fn v1(event: ClipboardEventPlaintext) -> Self {
+        Self {
+            version: 1,
+            event,
+            extensions: BTreeMap::new(),
+        }
+    }
+
+    /// `event` in the current encoding, without extensions yet.
+    pub fn v2(event: ClipboardEventPlaintext) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            ..Self::v1(event)
+        }
+    }
+
+    /// Add or replace extension `name`.
+    pub fn with_extension(mut self, name: &str, value: impl Serialize) -> Result<Self, CoreError> {
+        let value =
+            serde_json::to_value(value).map_err(|err| CoreError::Serialization(err.to_string()))?;
+        self.extensions.insert(name.to_owned(), value);
+        Ok(self)
+    }
+
+    /// Extension `name`, if present and of the expected shape.
+    pub fn extension<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.extensions
+            .get(name)
+            .and_then(|value| T::deserialize(value).ok())
+    }
+
+    /// JSON of the event.  Version 1 is written byte for byte as before
+    /// and drops any extensions.
+    pub fn encode(&self) -> Result<Vec<u8>, CoreError> {
+        let encoded = if self.version == 1 {
+            serde_json::to_vec(&self.event)
+        } else {
+            serde_json::to_vec(self)
+        };
+        encoded.map_err(|err| CoreError::Serialization(err.to_string()))
+    }
+
+    /// Read either version.
+    pub fn decode(bytes: &[u8]) -> Result<Self, CoreError> {
+        let envelope: Self = serde_json::from_slice(bytes)
+            .map_err(|err| CoreError::Serialization(err.to_string()))?;
+        if envelope.version == 0 || envelope.version > EVENT_SCHEMA_VERSION {
+            return Err(CoreError::UnsupportedEventVersion(envelope.version));
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> ClipboardEventPlaintext {
+        ClipboardEventPlaintext {
+            sender_device_id: "dev".to_owned(),
+            counter: 3,
+            timestamp_unix_ms: 9,
+            mime: "text/plain".to_owned(),
+            text_utf8: "hi".to_owned(),
+            pinned: false,
+            label: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn v1_is_written_unchanged_and_both_versions_are_read() {
+        let v1 = EventEnvelope::v1(event()).encode().unwrap();
+        assert_eq!(v1, serde_json::to_vec(&event()).unwrap());
+        assert_eq!(
+            EventEnvelope::decode(&v1).unwrap(),
+            EventEnvelope::v1(event())
+        );
+
+        let v2 = EventEnvelope::v2(event())
+            .with_extension("x-color", "red")
+            .unwrap();
+        let decoded = EventEnvelope::decode(&v2.encode().unwrap()).unwrap();
+        assert_eq!(decoded, v2);
+        assert_eq!(
+            decoded.extension::<String>("x-color").as_deref(),
+            Some("red")
+        );
+    }
+
+    #[test]
+    fn v1_readers_accept_v2_events() {
+        let v2 = EventEnvelope::v2(event())
+            .with_extension("x-color", "red")
+            .unwrap()
+            .encode()
+            .unwrap();
+        let old: ClipboardEventPlaintext = serde_json::from_slice(&v2).unwrap();
+        assert_eq!(old, event());
+    }
+
+    #[test]
+    fn encrypted_v2_events_decrypt_with_and_without_extensions() {
+        let key = [5_u8; 32];
+        let v2 = EventEnvelope::v2(event()).with_extension("x-n", 1).unwrap();
+        let payload = crate::encrypt_event_envelope(&key, &v2).unwrap();
+        assert_eq!(crate::decrypt_event_envelope(&key, &payload).unwrap(), v2);
+        assert_eq!(
+            crate::decrypt_clipboard_event(&key, &payload).unwrap(),
+            event()
+        );
+    }
+
+    #[test]
+    fn unknown_fields_and_extensions_are_ignored() {
+        let json = r#"{"v":2,"sender_device_id":"dev","counter":3,"timestamp_unix_ms":9,
+            "mime":"text/plain","text_utf8":"hi","later_field":{"a":1},
+            "ext":{"x-future":[1,2],"x-count":"not a number"}}"#;
+        let decoded = EventEnvelope::decode(json.as_bytes()).unwrap();
+        assert_eq!(decoded.event, event());
+        assert_eq!(decoded.extensions.len(), 2);
+        assert_eq!(decoded.extension::<u32>("x-count"), None);
+        assert_eq!(decoded.extension::<u32>("x-missing"), None);
+    }
+
+    #[test]
+    fn newer_versions_are_refused() {
+        let mut json = serde_json::to_value(EventEnvelope::v2(event())).unwrap();
+        json["v"] = (EVENT_SCHEMA_VERSION + 1).into();
+        assert!(matches!(
+            EventEnvelope::decode(json.to_string().as_bytes()),
+            Err(CoreError::UnsupportedEventVersion(v)) if v == EVENT_SCHEMA_VERSION + 1
+        ));
+    }
+}
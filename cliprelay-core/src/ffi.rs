@@ -0,0 +1,384 @@
+//! Kotlin/Swift bindings (feature `uniffi`), for companion apps that must
+//! speak the same crypto and wire format as the desktop clients.
+//!
+//! Generate the sources with the bundled tool, e.g.
+//! `cargo run -p cliprelay-core --features uniffi-cli --bin uniffi-bindgen --
+//! generate --library target/debug/libcliprelay_core.so --language kotlin --out-dir out`.
+//! Keys and payloads cross the boundary as byte arrays; a room key that is
+//! not 32 bytes long is refused.
+
+use crate::{
+    ClipboardEventPlaintext, ControlMessage, CoreError, EncryptedPayload, Hello, PeerInfo,
+    WireMessage, chunker,
+};
+
+/// Every failure, flattened to its message.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum FfiError {
+    #[error("{0}")]
+    Core(#[from] CoreError),
+    #[error("room key must be 32 bytes")]
+    InvalidKeyLength,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Peer {
+    pub device_id: String,
+    pub device_name: String,
+    /// The device opens sealed file chunks (see [`open_file_chunk`]).
+    pub sealed_chunks: bool,
+}
+
+/// A clipboard event in the clear.  Events encrypted here are unsigned;
+/// `verified_signer` is only filled in on decryption.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ClipboardEvent {
+    pub sender_device_id: String,
+    pub counter: u64,
+    pub timestamp_unix_ms: u64,
+    pub mime: String,
+    pub text_utf8: String,
+    pub pinned: bool,
+    pub label: Option<String>,
+    /// Hex public key whose signature over the event verified, if any.
+    pub verified_signer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct EncryptedEvent {
+    pub sender_device_id: String,
+    pub counter: u64,
+    pub ciphertext: Vec<u8>,
+    /// Set by the relay on forwarded frames; ignored when sending.
+    pub relay_seq: Option<u64>,
+}
+
+/// A decoded relay frame.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum Frame {
+    Hello { room_id: String, peer: Peer },
+    PeerList { peers: Vec<Peer> },
+    PeerJoined { peer: Peer },
+    PeerLeft { device_id: String },
+    SaltExchange { device_ids: Vec<String> },
+    Error { message: String },
+    Maintenance { at_unix_ms: u64, message: String },
+    Probe { probe: Probe },
+    Reconnect { at_unix_ms: u64 },
+    Encrypted { payload: EncryptedEvent },
+}
+
+/// A relay-stamped latency probe (see [`crate::LatencyProbe`]).
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Probe {
+    pub probe_id: u64,
+    pub sender_device_id: String,
+    pub sent_unix_ms: u64,
+    pub relay_received_unix_ms: Option<u64>,
+    pub relay_forwarded_unix_ms: Option<u64>,
+}
+
+/// One chunk of a file transfer, with its bytes decoded.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FileChunk {
+    pub transfer_id: String,
+    pub file_name: String,
+    pub total_size: u64,
+    pub chunk_index: u32,
+    pub total_chunks: u32,
+    pub data: Vec<u8>,
+}
+
+#[uniffi::export]
+pub fn room_id_from_code(room_code: String) -> String {
+    crate::room_id_from_code(&room_code)
+}
+
+#[uniffi::export]
+pub fn derive_room_key(room_code: String, device_ids: Vec<String>) -> Result<Vec<u8>, FfiError> {
+    Ok(crate::derive_room_key(&room_code, &device_ids)?.to_vec())
+}
+
+#[uniffi::export]
+pub fn encrypt_event(room_key: Vec<u8>, event: ClipboardEvent) -> Result<EncryptedEvent, FfiError> {
+    let event = ClipboardEventPlaintext {
+        sender_device_id: event.sender_device_id,
+        counter: event.counter,
+        timestamp_unix_ms: event.timestamp_unix_ms,
+        mime: event.mime,
+        text_utf8: event.text_utf8,
+        pinned: event.pinned,
+        label: event.label,
+        signature: None,
+    };
+    let payload = crate::encrypt_clipboard_event(&room_key_array(&room_key)?, &event)?;
+    Ok(payload.into())
+}
+
+#[uniffi::export]
+pub fn decrypt_event(
+    room_key: Vec<u8>,
+    payload: EncryptedEvent,
+) -> Result<ClipboardEvent, FfiError> {
+    let payload = EncryptedPayload {
+        sender_device_id: payload.sender_device_id,
+        counter: payload.counter,
+        ciphertext: payload.ciphertext,
+        relay_seq: payload.relay_seq,
+    };
+    let event = crate::decrypt_clipboard_event(&room_key_array(&room_key)?, &payload)?;
+    let verified_signer = crate::verified_signer(&event);
+    Ok(ClipboardEvent {
+        sender_device_id: event.sender_device_id,
+        counter: event.counter,
+        timestamp_unix_ms: event.timestamp_unix_ms,
+        mime: event.mime,
+        text_utf8: event.text_utf8,
+        pinned: event.pinned,
+        label: event.label,
+        verified_signer,
+    })
+}
+
+/// The frame that joins a room; the first thing a client sends.
+#[uniffi::export]
+pub fn encode_hello_frame(room_id: String, peer: Peer) -> Result<Vec<u8>, FfiError> {
+    Ok(crate::encode_frame(&WireMessage::Control(
+        ControlMessage::Hello(Hello {
+            room_id,
+            peer: peer.into(),
+            sequenced: true,
+            probes: false,
+        }),
+    ))?)
+}
+
+#[uniffi::export]
+pub fn encode_encrypted_frame(payload: EncryptedEvent) -> Result<Vec<u8>, FfiError> {
+    Ok(crate::encode_frame(&WireMessage::Encrypted(
+        EncryptedPayload {
+            sender_device_id: payload.sender_device_id,
+            counter: payload.counter,
+            ciphertext: payload.ciphertext,
+            relay_seq: None,
+        },
+    ))?)
+}
+
+#[uniffi::export]
+pub fn decode_frame(frame: Vec<u8>) -> Result<Frame, FfiError> {
+    Ok(match crate::decode_frame(&frame)? {
+        WireMessage::Encrypted(payload) => Frame::Encrypted {
+            payload: payload.into(),
+        },
+        WireMessage::Control(control) => match control {
+            ControlMessage::Hello(hello) => Frame::Hello {
+                room_id: hello.room_id,
+                peer: hello.peer.into(),
+            },
+            ControlMessage::PeerList(list) => Frame::PeerList {
+                peers: list.peers.into_iter().map(Peer::from).collect(),
+            },
+            ControlMessage::PeerJoined(joined) => Frame::PeerJoined {
+                peer: joined.peer.into(),
+            },
+            ControlMessage::PeerLeft(left) => Frame::PeerLeft {
+                device_id: left.device_id,
+            },
+            ControlMessage::SaltExchange(exchange) => Frame::SaltExchange {
+                device_ids: exchange.device_ids,
+            },
+            ControlMessage::Error { message, .. } => Frame::Error { message },
+            ControlMessage::Maintenance(notice) => Frame::Maintenance {
+                at_unix_ms: notice.at_unix_ms,
+                message: notice.message,
+            },
+            ControlMessage::Probe(probe) => Frame::Probe {
+                probe: Probe {
+                    probe_id: probe.probe_id,
+                    sender_device_id: probe.sender_device_id,
+                    sent_unix_ms: probe.sent_unix_ms,
+                    relay_received_unix_ms: probe.relay_received_unix_ms,
+                    relay_forwarded_unix_ms: probe.relay_forwarded_unix_ms,
+                },
+            },
+            ControlMessage::Reconnect(notice) => Frame::Reconnect {
+                at_unix_ms: notice.at_unix_ms,
+            },
+        },
+    })
+}
+
+/// Event texts (MIME [`crate::MIME_FILE_CHUNK_JSON_B64`]) that together
+/// carry `data`, in order.
+#[uniffi::export]
+pub fn split_file(
+    transfer_id: String,
+    file_name: String,
+    data: Vec<u8>,
+) -> Result<Vec<String>, FfiError> {
+    chunker::split_file(&transfer_id, &file_name, &data)?
+        .iter()
+        .map(|chunk| chunk.to_text().map_err(FfiError::from))
+        .collect()
+}
+
+/// Event texts (MIME [`crate::MIME_FILE_CHUNK_SEALED_JSON_B64`]) that
+/// together carry `data` sealed under the transfer's key, in order.
+#[uniffi::export]
+pub fn split_file_sealed(
+    room_key: Vec<u8>,
+    transfer_id: String,
+    file_name: String,
+    data: Vec<u8>,
+) -> Result<Vec<String>, FfiError> {
+    let room_key = room_key_array(&room_key)?;
+    chunker::split_file_sealed(&room_key, &transfer_id, &file_name, &data)?
+        .iter()
+        .map(|chunk| chunk.to_text().map_err(FfiError::from))
+        .collect()
+}
+
+/// Read and open the text of a sealed file-chunk event.
+#[uniffi::export]
+pub fn open_file_chunk(room_key: Vec<u8>, text_utf8: String) -> Result<FileChunk, FfiError> {
+    let room_key = room_key_array(&room_key)?;
+    let envelope = chunker::FileChunkEnvelope::parse(&text_utf8)?;
+    let data = envelope.open(&crate::derive_transfer_key(
+        &room_key,
+        &envelope.transfer_id,
+    )?)?;
+    Ok(FileChunk {
+        transfer_id: envelope.transfer_id,
+        file_name: envelope.file_name,
+        total_size: envelope.total_size,
+        chunk_index: envelope.chunk_index,
+        total_chunks: envelope.total_chunks,
+        data,
+    })
+}
+
+/// Read the text of a file-chunk event.
+#[uniffi::export]
+pub fn parse_file_chunk(text_utf8: String) -> Result<FileChunk, FfiError> {
+    let envelope = chunker::FileChunkEnvelope::parse(&text_utf8)?;
+    let data = envelope.data()?;
+    Ok(FileChunk {
+        transfer_id: envelope.transfer_id,
+        file_name: envelope.file_name,
+        total_size: envelope.total_size,
+        chunk_index: envelope.chunk_index,
+        total_chunks: envelope.total_chunks,
+        data,
+    })
+}
+
+/// MIME type of plain text events.
+#[uniffi::export]
+pub fn mime_text_plain() -> String {
+    crate::MIME_TEXT_PLAIN.to_owned()
+}
+
+/// MIME type of file-chunk events.
+#[uniffi::export]
+pub fn mime_file_chunk() -> String {
+    crate::MIME_FILE_CHUNK_JSON_B64.to_owned()
+}
+
+/// MIME type of sealed file-chunk events.
+#[uniffi::export]
+pub fn mime_file_chunk_sealed() -> String {
+    crate::MIME_FILE_CHUNK_SEALED_JSON_B64.to_owned()
+}
+
+fn room_key_array(room_key: &[u8]) -> Result<[u8; 32], FfiError> {
+    room_key.try_into().map_err(|_| FfiError::InvalidKeyLength)
+}
+
+impl From<PeerInfo> for Peer {
+    fn from(peer: PeerInfo) -> Self {
+        Self {
+            device_id: peer.device_id,
+            device_name: peer.device_name,
+            sealed_chunks: peer.sealed_chunks,
+        }
+    }
+}
+
+impl From<Peer> for PeerInfo {
+    fn from(peer: Peer) -> Self {
+        Self {
+            device_id: peer.device_id,
+            device_name: peer.device_name,
+            sealed_chunks: peer.sealed_chunks,
+        }
+    }
+}
+
+impl From<EncryptedPayload> for EncryptedEvent {
+    fn from(payload: EncryptedPayload) -> Self {
+        Self {
+            sender_device_id: payload.sender_device_id,
+            counter: payload.counter,
+            ciphertext: payload.ciphertext,
+            relay_seq: payload.relay_seq,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_survives_a_trip_through_the_bindings() {
+        let key = derive_room_key("room".to_owned(), vec!["a".to_owned()]).unwrap();
+        let event = ClipboardEvent {
+            sender_device_id: "a".to_owned(),
+            counter: 7,
+            timestamp_unix_ms: 1,
+            mime: mime_text_plain(),
+            text_utf8: "hello".to_owned(),
+            pinned: false,
+            label: Some("tag".to_owned()),
+            verified_signer: None,
+        };
+        let frame =
+            encode_encrypted_frame(encrypt_event(key.clone(), event.clone()).unwrap()).unwrap();
+        let Frame::Encrypted { payload } = decode_frame(frame).unwrap() else {
+            panic!("expected an encrypted frame");
+        };
+        assert_eq!(decrypt_event(key, payload).unwrap(), event);
+    }
+
+    #[test]
+    fn short_keys_and_bad_chunks_are_errors() {
+        let payload = EncryptedEvent {
+            sender_device_id: "a".to_owned(),
+            counter: 1,
+            ciphertext: vec![1, 2, 3],
+            relay_seq: None,
+        };
+        assert!(matches!(
+            decrypt_event(vec![0; 16], payload),
+            Err(FfiError::InvalidKeyLength)
+        ));
+        assert!(parse_file_chunk("not json".to_owned()).is_err());
+
+        let texts = split_file("t".to_owned(), "f.txt".to_owned(), b"abc".to_vec()).unwrap();
+        let chunk = parse_file_chunk(texts[0].clone()).unwrap();
+        assert_eq!((chunk.total_chunks, chunk.data), (1, b"abc".to_vec()));
+
+        let sealed = split_file_sealed(
+            vec![3; 32],
+            "t".to_owned(),
+            "f.txt".to_owned(),
+            b"abc".to_vec(),
+        )
+        .unwrap();
+        let chunk = open_file_chunk(vec![3; 32], sealed[0].clone()).unwrap();
+        assert_eq!(chunk.data, b"abc".to_vec());
+        assert!(open_file_chunk(vec![4; 32], sealed[0].clone()).is_err());
+    }
+}
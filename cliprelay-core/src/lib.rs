@@ -6,17 +6,47 @@ use chacha20poly1305::{
     aead::{Aead, Payload, generic_array::GenericArray},
 };
 use hkdf::Hkdf;
+use ring::{
+    rand::SystemRandom,
+    signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+use crate::{canonical::CanonicalEncoder, event_schema::EventEnvelope};
+
+pub mod canonical;
+
+pub mod chunker;
+
+pub mod event_schema;
+
+#[cfg(feature = "uniffi")]
+pub mod ffi;
+
+pub mod session;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
 pub const MAX_CLIPBOARD_TEXT_BYTES: usize = 256 * 1024;
 pub const MAX_RELAY_MESSAGE_BYTES: usize = 300 * 1024;
 pub const MAX_DEVICES_PER_ROOM: usize = 10;
 pub const MAX_MIME_LEN: usize = 128;
+/// Longest item label, in characters; longer ones are cut on receipt.
+pub const MAX_EVENT_LABEL_CHARS: usize = 40;
 pub const MIME_TEXT_PLAIN: &str = "text/plain";
 pub const MIME_FILE_CHUNK_JSON_B64: &str = "application/x-cliprelay-file-chunk+json;base64";
+/// File chunk whose bytes are also encrypted under the transfer's own key
+/// (see [`chunker`]).  Clients that predate it ignore these events.
+pub const MIME_FILE_CHUNK_SEALED_JSON_B64: &str =
+    "application/x-cliprelay-file-chunk-sealed+json;base64";
+/// Event carrying a JSON [`TransferCancel`].
+pub const MIME_TRANSFER_CANCEL_JSON: &str = "application/x-cliprelay-transfer-cancel+json";
 const ROOM_KEY_INFO: &[u8] = b"cliprelay v1 room key";
+const TRANSFER_KEY_CONTEXT: &[u8] = b"cliprelay v1 transfer key";
+const EVENT_SIGNATURE_CONTEXT: &[u8] = b"cliprelay v1 event signature";
 
 pub type DeviceId = String;
 pub type RoomId = String;
@@ -26,6 +56,11 @@ pub type Counter = u64;
 pub struct PeerInfo {
     pub device_id: String,
     pub device_name: String,
+    /// The device opens [`MIME_FILE_CHUNK_SEALED_JSON_B64`] chunks.  Older
+    /// clients and relays drop the field, so their peers fall back to
+    /// plain chunks.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sealed_chunks: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -35,6 +70,35 @@ pub struct ClipboardEventPlaintext {
     pub timestamp_unix_ms: u64,
     pub mime: String,
     pub text_utf8: String,
+    /// Asks receivers to keep the item at the top of their lists, and out
+    /// of history pruning, until they unpin it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// Short title chosen by the sender to tell similar items apart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Proof that the event came from the holder of the sender's device
+    /// identity key.  Absent in events from clients that predate identities.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<EventSignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventSignature {
+    /// Hex-encoded Ed25519 public key of the sender's device identity.
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature over the event's signing bytes.
+    pub signature: String,
+}
+
+/// Stops a file transfer.  Sent by the file's sender to tell receivers to
+/// drop what they buffered, or by a receiver to ask the sender to stop
+/// sending chunks.  Travels inside the room encryption like file chunks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TransferCancel {
+    pub transfer_id: String,
+    /// Device sending the file.
+    pub owner_device_id: DeviceId,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -42,12 +106,28 @@ pub struct EncryptedPayload {
     pub sender_device_id: String,
     pub counter: u64,
     pub ciphertext: Vec<u8>,
+    /// Position in the room's stream of payloads, stamped by the relay when
+    /// it forwards the payload to a client that asked for it (see
+    /// [`Hello::sequenced`]).  Every client in a room sees the same order.
+    /// `None` as sent by clients.  It is outside the encryption, so it
+    /// orders messages but proves nothing about them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_seq: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Hello {
     pub room_id: RoomId,
     pub peer: PeerInfo,
+    /// The client decodes [`EncryptedPayload::relay_seq`].  Older decoders
+    /// reject the extra bytes, so the relay stamps payloads only for
+    /// clients that set this.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sequenced: bool,
+    /// The client decodes [`ControlMessage::Probe`], so the relay forwards
+    /// other devices' probes to it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub probes: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -74,6 +154,104 @@ pub struct SaltExchange {
     pub device_ids: Vec<DeviceId>,
 }
 
+/// The relay goes down for maintenance at `at_unix_ms` and closes every
+/// connection then.  Clients older than this message log it as an
+/// undecodable frame and carry on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceNotice {
+    pub at_unix_ms: u64,
+    /// Free text from the relay operator; may be empty.
+    #[serde(default)]
+    pub message: String,
+}
+
+impl MaintenanceNotice {
+    /// One line for a notification, e.g. `"Relay maintenance in 5 min,
+    /// expect a disconnect: upgrade to 1.4"`.
+    pub fn describe(&self, now_unix_ms: u64) -> String {
+        let remaining_ms = self.at_unix_ms.saturating_sub(now_unix_ms);
+        let when = match remaining_ms.div_ceil(60_000) {
+            0 => "now".to_owned(),
+            minutes => format!("in {minutes} min"),
+        };
+        let message = self.message.trim();
+        if message.is_empty() {
+            format!("Relay maintenance {when}, expect a disconnect")
+        } else {
+            format!("Relay maintenance {when}, expect a disconnect: {message}")
+        }
+    }
+}
+
+/// The relay closes this connection at `at_unix_ms`, when it reaches the
+/// relay's maximum connection age.  The client should connect again before
+/// then, once nothing is left to send on it; the relay keeps the device in
+/// the room across the switch.  Clients older than this message log it as
+/// an undecodable frame and are disconnected at that time.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReconnectNotice {
+    pub at_unix_ms: u64,
+}
+
+/// Latency probe.  A client sends it with `sent_unix_ms`; the relay sets
+/// the sender's id and its own receive and forward times, then passes it to
+/// the sender and to every other device in the room that asked for probes.
+/// Relays that predate probes count them as bad frames, so clients stop
+/// sending them when none come back.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LatencyProbe {
+    pub probe_id: u64,
+    /// Set by the relay; whatever the client sent is replaced.
+    #[serde(default)]
+    pub sender_device_id: DeviceId,
+    pub sent_unix_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_received_unix_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay_forwarded_unix_ms: Option<u64>,
+}
+
+/// Where a probe spent its time, in milliseconds.  The legs into and out
+/// of the relay each compare two clocks, so they are off by the skew
+/// between them and can be negative; their sum compares the sender's clock
+/// with the receiver's, and is exact for a device's own probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeLatency {
+    pub to_relay_ms: i64,
+    pub in_relay_ms: i64,
+    pub from_relay_ms: i64,
+}
+
+impl ProbeLatency {
+    pub fn total_ms(&self) -> i64 {
+        self.to_relay_ms + self.in_relay_ms + self.from_relay_ms
+    }
+}
+
+impl LatencyProbe {
+    pub fn new(probe_id: u64, sent_unix_ms: u64) -> Self {
+        Self {
+            probe_id,
+            sender_device_id: DeviceId::new(),
+            sent_unix_ms,
+            relay_received_unix_ms: None,
+            relay_forwarded_unix_ms: None,
+        }
+    }
+
+    /// The breakdown of a probe received at `received_unix_ms`, once the
+    /// relay has stamped it.
+    pub fn latency(&self, received_unix_ms: u64) -> Option<ProbeLatency> {
+        let received_by_relay = self.relay_received_unix_ms? as i64;
+        let forwarded = self.relay_forwarded_unix_ms? as i64;
+        Some(ProbeLatency {
+            to_relay_ms: received_by_relay - self.sent_unix_ms as i64,
+            in_relay_ms: forwarded - received_by_relay,
+            from_relay_ms: received_unix_ms as i64 - forwarded,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "type", content = "data")]
 pub enum ControlMessage {
@@ -82,7 +260,27 @@ pub enum ControlMessage {
     PeerJoined(PeerJoined),
     PeerLeft(PeerLeft),
     SaltExchange(SaltExchange),
-    Error { message: String },
+    Error {
+        message: String,
+        /// Set when the relay refuses a Hello, so clients can tell why
+        /// without parsing `message`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<RelayErrorCode>,
+    },
+    Maintenance(MaintenanceNotice),
+    Probe(LatencyProbe),
+    Reconnect(ReconnectNotice),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayErrorCode {
+    /// The relay only serves rooms on its allow-list.
+    RoomNotAllowed,
+    RoomFull,
+    /// A code added after this build.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -119,6 +317,18 @@ pub enum CoreError {
     PayloadIdentityMismatch,
     #[error("hkdf expand failed")]
     KeyDerivationFailed,
+    #[error("device identity key is invalid")]
+    InvalidIdentityKey,
+    #[error("device identity key generation failed")]
+    KeyGenerationFailed,
+    #[error("file is empty")]
+    EmptyFile,
+    #[error("file needs too many chunks ({0})")]
+    TooManyChunks(u32),
+    #[error("room key is not ready yet")]
+    RoomKeyNotReady,
+    #[error("unsupported clipboard event schema version {0}")]
+    UnsupportedEventVersion(u32),
     #[error("stale or replayed counter for sender {sender}: got {counter}, last {last_seen}")]
     ReplayRejected {
         sender: String,
@@ -141,9 +351,42 @@ pub fn derive_room_key(room_code: &str, device_ids: &[DeviceId]) -> Result<[u8;
     Ok(output)
 }
 
+/// Key for the chunks of one file transfer, so that a leaked chunk key
+/// exposes one file rather than the room, and chunks cannot be moved
+/// between transfers.
+pub fn derive_transfer_key(room_key: &[u8; 32], transfer_id: &str) -> Result<[u8; 32], CoreError> {
+    let info = CanonicalEncoder::new(TRANSFER_KEY_CONTEXT)
+        .str(transfer_id)
+        .finish();
+    let hk = Hkdf::<Sha256>::new(None, room_key);
+    let mut output = [0_u8; 32];
+    hk.expand(&info, &mut output)
+        .map_err(|_| CoreError::KeyDerivationFailed)?;
+    Ok(output)
+}
+
+/// Encrypt `event` in the version 1 schema (see [`event_schema`]).
 pub fn encrypt_clipboard_event(
     room_key: &[u8; 32],
     event: &ClipboardEventPlaintext,
+) -> Result<EncryptedPayload, CoreError> {
+    let plaintext =
+        serde_json::to_vec(event).map_err(|err| CoreError::Serialization(err.to_string()))?;
+    seal_event(room_key, event, &plaintext)
+}
+
+/// Encrypt an event in the schema version of `envelope`.
+pub fn encrypt_event_envelope(
+    room_key: &[u8; 32],
+    envelope: &EventEnvelope,
+) -> Result<EncryptedPayload, CoreError> {
+    seal_event(room_key, &envelope.event, &envelope.encode()?)
+}
+
+fn seal_event(
+    room_key: &[u8; 32],
+    event: &ClipboardEventPlaintext,
+    plaintext: &[u8],
 ) -> Result<EncryptedPayload, CoreError> {
     let mime = event.mime.trim();
     if mime.is_empty() || mime.len() > MAX_MIME_LEN {
@@ -154,14 +397,12 @@ pub fn encrypt_clipboard_event(
     }
 
     let nonce = build_nonce(&event.sender_device_id, event.counter);
-    let plaintext =
-        serde_json::to_vec(event).map_err(|err| CoreError::Serialization(err.to_string()))?;
     let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(room_key));
     let ciphertext = cipher
         .encrypt(
             GenericArray::from_slice(&nonce),
             Payload {
-                msg: &plaintext,
+                msg: plaintext,
                 aad: b"cliprelay:v1",
             },
         )
@@ -171,13 +412,23 @@ pub fn encrypt_clipboard_event(
         sender_device_id: event.sender_device_id.clone(),
         counter: event.counter,
         ciphertext,
+        relay_seq: None,
     })
 }
 
+/// Decrypt an event of either schema version, without its extensions.
 pub fn decrypt_clipboard_event(
     room_key: &[u8; 32],
     payload: &EncryptedPayload,
 ) -> Result<ClipboardEventPlaintext, CoreError> {
+    decrypt_event_envelope(room_key, payload).map(|envelope| envelope.event)
+}
+
+/// Decrypt an event of either schema version, with its extensions.
+pub fn decrypt_event_envelope(
+    room_key: &[u8; 32],
+    payload: &EncryptedPayload,
+) -> Result<EventEnvelope, CoreError> {
     let nonce = build_nonce(&payload.sender_device_id, payload.counter);
     let cipher = XChaCha20Poly1305::new(GenericArray::from_slice(room_key));
     let plaintext = cipher
@@ -190,8 +441,8 @@ pub fn decrypt_clipboard_event(
         )
         .map_err(|_| CoreError::DecryptionFailed)?;
 
-    let event: ClipboardEventPlaintext = serde_json::from_slice(&plaintext)
-        .map_err(|err| CoreError::Serialization(err.to_string()))?;
+    let envelope = EventEnvelope::decode(&plaintext)?;
+    let event = &envelope.event;
     if event.sender_device_id != payload.sender_device_id || event.counter != payload.counter {
         return Err(CoreError::PayloadIdentityMismatch);
     }
@@ -202,7 +453,7 @@ pub fn decrypt_clipboard_event(
     if event.text_utf8.len() > MAX_CLIPBOARD_TEXT_BYTES {
         return Err(CoreError::ClipboardTooLarge);
     }
-    Ok(event)
+    Ok(envelope)
 }
 
 pub fn validate_counter(
@@ -224,6 +475,101 @@ pub fn validate_counter(
     Ok(())
 }
 
+/// Long-lived Ed25519 key pair identifying one device.  Peers pin its
+/// public key on first use; events are signed with it inside the room
+/// encryption, so the relay never sees the signatures.
+#[derive(Debug)]
+pub struct DeviceIdentity {
+    key_pair: Ed25519KeyPair,
+    pkcs8: Vec<u8>,
+}
+
+impl DeviceIdentity {
+    pub fn generate() -> Result<Self, CoreError> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| CoreError::KeyGenerationFailed)?;
+        Self::from_pkcs8(pkcs8.as_ref())
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, CoreError> {
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| CoreError::InvalidIdentityKey)?;
+        Ok(Self {
+            key_pair,
+            pkcs8: pkcs8.to_vec(),
+        })
+    }
+
+    /// PKCS#8 encoding of the private key, for storage.
+    pub fn pkcs8(&self) -> &[u8] {
+        &self.pkcs8
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.key_pair.public_key().as_ref())
+    }
+
+    /// Attach a signature over `event`'s content to `event`.
+    pub fn sign_event(&self, event: &mut ClipboardEventPlaintext) {
+        let signature = self.key_pair.sign(&event_signing_bytes(event));
+        event.signature = Some(EventSignature {
+            public_key: self.public_key_hex(),
+            signature: hex::encode(signature.as_ref()),
+        });
+    }
+}
+
+/// Hex public key that validly signed `event`, or `None` when the event is
+/// unsigned or the signature does not verify.
+pub fn verified_signer(event: &ClipboardEventPlaintext) -> Option<String> {
+    let signature = event.signature.as_ref()?;
+    let public_key = hex::decode(&signature.public_key).ok()?;
+    let sig = hex::decode(&signature.signature).ok()?;
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(&event_signing_bytes(event), &sig)
+        .ok()?;
+    Some(signature.public_key.to_ascii_lowercase())
+}
+
+/// Short human-comparable form of a hex public key, e.g. `"3f2a 91c0 …"`
+/// (the first 8 bytes of its SHA-256, in groups of two bytes).
+pub fn key_fingerprint(public_key_hex: &str) -> String {
+    let digest = Sha256::digest(public_key_hex.to_ascii_lowercase().as_bytes());
+    hex::encode(&digest[..8])
+        .as_bytes()
+        .chunks(4)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn event_signing_bytes(event: &ClipboardEventPlaintext) -> Vec<u8> {
+    CanonicalEncoder::new(EVENT_SIGNATURE_CONTEXT)
+        .str(&event.sender_device_id)
+        .str(&event.mime)
+        .u64(event.counter)
+        .u64(event.timestamp_unix_ms)
+        .fixed(&Sha256::digest(event.text_utf8.as_bytes()))
+        // Present only when set, so unpinned, unlabelled events sign
+        // exactly as before those fields existed.
+        .flag(b"pinned", event.pinned)
+        .optional(b"label", event.label.as_deref().map(str::as_bytes))
+        .finish()
+}
+
+/// A label fit for display: one line of at most [`MAX_EVENT_LABEL_CHARS`]
+/// characters, or `None` when nothing printable is left.
+pub fn normalize_event_label(raw: &str) -> Option<String> {
+    let label = raw
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let label: String = label.chars().take(MAX_EVENT_LABEL_CHARS).collect();
+    let label = label.trim_end();
+    (!label.is_empty()).then(|| label.to_owned())
+}
+
 pub fn encode_frame(message: &WireMessage) -> Result<Vec<u8>, CoreError> {
     let (message_type, payload) = match message {
         WireMessage::Control(control) => (
@@ -284,6 +630,7 @@ fn encode_encrypted_payload(payload: &EncryptedPayload) -> Result<Vec<u8>, CoreE
     // - counter: u64
     // - ciphertext_len: u32
     // - ciphertext bytes
+    // - relay_seq: u64, only when set
     let device_id = payload.sender_device_id.as_bytes();
     let device_id_len =
         u16::try_from(device_id.len()).map_err(|_| CoreError::InvalidFrameLength)?;
@@ -296,6 +643,9 @@ fn encode_encrypted_payload(payload: &EncryptedPayload) -> Result<Vec<u8>, CoreE
     out.put_u64_le(payload.counter);
     out.put_u32_le(ciphertext_len);
     out.extend_from_slice(&payload.ciphertext);
+    if let Some(seq) = payload.relay_seq {
+        out.put_u64_le(seq);
+    }
     Ok(out.to_vec())
 }
 
@@ -317,14 +667,17 @@ fn decode_encrypted_payload(mut bytes: &[u8]) -> Result<EncryptedPayload, CoreEr
 
     let counter = bytes.get_u64_le();
     let ciphertext_len = bytes.get_u32_le() as usize;
-    if bytes.len() != ciphertext_len {
-        return Err(CoreError::InvalidFrameLength);
-    }
+    let relay_seq = match bytes.len().checked_sub(ciphertext_len) {
+        Some(0) => None,
+        Some(8) => Some((&bytes[ciphertext_len..]).get_u64_le()),
+        _ => return Err(CoreError::InvalidFrameLength),
+    };
 
     Ok(EncryptedPayload {
         sender_device_id,
         counter,
-        ciphertext: bytes.to_vec(),
+        ciphertext: bytes[..ciphertext_len].to_vec(),
+        relay_seq,
     })
 }
 
@@ -333,6 +686,15 @@ pub fn room_id_from_code(room_code: &str) -> RoomId {
     hex::encode(digest)
 }
 
+/// Stable id of the device named `device_name` for `user` on `host`.  Every
+/// client derives it the same way, so a device keeps its id (and the trust
+/// peers placed in it) whichever client it runs.
+pub fn device_id_from(host: &str, user: &str, device_name: &str) -> DeviceId {
+    let raw = format!("{}:{}:{}", host, user, device_name.trim());
+    let digest = Sha256::digest(raw.as_bytes());
+    hex::encode(&digest[0..16])
+}
+
 fn compute_device_list_hash(device_ids: &[DeviceId]) -> [u8; 32] {
     let mut sorted = device_ids.to_vec();
     sorted.sort();
@@ -369,9 +731,50 @@ mod tests {
             timestamp_unix_ms: 1_735_000_000_000,
             mime: "text/plain".to_owned(),
             text_utf8: "hello cliprelay".to_owned(),
+            pinned: false,
+            label: None,
+            signature: None,
         }
     }
 
+    /// Signatures made by earlier versions must keep verifying, so the
+    /// signed bytes of an event may never change.
+    #[test]
+    fn event_signing_bytes_are_stable_across_versions() {
+        let digest = |event: &ClipboardEventPlaintext| {
+            hex::encode(Sha256::digest(event_signing_bytes(event)))
+        };
+        let mut event = sample_event(7);
+        assert_eq!(
+            digest(&event),
+            "ffb29643197d9218a9fd17152e42839c0b91351edb8b52ab8575722a36610884"
+        );
+        event.pinned = true;
+        event.label = Some("tag".to_owned());
+        assert_eq!(
+            digest(&event),
+            "b6c5d802cbf477d3c1f9f8f89a8fd587e17ea10b34ad91099ff7d7268224ba55"
+        );
+        // The JSON form is free to change without affecting the signature.
+        let signed_before = event_signing_bytes(&event);
+        event.signature = Some(EventSignature {
+            public_key: "00".to_owned(),
+            signature: "00".to_owned(),
+        });
+        assert_eq!(event_signing_bytes(&event), signed_before);
+    }
+
+    #[test]
+    fn transfer_keys_differ_per_transfer_and_room() {
+        let room_a = derive_room_key("room-a", &["d".to_owned()]).unwrap();
+        let room_b = derive_room_key("room-b", &["d".to_owned()]).unwrap();
+        let key = derive_transfer_key(&room_a, "t1").unwrap();
+        assert_eq!(key, derive_transfer_key(&room_a, "t1").unwrap());
+        assert_ne!(key, room_a);
+        assert_ne!(key, derive_transfer_key(&room_a, "t2").unwrap());
+        assert_ne!(key, derive_transfer_key(&room_b, "t1").unwrap());
+    }
+
     #[test]
     fn encryption_roundtrip() {
         let devices = vec!["device-a".to_owned(), "device-b".to_owned()];
@@ -382,6 +785,200 @@ mod tests {
         assert_eq!(event, decrypted);
     }
 
+    #[test]
+    fn signed_event_survives_encryption_and_verifies() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let key = derive_room_key("room", &["device-a".to_owned()]).unwrap();
+        let mut event = sample_event(1);
+        assert_eq!(verified_signer(&event), None);
+        identity.sign_event(&mut event);
+
+        let decrypted =
+            decrypt_clipboard_event(&key, &encrypt_clipboard_event(&key, &event).unwrap()).unwrap();
+        assert_eq!(verified_signer(&decrypted), Some(identity.public_key_hex()));
+
+        let reloaded = DeviceIdentity::from_pkcs8(identity.pkcs8()).unwrap();
+        assert_eq!(reloaded.public_key_hex(), identity.public_key_hex());
+    }
+
+    #[test]
+    fn tampered_or_foreign_signatures_are_rejected() {
+        let identity = DeviceIdentity::generate().unwrap();
+        let mut event = sample_event(1);
+        identity.sign_event(&mut event);
+
+        let mut tampered = event.clone();
+        tampered.text_utf8.push('!');
+        assert_eq!(verified_signer(&tampered), None);
+
+        let mut pinned = event.clone();
+        pinned.pinned = true;
+        assert_eq!(verified_signer(&pinned), None);
+
+        let mut labelled = event.clone();
+        labelled.label = Some("prod".to_owned());
+        assert_eq!(verified_signer(&labelled), None);
+
+        // Claiming someone else's public key does not verify either.
+        let other = DeviceIdentity::generate().unwrap();
+        let mut impersonated = event.clone();
+        if let Some(signature) = impersonated.signature.as_mut() {
+            signature.public_key = other.public_key_hex();
+        }
+        assert_eq!(verified_signer(&impersonated), None);
+    }
+
+    #[test]
+    fn pinned_flag_is_only_serialized_when_set() {
+        let mut event = sample_event(1);
+        let json = serde_json::to_value(&event).unwrap();
+        assert!(json.get("pinned").is_none());
+
+        event.pinned = true;
+        let json = serde_json::to_string(&event).unwrap();
+        let parsed: ClipboardEventPlaintext = serde_json::from_str(&json).unwrap();
+        assert!(parsed.pinned);
+    }
+
+    #[test]
+    fn labels_are_cut_to_one_short_line() {
+        assert_eq!(
+            normalize_event_label("  staging\n token\t2 "),
+            Some("staging token 2".to_owned())
+        );
+        assert_eq!(normalize_event_label(" \u{7}\n "), None);
+        let long = "é".repeat(MAX_EVENT_LABEL_CHARS + 5);
+        assert_eq!(
+            normalize_event_label(&long).map(|l| l.chars().count()),
+            Some(MAX_EVENT_LABEL_CHARS)
+        );
+    }
+
+    #[test]
+    fn relay_seq_is_an_optional_frame_trailer() {
+        let mut payload = EncryptedPayload {
+            sender_device_id: "device-a".to_owned(),
+            counter: 7,
+            ciphertext: vec![1, 2, 3],
+            relay_seq: None,
+        };
+        let plain = encode_frame(&WireMessage::Encrypted(payload.clone())).unwrap();
+        assert_eq!(
+            decode_frame(&plain).unwrap(),
+            WireMessage::Encrypted(payload.clone())
+        );
+
+        payload.relay_seq = Some(42);
+        let stamped = encode_frame(&WireMessage::Encrypted(payload.clone())).unwrap();
+        assert_eq!(stamped.len(), plain.len() + 8);
+        assert_eq!(
+            decode_frame(&stamped).unwrap(),
+            WireMessage::Encrypted(payload)
+        );
+
+        // Anything else after the ciphertext is still malformed.
+        let mut trailing = plain.clone();
+        trailing.push(0);
+        let frame_len = (trailing.len() - 4) as u32;
+        trailing[..4].copy_from_slice(&frame_len.to_le_bytes());
+        assert!(matches!(
+            decode_frame(&trailing),
+            Err(CoreError::InvalidFrameLength)
+        ));
+    }
+
+    #[test]
+    fn maintenance_notice_round_trips_and_describes_itself() {
+        let notice = MaintenanceNotice {
+            at_unix_ms: 10 * 60_000,
+            message: " upgrade ".to_owned(),
+        };
+        let frame = encode_frame(&WireMessage::Control(ControlMessage::Maintenance(
+            notice.clone(),
+        )))
+        .unwrap();
+        assert_eq!(
+            decode_frame(&frame).unwrap(),
+            WireMessage::Control(ControlMessage::Maintenance(notice.clone()))
+        );
+
+        assert_eq!(
+            notice.describe(5 * 60_000 + 1),
+            "Relay maintenance in 5 min, expect a disconnect: upgrade"
+        );
+        let bare = MaintenanceNotice {
+            message: String::new(),
+            ..notice
+        };
+        assert_eq!(
+            bare.describe(11 * 60_000),
+            "Relay maintenance now, expect a disconnect"
+        );
+    }
+
+    #[test]
+    fn reconnect_notice_round_trips() {
+        let notice = ControlMessage::Reconnect(ReconnectNotice { at_unix_ms: 42 });
+        let frame = encode_frame(&WireMessage::Control(notice.clone())).unwrap();
+        assert_eq!(decode_frame(&frame).unwrap(), WireMessage::Control(notice));
+    }
+
+    #[test]
+    fn probe_latency_is_split_at_the_relay() {
+        let mut probe = LatencyProbe::new(1, 1_000);
+        assert_eq!(probe.latency(1_100), None);
+        probe.relay_received_unix_ms = Some(1_030);
+        probe.relay_forwarded_unix_ms = Some(1_031);
+        let frame =
+            encode_frame(&WireMessage::Control(ControlMessage::Probe(probe.clone()))).unwrap();
+        let WireMessage::Control(ControlMessage::Probe(decoded)) = decode_frame(&frame).unwrap()
+        else {
+            panic!("expected a probe");
+        };
+        let latency = decoded.latency(1_070).unwrap();
+        assert_eq!(
+            latency,
+            ProbeLatency {
+                to_relay_ms: 30,
+                in_relay_ms: 1,
+                from_relay_ms: 39,
+            }
+        );
+        assert_eq!(latency.total_ms(), 70);
+        // A receiver whose clock runs behind the relay's sees a negative leg.
+        assert_eq!(decoded.latency(1_020).unwrap().from_relay_ms, -11);
+    }
+
+    #[test]
+    fn error_codes_are_optional_and_unknown_codes_are_kept_apart() {
+        let old: ControlMessage =
+            serde_json::from_str(r#"{"type":"Error","data":{"message":"full"}}"#).unwrap();
+        assert_eq!(
+            old,
+            ControlMessage::Error {
+                message: "full".to_owned(),
+                code: None,
+            }
+        );
+        let newer: ControlMessage = serde_json::from_str(
+            r#"{"type":"Error","data":{"message":"x","code":"room_renamed"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            newer,
+            ControlMessage::Error {
+                code: Some(RelayErrorCode::Unknown),
+                ..
+            }
+        ));
+        let refused = serde_json::to_string(&ControlMessage::Error {
+            message: "no".to_owned(),
+            code: Some(RelayErrorCode::RoomNotAllowed),
+        })
+        .unwrap();
+        assert!(refused.contains(r#""code":"room_not_allowed""#));
+    }
+
     #[test]
     fn replay_rejection() {
         let mut replay_state: HashMap<DeviceId, Counter> = HashMap::new();
@@ -401,6 +998,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn device_id_ignores_surrounding_whitespace_in_the_name() {
+        let id = device_id_from("host-a", "user-a", " Laptop ");
+        assert_eq!(id, device_id_from("host-a", "user-a", "Laptop"));
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, device_id_from("host-a", "user-a", "Desktop"));
+    }
+
     #[test]
     fn nonce_uniqueness() {
         let n1 = build_nonce("device-a", 1);
@@ -0,0 +1,498 @@
+//! Protocol state of one relay connection, without any I/O.
+//!
+//! A [`SessionState`] turns frames read from the relay into
+//! [`SessionEvent`]s and text to send into frames, keeping the room key,
+//! the send counter and the replay window in between.  Clients own the
+//! socket, the clipboard and the UI; everything that must behave the same
+//! on every platform lives here.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use crate::{
+    ClipboardEventPlaintext, ControlMessage, CoreError, Counter, DeviceId, DeviceIdentity,
+    EncryptedPayload, Hello, MIME_FILE_CHUNK_JSON_B64, MIME_FILE_CHUNK_SEALED_JSON_B64,
+    MIME_TEXT_PLAIN, MaintenanceNotice, PeerInfo, ReconnectNotice, WireMessage, chunker,
+    decode_frame, decrypt_event_envelope, derive_room_key, derive_transfer_key, encode_frame,
+    encrypt_clipboard_event, room_id_from_code, validate_counter, verified_signer,
+};
+
+/// Something a client reacts to, produced from a relay frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// The other devices in the room changed.
+    PeersChanged(Vec<PeerInfo>),
+    /// A room key was derived for the current device list; sending works
+    /// from now on.
+    RoomKeyReady,
+    /// A decrypted event from another device.  `verified_signer` is the hex
+    /// public key that signed it, if any signature verified.
+    Clipboard {
+        event: ClipboardEventPlaintext,
+        verified_signer: Option<String>,
+        /// The relay's sequence number for the payload, if it stamped one.
+        relay_seq: Option<u64>,
+        /// Extensions of a version 2 event (see
+        /// [`EventEnvelope`](crate::event_schema::EventEnvelope)); empty for version 1.
+        extensions: BTreeMap<String, serde_json::Value>,
+    },
+    /// The relay refused something, e.g. a full room.
+    RelayError(String),
+    /// The relay will close the connection for maintenance.
+    Maintenance(MaintenanceNotice),
+    /// The relay will close the connection at its maximum age; connect
+    /// again before then.
+    ReconnectSoon(ReconnectNotice),
+}
+
+/// One device's view of its room for the lifetime of a connection.
+#[derive(Debug)]
+pub struct SessionState {
+    room_code: String,
+    local: PeerInfo,
+    identity: Option<Arc<DeviceIdentity>>,
+    /// Ask the relay to forward latency probes (see [`Hello::probes`]).
+    probes: bool,
+    room_key: Option<[u8; 32]>,
+    counter: Counter,
+    last_seen: HashMap<DeviceId, Counter>,
+    peers: Vec<PeerInfo>,
+}
+
+impl SessionState {
+    /// State for `local` joining the room of `room_code`.  `counter` is the
+    /// last counter this device sent with; the next send uses the one after.
+    pub fn new(room_code: &str, local: PeerInfo, counter: Counter) -> Result<Self, CoreError> {
+        if room_code.trim().is_empty() {
+            return Err(CoreError::EmptyRoomCode);
+        }
+        Ok(Self {
+            room_code: room_code.to_owned(),
+            local,
+            identity: None,
+            probes: false,
+            room_key: None,
+            counter,
+            last_seen: HashMap::new(),
+            peers: Vec::new(),
+        })
+    }
+
+    /// Sign every sent event with `identity`.
+    pub fn with_identity(mut self, identity: impl Into<Arc<DeviceIdentity>>) -> Self {
+        self.identity = Some(identity.into());
+        self
+    }
+
+    /// Have the relay forward latency probes to this device.
+    pub fn with_probes(mut self) -> Self {
+        self.probes = true;
+        self
+    }
+
+    /// The message that joins the room; send it first.
+    pub fn hello(&self) -> WireMessage {
+        WireMessage::Control(ControlMessage::Hello(Hello {
+            room_id: room_id_from_code(&self.room_code),
+            peer: self.local.clone(),
+            sequenced: true,
+            probes: self.probes,
+        }))
+    }
+
+    /// [`hello`](Self::hello) as a frame.
+    pub fn hello_frame(&self) -> Result<Vec<u8>, CoreError> {
+        encode_frame(&self.hello())
+    }
+
+    pub fn room_key_ready(&self) -> bool {
+        self.room_key.is_some()
+    }
+
+    /// Whether both sessions derived the same room key, e.g. a test
+    /// connection and the one it checks.
+    pub fn shares_room_key(&self, other: &SessionState) -> bool {
+        self.room_key.is_some() && self.room_key == other.room_key
+    }
+
+    /// Other devices in the room, as last reported by the relay.
+    pub fn peers(&self) -> &[PeerInfo] {
+        &self.peers
+    }
+
+    /// Last counter used for a send, for clients that persist it.
+    pub fn counter(&self) -> Counter {
+        self.counter
+    }
+
+    /// Process one binary frame from the relay.
+    pub fn handle_frame(&mut self, frame: &[u8]) -> Result<Option<SessionEvent>, CoreError> {
+        self.handle_message(decode_frame(frame)?)
+    }
+
+    /// Process one decoded message from the relay.  Returns `Ok(None)` for
+    /// messages that need no reaction.
+    pub fn handle_message(
+        &mut self,
+        message: WireMessage,
+    ) -> Result<Option<SessionEvent>, CoreError> {
+        match message {
+            WireMessage::Control(ControlMessage::PeerList(list)) => {
+                self.peers = list.peers;
+                Ok(Some(self.peers_changed()))
+            }
+            WireMessage::Control(ControlMessage::PeerJoined(joined)) => {
+                self.peers.retain(|p| p.device_id != joined.peer.device_id);
+                self.peers.push(joined.peer);
+                Ok(Some(self.peers_changed()))
+            }
+            WireMessage::Control(ControlMessage::PeerLeft(left)) => {
+                self.peers.retain(|p| p.device_id != left.device_id);
+                Ok(Some(self.peers_changed()))
+            }
+            WireMessage::Control(ControlMessage::SaltExchange(exchange)) => {
+                self.room_key = Some(derive_room_key(&self.room_code, &exchange.device_ids)?);
+                Ok(Some(SessionEvent::RoomKeyReady))
+            }
+            WireMessage::Control(ControlMessage::Error { message, .. }) => {
+                Ok(Some(SessionEvent::RelayError(message)))
+            }
+            WireMessage::Control(ControlMessage::Maintenance(notice)) => {
+                Ok(Some(SessionEvent::Maintenance(notice)))
+            }
+            WireMessage::Control(ControlMessage::Reconnect(notice)) => {
+                Ok(Some(SessionEvent::ReconnectSoon(notice)))
+            }
+            // Probes only reach clients whose Hello asks for them.
+            WireMessage::Control(ControlMessage::Hello(_) | ControlMessage::Probe(_)) => Ok(None),
+            WireMessage::Encrypted(payload) => {
+                let key = self.room_key.ok_or(CoreError::RoomKeyNotReady)?;
+                let relay_seq = payload.relay_seq;
+                let envelope = decrypt_event_envelope(&key, &payload)?;
+                let event = envelope.event;
+                validate_counter(&mut self.last_seen, &event.sender_device_id, event.counter)?;
+                let verified_signer = verified_signer(&event);
+                Ok(Some(SessionEvent::Clipboard {
+                    event,
+                    verified_signer,
+                    relay_seq,
+                    extensions: envelope.extensions,
+                }))
+            }
+        }
+    }
+
+    /// Encrypt `text` as the next event from this device and frame it.
+    pub fn seal_text(&mut self, text: &str, timestamp_unix_ms: u64) -> Result<Vec<u8>, CoreError> {
+        self.seal(MIME_TEXT_PLAIN, text.to_owned(), timestamp_unix_ms)
+    }
+
+    /// Encrypt an event of any MIME type as the next event from this device
+    /// and frame it.  The counter only advances when the frame is built.
+    pub fn seal(
+        &mut self,
+        mime: &str,
+        text_utf8: String,
+        timestamp_unix_ms: u64,
+    ) -> Result<Vec<u8>, CoreError> {
+        let (payload, counter) =
+            self.encrypt_next(mime, text_utf8, timestamp_unix_ms, false, None)?;
+        let frame = encode_frame(&WireMessage::Encrypted(payload))?;
+        self.counter = counter;
+        Ok(frame)
+    }
+
+    /// Encrypt an event as the next event from this device without framing
+    /// it, for clients that queue messages rather than frames.  Unlike
+    /// [`seal`](Self::seal) it can mark the event pinned or labelled.
+    pub fn seal_payload(
+        &mut self,
+        mime: &str,
+        text_utf8: String,
+        timestamp_unix_ms: u64,
+        pinned: bool,
+        label: Option<String>,
+    ) -> Result<EncryptedPayload, CoreError> {
+        let (payload, counter) =
+            self.encrypt_next(mime, text_utf8, timestamp_unix_ms, pinned, label)?;
+        self.counter = counter;
+        Ok(payload)
+    }
+
+    fn encrypt_next(
+        &self,
+        mime: &str,
+        text_utf8: String,
+        timestamp_unix_ms: u64,
+        pinned: bool,
+        label: Option<String>,
+    ) -> Result<(EncryptedPayload, Counter), CoreError> {
+        let key = self.room_key.ok_or(CoreError::RoomKeyNotReady)?;
+        let mut event = ClipboardEventPlaintext {
+            sender_device_id: self.local.device_id.clone(),
+            counter: self.counter + 1,
+            timestamp_unix_ms,
+            mime: mime.to_owned(),
+            text_utf8,
+            pinned,
+            label,
+            signature: None,
+        };
+        if let Some(identity) = &self.identity {
+            identity.sign_event(&mut event);
+        }
+        Ok((encrypt_clipboard_event(&key, &event)?, event.counter))
+    }
+
+    /// Every chunk of a file as sealed frames, ready to send in order.
+    /// The chunks are [`MIME_FILE_CHUNK_SEALED_JSON_B64`] when every other
+    /// device in the room opens those, and plain
+    /// [`MIME_FILE_CHUNK_JSON_B64`] otherwise.
+    pub fn seal_file(
+        &mut self,
+        transfer_id: &str,
+        file_name: &str,
+        data: &[u8],
+        timestamp_unix_ms: u64,
+    ) -> Result<Vec<Vec<u8>>, CoreError> {
+        let key = self.room_key.ok_or(CoreError::RoomKeyNotReady)?;
+        let (mime, chunks) = if self.peers_open_sealed_chunks() {
+            (
+                MIME_FILE_CHUNK_SEALED_JSON_B64,
+                chunker::split_file_sealed(&key, transfer_id, file_name, data)?,
+            )
+        } else {
+            (
+                MIME_FILE_CHUNK_JSON_B64,
+                chunker::split_file(transfer_id, file_name, data)?,
+            )
+        };
+        chunks
+            .iter()
+            .map(|chunk| self.seal(mime, chunk.to_text()?, timestamp_unix_ms))
+            .collect()
+    }
+
+    /// Whether every other device in the room advertised
+    /// [`PeerInfo::sealed_chunks`].
+    pub fn peers_open_sealed_chunks(&self) -> bool {
+        self.peers
+            .iter()
+            .filter(|p| p.device_id != self.local.device_id)
+            .all(|p| p.sealed_chunks)
+    }
+
+    /// Chunk `chunk_index` of `data`, sealed under the key of
+    /// `transfer_id`, for clients that send a file a chunk at a time.
+    pub fn seal_file_chunk(
+        &self,
+        transfer_id: &str,
+        file_name: &str,
+        data: &[u8],
+        chunk_index: u32,
+    ) -> Result<chunker::FileChunkEnvelope, CoreError> {
+        let key = self.room_key.ok_or(CoreError::RoomKeyNotReady)?;
+        chunker::FileChunkEnvelope::sealed_chunk(
+            &derive_transfer_key(&key, transfer_id)?,
+            transfer_id,
+            file_name,
+            data,
+            chunk_index,
+        )
+    }
+
+    /// The file bytes of a chunk received as a
+    /// [`MIME_FILE_CHUNK_SEALED_JSON_B64`] event.
+    pub fn open_file_chunk(
+        &self,
+        chunk: &chunker::FileChunkEnvelope,
+    ) -> Result<Vec<u8>, CoreError> {
+        let key = self.room_key.ok_or(CoreError::RoomKeyNotReady)?;
+        chunk.open(&derive_transfer_key(&key, &chunk.transfer_id)?)
+    }
+
+    fn peers_changed(&self) -> SessionEvent {
+        SessionEvent::PeersChanged(
+            self.peers
+                .iter()
+                .filter(|p| p.device_id != self.local.device_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PeerJoined, PeerLeft, PeerList, SaltExchange};
+
+    fn peer(id: &str) -> PeerInfo {
+        PeerInfo {
+            device_id: id.to_owned(),
+            device_name: id.to_uppercase(),
+            sealed_chunks: true,
+        }
+    }
+
+    fn salt(room_code: &str, ids: &[&str]) -> WireMessage {
+        WireMessage::Control(ControlMessage::SaltExchange(SaltExchange {
+            room_id: room_id_from_code(room_code),
+            device_ids: ids.iter().map(|id| (*id).to_owned()).collect(),
+        }))
+    }
+
+    #[test]
+    fn text_round_trips_between_two_sessions() {
+        let mut a = SessionState::new("room", peer("a"), 0)
+            .unwrap()
+            .with_identity(DeviceIdentity::generate().unwrap());
+        let mut b = SessionState::new("room", peer("b"), 41).unwrap();
+        assert!(matches!(
+            a.seal_text("too early", 1),
+            Err(CoreError::RoomKeyNotReady)
+        ));
+
+        for session in [&mut a, &mut b] {
+            assert_eq!(
+                session.handle_message(salt("room", &["a", "b"])).unwrap(),
+                Some(SessionEvent::RoomKeyReady)
+            );
+        }
+        let frame = a.seal_text("hello", 1).unwrap();
+        assert_eq!(a.counter(), 1);
+        match b.handle_frame(&frame).unwrap() {
+            Some(SessionEvent::Clipboard {
+                event,
+                verified_signer,
+                relay_seq,
+                extensions,
+            }) => {
+                assert_eq!(event.text_utf8, "hello");
+                assert!(verified_signer.is_some());
+                // Not forwarded by a relay, so not stamped.
+                assert_eq!(relay_seq, None);
+                assert!(extensions.is_empty());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        // The same frame again is a replay.
+        assert!(matches!(
+            b.handle_frame(&frame),
+            Err(CoreError::ReplayRejected { .. })
+        ));
+        let reply = b.seal_text("hi", 2).unwrap();
+        assert_eq!(b.counter(), 42);
+        assert!(a.handle_frame(&reply).is_ok());
+    }
+
+    #[test]
+    fn peer_list_excludes_this_device() {
+        let room_id = room_id_from_code("room");
+        let mut s = SessionState::new("room", peer("a"), 0).unwrap();
+        let list = WireMessage::Control(ControlMessage::PeerList(PeerList {
+            room_id: room_id.clone(),
+            peers: vec![peer("a"), peer("b")],
+        }));
+        assert_eq!(
+            s.handle_message(list).unwrap(),
+            Some(SessionEvent::PeersChanged(vec![peer("b")]))
+        );
+        let joined = WireMessage::Control(ControlMessage::PeerJoined(PeerJoined {
+            room_id: room_id.clone(),
+            peer: peer("c"),
+        }));
+        s.handle_message(joined).unwrap();
+        let left = WireMessage::Control(ControlMessage::PeerLeft(PeerLeft {
+            room_id,
+            device_id: "b".to_owned(),
+        }));
+        assert_eq!(
+            s.handle_message(left).unwrap(),
+            Some(SessionEvent::PeersChanged(vec![peer("c")]))
+        );
+    }
+
+    #[test]
+    fn files_go_plain_while_any_peer_cannot_open_sealed_chunks() {
+        let room_id = room_id_from_code("room");
+        let mut s = SessionState::new("room", peer("a"), 0).unwrap();
+        s.handle_message(salt("room", &["a", "b", "c"])).unwrap();
+        let old_peer = PeerInfo {
+            sealed_chunks: false,
+            ..peer("c")
+        };
+        let list = WireMessage::Control(ControlMessage::PeerList(PeerList {
+            room_id: room_id.clone(),
+            peers: vec![peer("a"), peer("b"), old_peer],
+        }));
+        s.handle_message(list).unwrap();
+        let mime_of =
+            |frame: &[u8], receiver: &mut SessionState| match receiver.handle_frame(frame).unwrap()
+            {
+                Some(SessionEvent::Clipboard { event, .. }) => event.mime,
+                other => panic!("unexpected {other:?}"),
+            };
+        let mut b = SessionState::new("room", peer("b"), 0).unwrap();
+        b.handle_message(salt("room", &["a", "b", "c"])).unwrap();
+
+        let frames = s.seal_file("t1", "a.txt", b"data", 1).unwrap();
+        assert_eq!(mime_of(&frames[0], &mut b), MIME_FILE_CHUNK_JSON_B64);
+
+        let left = WireMessage::Control(ControlMessage::PeerLeft(PeerLeft {
+            room_id,
+            device_id: "c".to_owned(),
+        }));
+        s.handle_message(left).unwrap();
+        let frames = s.seal_file("t2", "a.txt", b"data", 1).unwrap();
+        assert_eq!(mime_of(&frames[0], &mut b), MIME_FILE_CHUNK_SEALED_JSON_B64);
+    }
+
+    #[test]
+    fn queued_payloads_and_single_chunks_open_on_the_other_side() {
+        let mut a = SessionState::new("room", peer("a"), 0)
+            .unwrap()
+            .with_probes();
+        let mut b = SessionState::new("room", peer("b"), 0).unwrap();
+        assert!(!a.shares_room_key(&b));
+        match a.hello() {
+            WireMessage::Control(ControlMessage::Hello(hello)) => assert!(hello.probes),
+            other => panic!("unexpected {other:?}"),
+        }
+        for session in [&mut a, &mut b] {
+            session.handle_message(salt("room", &["a", "b"])).unwrap();
+        }
+        assert!(a.shares_room_key(&b));
+
+        let payload = a
+            .seal_payload(
+                MIME_TEXT_PLAIN,
+                "note".to_owned(),
+                1,
+                true,
+                Some("L".to_owned()),
+            )
+            .unwrap();
+        assert_eq!(a.counter(), 1);
+        match b.handle_message(WireMessage::Encrypted(payload)).unwrap() {
+            Some(SessionEvent::Clipboard { event, .. }) => {
+                assert!(event.pinned);
+                assert_eq!(event.label.as_deref(), Some("L"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        let chunk = a.seal_file_chunk("t1", "a.bin", b"data", 0).unwrap();
+        assert_eq!(b.open_file_chunk(&chunk).unwrap(), b"data");
+    }
+
+    #[test]
+    fn empty_room_code_is_refused() {
+        assert!(matches!(
+            SessionState::new("  ", peer("a"), 0),
+            Err(CoreError::EmptyRoomCode)
+        ));
+    }
+}
@@ -0,0 +1,39 @@
+//! Menu-bar ClipRelay client for macOS, and tray client for Linux desktops.
+//!
+//! The relay connection ([`runtime`]) is platform-neutral and drives a
+//! [`cliprelay_core::session::SessionState`], the same session logic the
+//! Windows client's simulated peer uses; only the shell in `main.rs`
+//! differs per platform.
+
+use cliprelay_core::device_id_from;
+
+pub mod oversized;
+
+pub mod runtime;
+
+/// [`device_id_from`] for the current host and user.
+pub fn stable_device_id(device_name: &str) -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown-host".to_owned());
+    let user = std::env::var("USER")
+        .ok()
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "unknown-user".to_owned());
+    device_id_from(&host, &user, device_name)
+}
+
+/// Computer name to show to other devices when none is given.
+pub fn default_client_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "Mac".to_owned()
+            } else {
+                "Linux".to_owned()
+            }
+        })
+}
@@ -0,0 +1,534 @@
+// ─── Platform gate ─────────────────────────────────────────────────────────────
+
+#[cfg(not(all(feature = "tray", any(target_os = "macos", target_os = "linux"))))]
+fn main() {
+    eprintln!(
+        "cliprelay-menubar runs on macOS and Linux, built with the `tray` feature; \
+         use ClipRelay on Windows"
+    );
+}
+
+#[cfg(all(feature = "tray", any(target_os = "macos", target_os = "linux")))]
+fn main() {
+    desktop_client::run();
+}
+
+// ─── macOS / Linux client ──────────────────────────────────────────────────────
+
+#[cfg(all(feature = "tray", any(target_os = "macos", target_os = "linux")))]
+mod desktop_client {
+    use std::{
+        collections::VecDeque,
+        process::Command as Process,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::Duration,
+    };
+
+    use arboard::Clipboard;
+    use clap::Parser;
+    use cliprelay_core::{MAX_CLIPBOARD_TEXT_BYTES, PeerInfo};
+    use cliprelay_menubar::{
+        default_client_name,
+        oversized::{self, OversizedPolicy, Prepared},
+        runtime::{self, Command, ConnectionState, Event, RelayConfig},
+        stable_device_id,
+    };
+    use eframe::egui;
+    use tokio::sync::mpsc;
+    use tracing::{error, warn};
+    use tray_icon::{
+        Icon, TrayIcon, TrayIconBuilder,
+        menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+    };
+
+    /// Received items kept in the window.
+    const MAX_RECEIVED: usize = 20;
+    /// Side of the generated menu-bar icon, in pixels.
+    const ICON_SIZE: u32 = 22;
+    /// Black for the macOS template image; elsewhere a green that shows on
+    /// light and dark panels alike.
+    const ICON_RGB: [u8; 3] = if cfg!(target_os = "macos") {
+        [0, 0, 0]
+    } else {
+        [46, 160, 67]
+    };
+
+    const MENU_SHOW: &str = "show";
+    const MENU_SEND: &str = "send";
+    const MENU_AUTO_APPLY: &str = "auto_apply";
+    const MENU_QUIT: &str = "quit";
+
+    /// One clipboard handle for the whole process.  On Linux the text we
+    /// set is served by this handle, and disappears when it is dropped.
+    static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+    #[derive(Debug, Parser)]
+    #[command(
+        name = "cliprelay-menubar",
+        about = "ClipRelay menu-bar client for macOS and tray client for Linux"
+    )]
+    struct Args {
+        #[arg(long, default_value = "wss://relay.swatto.co.uk/ws")]
+        server_url: String,
+        #[arg(long)]
+        room_code: String,
+        #[arg(long = "client-name", default_value_t = default_client_name())]
+        client_name: String,
+        /// Put received text on the clipboard as soon as it arrives.
+        #[arg(long, default_value_t = false)]
+        auto_apply: bool,
+        /// What Send Clipboard does with text over the size limit: skip it,
+        /// truncate it, or send it as a .txt file.
+        #[arg(long, value_enum, default_value_t = OversizedPolicy::Skip)]
+        oversized_clipboard: OversizedPolicy,
+    }
+
+    struct Received {
+        from: String,
+        text: String,
+    }
+
+    /// What the event thread learns from the runtime, for the window.
+    struct Shared {
+        state: ConnectionState,
+        peers: Vec<PeerInfo>,
+        received: VecDeque<Received>,
+        last_error: Option<String>,
+    }
+
+    pub fn run() {
+        tracing_subscriber::fmt()
+            .with_env_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| "info".into()),
+            )
+            .init();
+        let args = Args::parse();
+        let config = RelayConfig {
+            server_url: args.server_url,
+            room_code: args.room_code,
+            device_id: stable_device_id(&args.client_name),
+            device_name: args.client_name,
+        };
+
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            match tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt.block_on(runtime::run(config, command_rx, event_tx)),
+                Err(err) => error!("failed to start the runtime: {err}"),
+            }
+        });
+
+        let shared = Arc::new(Mutex::new(Shared {
+            state: ConnectionState::Connecting,
+            peers: Vec::new(),
+            received: VecDeque::new(),
+            last_error: None,
+        }));
+        let auto_apply = Arc::new(AtomicBool::new(args.auto_apply));
+        let oversized_policy = args.oversized_clipboard;
+
+        let options = eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default()
+                .with_title("ClipRelay")
+                .with_inner_size([380.0, 420.0])
+                .with_visible(false),
+            ..Default::default()
+        };
+        let result = eframe::run_native(
+            "ClipRelay",
+            options,
+            Box::new(move |cc| {
+                let ctx = cc.egui_ctx.clone();
+                spawn_event_thread(event_rx, shared.clone(), auto_apply.clone(), ctx.clone());
+                Ok(Box::new(MenuBarApp::new(
+                    ctx,
+                    command_tx,
+                    shared,
+                    auto_apply,
+                    oversized_policy,
+                )))
+            }),
+        );
+        if let Err(err) = result {
+            error!("window failed: {err}");
+        }
+    }
+
+    /// Handle runtime events off the UI thread, so that notifications and
+    /// auto-apply work while the window is hidden.
+    fn spawn_event_thread(
+        mut events: mpsc::UnboundedReceiver<Event>,
+        shared: Arc<Mutex<Shared>>,
+        auto_apply: Arc<AtomicBool>,
+        ctx: egui::Context,
+    ) {
+        std::thread::spawn(move || {
+            while let Some(event) = events.blocking_recv() {
+                let Ok(mut shared) = shared.lock() else {
+                    return;
+                };
+                match event {
+                    Event::State(state) => shared.state = state,
+                    Event::Peers(peers) => shared.peers = peers,
+                    Event::SendFailed(reason) => {
+                        shared.last_error = Some(format!("Not sent: {reason}"));
+                    }
+                    Event::Notice(message) => show_notification("ClipRelay", &message),
+                    Event::Received { from, text } => {
+                        let applied = auto_apply.load(Ordering::Relaxed) && apply_text(&text);
+                        let title = if applied {
+                            format!("Copied from {from}")
+                        } else {
+                            format!("From {from}")
+                        };
+                        show_notification(&title, &text);
+                        shared.received.push_front(Received { from, text });
+                        shared.received.truncate(MAX_RECEIVED);
+                    }
+                }
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    fn with_clipboard<T>(
+        f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, arboard::Error> {
+        let mut slot = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+        let clipboard = match slot.as_mut() {
+            Some(clipboard) => clipboard,
+            None => slot.insert(Clipboard::new()?),
+        };
+        f(clipboard)
+    }
+
+    fn apply_text(text: &str) -> bool {
+        match with_clipboard(|c| c.set_text(text.to_owned())) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("clipboard write failed: {err}");
+                false
+            }
+        }
+    }
+
+    /// Post a Notification Center banner through AppleScript, which needs
+    /// no app bundle or entitlement.
+    #[cfg(target_os = "macos")]
+    fn show_notification(title: &str, body: &str) {
+        let preview: String = body.chars().take(200).collect();
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(&preview),
+            applescript_string(title)
+        );
+        if let Err(err) = Process::new("osascript").arg("-e").arg(script).spawn() {
+            warn!("notification failed: {err}");
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn applescript_string(s: &str) -> String {
+        format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    /// Post a desktop notification through `notify-send` (libnotify), which
+    /// every freedesktop notification server understands.
+    #[cfg(target_os = "linux")]
+    fn show_notification(title: &str, body: &str) {
+        let preview: String = body.chars().take(200).collect();
+        let spawned = Process::new("notify-send")
+            .args(["--app-name=ClipRelay", "--", title, &preview])
+            .spawn();
+        if let Err(err) = spawned {
+            warn!("notification failed (is notify-send installed?): {err}");
+        }
+    }
+
+    /// Ring with a dot.  macOS draws it as a template image, tinted for
+    /// light and dark menu bars.
+    fn menu_bar_icon() -> Option<Icon> {
+        let size = ICON_SIZE as i32;
+        let centre = (size - 1) as f32 / 2.0;
+        let radius = centre - 2.0;
+        let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let d = ((x as f32 - centre).powi(2) + (y as f32 - centre).powi(2)).sqrt();
+                let ring = (d - radius).abs() <= 1.5 || d <= radius * 0.35;
+                let [r, g, b] = ICON_RGB;
+                rgba.extend_from_slice(&[r, g, b, if ring { 255 } else { 0 }]);
+            }
+        }
+        Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE)
+            .map_err(|err| warn!("menu-bar icon failed: {err}"))
+            .ok()
+    }
+
+    /// The status item and its menu: Show, Send Clipboard, Auto-apply,
+    /// Quit.  `None` when the platform refused it.
+    fn build_tray(auto_apply: bool) -> Option<TrayIcon> {
+        let show = MenuItem::with_id(MENU_SHOW, "Show ClipRelay", true, None);
+        let send = MenuItem::with_id(MENU_SEND, "Send Clipboard", true, None);
+        let apply = CheckMenuItem::with_id(
+            MENU_AUTO_APPLY,
+            "Auto-apply Received Text",
+            true,
+            auto_apply,
+            None,
+        );
+        let quit = MenuItem::with_id(MENU_QUIT, "Quit ClipRelay", true, None);
+        let menu = Menu::new();
+        let built = menu.append_items(&[
+            &show,
+            &send,
+            &PredefinedMenuItem::separator(),
+            &apply,
+            &PredefinedMenuItem::separator(),
+            &quit,
+        ]);
+        if let Err(err) = built {
+            warn!("tray menu failed: {err}");
+        }
+
+        let tray = TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_tooltip("ClipRelay")
+            .with_icon_as_template(true);
+        let tray = match menu_bar_icon() {
+            Some(icon) => tray.with_icon(icon),
+            None => tray.with_title("CR"),
+        };
+        tray.build()
+            .map_err(|err| warn!("tray icon failed: {err}"))
+            .ok()
+    }
+
+    /// Send clipboard text, applying `policy` when it is over the limit and
+    /// saying what happened to it.
+    fn send_clipboard_text(
+        text: String,
+        policy: OversizedPolicy,
+        commands: &mpsc::UnboundedSender<Command>,
+    ) {
+        let limit = oversized::format_size(MAX_CLIPBOARD_TEXT_BYTES);
+        let now_unix_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let command = match oversized::prepare(text, policy, now_unix_ms) {
+            Prepared::Text(text) => Command::SendText(text),
+            Prepared::Truncated {
+                text,
+                original_bytes,
+            } => {
+                show_notification(
+                    "Clipboard truncated",
+                    &format!(
+                        "The clipboard held {}; only the first {limit} was sent.",
+                        oversized::format_size(original_bytes)
+                    ),
+                );
+                Command::SendText(text)
+            }
+            Prepared::File { file_name, data } => {
+                show_notification(
+                    "Clipboard sent as a file",
+                    &format!(
+                        "The clipboard held {}, over the {limit} text limit; sent as {file_name}.",
+                        oversized::format_size(data.len())
+                    ),
+                );
+                Command::SendFile { file_name, data }
+            }
+            Prepared::Skipped { bytes } => {
+                show_notification(
+                    "Clipboard not sent",
+                    &format!(
+                        "The clipboard holds {}, over the {limit} limit. Start ClipRelay with \
+                         --oversized-clipboard truncate or file to send it anyway.",
+                        oversized::format_size(bytes)
+                    ),
+                );
+                return;
+            }
+        };
+        let _ = commands.send(command);
+    }
+
+    struct MenuBarApp {
+        commands: mpsc::UnboundedSender<Command>,
+        shared: Arc<Mutex<Shared>>,
+        send_text: String,
+        quitting: Arc<AtomicBool>,
+        // Dropping the icon removes it from the menu bar.  Always `None` on
+        // Linux, where the GTK thread owns it.
+        _tray: Option<TrayIcon>,
+    }
+
+    impl MenuBarApp {
+        fn new(
+            ctx: egui::Context,
+            commands: mpsc::UnboundedSender<Command>,
+            shared: Arc<Mutex<Shared>>,
+            auto_apply: Arc<AtomicBool>,
+            oversized_policy: OversizedPolicy,
+        ) -> Self {
+            let quitting = Arc::new(AtomicBool::new(false));
+            let initial_auto_apply = auto_apply.load(Ordering::Relaxed);
+
+            // Menu events are handled here rather than in `update`, which
+            // does not run while the window is hidden.
+            let handler_ctx = ctx.clone();
+            let handler_commands = commands.clone();
+            let handler_quitting = quitting.clone();
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                match event.id.0.as_str() {
+                    MENU_SHOW => {
+                        handler_ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                        handler_ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                    }
+                    MENU_SEND => match with_clipboard(|c| c.get_text()) {
+                        Ok(text) if !text.is_empty() => {
+                            send_clipboard_text(text, oversized_policy, &handler_commands);
+                        }
+                        Ok(_) => {}
+                        Err(err) => warn!("clipboard read failed: {err}"),
+                    },
+                    MENU_AUTO_APPLY => {
+                        auto_apply.fetch_xor(true, Ordering::Relaxed);
+                    }
+                    MENU_QUIT => {
+                        handler_quitting.store(true, Ordering::Relaxed);
+                        handler_ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                    _ => {}
+                }
+                handler_ctx.request_repaint();
+            }));
+
+            // The status item must be created on the thread that runs the
+            // menu bar's event loop: the main thread on macOS.
+            #[cfg(target_os = "macos")]
+            let tray = build_tray(initial_auto_apply);
+            // On Linux the indicator lives in GTK, which gets a thread and
+            // main loop of its own beside the window's event loop.
+            #[cfg(target_os = "linux")]
+            let tray = {
+                let spawned =
+                    std::thread::Builder::new()
+                        .name("gtk-tray".into())
+                        .spawn(move || {
+                            if let Err(err) = gtk::init() {
+                                warn!("GTK failed to start, no tray icon: {err}");
+                                return;
+                            }
+                            let _tray = build_tray(initial_auto_apply);
+                            gtk::main();
+                        });
+                if let Err(err) = spawned {
+                    warn!("tray thread failed to start: {err}");
+                }
+                None
+            };
+
+            Self {
+                commands,
+                shared,
+                send_text: String::new(),
+                quitting,
+                _tray: tray,
+            }
+        }
+    }
+
+    impl eframe::App for MenuBarApp {
+        fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+            // Closing the window hides it; the app lives in the menu bar
+            // (or tray).
+            if ctx.input(|i| i.viewport().close_requested())
+                && !self.quitting.load(Ordering::Relaxed)
+            {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+            }
+
+            let Ok(mut shared) = self.shared.lock() else {
+                return;
+            };
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.label(shared.state.label());
+                if shared.peers.is_empty() {
+                    ui.label(egui::RichText::new("No other devices in the room").weak());
+                } else {
+                    let names: Vec<&str> = shared
+                        .peers
+                        .iter()
+                        .map(|p| p.device_name.as_str())
+                        .collect();
+                    ui.label(format!("Devices: {}", names.join(", ")));
+                }
+                ui.separator();
+
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.send_text)
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY)
+                        .hint_text("Text to send"),
+                );
+                let ready = shared.state == ConnectionState::Ready;
+                let fits = self.send_text.len() <= MAX_CLIPBOARD_TEXT_BYTES;
+                if ui
+                    .add_enabled(
+                        ready && fits && !self.send_text.is_empty(),
+                        egui::Button::new("Send"),
+                    )
+                    .clicked()
+                {
+                    let text = std::mem::take(&mut self.send_text);
+                    let _ = self.commands.send(Command::SendText(text));
+                    shared.last_error = None;
+                }
+                if !fits {
+                    ui.colored_label(egui::Color32::RED, "Text is too large to send");
+                }
+                if let Some(err) = &shared.last_error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+                ui.separator();
+
+                ui.heading("Received");
+                let mut remove = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (idx, item) in shared.received.iter().enumerate() {
+                        ui.label(egui::RichText::new(&item.from).strong());
+                        let preview: String = item.text.chars().take(300).collect();
+                        ui.label(preview);
+                        ui.horizontal(|ui| {
+                            if ui.button("Apply").clicked() {
+                                apply_text(&item.text);
+                            }
+                            if ui.button("Dismiss").clicked() {
+                                remove = Some(idx);
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+                if let Some(idx) = remove {
+                    shared.received.remove(idx);
+                }
+            });
+            // Keep the status line current while the window is open.
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Clipboard text over [`MAX_CLIPBOARD_TEXT_BYTES`]: what "Send Clipboard"
+//! does with it instead of failing quietly.
+
+use cliprelay_core::MAX_CLIPBOARD_TEXT_BYTES;
+
+/// Appended to truncated text; `{sent}` and `{total}` are byte counts.
+const TRUNCATION_MARKER: &str = "\n[… truncated by ClipRelay: {sent} of {total} bytes sent]";
+
+/// What to do with clipboard text too large for one event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OversizedPolicy {
+    /// Send nothing and say so.
+    #[default]
+    Skip,
+    /// Send the start of the text, with a marker saying it was cut.
+    Truncate,
+    /// Send the whole text as a `.txt` file transfer.
+    File,
+}
+
+/// Clipboard text ready to send, or the reason it is not sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prepared {
+    /// Fits as it is.
+    Text(String),
+    /// Cut to fit; `original_bytes` is the size before.
+    Truncated {
+        text: String,
+        original_bytes: usize,
+    },
+    File {
+        file_name: String,
+        data: Vec<u8>,
+    },
+    Skipped {
+        bytes: usize,
+    },
+}
+
+/// Apply `policy` to clipboard `text`.  Text within the limit is always
+/// sent as it is.
+pub fn prepare(text: String, policy: OversizedPolicy, now_unix_ms: u64) -> Prepared {
+    if text.len() <= MAX_CLIPBOARD_TEXT_BYTES {
+        return Prepared::Text(text);
+    }
+    match policy {
+        OversizedPolicy::Skip => Prepared::Skipped { bytes: text.len() },
+        OversizedPolicy::Truncate => Prepared::Truncated {
+            original_bytes: text.len(),
+            text: truncate_with_marker(&text, MAX_CLIPBOARD_TEXT_BYTES),
+        },
+        OversizedPolicy::File => Prepared::File {
+            file_name: format!("clipboard-{now_unix_ms}.txt"),
+            data: text.into_bytes(),
+        },
+    }
+}
+
+/// The longest start of `text` that, with the truncation marker, fits in
+/// `limit` bytes; cut on a character boundary, and on a line break when
+/// one is near the end.
+pub fn truncate_with_marker(text: &str, limit: usize) -> String {
+    if text.len() <= limit {
+        return text.to_owned();
+    }
+    // The marker's digits never exceed those of the full length.
+    let marker_room = TRUNCATION_MARKER.len() + 2 * text.len().to_string().len();
+    let mut end = limit.saturating_sub(marker_room).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    // Prefer a whole last line unless that throws away too much.
+    if let Some(newline) = text[..end].rfind('\n')
+        && newline >= end - end / 16
+    {
+        end = newline;
+    }
+    let marker = TRUNCATION_MARKER
+        .replace("{sent}", &end.to_string())
+        .replace("{total}", &text.len().to_string());
+    format!("{}{marker}", &text[..end])
+}
+
+/// `bytes` as a short size for messages, e.g. "2.0 MB".
+pub fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= KB * KB {
+        format!("{:.1} MB", bytes / (KB * KB))
+    } else {
+        format!("{:.0} KB", bytes / KB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_text_is_sent_whatever_the_policy() {
+        for policy in [
+            OversizedPolicy::Skip,
+            OversizedPolicy::Truncate,
+            OversizedPolicy::File,
+        ] {
+            assert_eq!(
+                prepare("hi".to_owned(), policy, 1),
+                Prepared::Text("hi".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn oversized_text_follows_the_policy() {
+        let big = "é".repeat(MAX_CLIPBOARD_TEXT_BYTES);
+        assert_eq!(
+            prepare(big.clone(), OversizedPolicy::Skip, 1),
+            Prepared::Skipped { bytes: big.len() }
+        );
+        assert_eq!(
+            prepare(big.clone(), OversizedPolicy::File, 7),
+            Prepared::File {
+                file_name: "clipboard-7.txt".to_owned(),
+                data: big.clone().into_bytes(),
+            }
+        );
+        let Prepared::Truncated {
+            text,
+            original_bytes,
+        } = prepare(big.clone(), OversizedPolicy::Truncate, 1)
+        else {
+            panic!("expected truncated text");
+        };
+        assert_eq!(original_bytes, big.len());
+        assert!(text.len() <= MAX_CLIPBOARD_TEXT_BYTES);
+        assert!(text.ends_with(&format!("of {} bytes sent]", big.len())));
+    }
+
+    #[test]
+    fn truncation_prefers_a_line_break_near_the_end() {
+        let text = format!("{}\n{}", "a".repeat(190), "b".repeat(200));
+        let cut = truncate_with_marker(&text, 256);
+        assert!(cut.starts_with(&"a".repeat(190)));
+        assert!(cut.len() <= 256);
+
+        let lines = format!("{}\n{}", "a".repeat(20), "b".repeat(400));
+        let cut = truncate_with_marker(&lines, 256);
+        assert!(cut.contains('b'), "a line break far back is not used");
+    }
+}
@@ -0,0 +1,273 @@
+//! The relay connection: joins the room, reconnects when the connection
+//! drops, and exchanges text with the UI over channels.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cliprelay_core::{
+    MIME_TEXT_PLAIN, PeerInfo,
+    session::{SessionEvent, SessionState},
+};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{debug, info, warn};
+
+/// Wait before reconnecting after the connection drops.
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+/// Gap between the chunks of a file, to stay under the relay's rate limit.
+const CHUNK_PACING: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone)]
+pub struct RelayConfig {
+    pub server_url: String,
+    pub room_code: String,
+    pub device_id: String,
+    pub device_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    /// Joined the room; waiting for the room key.
+    Connected,
+    /// The room key is ready: text can be sent and received.
+    Ready,
+    /// The connection dropped or could not be made; retrying shortly.
+    Disconnected(String),
+}
+
+impl ConnectionState {
+    pub fn label(&self) -> String {
+        match self {
+            ConnectionState::Connecting => "Connecting…".to_owned(),
+            ConnectionState::Connected => "Connected, waiting for room key".to_owned(),
+            ConnectionState::Ready => "Connected".to_owned(),
+            ConnectionState::Disconnected(reason) => format!("Disconnected: {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    SendText(String),
+    /// Send `data` as a file transfer, the way the Windows client sends
+    /// files.
+    SendFile {
+        file_name: String,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    State(ConnectionState),
+    /// The other devices in the room.
+    Peers(Vec<PeerInfo>),
+    /// Text from another device, with the name it goes by.
+    Received {
+        from: String,
+        text: String,
+    },
+    /// A send could not go out; the text was not delivered.
+    SendFailed(String),
+    /// Something the user should hear about, e.g. relay maintenance.
+    Notice(String),
+}
+
+/// Why one connection ended.
+enum SessionEnd {
+    /// The UI dropped its side of a channel: stop for good.
+    Shutdown,
+    Dropped(String),
+    /// The relay asked for a new connection; made at once.
+    Reconnect,
+}
+
+/// Keep a connection to the room until the UI drops `commands` or
+/// `events`.
+pub async fn run(
+    config: RelayConfig,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    events: mpsc::UnboundedSender<Event>,
+) {
+    // Seeded from the clock so counters keep rising across restarts
+    // without being stored.
+    let mut counter = now_unix_ms();
+    loop {
+        if events
+            .send(Event::State(ConnectionState::Connecting))
+            .is_err()
+        {
+            return;
+        }
+        let reason = match session(&config, &mut commands, &events, &mut counter).await {
+            SessionEnd::Shutdown => return,
+            SessionEnd::Dropped(reason) => reason,
+            SessionEnd::Reconnect => {
+                info!("reconnecting as the relay asked");
+                continue;
+            }
+        };
+        info!("relay connection ended: {reason}");
+        if events
+            .send(Event::State(ConnectionState::Disconnected(reason)))
+            .is_err()
+        {
+            return;
+        }
+        let retry = tokio::time::sleep(RECONNECT_DELAY);
+        tokio::pin!(retry);
+        loop {
+            tokio::select! {
+                _ = &mut retry => break,
+                command = commands.recv() => match command {
+                    None => return,
+                    Some(_) => {
+                        let _ = events.send(Event::SendFailed("not connected".to_owned()));
+                    }
+                },
+            }
+        }
+    }
+}
+
+async fn session(
+    config: &RelayConfig,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+    events: &mpsc::UnboundedSender<Event>,
+    counter: &mut u64,
+) -> SessionEnd {
+    let local = PeerInfo {
+        device_id: config.device_id.clone(),
+        device_name: config.device_name.clone(),
+        // Files are only sent from here, never received.
+        sealed_chunks: false,
+    };
+    let mut state = match SessionState::new(&config.room_code, local, *counter) {
+        Ok(state) => state,
+        Err(err) => return SessionEnd::Dropped(err.to_string()),
+    };
+    let (ws_stream, _) = match connect_async(&config.server_url).await {
+        Ok(ok) => ok,
+        Err(err) => return SessionEnd::Dropped(err.to_string()),
+    };
+    let (mut ws_write, mut ws_read) = ws_stream.split();
+    let hello = match state.hello_frame() {
+        Ok(frame) => frame,
+        Err(err) => return SessionEnd::Dropped(err.to_string()),
+    };
+    if let Err(err) = ws_write.send(Message::Binary(hello.into())).await {
+        return SessionEnd::Dropped(err.to_string());
+    }
+    if events
+        .send(Event::State(ConnectionState::Connected))
+        .is_err()
+    {
+        return SessionEnd::Shutdown;
+    }
+
+    loop {
+        tokio::select! {
+            next = ws_read.next() => {
+                let data = match next {
+                    None => return SessionEnd::Dropped("closed by the relay".to_owned()),
+                    Some(Err(err)) => return SessionEnd::Dropped(err.to_string()),
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(_)) => continue,
+                };
+                let event = match state.handle_frame(&data) {
+                    Ok(Some(SessionEvent::RoomKeyReady)) => {
+                        Event::State(ConnectionState::Ready)
+                    }
+                    Ok(Some(SessionEvent::PeersChanged(peers))) => Event::Peers(peers),
+                    Ok(Some(SessionEvent::Clipboard { event, .. })) => {
+                        if event.mime != MIME_TEXT_PLAIN {
+                            debug!(mime = %event.mime, "ignoring unsupported event type");
+                            continue;
+                        }
+                        let from = state
+                            .peers()
+                            .iter()
+                            .find(|p| p.device_id == event.sender_device_id)
+                            .map(|p| p.device_name.clone())
+                            .unwrap_or(event.sender_device_id);
+                        Event::Received {
+                            from,
+                            text: event.text_utf8,
+                        }
+                    }
+                    Ok(Some(SessionEvent::RelayError(message))) => {
+                        return SessionEnd::Dropped(message);
+                    }
+                    Ok(Some(SessionEvent::Maintenance(notice))) => {
+                        Event::Notice(notice.describe(now_unix_ms()))
+                    }
+                    // Sends are written before the next frame is read, so
+                    // nothing is left to flush.
+                    Ok(Some(SessionEvent::ReconnectSoon(_))) => return SessionEnd::Reconnect,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!("dropping frame from relay: {err}");
+                        continue;
+                    }
+                };
+                if events.send(event).is_err() {
+                    return SessionEnd::Shutdown;
+                }
+            }
+            command = commands.recv() => {
+                let frames = match command {
+                    None => return SessionEnd::Shutdown,
+                    Some(Command::SendText(text)) => {
+                        state.seal_text(&text, now_unix_ms()).map(|frame| vec![frame])
+                    }
+                    Some(Command::SendFile { file_name, data }) => {
+                        seal_file(&mut state, &file_name, &data)
+                    }
+                };
+                let frames = match frames {
+                    Ok(frames) => frames,
+                    Err(err) => {
+                        if events.send(Event::SendFailed(err.to_string())).is_err() {
+                            return SessionEnd::Shutdown;
+                        }
+                        continue;
+                    }
+                };
+                *counter = state.counter();
+                let paced = frames.len() > 1;
+                for frame in frames {
+                    if let Err(err) = ws_write.send(Message::Binary(frame.into())).await {
+                        let _ = events.send(Event::SendFailed(err.to_string()));
+                        return SessionEnd::Dropped(err.to_string());
+                    }
+                    if paced {
+                        tokio::time::sleep(CHUNK_PACING).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Every chunk of a file as sealed frames, ready to send in order.
+fn seal_file(
+    state: &mut SessionState,
+    file_name: &str,
+    data: &[u8],
+) -> Result<Vec<Vec<u8>>, cliprelay_core::CoreError> {
+    // Random: the chunk nonces are only unique while each transfer id
+    // seals a single file.
+    let transfer_id: String = rand::random::<[u8; 16]>()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    state.seal_file(&transfer_id, file_name, data, now_unix_ms())
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
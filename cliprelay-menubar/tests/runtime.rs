@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use cliprelay_core::{
+    MIME_FILE_CHUNK_SEALED_JSON_B64, PeerInfo,
+    chunker::FileChunkEnvelope,
+    session::{SessionEvent, SessionState},
+};
+use cliprelay_menubar::runtime::{self, Command, ConnectionState, Event, RelayConfig};
+use cliprelay_relay::{AppState, serve};
+use futures::{SinkExt, StreamExt};
+use tokio::{net::TcpListener, sync::mpsc, time::timeout};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Client {
+    commands: mpsc::UnboundedSender<Command>,
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+async fn start_relay() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind ephemeral relay socket");
+    let address = listener.local_addr().expect("relay local addr");
+    tokio::spawn(serve(listener, AppState::new()));
+    format!("ws://{address}/ws")
+}
+
+fn start_client(server_url: &str, device_id: &str, device_name: &str) -> Client {
+    let (command_tx, command_rx) = mpsc::unbounded_channel();
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let config = RelayConfig {
+        server_url: server_url.to_owned(),
+        room_code: "menubar-room".to_owned(),
+        device_id: device_id.to_owned(),
+        device_name: device_name.to_owned(),
+    };
+    tokio::spawn(runtime::run(config, command_rx, event_tx));
+    Client {
+        commands: command_tx,
+        events: event_rx,
+    }
+}
+
+/// Read events until one matches `wanted`.
+async fn wait_for(client: &mut Client, wanted: impl Fn(&Event) -> bool) -> Event {
+    timeout(RECV_TIMEOUT, async {
+        loop {
+            let event = client.events.recv().await.expect("runtime stopped");
+            if wanted(&event) {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("expected event did not arrive")
+}
+
+async fn wait_until_ready_with_peer(client: &mut Client) {
+    wait_for(
+        client,
+        |e| matches!(e, Event::Peers(peers) if !peers.is_empty()),
+    )
+    .await;
+    wait_for(client, |e| *e == Event::State(ConnectionState::Ready)).await;
+}
+
+#[tokio::test]
+async fn text_sent_by_one_client_reaches_the_other() {
+    let server_url = start_relay().await;
+    let mut a = start_client(&server_url, "dev-a", "Device A");
+    wait_for(&mut a, |e| *e == Event::State(ConnectionState::Ready)).await;
+    let mut b = start_client(&server_url, "dev-b", "Device B");
+    wait_until_ready_with_peer(&mut a).await;
+    wait_until_ready_with_peer(&mut b).await;
+
+    a.commands
+        .send(Command::SendText("hello from a".to_owned()))
+        .unwrap();
+    let received = wait_for(&mut b, |e| matches!(e, Event::Received { .. })).await;
+    assert_eq!(
+        received,
+        Event::Received {
+            from: "Device A".to_owned(),
+            text: "hello from a".to_owned(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn sending_without_a_relay_reports_failure() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let mut client = start_client(&format!("ws://{address}/ws"), "dev-a", "Device A");
+    wait_for(&mut client, |e| {
+        matches!(e, Event::State(ConnectionState::Disconnected(_)))
+    })
+    .await;
+
+    client
+        .commands
+        .send(Command::SendText("lost".to_owned()))
+        .unwrap();
+    let failed = wait_for(&mut client, |e| matches!(e, Event::SendFailed(_))).await;
+    assert_eq!(failed, Event::SendFailed("not connected".to_owned()));
+}
+
+#[tokio::test]
+async fn a_file_arrives_as_chunks_another_client_can_reassemble() {
+    let server_url = start_relay().await;
+    let mut a = start_client(&server_url, "dev-a", "Device A");
+    wait_for(&mut a, |e| *e == Event::State(ConnectionState::Ready)).await;
+
+    // The receiving side is a bare session, as the Windows client would
+    // run it, so that the chunk events themselves can be checked.
+    let local = PeerInfo {
+        device_id: "dev-b".to_owned(),
+        device_name: "Device B".to_owned(),
+        sealed_chunks: true,
+    };
+    let mut session = SessionState::new("menubar-room", local, 0).unwrap();
+    let (mut ws, _) = connect_async(&server_url).await.unwrap();
+    ws.send(Message::Binary(session.hello_frame().unwrap().into()))
+        .await
+        .unwrap();
+    wait_until_ready_with_peer(&mut a).await;
+
+    let data: Vec<u8> = (0..cliprelay_core::chunker::FILE_CHUNK_RAW_BYTES + 100)
+        .map(|i| i as u8)
+        .collect();
+    a.commands
+        .send(Command::SendFile {
+            file_name: "clip.txt".to_owned(),
+            data: data.clone(),
+        })
+        .unwrap();
+
+    let mut received = Vec::new();
+    timeout(RECV_TIMEOUT, async {
+        loop {
+            let Some(Ok(Message::Binary(frame))) = ws.next().await else {
+                continue;
+            };
+            if let Ok(Some(SessionEvent::Clipboard { event, .. })) = session.handle_frame(&frame) {
+                assert_eq!(event.mime, MIME_FILE_CHUNK_SEALED_JSON_B64);
+                let chunk = FileChunkEnvelope::parse(&event.text_utf8).unwrap();
+                assert_eq!(chunk.file_name, "clip.txt");
+                received.extend(session.open_file_chunk(&chunk).unwrap());
+                if chunk.chunk_index + 1 == chunk.total_chunks {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+    .expect("file chunks did not arrive");
+    assert_eq!(received, data);
+}
@@ -0,0 +1,184 @@
+//! Rooms a relay is limited to.
+//!
+//! Without an allow-list every room is served.  With one, a Hello for any
+//! other room is answered with a `room_not_allowed` error and the
+//! connection is closed.  The list comes from the command line or from a
+//! file of room ids, one per line (`#` starts a comment), which is read
+//! again when it changes.  Entries added or removed through the admin API
+//! are written back to that file through a `.tmp` file and a rename, so a
+//! crash mid-write never leaves it half written.  Comment lines and the
+//! lines of rooms still listed are kept as they were; a removed room's
+//! line goes, comment and all, and added rooms are appended.  Removing a room refuses new connections
+//! to it; devices already in it stay until they reconnect.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use cliprelay_core::RoomId;
+use tracing::{info, warn};
+
+/// How often the file's modification time is looked at, at most.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub(crate) struct RoomAllowList {
+    rooms: BTreeSet<RoomId>,
+    file: Option<WatchedFile>,
+}
+
+#[derive(Debug)]
+struct WatchedFile {
+    path: PathBuf,
+    /// The file's lines as last read or written, rewritten on save.
+    lines: Vec<String>,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl RoomAllowList {
+    pub(crate) fn new(rooms: impl IntoIterator<Item = RoomId>) -> Self {
+        Self {
+            rooms: rooms
+                .into_iter()
+                .map(|room| room.trim().to_owned())
+                .collect(),
+            file: None,
+        }
+    }
+
+    pub(crate) fn from_file(path: PathBuf) -> Result<Self, String> {
+        let (lines, modified) = read_file(&path)?;
+        Ok(Self {
+            rooms: rooms_in(&lines),
+            file: Some(WatchedFile {
+                path,
+                lines,
+                modified,
+                checked: Instant::now(),
+            }),
+        })
+    }
+
+    /// Whether `room_id` is served, after reloading the file if it changed.
+    pub(crate) fn allows(&mut self, room_id: &RoomId) -> bool {
+        self.reload_if_changed();
+        self.rooms.contains(room_id)
+    }
+
+    pub(crate) fn rooms(&mut self) -> Vec<RoomId> {
+        self.reload_if_changed();
+        self.rooms.iter().cloned().collect()
+    }
+
+    /// Add `room_id`; `Ok(false)` if it was already listed.
+    pub(crate) fn add(&mut self, room_id: RoomId) -> Result<bool, String> {
+        self.reload_if_changed();
+        let added = self.rooms.insert(room_id);
+        if added {
+            self.save()?;
+        }
+        Ok(added)
+    }
+
+    /// Remove `room_id`; `Ok(false)` if it was not listed.
+    pub(crate) fn remove(&mut self, room_id: &RoomId) -> Result<bool, String> {
+        self.reload_if_changed();
+        let removed = self.rooms.remove(room_id);
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    fn reload_if_changed(&mut self) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        if file.checked.elapsed() < RELOAD_CHECK_INTERVAL {
+            return;
+        }
+        file.checked = Instant::now();
+        let modified = std::fs::metadata(&file.path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if modified == file.modified {
+            return;
+        }
+        match read_file(&file.path) {
+            Ok((lines, modified)) => {
+                let rooms = rooms_in(&lines);
+                info!(
+                    "reloaded room allow-list {}: {} rooms",
+                    file.path.display(),
+                    rooms.len()
+                );
+                self.rooms = rooms;
+                file.lines = lines;
+                file.modified = modified;
+            }
+            // The last list read stays in force.
+            Err(err) => warn!("{}", err),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let Some(file) = &mut self.file else {
+            return Ok(());
+        };
+        let mut written = BTreeSet::new();
+        let mut lines: Vec<String> = file
+            .lines
+            .iter()
+            .filter(|line| match room_on(line) {
+                None => true,
+                Some(room) => self.rooms.contains(room) && written.insert(room.to_owned()),
+            })
+            .cloned()
+            .collect();
+        lines.extend(self.rooms.difference(&written).cloned());
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+
+        let mut tmp = file.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        std::fs::write(&tmp, contents)
+            .map_err(|err| format!("failed to write {}: {err}", tmp.display()))?;
+        std::fs::rename(&tmp, &file.path).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            format!("failed to replace {}: {err}", file.path.display())
+        })?;
+        file.lines = lines;
+        file.modified = std::fs::metadata(&file.path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        Ok(())
+    }
+}
+
+fn read_file(path: &Path) -> Result<(Vec<String>, Option<SystemTime>), String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read room allow-list {}: {err}", path.display()))?;
+    let modified = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok();
+    let lines = contents.lines().map(str::to_owned).collect();
+    Ok((lines, modified))
+}
+
+fn rooms_in(lines: &[String]) -> BTreeSet<RoomId> {
+    lines
+        .iter()
+        .filter_map(|line| room_on(line))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The room id on `line`, or `None` for a blank or comment-only line.
+fn room_on(line: &str) -> Option<&str> {
+    let room = line.split('#').next().unwrap_or_default().trim();
+    (!room.is_empty()).then_some(room)
+}
@@ -1,48 +1,300 @@
-use std::{collections::HashMap, sync::Arc, time::Duration, time::Instant};
+mod allowlist;
+
+mod lifetime;
+
+#[cfg(feature = "sled-store")]
+mod sled_store;
+
+mod store;
+
+mod strikes;
+
+mod webhooks;
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
-    Json, Router,
-    extract::{State, WebSocketUpgrade, ws::Message},
-    response::IntoResponse,
-    routing::get,
+    Extension, Json, Router,
+    extract::{
+        ConnectInfo, State, WebSocketUpgrade,
+        ws::{CloseFrame, Message, close_code},
+    },
+    http::{
+        HeaderMap, StatusCode,
+        header::{AUTHORIZATION, RETRY_AFTER},
+    },
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
 };
 use cliprelay_core::{
-    ControlMessage, DeviceId, Hello, MAX_DEVICES_PER_ROOM, MAX_RELAY_MESSAGE_BYTES, PeerInfo,
-    PeerJoined, PeerLeft, PeerList, RoomId, SaltExchange, WireMessage, decode_frame, encode_frame,
+    ControlMessage, DeviceId, Hello, LatencyProbe, MAX_DEVICES_PER_ROOM, MAX_RELAY_MESSAGE_BYTES,
+    MaintenanceNotice, PeerInfo, PeerJoined, PeerLeft, PeerList, ReconnectNotice, RelayErrorCode,
+    RoomId, SaltExchange, WireMessage, decode_frame, encode_frame,
 };
 use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
 use tokio::{
     net::TcpListener,
-    sync::{RwLock, mpsc},
+    sync::{Mutex, RwLock, mpsc},
 };
 use tracing::{error, info, warn};
 
-#[derive(Debug, Clone)]
-struct Connection {
-    peer: PeerInfo,
-    tx: mpsc::UnboundedSender<Message>,
+use allowlist::RoomAllowList;
+pub use lifetime::ConnectionLifetime;
+#[cfg(feature = "sled-store")]
+pub use sled_store::SledRoomStore;
+pub use store::{Member, MemoryRoomStore, RoomSequence, RoomStore, SEQ_RESERVATION};
+pub use strikes::StrikePolicy;
+use strikes::{BanList, StrikeCounter, StrikeMetrics};
+pub use webhooks::{SIGNATURE_HEADER, WebhookConfig, hash_room_id};
+use webhooks::{WebhookEvent, WebhookEventKind, Webhooks};
+
+type ConnectionTx = mpsc::UnboundedSender<Message>;
+
+/// How long a client closed at its maximum age has to answer the close
+/// before the relay drops the connection.
+const CLOSE_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct RelayState {
+    rooms: Box<dyn RoomStore>,
+    /// Connections open to this process, by room and device.
+    connections: HashMap<RoomId, HashMap<DeviceId, ConnectionTx>>,
+    maintenance: Option<Maintenance>,
 }
 
-#[derive(Debug, Default)]
-struct Room {
-    devices: HashMap<DeviceId, Connection>,
+impl RelayState {
+    fn new(rooms: Box<dyn RoomStore>) -> Self {
+        Self {
+            rooms,
+            connections: HashMap::new(),
+            maintenance: None,
+        }
+    }
+
+    /// Connections open to the devices in `room_id`.
+    fn senders(&self, room_id: &RoomId) -> Vec<ConnectionTx> {
+        self.connections
+            .get(room_id)
+            .map(|devices| devices.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn all_senders(&self) -> impl Iterator<Item = &ConnectionTx> {
+        self.connections
+            .values()
+            .flat_map(|devices| devices.values())
+    }
 }
 
-#[derive(Debug, Default)]
-struct RelayState {
-    rooms: HashMap<RoomId, Room>,
+/// Scheduled maintenance.  New connections are refused from the moment it
+/// is announced; at `notice.at_unix_ms` every connection is closed, and
+/// `downtime` later connections are accepted again.
+#[derive(Debug)]
+struct Maintenance {
+    notice: MaintenanceNotice,
+    /// How long after `notice.at_unix_ms` refused clients are told to
+    /// retry.
+    downtime: Duration,
+    drain: tokio::task::AbortHandle,
 }
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     inner: Arc<RwLock<RelayState>>,
+    strike_policy: StrikePolicy,
+    bans: Arc<Mutex<BanList>>,
+    strike_metrics: Arc<StrikeMetrics>,
+    /// Bearer token for `/admin/*`; without one those routes are not found.
+    admin_token: Option<Arc<str>>,
+    webhooks: Option<Arc<Webhooks>>,
+    /// Rooms served; without a list, every room.
+    allowed_rooms: Option<Arc<Mutex<RoomAllowList>>>,
+    /// Without one, connections stay open as long as their clients keep
+    /// them.
+    connection_lifetime: Option<ConnectionLifetime>,
+    /// Reverse proxies whose `X-Forwarded-For` header is believed.
+    trusted_proxies: Arc<[IpAddr]>,
 }
 
 impl AppState {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            inner: Arc::new(RwLock::new(RelayState::default())),
+            inner: Arc::new(RwLock::new(RelayState::new(Box::new(
+                MemoryRoomStore::default(),
+            )))),
+            strike_policy: StrikePolicy::default(),
+            bans: Arc::new(Mutex::new(BanList::default())),
+            strike_metrics: Arc::new(StrikeMetrics::default()),
+            admin_token: None,
+            webhooks: None,
+            allowed_rooms: None,
+            connection_lifetime: None,
+            trusted_proxies: Arc::new([]),
+        }
+    }
+
+    #[must_use]
+    pub fn with_strike_policy(mut self, policy: StrikePolicy) -> Self {
+        self.strike_policy = policy;
+        self
+    }
+
+    /// Close connections when they reach `lifetime`, after asking their
+    /// clients to reconnect.
+    #[must_use]
+    pub fn with_connection_lifetime(mut self, lifetime: ConnectionLifetime) -> Self {
+        self.connection_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Take the client address, which bans apply to, from the
+    /// `X-Forwarded-For` header of connections from `proxies`.
+    #[must_use]
+    pub fn with_trusted_proxies(mut self, proxies: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.trusted_proxies = proxies.into_iter().map(|ip| ip.to_canonical()).collect();
+        self
+    }
+
+    /// Keep rooms in `store` instead of memory.  Replaces any rooms of
+    /// this state, so it belongs before [`serve`].
+    #[must_use]
+    pub fn with_room_store(mut self, store: impl RoomStore + 'static) -> Self {
+        self.inner = Arc::new(RwLock::new(RelayState::new(Box::new(store))));
+        self
+    }
+
+    /// Serve only `rooms`.
+    #[must_use]
+    pub fn with_allowed_rooms(mut self, rooms: impl IntoIterator<Item = RoomId>) -> Self {
+        self.allowed_rooms = Some(Arc::new(Mutex::new(RoomAllowList::new(rooms))));
+        self
+    }
+
+    /// Serve only the rooms listed in `path`, one per line, reading the
+    /// file again when it changes.
+    pub fn with_allowed_rooms_file(mut self, path: PathBuf) -> Result<Self, String> {
+        let rooms = RoomAllowList::from_file(path)?;
+        self.allowed_rooms = Some(Arc::new(Mutex::new(rooms)));
+        Ok(self)
+    }
+
+    async fn room_allowed(&self, room_id: &RoomId) -> bool {
+        match &self.allowed_rooms {
+            Some(rooms) => rooms.lock().await.allows(room_id),
+            None => true,
+        }
+    }
+
+    #[must_use]
+    pub fn with_admin_token(mut self, token: String) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    /// Post room membership events to `config.url`; fails only if the HTTP
+    /// client cannot be set up.
+    pub fn with_webhooks(mut self, config: WebhookConfig) -> Result<Self, String> {
+        self.webhooks = Some(Arc::new(Webhooks::new(config)?));
+        Ok(self)
+    }
+
+    fn fire_webhook(
+        &self,
+        event: WebhookEventKind,
+        room_id: &RoomId,
+        peer: &PeerInfo,
+        reason: Option<String>,
+        devices_in_room: usize,
+    ) {
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.fire(WebhookEvent {
+                event,
+                room: hash_room_id(room_id),
+                device_id: peer.device_id.clone(),
+                device_name: peer.device_name.clone(),
+                reason,
+                devices_in_room,
+                at_unix_ms: now_unix_ms(),
+            });
+        }
+    }
+
+    /// Announce maintenance `after` from now to every connected client,
+    /// refuse new connections, and close all connections when the time
+    /// comes.  Connections are accepted again `downtime` after that.
+    /// Replaces maintenance already scheduled.
+    pub async fn schedule_maintenance(
+        &self,
+        after: Duration,
+        message: String,
+        downtime: Duration,
+    ) -> MaintenanceNotice {
+        let notice = MaintenanceNotice {
+            at_unix_ms: now_unix_ms().saturating_add(after.as_millis() as u64),
+            message,
+        };
+        let state = self.clone();
+        let window = notice.clone();
+        let drain = tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            drain_connections(&state).await;
+            tokio::time::sleep(downtime).await;
+            state.end_maintenance(&window).await;
+        })
+        .abort_handle();
+
+        let recipients = {
+            let mut relay = self.inner.write().await;
+            let previous = relay.maintenance.replace(Maintenance {
+                notice: notice.clone(),
+                downtime,
+                drain,
+            });
+            if let Some(previous) = previous {
+                previous.drain.abort();
+            }
+            relay.all_senders().cloned().collect::<Vec<_>>()
+        };
+        info!(
+            "maintenance scheduled in {}s, notifying {} connections",
+            after.as_secs(),
+            recipients.len()
+        );
+        broadcast_control(recipients, ControlMessage::Maintenance(notice.clone()));
+        notice
+    }
+
+    /// Call off scheduled maintenance and accept connections again.
+    /// `false` if none was scheduled.
+    pub async fn cancel_maintenance(&self) -> bool {
+        let Some(maintenance) = self.inner.write().await.maintenance.take() else {
+            return false;
+        };
+        maintenance.drain.abort();
+        info!("maintenance cancelled");
+        true
+    }
+
+    /// Accept connections again after the maintenance announced with
+    /// `notice`, unless newer maintenance has replaced it.
+    async fn end_maintenance(&self, notice: &MaintenanceNotice) {
+        let mut relay = self.inner.write().await;
+        if relay
+            .maintenance
+            .as_ref()
+            .is_some_and(|maintenance| maintenance.notice == *notice)
+        {
+            relay.maintenance = None;
+            info!("maintenance over, accepting connections again");
         }
     }
 }
@@ -90,6 +342,19 @@ pub fn build_router(state: AppState) -> Router {
     Router::new()
         .route("/ws", get(ws_handler))
         .route("/healthz", get(healthz_handler))
+        .route("/metrics", get(metrics_handler))
+        .route(
+            "/admin/maintenance",
+            post(start_maintenance_handler).delete(cancel_maintenance_handler),
+        )
+        .route(
+            "/admin/rooms",
+            get(list_allowed_rooms_handler).post(add_allowed_room_handler),
+        )
+        .route(
+            "/admin/rooms/{room_id}",
+            delete(remove_allowed_room_handler),
+        )
         .with_state(state)
 }
 
@@ -101,27 +366,240 @@ pub async fn serve(listener: TcpListener, state: AppState) -> Result<(), String>
             .map(|a| a.to_string())
             .unwrap_or_else(|_| "unknown".to_owned())
     );
-    axum::serve(listener, build_router(state))
-        .await
-        .map_err(|err| err.to_string())
+    axum::serve(
+        listener,
+        build_router(state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|err| err.to_string())
 }
 
 async fn healthz_handler() -> impl IntoResponse {
     Json(serde_json::json!({"ok": true}))
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let active_bans = state.bans.lock().await.active(Instant::now());
+    let metrics = &state.strike_metrics;
+    let (delivered, failed, dropped) = state
+        .webhooks
+        .as_ref()
+        .map_or((0, 0, 0), |webhooks| webhooks.metrics.totals());
+    Json(serde_json::json!({
+        "strikes_total": StrikeMetrics::get(&metrics.strikes),
+        "strike_disconnects_total": StrikeMetrics::get(&metrics.disconnects),
+        "bans_total": StrikeMetrics::get(&metrics.bans),
+        "rejected_while_banned_total": StrikeMetrics::get(&metrics.rejected_while_banned),
+        "active_bans": active_bans,
+        "webhooks_delivered_total": delivered,
+        "webhooks_failed_total": failed,
+        "webhooks_dropped_total": dropped,
+    }))
+}
+
+fn default_downtime_secs() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceRequest {
+    /// Seconds from now until connections are closed.
+    in_secs: u64,
+    #[serde(default)]
+    message: String,
+    /// Expected length of the outage, added to the `Retry-After` of
+    /// refused connections.
+    #[serde(default = "default_downtime_secs")]
+    downtime_secs: u64,
+}
+
+async fn start_maintenance_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<MaintenanceRequest>,
+) -> Response {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status.into_response();
+    }
+    let notice = state
+        .schedule_maintenance(
+            Duration::from_secs(request.in_secs),
+            request.message,
+            Duration::from_secs(request.downtime_secs),
+        )
+        .await;
+    Json(notice).into_response()
+}
+
+async fn cancel_maintenance_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status.into_response();
+    }
+    if state.cancel_maintenance().await {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AllowedRoomRequest {
+    room_id: RoomId,
+}
+
+async fn list_allowed_rooms_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status.into_response();
+    }
+    let Some(allowed_rooms) = &state.allowed_rooms else {
+        return (StatusCode::CONFLICT, "relay has no room allow-list").into_response();
+    };
+    let rooms = allowed_rooms.lock().await.rooms();
+    Json(serde_json::json!({ "rooms": rooms })).into_response()
+}
+
+async fn add_allowed_room_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AllowedRoomRequest>,
+) -> Response {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status.into_response();
+    }
+    let Some(allowed_rooms) = &state.allowed_rooms else {
+        return (StatusCode::CONFLICT, "relay has no room allow-list").into_response();
+    };
+    let room_id = request.room_id.trim().to_owned();
+    if room_id.is_empty() {
+        return (StatusCode::BAD_REQUEST, "room_id cannot be empty").into_response();
+    }
+    match allowed_rooms.lock().await.add(room_id) {
+        Ok(true) => StatusCode::CREATED.into_response(),
+        Ok(false) => StatusCode::OK.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+async fn remove_allowed_room_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(room_id): axum::extract::Path<RoomId>,
+) -> Response {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status.into_response();
+    }
+    let Some(allowed_rooms) = &state.allowed_rooms else {
+        return (StatusCode::CONFLICT, "relay has no room allow-list").into_response();
+    };
+    match allowed_rooms.lock().await.remove(&room_id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    }
+}
+
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(token) = state.admin_token.as_deref() else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let given = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if tokens_match(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/// Compare without stopping at the first differing byte.
+fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(retry_after) = maintenance_retry_after(&state).await {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+            "relay maintenance",
+        )
+            .into_response();
+    }
+
+    let client_ip = client_ip(
+        connect_info.map(|Extension(ConnectInfo(addr))| addr),
+        &headers,
+        &state.trusted_proxies,
+    );
+    if let Some(ip) = client_ip {
+        let remaining = state.bans.lock().await.remaining(ip, Instant::now());
+        if let Some(remaining) = remaining {
+            StrikeMetrics::add(&state.strike_metrics.rejected_while_banned);
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, remaining.as_secs().max(1).to_string())],
+                "temporarily banned after too many malformed frames",
+            )
+                .into_response();
+        }
+    }
+
     ws.max_frame_size(MAX_RELAY_MESSAGE_BYTES)
         .on_upgrade(move |socket| async move {
-            if let Err(err) = handle_socket(state, socket).await {
+            if let Err(err) = handle_socket(state, socket, client_ip).await {
                 warn!("socket session ended with error: {}", err);
             }
         })
 }
 
+/// How long a client refused during maintenance should wait, if
+/// maintenance is scheduled.
+async fn maintenance_retry_after(state: &AppState) -> Option<Duration> {
+    let relay = state.inner.read().await;
+    let maintenance = relay.maintenance.as_ref()?;
+    let until_start = maintenance.notice.at_unix_ms.saturating_sub(now_unix_ms());
+    Some(Duration::from_millis(until_start) + maintenance.downtime)
+}
+
+/// Address bans apply to.  Behind a trusted reverse proxy (the Caddy setup
+/// in the README) every connection comes from the proxy, so the last
+/// `X-Forwarded-For` entry, the one the proxy added, is used instead.
+/// Loopback is never returned: banning it would lock out everyone coming
+/// through a proxy on the same host.
+fn client_ip(
+    peer: Option<SocketAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let peer = peer?.ip().to_canonical();
+    let ip = if trusted_proxies.contains(&peer) {
+        headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())?
+            .to_canonical()
+    } else {
+        peer
+    };
+    (!ip.is_loopback()).then_some(ip)
+}
+
 async fn handle_socket(
     state: AppState,
     socket: axum::extract::ws::WebSocket,
+    client_ip: Option<IpAddr>,
 ) -> Result<(), String> {
     let (mut ws_sender, mut ws_receiver) = socket.split();
     let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<Message>();
@@ -142,7 +620,8 @@ async fn handle_socket(
                 msg = outbound_rx.recv() => {
                     match msg {
                         Some(message) => {
-                            if ws_sender.send(message).await.is_err() {
+                            let closing = matches!(message, Message::Close(_));
+                            if ws_sender.send(message).await.is_err() || closing {
                                 break;
                             }
                         }
@@ -170,24 +649,58 @@ async fn handle_socket(
     let device_id = hello.peer.device_id.clone();
     let device_name = hello.peer.device_name.clone();
 
-    register_client(
+    if !state.room_allowed(&room_id).await {
+        let devices_in_room = state.inner.read().await.rooms.members(&room_id).len();
+        state.fire_webhook(
+            WebhookEventKind::Reject,
+            &room_id,
+            &hello.peer,
+            Some("room not allowed".to_owned()),
+            devices_in_room,
+        );
+        let message = format!("room {} is not served by this relay", room_id);
+        refuse_hello(&outbound_tx, RelayErrorCode::RoomNotAllowed, &message);
+        return Err(message);
+    }
+
+    if let Err(message) = register_client(
         &state,
         &room_id,
-        Connection {
+        Member {
             peer: PeerInfo {
                 device_id: device_id.clone(),
                 device_name,
+                sealed_chunks: hello.peer.sealed_chunks,
             },
-            tx: outbound_tx.clone(),
+            sequenced: hello.sequenced,
+            probes: hello.probes,
         },
+        outbound_tx.clone(),
     )
-    .await?;
+    .await
+    {
+        refuse_hello(&outbound_tx, RelayErrorCode::RoomFull, &message);
+        return Err(message);
+    }
 
     info!("device {} joined room {}", device_id, room_id);
 
     let mut rate_limiter = TokenBucket::new(400.0, 200.0);
+    let mut strikes = StrikeCounter::new(state.strike_policy);
+    let expiry = expire_connection(state.connection_lifetime, outbound_tx.clone());
+    tokio::pin!(expiry);
 
-    while let Some(next_message) = ws_receiver.next().await {
+    loop {
+        let next_message = tokio::select! {
+            next = ws_receiver.next() => match next {
+                Some(next) => next,
+                None => break,
+            },
+            () = &mut expiry => {
+                info!("closing {} at its maximum age", device_id);
+                break;
+            }
+        };
         let message = match next_message {
             Ok(message) => message,
             Err(err) => {
@@ -198,8 +711,12 @@ async fn handle_socket(
 
         match message {
             Message::Binary(data) => {
+                let received_unix_ms = now_unix_ms();
                 if data.len() > MAX_RELAY_MESSAGE_BYTES {
                     warn!("dropping oversized message from {}", device_id);
+                    if record_strike(&state, &mut strikes, client_ip, &device_id).await {
+                        break;
+                    }
                     continue;
                 }
 
@@ -207,6 +724,9 @@ async fn handle_socket(
                     Ok(wire) => wire,
                     Err(err) => {
                         warn!("failed to decode frame from {}: {}", device_id, err);
+                        if record_strike(&state, &mut strikes, client_ip, &device_id).await {
+                            break;
+                        }
                         continue;
                     }
                 };
@@ -215,6 +735,9 @@ async fn handle_socket(
                     WireMessage::Encrypted(payload) => {
                         if payload.sender_device_id != device_id {
                             warn!("sender id mismatch from {}", device_id);
+                            if record_strike(&state, &mut strikes, client_ip, &device_id).await {
+                                break;
+                            }
                             continue;
                         }
 
@@ -225,8 +748,21 @@ async fn handle_socket(
 
                         forward_encrypted(&state, &room_id, &device_id, payload).await;
                     }
+                    WireMessage::Control(ControlMessage::Probe(mut probe)) => {
+                        if !rate_limiter.consume(1.0) {
+                            warn!("rate limit exceeded for {}", device_id);
+                            continue;
+                        }
+
+                        probe.sender_device_id = device_id.clone();
+                        probe.relay_received_unix_ms = Some(received_unix_ms);
+                        forward_probe(&state, &room_id, probe).await;
+                    }
                     WireMessage::Control(_) => {
                         warn!("unexpected control message after hello from {}", device_id);
+                        if record_strike(&state, &mut strikes, client_ip, &device_id).await {
+                            break;
+                        }
                     }
                 }
             }
@@ -235,12 +771,60 @@ async fn handle_socket(
         }
     }
 
-    unregister_client(&state, &room_id, &device_id).await;
+    unregister_client(&state, &room_id, &device_id, &outbound_tx).await;
     send_task.abort();
     info!("device {} left room {}", device_id, room_id);
     Ok(())
 }
 
+/// Count a strike against the connection; `true` when it has reached the
+/// limit and must be closed.  Its address, if known, is banned.
+async fn record_strike(
+    state: &AppState,
+    strikes: &mut StrikeCounter,
+    client_ip: Option<IpAddr>,
+    device_id: &DeviceId,
+) -> bool {
+    StrikeMetrics::add(&state.strike_metrics.strikes);
+    let now = Instant::now();
+    if !strikes.strike(now) {
+        return false;
+    }
+
+    StrikeMetrics::add(&state.strike_metrics.disconnects);
+    let ban = state.strike_policy.ban;
+    match client_ip {
+        Some(ip) => {
+            state.bans.lock().await.ban(ip, now, ban);
+            StrikeMetrics::add(&state.strike_metrics.bans);
+            warn!(
+                "closing {} and banning {} for {}s after too many bad frames",
+                device_id,
+                ip,
+                ban.as_secs()
+            );
+        }
+        None => warn!("closing {} after too many bad frames", device_id),
+    }
+    true
+}
+
+/// Tell a client why its Hello was refused, then close the connection.
+/// The send task flushes both before it ends.
+fn refuse_hello(tx: &ConnectionTx, code: RelayErrorCode, message: &str) {
+    broadcast_control(
+        vec![tx.clone()],
+        ControlMessage::Error {
+            message: message.to_owned(),
+            code: Some(code),
+        },
+    );
+    let _ = tx.send(Message::Close(Some(CloseFrame {
+        code: close_code::POLICY,
+        reason: "hello refused".into(),
+    })));
+}
+
 fn parse_hello_message(message: &Message) -> Result<Hello, String> {
     let data = match message {
         Message::Binary(data) => data,
@@ -268,32 +852,47 @@ fn parse_hello_message(message: &Message) -> Result<Hello, String> {
 async fn register_client(
     state: &AppState,
     room_id: &RoomId,
-    connection: Connection,
+    member: Member,
+    tx: ConnectionTx,
 ) -> Result<(), String> {
     let mut relay = state.inner.write().await;
-    let room = relay.rooms.entry(room_id.clone()).or_default();
-    if room.devices.len() >= MAX_DEVICES_PER_ROOM {
+    let members = relay.rooms.members(room_id);
+    let devices_in_room = members.len();
+    // A device reconnecting takes over its own place.
+    let rejoining = members
+        .iter()
+        .any(|joined| joined.peer.device_id == member.peer.device_id);
+    if devices_in_room >= MAX_DEVICES_PER_ROOM && !rejoining {
+        state.fire_webhook(
+            WebhookEventKind::Reject,
+            room_id,
+            &member.peer,
+            Some("room full".to_owned()),
+            devices_in_room,
+        );
         return Err(format!(
             "room {} is full (max {})",
             room_id, MAX_DEVICES_PER_ROOM
         ));
     }
-    room.devices
-        .insert(connection.peer.device_id.clone(), connection.clone());
+    let peer = member.peer.clone();
+    relay.rooms.join(room_id, member);
+    relay
+        .connections
+        .entry(room_id.clone())
+        .or_default()
+        .insert(peer.device_id.clone(), tx);
 
-    let peer = connection.peer.clone();
-    let peers = room
-        .devices
-        .values()
-        .map(|conn| conn.peer.clone())
-        .collect::<Vec<_>>();
-    let recipients = room
-        .devices
-        .values()
-        .map(|conn| conn.tx.clone())
+    let peers = relay
+        .rooms
+        .members(room_id)
+        .into_iter()
+        .map(|member| member.peer)
         .collect::<Vec<_>>();
+    let recipients = relay.senders(room_id);
     drop(relay);
 
+    state.fire_webhook(WebhookEventKind::Join, room_id, &peer, None, peers.len());
     broadcast_control(
         recipients.clone(),
         ControlMessage::PeerJoined(PeerJoined {
@@ -319,24 +918,49 @@ async fn register_client(
     Ok(())
 }
 
-async fn unregister_client(state: &AppState, room_id: &RoomId, device_id: &DeviceId) {
+/// Remove the connection `tx` of `device_id`.  A device that has already
+/// connected again, e.g. when told to reconnect, stays in the room.
+async fn unregister_client(
+    state: &AppState,
+    room_id: &RoomId,
+    device_id: &DeviceId,
+    tx: &ConnectionTx,
+) {
     let mut relay = state.inner.write().await;
-    let mut recipients = Vec::new();
-    let mut peers = Vec::new();
-    if let Some(room) = relay.rooms.get_mut(room_id) {
-        room.devices.remove(device_id);
-        recipients = room.devices.values().map(|conn| conn.tx.clone()).collect();
-        peers = room
-            .devices
-            .values()
-            .map(|conn| conn.peer.clone())
-            .collect();
-        if room.devices.is_empty() {
-            relay.rooms.remove(room_id);
+    let replaced = relay
+        .connections
+        .get(room_id)
+        .and_then(|devices| devices.get(device_id))
+        .is_some_and(|current| !current.same_channel(tx));
+    if replaced {
+        return;
+    }
+    let left = relay.rooms.leave(room_id, device_id);
+    if let Some(devices) = relay.connections.get_mut(room_id) {
+        devices.remove(device_id);
+        if devices.is_empty() {
+            relay.connections.remove(room_id);
         }
     }
+    let peers = relay
+        .rooms
+        .members(room_id)
+        .into_iter()
+        .map(|member| member.peer)
+        .collect::<Vec<_>>();
+    let recipients = relay.senders(room_id);
     drop(relay);
 
+    if let Some(member) = left {
+        state.fire_webhook(
+            WebhookEventKind::Leave,
+            room_id,
+            &member.peer,
+            None,
+            peers.len(),
+        );
+    }
+
     if recipients.is_empty() {
         return;
     }
@@ -368,32 +992,118 @@ async fn forward_encrypted(
     state: &AppState,
     room_id: &RoomId,
     sender_device_id: &DeviceId,
-    payload: cliprelay_core::EncryptedPayload,
+    mut payload: cliprelay_core::EncryptedPayload,
 ) {
-    let recipients = {
-        let relay = state.inner.read().await;
-        relay
-            .rooms
-            .get(room_id)
-            .map(|room| {
-                room.devices
-                    .iter()
-                    .filter(|(device_id, _)| *device_id != sender_device_id)
-                    .map(|(_, conn)| conn.tx.clone())
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
+    let relay = state.inner.read().await;
+    let Some(sequence) = relay.rooms.sequence(room_id) else {
+        return;
+    };
+    let Some(connections) = relay.connections.get(room_id) else {
+        return;
     };
 
-    let message = WireMessage::Encrypted(payload);
-    if let Ok(frame) = encode_frame(&message) {
-        for tx in recipients {
+    // Whatever the client sent in this field is not trusted.
+    payload.relay_seq = None;
+    let plain = encode_frame(&WireMessage::Encrypted(payload.clone()));
+    let members = relay.rooms.members(room_id);
+
+    // Only this room's sequence stays locked until every recipient has been
+    // queued the frame, so payloads reach each of them in `relay_seq` order.
+    sequence.with_next(|seq| {
+        payload.relay_seq = Some(seq);
+        let stamped = encode_frame(&WireMessage::Encrypted(payload));
+        for member in members {
+            if member.peer.device_id == *sender_device_id {
+                continue;
+            }
+            let Some(tx) = connections.get(&member.peer.device_id) else {
+                continue;
+            };
+            let frame = if member.sequenced { &stamped } else { &plain };
+            if let Ok(frame) = frame {
+                let _ = tx.send(Message::Binary(frame.clone().into()));
+            }
+        }
+    });
+}
+
+/// Stamp a probe with its forward time and pass it back to its sender and
+/// on to every other device that asked for probes.
+async fn forward_probe(state: &AppState, room_id: &RoomId, mut probe: LatencyProbe) {
+    let relay = state.inner.read().await;
+    let Some(connections) = relay.connections.get(room_id) else {
+        return;
+    };
+
+    probe.relay_forwarded_unix_ms = Some(now_unix_ms());
+    let sender_device_id = probe.sender_device_id.clone();
+    let frame = match encode_frame(&WireMessage::Control(ControlMessage::Probe(probe))) {
+        Ok(frame) => frame,
+        Err(err) => {
+            error!("failed to serialize probe: {}", err);
+            return;
+        }
+    };
+    for member in relay.rooms.members(room_id) {
+        if !member.probes && member.peer.device_id != sender_device_id {
+            continue;
+        }
+        if let Some(tx) = connections.get(&member.peer.device_id) {
             let _ = tx.send(Message::Binary(frame.clone().into()));
         }
     }
 }
 
-fn broadcast_control(recipients: Vec<mpsc::UnboundedSender<Message>>, control: ControlMessage) {
+/// With `lifetime`, tell the client behind `tx` to reconnect shortly before
+/// the connection's age limit and close it at the limit.  Ends once the
+/// client has had [`CLOSE_GRACE`] to answer the close; never ends without a
+/// lifetime.
+async fn expire_connection(lifetime: Option<ConnectionLifetime>, tx: ConnectionTx) {
+    let Some(lifetime) = lifetime else {
+        return std::future::pending().await;
+    };
+    let age = lifetime.age_limit();
+    let notice = lifetime.notice.min(age);
+    tokio::time::sleep(age - notice).await;
+    broadcast_control(
+        vec![tx.clone()],
+        ControlMessage::Reconnect(ReconnectNotice {
+            at_unix_ms: now_unix_ms().saturating_add(notice.as_millis() as u64),
+        }),
+    );
+    tokio::time::sleep(notice).await;
+    let _ = tx.send(Message::Close(Some(CloseFrame {
+        code: close_code::NORMAL,
+        reason: "maximum connection age".into(),
+    })));
+    tokio::time::sleep(CLOSE_GRACE).await;
+}
+
+/// Close every connection for maintenance.  Clients answer the close and
+/// leave their rooms the usual way.
+async fn drain_connections(state: &AppState) {
+    let relay = state.inner.read().await;
+    let mut closed = 0;
+    for tx in relay.all_senders() {
+        let frame = CloseFrame {
+            code: close_code::RESTART,
+            reason: "relay maintenance".into(),
+        };
+        if tx.send(Message::Close(Some(frame))).is_ok() {
+            closed += 1;
+        }
+    }
+    info!("maintenance started, closing {} connections", closed);
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn broadcast_control(recipients: Vec<ConnectionTx>, control: ControlMessage) {
     let frame = match encode_frame(&WireMessage::Control(control)) {
         Ok(frame) => frame,
         Err(err) => {
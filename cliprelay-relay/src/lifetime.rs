@@ -0,0 +1,47 @@
+//! Optional maximum age of a connection.
+//!
+//! Long-lived connections pile up state in proxies and hold up rolling
+//! upgrades.  With a lifetime set, the relay tells each client `notice`
+//! before its connection reaches its age limit to connect again
+//! (`ControlMessage::Reconnect`), and closes the connection at the limit.
+//! Each connection's limit is cut short by a random part of `jitter`, so
+//! clients that connected together, e.g. after a restart, do not all
+//! reconnect together.
+
+use std::time::Duration;
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionLifetime {
+    pub max_age: Duration,
+    /// How long before the close the client is told to reconnect.
+    pub notice: Duration,
+    /// Taken off `max_age` at random, up to this much, per connection.
+    pub jitter: Duration,
+}
+
+impl ConnectionLifetime {
+    /// `max_age` with a one-minute notice (at most half the age) and a
+    /// tenth of the age as jitter.
+    #[must_use]
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            notice: Duration::from_secs(60).min(max_age / 2),
+            jitter: max_age / 10,
+        }
+    }
+
+    /// The age limit of a new connection, never less than the notice.
+    pub(crate) fn age_limit(&self) -> Duration {
+        let mut bytes = [0u8; 4];
+        let fraction = match SystemRandom::new().fill(&mut bytes) {
+            Ok(()) => f64::from(u32::from_le_bytes(bytes)) / (f64::from(u32::MAX) + 1.0),
+            Err(_) => 0.0,
+        };
+        self.max_age
+            .saturating_sub(self.jitter.mul_f64(fraction))
+            .max(self.notice)
+    }
+}
@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use clap::Parser;
-use cliprelay_relay::{AppState, serve};
+use cliprelay_relay::{AppState, ConnectionLifetime, StrikePolicy, WebhookConfig, serve};
 use tracing::{error, info, warn};
 
 #[derive(Parser, Debug)]
@@ -7,6 +9,41 @@ use tracing::{error, info, warn};
 struct RelayArgs {
     #[arg(long, default_value = "0.0.0.0:8080")]
     bind_address: String,
+    /// Malformed frames or protocol violations that close a connection.
+    #[arg(long, default_value_t = 20)]
+    strike_limit: u32,
+    /// Window the strikes are counted in.
+    #[arg(long, default_value_t = 60)]
+    strike_window_secs: u64,
+    /// How long the address of a closed connection is refused.
+    #[arg(long, default_value_t = 300)]
+    ban_secs: u64,
+    /// URL that receives room join/leave/reject events.  The signing secret
+    /// is read from `CLIPRELAY_WEBHOOK_SECRET`.
+    #[arg(long)]
+    webhook_url: Option<String>,
+    /// Webhook events sent per minute at most; the rest are dropped.
+    #[arg(long, default_value_t = 30)]
+    webhook_max_per_minute: u32,
+    /// Serve only this room id; repeat for more rooms.
+    #[arg(long = "allow-room", value_name = "ROOM_ID")]
+    allow_rooms: Vec<String>,
+    /// Serve only the room ids listed in this file, one per line.  Changes
+    /// to the file take effect without a restart.
+    #[arg(long, conflicts_with = "allow_rooms")]
+    allowed_rooms_file: Option<std::path::PathBuf>,
+    /// Address of a reverse proxy whose `X-Forwarded-For` header names the
+    /// client, e.g. 127.0.0.1 for Caddy on the same host; repeat for more.
+    #[arg(long = "trusted-proxy", value_name = "IP")]
+    trusted_proxies: Vec<std::net::IpAddr>,
+    /// Close connections older than this, after asking their clients to
+    /// reconnect; by default connections have no age limit.
+    #[arg(long)]
+    max_connection_age_secs: Option<u64>,
+    /// Directory of the database rooms are kept in across restarts.
+    #[cfg(feature = "sled-store")]
+    #[arg(long)]
+    room_store: Option<std::path::PathBuf>,
 }
 
 #[tokio::main]
@@ -25,7 +62,68 @@ async fn main() {
     };
 
     info!("relay starting on {}", args.bind_address);
-    if let Err(err) = serve(listener, AppState::new()).await {
+    let state = AppState::new()
+        .with_strike_policy(StrikePolicy {
+            max_strikes: args.strike_limit.max(1),
+            window: Duration::from_secs(args.strike_window_secs),
+            ban: Duration::from_secs(args.ban_secs),
+        })
+        .with_trusted_proxies(args.trusted_proxies);
+    // Kept out of the command line, where other local users could read it.
+    let state = match std::env::var("CLIPRELAY_ADMIN_TOKEN") {
+        Ok(token) if !token.trim().is_empty() => state.with_admin_token(token.trim().to_owned()),
+        _ => state,
+    };
+    let state = match args.webhook_url {
+        Some(url) => {
+            let secret = std::env::var("CLIPRELAY_WEBHOOK_SECRET").unwrap_or_default();
+            if secret.trim().is_empty() {
+                error!("--webhook-url needs CLIPRELAY_WEBHOOK_SECRET to sign requests");
+                std::process::exit(1);
+            }
+            let config = WebhookConfig {
+                max_per_minute: args.webhook_max_per_minute,
+                ..WebhookConfig::new(url, secret.trim().to_owned())
+            };
+            match state.with_webhooks(config) {
+                Ok(state) => state,
+                Err(err) => {
+                    error!("failed to set up webhooks: {}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => state,
+    };
+    let state = match args.allowed_rooms_file {
+        Some(path) => match state.with_allowed_rooms_file(path) {
+            Ok(state) => state,
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None if !args.allow_rooms.is_empty() => state.with_allowed_rooms(args.allow_rooms),
+        None => state,
+    };
+    let state = match args.max_connection_age_secs {
+        Some(secs) if secs > 0 => {
+            state.with_connection_lifetime(ConnectionLifetime::new(Duration::from_secs(secs)))
+        }
+        _ => state,
+    };
+    #[cfg(feature = "sled-store")]
+    let state = match &args.room_store {
+        Some(path) => match cliprelay_relay::SledRoomStore::open(path) {
+            Ok(store) => state.with_room_store(store),
+            Err(err) => {
+                error!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None => state,
+    };
+    if let Err(err) = serve(listener, state).await {
         warn!("relay server exited: {}", err);
     }
 }
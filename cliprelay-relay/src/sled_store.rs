@@ -0,0 +1,100 @@
+//! [`RoomStore`] persisted in a sled database (feature `sled-store`).
+//!
+//! Rooms are served from a [`MemoryRoomStore`]; each room's sequence is
+//! also written to disk, [`SEQ_RESERVATION`](crate::SEQ_RESERVATION)
+//! numbers at a time, and loaded when the relay starts.  Members are not
+//! persisted: their connections do not survive a restart.  A room that had
+//! members when the relay stopped keeps its sequence, so clients
+//! reconnecting after the restart see the order continue (past the rest of
+//! the last reservation) instead of starting again at 0.
+
+use std::{path::Path, sync::Arc};
+
+use cliprelay_core::{DeviceId, RoomId};
+use tracing::warn;
+
+use crate::store::{Member, MemoryRoomStore, RoomSequence, RoomStore};
+
+const ROOMS_TREE: &str = "rooms";
+
+#[derive(Debug)]
+pub struct SledRoomStore {
+    memory: MemoryRoomStore,
+    /// Room id to the end of its sequence's last reservation, `u64`
+    /// little-endian: the first number after a restart.
+    rooms: sled::Tree,
+}
+
+impl SledRoomStore {
+    /// Open or create the database at `path` and load the rooms kept in it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let db = sled::open(path)
+            .map_err(|err| format!("failed to open room store {}: {err}", path.display()))?;
+        let rooms = db.open_tree(ROOMS_TREE).map_err(|err| err.to_string())?;
+
+        let mut memory = MemoryRoomStore::default();
+        for entry in rooms.iter() {
+            let (room_id, next_seq) = entry.map_err(|err| err.to_string())?;
+            let (Ok(room_id), Ok(next_seq)) = (
+                String::from_utf8(room_id.to_vec()),
+                <[u8; 8]>::try_from(next_seq.as_ref()),
+            ) else {
+                warn!("skipping unreadable room entry in room store");
+                continue;
+            };
+            let sequence = saved_sequence(&rooms, &room_id, u64::from_le_bytes(next_seq));
+            memory.rooms.entry(room_id).or_default().sequence = sequence;
+        }
+
+        Ok(Self { memory, rooms })
+    }
+
+    fn forget_if_gone(&self, room_id: &RoomId) {
+        if !self.memory.rooms.contains_key(room_id) {
+            persist(self.rooms.remove(room_id.as_bytes()));
+        }
+    }
+}
+
+impl RoomStore for SledRoomStore {
+    fn join(&mut self, room_id: &RoomId, member: Member) {
+        let new_room = !self.memory.rooms.contains_key(room_id);
+        self.memory.join(room_id, member);
+        if new_room && let Some(room) = self.memory.rooms.get_mut(room_id) {
+            room.sequence = saved_sequence(&self.rooms, room_id, 0);
+        }
+    }
+
+    fn leave(&mut self, room_id: &RoomId, device_id: &DeviceId) -> Option<Member> {
+        let member = self.memory.leave(room_id, device_id);
+        self.forget_if_gone(room_id);
+        member
+    }
+
+    fn members(&self, room_id: &RoomId) -> Vec<Member> {
+        self.memory.members(room_id)
+    }
+
+    fn sequence(&self, room_id: &RoomId) -> Option<Arc<RoomSequence>> {
+        self.memory.sequence(room_id)
+    }
+}
+
+/// A sequence of `room_id` going on from `next` that writes the end of
+/// each reservation to `rooms`.
+fn saved_sequence(rooms: &sled::Tree, room_id: &RoomId, next: u64) -> Arc<RoomSequence> {
+    let rooms = rooms.clone();
+    let key = room_id.clone();
+    Arc::new(RoomSequence::saved(next, move |reserved_until| {
+        persist(rooms.insert(key.as_bytes(), &reserved_until.to_le_bytes()));
+    }))
+}
+
+/// The store keeps serving from memory when a write fails; the failure is
+/// logged and the disk copy is behind until the next write.
+fn persist<T>(result: sled::Result<T>) -> Option<T> {
+    result
+        .map_err(|err| warn!("room store write failed: {}", err))
+        .ok()
+}
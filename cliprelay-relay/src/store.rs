@@ -0,0 +1,149 @@
+//! Room bookkeeping behind the [`RoomStore`] trait.
+//!
+//! A store knows which devices are in which room and hands out each room's
+//! relay sequence numbers.  The open WebSocket connections themselves stay
+//! in the relay process, so a store can be shared or persisted without
+//! them.  [`MemoryRoomStore`] is the default; with the `sled-store`
+//! feature, `SledRoomStore` also keeps sequence numbers across restarts.
+//!
+//! Stores are called with the relay's room lock held and must not block
+//! for long.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use cliprelay_core::{DeviceId, PeerInfo, RoomId};
+use serde::{Deserialize, Serialize};
+
+/// A device in a room, with what it asked for in its Hello.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    pub peer: PeerInfo,
+    /// Wants `relay_seq` on forwarded payloads (see `Hello::sequenced`).
+    pub sequenced: bool,
+    /// Wants other devices' latency probes (see `Hello::probes`).
+    pub probes: bool,
+}
+
+/// Sequence numbers a saved sequence reserves at a time.  The store writes
+/// once per this many forwarded payloads instead of once per payload, and
+/// after a restart a room goes on from the end of its last reservation,
+/// skipping the numbers that were left in it.
+pub const SEQ_RESERVATION: u64 = 1024;
+
+/// One room's relay sequence numbers.  Forwarding holds its lock from
+/// taking a number until every recipient has been queued the frame, so
+/// payloads reach each of them in order without holding up other rooms.
+#[derive(Default)]
+pub struct RoomSequence {
+    state: Mutex<SequenceState>,
+    /// Saves the end of each new reservation, for stores that keep
+    /// sequences across restarts.
+    reserve: Option<Box<dyn Fn(u64) + Send + Sync>>,
+}
+
+#[derive(Debug, Default)]
+struct SequenceState {
+    next: u64,
+    /// Numbers below this have been reserved.
+    reserved_until: u64,
+}
+
+impl std::fmt::Debug for RoomSequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoomSequence")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RoomSequence {
+    /// A sequence going on from `next` that passes the end of each
+    /// [`SEQ_RESERVATION`] it takes numbers from to `reserve`.
+    #[must_use]
+    pub fn saved(next: u64, reserve: impl Fn(u64) + Send + Sync + 'static) -> Self {
+        Self {
+            state: Mutex::new(SequenceState {
+                next,
+                reserved_until: next,
+            }),
+            reserve: Some(Box::new(reserve)),
+        }
+    }
+
+    /// Take the next number and run `forward` with it, with the sequence
+    /// locked until `forward` returns.
+    pub fn with_next<R>(&self, forward: impl FnOnce(u64) -> R) -> R {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = state.next;
+        state.next += 1;
+        if let Some(reserve) = &self.reserve
+            && seq >= state.reserved_until
+        {
+            state.reserved_until = seq + SEQ_RESERVATION;
+            reserve(state.reserved_until);
+        }
+        forward(seq)
+    }
+}
+
+pub trait RoomStore: Send + Sync + std::fmt::Debug {
+    /// Add `member` to `room_id`, replacing a member with the same device
+    /// id.  Room capacity is checked by the caller.
+    fn join(&mut self, room_id: &RoomId, member: Member);
+
+    /// Remove `device_id` from `room_id`.  A room is forgotten, and its
+    /// sequence numbers start again at 0, once it has no members.
+    fn leave(&mut self, room_id: &RoomId, device_id: &DeviceId) -> Option<Member>;
+
+    /// Members of `room_id`, empty if there is no such room.
+    fn members(&self, room_id: &RoomId) -> Vec<Member>;
+
+    /// The relay sequence of `room_id`, if it has members.
+    fn sequence(&self, room_id: &RoomId) -> Option<Arc<RoomSequence>>;
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct RoomRecord {
+    pub(crate) members: HashMap<DeviceId, Member>,
+    pub(crate) sequence: Arc<RoomSequence>,
+}
+
+/// Rooms in memory only; everything is lost when the relay stops.
+#[derive(Debug, Default)]
+pub struct MemoryRoomStore {
+    pub(crate) rooms: HashMap<RoomId, RoomRecord>,
+}
+
+impl RoomStore for MemoryRoomStore {
+    fn join(&mut self, room_id: &RoomId, member: Member) {
+        self.rooms
+            .entry(room_id.clone())
+            .or_default()
+            .members
+            .insert(member.peer.device_id.clone(), member);
+    }
+
+    fn leave(&mut self, room_id: &RoomId, device_id: &DeviceId) -> Option<Member> {
+        let room = self.rooms.get_mut(room_id)?;
+        let member = room.members.remove(device_id);
+        if room.members.is_empty() {
+            self.rooms.remove(room_id);
+        }
+        member
+    }
+
+    fn members(&self, room_id: &RoomId) -> Vec<Member> {
+        self.rooms
+            .get(room_id)
+            .map(|room| room.members.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn sequence(&self, room_id: &RoomId) -> Option<Arc<RoomSequence>> {
+        let room = self.rooms.get(room_id)?;
+        (!room.members.is_empty()).then(|| Arc::clone(&room.sequence))
+    }
+}
@@ -0,0 +1,104 @@
+//! Strikes and temporary bans for clients that keep sending frames the
+//! relay cannot use.
+//!
+//! Each undecodable or oversized frame, payload under another device's id
+//! or control message after the Hello is a strike against the connection.
+//! At `max_strikes` within `window` the relay closes the connection and
+//! refuses new connections from the same address for `ban`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrikePolicy {
+    pub max_strikes: u32,
+    pub window: Duration,
+    pub ban: Duration,
+}
+
+impl Default for StrikePolicy {
+    fn default() -> Self {
+        Self {
+            max_strikes: 20,
+            window: Duration::from_secs(60),
+            ban: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Strikes of one connection within the policy window.
+#[derive(Debug)]
+pub(crate) struct StrikeCounter {
+    policy: StrikePolicy,
+    strikes: VecDeque<Instant>,
+}
+
+impl StrikeCounter {
+    pub(crate) fn new(policy: StrikePolicy) -> Self {
+        Self {
+            policy,
+            strikes: VecDeque::new(),
+        }
+    }
+
+    /// Record a strike; `true` once the connection has reached the limit.
+    pub(crate) fn strike(&mut self, now: Instant) -> bool {
+        while self
+            .strikes
+            .front()
+            .is_some_and(|first| now.saturating_duration_since(*first) >= self.policy.window)
+        {
+            self.strikes.pop_front();
+        }
+        self.strikes.push_back(now);
+        self.strikes.len() >= self.policy.max_strikes as usize
+    }
+}
+
+/// Addresses refused until the time stored with them.
+#[derive(Debug, Default)]
+pub(crate) struct BanList {
+    until: HashMap<IpAddr, Instant>,
+}
+
+impl BanList {
+    pub(crate) fn ban(&mut self, ip: IpAddr, now: Instant, duration: Duration) {
+        self.until.insert(ip, now + duration);
+    }
+
+    /// Time left on the ban of `ip`, if it is banned.
+    pub(crate) fn remaining(&mut self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        self.until.retain(|_, until| *until > now);
+        self.until
+            .get(&ip)
+            .map(|until| until.saturating_duration_since(now))
+    }
+
+    pub(crate) fn active(&mut self, now: Instant) -> usize {
+        self.until.retain(|_, until| *until > now);
+        self.until.len()
+    }
+}
+
+/// Counters served on `/metrics`.
+#[derive(Debug, Default)]
+pub(crate) struct StrikeMetrics {
+    pub(crate) strikes: AtomicU64,
+    pub(crate) disconnects: AtomicU64,
+    pub(crate) bans: AtomicU64,
+    pub(crate) rejected_while_banned: AtomicU64,
+}
+
+impl StrikeMetrics {
+    pub(crate) fn add(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(counter: &AtomicU64) -> u64 {
+        counter.load(Ordering::Relaxed)
+    }
+}
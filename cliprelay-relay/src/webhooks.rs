@@ -0,0 +1,191 @@
+//! Webhooks on room membership: a signed JSON POST to a configured URL when
+//! a device joins or leaves a room, or is refused.
+//!
+//! Room ids are hashed again before they leave the relay, so a webhook log
+//! cannot be used to join the room; device ids and names are sent as the
+//! devices announced them.  The body is signed with HMAC-SHA256 under the
+//! shared secret, hex-encoded in `X-ClipRelay-Signature: sha256=<hex>`.
+//! Events beyond `max_per_minute` are dropped; failed deliveries (network
+//! errors, 429 and 5xx) are retried with exponential backoff.
+
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use ring::{digest, hmac};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::TokenBucket;
+
+/// Header carrying the body's signature.
+pub const SIGNATURE_HEADER: &str = "x-cliprelay-signature";
+
+const ROOM_HASH_CONTEXT: &[u8] = b"cliprelay webhook room";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// HMAC key the receiver verifies signatures with.
+    pub secret: String,
+    pub max_per_minute: u32,
+    /// Tries per event, the first included.
+    pub max_attempts: u32,
+}
+
+impl WebhookConfig {
+    pub fn new(url: String, secret: String) -> Self {
+        Self {
+            url,
+            secret,
+            max_per_minute: 30,
+            max_attempts: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WebhookEventKind {
+    Join,
+    Leave,
+    Reject,
+}
+
+/// Body of one webhook request.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct WebhookEvent {
+    pub(crate) event: WebhookEventKind,
+    /// See [`hash_room_id`].
+    pub(crate) room: String,
+    pub(crate) device_id: String,
+    pub(crate) device_name: String,
+    /// Why a device was refused.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) reason: Option<String>,
+    /// Devices in the room after the event.
+    pub(crate) devices_in_room: usize,
+    pub(crate) at_unix_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct WebhookMetrics {
+    pub(crate) delivered: AtomicU64,
+    pub(crate) failed: AtomicU64,
+    pub(crate) dropped: AtomicU64,
+}
+
+impl WebhookMetrics {
+    /// Delivered, failed and dropped events.
+    pub(crate) fn totals(&self) -> (u64, u64, u64) {
+        (
+            self.delivered.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Webhooks {
+    config: WebhookConfig,
+    client: reqwest::Client,
+    key: hmac::Key,
+    limit: Mutex<TokenBucket>,
+    pub(crate) metrics: WebhookMetrics,
+}
+
+impl Webhooks {
+    pub(crate) fn new(config: WebhookConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| format!("webhook client: {err}"))?;
+        let per_minute = f64::from(config.max_per_minute.max(1));
+        Ok(Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, config.secret.as_bytes()),
+            client,
+            limit: Mutex::new(TokenBucket::new(per_minute, per_minute / 60.0)),
+            metrics: WebhookMetrics::default(),
+            config,
+        })
+    }
+
+    /// Queue `event` for delivery, unless the rate limit is reached.
+    pub(crate) fn fire(self: &Arc<Self>, event: WebhookEvent) {
+        let allowed = self
+            .limit
+            .lock()
+            .map(|mut limit| limit.consume(1.0))
+            .unwrap_or(false);
+        if !allowed {
+            self.metrics.dropped.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "webhook rate limit reached, dropping {:?} event",
+                event.event
+            );
+            return;
+        }
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(err) => {
+                warn!("failed to serialize webhook event: {}", err);
+                return;
+            }
+        };
+        let webhooks = Arc::clone(self);
+        tokio::spawn(async move { webhooks.deliver(body).await });
+    }
+
+    async fn deliver(&self, body: Vec<u8>) {
+        let signature = format!("sha256={}", hex::encode(hmac::sign(&self.key, &body)));
+        let attempts = self.config.max_attempts.max(1);
+        let mut delay = FIRST_RETRY_DELAY;
+        for attempt in 1..=attempts {
+            let result = self
+                .client
+                .post(&self.config.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await;
+            let retry = match result {
+                Ok(response) if response.status().is_success() => {
+                    self.metrics.delivered.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    warn!("webhook attempt {} answered {}", attempt, status);
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(err) => {
+                    warn!("webhook attempt {} failed: {}", attempt, err);
+                    true
+                }
+            };
+            if !retry || attempt == attempts {
+                break;
+            }
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        self.metrics.failed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// First 16 bytes of `SHA-256("cliprelay webhook room" || room_id)`, in
+/// hex: stable per room, but not the id clients join with.
+pub fn hash_room_id(room_id: &str) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(ROOM_HASH_CONTEXT);
+    context.update(room_id.as_bytes());
+    hex::encode(&context.finish().as_ref()[..16])
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{net::SocketAddr, time::Duration};
 
 // ── Test timeout constants ─────────────────────────────────────────────────
 //
@@ -18,13 +18,25 @@ const DRAIN_TIMEOUT: Duration = Duration::from_millis(120);
 const OVERFLOW_SETTLE: Duration = Duration::from_millis(200);
 
 use cliprelay_core::{
-    ControlMessage, EncryptedPayload, Hello, MAX_DEVICES_PER_ROOM, PeerInfo, WireMessage,
-    decode_frame, encode_frame,
+    ControlMessage, EncryptedPayload, Hello, LatencyProbe, MAX_DEVICES_PER_ROOM, PeerInfo,
+    RelayErrorCode, WireMessage, decode_frame, encode_frame,
+};
+use cliprelay_relay::{
+    AppState, ConnectionLifetime, Member, MemoryRoomStore, RoomSequence, RoomStore,
+    SEQ_RESERVATION, SIGNATURE_HEADER, StrikePolicy, WebhookConfig, build_router, hash_room_id,
 };
-use cliprelay_relay::{AppState, build_router};
 use futures::{SinkExt, StreamExt};
-use tokio::{net::TcpListener, sync::oneshot, time::timeout};
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite::Message};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot},
+    time::timeout,
+};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async,
+    tungstenite::{
+        Message, client::IntoClientRequest, handshake::client::Request, http::HeaderValue,
+    },
+};
 
 type WsStream = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
 type WsWrite = futures::stream::SplitSink<WsStream, Message>;
@@ -49,6 +61,7 @@ async fn encrypted_payload_is_forwarded_to_other_peers_only() {
         sender_device_id: "dev-a".to_owned(),
         counter: 1,
         ciphertext: vec![9, 8, 7, 6, 5],
+        relay_seq: None,
     };
 
     let frame = encode_frame(&WireMessage::Encrypted(payload.clone())).expect("encode payload");
@@ -72,6 +85,54 @@ async fn encrypted_payload_is_forwarded_to_other_peers_only() {
     let _ = shutdown_tx.send(());
 }
 
+#[tokio::test]
+async fn sequenced_peers_see_one_increasing_order() {
+    let (address, shutdown_tx) = start_relay().await;
+
+    let mut client_a = connect_client_with(&address, "room-seq", "dev-a", "Device A", true).await;
+    let mut client_b = connect_client_with(&address, "room-seq", "dev-b", "Device B", true).await;
+    let mut legacy = connect_client(&address, "room-seq", "dev-c", "Device C").await;
+
+    drain_non_encrypted(&mut client_a).await;
+    drain_non_encrypted(&mut client_b).await;
+    drain_non_encrypted(&mut legacy).await;
+
+    send_sequenced_test_payload(&mut client_a, "dev-a", 1).await;
+    send_sequenced_test_payload(&mut client_b, "dev-b", 1).await;
+    send_sequenced_test_payload(&mut client_a, "dev-a", 2).await;
+
+    let mut seen_by_a = Vec::new();
+    while let Some(payload) = recv_encrypted_payload(&mut client_a, NO_RECV_TIMEOUT).await {
+        seen_by_a.push(payload.relay_seq);
+    }
+    let mut seen_by_b = Vec::new();
+    while let Some(payload) = recv_encrypted_payload(&mut client_b, NO_RECV_TIMEOUT).await {
+        seen_by_b.push(payload.relay_seq);
+    }
+    // Each sees the other's payloads; all three came in a single room order.
+    assert_eq!(seen_by_a.len(), 1);
+    assert_eq!(seen_by_b.len(), 2);
+    let mut all: Vec<u64> = seen_by_a
+        .iter()
+        .chain(&seen_by_b)
+        .flatten()
+        .copied()
+        .collect();
+    all.sort_unstable();
+    assert_eq!(all, vec![0, 1, 2]);
+    assert!(seen_by_b[0] < seen_by_b[1]);
+
+    // A client that did not ask for sequence numbers gets frames it can decode.
+    for _ in 0..3 {
+        let payload = recv_encrypted_payload(&mut legacy, RECV_TIMEOUT)
+            .await
+            .expect("legacy client receives payload");
+        assert_eq!(payload.relay_seq, None);
+    }
+
+    let _ = shutdown_tx.send(());
+}
+
 #[tokio::test]
 async fn oversized_binary_frame_is_dropped_and_not_forwarded() {
     let (address, shutdown_tx) = start_relay().await;
@@ -109,6 +170,7 @@ async fn invalid_first_frame_is_rejected() {
         sender_device_id: "dev-x".to_owned(),
         counter: 1,
         ciphertext: vec![1, 2, 3],
+        relay_seq: None,
     };
     let frame = encode_frame(&WireMessage::Encrypted(invalid_first)).expect("encode encrypted");
     write
@@ -143,6 +205,7 @@ async fn sender_identity_mismatch_is_dropped() {
         sender_device_id: "dev-spoofed".to_owned(),
         counter: 1,
         ciphertext: vec![7, 7, 7],
+        relay_seq: None,
     };
     let frame = encode_frame(&WireMessage::Encrypted(spoofed_payload)).expect("encode payload");
     client_a
@@ -185,6 +248,227 @@ async fn malformed_binary_frame_is_dropped_and_not_forwarded() {
     let _ = shutdown_tx.send(());
 }
 
+#[tokio::test]
+async fn repeated_bad_frames_close_the_connection_and_ban_the_address() {
+    let policy = StrikePolicy {
+        max_strikes: 3,
+        window: Duration::from_secs(60),
+        ban: Duration::from_secs(60),
+    };
+    let state = AppState::new()
+        .with_strike_policy(policy)
+        .with_trusted_proxies(["127.0.0.1".parse().expect("ip")]);
+    let (address, shutdown_tx) = start_relay_with(state).await;
+
+    let mut client_a = connect_with_hello(
+        proxied_request(&address, "198.51.100.1, 203.0.113.7"),
+        test_hello("room-strikes", "dev-a"),
+    )
+    .await;
+    drain_non_encrypted(&mut client_a).await;
+    send_bad_frames_until_closed(&mut client_a, policy.max_strikes).await;
+
+    match connect_async(proxied_request(&address, "203.0.113.7")).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 429);
+            assert!(response.headers().contains_key("retry-after"));
+        }
+        other => panic!("expected the banned address to be refused, got {other:?}"),
+    }
+    assert!(
+        connect_async(proxied_request(&address, "203.0.113.8"))
+            .await
+            .is_ok(),
+        "other clients of the proxy are not banned"
+    );
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn loopback_and_untrusted_forwarded_addresses_are_never_banned() {
+    let policy = StrikePolicy {
+        max_strikes: 3,
+        window: Duration::from_secs(60),
+        ban: Duration::from_secs(60),
+    };
+    let (address, shutdown_tx) = start_relay_with(AppState::new().with_strike_policy(policy)).await;
+
+    // No proxy is trusted, so the header is ignored and the address is
+    // loopback, which stays reachable for everyone behind a local proxy.
+    let mut client_a = connect_with_hello(
+        proxied_request(&address, "203.0.113.7"),
+        test_hello("room-strikes-loopback", "dev-a"),
+    )
+    .await;
+    drain_non_encrypted(&mut client_a).await;
+    send_bad_frames_until_closed(&mut client_a, policy.max_strikes).await;
+
+    for request in [
+        address
+            .as_str()
+            .into_client_request()
+            .expect("client request"),
+        proxied_request(&address, "203.0.113.7"),
+    ] {
+        assert!(connect_async(request).await.is_ok(), "loopback was banned");
+    }
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn maintenance_is_announced_refuses_joins_and_closes_connections() {
+    let state = AppState::new();
+    let (address, shutdown_tx) = start_relay_with(state.clone()).await;
+
+    let mut client_a = connect_client(&address, "room-maint", "dev-a", "Device A").await;
+    let mut client_b = connect_client(&address, "room-maint", "dev-b", "Device B").await;
+    drain_non_encrypted(&mut client_a).await;
+    drain_non_encrypted(&mut client_b).await;
+
+    let notice = state
+        .schedule_maintenance(
+            Duration::from_millis(500),
+            "upgrade".to_owned(),
+            Duration::from_secs(60),
+        )
+        .await;
+    for client in [&mut client_a, &mut client_b] {
+        let received = recv_next_wire_message(client, RECV_TIMEOUT).await;
+        assert_eq!(
+            received,
+            Some(WireMessage::Control(ControlMessage::Maintenance(
+                notice.clone()
+            )))
+        );
+    }
+
+    match connect_async(&address).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 503);
+            let retry_after: u64 = response.headers()["retry-after"]
+                .to_str()
+                .expect("ascii header")
+                .parse()
+                .expect("seconds");
+            assert!((60..=61).contains(&retry_after));
+        }
+        other => panic!("expected the join to be refused, got {other:?}"),
+    }
+
+    let closed = timeout(RECV_TIMEOUT, client_a.read.next())
+        .await
+        .expect("relay closes the connection at the scheduled time");
+    assert!(
+        matches!(closed, Some(Ok(Message::Close(Some(ref frame)))) if u16::from(frame.code) == 1012),
+        "expected a service-restart close, got {closed:?}"
+    );
+
+    assert!(state.cancel_maintenance().await);
+    let mut client_c = connect_client(&address, "room-maint", "dev-c", "Device C").await;
+    assert!(
+        recv_next_wire_message(&mut client_c, RECV_TIMEOUT)
+            .await
+            .is_some(),
+        "joins are accepted again after cancelling"
+    );
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn joins_are_accepted_again_once_the_maintenance_window_ends() {
+    let state = AppState::new();
+    let (address, shutdown_tx) = start_relay_with(state.clone()).await;
+
+    state
+        .schedule_maintenance(
+            Duration::from_millis(200),
+            "upgrade".to_owned(),
+            Duration::from_millis(300),
+        )
+        .await;
+    match connect_async(&address).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), 503);
+        }
+        other => panic!("expected the join to be refused, got {other:?}"),
+    }
+
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    let mut client = connect_client(&address, "room-maint-end", "dev-a", "Device A").await;
+    assert!(
+        recv_next_wire_message(&mut client, RECV_TIMEOUT)
+            .await
+            .is_some(),
+        "joins are accepted again after the downtime"
+    );
+    assert!(!state.cancel_maintenance().await, "nothing left to cancel");
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn old_connections_are_told_to_reconnect_and_closed_without_leaving() {
+    let state = AppState::new().with_connection_lifetime(ConnectionLifetime {
+        max_age: Duration::from_secs(3),
+        notice: Duration::from_millis(2_500),
+        jitter: Duration::ZERO,
+    });
+    let (address, shutdown_tx) = start_relay_with(state).await;
+
+    let mut client_a = connect_client(&address, "room-age", "dev-a", "Device A").await;
+    let notice = loop {
+        match recv_next_wire_message(&mut client_a, RECV_TIMEOUT).await {
+            Some(WireMessage::Control(ControlMessage::Reconnect(notice))) => break notice,
+            Some(_) => continue,
+            None => panic!("expected a reconnect notice"),
+        }
+    };
+    assert!(notice.at_unix_ms > 0);
+
+    // Reconnect as the same device well before the old connection is
+    // closed, leaving the new one time to outlive the check below.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let mut client_a2 = connect_client(&address, "room-age", "dev-a", "Device A").await;
+    drain_non_encrypted(&mut client_a2).await;
+
+    let closed = loop {
+        match timeout(RECV_TIMEOUT, client_a.read.next()).await {
+            Ok(Some(Ok(Message::Binary(_)))) => continue,
+            other => break other,
+        }
+    };
+    assert!(
+        matches!(closed, Ok(Some(Ok(Message::Close(Some(ref frame))))) if u16::from(frame.code) == 1000),
+        "expected a normal close, got {closed:?}"
+    );
+    drop(client_a);
+    tokio::time::sleep(OVERFLOW_SETTLE).await;
+
+    // The old connection going away did not take the device out of the room.
+    let mut client_b = connect_client(&address, "room-age", "dev-b", "Device B").await;
+    let peers = loop {
+        match recv_next_wire_message(&mut client_b, RECV_TIMEOUT).await {
+            Some(WireMessage::Control(ControlMessage::PeerList(list))) => break list.peers,
+            Some(_) => continue,
+            None => panic!("expected a peer list"),
+        }
+    };
+    assert!(peers.iter().any(|peer| peer.device_id == "dev-a"));
+    drain_non_encrypted(&mut client_b).await;
+    send_sequenced_test_payload(&mut client_b, "dev-b", 1).await;
+    assert!(
+        recv_encrypted_payload(&mut client_a2, RECV_TIMEOUT)
+            .await
+            .is_some(),
+        "the new connection receives room traffic"
+    );
+
+    let _ = shutdown_tx.send(());
+}
+
 #[tokio::test]
 async fn unexpected_control_after_hello_is_ignored() {
     let (address, shutdown_tx) = start_relay().await;
@@ -211,6 +495,7 @@ async fn unexpected_control_after_hello_is_ignored() {
         sender_device_id: "dev-a".to_owned(),
         counter: 2,
         ciphertext: vec![5, 4, 3, 2, 1],
+        relay_seq: None,
     };
     let payload_frame =
         encode_frame(&WireMessage::Encrypted(sender_payload.clone())).expect("encode payload");
@@ -226,6 +511,179 @@ async fn unexpected_control_after_hello_is_ignored() {
     let _ = shutdown_tx.send(());
 }
 
+#[tokio::test]
+async fn probes_are_stamped_echoed_and_forwarded_to_probing_peers() {
+    let (address, shutdown_tx) = start_relay().await;
+
+    let probing_hello = |device_id: &str| Hello {
+        room_id: "room-probe".to_owned(),
+        peer: PeerInfo {
+            device_id: device_id.to_owned(),
+            device_name: device_id.to_owned(),
+            sealed_chunks: false,
+        },
+        sequenced: true,
+        probes: true,
+    };
+    let mut client_a = connect_with_hello(&address, probing_hello("dev-a")).await;
+    let mut client_b = connect_with_hello(&address, probing_hello("dev-b")).await;
+    let mut client_c = connect_client(&address, "room-probe", "dev-c", "Device C").await;
+
+    drain_non_encrypted(&mut client_a).await;
+    drain_non_encrypted(&mut client_b).await;
+    drain_non_encrypted(&mut client_c).await;
+
+    let mut probe = LatencyProbe::new(7, 1_000);
+    // Claimed ids are replaced by the connection's own.
+    probe.sender_device_id = "dev-b".to_owned();
+    let frame =
+        encode_frame(&WireMessage::Control(ControlMessage::Probe(probe))).expect("encode probe");
+    client_a
+        .write
+        .send(Message::Binary(frame.into()))
+        .await
+        .expect("send probe");
+
+    let echoed = recv_probe(&mut client_a, RECV_TIMEOUT)
+        .await
+        .expect("probe echoed to its sender");
+    assert_eq!((echoed.probe_id, echoed.sent_unix_ms), (7, 1_000));
+    assert_eq!(echoed.sender_device_id, "dev-a");
+    let received = echoed.relay_received_unix_ms.expect("receive time");
+    let forwarded = echoed.relay_forwarded_unix_ms.expect("forward time");
+    assert!(received <= forwarded);
+
+    assert_eq!(recv_probe(&mut client_b, RECV_TIMEOUT).await, Some(echoed));
+    assert_eq!(recv_probe(&mut client_c, NO_RECV_TIMEOUT).await, None);
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn rooms_off_the_allow_list_are_refused_until_an_admin_adds_them() {
+    let state = AppState::new()
+        .with_allowed_rooms(["room-family".to_owned()])
+        .with_admin_token("admin-secret".to_owned());
+    let (address, shutdown_tx) = start_relay_with(state).await;
+    let rooms_url = address
+        .replace("ws://", "http://")
+        .replace("/ws", "/admin/rooms");
+    let admin = reqwest::Client::new();
+
+    let mut allowed = connect_client(&address, "room-family", "dev-a", "Device A").await;
+    assert!(matches!(
+        recv_next_wire_message(&mut allowed, RECV_TIMEOUT).await,
+        Some(WireMessage::Control(_))
+    ));
+    let mut refused = connect_client(&address, "room-other", "dev-b", "Device B").await;
+    assert_refused(&mut refused, RelayErrorCode::RoomNotAllowed).await;
+
+    let added = admin
+        .post(&rooms_url)
+        .bearer_auth("admin-secret")
+        .header("content-type", "application/json")
+        .body(r#"{"room_id":"room-other"}"#)
+        .send()
+        .await
+        .expect("add room");
+    assert_eq!(added.status(), 201);
+    let mut joined = connect_client(&address, "room-other", "dev-b", "Device B").await;
+    assert!(matches!(
+        recv_next_wire_message(&mut joined, RECV_TIMEOUT).await,
+        Some(WireMessage::Control(ControlMessage::PeerJoined(_)))
+    ));
+
+    let removed = admin
+        .delete(format!("{rooms_url}/room-other"))
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .expect("remove room");
+    assert_eq!(removed.status(), 204);
+    let listed = admin
+        .get(&rooms_url)
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .expect("list rooms")
+        .bytes()
+        .await
+        .expect("room list body");
+    let listed: serde_json::Value = serde_json::from_slice(&listed).expect("room list json");
+    assert_eq!(listed["rooms"], serde_json::json!(["room-family"]));
+    let mut refused = connect_client(&address, "room-other", "dev-c", "Device C").await;
+    assert_refused(&mut refused, RelayErrorCode::RoomNotAllowed).await;
+
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn allow_list_file_is_reloaded_when_it_changes() {
+    let path = std::env::temp_dir().join(format!("cliprelay-rooms-{}.txt", std::process::id()));
+    std::fs::write(&path, "# family\nroom-a\n").expect("write allow-list");
+    let state = AppState::new()
+        .with_allowed_rooms_file(path.clone())
+        .expect("read allow-list");
+    let (address, shutdown_tx) = start_relay_with(state).await;
+
+    let mut refused = connect_client(&address, "room-b", "dev-b", "Device B").await;
+    assert_refused(&mut refused, RelayErrorCode::RoomNotAllowed).await;
+
+    std::fs::write(&path, "room-a\nroom-b # added later\n").expect("rewrite allow-list");
+    // Past the relay's one-second recheck interval.
+    tokio::time::sleep(Duration::from_millis(1_200)).await;
+    let mut joined = connect_client(&address, "room-b", "dev-b", "Device B").await;
+    assert!(matches!(
+        recv_next_wire_message(&mut joined, RECV_TIMEOUT).await,
+        Some(WireMessage::Control(ControlMessage::PeerJoined(_)))
+    ));
+
+    let _ = std::fs::remove_file(&path);
+    let _ = shutdown_tx.send(());
+}
+
+#[tokio::test]
+async fn admin_edits_keep_the_allow_list_file_comments() {
+    let path =
+        std::env::temp_dir().join(format!("cliprelay-rooms-admin-{}.txt", std::process::id()));
+    std::fs::write(&path, "# family\nroom-a # laptop\nroom-b\n").expect("write allow-list");
+    let state = AppState::new()
+        .with_allowed_rooms_file(path.clone())
+        .expect("read allow-list")
+        .with_admin_token("admin-secret".to_owned());
+    let (address, shutdown_tx) = start_relay_with(state).await;
+    let rooms_url = address
+        .replace("ws://", "http://")
+        .replace("/ws", "/admin/rooms");
+    let admin = reqwest::Client::new();
+
+    let added = admin
+        .post(&rooms_url)
+        .bearer_auth("admin-secret")
+        .header("content-type", "application/json")
+        .body(r#"{"room_id":"room-c"}"#)
+        .send()
+        .await
+        .expect("add room");
+    assert_eq!(added.status(), 201);
+    let removed = admin
+        .delete(format!("{rooms_url}/room-b"))
+        .bearer_auth("admin-secret")
+        .send()
+        .await
+        .expect("remove room");
+    assert_eq!(removed.status(), 204);
+
+    let contents = std::fs::read_to_string(&path).expect("read allow-list");
+    assert_eq!(contents, "# family\nroom-a # laptop\nroom-c\n");
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    assert!(!std::path::Path::new(&tmp).exists());
+
+    let _ = std::fs::remove_file(&path);
+    let _ = shutdown_tx.send(());
+}
+
 #[tokio::test]
 async fn room_capacity_rejects_eleventh_device() {
     let (address, shutdown_tx) = start_relay().await;
@@ -250,6 +708,7 @@ async fn room_capacity_rejects_eleventh_device() {
         sender_device_id: "dev-1".to_owned(),
         counter: 42,
         ciphertext: vec![1, 2, 3, 4],
+        relay_seq: None,
     };
     let frame =
         encode_frame(&WireMessage::Encrypted(sender_payload.clone())).expect("encode payload");
@@ -273,17 +732,162 @@ async fn room_capacity_rejects_eleventh_device() {
     let _ = shutdown_tx.send(());
 }
 
+#[tokio::test]
+async fn join_and_leave_fire_signed_webhooks() {
+    let (hook_url, mut hooks) = start_webhook_receiver().await;
+    let state = AppState::new()
+        .with_webhooks(WebhookConfig::new(hook_url, "hook-secret".to_owned()))
+        .expect("webhook config");
+    let (address, shutdown_tx) = start_relay_with(state).await;
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"hook-secret");
+
+    let mut next_event = async || {
+        let (signature, body) = timeout(RECV_TIMEOUT, hooks.recv())
+            .await
+            .expect("webhook within timeout")
+            .expect("webhook receiver open");
+        let signature = signature
+            .strip_prefix("sha256=")
+            .and_then(|hex_signature| hex::decode(hex_signature).ok())
+            .expect("sha256 signature");
+        ring::hmac::verify(&key, &body, &signature).expect("signature verifies");
+        serde_json::from_slice::<serde_json::Value>(&body).expect("webhook body json")
+    };
+
+    let client = connect_client(&address, "room-hook", "dev-a", "Device A").await;
+    let joined = next_event().await;
+    assert_eq!(joined["event"], "join");
+    assert_eq!(joined["room"], hash_room_id("room-hook"));
+    assert_ne!(joined["room"], "room-hook");
+    assert_eq!(joined["device_id"], "dev-a");
+    assert_eq!(joined["device_name"], "Device A");
+    assert_eq!(joined["devices_in_room"], 1);
+
+    drop(client);
+    let left = next_event().await;
+    assert_eq!(left["event"], "leave");
+    assert_eq!(left["device_id"], "dev-a");
+    assert_eq!(left["devices_in_room"], 0);
+
+    let _ = shutdown_tx.send(());
+}
+
+#[test]
+fn memory_room_store_keeps_sequence_per_room() {
+    check_room_store(&mut MemoryRoomStore::default());
+}
+
+#[cfg(feature = "sled-store")]
+#[test]
+fn sled_room_store_keeps_sequence_across_restarts() {
+    use cliprelay_relay::SledRoomStore;
+
+    let path = std::env::temp_dir().join(format!("cliprelay-room-store-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let room = "room-store".to_owned();
+    {
+        let mut store = SledRoomStore::open(&path).expect("open room store");
+        check_room_store(&mut store);
+        store.join(&room, member("dev-a"));
+        assert_eq!(next_seq(&store, &room), Some(0));
+    }
+
+    let mut store = SledRoomStore::open(&path).expect("reopen room store");
+    assert!(store.members(&room).is_empty());
+    store.join(&room, member("dev-b"));
+    assert_eq!(
+        next_seq(&store, &room),
+        Some(SEQ_RESERVATION),
+        "goes on after the reserved numbers"
+    );
+    drop(store);
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[test]
+fn saved_room_sequences_are_written_once_per_reservation() {
+    use std::sync::{Arc, Mutex};
+
+    let reservations = Arc::new(Mutex::new(Vec::new()));
+    let sequence = RoomSequence::saved(5, {
+        let reservations = Arc::clone(&reservations);
+        move |reserved_until| reservations.lock().expect("lock").push(reserved_until)
+    });
+    for expected in 5..5 + 2 * SEQ_RESERVATION + 1 {
+        assert_eq!(sequence.with_next(|seq| seq), expected);
+    }
+    assert_eq!(
+        *reservations.lock().expect("lock"),
+        vec![
+            5 + SEQ_RESERVATION,
+            5 + 2 * SEQ_RESERVATION,
+            5 + 3 * SEQ_RESERVATION
+        ]
+    );
+}
+
+fn check_room_store(store: &mut impl RoomStore) {
+    let room = "room-check".to_owned();
+    assert_eq!(next_seq(&*store, &room), None);
+
+    store.join(&room, member("dev-a"));
+    store.join(&room, member("dev-b"));
+    assert_eq!(store.members(&room).len(), 2);
+    assert_eq!(next_seq(&*store, &room), Some(0));
+    assert_eq!(next_seq(&*store, &room), Some(1));
+
+    assert_eq!(
+        store.leave(&room, &"dev-a".to_owned()),
+        Some(member("dev-a"))
+    );
+    assert_eq!(next_seq(&*store, &room), Some(2));
+    store.leave(&room, &"dev-b".to_owned());
+    store.join(&room, member("dev-a"));
+    assert_eq!(
+        next_seq(&*store, &room),
+        Some(0),
+        "an emptied room starts again"
+    );
+    store.leave(&room, &"dev-a".to_owned());
+    assert_eq!(next_seq(&*store, &room), None);
+}
+
+fn next_seq(store: &impl RoomStore, room: &str) -> Option<u64> {
+    store
+        .sequence(&room.to_owned())
+        .map(|sequence| sequence.with_next(|seq| seq))
+}
+
+fn member(device_id: &str) -> Member {
+    Member {
+        peer: PeerInfo {
+            device_id: device_id.to_owned(),
+            device_name: device_id.to_owned(),
+            sealed_chunks: false,
+        },
+        sequenced: true,
+        probes: false,
+    }
+}
+
 async fn start_relay() -> (String, oneshot::Sender<()>) {
+    start_relay_with(AppState::new()).await
+}
+
+async fn start_relay_with(state: AppState) -> (String, oneshot::Sender<()>) {
     let listener = TcpListener::bind("127.0.0.1:0")
         .await
         .expect("bind ephemeral relay socket");
     let address = listener.local_addr().expect("relay local addr");
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
 
-    let server =
-        axum::serve(listener, build_router(AppState::new())).with_graceful_shutdown(async {
-            let _ = shutdown_rx.await;
-        });
+    let server = axum::serve(
+        listener,
+        build_router(state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async {
+        let _ = shutdown_rx.await;
+    });
     tokio::spawn(async move {
         let _ = server.await;
     });
@@ -291,23 +895,112 @@ async fn start_relay() -> (String, oneshot::Sender<()>) {
     (format!("ws://{}/ws", address), shutdown_tx)
 }
 
+/// An HTTP server that passes each POST's signature header and body on.
+async fn start_webhook_receiver() -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind ephemeral webhook socket");
+    let address = listener.local_addr().expect("webhook local addr");
+    let (hooks_tx, hooks_rx) = mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/hook",
+        axum::routing::post(
+            move |headers: axum::http::HeaderMap, body: axum::body::Bytes| async move {
+                let signature = headers
+                    .get(SIGNATURE_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_owned();
+                let _ = hooks_tx.send((signature, body.to_vec()));
+            },
+        ),
+    );
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    (format!("http://{}/hook", address), hooks_rx)
+}
+
 async fn connect_client(
     ws_url: &str,
     room_id: &str,
     device_id: &str,
     device_name: &str,
 ) -> TestClient {
-    let (ws_stream, _) = connect_async(ws_url).await.expect("connect websocket");
-    let (mut write, read) = ws_stream.split();
+    connect_client_with(ws_url, room_id, device_id, device_name, false).await
+}
 
-    let hello = WireMessage::Control(ControlMessage::Hello(Hello {
+fn test_hello(room_id: &str, device_id: &str) -> Hello {
+    Hello {
         room_id: room_id.to_owned(),
         peer: PeerInfo {
             device_id: device_id.to_owned(),
-            device_name: device_name.to_owned(),
+            device_name: device_id.to_owned(),
+            sealed_chunks: false,
         },
-    }));
-    let frame = encode_frame(&hello).expect("encode hello");
+        sequenced: false,
+        probes: false,
+    }
+}
+
+/// Send `count` undecodable frames and wait for the relay to close.
+async fn send_bad_frames_until_closed(client: &mut TestClient, count: u32) {
+    for _ in 0..count {
+        client
+            .write
+            .send(Message::Binary(vec![0xFF, 0x00, 0xAB, 0xCD].into()))
+            .await
+            .expect("send malformed frame");
+    }
+    let closed = timeout(RECV_TIMEOUT, async {
+        while let Some(Ok(message)) = client.read.next().await {
+            if matches!(message, Message::Close(_)) {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "relay kept the connection open");
+}
+
+async fn connect_client_with(
+    ws_url: &str,
+    room_id: &str,
+    device_id: &str,
+    device_name: &str,
+    sequenced: bool,
+) -> TestClient {
+    connect_with_hello(
+        ws_url,
+        Hello {
+            room_id: room_id.to_owned(),
+            peer: PeerInfo {
+                device_id: device_id.to_owned(),
+                device_name: device_name.to_owned(),
+                sealed_chunks: false,
+            },
+            sequenced,
+            probes: false,
+        },
+    )
+    .await
+}
+
+/// A connection request as a reverse proxy forwards it for `client_ip`.
+fn proxied_request(ws_url: &str, client_ip: &'static str) -> Request {
+    let mut request = ws_url.into_client_request().expect("client request");
+    request
+        .headers_mut()
+        .insert("x-forwarded-for", HeaderValue::from_static(client_ip));
+    request
+}
+
+async fn connect_with_hello(request: impl IntoClientRequest + Unpin, hello: Hello) -> TestClient {
+    let (ws_stream, _) = connect_async(request).await.expect("connect websocket");
+    let (mut write, read) = ws_stream.split();
+
+    let frame =
+        encode_frame(&WireMessage::Control(ControlMessage::Hello(hello))).expect("encode hello");
     write
         .send(Message::Binary(frame.into()))
         .await
@@ -316,6 +1009,41 @@ async fn connect_client(
     TestClient { write, read }
 }
 
+/// The next message is an error with `code`, then the relay closes.
+async fn assert_refused(client: &mut TestClient, code: RelayErrorCode) {
+    let received = recv_next_wire_message(client, RECV_TIMEOUT).await;
+    assert!(
+        matches!(
+            received,
+            Some(WireMessage::Control(ControlMessage::Error { code: Some(got), .. })) if got == code
+        ),
+        "expected a {code:?} error, got {received:?}"
+    );
+    let closed = timeout(RECV_TIMEOUT, client.read.next())
+        .await
+        .expect("relay closes a refused connection");
+    assert!(
+        matches!(closed, Some(Ok(Message::Close(_))) | None),
+        "expected a close, got {closed:?}"
+    );
+}
+
+async fn send_sequenced_test_payload(client: &mut TestClient, device_id: &str, counter: u64) {
+    let payload = EncryptedPayload {
+        sender_device_id: device_id.to_owned(),
+        counter,
+        ciphertext: vec![1, 2, 3],
+        // Overwritten by the relay.
+        relay_seq: Some(99),
+    };
+    let frame = encode_frame(&WireMessage::Encrypted(payload)).expect("encode payload");
+    client
+        .write
+        .send(Message::Binary(frame.into()))
+        .await
+        .expect("send encrypted payload");
+}
+
 async fn drain_non_encrypted(client: &mut TestClient) {
     loop {
         match recv_next_wire_message(client, DRAIN_TIMEOUT).await {
@@ -326,6 +1054,18 @@ async fn drain_non_encrypted(client: &mut TestClient) {
     }
 }
 
+async fn recv_probe(client: &mut TestClient, wait: Duration) -> Option<LatencyProbe> {
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        let remaining = deadline.checked_duration_since(tokio::time::Instant::now())?;
+        match recv_next_wire_message(client, remaining).await {
+            Some(WireMessage::Control(ControlMessage::Probe(probe))) => return Some(probe),
+            Some(_) => continue,
+            None => return None,
+        }
+    }
+}
+
 async fn recv_encrypted_payload(
     client: &mut TestClient,
     wait: Duration,
@@ -0,0 +1,150 @@
+//! What the browser client does with relay frames, without touching the
+//! browser: wraps a [`SessionState`] and reduces its events to the ones a
+//! text-only web page shows.
+
+use cliprelay_core::{
+    CoreError, MIME_TEXT_PLAIN, MaintenanceNotice, PeerInfo, ReconnectNotice,
+    session::{SessionEvent, SessionState},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebEvent {
+    /// The room key is ready: text can be sent and received.
+    Ready,
+    /// The other devices in the room.
+    Peers(Vec<PeerInfo>),
+    /// Text from another device, with the name it goes by.
+    Received { from: String, text: String },
+    /// The relay refused the connection, e.g. a full room.
+    RelayError(String),
+    /// The relay will close the connection for maintenance.
+    Maintenance(MaintenanceNotice),
+    /// The relay will close this connection and asks for a new one.
+    ReconnectSoon(ReconnectNotice),
+}
+
+#[derive(Debug)]
+pub struct Driver {
+    state: SessionState,
+}
+
+impl Driver {
+    pub fn new(
+        room_code: &str,
+        device_id: &str,
+        device_name: &str,
+        counter: u64,
+    ) -> Result<Self, CoreError> {
+        let local = PeerInfo {
+            device_id: device_id.to_owned(),
+            device_name: device_name.to_owned(),
+            sealed_chunks: false,
+        };
+        Ok(Self {
+            state: SessionState::new(room_code, local, counter)?,
+        })
+    }
+
+    /// The frame that joins the room; send it once the socket opens.
+    pub fn hello_frame(&self) -> Result<Vec<u8>, CoreError> {
+        self.state.hello_frame()
+    }
+
+    /// Process one binary frame from the relay.  Events other than plain
+    /// text (file chunks, cancels) are ignored.
+    pub fn handle_frame(&mut self, frame: &[u8]) -> Result<Option<WebEvent>, CoreError> {
+        Ok(match self.state.handle_frame(frame)? {
+            Some(SessionEvent::RoomKeyReady) => Some(WebEvent::Ready),
+            Some(SessionEvent::PeersChanged(peers)) => Some(WebEvent::Peers(peers)),
+            Some(SessionEvent::RelayError(message)) => Some(WebEvent::RelayError(message)),
+            Some(SessionEvent::Maintenance(notice)) => Some(WebEvent::Maintenance(notice)),
+            Some(SessionEvent::ReconnectSoon(notice)) => Some(WebEvent::ReconnectSoon(notice)),
+            Some(SessionEvent::Clipboard { event, .. }) if event.mime == MIME_TEXT_PLAIN => {
+                let from = self
+                    .state
+                    .peers()
+                    .iter()
+                    .find(|p| p.device_id == event.sender_device_id)
+                    .map(|p| p.device_name.clone())
+                    .unwrap_or(event.sender_device_id);
+                Some(WebEvent::Received {
+                    from,
+                    text: event.text_utf8,
+                })
+            }
+            Some(SessionEvent::Clipboard { .. }) | None => None,
+        })
+    }
+
+    /// Encrypt `text` as the next event from this device and frame it.
+    pub fn seal_text(&mut self, text: &str, timestamp_unix_ms: u64) -> Result<Vec<u8>, CoreError> {
+        self.state.seal_text(text, timestamp_unix_ms)
+    }
+
+    pub fn room_key_ready(&self) -> bool {
+        self.state.room_key_ready()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cliprelay_core::{
+        ControlMessage, PeerList, SaltExchange, WireMessage, encode_frame, room_id_from_code,
+    };
+
+    fn control(message: ControlMessage) -> Vec<u8> {
+        encode_frame(&WireMessage::Control(message)).unwrap()
+    }
+
+    #[test]
+    fn text_round_trips_and_names_the_sender() {
+        let mut a = Driver::new("web-room", "a", "Browser", 0).unwrap();
+        let mut b = Driver::new("web-room", "b", "Laptop", 0).unwrap();
+        let peers = control(ControlMessage::PeerList(PeerList {
+            room_id: room_id_from_code("web-room"),
+            peers: vec![
+                PeerInfo {
+                    device_id: "a".to_owned(),
+                    device_name: "Browser".to_owned(),
+                    sealed_chunks: false,
+                },
+                PeerInfo {
+                    device_id: "b".to_owned(),
+                    device_name: "Laptop".to_owned(),
+                    sealed_chunks: false,
+                },
+            ],
+        }));
+        let salt = control(ControlMessage::SaltExchange(SaltExchange {
+            room_id: room_id_from_code("web-room"),
+            device_ids: vec!["a".to_owned(), "b".to_owned()],
+        }));
+        for driver in [&mut a, &mut b] {
+            assert!(matches!(
+                driver.handle_frame(&peers).unwrap(),
+                Some(WebEvent::Peers(peers)) if peers.len() == 1
+            ));
+            assert_eq!(driver.handle_frame(&salt).unwrap(), Some(WebEvent::Ready));
+        }
+
+        let frame = a.seal_text("from the browser", 1).unwrap();
+        assert_eq!(
+            b.handle_frame(&frame).unwrap(),
+            Some(WebEvent::Received {
+                from: "Browser".to_owned(),
+                text: "from the browser".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn sending_before_the_room_key_fails() {
+        let mut driver = Driver::new("web-room", "a", "Browser", 0).unwrap();
+        assert!(!driver.room_key_ready());
+        assert!(matches!(
+            driver.seal_text("early", 1),
+            Err(CoreError::RoomKeyNotReady)
+        ));
+    }
+}
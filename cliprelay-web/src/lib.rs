@@ -0,0 +1,213 @@
+//! Browser ClipRelay client: `cliprelay-core` compiled to WebAssembly, with
+//! a small connect/send/receive API over the browser's WebSocket so that a
+//! static page can join a room like any other device.
+//!
+//! ```js
+//! import init, { WebClient } from "./pkg/cliprelay_web.js";
+//! await init();
+//! const client = new WebClient(url, roomCode, deviceId, "Browser", (event) => {
+//!   if (event.type === "received") console.log(event.from, event.text);
+//! });
+//! client.sendText("hello");
+//! ```
+//!
+//! Events passed to the callback are plain objects with a `type` of
+//! `connected`, `ready`, `peers` (`peers`: array of `{deviceId, deviceName}`),
+//! `received` (`from`, `text`), `error` (`message`), `maintenance`
+//! (`atUnixMs`, `message`: a line to show), `reconnect` (`atUnixMs`: when
+//! the relay closes this connection) or `closed` (`reason`).
+//! The client does not reconnect; create a new one after `reconnect` or
+//! `closed`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use js_sys::{Array, Function, Object, Reflect, Uint8Array};
+use wasm_bindgen::{JsCast, prelude::*};
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+pub mod driver;
+
+use driver::{Driver, WebEvent};
+
+/// One connection to a room.
+#[wasm_bindgen]
+pub struct WebClient {
+    socket: WebSocket,
+    driver: Rc<RefCell<Driver>>,
+    // Held so the handlers stay alive as long as the socket can call them.
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+#[wasm_bindgen]
+impl WebClient {
+    /// Connect to the relay at `server_url` (e.g. `wss://relay/ws`) and join
+    /// the room of `room_code`.  `device_id` should be stored by the page
+    /// (e.g. in `localStorage`) so the browser keeps one identity.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        server_url: &str,
+        room_code: &str,
+        device_id: &str,
+        device_name: &str,
+        on_event: Function,
+    ) -> Result<WebClient, JsValue> {
+        // Seeded from the clock so counters keep rising across page loads
+        // without being stored.
+        let counter = js_sys::Date::now() as u64;
+        let driver = Driver::new(room_code, device_id, device_name, counter)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        let driver = Rc::new(RefCell::new(driver));
+
+        let socket = WebSocket::new(server_url)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let on_open = {
+            let socket = socket.clone();
+            let driver = Rc::clone(&driver);
+            let on_event = on_event.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                let hello = driver.borrow().hello_frame();
+                match hello {
+                    Ok(frame) => match socket.send_with_u8_array(&frame) {
+                        Ok(()) => emit(&on_event, "connected", &[]),
+                        Err(err) => emit(&on_event, "error", &[("message", err)]),
+                    },
+                    Err(err) => emit(&on_event, "error", &[("message", err.to_string().into())]),
+                }
+            })
+        };
+        let on_message = {
+            let socket = socket.clone();
+            let driver = Rc::clone(&driver);
+            let on_event = on_event.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |message: MessageEvent| {
+                let Ok(buffer) = message.data().dyn_into::<js_sys::ArrayBuffer>() else {
+                    return;
+                };
+                let frame = Uint8Array::new(&buffer).to_vec();
+                let event = driver.borrow_mut().handle_frame(&frame);
+                match event {
+                    Ok(Some(event)) => {
+                        if let WebEvent::RelayError(_) = event {
+                            let _ = socket.close();
+                        }
+                        emit_web_event(&on_event, event);
+                    }
+                    Ok(None) => {}
+                    Err(err) => web_sys::console::warn_1(
+                        &format!("dropping frame from relay: {err}").into(),
+                    ),
+                }
+            })
+        };
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+            let reason = match event.reason() {
+                reason if reason.is_empty() => "closed by the relay".to_owned(),
+                reason => reason,
+            };
+            emit(&on_event, "closed", &[("reason", reason.into())]);
+        });
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(WebClient {
+            socket,
+            driver,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    /// Send `text` to every other device in the room.  Fails until the
+    /// `ready` event and after `closed`.
+    #[wasm_bindgen(js_name = sendText)]
+    pub fn send_text(&self, text: &str) -> Result<(), JsValue> {
+        if self.socket.ready_state() != WebSocket::OPEN {
+            return Err(JsValue::from_str("not connected"));
+        }
+        let frame = self
+            .driver
+            .borrow_mut()
+            .seal_text(text, js_sys::Date::now() as u64)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.socket.send_with_u8_array(&frame)
+    }
+
+    /// Whether text can be sent.
+    #[wasm_bindgen(getter)]
+    pub fn ready(&self) -> bool {
+        self.socket.ready_state() == WebSocket::OPEN && self.driver.borrow().room_key_ready()
+    }
+
+    /// Leave the room.
+    pub fn close(&self) -> Result<(), JsValue> {
+        self.socket.close()
+    }
+}
+
+impl Drop for WebClient {
+    fn drop(&mut self) {
+        // The handlers are freed with the client; the socket must not call
+        // them afterwards.
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+fn emit_web_event(on_event: &Function, event: WebEvent) {
+    match event {
+        WebEvent::Ready => emit(on_event, "ready", &[]),
+        WebEvent::Peers(peers) => {
+            let list = Array::new();
+            for peer in peers {
+                let entry = Object::new();
+                let _ = Reflect::set(&entry, &"deviceId".into(), &peer.device_id.into());
+                let _ = Reflect::set(&entry, &"deviceName".into(), &peer.device_name.into());
+                list.push(&entry);
+            }
+            emit(on_event, "peers", &[("peers", list.into())]);
+        }
+        WebEvent::Received { from, text } => {
+            emit(
+                on_event,
+                "received",
+                &[("from", from.into()), ("text", text.into())],
+            );
+        }
+        WebEvent::RelayError(message) => emit(on_event, "error", &[("message", message.into())]),
+        WebEvent::Maintenance(notice) => {
+            let message = notice.describe(js_sys::Date::now() as u64);
+            emit(
+                on_event,
+                "maintenance",
+                &[
+                    ("atUnixMs", (notice.at_unix_ms as f64).into()),
+                    ("message", message.into()),
+                ],
+            );
+        }
+        WebEvent::ReconnectSoon(notice) => emit(
+            on_event,
+            "reconnect",
+            &[("atUnixMs", (notice.at_unix_ms as f64).into())],
+        ),
+    }
+}
+
+/// Call the page's callback with `{type: kind, ...fields}`.
+fn emit(on_event: &Function, kind: &str, fields: &[(&str, JsValue)]) {
+    let event = Object::new();
+    let _ = Reflect::set(&event, &"type".into(), &kind.into());
+    for (name, value) in fields {
+        let _ = Reflect::set(&event, &(*name).into(), value);
+    }
+    if let Err(err) = on_event.call1(&JsValue::NULL, &event) {
+        web_sys::console::error_2(&"ClipRelay event handler threw".into(), &err);
+    }
+}